@@ -0,0 +1,37 @@
+use clap::{builder::PossibleValue, ValueEnum};
+
+/// The syntax of the assembly language written for EPOC16, which decides the assembler that the
+/// driver runs. The other targets each have only one assembler.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AssemblerSyntax {
+    /// The Microsoft Macro Assembler's syntax, which Borland's Turbo Assembler also accepts, as
+    /// do JWasm and its descendants, which run on the hosts the compiler does.
+    #[default]
+    MASM,
+    /// The Netwide Assembler's syntax.
+    NASM,
+}
+
+impl ValueEnum for AssemblerSyntax {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[AssemblerSyntax::MASM, AssemblerSyntax::NASM]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(match self {
+            AssemblerSyntax::MASM => {
+                PossibleValue::new("MASM").help("MASM/TASM compatible, assembled with JWasm")
+            }
+            AssemblerSyntax::NASM => PossibleValue::new("NASM").help("Netwide Assembler"),
+        })
+    }
+}
+
+impl std::fmt::Display for AssemblerSyntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
+    }
+}
@@ -0,0 +1,48 @@
+use crate::target_platform::TargetPlatform;
+
+/// The sizes of the C integer types, and the signedness of plain 'char', on a target platform.
+/// All sizes are in bits.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DataModel {
+    pub short_bits: u32,
+    pub int_bits: u32,
+    pub long_bits: u32,
+    pub pointer_bits: u32,
+    pub char_is_signed: bool,
+}
+
+impl DataModel {
+    pub const CHAR_BITS: u32 = 8;
+}
+
+impl TargetPlatform {
+    pub fn data_model(&self) -> DataModel {
+        match self {
+            // The T425 is a 32-bit word machine. Its byte load instruction 'lb' zero-extends, so
+            // plain char is unsigned, as with the INMOS C toolset.
+            TargetPlatform::Transputer => DataModel {
+                short_bits: 16,
+                int_bits: 32,
+                long_bits: 32,
+                pointer_bits: 32,
+                char_is_signed: false,
+            },
+            // The 8086 small memory model, as used by TopSpeed C.
+            TargetPlatform::EPOC16 => DataModel {
+                short_bits: 16,
+                int_bits: 16,
+                long_bits: 32,
+                pointer_bits: 16,
+                char_is_signed: true,
+            },
+            // LP64, per the System V ABI.
+            TargetPlatform::X86_64 => DataModel {
+                short_bits: 16,
+                int_bits: 32,
+                long_bits: 64,
+                pointer_bits: 64,
+                char_is_signed: true,
+            },
+        }
+    }
+}
@@ -1 +1,4 @@
-pub mod target_platform;
\ No newline at end of file
+pub mod assembler_syntax;
+pub mod data_model;
+pub mod processor;
+pub mod target_platform;
@@ -0,0 +1,44 @@
+use clap::{builder::PossibleValue, ValueEnum};
+
+/// The Transputer the code is generated for. They share an instruction set and calling
+/// convention, but only the T800 family has an on-chip floating point unit; without one, floating
+/// point is lowered to calls into a soft-float runtime.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Processor {
+    /// The 32-bit Transputer without a floating point unit.
+    #[default]
+    T425,
+    /// The 32-bit Transputer with a floating point unit.
+    T800,
+    /// The T800's successor, with the same floating point unit.
+    T805,
+}
+
+impl Processor {
+    pub fn has_floating_point_unit(&self) -> bool {
+        matches!(self, Processor::T800 | Processor::T805)
+    }
+}
+
+impl ValueEnum for Processor {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Processor::T425, Processor::T800, Processor::T805]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(match self {
+            Processor::T425 => PossibleValue::new("T425").help("No floating point unit"),
+            Processor::T800 => PossibleValue::new("T800").help("Floating point unit"),
+            Processor::T805 => PossibleValue::new("T805").help("Floating point unit"),
+        })
+    }
+}
+
+impl std::fmt::Display for Processor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
+    }
+}
@@ -0,0 +1,15 @@
+/*
+ * stdarg.h for rcc's freestanding targets, the Transputer and EPOC16. Both pass their first
+ * arguments in registers, so a variable argument list cannot be walked through memory from the
+ * last named parameter; the compiler provides it. rcc1 does not yet compile variadic functions:
+ * these are the builtins it will provide when it does.
+ */
+#ifndef _STDARG_H
+#define _STDARG_H
+
+#define va_list __builtin_va_list
+#define va_start(ap, last) __builtin_va_start(ap, last)
+#define va_arg(ap, type) __builtin_va_arg(ap, type)
+#define va_end(ap) __builtin_va_end(ap)
+
+#endif
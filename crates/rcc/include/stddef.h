@@ -0,0 +1,16 @@
+/*
+ * stddef.h for rcc's freestanding targets, the Transputer and EPOC16. rcc1 does not yet have
+ * typedef, so the types are macros, for the types rcc predefines from the target's data model.
+ */
+#ifndef _STDDEF_H
+#define _STDDEF_H
+
+#define size_t __SIZE_TYPE__
+#define ptrdiff_t __PTRDIFF_TYPE__
+#define wchar_t __WCHAR_TYPE__
+
+#define NULL 0
+
+#define offsetof(type, member) ((size_t) &((type *) 0)->member)
+
+#endif
@@ -0,0 +1,87 @@
+/*
+ * stdint.h for rcc's freestanding targets, the Transputer and EPOC16. rcc1 does not yet have
+ * typedef, so the types are macros, for the types rcc predefines from the target's data model.
+ * Neither target has a 64 bit type.
+ */
+#ifndef _STDINT_H
+#define _STDINT_H
+
+#define int8_t __INT8_TYPE__
+#define int16_t __INT16_TYPE__
+#define int32_t __INT32_TYPE__
+#define uint8_t __UINT8_TYPE__
+#define uint16_t __UINT16_TYPE__
+#define uint32_t __UINT32_TYPE__
+
+#define int_least8_t int8_t
+#define int_least16_t int16_t
+#define int_least32_t int32_t
+#define uint_least8_t uint8_t
+#define uint_least16_t uint16_t
+#define uint_least32_t uint32_t
+
+#define int_fast8_t int
+#define int_fast16_t int
+#define int_fast32_t int32_t
+#define uint_fast8_t unsigned int
+#define uint_fast16_t unsigned int
+#define uint_fast32_t uint32_t
+
+#define intptr_t __INTPTR_TYPE__
+#define uintptr_t __UINTPTR_TYPE__
+
+#define intmax_t long
+#define uintmax_t unsigned long
+
+#define INT8_MIN (-INT8_MAX - 1)
+#define INT8_MAX 127
+#define UINT8_MAX 255
+#define INT16_MIN (-INT16_MAX - 1)
+#define INT16_MAX 32767
+#define UINT16_MAX 65535U
+#define INT32_MIN (-INT32_MAX - 1)
+#define INT32_MAX __INT32_MAX__
+#define UINT32_MAX __UINT32_MAX__
+
+#define INT_LEAST8_MIN INT8_MIN
+#define INT_LEAST8_MAX INT8_MAX
+#define UINT_LEAST8_MAX UINT8_MAX
+#define INT_LEAST16_MIN INT16_MIN
+#define INT_LEAST16_MAX INT16_MAX
+#define UINT_LEAST16_MAX UINT16_MAX
+#define INT_LEAST32_MIN INT32_MIN
+#define INT_LEAST32_MAX INT32_MAX
+#define UINT_LEAST32_MAX UINT32_MAX
+
+#define INT_FAST8_MIN (-__INT_MAX__ - 1)
+#define INT_FAST8_MAX __INT_MAX__
+#define UINT_FAST8_MAX (__INT_MAX__ * 2U + 1U)
+#define INT_FAST16_MIN (-__INT_MAX__ - 1)
+#define INT_FAST16_MAX __INT_MAX__
+#define UINT_FAST16_MAX (__INT_MAX__ * 2U + 1U)
+#define INT_FAST32_MIN INT32_MIN
+#define INT_FAST32_MAX INT32_MAX
+#define UINT_FAST32_MAX UINT32_MAX
+
+#define INTPTR_MIN (-INTPTR_MAX - 1)
+#define INTPTR_MAX __INTPTR_MAX__
+#define UINTPTR_MAX __UINTPTR_MAX__
+
+#define INTMAX_MIN (-INTMAX_MAX - 1L)
+#define INTMAX_MAX __LONG_MAX__
+#define UINTMAX_MAX (__LONG_MAX__ * 2UL + 1UL)
+
+#define PTRDIFF_MIN (-PTRDIFF_MAX - 1)
+#define PTRDIFF_MAX __PTRDIFF_MAX__
+#define SIZE_MAX __SIZE_MAX__
+
+#define INT8_C(c) c
+#define INT16_C(c) c
+#define INT32_C(c) __INT32_C(c)
+#define UINT8_C(c) c
+#define UINT16_C(c) c ## U
+#define UINT32_C(c) __UINT32_C(c)
+#define INTMAX_C(c) c ## L
+#define UINTMAX_C(c) c ## UL
+
+#endif
@@ -1,25 +1,57 @@
-use std::{ffi::OsString, path::Path};
+use std::{ffi::OsString, path::{Path, PathBuf}};
 
 use anyhow::{bail, Result};
 use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use common::assembler_syntax::AssemblerSyntax;
+use common::processor::Processor;
 use common::target_platform::TargetPlatform;
 
 use crate::driver::DriverOptions;
+use crate::suffix_translator::InputKind;
+use crate::toolchain::Toolchain;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// The options given to the preprocessor, with the gcc option each is passed as.
+const PREPROCESSOR_OPTIONS: [(&str, &str); 5] = [
+    ("include-dir", "-I"),
+    ("define", "-D"),
+    ("undefine", "-U"),
+    ("include", "-include"),
+    ("isystem", "-isystem"),
+];
+
+/// gcc's -include and -isystem are long options with a single dash, which clap would take as a
+/// cluster of short options, so they are given their second dash before parsing.
+fn with_gcc_long_options(arg: OsString) -> OsString {
+    let Some(text) = arg.to_str() else {
+        return arg;
+    };
+    for option in ["-include", "-isystem"] {
+        if let Some(value) = text.strip_prefix(option) {
+            if value.is_empty() {
+                return OsString::from(format!("-{}", option));
+            }
+            return OsString::from(format!("-{}={}", option, value));
+        }
+    }
+    arg
+}
+
 pub fn parse_command_line<I, T>(itr: I) -> Result<ArgMatches, clap::Error>
 where
     I: IntoIterator<Item = T>,
     T: Into<OsString> + Clone,
 {
+    let args = itr.into_iter().map(|arg| with_gcc_long_options(arg.into()));
     Command::new("rcc")
         .version(VERSION)
         .author("DevZendo.org")
         .about("Transputer & EPOC16 C Compiler")
         .arg(
             Arg::new("file")
-                .help("The path (absolute or relative) of a C file to compile")
+                .help("The paths (absolute or relative) of the C, preprocessed C (.i), assembly (.asm) and object (.o, .obj) files to build")
+                .num_args(1..)
                 .required(true) // nice, but causes termination with a less-than-perfect error, and we want to test for its absence
         )
         .arg(
@@ -57,6 +89,32 @@ where
                 .help("Stop after compilation; do not assemble")
                 .action(ArgAction::SetTrue)
         )
+        .arg(
+            Arg::new("stop-after-assembly")
+                .long("no-link")
+                .help("Stop after assembly; do not link")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .value_name("FILE")
+                .help("Write the final output to FILE")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("build-dir")
+                .long("build-dir")
+                .value_name("DIR")
+                .help("Write the files made along the way into DIR, rather than beside their inputs")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("external-compiler")
+                .long("external-compiler")
+                .help("Run the compiler as the rcc1 program on the PATH, rather than within rcc")
+                .action(ArgAction::SetTrue)
+        )
         .arg(
             Arg::new("arch")
                 .short('a')
@@ -64,35 +122,141 @@ where
                 .help("Choose the target architecture")
                 .value_parser(value_parser!(TargetPlatform)),
         )
-        .try_get_matches_from(itr)
+        .arg(
+            Arg::new("optimisation")
+                .short('O')
+                .help("The optimisation level: 0 places every variable on the stack, 1 allocates registers")
+                .value_parser(value_parser!(u8).range(0..=1))
+                .default_value("0"),
+        )
+        .arg(
+            Arg::new("syntax")
+                .long("syntax")
+                .help("Choose the syntax of the assembler for EPOC16, and so the assembler run")
+                .value_parser(value_parser!(AssemblerSyntax)),
+        )
+        .arg(
+            Arg::new("cpu")
+                .long("cpu")
+                .help("Choose the Transputer, and so whether floating point uses its FPU")
+                .value_parser(value_parser!(Processor)),
+        )
+        .arg(
+            Arg::new("include-dir")
+                .short('I')
+                .value_name("DIR")
+                .help("Search DIR for included headers")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("define")
+                .short('D')
+                .value_name("MACRO[=VALUE]")
+                .help("Define a preprocessor macro")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("undefine")
+                .short('U')
+                .value_name("MACRO")
+                .help("Undefine a preprocessor macro")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("include")
+                .long("include")
+                .value_name("FILE")
+                .help("Include FILE before the source, as -include")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("isystem")
+                .long("isystem")
+                .value_name("DIR")
+                .help("Search DIR for system headers, as -isystem")
+                .action(ArgAction::Append),
+        )
+        .try_get_matches_from(args)
 }
 
 pub fn validate_command_line(arguments: ArgMatches) -> Result<DriverOptions> {
-    let file = arguments.get_one::<String>("file");
-    match file {
-        Some(file) => {
-            if file.to_lowercase().ends_with(".c") {
-                let file_path = Path::new(file);
-                if !file_path.exists() {
-                    bail!(format!("'{}' could not be found", file));
-                }
-                Ok(DriverOptions {
-                    c_file: Box::new(file_path.to_owned()),
-                    lex: arguments.get_flag("lex"),
-                    parse: arguments.get_flag("parse"),
-                    codegen: arguments.get_flag("codegen"),
-                    save_temps: arguments.get_flag("save-temps"),
-                    stop_after_compilation: arguments.get_flag("stop-after-compilation"),
-                    target_platform: *arguments
-                        .get_one::<TargetPlatform>("arch")
-                        .unwrap_or(&TargetPlatform::Transputer),
-                })
-            } else {
-                bail!(format!("'{}' is not a C filename", file))
-            }
+    let files: Vec<&String> = match arguments.get_many::<String>("file") {
+        Some(files) => files.collect(),
+        None => bail!("No input files supplied"),
+    };
+    let mut input_files = Vec::with_capacity(files.len());
+    for file in files {
+        let file_path = Path::new(file);
+        if InputKind::of(file_path).is_none() {
+            bail!(format!("'{}' is not a C, preprocessed C, assembly or object filename", file))
+        }
+        if !file_path.exists() {
+            bail!(format!("'{}' could not be found", file));
+        }
+        input_files.push(file_path.to_owned());
+    }
+    let target_platform = *arguments
+        .get_one::<TargetPlatform>("arch")
+        .unwrap_or(&TargetPlatform::Transputer);
+    // Only EPOC16 has a choice of assembler.
+    let assembler_syntax = match arguments.get_one::<AssemblerSyntax>("syntax") {
+        Some(syntax) if target_platform == TargetPlatform::EPOC16 => *syntax,
+        Some(_) => bail!("There is no choice of assembler syntax for {}", target_platform),
+        None => AssemblerSyntax::default(),
+    };
+    // Only the Transputer has a choice of processor.
+    let processor = match arguments.get_one::<Processor>("cpu") {
+        Some(processor) if target_platform == TargetPlatform::Transputer => *processor,
+        Some(_) => bail!("There is no choice of CPU for {}", target_platform),
+        None => Processor::default(),
+    };
+    let stop_after_compilation = arguments.get_flag("stop-after-compilation");
+    let stop_after_assembly = arguments.get_flag("stop-after-assembly");
+    // Linking makes a single output of several inputs; stopping before it, each module has its own.
+    let output_file = arguments.get_one::<PathBuf>("output").cloned();
+    if output_file.is_some() && input_files.len() > 1 && (stop_after_compilation || stop_after_assembly) {
+        bail!("-o cannot name the outputs of {} input files", input_files.len());
+    }
+    Ok(DriverOptions {
+        input_files,
+        lex: arguments.get_flag("lex"),
+        parse: arguments.get_flag("parse"),
+        codegen: arguments.get_flag("codegen"),
+        save_temps: arguments.get_flag("save-temps"),
+        stop_after_compilation,
+        stop_after_assembly,
+        target_platform,
+        assembler_syntax,
+        processor,
+        optimisation_level: *arguments.get_one::<u8>("optimisation").expect("it has a default"),
+        external_compiler: arguments.get_flag("external-compiler"),
+        output_file,
+        build_directory: arguments.get_one::<PathBuf>("build-dir").cloned(),
+        preprocessor_options: preprocessor_options(&arguments),
+        // Configured from files and the environment by the caller.
+        toolchain: Toolchain::default(),
+    })
+}
+
+/// The preprocessor options as gcc arguments, in the order they were given, as a later -D or -U
+/// overrides an earlier one, and include directories are searched in order.
+fn preprocessor_options(arguments: &ArgMatches) -> Vec<String> {
+    let mut given: Vec<(usize, &str, &String)> = vec![];
+    for (id, option) in PREPROCESSOR_OPTIONS {
+        if let (Some(indices), Some(values)) = (arguments.indices_of(id), arguments.get_many::<String>(id)) {
+            given.extend(indices.zip(values).map(|(index, value)| (index, option, value)));
         }
-        None => bail!("C filename not supplied"),
     }
+    given.sort_by_key(|(index, _, _)| *index);
+    given.into_iter()
+        .flat_map(|(_, option, value)| {
+            if option.len() == 2 {
+                vec![format!("{}{}", option, value)]
+            } else {
+                vec![option.to_string(), value.clone()]
+            }
+        })
+        .collect()
 }
 
 pub fn parse_and_validate<I, T>(itr: I) -> Result<DriverOptions>
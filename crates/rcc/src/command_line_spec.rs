@@ -4,7 +4,10 @@ extern crate hamcrest2;
 mod command_line_spec {
 
     use std::fs::File;
+    use std::path::PathBuf;
 
+    use common::assembler_syntax::AssemblerSyntax;
+    use common::processor::Processor;
     use common::target_platform::TargetPlatform;
     use common_test::file_utils_test_helper::temp_config_dir;
     use hamcrest2::prelude::*;
@@ -22,7 +25,7 @@ mod command_line_spec {
         let arg_vec: Vec<&str> = vec!["rcc"];
         let result = parse_command_line(arg_vec);
         assert_that!(result.is_err(), equal_to(true));
-        assert_that!(result.unwrap_err().to_string(), equal_to("error: the following required arguments were not provided:\n  <file>\n\nUsage: rcc <file>\n\nFor more information, try '--help'.\n"));
+        assert_that!(result.unwrap_err().to_string(), equal_to("error: the following required arguments were not provided:\n  <file>...\n\nUsage: rcc <file>...\n\nFor more information, try '--help'.\n"));
     }
 
     #[test]
@@ -31,7 +34,7 @@ mod command_line_spec {
         let result = validate_command_line(parse_command_line(arg_vec).unwrap());
         assert_that!(
             result.unwrap_err().to_string(),
-            equal_to("'aardvark.stl' is not a C filename")
+            equal_to("'aardvark.stl' is not a C, preprocessed C, assembly or object filename")
         );
     }
 
@@ -63,11 +66,33 @@ mod command_line_spec {
         let result = validate_command_line(parse_command_line(arg_vec).unwrap());
         assert_that!(&result, ok());
         assert_that!(
-            result.unwrap().c_file.to_str().unwrap(),
+            result.unwrap().input_files[0].to_str().unwrap(),
             equal_to(c_file.to_str().unwrap())
         );
     }
 
+    #[test]
+    fn mixed_input_files_given_in_order() {
+        let (c_file, temp_dir) = create_file();
+        let i_file = temp_dir.join("second.i");
+        File::create(i_file.clone()).unwrap();
+        let asm_file = temp_dir.join("third.asm");
+        File::create(asm_file.clone()).unwrap();
+
+        let arg_vec = vec!["rcc", c_file.to_str().unwrap(), i_file.to_str().unwrap(), asm_file.to_str().unwrap()];
+        let driver_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(driver_options.input_files, equal_to(vec![c_file, i_file, asm_file]));
+    }
+
+    #[test]
+    fn one_of_several_input_files_not_found() {
+        let (c_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc", c_file.to_str().unwrap(), "missing.asm"];
+        let result = validate_command_line(parse_command_line(arg_vec).unwrap());
+        assert_that!(result.unwrap_err().to_string(), equal_to("'missing.asm' could not be found"));
+    }
+
     #[test]
     fn all_flags_off_by_default() {
         let (c_file, _temp_dir) = create_file();
@@ -78,7 +103,9 @@ mod command_line_spec {
         assert_that!(driver_options.parse, equal_to(false));
         assert_that!(driver_options.codegen, equal_to(false));
         assert_that!(driver_options.stop_after_compilation, equal_to(false));
+        assert_that!(driver_options.stop_after_assembly, equal_to(false));
         assert_that!(driver_options.target_platform, equal_to(TargetPlatform::Transputer));
+        assert_that!(driver_options.external_compiler, equal_to(false));
     }
 
     #[test]
@@ -117,6 +144,15 @@ mod command_line_spec {
         assert_that!(driver_options.stop_after_compilation, equal_to(true));
     }
 
+    #[test]
+    fn external_compiler_flag() {
+        let (c_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc", c_file.to_str().unwrap(), "--external-compiler"];
+        let driver_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(driver_options.external_compiler, equal_to(true));
+    }
+
     #[test]
     fn architecture_epoc16() {
         let (c_file, _temp_dir) = create_file();
@@ -135,6 +171,166 @@ mod command_line_spec {
         assert_that!(driver_options.target_platform, equal_to(TargetPlatform::X86_64));
     }
 
+    #[test]
+    fn nasm_syntax_for_epoc16() {
+        let (c_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc", c_file.to_str().unwrap(), "-a", "EPOC16", "--syntax", "NASM"];
+        let driver_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(driver_options.assembler_syntax, equal_to(AssemblerSyntax::NASM));
+    }
+
+    #[test]
+    fn syntax_given_for_x86_64() {
+        let (c_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc", c_file.to_str().unwrap(), "-a", "X86_64", "--syntax", "MASM"];
+        let result = validate_command_line(parse_command_line(arg_vec).unwrap());
+        assert_that!(result.unwrap_err().to_string(), equal_to("There is no choice of assembler syntax for X86_64"));
+    }
+
+    #[test]
+    fn t800_for_transputer() {
+        let (c_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc", c_file.to_str().unwrap(), "--cpu", "T800"];
+        let driver_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(driver_options.processor, equal_to(Processor::T800));
+    }
+
+    #[test]
+    fn cpu_given_for_x86_64() {
+        let (c_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc", c_file.to_str().unwrap(), "-a", "X86_64", "--cpu", "T805"];
+        let result = validate_command_line(parse_command_line(arg_vec).unwrap());
+        assert_that!(result.unwrap_err().to_string(), equal_to("There is no choice of CPU for X86_64"));
+    }
+
+    #[test]
+    fn optimisation_level_one() {
+        let (c_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc", c_file.to_str().unwrap(), "-O1"];
+        let driver_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(driver_options.optimisation_level, equal_to(1));
+    }
+
+    #[test]
+    fn output_file_and_build_directory() {
+        let (c_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc", c_file.to_str().unwrap(), "-o", "out/prog.bin", "--build-dir", "build"];
+        let driver_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(driver_options.output_file, equal_to(Some(PathBuf::from("out/prog.bin"))));
+        assert_that!(driver_options.build_directory, equal_to(Some(PathBuf::from("build"))));
+    }
+
+    #[test]
+    fn no_output_file_or_build_directory_by_default() {
+        let (c_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc", c_file.to_str().unwrap()];
+        let driver_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(driver_options.output_file, none());
+        assert_that!(driver_options.build_directory, none());
+    }
+
+    #[test]
+    fn output_file_names_linked_x86_64_program_of_several_inputs() {
+        let (c_file, temp_dir) = create_file();
+        let other_file = temp_dir.join("other.c");
+        File::create(other_file.clone()).unwrap();
+
+        let arg_vec = vec!["rcc", c_file.to_str().unwrap(), other_file.to_str().unwrap(), "-a", "X86_64", "-o", "prog"];
+        let driver_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(driver_options.output_file, equal_to(Some(PathBuf::from("prog"))));
+    }
+
+    #[test]
+    fn output_file_names_image_linked_from_several_transputer_modules() {
+        let (c_file, temp_dir) = create_file();
+        let other_file = temp_dir.join("other.c");
+        File::create(other_file.clone()).unwrap();
+
+        let arg_vec = vec!["rcc", c_file.to_str().unwrap(), other_file.to_str().unwrap(), "-o", "prog.bin"];
+        let driver_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(driver_options.output_file, equal_to(Some(PathBuf::from("prog.bin"))));
+    }
+
+    #[test]
+    fn output_file_cannot_name_several_objects() {
+        let (c_file, temp_dir) = create_file();
+        let other_file = temp_dir.join("other.c");
+        File::create(other_file.clone()).unwrap();
+
+        let arg_vec = vec!["rcc", c_file.to_str().unwrap(), other_file.to_str().unwrap(), "--no-link", "-o", "prog.o"];
+        let result = validate_command_line(parse_command_line(arg_vec).unwrap());
+        assert_that!(result.unwrap_err().to_string(), equal_to("-o cannot name the outputs of 2 input files"));
+    }
+
+    #[test]
+    fn stop_after_assembly_flag() {
+        let (c_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc", c_file.to_str().unwrap(), "--no-link"];
+        let driver_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(driver_options.stop_after_assembly, equal_to(true));
+    }
+
+    #[test]
+    fn object_files_accepted() {
+        let (c_file, temp_dir) = create_file();
+        let object_file = temp_dir.join("crt0.o");
+        File::create(object_file.clone()).unwrap();
+
+        let arg_vec = vec!["rcc", object_file.to_str().unwrap(), c_file.to_str().unwrap()];
+        let driver_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(driver_options.input_files, equal_to(vec![object_file, c_file]));
+    }
+
+    #[test]
+    fn output_file_cannot_name_several_assembly_files() {
+        let (c_file, temp_dir) = create_file();
+        let other_file = temp_dir.join("other.c");
+        File::create(other_file.clone()).unwrap();
+
+        let arg_vec = vec!["rcc", c_file.to_str().unwrap(), other_file.to_str().unwrap(), "-a", "X86_64", "-S", "-o", "prog.s"];
+        let result = validate_command_line(parse_command_line(arg_vec).unwrap());
+        assert_that!(result.unwrap_err().to_string(), equal_to("-o cannot name the outputs of 2 input files"));
+    }
+
+    #[test]
+    fn preprocessor_options_passed_in_order() {
+        let (c_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc", "-DDEBUG", c_file.to_str().unwrap(), "-I", "include", "-UDEBUG", "-include", "config.h", "-Ilib/include", "-isystem", "sys", "-D", "LEVEL=2"];
+        let driver_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(driver_options.preprocessor_options, equal_to([
+            "-DDEBUG", "-Iinclude", "-UDEBUG", "-include", "config.h", "-Ilib/include", "-isystem", "sys", "-DLEVEL=2"
+        ].iter().map(|str| str.to_string()).collect::<Vec<String>>()));
+    }
+
+    #[test]
+    fn attached_include_and_isystem_values() {
+        let (c_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc", c_file.to_str().unwrap(), "-isystemsys", "-includeconfig.h"];
+        let driver_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(driver_options.preprocessor_options, equal_to([
+            "-isystem", "sys", "-include", "config.h"
+        ].iter().map(|str| str.to_string()).collect::<Vec<String>>()));
+    }
+
+    #[test]
+    fn no_preprocessor_options_by_default() {
+        let (c_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc", c_file.to_str().unwrap()];
+        let driver_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(driver_options.preprocessor_options.is_empty(), equal_to(true));
+    }
+
     fn create_file() -> (std::path::PathBuf, TempDir) {
         let (temp, temp_dir) = temp_config_dir();
         let c_file = temp.join("HELLOWORLD.C");
@@ -1,30 +1,57 @@
-/// The Driver is responsible for running specific external processes with the relevant arguments.
-/// It's the lower level of the driver - for the higher level, see the DriverController.
+//! The Driver is responsible for running specific external processes with the relevant arguments.
+//! It's the lower level of the driver - for the higher level, see the DriverController.
 
-use std::path::PathBuf;
-use crate::{executor::{Execution, Executor}, suffix_translator::SuffixTranslator};
+use std::path::{Path, PathBuf};
+use crate::{executor::{Execution, Executor}, freestanding, suffix_translator::{InputKind, SuffixTranslator}, toolchain::{Tool, Toolchain}};
 
+use common::assembler_syntax::AssemblerSyntax;
+use common::processor::Processor;
 use common::target_platform::TargetPlatform;
-use log::{debug, warn};
+use anyhow::{bail, Context};
+use log::{debug, error, warn};
+use sysexits::ExitCode;
+use tlink::linker::{LinkOptions, Module};
+use tlink::object::Object;
 #[cfg(test)]
 use mockall::automock;
 
 #[derive(Debug, Clone)]
 pub struct DriverOptions {
-    pub c_file: Box<PathBuf>,
+    /// The C, preprocessed C and assembly files, each run through only the stages it needs.
+    pub input_files: Vec<PathBuf>,
     pub lex: bool,
     pub parse: bool,
     pub codegen: bool,
     pub save_temps: bool,
     pub stop_after_compilation: bool,
+    /// Stop before linking, leaving each module's object.
+    pub stop_after_assembly: bool,
     pub target_platform: TargetPlatform,
+    pub assembler_syntax: AssemblerSyntax,
+    pub processor: Processor,
+    pub optimisation_level: u8,
+    /// Run the compiler as the rcc1 program on the PATH, rather than within rcc.
+    pub external_compiler: bool,
+    /// The final output: the program or image, or with stop_after_compilation or
+    /// stop_after_assembly, a module's assembly or object.
+    pub output_file: Option<PathBuf>,
+    /// Where the files made along the way are written, rather than beside their inputs.
+    pub build_directory: Option<PathBuf>,
+    /// The -I, -D, -U, -include and -isystem options for gcc, in the order given.
+    pub preprocessor_options: Vec<String>,
+    /// The commands run for each stage, and their flags.
+    pub toolchain: Toolchain,
 }
 
+/// Each stage is given the input file it works on; the files it reads and writes are named from
+/// that. Assembly is given the inputs needing it, and linking all the inputs, as the program is
+/// built from all of them.
 #[cfg_attr(test, automock)]
 pub trait Driver {
-    fn preprocess(&self) -> Result<Execution, anyhow::Error>;
-    fn compile(&self) -> Result<Execution, anyhow::Error>;
-    fn assemble(&self) -> Result<Execution, anyhow::Error>;
+    fn preprocess(&self, source: &Path) -> Result<Execution, anyhow::Error>;
+    fn compile(&self, source: &Path) -> Result<Execution, anyhow::Error>;
+    fn assemble(&self, sources: &[PathBuf]) -> Result<Execution, anyhow::Error>;
+    fn link(&self, sources: &[PathBuf]) -> Result<Execution, anyhow::Error>;
 }
 
 pub struct DefaultDriver {
@@ -40,24 +67,130 @@ impl DefaultDriver {
             executor,
         }
     }
+
+    /// Names the files made from an input, in the build directory if one was given.
+    pub(crate) fn translator(&self, source: &Path) -> SuffixTranslator {
+        SuffixTranslator::new(source.to_path_buf(), self.driver_options.target_platform)
+            .in_directory(self.driver_options.build_directory.as_deref())
+    }
+
+    /// The compiler's output, which is the final output when stopping after compilation.
+    pub(crate) fn assembly_file(&self, source: &Path) -> PathBuf {
+        match &self.driver_options.output_file {
+            Some(output_file) if self.driver_options.stop_after_compilation => output_file.clone(),
+            _ => self.translator(source).assembler(),
+        }
+    }
+
+    /// The final output is named by -o if given; the command line allows that only when there is
+    /// a single final output.
+    fn output_file(&self, derived: PathBuf) -> PathBuf {
+        self.driver_options.output_file.clone().unwrap_or(derived)
+    }
+
+    /// The assembler's output, which is the final output when stopping after assembly.
+    fn object_file(&self, source: &Path) -> PathBuf {
+        match &self.driver_options.output_file {
+            Some(output_file) if self.driver_options.stop_after_assembly => output_file.clone(),
+            _ => self.translator(source).object(),
+        }
+    }
+
+    /// Without a linker configured for the Transputer, its modules are linked by rcc itself, which
+    /// reads the objects rcc1 writes rather than TMASM's, so C is compiled straight to an object.
+    pub(crate) fn compiler_writes_object(&self, source: &Path) -> bool {
+        self.driver_options.target_platform == TargetPlatform::Transputer
+            && self.driver_options.toolchain.linker(TargetPlatform::Transputer).is_none()
+            && !self.driver_options.stop_after_compilation
+            && matches!(InputKind::of(source), Some(InputKind::C) | Some(InputKind::Preprocessed))
+    }
+
+    /// The compiler's output: the module's assembly, or its object.
+    pub(crate) fn compiler_output_file(&self, source: &Path) -> PathBuf {
+        if self.compiler_writes_object(source) {
+            self.object_file(source)
+        } else {
+            self.assembly_file(source)
+        }
+    }
+
+    /// The assembler is run on each module in turn.
+    fn assembler_args(&self, source: &Path) -> Vec<String> {
+        let xlat = self.translator(source);
+        // TODO: CROSSPLATFORM EPOC16
+        let assembly = &xlat.assembler();
+        let assembly_file = assembly.as_os_str().to_string_lossy();
+        let listing = &xlat.listing();
+        let listing_file = listing.as_os_str().to_string_lossy();
+        let object = &self.object_file(source);
+        let object_file = object.as_os_str().to_string_lossy();
+        let mut args = self.driver_options.toolchain
+            .assembler(self.driver_options.target_platform, self.driver_options.assembler_syntax)
+            .args();
+        match self.driver_options.target_platform {
+            TargetPlatform::X86_64 => {
+                args.extend(["-c", &assembly_file, "-o", &object_file].iter().map(|str| str.to_string()));
+            }
+            TargetPlatform::Transputer => {
+                args.extend([&assembly_file, "-o", &object_file, "-l", &listing_file].iter().map(|str| str.to_string()));
+            }
+            // The 8086 assemblers write OMF objects, for linking with the SIBO SDK's libraries.
+            TargetPlatform::EPOC16 => {
+                match self.driver_options.assembler_syntax {
+                    AssemblerSyntax::MASM => args.extend([
+                        "-q".to_string(),
+                        "-omf".to_string(),
+                        format!("-Fo{}", object_file),
+                        format!("-Fl={}", listing_file),
+                        assembly_file.to_string(),
+                    ]),
+                    AssemblerSyntax::NASM => args.extend(
+                        ["-f", "obj", &assembly_file, "-o", &object_file, "-l", &listing_file].iter().map(|str| str.to_string()),
+                    ),
+                }
+            }
+        }
+        args
+    }
+
+    /// The linker is given the objects in the order of the inputs, and names its output with -o.
+    fn linker_args(&self, linker: &Tool, objects: &[PathBuf], image: &Path) -> Vec<String> {
+        let mut args = linker.args();
+        args.extend(objects.iter().map(|object| object.as_os_str().to_string_lossy().to_string()));
+        args.push("-o".to_string());
+        args.push(image.as_os_str().to_string_lossy().to_string());
+        args
+    }
 }
 
 impl Driver for DefaultDriver {
-    fn preprocess(&self) -> Result<Execution, anyhow::Error> {
-        let xlat = SuffixTranslator::new(self.driver_options.c_file.to_path_buf());
+    fn preprocess(&self, source: &Path) -> Result<Execution, anyhow::Error> {
+        let xlat = self.translator(source);
         // TODO: CROSSPLATFORM EPOC16
         // TODO move this conversion mess into driver options...
         let preprocessor = &xlat.preprocessor();
         let preprocessor_file = preprocessor.as_os_str().to_string_lossy();
-        let c_file = self.driver_options.c_file.as_os_str().to_string_lossy();
-        let args: Vec<String> = ["gcc", "-E", "-P", &c_file, "-o", &preprocessor_file].iter().map(|str| str.to_string()).collect();
+        let c_file = source.as_os_str().to_string_lossy();
+        let mut args = self.driver_options.toolchain.preprocessor(self.driver_options.target_platform).args();
+        args.extend(["-E", "-P"].iter().map(|str| str.to_string()));
+        // x86_64 programs are built with the host's C library, so use its headers and macros.
+        if self.driver_options.target_platform != TargetPlatform::X86_64 {
+            args.extend(["-undef", "-nostdinc", "-ffreestanding"].iter().map(|str| str.to_string()));
+            // After any directories given with -I or -isystem, so that those can replace them.
+            args.push("-idirafter".to_string());
+            args.push(freestanding::header_directory()?.as_os_str().to_string_lossy().to_string());
+            let macros = freestanding::predefined_macros(self.driver_options.target_platform, self.driver_options.processor);
+            args.extend(macros.iter().map(|(name, value)| format!("-D{}={}", name, value)));
+        }
+        args.extend(self.driver_options.preprocessor_options.iter().cloned());
+        args.extend([&c_file, "-o", &preprocessor_file].iter().map(|str| str.to_string()));
 
         self.executor.run(args)
     }
     
-    fn compile(&self) -> Result<Execution,anyhow::Error> {
+    fn compile(&self, source: &Path) -> Result<Execution,anyhow::Error> {
         // TODO don't know what the actual command line will be just yet, so this is made up..
-        let xlat = SuffixTranslator::new(self.driver_options.c_file.to_path_buf());
+        let xlat = self.translator(source);
         // TODO: CROSSPLATFORM EPOC16
         // TODO move this conversion mess into driver options...
         let preprocessor = &xlat.preprocessor();
@@ -66,9 +199,9 @@ impl Driver for DefaultDriver {
             warn!("Preprocessed file {} does not exist", preprocessor_file);
             // is there any point running the compiler in this case?
         }
-        let assembly = &xlat.assembler();
-        let assembly_file = assembly.as_os_str().to_string_lossy();
-        let mut args: Vec<String> = vec!["rcc1".to_string()];
+        let output = &self.compiler_output_file(source);
+        let output_file = output.as_os_str().to_string_lossy();
+        let mut args = self.driver_options.toolchain.compiler(self.driver_options.target_platform).args();
         if self.driver_options.lex {
             args.push("--lex".to_string())
         }
@@ -83,47 +216,120 @@ impl Driver for DefaultDriver {
             let name = self.driver_options.target_platform.to_string();
             args.push(name);
         }
-        let mut rest: Vec<String> = vec![preprocessor_file.to_string(), "-o".to_string(), assembly_file.to_string()];
+        if self.driver_options.optimisation_level != 0 {
+            args.push(format!("-O{}", self.driver_options.optimisation_level));
+        }
+        if self.driver_options.assembler_syntax != AssemblerSyntax::default() {
+            args.push("--syntax".to_string());
+            args.push(self.driver_options.assembler_syntax.to_string());
+        }
+        if self.driver_options.processor != Processor::default() {
+            args.push("--cpu".to_string());
+            args.push(self.driver_options.processor.to_string());
+        }
+        let mut rest: Vec<String> = vec![preprocessor_file.to_string(), "-o".to_string(), output_file.to_string()];
         args.append(&mut rest);
 
         let result = self.executor.run(args.iter().map(|str| str.to_string()).collect());
-        // tidy up after the preprocessor unless requested
-        if self.driver_options.save_temps {
-            debug!("Retaining temporary preprocessor file {}", preprocessor_file);
-        } else {
-            match std::fs::remove_file(preprocessor) {
-                Ok(_) => debug!("Removed preprocessor file {}", preprocessor_file),
-                Err(e) => warn!("Could not remove preprocessor file {}: {}", preprocessor_file, e),
-            }
+        // tidy up after the preprocessor unless requested, or the preprocessed file was an input
+        if InputKind::of(source) == Some(InputKind::C) {
+            remove_temporary(self.driver_options.save_temps, "preprocessor", preprocessor);
         }
         result
     }
     
-    fn assemble(&self) -> Result<Execution,anyhow::Error> {
-        let xlat = SuffixTranslator::new(self.driver_options.c_file.to_path_buf());
-        // TODO: CROSSPLATFORM EPOC16
-        let assembly = &xlat.assembler();
-        let assembly_file = assembly.as_os_str().to_string_lossy();
-        let binary = &xlat.binary();
-        let binary_file = binary.as_os_str().to_string_lossy();
-        let listing = &xlat.listing();
-        let listing_file = listing.as_os_str().to_string_lossy();
-        let args: Vec<String> = ["tmasm", &assembly_file, "-o", &binary_file, "-l", &listing_file].iter().map(|str| str.to_string()).collect();
-    
-        let result = self.executor.run(args);
-        // tidy up after the assembler unless requested
-        if self.driver_options.save_temps {
-            debug!("Retaining temporary assembler file {}", assembly_file);
-        } else {
-            match std::fs::remove_file(assembly) {
-                Ok(_) => debug!("Removed assembler file {}", assembly_file),
-                Err(e) => warn!("Could not remove assembler file {}: {}", assembly_file, e),
+    fn assemble(&self, sources: &[PathBuf]) -> Result<Execution,anyhow::Error> {
+        if sources.is_empty() {
+            bail!("No files to assemble");
+        }
+        // Modules compiled straight to objects have nothing to assemble.
+        let sources: Vec<&PathBuf> = sources.iter()
+            .filter(|source| !self.compiler_writes_object(source))
+            .collect();
+        if sources.is_empty() {
+            return Ok(Execution { exit_code: Some(0), stdout: None, stderr: None });
+        }
+        // Each module is assembled on its own, stopping at the first that fails.
+        let mut result = self.executor.run(self.assembler_args(sources[0]));
+        for source in &sources[1..] {
+            if !matches!(&result, Ok(execution) if execution.code() == Some(0)) {
+                break;
+            }
+            result = self.executor.run(self.assembler_args(source));
+        }
+        // tidy up after the assembler unless requested, or the assembly file was an input
+        for source in &sources {
+            if InputKind::of(source) != Some(InputKind::Assembly) {
+                remove_temporary(self.driver_options.save_temps, "assembler", &self.translator(source).assembler());
+            }
+        }
+        result
+    }
+
+    fn link(&self, sources: &[PathBuf]) -> Result<Execution,anyhow::Error> {
+        if sources.is_empty() {
+            bail!("No files to link");
+        }
+        let target_platform = self.driver_options.target_platform;
+        let objects: Vec<PathBuf> = sources.iter()
+            .map(|source| self.translator(source).object())
+            .collect();
+        // The program or image is named after the first input.
+        let image = &self.output_file(self.translator(&sources[0]).binary());
+        let result = match self.driver_options.toolchain.linker(target_platform) {
+            Some(linker) => self.executor.run(self.linker_args(&linker, &objects, image)),
+            None if target_platform == TargetPlatform::Transputer => boot_image(&objects, image),
+            None => bail!("No linker is configured for {}; configure one, or stop before linking with --no-link", target_platform),
+        };
+        // tidy up after the linker unless requested, or the object file was an input
+        for (source, object) in sources.iter().zip(objects.iter()) {
+            if InputKind::of(source) != Some(InputKind::Object) {
+                remove_temporary(self.driver_options.save_temps, "object", object);
             }
         }
         result
     }
 }
 
+/// Without a linker configured, the Transputer modules are linked by rcc itself into a boot image
+/// for Parachute, with a map of where everything was placed beside it.
+fn boot_image(objects: &[PathBuf], image: &Path) -> Result<Execution, anyhow::Error> {
+    let mut modules = vec![];
+    for object in objects {
+        let contents = std::fs::read(object)
+            .with_context(|| format!("Could not read object {}", object.display()))?;
+        let object_module = Object::from_bytes(&contents)
+            .with_context(|| format!("'{}' is not a Transputer object", object.display()))?;
+        let name = object.file_name().unwrap_or(object.as_os_str()).to_string_lossy().to_string();
+        modules.push(Module { name, object: object_module });
+    }
+    // Errors in the program as a whole are reported as the errors of an external linker would be.
+    let linked = match tlink::linker::link(&modules, &LinkOptions::default()) {
+        Ok(linked) => linked,
+        Err(err) => {
+            error!("{}", err);
+            return Ok(Execution { exit_code: Some(i32::from(ExitCode::DataErr)), stdout: None, stderr: None });
+        }
+    };
+    let map = image.with_extension("map");
+    linked.write(image, &map)?;
+    debug!("Wrote boot image {} of {} modules, and map {}", image.display(), objects.len(), map.display());
+    Ok(Execution { exit_code: Some(0), stdout: None, stderr: None })
+}
+
+/// Removes a temporary file once the stage reading it has run, unless temporaries are to be saved.
+pub(crate) fn remove_temporary(save_temps: bool, kind: &str, file: &Path) {
+    let file_name = file.as_os_str().to_string_lossy();
+    if save_temps {
+        debug!("Retaining temporary {} file {}", kind, file_name);
+    } else {
+        match std::fs::remove_file(file) {
+            Ok(_) => debug!("Removed {} file {}", kind, file_name),
+            Err(e) => warn!("Could not remove {} file {}: {}", kind, file_name, e),
+        }
+    }
+}
+
 #[cfg(test)]
 #[path = "./driver_spec.rs"]
 mod driver_spec;
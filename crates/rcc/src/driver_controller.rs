@@ -1,10 +1,13 @@
-use log::{debug, info};
+use common::target_platform::TargetPlatform;
+use log::{debug, error, info};
 /// The DriverController is responsible for running the various stages of the compilation.
 /// It orchestrates the various executions using a Driver to run the actual external tools.
 /// It is the high level of the driver - for the low level, see Driver.
 use sysexits::ExitCode;
 
 use crate::driver::{Driver, DriverOptions};
+use crate::executor::Execution;
+use crate::suffix_translator::InputKind;
 
 pub trait DriverController {
     fn drive(
@@ -18,6 +21,25 @@ pub trait DriverController {
 pub struct DefaultDriverController {}
 
 impl DefaultDriverController {
+    /// Whether a stage's tool ran successfully, giving the exit code to stop with if not, having
+    /// logged the tool's stderr, where it explains its failure. A tool killed by a signal has no
+    /// exit code, and is reported as a software error; otherwise `failure` maps the tool's code.
+    fn failed(tool: &str, execution: &Execution, failure: impl Fn(i32) -> ExitCode) -> Option<ExitCode> {
+        match execution.code() {
+            Some(0) => None,
+            code => {
+                match code {
+                    Some(code) => error!("{} failed with exit code {}", tool, code),
+                    None => error!("{} was terminated by a signal", tool),
+                }
+                let stderr = execution.stderr();
+                if !stderr.trim().is_empty() {
+                    error!("{}", stderr.trim_end());
+                }
+                Some(code.map_or(ExitCode::Software, failure))
+            }
+        }
+    }
 }
 
 impl DriverController for DefaultDriverController {
@@ -27,39 +49,111 @@ impl DriverController for DefaultDriverController {
         driver: Box<dyn Driver>,
     ) -> Result<ExitCode, anyhow::Error> {
         
-        // Preprocess...
-        match driver.preprocess() {
-            Ok(_success) => {
-                debug!("Preprocessor ok");
+        if let Some(build_directory) = &driver_options.build_directory {
+            if let Err(err) = std::fs::create_dir_all(build_directory) {
+                anyhow::bail!("Could not create build directory {}: {}", build_directory.display(), err);
             }
-            Err(err) => {
-                anyhow::bail!(format!("Could not run preprocessor: {}", err));
+        }
+
+        // Without a linker, the Transputer's modules are linked by tlink, which reads the objects
+        // rcc1 writes but not TMASM's flat binaries, so an assembly file could never be linked.
+        let stops_early = driver_options.stop_after_compilation || driver_options.stop_after_assembly
+            || driver_options.lex || driver_options.parse || driver_options.codegen;
+        let target_platform = driver_options.target_platform;
+        if !stops_early && target_platform == TargetPlatform::Transputer
+            && driver_options.toolchain.linker(target_platform).is_none() {
+            let assembly = driver_options.input_files.iter()
+                .find(|source| InputKind::of(source) == Some(InputKind::Assembly));
+            if let Some(assembly) = assembly {
+                error!("'{}' cannot be linked without a Transputer linker configured, as rcc only links the \
+                    modules it compiles; configure one, or stop before linking with --no-link", assembly.display());
+                return Ok(ExitCode::Usage);
             }
         }
 
-        // Compile...
-        match driver.compile() {
-            Ok(_success) => {
-                debug!("Compiler ok");
+        for source in driver_options.input_files.iter() {
+            let input_kind = match InputKind::of(source) {
+                Some(input_kind) => input_kind,
+                None => anyhow::bail!("'{}' is not a C, preprocessed C, assembly or object file", source.display()),
+            };
+
+            // Preprocess... gcc fails on errors in the source, e.g. a missing header.
+            if input_kind == InputKind::C {
+                match driver.preprocess(source) {
+                    Ok(execution) => {
+                        if let Some(code) = Self::failed("Preprocessor", &execution, |_| ExitCode::DataErr) {
+                            return Ok(code);
+                        }
+                        debug!("Preprocessor ok");
+                    }
+                    Err(err) => {
+                        anyhow::bail!(format!("Could not run preprocessor: {}", err));
+                    }
+                }
             }
-            Err(err) => {
-                anyhow::bail!(format!("Could not run compiler: {}", err));
+
+            // Compile... rcc1 exits with a sysexits code of its own, e.g. DataErr for a program with
+            // errors, which is passed on.
+            if input_kind == InputKind::C || input_kind == InputKind::Preprocessed {
+                match driver.compile(source) {
+                    Ok(execution) => {
+                        let failure = |code| ExitCode::try_from(code).unwrap_or(ExitCode::Software);
+                        if let Some(code) = Self::failed("Compiler", &execution, failure) {
+                            return Ok(code);
+                        }
+                        debug!("Compiler ok");
+                    }
+                    Err(err) => {
+                        anyhow::bail!(format!("Could not run compiler: {}", err));
+                    }
+                }
             }
         }
-        
-        if driver_options.stop_after_compilation {
+
+        // The compiler writes no assembly when it is stopped early.
+        if driver_options.stop_after_compilation || driver_options.lex || driver_options.parse || driver_options.codegen {
             info!("Stopping after compilation");
             return Ok(ExitCode::Ok);
-        } 
-            
+        }
+
+        // Assemble... each module on its own, so that a failure can be blamed on the right input:
+        // the assembler rejecting the compiler's output is the compiler's fault, but rejecting an
+        // assembly file given on the command line is an error in that file.
+        let modules = driver_options.input_files.iter()
+            .filter(|source| InputKind::of(source) != Some(InputKind::Object));
+        for module in modules {
+            match driver.assemble(std::slice::from_ref(module)) {
+                Ok(execution) => {
+                    let failure = |_| match InputKind::of(module) {
+                        Some(InputKind::Assembly) => ExitCode::DataErr,
+                        _ => ExitCode::Software,
+                    };
+                    if let Some(code) = Self::failed("Assembler", &execution, failure) {
+                        return Ok(code);
+                    }
+                    debug!("Assembler ok");
+                }
+                Err(err) => {
+                    anyhow::bail!(format!("Could not run assembler: {}", err));
+                }
+            }
+        }
+
+        if driver_options.stop_after_assembly {
+            info!("Stopping after assembly");
+            return Ok(ExitCode::Ok);
+        }
 
-        // Assemble...
-        match driver.assemble() {
-            Ok(_success) => {
-                debug!("Assembler ok");
+        // Link... the linker fails on errors in the program as a whole, e.g. an undefined function.
+        match driver.link(&driver_options.input_files) {
+            Ok(execution) => {
+                if let Some(code) = Self::failed("Linker", &execution, |_| ExitCode::DataErr) {
+                    return Ok(code);
+                }
+                debug!("Linker ok");
             }
             Err(err) => {
-                anyhow::bail!(format!("Could not run assembler: {}", err));
+                anyhow::bail!(format!("Could not run linker: {}", err));
             }
         }
 
@@ -4,13 +4,17 @@ extern crate hamcrest2;
 mod driver_controller_spec {
 
     use anyhow::bail;
+    use common::assembler_syntax::AssemblerSyntax;
+    use common::processor::Processor;
     use common::target_platform::TargetPlatform;
+    use common_test::file_utils_test_helper::temp_config_dir;
     use sysexits::ExitCode;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
 
     use crate::driver::{DriverOptions, MockDriver};
     use crate::driver_controller::{DefaultDriverController, DriverController};
     use crate::executor::Execution;
+    use crate::toolchain::Toolchain;
 
     #[ctor::ctor]
     fn before_each() {
@@ -20,13 +24,22 @@ mod driver_controller_spec {
     fn driver_options() -> DriverOptions {
         let c_file = PathBuf::from("file.c");
         DriverOptions {
-            c_file: Box::new(c_file),
+            input_files: vec![c_file],
             lex: false,
             parse: false,
             codegen: false,
             save_temps: false,
             stop_after_compilation: false,
+            stop_after_assembly: false,
             target_platform: TargetPlatform::Transputer,
+            assembler_syntax: AssemblerSyntax::MASM,
+            processor: Processor::T425,
+            optimisation_level: 0,
+            external_compiler: false,
+            output_file: None,
+            build_directory: None,
+            preprocessor_options: vec![],
+            toolchain: Toolchain::default(),
         }
     }
     
@@ -34,11 +47,13 @@ mod driver_controller_spec {
     fn calls_phases_happy_path() {
         let mut mock_driver = MockDriver::new();
         let expected_preprocessor_return: Result<Execution, anyhow::Error> = Ok(Execution { exit_code: Some(0), stdout: None, stderr: None });
-        mock_driver.expect_preprocess().times(1).return_once(move || expected_preprocessor_return);
+        mock_driver.expect_preprocess().times(1).return_once(move |_| expected_preprocessor_return);
         let expected_compiler_return: Result<Execution, anyhow::Error> = Ok(Execution { exit_code: Some(0), stdout: None, stderr: None });
-        mock_driver.expect_compile().times(1).return_once(move || expected_compiler_return);
+        mock_driver.expect_compile().times(1).return_once(move |_| expected_compiler_return);
         let expected_assembler_return: Result<Execution, anyhow::Error> = Ok(Execution { exit_code: Some(0), stdout: None, stderr: None });
-        mock_driver.expect_assemble().times(1).return_once(move || expected_assembler_return);
+        mock_driver.expect_assemble().times(1).return_once(move |_| expected_assembler_return);
+        let expected_linker_return: Result<Execution, anyhow::Error> = Ok(Execution { exit_code: Some(0), stdout: None, stderr: None });
+        mock_driver.expect_link().times(1).return_once(move |_| expected_linker_return);
         let driver_options = driver_options();
 
         let sut = DefaultDriverController::default();
@@ -51,7 +66,7 @@ mod driver_controller_spec {
     #[test]
     fn preprocessor_fails() {
         let mut mock_driver = MockDriver::new();
-        mock_driver.expect_preprocess().return_once(move || bail!("Preprocessor failed"));
+        mock_driver.expect_preprocess().return_once(move |_| bail!("Preprocessor failed"));
         let driver_options = driver_options();
 
         let sut = DefaultDriverController::default();
@@ -65,8 +80,8 @@ mod driver_controller_spec {
     fn compiler_fails() {
         let mut mock_driver = MockDriver::new();
         let expected_preprocessor_return: Result<Execution, anyhow::Error> = Ok(Execution { exit_code: Some(0), stdout: None, stderr: None });
-        mock_driver.expect_preprocess().return_once(move || expected_preprocessor_return);
-        mock_driver.expect_compile().return_once(move || bail!("Compiler failed"));
+        mock_driver.expect_preprocess().return_once(move |_| expected_preprocessor_return);
+        mock_driver.expect_compile().return_once(move |_| bail!("Compiler failed"));
         let driver_options = driver_options();
 
         let sut = DefaultDriverController::default();
@@ -80,10 +95,10 @@ mod driver_controller_spec {
     fn assembler_fails() {
         let mut mock_driver = MockDriver::new();
         let expected_preprocessor_return: Result<Execution, anyhow::Error> = Ok(Execution { exit_code: Some(0), stdout: None, stderr: None });
-        mock_driver.expect_preprocess().return_once(move || expected_preprocessor_return);
+        mock_driver.expect_preprocess().return_once(move |_| expected_preprocessor_return);
         let expected_compiler_return: Result<Execution, anyhow::Error> = Ok(Execution { exit_code: Some(0), stdout: None, stderr: None });
-        mock_driver.expect_compile().return_once(move || expected_compiler_return);
-        mock_driver.expect_assemble().return_once(move || bail!("Assembler failed"));
+        mock_driver.expect_compile().return_once(move |_| expected_compiler_return);
+        mock_driver.expect_assemble().return_once(move |_| bail!("Assembler failed"));
         let driver_options = driver_options();
 
         let sut = DefaultDriverController::default();
@@ -97,9 +112,9 @@ mod driver_controller_spec {
     fn only_compiles_with_stop_option() {
         let mut mock_driver = MockDriver::new();
         let expected_preprocessor_return: Result<Execution, anyhow::Error> = Ok(Execution { exit_code: Some(0), stdout: None, stderr: None });
-        mock_driver.expect_preprocess().times(1).return_once(move || expected_preprocessor_return);
+        mock_driver.expect_preprocess().times(1).return_once(move |_| expected_preprocessor_return);
         let expected_compiler_return: Result<Execution, anyhow::Error> = Ok(Execution { exit_code: Some(0), stdout: None, stderr: None });
-        mock_driver.expect_compile().times(1).return_once(move || expected_compiler_return);
+        mock_driver.expect_compile().times(1).return_once(move |_| expected_compiler_return);
         mock_driver.expect_assemble().never();
         let mut driver_options = driver_options();
         driver_options.stop_after_compilation = true; // Critical!
@@ -110,4 +125,268 @@ mod driver_controller_spec {
         let exit_code = res.ok().unwrap();
         assert_eq!(exit_code, ExitCode::Ok);
     }
+
+    fn exited(code: Option<i32>, stderr: &str) -> Result<Execution, anyhow::Error> {
+        Ok(Execution { exit_code: code, stdout: None, stderr: Some(stderr.to_owned()) })
+    }
+
+    #[test]
+    fn preprocessor_exiting_non_zero_stops_before_compilation() {
+        let mut mock_driver = MockDriver::new();
+        mock_driver.expect_preprocess().times(1).return_once(move |_| exited(Some(1), "file.c:1:10: fatal error: nothere.h: No such file or directory"));
+        mock_driver.expect_compile().never();
+        mock_driver.expect_assemble().never();
+        let driver_options = driver_options();
+
+        let sut = DefaultDriverController::default();
+        let res = sut.drive(driver_options, Box::new(mock_driver));
+
+        let exit_code = res.ok().unwrap();
+        assert_eq!(exit_code, ExitCode::DataErr);
+    }
+
+    #[test]
+    fn compiler_exit_code_is_propagated() {
+        let mut mock_driver = MockDriver::new();
+        mock_driver.expect_preprocess().times(1).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_compile().times(1).return_once(move |_| exited(Some(65), "Semantic analysis failed"));
+        mock_driver.expect_assemble().never();
+        let driver_options = driver_options();
+
+        let sut = DefaultDriverController::default();
+        let res = sut.drive(driver_options, Box::new(mock_driver));
+
+        let exit_code = res.ok().unwrap();
+        assert_eq!(exit_code, ExitCode::DataErr);
+    }
+
+    #[test]
+    fn compiler_exiting_with_a_non_sysexits_code_is_a_software_error() {
+        let mut mock_driver = MockDriver::new();
+        mock_driver.expect_preprocess().times(1).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_compile().times(1).return_once(move |_| exited(Some(101), "thread 'main' panicked"));
+        mock_driver.expect_assemble().never();
+        let driver_options = driver_options();
+
+        let sut = DefaultDriverController::default();
+        let res = sut.drive(driver_options, Box::new(mock_driver));
+
+        let exit_code = res.ok().unwrap();
+        assert_eq!(exit_code, ExitCode::Software);
+    }
+
+    #[test]
+    fn compiler_killed_by_a_signal_is_a_software_error() {
+        let mut mock_driver = MockDriver::new();
+        mock_driver.expect_preprocess().times(1).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_compile().times(1).return_once(move |_| exited(None, ""));
+        mock_driver.expect_assemble().never();
+        let driver_options = driver_options();
+
+        let sut = DefaultDriverController::default();
+        let res = sut.drive(driver_options, Box::new(mock_driver));
+
+        let exit_code = res.ok().unwrap();
+        assert_eq!(exit_code, ExitCode::Software);
+    }
+
+    #[test]
+    fn assembler_exiting_non_zero_is_a_software_error() {
+        let mut mock_driver = MockDriver::new();
+        mock_driver.expect_preprocess().times(1).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_compile().times(1).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_assemble().times(1).return_once(move |_| exited(Some(1), "file.s(3): syntax error"));
+        let driver_options = driver_options();
+
+        let sut = DefaultDriverController::default();
+        let res = sut.drive(driver_options, Box::new(mock_driver));
+
+        let exit_code = res.ok().unwrap();
+        assert_eq!(exit_code, ExitCode::Software);
+    }
+
+    #[test]
+    fn assembler_rejecting_an_assembly_input_is_a_data_error() {
+        let mut mock_driver = MockDriver::new();
+        mock_driver.expect_preprocess().never();
+        mock_driver.expect_compile().never();
+        mock_driver.expect_assemble().times(1).return_once(move |_| exited(Some(1), "file.asm(3): syntax error"));
+        let mut driver_options = driver_options();
+        driver_options.input_files = vec![PathBuf::from("file.asm")];
+        driver_options.toolchain = Toolchain::from_toml("[Transputer]\nlinker = \"ilink\"\n").unwrap();
+
+        let sut = DefaultDriverController::default();
+        let res = sut.drive(driver_options, Box::new(mock_driver));
+
+        let exit_code = res.ok().unwrap();
+        assert_eq!(exit_code, ExitCode::DataErr);
+    }
+
+    #[test]
+    fn stops_after_compilation_when_compiler_is_stopped_early() {
+        let mut mock_driver = MockDriver::new();
+        mock_driver.expect_preprocess().times(1).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_compile().times(1).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_assemble().never();
+        let mut driver_options = driver_options();
+        driver_options.parse = true;
+
+        let sut = DefaultDriverController::default();
+        let res = sut.drive(driver_options, Box::new(mock_driver));
+
+        let exit_code = res.ok().unwrap();
+        assert_eq!(exit_code, ExitCode::Ok);
+    }
+
+    #[test]
+    fn inputs_run_through_only_the_stages_they_need() {
+        let mut mock_driver = MockDriver::new();
+        mock_driver.expect_preprocess().times(1).withf(|source| source == Path::new("first.c")).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_compile().times(1).withf(|source| source == Path::new("first.c")).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_compile().times(1).withf(|source| source == Path::new("second.i")).return_once(move |_| exited(Some(0), ""));
+        for module in ["first.c", "second.i", "third.asm"] {
+            mock_driver.expect_assemble().times(1).withf(move |sources| sources == [PathBuf::from(module)]).return_once(move |_| exited(Some(0), ""));
+        }
+        let all_sources = vec![PathBuf::from("first.c"), PathBuf::from("second.i"), PathBuf::from("third.asm"), PathBuf::from("fourth.o")];
+        mock_driver.expect_link().times(1).withf(move |sources| sources == all_sources.as_slice()).return_once(move |_| exited(Some(0), ""));
+        let mut driver_options = driver_options();
+        driver_options.input_files = vec![PathBuf::from("first.c"), PathBuf::from("second.i"), PathBuf::from("third.asm"), PathBuf::from("fourth.o")];
+        // Assembly inputs can only be linked by a configured Transputer linker.
+        driver_options.toolchain = Toolchain::from_toml("[Transputer]\nlinker = \"ilink\"\n").unwrap();
+
+        let sut = DefaultDriverController::default();
+        let res = sut.drive(driver_options, Box::new(mock_driver));
+
+        let exit_code = res.ok().unwrap();
+        assert_eq!(exit_code, ExitCode::Ok);
+    }
+
+    #[test]
+    fn assembly_inputs_are_rejected_without_a_transputer_linker() {
+        let mut mock_driver = MockDriver::new();
+        mock_driver.expect_preprocess().never();
+        mock_driver.expect_compile().never();
+        mock_driver.expect_assemble().never();
+        mock_driver.expect_link().never();
+        let mut driver_options = driver_options();
+        driver_options.input_files = vec![PathBuf::from("main.c"), PathBuf::from("extra.asm")];
+
+        let sut = DefaultDriverController::default();
+        let res = sut.drive(driver_options, Box::new(mock_driver));
+
+        assert_eq!(res.ok().unwrap(), ExitCode::Usage);
+    }
+
+    #[test]
+    fn assembly_inputs_are_assembled_without_a_transputer_linker_when_not_linking() {
+        let mut mock_driver = MockDriver::new();
+        mock_driver.expect_assemble().times(1).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_link().never();
+        let mut driver_options = driver_options();
+        driver_options.input_files = vec![PathBuf::from("extra.asm")];
+        driver_options.stop_after_assembly = true;
+
+        let sut = DefaultDriverController::default();
+        let res = sut.drive(driver_options, Box::new(mock_driver));
+
+        assert_eq!(res.ok().unwrap(), ExitCode::Ok);
+    }
+
+    #[test]
+    fn failing_input_stops_later_inputs() {
+        let mut mock_driver = MockDriver::new();
+        mock_driver.expect_preprocess().times(1).withf(|source| source == Path::new("first.c")).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_compile().times(1).withf(|source| source == Path::new("first.c")).return_once(move |_| exited(Some(65), ""));
+        mock_driver.expect_assemble().never();
+        let mut driver_options = driver_options();
+        driver_options.input_files = vec![PathBuf::from("first.c"), PathBuf::from("second.c")];
+
+        let sut = DefaultDriverController::default();
+        let res = sut.drive(driver_options, Box::new(mock_driver));
+
+        let exit_code = res.ok().unwrap();
+        assert_eq!(exit_code, ExitCode::DataErr);
+    }
+
+    #[test]
+    fn build_directory_is_created() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let build_directory = temp.join("build").join("transputer");
+        let mut mock_driver = MockDriver::new();
+        mock_driver.expect_preprocess().times(1).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_compile().times(1).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_assemble().times(1).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_link().times(1).return_once(move |_| exited(Some(0), ""));
+        let mut driver_options = driver_options();
+        driver_options.build_directory = Some(build_directory.clone());
+
+        let sut = DefaultDriverController::default();
+        let res = sut.drive(driver_options, Box::new(mock_driver));
+
+        assert_eq!(res.ok().unwrap(), ExitCode::Ok);
+        assert!(build_directory.is_dir(), "build directory was not created");
+    }
+
+    #[test]
+    fn only_assembles_with_no_link_option() {
+        let mut mock_driver = MockDriver::new();
+        mock_driver.expect_preprocess().times(1).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_compile().times(1).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_assemble().times(1).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_link().never();
+        let mut driver_options = driver_options();
+        driver_options.stop_after_assembly = true;
+
+        let sut = DefaultDriverController::default();
+        let res = sut.drive(driver_options, Box::new(mock_driver));
+
+        assert_eq!(res.ok().unwrap(), ExitCode::Ok);
+    }
+
+    #[test]
+    fn objects_are_only_linked() {
+        let mut mock_driver = MockDriver::new();
+        mock_driver.expect_preprocess().never();
+        mock_driver.expect_compile().never();
+        mock_driver.expect_assemble().never();
+        mock_driver.expect_link().times(1).return_once(move |_| exited(Some(0), ""));
+        let mut driver_options = driver_options();
+        driver_options.input_files = vec![PathBuf::from("first.o"), PathBuf::from("runtime.lib")];
+
+        let sut = DefaultDriverController::default();
+        let res = sut.drive(driver_options, Box::new(mock_driver));
+
+        assert_eq!(res.ok().unwrap(), ExitCode::Ok);
+    }
+
+    #[test]
+    fn linker_exiting_non_zero_is_a_data_error() {
+        let mut mock_driver = MockDriver::new();
+        mock_driver.expect_preprocess().times(1).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_compile().times(1).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_assemble().times(1).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_link().times(1).return_once(move |_| exited(Some(1), "undefined reference to `helper'"));
+        let driver_options = driver_options();
+
+        let sut = DefaultDriverController::default();
+        let res = sut.drive(driver_options, Box::new(mock_driver));
+
+        assert_eq!(res.ok().unwrap(), ExitCode::DataErr);
+    }
+
+    #[test]
+    fn linker_fails() {
+        let mut mock_driver = MockDriver::new();
+        mock_driver.expect_preprocess().times(1).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_compile().times(1).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_assemble().times(1).return_once(move |_| exited(Some(0), ""));
+        mock_driver.expect_link().times(1).return_once(move |_| bail!("No linker is configured for EPOC16"));
+        let driver_options = driver_options();
+
+        let sut = DefaultDriverController::default();
+        let res = sut.drive(driver_options, Box::new(mock_driver));
+
+        let msg = res.err().unwrap().to_string();
+        assert_eq!(msg, "Could not run linker: No linker is configured for EPOC16");
+    }
 }
@@ -3,14 +3,20 @@ extern crate hamcrest2;
 #[cfg(test)]
 mod driver_spec {
 
+    use common::assembler_syntax::AssemblerSyntax;
+    use common::processor::Processor;
     use common_test::file_utils_test_helper::temp_config_dir;
     use hamcrest2::prelude::*;
     use mockall::*;
     use std::fs::File;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
 
     use crate::driver::{DefaultDriver, Driver, DriverOptions, TargetPlatform};
     use crate::executor::{Execution, MockExecutor};
+    use crate::freestanding::{header_directory, predefined_macros};
+    use crate::toolchain::Toolchain;
+    use tlink::encoding::CALL;
+    use tlink::object::{Fragment, Object, Operand};
 
     #[ctor::ctor]
     fn before_each() {
@@ -20,10 +26,7 @@ mod driver_spec {
     #[test]
     fn calls_preprocessor() {
         let mut mock_executor = MockExecutor::new();
-        let expected_executor_args: Vec<String> = vec!["gcc", "-E", "-P", "file.c", "-o", "file.i"]
-            .iter()
-            .map(|str| str.to_string())
-            .collect();
+        let expected_executor_args: Vec<String> = transputer_preprocessor_args(&["file.c", "-o", "file.i"]);
         let expected_executor_return = Ok(Execution {
             exit_code: Some(0i32),
             stdout: None,
@@ -36,17 +39,38 @@ mod driver_spec {
             .return_once(move |_| expected_executor_return);
         let c_file = PathBuf::from("file.c");
         let driver_options = DriverOptions {
-            c_file: Box::new(c_file),
+            input_files: vec![c_file.clone()],
             lex: false,
             parse: false,
             codegen: false,
             save_temps: false,
             stop_after_compilation: false,
+            stop_after_assembly: false,
             target_platform: TargetPlatform::Transputer,
+            assembler_syntax: AssemblerSyntax::MASM,
+            processor: Processor::T425,
+            optimisation_level: 0,
+            external_compiler: true,
+            output_file: None,
+            build_directory: None,
+            preprocessor_options: vec![],
+            toolchain: Toolchain::default(),
         };
 
         let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
-        execution_ok(sut.preprocess());
+        execution_ok(sut.preprocess(&c_file));
+    }
+
+    /// gcc's command line for preprocessing for the T425, before the given arguments.
+    fn transputer_preprocessor_args(args: &[&str]) -> Vec<String> {
+        let mut expected: Vec<String> = ["gcc", "-E", "-P", "-undef", "-nostdinc", "-ffreestanding", "-idirafter"]
+            .iter()
+            .map(|str| str.to_string())
+            .collect();
+        expected.push(header_directory().unwrap().to_string_lossy().to_string());
+        expected.extend(predefined_macros(TargetPlatform::Transputer, Processor::T425).iter().map(|(name, value)| format!("-D{}={}", name, value)));
+        expected.extend(args.iter().map(|str| str.to_string()));
+        expected
     }
 
     fn execution_ok(result: Result<Execution, anyhow::Error>) {
@@ -67,7 +91,7 @@ mod driver_spec {
     fn calls_compiler() {
         // TODO will need revisiting when we have a compiler!
         let mut mock_executor = MockExecutor::new();
-        let expected_executor_args: Vec<String> = vec!["rcc1", "file.i", "-o", "file.asm"]
+        let expected_executor_args: Vec<String> = ["rcc1", "file.i", "-o", "file.o"]
             .iter()
             .map(|str| str.to_string())
             .collect();
@@ -83,32 +107,50 @@ mod driver_spec {
             .return_once(move |_| expected_executor_return);
         let c_file = PathBuf::from("file.c");
         let driver_options = DriverOptions {
-            c_file: Box::new(c_file),
+            input_files: vec![c_file.clone()],
             lex: false,
             parse: false,
             codegen: false,
             save_temps: false,
             stop_after_compilation: false,
+            stop_after_assembly: false,
             target_platform: TargetPlatform::Transputer,
+            assembler_syntax: AssemblerSyntax::MASM,
+            processor: Processor::T425,
+            optimisation_level: 0,
+            external_compiler: true,
+            output_file: None,
+            build_directory: None,
+            preprocessor_options: vec![],
+            toolchain: Toolchain::default(),
         };
 
         let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
-        execution_ok(sut.compile());
+        execution_ok(sut.compile(&c_file));
     }
 
     #[test]
     fn flags_passed_to_compiler() {
         // TODO will need revisiting when we have a compiler!
         let driver_options = DriverOptions {
-            c_file: Box::new(PathBuf::from("file.c")),
+            input_files: vec![PathBuf::from("file.c")],
             lex: true,
             parse: true,
             codegen: true,
-            save_temps: false,              // These two aren't passed through
-            stop_after_compilation: false,  // These two aren't passed through
+            save_temps: false,              // These three aren't passed through
+            stop_after_compilation: false,  // These three aren't passed through
+            stop_after_assembly: false,     // These three aren't passed through
             target_platform: TargetPlatform::Transputer,
+            assembler_syntax: AssemblerSyntax::MASM,
+            processor: Processor::T425,
+            optimisation_level: 0,
+            external_compiler: true,
+            output_file: None,
+            build_directory: None,
+            preprocessor_options: vec![],
+            toolchain: Toolchain::default(),
         };
-        let expected_args = vec!["rcc1", "--lex", "--parse", "--codegen", "file.i", "-o", "file.asm"];
+        let expected_args = vec!["rcc1", "--lex", "--parse", "--codegen", "file.i", "-o", "file.o"];
         check_compiler_flags(driver_options, &expected_args);
     }
 
@@ -130,21 +172,29 @@ mod driver_spec {
             .return_once(move |_| expected_executor_return);
 
         let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
-        execution_ok(sut.compile());
+        execution_ok(sut.compile(Path::new("file.c")));
     }
 
     #[test]
     fn x86_64_architecture_passed_to_compiler() {
-        // TODO will need revisiting when we have a compiler!
-        let expected_args = vec!["rcc1", "--architecture", "X86_64", "file.i", "-o", "file.asm"];
+        let expected_args = vec!["rcc1", "--architecture", "X86_64", "file.i", "-o", "file.s"];
         let driver_options = DriverOptions {
-            c_file: Box::new(PathBuf::from("file.c")),
+            input_files: vec![PathBuf::from("file.c")],
             lex: false,
             parse: false,
             codegen: false,
             save_temps: false,
             stop_after_compilation: false,
+            stop_after_assembly: false,
             target_platform: TargetPlatform::X86_64,
+            assembler_syntax: AssemblerSyntax::MASM,
+            processor: Processor::T425,
+            optimisation_level: 0,
+            external_compiler: true,
+            output_file: None,
+            build_directory: None,
+            preprocessor_options: vec![],
+            toolchain: Toolchain::default(),
         };
         check_compiler_flags(driver_options, &expected_args);
     }
@@ -154,13 +204,94 @@ mod driver_spec {
         // TODO will need revisiting when we have a compiler!
         let expected_args = vec!["rcc1", "--architecture", "EPOC16", "file.i", "-o", "file.asm"];
         let driver_options = DriverOptions {
-            c_file: Box::new(PathBuf::from("file.c")),
+            input_files: vec![PathBuf::from("file.c")],
+            lex: false,
+            parse: false,
+            codegen: false,
+            save_temps: false,
+            stop_after_compilation: false,
+            stop_after_assembly: false,
+            target_platform: TargetPlatform::EPOC16,
+            assembler_syntax: AssemblerSyntax::MASM,
+            processor: Processor::T425,
+            optimisation_level: 0,
+            external_compiler: true,
+            output_file: None,
+            build_directory: None,
+            preprocessor_options: vec![],
+            toolchain: Toolchain::default(),
+        };
+        check_compiler_flags(driver_options, &expected_args);
+    }
+
+    #[test]
+    fn nasm_syntax_passed_to_compiler() {
+        let expected_args = vec!["rcc1", "--architecture", "EPOC16", "--syntax", "NASM", "file.i", "-o", "file.asm"];
+        let driver_options = DriverOptions {
+            input_files: vec![PathBuf::from("file.c")],
             lex: false,
             parse: false,
             codegen: false,
             save_temps: false,
             stop_after_compilation: false,
+            stop_after_assembly: false,
             target_platform: TargetPlatform::EPOC16,
+            assembler_syntax: AssemblerSyntax::NASM,
+            processor: Processor::T425,
+            optimisation_level: 0,
+            external_compiler: true,
+            output_file: None,
+            build_directory: None,
+            preprocessor_options: vec![],
+            toolchain: Toolchain::default(),
+        };
+        check_compiler_flags(driver_options, &expected_args);
+    }
+
+    #[test]
+    fn cpu_passed_to_compiler() {
+        let expected_args = vec!["rcc1", "--cpu", "T800", "file.i", "-o", "file.o"];
+        let driver_options = DriverOptions {
+            input_files: vec![PathBuf::from("file.c")],
+            lex: false,
+            parse: false,
+            codegen: false,
+            save_temps: false,
+            stop_after_compilation: false,
+            stop_after_assembly: false,
+            target_platform: TargetPlatform::Transputer,
+            assembler_syntax: AssemblerSyntax::MASM,
+            processor: Processor::T800,
+            optimisation_level: 0,
+            external_compiler: true,
+            output_file: None,
+            build_directory: None,
+            preprocessor_options: vec![],
+            toolchain: Toolchain::default(),
+        };
+        check_compiler_flags(driver_options, &expected_args);
+    }
+
+    #[test]
+    fn optimisation_level_passed_to_compiler() {
+        let expected_args = vec!["rcc1", "--architecture", "X86_64", "-O1", "file.i", "-o", "file.s"];
+        let driver_options = DriverOptions {
+            input_files: vec![PathBuf::from("file.c")],
+            lex: false,
+            parse: false,
+            codegen: false,
+            save_temps: false,
+            stop_after_compilation: false,
+            stop_after_assembly: false,
+            target_platform: TargetPlatform::X86_64,
+            assembler_syntax: AssemblerSyntax::MASM,
+            processor: Processor::T425,
+            optimisation_level: 1,
+            external_compiler: true,
+            output_file: None,
+            build_directory: None,
+            preprocessor_options: vec![],
+            toolchain: Toolchain::default(),
         };
         check_compiler_flags(driver_options, &expected_args);
     }
@@ -168,15 +299,24 @@ mod driver_spec {
     #[test]
     fn transputer_architecture_is_default_and_not_passed_to_compiler() {
         // TODO will need revisiting when we have a compiler!
-        let expected_args = vec!["rcc1", "file.i", "-o", "file.asm"];
+        let expected_args = vec!["rcc1", "file.i", "-o", "file.o"];
         let driver_options = DriverOptions {
-            c_file: Box::new(PathBuf::from("file.c")),
+            input_files: vec![PathBuf::from("file.c")],
             lex: false,
             parse: false,
             codegen: false,
             save_temps: false,
             stop_after_compilation: false,
+            stop_after_assembly: false,
             target_platform: TargetPlatform::Transputer,
+            assembler_syntax: AssemblerSyntax::MASM,
+            processor: Processor::T425,
+            optimisation_level: 0,
+            external_compiler: true,
+            output_file: None,
+            build_directory: None,
+            preprocessor_options: vec![],
+            toolchain: Toolchain::default(),
         };
         check_compiler_flags(driver_options, &expected_args);
     }
@@ -184,7 +324,7 @@ mod driver_spec {
     #[test]
     fn calls_assembler() {
         let mut mock_executor = MockExecutor::new();
-        let expected_executor_args: Vec<String> = vec!["tmasm", "file.asm", "-o", "file.bin", "-l", "file.lst"]
+        let expected_executor_args: Vec<String> = ["tmasm", "file.asm", "-o", "file.o", "-l", "file.lst"]
             .iter()
             .map(|str| str.to_string())
             .collect();
@@ -200,17 +340,115 @@ mod driver_spec {
             .return_once(move |_| expected_executor_return);
         let c_file = PathBuf::from("file.c");
         let driver_options = DriverOptions {
-            c_file: Box::new(c_file),
+            input_files: vec![c_file.clone()],
             lex: false,
             parse: false,
             codegen: false,
             save_temps: false,
             stop_after_compilation: false,
+            stop_after_assembly: false,
             target_platform: TargetPlatform::Transputer,
+            assembler_syntax: AssemblerSyntax::MASM,
+            processor: Processor::T425,
+            optimisation_level: 0,
+            external_compiler: true,
+            output_file: None,
+            build_directory: None,
+            preprocessor_options: vec![],
+            toolchain: tmasm_toolchain(),
         };
 
         let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
-        execution_ok(sut.assemble());
+        execution_ok(sut.assemble(&[c_file]));
+    }
+
+    #[test]
+    fn calls_gcc_to_assemble_for_x86_64() {
+        let mut mock_executor = MockExecutor::new();
+        let expected_executor_args: Vec<String> = ["gcc", "-c", "file.s", "-o", "file.o"]
+            .iter()
+            .map(|str| str.to_string())
+            .collect();
+        let expected_executor_return = Ok(Execution {
+            exit_code: Some(0i32),
+            stdout: None,
+            stderr: None,
+        });
+        mock_executor
+            .expect_run()
+            .times(1)
+            .with(predicate::eq(expected_executor_args))
+            .return_once(move |_| expected_executor_return);
+        let driver_options = DriverOptions {
+            input_files: vec![PathBuf::from("file.c")],
+            lex: false,
+            parse: false,
+            codegen: false,
+            save_temps: true,
+            stop_after_compilation: false,
+            stop_after_assembly: false,
+            target_platform: TargetPlatform::X86_64,
+            assembler_syntax: AssemblerSyntax::MASM,
+            processor: Processor::T425,
+            optimisation_level: 0,
+            external_compiler: true,
+            output_file: None,
+            build_directory: None,
+            preprocessor_options: vec![],
+            toolchain: Toolchain::default(),
+        };
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        execution_ok(sut.assemble(&[PathBuf::from("file.c")]));
+    }
+
+    fn check_epoc16_assembler(assembler_syntax: AssemblerSyntax, expected_args: &[&str]) {
+        let mut mock_executor = MockExecutor::new();
+        let expected_executor_args: Vec<String> = expected_args
+            .iter()
+            .map(|str| str.to_string())
+            .collect();
+        let expected_executor_return = Ok(Execution {
+            exit_code: Some(0i32),
+            stdout: None,
+            stderr: None,
+        });
+        mock_executor
+            .expect_run()
+            .times(1)
+            .with(predicate::eq(expected_executor_args))
+            .return_once(move |_| expected_executor_return);
+        let driver_options = DriverOptions {
+            input_files: vec![PathBuf::from("file.c")],
+            lex: false,
+            parse: false,
+            codegen: false,
+            save_temps: true,
+            stop_after_compilation: false,
+            stop_after_assembly: false,
+            target_platform: TargetPlatform::EPOC16,
+            assembler_syntax,
+            processor: Processor::T425,
+            optimisation_level: 0,
+            external_compiler: true,
+            output_file: None,
+            build_directory: None,
+            preprocessor_options: vec![],
+            toolchain: Toolchain::default(),
+        };
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        execution_ok(sut.assemble(&[PathBuf::from("file.c")]));
+    }
+
+    #[test]
+    fn calls_jwasm_for_epoc16_masm_syntax() {
+        check_epoc16_assembler(AssemblerSyntax::MASM, &["jwasm", "-q", "-omf", "-Fofile.obj", "-Fl=file.lst", "file.asm"]);
+    }
+
+    #[test]
+    fn calls_nasm_for_epoc16_nasm_syntax() {
+        check_epoc16_assembler(AssemblerSyntax::NASM, &["nasm", "-f", "obj", "file.asm", "-o", "file.obj", "-l", "file.lst"]);
     }
 
     #[test]
@@ -220,12 +458,12 @@ mod driver_spec {
         File::create(i_file.clone()).unwrap();
         let i_file_absolute = i_file.as_os_str().to_str().unwrap();
         assert!(i_file.exists(), "temp preprocessor file was not created");
-        let asm_file = temp.join("file.asm");
-        let asm_file_absolute = asm_file.as_os_str().to_str().unwrap();
+        let o_file = temp.join("file.o");
+        let o_file_absolute = o_file.as_os_str().to_str().unwrap();
 
         // Pretend to run the compiler.
         let mut mock_executor = MockExecutor::new();
-        let expected_executor_args: Vec<String> = vec!["rcc1", i_file_absolute, "-o", asm_file_absolute]
+        let expected_executor_args: Vec<String> = ["rcc1", i_file_absolute, "-o", o_file_absolute]
             .iter()
             .map(|str| str.to_string())
             .collect();
@@ -241,17 +479,26 @@ mod driver_spec {
             .return_once(move |_| expected_executor_return);
         let c_file = temp.join("file.c");
         let driver_options = DriverOptions {
-            c_file: Box::new(c_file),
+            input_files: vec![c_file.clone()],
             lex: false,
             parse: false,
             codegen: false,
             save_temps: false,
             stop_after_compilation: false,
+            stop_after_assembly: false,
             target_platform: TargetPlatform::Transputer,
+            assembler_syntax: AssemblerSyntax::MASM,
+            processor: Processor::T425,
+            optimisation_level: 0,
+            external_compiler: true,
+            output_file: None,
+            build_directory: None,
+            preprocessor_options: vec![],
+            toolchain: Toolchain::default(),
         };
 
         let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
-        execution_ok(sut.compile());
+        execution_ok(sut.compile(&c_file));
         assert!(!i_file.exists(), "temp preprocessor file was not deleted by driver");
     }
 
@@ -262,12 +509,12 @@ mod driver_spec {
         File::create(i_file.clone()).unwrap();
         let i_file_absolute = i_file.as_os_str().to_str().unwrap();
         assert!(i_file.exists(), "temp preprocessor file was not created");
-        let asm_file = temp.join("file.asm");
-        let asm_file_absolute = asm_file.as_os_str().to_str().unwrap();
+        let o_file = temp.join("file.o");
+        let o_file_absolute = o_file.as_os_str().to_str().unwrap();
 
         // Pretend to run the compiler.
         let mut mock_executor = MockExecutor::new();
-        let expected_executor_args: Vec<String> = vec!["rcc1", i_file_absolute, "-o", asm_file_absolute]
+        let expected_executor_args: Vec<String> = ["rcc1", i_file_absolute, "-o", o_file_absolute]
             .iter()
             .map(|str| str.to_string())
             .collect();
@@ -283,17 +530,26 @@ mod driver_spec {
             .return_once(move |_| expected_executor_return);
         let c_file = temp.join("file.c");
         let driver_options = DriverOptions {
-            c_file: Box::new(c_file),
+            input_files: vec![c_file.clone()],
             lex: false,
             parse: false,
             codegen: false,
             save_temps: true,
             stop_after_compilation: false,
+            stop_after_assembly: false,
             target_platform: TargetPlatform::Transputer,
+            assembler_syntax: AssemblerSyntax::MASM,
+            processor: Processor::T425,
+            optimisation_level: 0,
+            external_compiler: true,
+            output_file: None,
+            build_directory: None,
+            preprocessor_options: vec![],
+            toolchain: Toolchain::default(),
         };
 
         let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
-        execution_ok(sut.compile());
+        execution_ok(sut.compile(&c_file));
         assert!(i_file.exists(), "temp preprocessor file was deleted by driver");
     }
 
@@ -307,14 +563,14 @@ mod driver_spec {
         File::create(asm_file.clone()).unwrap();
         let asm_file_absolute = asm_file.as_os_str().to_str().unwrap();
         assert!(asm_file.exists(), "temp assembly file was not created");
-        let bin_file = temp.join("file.bin");
-        let bin_file_absolute = bin_file.as_os_str().to_str().unwrap();
+        let o_file = temp.join("file.o");
+        let o_file_absolute = o_file.as_os_str().to_str().unwrap();
         let lst_file = temp.join("file.lst");
         let lst_file_absolute = lst_file.as_os_str().to_str().unwrap();
 
         // Pretend to run the assembler.
         let mut mock_executor = MockExecutor::new();
-        let expected_executor_args: Vec<String> = vec!["tmasm", asm_file_absolute, "-o", bin_file_absolute, "-l", lst_file_absolute]
+        let expected_executor_args: Vec<String> = ["tmasm", asm_file_absolute, "-o", o_file_absolute, "-l", lst_file_absolute]
             .iter()
             .map(|str| str.to_string())
             .collect();
@@ -330,17 +586,26 @@ mod driver_spec {
             .return_once(move |_| expected_executor_return);
         let c_file = temp.join("file.c");
         let driver_options = DriverOptions {
-            c_file: Box::new(c_file),
+            input_files: vec![c_file.clone()],
             lex: false,
             parse: false,
             codegen: false,
             save_temps: false,
             stop_after_compilation: false,
+            stop_after_assembly: false,
             target_platform: TargetPlatform::Transputer,
+            assembler_syntax: AssemblerSyntax::MASM,
+            processor: Processor::T425,
+            optimisation_level: 0,
+            external_compiler: true,
+            output_file: None,
+            build_directory: None,
+            preprocessor_options: vec![],
+            toolchain: tmasm_toolchain(),
         };
 
         let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
-        execution_ok(sut.assemble());
+        execution_ok(sut.assemble(&[c_file]));
         assert!(!asm_file.exists(), "temp assembly file was not deleted by driver");
     }
 
@@ -351,14 +616,14 @@ mod driver_spec {
         File::create(asm_file.clone()).unwrap();
         let asm_file_absolute = asm_file.as_os_str().to_str().unwrap();
         assert!(asm_file.exists(), "temp assembly file was not created");
-        let bin_file = temp.join("file.bin");
-        let bin_file_absolute = bin_file.as_os_str().to_str().unwrap();
+        let o_file = temp.join("file.o");
+        let o_file_absolute = o_file.as_os_str().to_str().unwrap();
         let lst_file = temp.join("file.lst");
         let lst_file_absolute = lst_file.as_os_str().to_str().unwrap();
 
         // Pretend to run the assembler.
         let mut mock_executor = MockExecutor::new();
-        let expected_executor_args: Vec<String> = vec!["tmasm", asm_file_absolute, "-o", bin_file_absolute, "-l", lst_file_absolute]
+        let expected_executor_args: Vec<String> = ["tmasm", asm_file_absolute, "-o", o_file_absolute, "-l", lst_file_absolute]
             .iter()
             .map(|str| str.to_string())
             .collect();
@@ -374,17 +639,558 @@ mod driver_spec {
             .return_once(move |_| expected_executor_return);
         let c_file = temp.join("file.c");
         let driver_options = DriverOptions {
-            c_file: Box::new(c_file),
+            input_files: vec![c_file.clone()],
             lex: false,
             parse: false,
             codegen: false,
             save_temps: true,
             stop_after_compilation: false,
+            stop_after_assembly: false,
             target_platform: TargetPlatform::Transputer,
+            assembler_syntax: AssemblerSyntax::MASM,
+            processor: Processor::T425,
+            optimisation_level: 0,
+            external_compiler: true,
+            output_file: None,
+            build_directory: None,
+            preprocessor_options: vec![],
+            toolchain: tmasm_toolchain(),
         };
 
         let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
-        execution_ok(sut.assemble());
+        execution_ok(sut.assemble(&[c_file]));
         assert!(asm_file.exists(), "temp assembly file was deleted by driver but save-temps given");
     }
+
+    fn modules_driver_options(input_files: Vec<PathBuf>, target_platform: TargetPlatform) -> DriverOptions {
+        DriverOptions {
+            input_files,
+            lex: false,
+            parse: false,
+            codegen: false,
+            save_temps: true,
+            stop_after_compilation: false,
+            stop_after_assembly: false,
+            target_platform,
+            assembler_syntax: AssemblerSyntax::MASM,
+            processor: Processor::T425,
+            optimisation_level: 0,
+            external_compiler: true,
+            output_file: None,
+            build_directory: None,
+            preprocessor_options: vec![],
+            toolchain: Toolchain::default(),
+        }
+    }
+
+    /// With a linker configured, the Transputer's modules are assembled by TMASM for it, rather
+    /// than compiled straight to the objects rcc links itself.
+    fn tmasm_toolchain() -> Toolchain {
+        Toolchain::from_toml("[Transputer]\nlinker = \"ilink\"\n").unwrap()
+    }
+
+    #[test]
+    fn x86_64_modules_are_linked_together() {
+        let mut mock_executor = MockExecutor::new();
+        let expected_executor_args: Vec<String> = ["gcc", "file.o", "other.o", "start.o", "crt.a", "-o", "file"]
+            .iter()
+            .map(|str| str.to_string())
+            .collect();
+        mock_executor
+            .expect_run()
+            .times(1)
+            .with(predicate::eq(expected_executor_args))
+            .return_once(move |_| Ok(Execution { exit_code: Some(0i32), stdout: None, stderr: None }));
+        let input_files = vec![PathBuf::from("file.c"), PathBuf::from("other.i"), PathBuf::from("start.s"), PathBuf::from("crt.a")];
+        let driver_options = modules_driver_options(input_files.clone(), TargetPlatform::X86_64);
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        execution_ok(sut.link(&input_files));
+    }
+
+    #[test]
+    fn transputer_modules_are_assembled_in_turn() {
+        let mut mock_executor = MockExecutor::new();
+        let mut sequence = Sequence::new();
+        for module in ["file", "other"] {
+            let expected_executor_args: Vec<String> = ["tmasm", &format!("{}.asm", module), "-o", &format!("{}.o", module), "-l", &format!("{}.lst", module)]
+                .iter()
+                .map(|str| str.to_string())
+                .collect();
+            mock_executor
+                .expect_run()
+                .times(1)
+                .in_sequence(&mut sequence)
+                .with(predicate::eq(expected_executor_args))
+                .return_once(move |_| Ok(Execution { exit_code: Some(0i32), stdout: None, stderr: None }));
+        }
+        let input_files = vec![PathBuf::from("file.c"), PathBuf::from("other.asm")];
+        let mut driver_options = modules_driver_options(input_files.clone(), TargetPlatform::Transputer);
+        driver_options.toolchain = tmasm_toolchain();
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        execution_ok(sut.assemble(&input_files));
+    }
+
+    #[test]
+    fn transputer_compiler_writes_assembly_for_a_configured_linker() {
+        let mut driver_options = modules_driver_options(vec![PathBuf::from("file.c")], TargetPlatform::Transputer);
+        driver_options.toolchain = tmasm_toolchain();
+        let expected_args = vec!["rcc1", "file.i", "-o", "file.asm"];
+        check_compiler_flags(driver_options, &expected_args);
+    }
+
+    #[test]
+    fn transputer_modules_compiled_to_objects_are_not_assembled() {
+        let mut mock_executor = MockExecutor::new();
+        let expected_executor_args: Vec<String> = ["tmasm", "other.asm", "-o", "other.o", "-l", "other.lst"]
+            .iter()
+            .map(|str| str.to_string())
+            .collect();
+        mock_executor
+            .expect_run()
+            .times(1)
+            .with(predicate::eq(expected_executor_args))
+            .return_once(move |_| Ok(Execution { exit_code: Some(0i32), stdout: None, stderr: None }));
+        let input_files = vec![PathBuf::from("file.c"), PathBuf::from("other.asm"), PathBuf::from("third.i")];
+        let driver_options = modules_driver_options(input_files.clone(), TargetPlatform::Transputer);
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        execution_ok(sut.assemble(&input_files));
+    }
+
+    #[test]
+    fn assembly_stops_at_the_first_failing_module() {
+        let mut mock_executor = MockExecutor::new();
+        mock_executor
+            .expect_run()
+            .times(1)
+            .return_once(move |_| Ok(Execution { exit_code: Some(1i32), stdout: None, stderr: None }));
+        let input_files = vec![PathBuf::from("file.c"), PathBuf::from("other.c")];
+        let driver_options = modules_driver_options(input_files.clone(), TargetPlatform::EPOC16);
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        let execution = sut.assemble(&input_files).unwrap();
+        assert_that!(execution.code(), equal_to(Some(1i32)));
+    }
+
+    #[test]
+    fn assembly_input_retained_after_assembly() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let asm_file = temp.join("file.asm");
+        File::create(asm_file.clone()).unwrap();
+
+        // Pretend to run the assembler.
+        let mut mock_executor = MockExecutor::new();
+        mock_executor
+            .expect_run()
+            .times(1)
+            .return_once(move |_| Ok(Execution { exit_code: Some(0i32), stdout: None, stderr: None }));
+        let mut driver_options = modules_driver_options(vec![asm_file.clone()], TargetPlatform::Transputer);
+        driver_options.save_temps = false;
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        execution_ok(sut.assemble(std::slice::from_ref(&asm_file)));
+        assert!(asm_file.exists(), "assembly input was deleted by driver");
+    }
+
+    #[test]
+    fn preprocessed_input_retained_after_compilation() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let i_file = temp.join("file.i");
+        File::create(i_file.clone()).unwrap();
+        let i_file_absolute = i_file.as_os_str().to_str().unwrap();
+        let o_file = temp.join("file.o");
+        let o_file_absolute = o_file.as_os_str().to_str().unwrap();
+
+        // Pretend to run the compiler.
+        let mut mock_executor = MockExecutor::new();
+        let expected_executor_args: Vec<String> = ["rcc1", i_file_absolute, "-o", o_file_absolute]
+            .iter()
+            .map(|str| str.to_string())
+            .collect();
+        mock_executor
+            .expect_run()
+            .times(1)
+            .with(predicate::eq(expected_executor_args))
+            .return_once(move |_| Ok(Execution { exit_code: Some(0i32), stdout: None, stderr: None }));
+        let mut driver_options = modules_driver_options(vec![i_file.clone()], TargetPlatform::Transputer);
+        driver_options.save_temps = false;
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        execution_ok(sut.compile(&i_file));
+        assert!(i_file.exists(), "preprocessed input was deleted by driver");
+    }
+
+    #[test]
+    fn output_file_names_transputer_object_when_stopping_after_assembly() {
+        let mut mock_executor = MockExecutor::new();
+        let expected_executor_args: Vec<String> = ["tmasm", "file.asm", "-o", "out/prog.o", "-l", "file.lst"]
+            .iter()
+            .map(|str| str.to_string())
+            .collect();
+        mock_executor
+            .expect_run()
+            .times(1)
+            .with(predicate::eq(expected_executor_args))
+            .return_once(move |_| Ok(Execution { exit_code: Some(0i32), stdout: None, stderr: None }));
+        let mut driver_options = modules_driver_options(vec![PathBuf::from("file.c")], TargetPlatform::Transputer);
+        driver_options.toolchain = tmasm_toolchain();
+        driver_options.stop_after_assembly = true;
+        driver_options.output_file = Some(PathBuf::from("out/prog.o"));
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        execution_ok(sut.assemble(&[PathBuf::from("file.c")]));
+    }
+
+    #[test]
+    fn output_file_names_object_only_when_stopping_after_assembly() {
+        let mut mock_executor = MockExecutor::new();
+        let expected_executor_args: Vec<String> = ["tmasm", "file.asm", "-o", "file.o", "-l", "file.lst"]
+            .iter()
+            .map(|str| str.to_string())
+            .collect();
+        mock_executor
+            .expect_run()
+            .times(1)
+            .with(predicate::eq(expected_executor_args))
+            .return_once(move |_| Ok(Execution { exit_code: Some(0i32), stdout: None, stderr: None }));
+        let mut driver_options = modules_driver_options(vec![PathBuf::from("file.c")], TargetPlatform::Transputer);
+        driver_options.toolchain = tmasm_toolchain();
+        driver_options.output_file = Some(PathBuf::from("out/prog.bin"));
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        execution_ok(sut.assemble(&[PathBuf::from("file.c")]));
+    }
+
+    #[test]
+    fn output_file_names_x86_64_program() {
+        let mut mock_executor = MockExecutor::new();
+        let expected_executor_args: Vec<String> = ["gcc", "build/file.o", "build/other.o", "-o", "prog"]
+            .iter()
+            .map(|str| str.to_string())
+            .collect();
+        mock_executor
+            .expect_run()
+            .times(1)
+            .with(predicate::eq(expected_executor_args))
+            .return_once(move |_| Ok(Execution { exit_code: Some(0i32), stdout: None, stderr: None }));
+        let input_files = vec![PathBuf::from("src/file.c"), PathBuf::from("src/other.c")];
+        let mut driver_options = modules_driver_options(input_files.clone(), TargetPlatform::X86_64);
+        driver_options.output_file = Some(PathBuf::from("prog"));
+        driver_options.build_directory = Some(PathBuf::from("build"));
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        execution_ok(sut.link(&input_files));
+    }
+
+    #[test]
+    fn output_file_names_assembly_when_stopping_after_compilation() {
+        let driver_options = DriverOptions {
+            stop_after_compilation: true,
+            output_file: Some(PathBuf::from("out.asm")),
+            ..modules_driver_options(vec![PathBuf::from("file.c")], TargetPlatform::Transputer)
+        };
+        let expected_args = vec!["rcc1", "file.i", "-o", "out.asm"];
+        check_compiler_flags(driver_options, &expected_args);
+    }
+
+    #[test]
+    fn files_made_along_the_way_are_written_in_build_directory() {
+        let mut mock_executor = MockExecutor::new();
+        let expected_executor_args: Vec<String> = transputer_preprocessor_args(&["src/file.c", "-o", "build/file.i"]);
+        mock_executor
+            .expect_run()
+            .times(1)
+            .with(predicate::eq(expected_executor_args))
+            .return_once(move |_| Ok(Execution { exit_code: Some(0i32), stdout: None, stderr: None }));
+        let mut driver_options = modules_driver_options(vec![PathBuf::from("src/file.c")], TargetPlatform::Transputer);
+        driver_options.build_directory = Some(PathBuf::from("build"));
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        execution_ok(sut.preprocess(Path::new("src/file.c")));
+    }
+
+    #[test]
+    fn epoc16_object_and_listing_in_build_directory() {
+        let mut mock_executor = MockExecutor::new();
+        let expected_executor_args: Vec<String> = ["nasm", "-f", "obj", "build/file.asm", "-o", "build/file.obj", "-l", "build/file.lst"]
+            .iter()
+            .map(|str| str.to_string())
+            .collect();
+        mock_executor
+            .expect_run()
+            .times(1)
+            .with(predicate::eq(expected_executor_args))
+            .return_once(move |_| Ok(Execution { exit_code: Some(0i32), stdout: None, stderr: None }));
+        let mut driver_options = modules_driver_options(vec![PathBuf::from("src/file.c")], TargetPlatform::EPOC16);
+        driver_options.assembler_syntax = AssemblerSyntax::NASM;
+        driver_options.build_directory = Some(PathBuf::from("build"));
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        execution_ok(sut.assemble(&[PathBuf::from("src/file.c")]));
+    }
+
+    #[test]
+    fn preprocessor_options_passed_to_preprocessor() {
+        let mut mock_executor = MockExecutor::new();
+        let expected_executor_args: Vec<String> = transputer_preprocessor_args(&["-Iinclude", "-DLEVEL=2", "-include", "config.h", "file.c", "-o", "file.i"]);
+        mock_executor
+            .expect_run()
+            .times(1)
+            .with(predicate::eq(expected_executor_args))
+            .return_once(move |_| Ok(Execution { exit_code: Some(0i32), stdout: None, stderr: None }));
+        let mut driver_options = modules_driver_options(vec![PathBuf::from("file.c")], TargetPlatform::Transputer);
+        driver_options.preprocessor_options = ["-Iinclude", "-DLEVEL=2", "-include", "config.h"].iter().map(|str| str.to_string()).collect();
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        execution_ok(sut.preprocess(Path::new("file.c")));
+    }
+
+    #[test]
+    fn preprocessor_defines_transputer_macros_without_host_headers() {
+        let args = transputer_preprocessor_args(&[]);
+        for expected in ["-undef", "-nostdinc", "-ffreestanding", "-D__TRANSPUTER__=1", "-D__T425__=1", "-D__INT_WIDTH__=32"] {
+            assert_that!(args.contains(&expected.to_string()), equal_to(true));
+        }
+    }
+
+    #[test]
+    fn x86_64_preprocessor_uses_host_headers_and_macros() {
+        let mut mock_executor = MockExecutor::new();
+        let expected_executor_args: Vec<String> = ["gcc", "-E", "-P", "-DLEVEL=2", "file.c", "-o", "file.i"]
+            .iter()
+            .map(|str| str.to_string())
+            .collect();
+        mock_executor
+            .expect_run()
+            .times(1)
+            .with(predicate::eq(expected_executor_args))
+            .return_once(move |_| Ok(Execution { exit_code: Some(0i32), stdout: None, stderr: None }));
+        let mut driver_options = modules_driver_options(vec![PathBuf::from("file.c")], TargetPlatform::X86_64);
+        driver_options.preprocessor_options = vec!["-DLEVEL=2".to_string()];
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        execution_ok(sut.preprocess(Path::new("file.c")));
+    }
+
+    #[test]
+    fn configured_assembler_and_flags_are_run() {
+        let mut mock_executor = MockExecutor::new();
+        let expected_executor_args: Vec<String> = ["/opt/parachute/bin/tmasm", "-v", "file.asm", "-o", "file.o", "-l", "file.lst"]
+            .iter()
+            .map(|str| str.to_string())
+            .collect();
+        mock_executor
+            .expect_run()
+            .times(1)
+            .with(predicate::eq(expected_executor_args))
+            .return_once(move |_| Ok(Execution { exit_code: Some(0i32), stdout: None, stderr: None }));
+        let mut driver_options = modules_driver_options(vec![PathBuf::from("file.c")], TargetPlatform::Transputer);
+        driver_options.toolchain = Toolchain::from_toml("[Transputer]\nassembler = \"/opt/parachute/bin/tmasm\"\nassembler_flags = [\"-v\"]\nlinker = \"ilink\"\n").unwrap();
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        execution_ok(sut.assemble(&[PathBuf::from("file.c")]));
+    }
+
+    #[test]
+    fn configured_compiler_is_run() {
+        let mut mock_executor = MockExecutor::new();
+        let expected_executor_args: Vec<String> = ["rcc1-dev", "--debug", "file.i", "-o", "file.o"]
+            .iter()
+            .map(|str| str.to_string())
+            .collect();
+        mock_executor
+            .expect_run()
+            .times(1)
+            .with(predicate::eq(expected_executor_args))
+            .return_once(move |_| Ok(Execution { exit_code: Some(0i32), stdout: None, stderr: None }));
+        let mut driver_options = modules_driver_options(vec![PathBuf::from("file.c")], TargetPlatform::Transputer);
+        driver_options.toolchain = Toolchain::from_toml("[Transputer]\ncompiler = \"rcc1-dev\"\ncompiler_flags = [\"--debug\"]\n").unwrap();
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        execution_ok(sut.compile(Path::new("file.c")));
+    }
+
+    #[test]
+    fn configured_x86_64_preprocessor_is_run() {
+        let mut mock_executor = MockExecutor::new();
+        let expected_executor_args: Vec<String> = ["clang", "-E", "-P", "file.c", "-o", "file.i"]
+            .iter()
+            .map(|str| str.to_string())
+            .collect();
+        mock_executor
+            .expect_run()
+            .times(1)
+            .with(predicate::eq(expected_executor_args))
+            .return_once(move |_| Ok(Execution { exit_code: Some(0i32), stdout: None, stderr: None }));
+        let mut driver_options = modules_driver_options(vec![PathBuf::from("file.c")], TargetPlatform::X86_64);
+        driver_options.toolchain = Toolchain::from_toml("[X86_64]\npreprocessor = \"clang\"\n").unwrap();
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        execution_ok(sut.preprocess(Path::new("file.c")));
+    }
+
+    /// 'main' calls 'f', in another module.
+    fn write_transputer_objects(temp: &Path) {
+        let main = Object {
+            code: vec![
+                Fragment::Label("main".to_owned()),
+                Fragment::Direct(CALL, Operand::Relative("f".to_owned())),
+                Fragment::Bytes(vec![0x22, 0xf0]),
+            ],
+            data: vec![],
+            globals: vec!["main".to_owned()],
+        };
+        let f = Object {
+            code: vec![Fragment::Label("f".to_owned()), Fragment::Bytes(vec![0x22, 0xf0])],
+            data: vec![],
+            globals: vec!["f".to_owned()],
+        };
+        std::fs::write(temp.join("file.o"), main.to_bytes()).unwrap();
+        std::fs::write(temp.join("other.o"), f.to_bytes()).unwrap();
+    }
+
+    #[test]
+    fn transputer_modules_are_linked_into_boot_image_without_linker() {
+        let (temp, _temp_dir) = temp_config_dir();
+        write_transputer_objects(&temp);
+        let input_files = vec![temp.join("file.c"), temp.join("other.o")];
+        let mut driver_options = modules_driver_options(input_files.clone(), TargetPlatform::Transputer);
+        driver_options.save_temps = false;
+
+        let sut = DefaultDriver::new(driver_options, Box::new(MockExecutor::new()));
+        execution_ok(sut.link(&input_files));
+        // call main; stopp; main: call f; ret; f: ret
+        assert_that!(std::fs::read(temp.join("file.bin")).unwrap(), equal_to(vec![0x92, 0x21, 0xf5, 0x92, 0x22, 0xf0, 0x22, 0xf0]));
+        let map = std::fs::read_to_string(temp.join("file.map")).unwrap();
+        assert_that!(map.contains("80000076 f (other.o)\n"), equal_to(true));
+        assert!(!temp.join("file.o").exists(), "temp object file was not deleted by driver");
+        assert!(temp.join("other.o").exists(), "object input was deleted by driver");
+    }
+
+    #[test]
+    fn configured_transputer_linker_is_run_instead_of_tlink() {
+        let (temp, _temp_dir) = temp_config_dir();
+        write_transputer_objects(&temp);
+        let path = |name: &str| temp.join(name).as_os_str().to_string_lossy().to_string();
+        let expected_executor_args = vec!["ilink".to_owned(), path("file.o"), path("other.o"), "-o".to_owned(), path("file.bin")];
+        let mut mock_executor = MockExecutor::new();
+        mock_executor
+            .expect_run()
+            .times(1)
+            .with(predicate::eq(expected_executor_args))
+            .return_once(move |_| Ok(Execution { exit_code: Some(0i32), stdout: None, stderr: None }));
+        let input_files = vec![temp.join("file.c"), temp.join("other.o")];
+        let mut driver_options = modules_driver_options(input_files.clone(), TargetPlatform::Transputer);
+        driver_options.toolchain = tmasm_toolchain();
+        driver_options.save_temps = false;
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        execution_ok(sut.link(&input_files));
+        assert!(!temp.join("file.bin").exists(), "boot image was written by tlink rather than the linker");
+        assert!(!temp.join("file.map").exists(), "map was written by tlink rather than the linker");
+        assert!(!temp.join("file.o").exists(), "temp object file was not deleted by driver");
+        assert!(temp.join("other.o").exists(), "object input was deleted by driver");
+    }
+
+    #[test]
+    fn transputer_link_errors_are_data_errors() {
+        let (temp, _temp_dir) = temp_config_dir();
+        write_transputer_objects(&temp);
+        let input_files = vec![temp.join("file.c")];
+        let driver_options = modules_driver_options(input_files.clone(), TargetPlatform::Transputer);
+
+        let sut = DefaultDriver::new(driver_options, Box::new(MockExecutor::new()));
+        let execution = sut.link(&input_files).unwrap();
+        assert_that!(execution.code(), equal_to(Some(65)));
+        assert!(!temp.join("file.bin").exists(), "boot image was written for an unlinkable program");
+    }
+
+    #[test]
+    fn other_objects_cannot_be_linked_into_boot_image() {
+        let (temp, _temp_dir) = temp_config_dir();
+        std::fs::write(temp.join("file.o"), [0x24, 0xf2]).unwrap();
+        let input_files = vec![temp.join("file.o")];
+        let driver_options = modules_driver_options(input_files.clone(), TargetPlatform::Transputer);
+
+        let sut = DefaultDriver::new(driver_options, Box::new(MockExecutor::new()));
+        let msg = sut.link(&input_files).err().unwrap().to_string();
+        assert_that!(msg, equal_to(format!("'{}' is not a Transputer object", temp.join("file.o").display())));
+    }
+
+    #[test]
+    fn object_file_retained_after_linking_with_save_temps() {
+        let (temp, _temp_dir) = temp_config_dir();
+        write_transputer_objects(&temp);
+        let input_files = vec![temp.join("file.c"), temp.join("other.c")];
+        let driver_options = modules_driver_options(input_files.clone(), TargetPlatform::Transputer);
+
+        let sut = DefaultDriver::new(driver_options, Box::new(MockExecutor::new()));
+        execution_ok(sut.link(&input_files));
+        assert!(temp.join("file.o").exists(), "temp object file was deleted by driver but save-temps given");
+    }
+
+    #[test]
+    fn missing_transputer_module_is_an_error() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let input_files = vec![temp.join("file.c")];
+        let driver_options = modules_driver_options(input_files.clone(), TargetPlatform::Transputer);
+
+        let sut = DefaultDriver::new(driver_options, Box::new(MockExecutor::new()));
+        let msg = sut.link(&input_files).err().unwrap().to_string();
+        assert_that!(msg, equal_to(format!("Could not read object {}", temp.join("file.o").display())));
+    }
+
+    #[test]
+    fn configured_transputer_linker_is_run() {
+        let mut mock_executor = MockExecutor::new();
+        let expected_executor_args: Vec<String> = ["tlink", "-m", "file.o", "other.o", "-o", "prog.bin"]
+            .iter()
+            .map(|str| str.to_string())
+            .collect();
+        mock_executor
+            .expect_run()
+            .times(1)
+            .with(predicate::eq(expected_executor_args))
+            .return_once(move |_| Ok(Execution { exit_code: Some(0i32), stdout: None, stderr: None }));
+        let input_files = vec![PathBuf::from("file.c"), PathBuf::from("other.asm")];
+        let mut driver_options = modules_driver_options(input_files.clone(), TargetPlatform::Transputer);
+        driver_options.output_file = Some(PathBuf::from("prog.bin"));
+        driver_options.toolchain = Toolchain::from_toml("[Transputer]\nlinker = \"tlink\"\nlinker_flags = [\"-m\"]\n").unwrap();
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        execution_ok(sut.link(&input_files));
+    }
+
+    #[test]
+    fn configured_epoc16_linker_makes_image() {
+        let mut mock_executor = MockExecutor::new();
+        let expected_executor_args: Vec<String> = ["tslink", "build/file.obj", "lib/sibo.lib", "-o", "build/file.img"]
+            .iter()
+            .map(|str| str.to_string())
+            .collect();
+        mock_executor
+            .expect_run()
+            .times(1)
+            .with(predicate::eq(expected_executor_args))
+            .return_once(move |_| Ok(Execution { exit_code: Some(0i32), stdout: None, stderr: None }));
+        let input_files = vec![PathBuf::from("src/file.c"), PathBuf::from("lib/sibo.lib")];
+        let mut driver_options = modules_driver_options(input_files.clone(), TargetPlatform::EPOC16);
+        driver_options.build_directory = Some(PathBuf::from("build"));
+        driver_options.toolchain = Toolchain::from_toml("[EPOC16]\nlinker = \"tslink\"\n").unwrap();
+
+        let sut = DefaultDriver::new(driver_options, Box::new(mock_executor));
+        execution_ok(sut.link(&input_files));
+    }
+
+    #[test]
+    fn epoc16_without_linker_is_an_error() {
+        let input_files = vec![PathBuf::from("file.c")];
+        let driver_options = modules_driver_options(input_files.clone(), TargetPlatform::EPOC16);
+
+        let sut = DefaultDriver::new(driver_options, Box::new(MockExecutor::new()));
+        let msg = sut.link(&input_files).err().unwrap().to_string();
+        assert_that!(msg, equal_to("No linker is configured for EPOC16; configure one, or stop before linking with --no-link"));
+    }
 }
@@ -1,10 +1,10 @@
-/// The Executor allows the running of external programs and handling their exit codes and outputs.
+//! The Executor allows the running of external programs and handling their exit codes and outputs.
 
 use std::process::Command;
 
 use anyhow::{bail, Result};
 
-use log::{debug, error};
+use log::debug;
 #[cfg(test)]
 use mockall::automock;
 
@@ -58,16 +58,10 @@ impl Executor for CommandExecutor {
             Ok(output) => {
                 let stdout = Some(String::from_utf8_lossy(&output.stdout).to_string());
                 let stderr = Some(String::from_utf8_lossy(&output.stderr).to_string());
-                if output.status.code().unwrap_or(1) == 0 {
-                    debug!("status: {}", output.status);
-                    debug!("stdout: {}", stdout.as_ref().unwrap());
-                    debug!("stderr: {}", stderr.as_ref().unwrap());
-                } else {
-                    error!("Execution failure of {:?}", args.clone().join(" "));
-                    error!("status: {}", output.status);
-                    error!("stdout: {}", stdout.as_ref().unwrap());
-                    error!("stderr: {}", stderr.as_ref().unwrap());
-                }
+                // A failure is reported by the caller, which knows which stage failed, with stderr.
+                debug!("status: {}", output.status);
+                debug!("stdout: {}", stdout.as_ref().unwrap());
+                debug!("stderr: {}", stderr.as_ref().unwrap());
                 Ok(Execution { exit_code: output.status.code(), stdout, stderr })
             }
             Err(err) => bail!("Could not run command '{}': {}", args_split.0, err),
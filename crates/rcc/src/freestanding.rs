@@ -0,0 +1,149 @@
+//! The Transputer and EPOC16 are freestanding targets: gcc preprocesses for them without the
+//! host's predefined macros and headers, and is given rcc's own in their place. The macros
+//! describe the target and its data model, and the headers are written in terms of them.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use common::data_model::DataModel;
+use common::processor::Processor;
+use common::target_platform::TargetPlatform;
+use tempfile::NamedTempFile;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The headers, built into rcc so that it needs no installation beside its executable.
+const HEADERS: [(&str, &str); 4] = [
+    ("limits.h", include_str!("../include/limits.h")),
+    ("stdarg.h", include_str!("../include/stdarg.h")),
+    ("stddef.h", include_str!("../include/stddef.h")),
+    ("stdint.h", include_str!("../include/stdint.h")),
+];
+
+/// The macros predefined for a freestanding target, as names (possibly with parameters) and
+/// values. The names of those describing the data model are gcc's.
+pub fn predefined_macros(target_platform: TargetPlatform, processor: Processor) -> Vec<(String, String)> {
+    let mut macros: Vec<(String, String)> = vec![("__RCC__".to_owned(), "1".to_owned())];
+    match target_platform {
+        TargetPlatform::Transputer => {
+            macros.push(("__TRANSPUTER__".to_owned(), "1".to_owned()));
+            macros.push((format!("__{}__", processor), "1".to_owned()));
+        }
+        TargetPlatform::EPOC16 => macros.push(("__EPOC16__".to_owned(), "1".to_owned())),
+        TargetPlatform::X86_64 => {}
+    }
+
+    let data_model = target_platform.data_model();
+    let bytes = |bits: u32| (bits / DataModel::CHAR_BITS).to_string();
+    // The type of a width of integer, which must be int or long beyond short.
+    let word_type = |bits: u32| {
+        if bits == data_model.int_bits {
+            "int"
+        } else {
+            "long"
+        }
+    };
+    let signed_max = |bits: u32| format!("{}{}", (1u64 << (bits - 1)) - 1, suffix(word_type(bits), false));
+    let unsigned_max = |bits: u32| format!("{}{}", (1u64 << bits) - 1, suffix(word_type(bits), true));
+    let mut define = |name: &str, value: String| macros.push((name.to_owned(), value));
+
+    define("__CHAR_BIT__", DataModel::CHAR_BITS.to_string());
+    if !data_model.char_is_signed {
+        define("__CHAR_UNSIGNED__", "1".to_owned());
+    }
+    define("__SIZEOF_SHORT__", bytes(data_model.short_bits));
+    define("__SIZEOF_INT__", bytes(data_model.int_bits));
+    define("__SIZEOF_LONG__", bytes(data_model.long_bits));
+    define("__SIZEOF_POINTER__", bytes(data_model.pointer_bits));
+    define("__SHRT_WIDTH__", data_model.short_bits.to_string());
+    define("__INT_WIDTH__", data_model.int_bits.to_string());
+    define("__LONG_WIDTH__", data_model.long_bits.to_string());
+    define("__INTPTR_WIDTH__", data_model.pointer_bits.to_string());
+    define("__SCHAR_MAX__", "127".to_owned());
+    define("__SHRT_MAX__", ((1u64 << (data_model.short_bits - 1)) - 1).to_string());
+    define("__INT_MAX__", signed_max(data_model.int_bits));
+    define("__LONG_MAX__", signed_max(data_model.long_bits));
+
+    // size_t and the pointer-sized integers are whichever of int and long is as wide as a pointer.
+    let pointer_type = word_type(data_model.pointer_bits);
+    define("__SIZE_TYPE__", format!("unsigned {}", pointer_type));
+    define("__SIZE_MAX__", unsigned_max(data_model.pointer_bits));
+    define("__PTRDIFF_TYPE__", pointer_type.to_owned());
+    define("__PTRDIFF_MAX__", signed_max(data_model.pointer_bits));
+    define("__INTPTR_TYPE__", pointer_type.to_owned());
+    define("__INTPTR_MAX__", signed_max(data_model.pointer_bits));
+    define("__UINTPTR_TYPE__", format!("unsigned {}", pointer_type));
+    define("__UINTPTR_MAX__", unsigned_max(data_model.pointer_bits));
+    define("__WCHAR_TYPE__", "int".to_owned());
+
+    let int32_type = word_type(32);
+    define("__INT8_TYPE__", "signed char".to_owned());
+    define("__INT16_TYPE__", "short".to_owned());
+    define("__INT32_TYPE__", int32_type.to_owned());
+    define("__UINT8_TYPE__", "unsigned char".to_owned());
+    define("__UINT16_TYPE__", "unsigned short".to_owned());
+    define("__UINT32_TYPE__", format!("unsigned {}", int32_type));
+    define("__INT32_MAX__", signed_max(32));
+    define("__UINT32_MAX__", unsigned_max(32));
+    define("__INT32_C(c)", constant(suffix(int32_type, false)));
+    define("__UINT32_C(c)", constant(suffix(int32_type, true)));
+    macros
+}
+
+/// The suffix of an integer constant of a type.
+fn suffix(word_type: &str, unsigned: bool) -> &'static str {
+    match (word_type, unsigned) {
+        ("int", false) => "",
+        ("int", true) => "U",
+        (_, false) => "L",
+        (_, true) => "UL",
+    }
+}
+
+/// The body of a macro making a constant of its parameter 'c', with a suffix.
+fn constant(suffix: &str) -> String {
+    if suffix.is_empty() {
+        "c".to_owned()
+    } else {
+        format!("c ## {}", suffix)
+    }
+}
+
+/// Writes the headers where gcc can read them, in a directory for this version of rcc in the
+/// user's cache directory, which is returned.
+/// TODO: CROSSPLATFORM the user's cache directory on Windows.
+pub fn header_directory() -> Result<PathBuf> {
+    let cache_directory = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(directory) if !directory.is_empty() => PathBuf::from(directory),
+        _ => match std::env::var_os("HOME") {
+            Some(home) => PathBuf::from(home).join(".cache"),
+            None => bail!("Could not find a directory for headers: neither XDG_CACHE_HOME nor HOME is set"),
+        },
+    };
+    let directory = cache_directory.join("rcc").join(VERSION).join("include");
+    write_headers(&directory)?;
+    Ok(directory)
+}
+
+/// Writes the headers into a directory. Headers that are already there are left, and the others
+/// are written whole, then renamed into place, as another rcc may be reading them.
+pub fn write_headers(directory: &Path) -> Result<()> {
+    std::fs::create_dir_all(directory)
+        .with_context(|| format!("Could not create header directory {}", directory.display()))?;
+    for (name, contents) in HEADERS {
+        let header = directory.join(name);
+        if std::fs::read_to_string(&header).ok().as_deref() != Some(contents) {
+            let mut temporary = NamedTempFile::new_in(directory)
+                .with_context(|| format!("Could not create a temporary file in {}", directory.display()))?;
+            temporary.write_all(contents.as_bytes())
+                .and_then(|_| temporary.persist(&header).map(|_| ()).map_err(|err| err.error))
+                .with_context(|| format!("Could not write header {}", header.display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "./freestanding_spec.rs"]
+mod freestanding_spec;
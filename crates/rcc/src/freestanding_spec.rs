@@ -0,0 +1,89 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod freestanding_spec {
+
+    use common::processor::Processor;
+    use common::target_platform::TargetPlatform;
+    use common_test::file_utils_test_helper::temp_config_dir;
+    use hamcrest2::prelude::*;
+
+    use crate::freestanding::{header_directory, predefined_macros, write_headers};
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn value_of(macros: &[(String, String)], name: &str) -> Option<String> {
+        macros.iter().find(|(macro_name, _)| macro_name == name).map(|(_, value)| value.clone())
+    }
+
+    #[test]
+    fn transputer_macros() {
+        let macros = predefined_macros(TargetPlatform::Transputer, Processor::T425);
+        assert_that!(value_of(&macros, "__RCC__"), equal_to(Some("1".to_owned())));
+        assert_that!(value_of(&macros, "__TRANSPUTER__"), equal_to(Some("1".to_owned())));
+        assert_that!(value_of(&macros, "__T425__"), equal_to(Some("1".to_owned())));
+        assert_that!(value_of(&macros, "__EPOC16__"), none());
+        assert_that!(value_of(&macros, "__CHAR_UNSIGNED__"), equal_to(Some("1".to_owned())));
+        assert_that!(value_of(&macros, "__INT_WIDTH__"), equal_to(Some("32".to_owned())));
+        assert_that!(value_of(&macros, "__LONG_WIDTH__"), equal_to(Some("32".to_owned())));
+        assert_that!(value_of(&macros, "__SIZEOF_POINTER__"), equal_to(Some("4".to_owned())));
+        assert_that!(value_of(&macros, "__INT_MAX__"), equal_to(Some("2147483647".to_owned())));
+        assert_that!(value_of(&macros, "__SIZE_TYPE__"), equal_to(Some("unsigned int".to_owned())));
+        assert_that!(value_of(&macros, "__SIZE_MAX__"), equal_to(Some("4294967295U".to_owned())));
+        assert_that!(value_of(&macros, "__INT32_TYPE__"), equal_to(Some("int".to_owned())));
+        assert_that!(value_of(&macros, "__INT32_C(c)"), equal_to(Some("c".to_owned())));
+    }
+
+    #[test]
+    fn transputer_processor_macro() {
+        let macros = predefined_macros(TargetPlatform::Transputer, Processor::T805);
+        assert_that!(value_of(&macros, "__T805__"), equal_to(Some("1".to_owned())));
+        assert_that!(value_of(&macros, "__T425__"), none());
+    }
+
+    #[test]
+    fn epoc16_macros() {
+        let macros = predefined_macros(TargetPlatform::EPOC16, Processor::T425);
+        assert_that!(value_of(&macros, "__EPOC16__"), equal_to(Some("1".to_owned())));
+        assert_that!(value_of(&macros, "__TRANSPUTER__"), none());
+        assert_that!(value_of(&macros, "__T425__"), none());
+        assert_that!(value_of(&macros, "__CHAR_UNSIGNED__"), none());
+        assert_that!(value_of(&macros, "__INT_WIDTH__"), equal_to(Some("16".to_owned())));
+        assert_that!(value_of(&macros, "__LONG_WIDTH__"), equal_to(Some("32".to_owned())));
+        assert_that!(value_of(&macros, "__SIZEOF_POINTER__"), equal_to(Some("2".to_owned())));
+        assert_that!(value_of(&macros, "__INT_MAX__"), equal_to(Some("32767".to_owned())));
+        assert_that!(value_of(&macros, "__LONG_MAX__"), equal_to(Some("2147483647L".to_owned())));
+        assert_that!(value_of(&macros, "__SIZE_MAX__"), equal_to(Some("65535U".to_owned())));
+        assert_that!(value_of(&macros, "__INT32_TYPE__"), equal_to(Some("long".to_owned())));
+        assert_that!(value_of(&macros, "__UINT32_MAX__"), equal_to(Some("4294967295UL".to_owned())));
+        assert_that!(value_of(&macros, "__INT32_C(c)"), equal_to(Some("c ## L".to_owned())));
+    }
+
+    #[test]
+    fn headers_are_written() {
+        let directory = header_directory().unwrap();
+        for header in ["limits.h", "stdarg.h", "stddef.h", "stdint.h"] {
+            let contents = std::fs::read_to_string(directory.join(header)).unwrap();
+            assert_that!(contents.contains("rcc's freestanding targets"), equal_to(true));
+        }
+    }
+
+    #[test]
+    fn stale_headers_are_replaced_whole() {
+        let (temp, _temp_dir) = temp_config_dir();
+        std::fs::write(temp.join("limits.h"), "#define CHAR_BIT 9\n").unwrap();
+        write_headers(&temp).unwrap();
+        let contents = std::fs::read_to_string(temp.join("limits.h")).unwrap();
+        assert_that!(contents.contains("rcc's freestanding targets"), equal_to(true));
+        assert_that!(contents.contains("CHAR_BIT 9"), equal_to(false));
+        // Only the headers are left: each was written to a temporary file and renamed.
+        let mut names: Vec<String> = std::fs::read_dir(&temp).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_that!(names, equal_to(vec!["limits.h".to_owned(), "stdarg.h".to_owned(), "stddef.h".to_owned(), "stdint.h".to_owned()]));
+    }
+}
@@ -0,0 +1,83 @@
+//! The InProcessDriver runs the compiler within rcc, by calling the rcc1 library, rather than
+//! running the rcc1 program found on the PATH. The preprocessor, assembler and linker are still
+//! run as the DefaultDriver runs them.
+
+use std::path::{Path, PathBuf};
+
+use rcc1::compiler::{Compiler, CompilerOptions};
+use sysexits::ExitCode;
+
+use crate::driver::{remove_temporary, DefaultDriver, Driver, DriverOptions};
+use crate::executor::{Execution, Executor};
+use crate::suffix_translator::InputKind;
+
+use anyhow::bail;
+use log::error;
+
+pub struct InProcessDriver {
+    driver_options: DriverOptions,
+    external: DefaultDriver,
+}
+
+impl InProcessDriver {
+    pub fn new(driver_options: DriverOptions, executor: Box<dyn Executor>) -> Self {
+        Self {
+            external: DefaultDriver::new(driver_options.clone(), executor),
+            driver_options,
+        }
+    }
+}
+
+impl Driver for InProcessDriver {
+    fn preprocess(&self, source: &Path) -> Result<Execution, anyhow::Error> {
+        self.external.preprocess(source)
+    }
+
+    fn compile(&self, source: &Path) -> Result<Execution, anyhow::Error> {
+        let preprocessor = self.external.translator(source).preprocessor();
+        if !preprocessor.exists() {
+            bail!("Preprocessed file {} does not exist", preprocessor.display());
+        }
+        let writes_object = self.external.compiler_writes_object(source);
+        let compiler_options = CompilerOptions {
+            c_file: Box::new(preprocessor.clone()),
+            asm_file: (!writes_object).then(|| Box::new(self.external.assembly_file(source))),
+            object_file: writes_object.then(|| Box::new(self.external.compiler_output_file(source))),
+            lex: self.driver_options.lex,
+            parse: self.driver_options.parse,
+            codegen: self.driver_options.codegen,
+            dump_ir: false,
+            target_platform: self.driver_options.target_platform,
+            assembler_syntax: self.driver_options.assembler_syntax,
+            processor: self.driver_options.processor,
+            optimisation_level: self.driver_options.optimisation_level,
+        };
+
+        // The compiler logs its errors through rcc's logger, so there is no output to capture. Its
+        // failures are reported with the exit codes the rcc1 program would give.
+        let exit_code = match Compiler::new().compile(compiler_options) {
+            Ok(exit_code) => exit_code,
+            Err(err) => {
+                error!("Compilation failed: {}", err);
+                ExitCode::Software
+            }
+        };
+        // tidy up after the preprocessor unless requested, or the preprocessed file was an input
+        if InputKind::of(source) == Some(InputKind::C) {
+            remove_temporary(self.driver_options.save_temps, "preprocessor", &preprocessor);
+        }
+        Ok(Execution { exit_code: Some(i32::from(exit_code)), stdout: None, stderr: None })
+    }
+
+    fn assemble(&self, sources: &[PathBuf]) -> Result<Execution, anyhow::Error> {
+        self.external.assemble(sources)
+    }
+
+    fn link(&self, sources: &[PathBuf]) -> Result<Execution, anyhow::Error> {
+        self.external.link(sources)
+    }
+}
+
+#[cfg(test)]
+#[path = "./in_process_driver_spec.rs"]
+mod in_process_driver_spec;
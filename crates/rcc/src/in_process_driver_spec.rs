@@ -0,0 +1,167 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod in_process_driver_spec {
+
+    use common::assembler_syntax::AssemblerSyntax;
+    use common::processor::Processor;
+    use common::target_platform::TargetPlatform;
+    use common_test::file_utils_test_helper::temp_config_dir;
+    use hamcrest2::prelude::*;
+    use mockall::*;
+    use std::path::Path;
+    use temp_testdir::TempDir;
+
+    use crate::driver::{Driver, DriverOptions};
+    use crate::executor::{Execution, MockExecutor};
+    use crate::in_process_driver::InProcessDriver;
+    use crate::toolchain::Toolchain;
+    use tlink::object::Object;
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn driver_options(temp: &Path) -> DriverOptions {
+        DriverOptions {
+            input_files: vec![temp.join("file.c")],
+            lex: false,
+            parse: false,
+            codegen: false,
+            save_temps: false,
+            stop_after_compilation: false,
+            stop_after_assembly: false,
+            target_platform: TargetPlatform::Transputer,
+            assembler_syntax: AssemblerSyntax::MASM,
+            processor: Processor::T425,
+            optimisation_level: 0,
+            external_compiler: false,
+            output_file: None,
+            build_directory: None,
+            preprocessor_options: vec![],
+            toolchain: Toolchain::default(),
+        }
+    }
+
+    fn preprocessed(program: &str) -> (Box<Path>, TempDir) {
+        let (temp, temp_dir) = temp_config_dir();
+        std::fs::write(temp.join("file.i"), program).unwrap();
+        (temp, temp_dir)
+    }
+
+    #[test]
+    fn compiles_preprocessed_file_to_assembly() {
+        let (temp, _temp_dir) = preprocessed("int main(void) { return 2; }");
+
+        let mut driver_options = driver_options(&temp);
+        driver_options.toolchain = Toolchain::from_toml("[Transputer]\nlinker = \"ilink\"\n").unwrap();
+
+        let sut = InProcessDriver::new(driver_options, Box::new(MockExecutor::new()));
+        let execution = sut.compile(&temp.join("file.c")).unwrap();
+
+        assert_that!(execution.code(), equal_to(Some(0)));
+        let assembly = std::fs::read_to_string(temp.join("file.asm")).unwrap();
+        assert_that!(assembly.contains("main"), equal_to(true));
+        assert!(!temp.join("file.i").exists(), "temp preprocessor file was not deleted by driver");
+    }
+
+    #[test]
+    fn compiles_preprocessed_file_to_object_without_linker() {
+        let (temp, _temp_dir) = preprocessed("int main(void) { return 2; }");
+
+        let sut = InProcessDriver::new(driver_options(&temp), Box::new(MockExecutor::new()));
+        let execution = sut.compile(&temp.join("file.c")).unwrap();
+
+        assert_that!(execution.code(), equal_to(Some(0)));
+        let object = Object::from_bytes(&std::fs::read(temp.join("file.o")).unwrap()).unwrap();
+        assert_that!(object.globals, equal_to(vec!["main".to_owned()]));
+        assert!(!temp.join("file.asm").exists(), "assembly was written as well as the object");
+    }
+
+    #[test]
+    fn preprocessor_file_retained_after_compilation_with_save_temps() {
+        let (temp, _temp_dir) = preprocessed("int main(void) { return 2; }");
+        let mut driver_options = driver_options(&temp);
+        driver_options.save_temps = true;
+
+        let sut = InProcessDriver::new(driver_options, Box::new(MockExecutor::new()));
+        let execution = sut.compile(&temp.join("file.c")).unwrap();
+
+        assert_that!(execution.code(), equal_to(Some(0)));
+        assert!(temp.join("file.i").exists(), "temp preprocessor file was deleted by driver");
+    }
+
+    #[test]
+    fn program_with_errors_exits_with_data_error() {
+        let (temp, _temp_dir) = preprocessed("int main(void) { return 2 }");
+
+        let sut = InProcessDriver::new(driver_options(&temp), Box::new(MockExecutor::new()));
+        let execution = sut.compile(&temp.join("file.c")).unwrap();
+
+        assert_that!(execution.code(), equal_to(Some(65)));
+        assert!(!temp.join("file.o").exists(), "object was written for an erroneous program");
+    }
+
+    #[test]
+    fn stopping_early_writes_no_assembly() {
+        let (temp, _temp_dir) = preprocessed("int main(void) { return 2; }");
+        let mut driver_options = driver_options(&temp);
+        driver_options.parse = true;
+
+        let sut = InProcessDriver::new(driver_options, Box::new(MockExecutor::new()));
+        let execution = sut.compile(&temp.join("file.c")).unwrap();
+
+        assert_that!(execution.code(), equal_to(Some(0)));
+        assert!(!temp.join("file.o").exists(), "object was written when stopped after parsing");
+    }
+
+    #[test]
+    fn missing_preprocessed_file_is_an_error() {
+        let (temp, _temp_dir) = temp_config_dir();
+
+        let sut = InProcessDriver::new(driver_options(&temp), Box::new(MockExecutor::new()));
+        let result = sut.compile(&temp.join("file.c"));
+
+        let msg = result.err().unwrap().to_string();
+        assert_that!(msg, equal_to(format!("Preprocessed file {} does not exist", temp.join("file.i").display())));
+    }
+
+    #[test]
+    fn preprocessor_is_run_externally() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let c_file = temp.join("file.c");
+        let i_file = temp.join("file.i");
+        let expected_executor_args: Vec<String> = ["gcc", "-E", "-P", c_file.to_str().unwrap(), "-o", i_file.to_str().unwrap()]
+            .iter()
+            .map(|str| str.to_string())
+            .collect();
+        let mut mock_executor = MockExecutor::new();
+        mock_executor
+            .expect_run()
+            .times(1)
+            .with(predicate::eq(expected_executor_args))
+            .return_once(move |_| Ok(Execution { exit_code: Some(0), stdout: None, stderr: None }));
+
+        let mut driver_options = driver_options(&temp);
+        driver_options.target_platform = TargetPlatform::X86_64;
+
+        let sut = InProcessDriver::new(driver_options, Box::new(mock_executor));
+        let execution = sut.preprocess(&c_file).unwrap();
+
+        assert_that!(execution.code(), equal_to(Some(0)));
+    }
+
+    #[test]
+    fn preprocessed_input_is_compiled_and_retained() {
+        let (temp, _temp_dir) = preprocessed("int main(void) { return 2; }");
+        let i_file = temp.join("file.i");
+
+        let sut = InProcessDriver::new(driver_options(&temp), Box::new(MockExecutor::new()));
+        let execution = sut.compile(&i_file).unwrap();
+
+        assert_that!(execution.code(), equal_to(Some(0)));
+        assert!(temp.join("file.o").exists(), "object was not written");
+        assert!(i_file.exists(), "preprocessed input was deleted by driver");
+    }
+}
@@ -2,4 +2,8 @@ pub mod command_line;
 pub mod driver;
 pub mod driver_controller;
 pub mod executor;
-pub mod suffix_translator;
\ No newline at end of file
+pub mod freestanding;
+pub mod in_process_driver;
+pub mod suffix_translator;
+pub mod toolchain;
+
@@ -5,9 +5,11 @@ use std::env;
 use log::{debug, error, info};
 use rcc::{
     command_line::parse_and_validate,
-    driver::DefaultDriver,
+    driver::{DefaultDriver, Driver, DriverOptions},
     driver_controller::{DefaultDriverController, DriverController},
     executor::CommandExecutor,
+    in_process_driver::InProcessDriver,
+    toolchain::Toolchain,
 };
 use sysexits::ExitCode;
 
@@ -29,10 +31,24 @@ fn main() -> ExitCode {
         }
     };
 
+    // The tools run are configured per user, per project, and by the environment.
+    let driver_options = match Toolchain::load() {
+        Ok(toolchain) => DriverOptions { toolchain, ..driver_options },
+        Err(err) => {
+            error!("{:#}", err);
+            return ExitCode::Config;
+        }
+    };
+
     let command_executor = CommandExecutor::default();
-    let driver = DefaultDriver::new(driver_options.clone(), Box::new(command_executor));
+    // The compiler runs within rcc, unless the rcc1 program itself is to be run.
+    let driver: Box<dyn Driver> = if driver_options.external_compiler {
+        Box::new(DefaultDriver::new(driver_options.clone(), Box::new(command_executor)))
+    } else {
+        Box::new(InProcessDriver::new(driver_options.clone(), Box::new(command_executor)))
+    };
     let driver_controller = DefaultDriverController::default();
-    match driver_controller.drive(driver_options, Box::new(driver)) {
+    match driver_controller.drive(driver_options, driver) {
         Ok(code) => {
             debug!("Exiting with code {code}");
             code
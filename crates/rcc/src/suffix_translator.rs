@@ -1,36 +1,106 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use common::target_platform::TargetPlatform;
+
+/// What an input file holds, known from its suffix, and so the stages it is run through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputKind {
+    /// C, which is preprocessed, compiled, assembled and linked.
+    C,
+    /// Preprocessed C, which is compiled, assembled and linked.
+    Preprocessed,
+    /// Assembly, which is assembled and linked. Either assembler suffix is accepted for any target.
+    Assembly,
+    /// An object, or a library of them, e.g. the runtime's, which is only linked.
+    Object,
+}
+
+impl InputKind {
+    pub fn of(file: &Path) -> Option<InputKind> {
+        let extension = file.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "c" => Some(InputKind::C),
+            "i" => Some(InputKind::Preprocessed),
+            "asm" | "s" => Some(InputKind::Assembly),
+            "o" | "obj" | "a" | "lib" => Some(InputKind::Object),
+            _ => None,
+        }
+    }
+}
+
+/// Gives the names of the files produced from an input file by each stage of compilation, which
+/// depend on the target's tools. They are written beside the input, or in a build directory if
+/// one is given. A preprocessed, assembly or object input is itself the file its stage would
+/// have produced.
 pub struct SuffixTranslator {
-    c_file: PathBuf,
+    file: PathBuf,
+    target_platform: TargetPlatform,
+    build_directory: Option<PathBuf>,
 }
 
 impl SuffixTranslator {
-    pub fn new(c_file: PathBuf) -> Self {
-        Self { c_file }
+    pub fn new(file: PathBuf, target_platform: TargetPlatform) -> Self {
+        Self { file, target_platform, build_directory: None }
     }
 
-    pub fn preprocessor(&self) -> PathBuf {
-        let mut out = self.c_file.clone();
-        out.set_extension("i");
+    pub fn in_directory(mut self, build_directory: Option<&Path>) -> Self {
+        self.build_directory = build_directory.map(|directory| directory.to_path_buf());
+        self
+    }
+
+    fn derived(&self, extension: &str) -> PathBuf {
+        let mut out = match (&self.build_directory, self.file.file_name()) {
+            (Some(directory), Some(file_name)) => directory.join(file_name),
+            _ => self.file.clone(),
+        };
+        out.set_extension(extension);
         out
     }
 
+    pub fn preprocessor(&self) -> PathBuf {
+        if InputKind::of(&self.file) == Some(InputKind::Preprocessed) {
+            return self.file.clone();
+        }
+        self.derived("i")
+    }
+
+    /// The GNU assembler used for x86_64 expects '.s'; the Transputer and EPOC16 assemblers
+    /// '.asm'.
     pub fn assembler(&self) -> PathBuf {
-        let mut out = self.c_file.clone();
-        out.set_extension("asm");
-        out
+        if InputKind::of(&self.file) == Some(InputKind::Assembly) {
+            return self.file.clone();
+        }
+        match self.target_platform {
+            TargetPlatform::X86_64 => self.derived("s"),
+            TargetPlatform::Transputer | TargetPlatform::EPOC16 => self.derived("asm"),
+        }
     }
 
+    /// The linker's output. x86_64 programs are native executables, named without a suffix, as
+    /// gcc would; Transputer programs are boot images for Parachute, and EPOC16 programs images
+    /// for the Psion.
     pub fn binary(&self) -> PathBuf {
-        let mut out = self.c_file.clone();
-        out.set_extension("bin");
-        out
+        match self.target_platform {
+            TargetPlatform::X86_64 => self.derived(""),
+            TargetPlatform::Transputer => self.derived("bin"),
+            TargetPlatform::EPOC16 => self.derived("img"),
+        }
+    }
+
+    /// The assembler's output, which is linked into the binary. The EPOC16 assemblers write OMF
+    /// objects, suffixed as the SIBO SDK's are.
+    pub fn object(&self) -> PathBuf {
+        if InputKind::of(&self.file) == Some(InputKind::Object) {
+            return self.file.clone();
+        }
+        match self.target_platform {
+            TargetPlatform::X86_64 | TargetPlatform::Transputer => self.derived("o"),
+            TargetPlatform::EPOC16 => self.derived("obj"),
+        }
     }
 
     pub fn listing(&self) -> PathBuf {
-        let mut out = self.c_file.clone();
-        out.set_extension("lst");
-        out
+        self.derived("lst")
     }
 }
 
@@ -3,10 +3,11 @@ extern crate hamcrest2;
 #[cfg(test)]
 mod suffix_translator_spec {
 
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
 
     use hamcrest2::prelude::*;
-    use crate::suffix_translator::SuffixTranslator;
+    use common::target_platform::TargetPlatform;
+    use crate::suffix_translator::{InputKind, SuffixTranslator};
 
     #[ctor::ctor]
     fn before_each() {
@@ -16,28 +17,129 @@ mod suffix_translator_spec {
     #[test]
     fn preprocessor() {
         let c_file = PathBuf::from("file.c");
-        let xlat = SuffixTranslator::new(c_file);
+        let xlat = SuffixTranslator::new(c_file, TargetPlatform::Transputer);
         assert_that!(xlat.preprocessor(), equal_to(PathBuf::from("file.i")));
     }
 
     #[test]
     fn assembler() {
         let c_file = PathBuf::from("file.c");
-        let xlat = SuffixTranslator::new(c_file);
+        let xlat = SuffixTranslator::new(c_file, TargetPlatform::Transputer);
         assert_that!(xlat.assembler(), equal_to(PathBuf::from("file.asm")));
     }
 
     #[test]
     fn binary() {
         let c_file = PathBuf::from("file.c");
-        let xlat = SuffixTranslator::new(c_file);
+        let xlat = SuffixTranslator::new(c_file, TargetPlatform::Transputer);
         assert_that!(xlat.binary(), equal_to(PathBuf::from("file.bin")));
     }
 
+    #[test]
+    fn object() {
+        let c_file = PathBuf::from("file.c");
+        let xlat = SuffixTranslator::new(c_file, TargetPlatform::EPOC16);
+        assert_that!(xlat.object(), equal_to(PathBuf::from("file.obj")));
+    }
+
+    #[test]
+    fn transputer_object() {
+        let xlat = SuffixTranslator::new(PathBuf::from("file.c"), TargetPlatform::Transputer);
+        assert_that!(xlat.object(), equal_to(PathBuf::from("file.o")));
+    }
+
+    #[test]
+    fn x86_64_object() {
+        let xlat = SuffixTranslator::new(PathBuf::from("file.c"), TargetPlatform::X86_64);
+        assert_that!(xlat.object(), equal_to(PathBuf::from("file.o")));
+    }
+
+    #[test]
+    fn epoc16_binary() {
+        let xlat = SuffixTranslator::new(PathBuf::from("file.c"), TargetPlatform::EPOC16);
+        assert_that!(xlat.binary(), equal_to(PathBuf::from("file.img")));
+    }
+
+    #[test]
+    fn object_input_is_its_own_object_file() {
+        let xlat = SuffixTranslator::new(PathBuf::from("lib/crt0.obj"), TargetPlatform::EPOC16).in_directory(Some(Path::new("build")));
+        assert_that!(xlat.object(), equal_to(PathBuf::from("lib/crt0.obj")));
+        assert_that!(xlat.binary(), equal_to(PathBuf::from("build/crt0.img")));
+    }
+
     #[test]
     fn listing() {
         let c_file = PathBuf::from("file.c");
-        let xlat = SuffixTranslator::new(c_file);
+        let xlat = SuffixTranslator::new(c_file, TargetPlatform::Transputer);
         assert_that!(xlat.listing(), equal_to(PathBuf::from("file.lst")));
     }
+
+    #[test]
+    fn x86_64_assembler() {
+        let xlat = SuffixTranslator::new(PathBuf::from("file.c"), TargetPlatform::X86_64);
+        assert_that!(xlat.assembler(), equal_to(PathBuf::from("file.s")));
+    }
+
+    #[test]
+    fn x86_64_binary() {
+        let xlat = SuffixTranslator::new(PathBuf::from("dir/file.c"), TargetPlatform::X86_64);
+        assert_that!(xlat.binary(), equal_to(PathBuf::from("dir/file")));
+    }
+
+    #[test]
+    fn epoc16_assembler() {
+        let xlat = SuffixTranslator::new(PathBuf::from("file.c"), TargetPlatform::EPOC16);
+        assert_that!(xlat.assembler(), equal_to(PathBuf::from("file.asm")));
+    }
+
+    #[test]
+    fn preprocessed_input_is_its_own_preprocessor_file() {
+        let i_file = PathBuf::from("file.i");
+        let xlat = SuffixTranslator::new(i_file, TargetPlatform::Transputer);
+        assert_that!(xlat.preprocessor(), equal_to(PathBuf::from("file.i")));
+        assert_that!(xlat.assembler(), equal_to(PathBuf::from("file.asm")));
+    }
+
+    #[test]
+    fn assembly_input_is_its_own_assembler_file() {
+        let s_file = PathBuf::from("file.s");
+        let xlat = SuffixTranslator::new(s_file, TargetPlatform::Transputer);
+        assert_that!(xlat.assembler(), equal_to(PathBuf::from("file.s")));
+        assert_that!(xlat.binary(), equal_to(PathBuf::from("file.bin")));
+    }
+
+    #[test]
+    fn input_kinds() {
+        assert_that!(InputKind::of(Path::new("file.c")), equal_to(Some(InputKind::C)));
+        assert_that!(InputKind::of(Path::new("FILE.C")), equal_to(Some(InputKind::C)));
+        assert_that!(InputKind::of(Path::new("file.i")), equal_to(Some(InputKind::Preprocessed)));
+        assert_that!(InputKind::of(Path::new("file.asm")), equal_to(Some(InputKind::Assembly)));
+        assert_that!(InputKind::of(Path::new("file.s")), equal_to(Some(InputKind::Assembly)));
+        assert_that!(InputKind::of(Path::new("file.o")), equal_to(Some(InputKind::Object)));
+        assert_that!(InputKind::of(Path::new("FILE.OBJ")), equal_to(Some(InputKind::Object)));
+        assert_that!(InputKind::of(Path::new("libc.a")), equal_to(Some(InputKind::Object)));
+        assert_that!(InputKind::of(Path::new("sibo.lib")), equal_to(Some(InputKind::Object)));
+        assert_that!(InputKind::of(Path::new("file.stl")), equal_to(None));
+        assert_that!(InputKind::of(Path::new("file")), equal_to(None));
+    }
+
+    #[test]
+    fn derived_files_in_build_directory() {
+        let xlat = SuffixTranslator::new(PathBuf::from("src/file.c"), TargetPlatform::EPOC16).in_directory(Some(Path::new("build")));
+        assert_that!(xlat.preprocessor(), equal_to(PathBuf::from("build/file.i")));
+        assert_that!(xlat.assembler(), equal_to(PathBuf::from("build/file.asm")));
+        assert_that!(xlat.binary(), equal_to(PathBuf::from("build/file.img")));
+        assert_that!(xlat.object(), equal_to(PathBuf::from("build/file.obj")));
+        assert_that!(xlat.listing(), equal_to(PathBuf::from("build/file.lst")));
+    }
+
+    #[test]
+    fn inputs_stay_where_they_are_with_build_directory() {
+        let i_xlat = SuffixTranslator::new(PathBuf::from("src/file.i"), TargetPlatform::Transputer).in_directory(Some(Path::new("build")));
+        assert_that!(i_xlat.preprocessor(), equal_to(PathBuf::from("src/file.i")));
+        assert_that!(i_xlat.assembler(), equal_to(PathBuf::from("build/file.asm")));
+        let asm_xlat = SuffixTranslator::new(PathBuf::from("src/file.asm"), TargetPlatform::Transputer).in_directory(Some(Path::new("build")));
+        assert_that!(asm_xlat.assembler(), equal_to(PathBuf::from("src/file.asm")));
+        assert_that!(asm_xlat.listing(), equal_to(PathBuf::from("build/file.lst")));
+    }
 }
@@ -0,0 +1,217 @@
+//! The Toolchain gives the commands run for each target's preprocessor, compiler, assembler and
+//! linker, and the flags each is given before the arguments rcc passes. rcc has a default for
+//! each, which can be replaced by a TOML configuration file per user and per project, and then
+//! by environment variables. The file has a table for each target, named as the --architecture
+//! option names it:
+//!
+//! ```toml
+//! [Transputer]
+//! assembler = "/opt/parachute/bin/tmasm"
+//! assembler_flags = ["-v"]
+//! ```
+//!
+//! The environment variables are named for the target and tool, e.g. RCC_TRANSPUTER_ASSEMBLER
+//! and RCC_TRANSPUTER_ASSEMBLER_FLAGS, whose flags are separated by whitespace.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use common::assembler_syntax::AssemblerSyntax;
+use common::target_platform::TargetPlatform;
+use log::debug;
+use serde::Deserialize;
+
+/// The name of the project configuration file, looked for in the current directory and then in
+/// each of its ancestors.
+pub const PROJECT_FILE: &str = "rcc-toolchain.toml";
+
+/// A command run for a stage, and the flags given to it before rcc's own arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tool {
+    pub command: String,
+    pub flags: Vec<String>,
+}
+
+impl Tool {
+    fn new(command: &str) -> Self {
+        Self { command: command.to_owned(), flags: vec![] }
+    }
+
+    /// The command and its flags, to which rcc's arguments are added.
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec![self.command.clone()];
+        args.extend(self.flags.iter().cloned());
+        args
+    }
+}
+
+/// The configured tools of one target. Anything not configured is rcc's default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TargetToolchain {
+    pub preprocessor: Option<String>,
+    pub preprocessor_flags: Option<Vec<String>>,
+    pub compiler: Option<String>,
+    pub compiler_flags: Option<Vec<String>>,
+    pub assembler: Option<String>,
+    pub assembler_flags: Option<Vec<String>>,
+    pub linker: Option<String>,
+    pub linker_flags: Option<Vec<String>>,
+}
+
+impl TargetToolchain {
+    /// Replaces what this configures with what the later configuration does.
+    fn merge(&mut self, later: TargetToolchain) {
+        fn replace<T>(setting: &mut Option<T>, later: Option<T>) {
+            if later.is_some() {
+                *setting = later;
+            }
+        }
+        replace(&mut self.preprocessor, later.preprocessor);
+        replace(&mut self.preprocessor_flags, later.preprocessor_flags);
+        replace(&mut self.compiler, later.compiler);
+        replace(&mut self.compiler_flags, later.compiler_flags);
+        replace(&mut self.assembler, later.assembler);
+        replace(&mut self.assembler_flags, later.assembler_flags);
+        replace(&mut self.linker, later.linker);
+        replace(&mut self.linker_flags, later.linker_flags);
+    }
+
+    fn tool(command: &Option<String>, flags: &Option<Vec<String>>, default: Option<&str>) -> Option<Tool> {
+        let command = command.as_deref().or(default)?;
+        Some(Tool { flags: flags.clone().unwrap_or_default(), ..Tool::new(command) })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Toolchain {
+    #[serde(rename = "Transputer")]
+    transputer: TargetToolchain,
+    #[serde(rename = "EPOC16")]
+    epoc16: TargetToolchain,
+    #[serde(rename = "X86_64")]
+    x86_64: TargetToolchain,
+}
+
+impl Toolchain {
+    pub fn from_toml(text: &str) -> Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+
+    /// The toolchain configured by the user's file, the project's file, and the environment, in
+    /// increasing precedence.
+    pub fn load() -> Result<Self> {
+        let current_directory = std::env::current_dir().context("Could not find the current directory")?;
+        let files = [Self::user_file(), Self::project_file(&current_directory)];
+        Self::load_from(files.iter().flatten(), |name| std::env::var(name).ok())
+    }
+
+    pub fn load_from<'a>(files: impl Iterator<Item = &'a PathBuf>, variable: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let mut toolchain = Toolchain::default();
+        for file in files {
+            debug!("Reading toolchain configuration {}", file.display());
+            let text = std::fs::read_to_string(file)
+                .with_context(|| format!("Could not read toolchain configuration {}", file.display()))?;
+            let configured = Self::from_toml(&text)
+                .with_context(|| format!("Could not read toolchain configuration {}", file.display()))?;
+            toolchain.merge(configured);
+        }
+        for target_platform in [TargetPlatform::Transputer, TargetPlatform::EPOC16, TargetPlatform::X86_64] {
+            let prefix = format!("RCC_{}", target_platform.to_string().to_uppercase());
+            let tool = |name: &str| variable(&format!("{}_{}", prefix, name));
+            let flags = |name: &str| tool(&format!("{}_FLAGS", name)).map(|flags| flags.split_whitespace().map(str::to_owned).collect());
+            let configured = TargetToolchain {
+                preprocessor: tool("PREPROCESSOR"),
+                preprocessor_flags: flags("PREPROCESSOR"),
+                compiler: tool("COMPILER"),
+                compiler_flags: flags("COMPILER"),
+                assembler: tool("ASSEMBLER"),
+                assembler_flags: flags("ASSEMBLER"),
+                linker: tool("LINKER"),
+                linker_flags: flags("LINKER"),
+            };
+            toolchain.target_mut(target_platform).merge(configured);
+        }
+        Ok(toolchain)
+    }
+
+    /// TODO: CROSSPLATFORM the user's configuration directory on Windows.
+    fn user_file() -> Option<PathBuf> {
+        let configuration_directory = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(directory) if !directory.is_empty() => PathBuf::from(directory),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(configuration_directory.join("rcc").join("toolchain.toml")).filter(|file| file.is_file())
+    }
+
+    /// The project's configuration is the nearest there is to the current directory.
+    pub fn project_file(current_directory: &Path) -> Option<PathBuf> {
+        current_directory.ancestors()
+            .map(|directory| directory.join(PROJECT_FILE))
+            .find(|file| file.is_file())
+    }
+
+    fn merge(&mut self, later: Toolchain) {
+        self.transputer.merge(later.transputer);
+        self.epoc16.merge(later.epoc16);
+        self.x86_64.merge(later.x86_64);
+    }
+
+    fn target(&self, target_platform: TargetPlatform) -> &TargetToolchain {
+        match target_platform {
+            TargetPlatform::Transputer => &self.transputer,
+            TargetPlatform::EPOC16 => &self.epoc16,
+            TargetPlatform::X86_64 => &self.x86_64,
+        }
+    }
+
+    fn target_mut(&mut self, target_platform: TargetPlatform) -> &mut TargetToolchain {
+        match target_platform {
+            TargetPlatform::Transputer => &mut self.transputer,
+            TargetPlatform::EPOC16 => &mut self.epoc16,
+            TargetPlatform::X86_64 => &mut self.x86_64,
+        }
+    }
+
+    pub fn preprocessor(&self, target_platform: TargetPlatform) -> Tool {
+        let target = self.target(target_platform);
+        TargetToolchain::tool(&target.preprocessor, &target.preprocessor_flags, Some("gcc")).expect("there is a default")
+    }
+
+    /// The compiler run with --external-compiler.
+    pub fn compiler(&self, target_platform: TargetPlatform) -> Tool {
+        let target = self.target(target_platform);
+        TargetToolchain::tool(&target.compiler, &target.compiler_flags, Some("rcc1")).expect("there is a default")
+    }
+
+    /// gcc assembles for x86_64. For EPOC16, the default is the assembler of the syntax; one that
+    /// is configured is run with that assembler's arguments.
+    pub fn assembler(&self, target_platform: TargetPlatform, assembler_syntax: AssemblerSyntax) -> Tool {
+        let default = match target_platform {
+            TargetPlatform::X86_64 => "gcc",
+            TargetPlatform::Transputer => "tmasm",
+            TargetPlatform::EPOC16 => match assembler_syntax {
+                AssemblerSyntax::MASM => "jwasm",
+                AssemblerSyntax::NASM => "nasm",
+            },
+        };
+        let target = self.target(target_platform);
+        TargetToolchain::tool(&target.assembler, &target.assembler_flags, Some(default)).expect("there is a default")
+    }
+
+    /// Only x86_64 has a linker by default, gcc, which links with the C library's startup code.
+    /// Without one, the Transputer's modules are linked into a boot image by rcc itself.
+    pub fn linker(&self, target_platform: TargetPlatform) -> Option<Tool> {
+        let default = match target_platform {
+            TargetPlatform::X86_64 => Some("gcc"),
+            TargetPlatform::Transputer | TargetPlatform::EPOC16 => None,
+        };
+        let target = self.target(target_platform);
+        TargetToolchain::tool(&target.linker, &target.linker_flags, default)
+    }
+}
+
+#[cfg(test)]
+#[path = "./toolchain_spec.rs"]
+mod toolchain_spec;
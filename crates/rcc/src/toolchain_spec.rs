@@ -0,0 +1,148 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod toolchain_spec {
+
+    use common::assembler_syntax::AssemblerSyntax;
+    use common::target_platform::TargetPlatform;
+    use common_test::file_utils_test_helper::temp_config_dir;
+    use hamcrest2::prelude::*;
+
+    use crate::toolchain::{Tool, Toolchain, PROJECT_FILE};
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn tool(command: &str, flags: &[&str]) -> Tool {
+        Tool { command: command.to_owned(), flags: flags.iter().map(|flag| flag.to_string()).collect() }
+    }
+
+    fn no_variables(_name: &str) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn defaults() {
+        let toolchain = Toolchain::default();
+        assert_that!(toolchain.preprocessor(TargetPlatform::Transputer), equal_to(tool("gcc", &[])));
+        assert_that!(toolchain.compiler(TargetPlatform::EPOC16), equal_to(tool("rcc1", &[])));
+        assert_that!(toolchain.assembler(TargetPlatform::Transputer, AssemblerSyntax::MASM), equal_to(tool("tmasm", &[])));
+        assert_that!(toolchain.assembler(TargetPlatform::EPOC16, AssemblerSyntax::MASM), equal_to(tool("jwasm", &[])));
+        assert_that!(toolchain.assembler(TargetPlatform::EPOC16, AssemblerSyntax::NASM), equal_to(tool("nasm", &[])));
+        assert_that!(toolchain.assembler(TargetPlatform::X86_64, AssemblerSyntax::MASM), equal_to(tool("gcc", &[])));
+        assert_that!(toolchain.linker(TargetPlatform::X86_64), equal_to(Some(tool("gcc", &[]))));
+        assert_that!(toolchain.linker(TargetPlatform::Transputer), none());
+        assert_that!(toolchain.linker(TargetPlatform::EPOC16), none());
+    }
+
+    #[test]
+    fn file_configures_a_target() {
+        let toolchain = Toolchain::from_toml(r#"
+            [Transputer]
+            assembler = "/opt/parachute/bin/tmasm"
+            assembler_flags = ["-v"]
+            linker = "tlink"
+        "#).unwrap();
+
+        assert_that!(toolchain.assembler(TargetPlatform::Transputer, AssemblerSyntax::MASM), equal_to(tool("/opt/parachute/bin/tmasm", &["-v"])));
+        assert_that!(toolchain.linker(TargetPlatform::Transputer), equal_to(Some(tool("tlink", &[]))));
+        assert_that!(toolchain.preprocessor(TargetPlatform::Transputer), equal_to(tool("gcc", &[])));
+        assert_that!(toolchain.assembler(TargetPlatform::EPOC16, AssemblerSyntax::MASM), equal_to(tool("jwasm", &[])));
+    }
+
+    #[test]
+    fn flags_alone_are_given_to_the_default() {
+        let toolchain = Toolchain::from_toml(r#"
+            [X86_64]
+            preprocessor_flags = ["-Wall"]
+        "#).unwrap();
+
+        assert_that!(toolchain.preprocessor(TargetPlatform::X86_64), equal_to(tool("gcc", &["-Wall"])));
+    }
+
+    #[test]
+    fn unknown_settings_are_errors() {
+        let result = Toolchain::from_toml(r#"
+            [Transputer]
+            asembler = "tmasm"
+        "#);
+
+        assert_that!(result.is_err(), equal_to(true));
+    }
+
+    #[test]
+    fn unknown_targets_are_errors() {
+        let result = Toolchain::from_toml(r#"
+            [PDP11]
+            assembler = "as"
+        "#);
+
+        assert_that!(result.is_err(), equal_to(true));
+    }
+
+    #[test]
+    fn later_files_override_earlier() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let user_file = temp.join("user.toml");
+        let project_file = temp.join("project.toml");
+        std::fs::write(&user_file, "[EPOC16]\nassembler = \"jwasm-2.17\"\nassembler_flags = [\"-Zm\"]\nlinker = \"tslink\"\n").unwrap();
+        std::fs::write(&project_file, "[EPOC16]\nassembler = \"jwasm-2.18\"\n").unwrap();
+
+        let toolchain = Toolchain::load_from([user_file, project_file].iter(), no_variables).unwrap();
+
+        assert_that!(toolchain.assembler(TargetPlatform::EPOC16, AssemblerSyntax::MASM), equal_to(tool("jwasm-2.18", &["-Zm"])));
+        assert_that!(toolchain.linker(TargetPlatform::EPOC16), equal_to(Some(tool("tslink", &[]))));
+    }
+
+    #[test]
+    fn environment_overrides_files() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let project_file = temp.join("project.toml");
+        std::fs::write(&project_file, "[Transputer]\nassembler = \"tmasm-1\"\ncompiler = \"rcc1-dev\"\n").unwrap();
+        let variable = |name: &str| match name {
+            "RCC_TRANSPUTER_ASSEMBLER" => Some("tmasm-2".to_owned()),
+            "RCC_TRANSPUTER_ASSEMBLER_FLAGS" => Some(" -v  -x ".to_owned()),
+            _ => None,
+        };
+
+        let toolchain = Toolchain::load_from([project_file].iter(), variable).unwrap();
+
+        assert_that!(toolchain.assembler(TargetPlatform::Transputer, AssemblerSyntax::MASM), equal_to(tool("tmasm-2", &["-v", "-x"])));
+        assert_that!(toolchain.compiler(TargetPlatform::Transputer), equal_to(tool("rcc1-dev", &[])));
+    }
+
+    #[test]
+    fn unreadable_file_is_an_error() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let project_file = temp.join("project.toml");
+        std::fs::write(&project_file, "[Transputer\n").unwrap();
+
+        let result = Toolchain::load_from([project_file.clone()].iter(), no_variables);
+
+        let msg = result.err().unwrap().to_string();
+        assert_that!(msg, equal_to(format!("Could not read toolchain configuration {}", project_file.display())));
+    }
+
+    #[test]
+    fn project_file_is_found_in_an_ancestor() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let nested = temp.join("src").join("module");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(temp.join(PROJECT_FILE), "").unwrap();
+
+        assert_that!(Toolchain::project_file(&nested), equal_to(Some(temp.join(PROJECT_FILE))));
+    }
+
+    #[test]
+    fn nearest_project_file_is_found() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let nested = temp.join("src");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(temp.join(PROJECT_FILE), "").unwrap();
+        std::fs::write(nested.join(PROJECT_FILE), "").unwrap();
+
+        assert_that!(Toolchain::project_file(&nested), equal_to(Some(nested.join(PROJECT_FILE))));
+    }
+}
@@ -9,8 +9,8 @@ mod executor_itest {
     use log::Level;
     use rcc::executor::{CommandExecutor, Executor};
 
-    // TODO need a test that captures logging, and if there's an error from the command, display the error/output as ERROR level
-    // rather than just DEBUG.
+    // A command's failure is reported by the driver controller, with its stderr, so the executor
+    // only logs at DEBUG.
 
     #[test]
     #[serial_test::serial]
@@ -36,7 +36,7 @@ mod executor_itest {
 
     #[test]
     #[serial_test::serial]
-    fn command_fails_and_logs_stdout_and_stderr() {
+    fn command_fails_and_logs_stdout_and_stderr_at_debug() {
         testing_logger::setup();
         // TODO: CROSSPLATFORM
         let e = CommandExecutor::default();
@@ -44,17 +44,15 @@ mod executor_itest {
         let es = ex.ok().unwrap();
         assert_that!(es.code().unwrap(), equal_to(1));
         testing_logger::validate( |captured_logs| {
-            assert_eq!(captured_logs.len(), 5);
+            assert_eq!(captured_logs.len(), 4);
             assert_eq!(captured_logs[0].body, "Executing \"cat nonexistant.txt\"");
             assert_eq!(captured_logs[0].level, Level::Debug);
-            assert_eq!(captured_logs[1].body, "Execution failure of \"cat nonexistant.txt\"");
-            assert_eq!(captured_logs[1].level, Level::Error);
-            assert_eq!(captured_logs[2].body, "status: exit status: 1");
-            assert_eq!(captured_logs[2].level, Level::Error);
-            assert_eq!(captured_logs[3].body, "stdout: ");
-            assert_eq!(captured_logs[3].level, Level::Error);
-            assert_eq!(captured_logs[4].body, "stderr: cat: nonexistant.txt: No such file or directory\n");
-            assert_eq!(captured_logs[4].level, Level::Error);
+            assert_eq!(captured_logs[1].body, "status: exit status: 1");
+            assert_eq!(captured_logs[1].level, Level::Debug);
+            assert_eq!(captured_logs[2].body, "stdout: ");
+            assert_eq!(captured_logs[2].level, Level::Debug);
+            assert_eq!(captured_logs[3].body, "stderr: cat: nonexistant.txt: No such file or directory\n");
+            assert_eq!(captured_logs[3].level, Level::Debug);
         });
     }
 
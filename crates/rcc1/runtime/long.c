@@ -0,0 +1,69 @@
+/*
+ * The 'long' arithmetic runtime, for EPOC16, where the 8086 has no instructions to multiply or
+ * divide 32 bit values. rcc1 replaces every 'long' and 'unsigned long' multiplication, division
+ * and remainder with a call to one of these routines, which have the names and results of those
+ * in libgcc, and are called as any other function. It is written in the C that rcc compiles,
+ * using only the 'long' operations the code generator does inline, so build it with rcc for
+ * EPOC16 and link it with the program.
+ *
+ * Division truncates towards zero, and the remainder has the sign of the dividend. Dividing by
+ * zero gives all ones as the quotient, and the dividend as the remainder.
+ */
+
+/* The remainder of the last unsigned_divide. */
+static unsigned long division_remainder;
+
+unsigned long __mulsi3(unsigned long a, unsigned long b)
+{
+    unsigned long product = 0;
+
+    while (b != 0) {
+        if (b & 1)
+            product = product + a;
+        a = a << 1;
+        b = b >> 1;
+    }
+    return product;
+}
+
+/* Divides a by b one bit at a time, leaving the remainder in division_remainder. */
+static unsigned long unsigned_divide(unsigned long a, unsigned long b)
+{
+    unsigned long quotient = 0;
+    int bit;
+
+    division_remainder = 0;
+    for (bit = 31; bit >= 0; bit--) {
+        division_remainder = (division_remainder << 1) | ((a >> bit) & 1);
+        quotient = quotient << 1;
+        if (division_remainder >= b) {
+            division_remainder = division_remainder - b;
+            quotient = quotient | 1;
+        }
+    }
+    return quotient;
+}
+
+unsigned long __udivsi3(unsigned long a, unsigned long b)
+{
+    return unsigned_divide(a, b);
+}
+
+unsigned long __umodsi3(unsigned long a, unsigned long b)
+{
+    unsigned_divide(a, b);
+    return division_remainder;
+}
+
+long __divsi3(long a, long b)
+{
+    unsigned long quotient = unsigned_divide(a < 0 ? -a : a, b < 0 ? -b : b);
+
+    return (a < 0) != (b < 0) ? -quotient : quotient;
+}
+
+long __modsi3(long a, long b)
+{
+    unsigned_divide(a < 0 ? -a : a, b < 0 ? -b : b);
+    return a < 0 ? -division_remainder : division_remainder;
+}
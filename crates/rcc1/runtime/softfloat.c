@@ -0,0 +1,792 @@
+/*
+ * The soft-float runtime: IEEE 754 single and double precision arithmetic for the targets without
+ * floating point hardware, the T425 and the Psion's 8086/V20. Soft-float lowering in rcc1 replaces
+ * every floating operation with a call to one of these routines, which have the names and results
+ * of those in libgcc. It is written in the C that rcc compiles, using 'unsigned long' words, which
+ * are 32 bits wide on both targets, so build it with rcc for the target and link it with the
+ * program.
+ *
+ * A float is passed in one word, as its bits. A double is passed in two, high word first, and is
+ * returned with its high word as the result and its low word in '__double_low'.
+ *
+ * Rounding is always to nearest, with ties to even. Subnormals are supported, and there are no
+ * exception flags. A NaN result is quiet: either a NaN operand, quietened, or the default NaN.
+ * Converting a value that is out of range of 'long' or 'unsigned long' saturates, and a NaN
+ * converts to zero.
+ */
+
+unsigned long __double_low;
+
+/*
+ * The significand of a double being worked on, with three guard bits below it: its bits 55 to 32
+ * are in significand_high, and 31 to 0 in significand_low.
+ */
+static unsigned long significand_high;
+static unsigned long significand_low;
+
+/* The 64 bit product of two words, from multiply_words. */
+static unsigned long product_high;
+static unsigned long product_low;
+
+/* Shifts a word right, setting its lowest bit if any of the bits shifted out were set. */
+static unsigned long shift_right_sticky(unsigned long x, long n)
+{
+    if (n <= 0)
+        return x;
+    if (n >= 32)
+        return x != 0;
+    return (x >> n) | ((x & ((1ul << n) - 1)) != 0);
+}
+
+static void multiply_words(unsigned long x, unsigned long y)
+{
+    unsigned long xl, xh, yl, yh, ll, lh, hl, middle;
+
+    xl = x & 0xfffful;
+    xh = x >> 16;
+    yl = y & 0xfffful;
+    yh = y >> 16;
+    ll = xl * yl;
+    lh = xl * yh;
+    hl = xh * yl;
+    middle = lh + (ll >> 16) + (hl & 0xfffful);
+    product_low = (middle << 16) | (ll & 0xfffful);
+    product_high = xh * yh + (middle >> 16) + (hl >> 16);
+}
+
+/* Single precision */
+
+static int float_is_nan(unsigned long a)
+{
+    return (a & 0x7ffffffful) > 0x7f800000ul;
+}
+
+/*
+ * Rounds and packs a float, from its sign, biased exponent and significand. The significand has
+ * its leading one at bit 26 when the exponent is right, above three guard bits, the lowest of
+ * which is sticky. It need not be normalised.
+ */
+static unsigned long round_float(unsigned long sign, long exponent, unsigned long significand)
+{
+    unsigned long guard;
+
+    if (significand == 0)
+        return sign;
+    while (significand >= 0x8000000ul) {
+        significand = shift_right_sticky(significand, 1);
+        exponent++;
+    }
+    while (significand < 0x4000000ul) {
+        significand <<= 1;
+        exponent--;
+    }
+    if (exponent <= 0) {
+        significand = shift_right_sticky(significand, 1 - exponent);
+        exponent = 0;
+    }
+    guard = significand & 7;
+    significand >>= 3;
+    if (guard > 4 || (guard == 4 && (significand & 1)))
+        significand++;
+    if (significand >= 0x1000000ul) {
+        significand >>= 1;
+        exponent++;
+    }
+    if (exponent == 0 && significand >= 0x800000ul)
+        exponent = 1;
+    if (exponent >= 255)
+        return sign | 0x7f800000ul;
+    return sign | ((unsigned long) exponent << 23) | (significand & 0x7ffffful);
+}
+
+unsigned long __addsf3(unsigned long a, unsigned long b)
+{
+    unsigned long t, sign, subtract, ma, mb;
+    long ea, eb;
+
+    if (float_is_nan(a))
+        return a | 0x400000ul;
+    if (float_is_nan(b))
+        return b | 0x400000ul;
+    if ((a & 0x7ffffffful) < (b & 0x7ffffffful)) {
+        t = a;
+        a = b;
+        b = t;
+    }
+    if ((a & 0x7ffffffful) == 0x7f800000ul) {
+        if ((b & 0x7ffffffful) == 0x7f800000ul && a != b)
+            return 0x7fc00000ul;
+        return a;
+    }
+    if ((a & 0x7ffffffful) == 0)
+        return a & b;
+    sign = a & 0x80000000ul;
+    subtract = (a ^ b) & 0x80000000ul;
+    ea = (a >> 23) & 0xff;
+    eb = (b >> 23) & 0xff;
+    ma = a & 0x7ffffful;
+    mb = b & 0x7ffffful;
+    if (ea == 0)
+        ea = 1;
+    else
+        ma |= 0x800000ul;
+    if (eb == 0)
+        eb = 1;
+    else
+        mb |= 0x800000ul;
+    ma <<= 3;
+    mb = shift_right_sticky(mb << 3, ea - eb);
+    if (subtract)
+        ma -= mb;
+    else
+        ma += mb;
+    if (ma == 0)
+        return 0;
+    return round_float(sign, ea, ma);
+}
+
+unsigned long __subsf3(unsigned long a, unsigned long b)
+{
+    return __addsf3(a, b ^ 0x80000000ul);
+}
+
+unsigned long __mulsf3(unsigned long a, unsigned long b)
+{
+    unsigned long sign, ma, mb;
+    long ea, eb;
+
+    if (float_is_nan(a))
+        return a | 0x400000ul;
+    if (float_is_nan(b))
+        return b | 0x400000ul;
+    sign = (a ^ b) & 0x80000000ul;
+    a &= 0x7ffffffful;
+    b &= 0x7ffffffful;
+    if (a == 0x7f800000ul)
+        return b == 0 ? 0x7fc00000ul : sign | 0x7f800000ul;
+    if (b == 0x7f800000ul)
+        return a == 0 ? 0x7fc00000ul : sign | 0x7f800000ul;
+    if (a == 0 || b == 0)
+        return sign;
+    ea = a >> 23;
+    eb = b >> 23;
+    ma = a & 0x7ffffful;
+    mb = b & 0x7ffffful;
+    if (ea == 0) {
+        ea = 1;
+        while (ma < 0x800000ul) {
+            ma <<= 1;
+            ea--;
+        }
+    } else
+        ma |= 0x800000ul;
+    if (eb == 0) {
+        eb = 1;
+        while (mb < 0x800000ul) {
+            mb <<= 1;
+            eb--;
+        }
+    } else
+        mb |= 0x800000ul;
+    multiply_words(ma, mb);
+    return round_float(sign, ea + eb - 127, (product_high << 12) | (product_low >> 20) | ((product_low & 0xffffful) != 0));
+}
+
+unsigned long __divsf3(unsigned long a, unsigned long b)
+{
+    unsigned long sign, ma, mb, quotient;
+    long ea, eb, exponent;
+    int i;
+
+    if (float_is_nan(a))
+        return a | 0x400000ul;
+    if (float_is_nan(b))
+        return b | 0x400000ul;
+    sign = (a ^ b) & 0x80000000ul;
+    a &= 0x7ffffffful;
+    b &= 0x7ffffffful;
+    if (a == 0x7f800000ul)
+        return b == 0x7f800000ul ? 0x7fc00000ul : sign | 0x7f800000ul;
+    if (b == 0x7f800000ul)
+        return sign;
+    if (b == 0)
+        return a == 0 ? 0x7fc00000ul : sign | 0x7f800000ul;
+    if (a == 0)
+        return sign;
+    ea = a >> 23;
+    eb = b >> 23;
+    ma = a & 0x7ffffful;
+    mb = b & 0x7ffffful;
+    if (ea == 0) {
+        ea = 1;
+        while (ma < 0x800000ul) {
+            ma <<= 1;
+            ea--;
+        }
+    } else
+        ma |= 0x800000ul;
+    if (eb == 0) {
+        eb = 1;
+        while (mb < 0x800000ul) {
+            mb <<= 1;
+            eb--;
+        }
+    } else
+        mb |= 0x800000ul;
+    exponent = ea - eb + 127;
+    if (ma < mb) {
+        ma <<= 1;
+        exponent--;
+    }
+    quotient = 0;
+    for (i = 0; i < 27; i++) {
+        quotient <<= 1;
+        if (ma >= mb) {
+            ma -= mb;
+            quotient |= 1;
+        }
+        ma <<= 1;
+    }
+    return round_float(sign, exponent, quotient | (ma != 0));
+}
+
+/* Compares two floats, giving -1, 0 or 1, or the given result if they are unordered. */
+static int compare_float(unsigned long a, unsigned long b, int unordered)
+{
+    if (float_is_nan(a) || float_is_nan(b))
+        return unordered;
+    if (((a | b) & 0x7ffffffful) == 0)
+        return 0;
+    if ((a ^ b) & 0x80000000ul)
+        return a & 0x80000000ul ? -1 : 1;
+    if (a == b)
+        return 0;
+    if (a & 0x80000000ul)
+        return a > b ? -1 : 1;
+    return a < b ? -1 : 1;
+}
+
+int __eqsf2(unsigned long a, unsigned long b)
+{
+    return compare_float(a, b, 1);
+}
+
+int __nesf2(unsigned long a, unsigned long b)
+{
+    return compare_float(a, b, 1);
+}
+
+int __ltsf2(unsigned long a, unsigned long b)
+{
+    return compare_float(a, b, 1);
+}
+
+int __lesf2(unsigned long a, unsigned long b)
+{
+    return compare_float(a, b, 1);
+}
+
+int __gtsf2(unsigned long a, unsigned long b)
+{
+    return compare_float(a, b, -1);
+}
+
+int __gesf2(unsigned long a, unsigned long b)
+{
+    return compare_float(a, b, -1);
+}
+
+unsigned long __floatsisf(long i)
+{
+    if (i < 0)
+        return round_float(0x80000000ul, 153, 0 - (unsigned long) i);
+    return round_float(0, 153, i);
+}
+
+unsigned long __floatunsisf(unsigned long u)
+{
+    return round_float(0, 153, u);
+}
+
+long __fixsfsi(unsigned long a)
+{
+    unsigned long significand;
+    long exponent;
+
+    if (float_is_nan(a))
+        return 0;
+    exponent = (a >> 23) & 0xff;
+    if (exponent < 127)
+        return 0;
+    if (exponent >= 158)
+        return a & 0x80000000ul ? -2147483647l - 1 : 2147483647l;
+    significand = (a & 0x7ffffful) | 0x800000ul;
+    if (exponent >= 150)
+        significand <<= exponent - 150;
+    else
+        significand >>= 150 - exponent;
+    if (a & 0x80000000ul)
+        return -(long) significand;
+    return significand;
+}
+
+unsigned long __fixunssfsi(unsigned long a)
+{
+    unsigned long significand;
+    long exponent;
+
+    if (float_is_nan(a) || (a & 0x80000000ul))
+        return 0;
+    exponent = a >> 23;
+    if (exponent < 127)
+        return 0;
+    if (exponent >= 159)
+        return 0xfffffffful;
+    significand = (a & 0x7ffffful) | 0x800000ul;
+    if (exponent >= 150)
+        significand <<= exponent - 150;
+    else
+        significand >>= 150 - exponent;
+    return significand;
+}
+
+/* Double precision */
+
+static int double_is_nan(unsigned long high, unsigned long low)
+{
+    high &= 0x7ffffffful;
+    return high > 0x7ff00000ul || (high == 0x7ff00000ul && low != 0);
+}
+
+/* Shifts the significand right, setting its lowest bit if any of the bits shifted out were set. */
+static void shift_significand_right(long n)
+{
+    unsigned long sticky;
+
+    if (n <= 0)
+        return;
+    if (n >= 64) {
+        significand_low = (significand_high | significand_low) != 0;
+        significand_high = 0;
+        return;
+    }
+    if (n >= 32) {
+        sticky = significand_low != 0;
+        significand_low = shift_right_sticky(significand_high, n - 32) | sticky;
+        significand_high = 0;
+        return;
+    }
+    sticky = (significand_low & ((1ul << n) - 1)) != 0;
+    significand_low = (significand_low >> n) | (significand_high << (32 - n)) | sticky;
+    significand_high >>= n;
+}
+
+/*
+ * Rounds and packs the double in significand_high and significand_low, which has its leading one
+ * at bit 55 when the exponent is right, returning its high word and leaving its low word in
+ * __double_low.
+ */
+static unsigned long round_double(unsigned long sign, long exponent)
+{
+    unsigned long guard;
+
+    if (significand_high == 0 && significand_low == 0) {
+        __double_low = 0;
+        return sign;
+    }
+    if (significand_high == 0) {
+        significand_high = significand_low;
+        significand_low = 0;
+        exponent -= 32;
+    }
+    while (significand_high >= 0x1000000ul) {
+        shift_significand_right(1);
+        exponent++;
+    }
+    while (significand_high < 0x800000ul) {
+        significand_high = (significand_high << 1) | (significand_low >> 31);
+        significand_low <<= 1;
+        exponent--;
+    }
+    if (exponent <= 0) {
+        shift_significand_right(1 - exponent);
+        exponent = 0;
+    }
+    guard = significand_low & 7;
+    significand_low = (significand_low >> 3) | (significand_high << 29);
+    significand_high >>= 3;
+    if (guard > 4 || (guard == 4 && (significand_low & 1))) {
+        significand_low++;
+        if (significand_low == 0)
+            significand_high++;
+    }
+    if (significand_high >= 0x200000ul) {
+        significand_low = (significand_low >> 1) | (significand_high << 31);
+        significand_high >>= 1;
+        exponent++;
+    }
+    if (exponent == 0 && significand_high >= 0x100000ul)
+        exponent = 1;
+    if (exponent >= 2047) {
+        __double_low = 0;
+        return sign | 0x7ff00000ul;
+    }
+    __double_low = significand_low;
+    return sign | ((unsigned long) exponent << 20) | (significand_high & 0xffffful);
+}
+
+unsigned long __adddf3(unsigned long ahigh, unsigned long alow, unsigned long bhigh, unsigned long blow)
+{
+    unsigned long t, sign, subtract, high, low;
+    long aexponent, bexponent;
+
+    if (double_is_nan(ahigh, alow)) {
+        __double_low = alow;
+        return ahigh | 0x80000ul;
+    }
+    if (double_is_nan(bhigh, blow)) {
+        __double_low = blow;
+        return bhigh | 0x80000ul;
+    }
+    if ((ahigh & 0x7ffffffful) < (bhigh & 0x7ffffffful)
+        || ((ahigh & 0x7ffffffful) == (bhigh & 0x7ffffffful) && alow < blow)) {
+        t = ahigh;
+        ahigh = bhigh;
+        bhigh = t;
+        t = alow;
+        alow = blow;
+        blow = t;
+    }
+    __double_low = 0;
+    if ((ahigh & 0x7ffffffful) == 0x7ff00000ul) {
+        if ((bhigh & 0x7ffffffful) == 0x7ff00000ul && ahigh != bhigh)
+            return 0x7ff80000ul;
+        return ahigh;
+    }
+    if ((ahigh & 0x7ffffffful) == 0 && alow == 0)
+        return ahigh & bhigh;
+    sign = ahigh & 0x80000000ul;
+    subtract = (ahigh ^ bhigh) & 0x80000000ul;
+    aexponent = (ahigh >> 20) & 0x7ff;
+    bexponent = (bhigh >> 20) & 0x7ff;
+    ahigh &= 0xffffful;
+    bhigh &= 0xffffful;
+    if (aexponent == 0)
+        aexponent = 1;
+    else
+        ahigh |= 0x100000ul;
+    if (bexponent == 0)
+        bexponent = 1;
+    else
+        bhigh |= 0x100000ul;
+    significand_high = (bhigh << 3) | (blow >> 29);
+    significand_low = blow << 3;
+    shift_significand_right(aexponent - bexponent);
+    high = (ahigh << 3) | (alow >> 29);
+    low = alow << 3;
+    if (subtract) {
+        significand_high = high - significand_high - (low < significand_low);
+        significand_low = low - significand_low;
+    } else {
+        low += significand_low;
+        significand_high = high + significand_high + (low < significand_low);
+        significand_low = low;
+    }
+    if (significand_high == 0 && significand_low == 0)
+        return 0;
+    return round_double(sign, aexponent);
+}
+
+unsigned long __subdf3(unsigned long ahigh, unsigned long alow, unsigned long bhigh, unsigned long blow)
+{
+    return __adddf3(ahigh, alow, bhigh ^ 0x80000000ul, blow);
+}
+
+unsigned long __muldf3(unsigned long ahigh, unsigned long alow, unsigned long bhigh, unsigned long blow)
+{
+    unsigned long sign, p0, p1, p2, p3;
+    long aexponent, bexponent;
+
+    if (double_is_nan(ahigh, alow)) {
+        __double_low = alow;
+        return ahigh | 0x80000ul;
+    }
+    if (double_is_nan(bhigh, blow)) {
+        __double_low = blow;
+        return bhigh | 0x80000ul;
+    }
+    __double_low = 0;
+    sign = (ahigh ^ bhigh) & 0x80000000ul;
+    ahigh &= 0x7ffffffful;
+    bhigh &= 0x7ffffffful;
+    if (ahigh == 0x7ff00000ul)
+        return (bhigh | blow) == 0 ? 0x7ff80000ul : sign | 0x7ff00000ul;
+    if (bhigh == 0x7ff00000ul)
+        return (ahigh | alow) == 0 ? 0x7ff80000ul : sign | 0x7ff00000ul;
+    if ((ahigh | alow) == 0 || (bhigh | blow) == 0)
+        return sign;
+    aexponent = ahigh >> 20;
+    bexponent = bhigh >> 20;
+    ahigh &= 0xffffful;
+    bhigh &= 0xffffful;
+    if (aexponent == 0) {
+        aexponent = 1;
+        while (ahigh < 0x100000ul) {
+            ahigh = (ahigh << 1) | (alow >> 31);
+            alow <<= 1;
+            aexponent--;
+        }
+    } else
+        ahigh |= 0x100000ul;
+    if (bexponent == 0) {
+        bexponent = 1;
+        while (bhigh < 0x100000ul) {
+            bhigh = (bhigh << 1) | (blow >> 31);
+            blow <<= 1;
+            bexponent--;
+        }
+    } else
+        bhigh |= 0x100000ul;
+    multiply_words(alow, blow);
+    p0 = product_low;
+    p1 = product_high;
+    multiply_words(ahigh, blow);
+    p1 += product_low;
+    p2 = product_high + (p1 < product_low);
+    multiply_words(alow, bhigh);
+    p1 += product_low;
+    p2 += product_high + (p1 < product_low);
+    multiply_words(ahigh, bhigh);
+    p2 += product_low;
+    p3 = product_high + (p2 < product_low);
+    significand_high = (p3 << 15) | (p2 >> 17);
+    significand_low = (p2 << 15) | (p1 >> 17) | ((p1 & 0x1fffful) != 0) | (p0 != 0);
+    return round_double(sign, aexponent + bexponent - 1023);
+}
+
+unsigned long __divdf3(unsigned long ahigh, unsigned long alow, unsigned long bhigh, unsigned long blow)
+{
+    unsigned long sign;
+    long aexponent, bexponent, exponent;
+    int i;
+
+    if (double_is_nan(ahigh, alow)) {
+        __double_low = alow;
+        return ahigh | 0x80000ul;
+    }
+    if (double_is_nan(bhigh, blow)) {
+        __double_low = blow;
+        return bhigh | 0x80000ul;
+    }
+    __double_low = 0;
+    sign = (ahigh ^ bhigh) & 0x80000000ul;
+    ahigh &= 0x7ffffffful;
+    bhigh &= 0x7ffffffful;
+    if (ahigh == 0x7ff00000ul)
+        return bhigh == 0x7ff00000ul ? 0x7ff80000ul : sign | 0x7ff00000ul;
+    if (bhigh == 0x7ff00000ul)
+        return sign;
+    if ((bhigh | blow) == 0)
+        return (ahigh | alow) == 0 ? 0x7ff80000ul : sign | 0x7ff00000ul;
+    if ((ahigh | alow) == 0)
+        return sign;
+    aexponent = ahigh >> 20;
+    bexponent = bhigh >> 20;
+    ahigh &= 0xffffful;
+    bhigh &= 0xffffful;
+    if (aexponent == 0) {
+        aexponent = 1;
+        while (ahigh < 0x100000ul) {
+            ahigh = (ahigh << 1) | (alow >> 31);
+            alow <<= 1;
+            aexponent--;
+        }
+    } else
+        ahigh |= 0x100000ul;
+    if (bexponent == 0) {
+        bexponent = 1;
+        while (bhigh < 0x100000ul) {
+            bhigh = (bhigh << 1) | (blow >> 31);
+            blow <<= 1;
+            bexponent--;
+        }
+    } else
+        bhigh |= 0x100000ul;
+    exponent = aexponent - bexponent + 1023;
+    if (ahigh < bhigh || (ahigh == bhigh && alow < blow)) {
+        ahigh = (ahigh << 1) | (alow >> 31);
+        alow <<= 1;
+        exponent--;
+    }
+    significand_high = 0;
+    significand_low = 0;
+    for (i = 0; i < 56; i++) {
+        significand_high = (significand_high << 1) | (significand_low >> 31);
+        significand_low <<= 1;
+        if (ahigh > bhigh || (ahigh == bhigh && alow >= blow)) {
+            ahigh = ahigh - bhigh - (alow < blow);
+            alow -= blow;
+            significand_low |= 1;
+        }
+        ahigh = (ahigh << 1) | (alow >> 31);
+        alow <<= 1;
+    }
+    significand_low |= (ahigh | alow) != 0;
+    return round_double(sign, exponent);
+}
+
+/* Compares two doubles, giving -1, 0 or 1, or the given result if they are unordered. */
+static int compare_double(unsigned long ahigh, unsigned long alow, unsigned long bhigh, unsigned long blow, int unordered)
+{
+    if (double_is_nan(ahigh, alow) || double_is_nan(bhigh, blow))
+        return unordered;
+    if ((((ahigh | bhigh) & 0x7ffffffful) | alow | blow) == 0)
+        return 0;
+    if ((ahigh ^ bhigh) & 0x80000000ul)
+        return ahigh & 0x80000000ul ? -1 : 1;
+    if (ahigh == bhigh && alow == blow)
+        return 0;
+    if (ahigh > bhigh || (ahigh == bhigh && alow > blow))
+        return ahigh & 0x80000000ul ? -1 : 1;
+    return ahigh & 0x80000000ul ? 1 : -1;
+}
+
+int __eqdf2(unsigned long ahigh, unsigned long alow, unsigned long bhigh, unsigned long blow)
+{
+    return compare_double(ahigh, alow, bhigh, blow, 1);
+}
+
+int __nedf2(unsigned long ahigh, unsigned long alow, unsigned long bhigh, unsigned long blow)
+{
+    return compare_double(ahigh, alow, bhigh, blow, 1);
+}
+
+int __ltdf2(unsigned long ahigh, unsigned long alow, unsigned long bhigh, unsigned long blow)
+{
+    return compare_double(ahigh, alow, bhigh, blow, 1);
+}
+
+int __ledf2(unsigned long ahigh, unsigned long alow, unsigned long bhigh, unsigned long blow)
+{
+    return compare_double(ahigh, alow, bhigh, blow, 1);
+}
+
+int __gtdf2(unsigned long ahigh, unsigned long alow, unsigned long bhigh, unsigned long blow)
+{
+    return compare_double(ahigh, alow, bhigh, blow, -1);
+}
+
+int __gedf2(unsigned long ahigh, unsigned long alow, unsigned long bhigh, unsigned long blow)
+{
+    return compare_double(ahigh, alow, bhigh, blow, -1);
+}
+
+unsigned long __floatsidf(long i)
+{
+    significand_high = 0;
+    if (i < 0) {
+        significand_low = 0 - (unsigned long) i;
+        return round_double(0x80000000ul, 1078);
+    }
+    significand_low = i;
+    return round_double(0, 1078);
+}
+
+unsigned long __floatunsidf(unsigned long u)
+{
+    significand_high = 0;
+    significand_low = u;
+    return round_double(0, 1078);
+}
+
+long __fixdfsi(unsigned long high, unsigned long low)
+{
+    unsigned long sign, significand;
+    long exponent, shift;
+
+    if (double_is_nan(high, low))
+        return 0;
+    sign = high & 0x80000000ul;
+    exponent = (high >> 20) & 0x7ff;
+    if (exponent < 1023)
+        return 0;
+    if (exponent >= 1054)
+        return sign ? -2147483647l - 1 : 2147483647l;
+    high = (high & 0xffffful) | 0x100000ul;
+    shift = 1075 - exponent;
+    if (shift >= 32)
+        significand = high >> (shift - 32);
+    else
+        significand = (low >> shift) | (high << (32 - shift));
+    if (sign)
+        return -(long) significand;
+    return significand;
+}
+
+unsigned long __fixunsdfsi(unsigned long high, unsigned long low)
+{
+    long exponent, shift;
+
+    if (double_is_nan(high, low) || (high & 0x80000000ul))
+        return 0;
+    exponent = high >> 20;
+    if (exponent < 1023)
+        return 0;
+    if (exponent >= 1055)
+        return 0xfffffffful;
+    high = (high & 0xffffful) | 0x100000ul;
+    shift = 1075 - exponent;
+    if (shift >= 32)
+        return high >> (shift - 32);
+    return (low >> shift) | (high << (32 - shift));
+}
+
+unsigned long __extendsfdf2(unsigned long a)
+{
+    unsigned long sign, significand;
+    long exponent;
+
+    sign = a & 0x80000000ul;
+    exponent = (a >> 23) & 0xff;
+    significand = a & 0x7ffffful;
+    if (exponent == 255) {
+        if (significand)
+            significand |= 0x400000ul;
+        __double_low = significand << 29;
+        return sign | 0x7ff00000ul | (significand >> 3);
+    }
+    if (exponent == 0) {
+        if (significand == 0) {
+            __double_low = 0;
+            return sign;
+        }
+        exponent = 1;
+        while (significand < 0x800000ul) {
+            significand <<= 1;
+            exponent--;
+        }
+        significand &= 0x7ffffful;
+    }
+    __double_low = significand << 29;
+    return sign | ((unsigned long) (exponent + 896) << 20) | (significand >> 3);
+}
+
+unsigned long __truncdfsf2(unsigned long high, unsigned long low)
+{
+    unsigned long sign;
+    long exponent;
+
+    sign = high & 0x80000000ul;
+    exponent = (high >> 20) & 0x7ff;
+    high &= 0xffffful;
+    if (exponent == 2047) {
+        if (high | low)
+            return sign | 0x7fc00000ul | (high << 3) | (low >> 29);
+        return sign | 0x7f800000ul;
+    }
+    if (exponent == 0)
+        exponent = 1;
+    else
+        high |= 0x100000ul;
+    return round_float(sign, exponent - 896, (high << 6) | (low >> 26) | ((low & 0x3fffffful) != 0));
+}
@@ -1,4 +1,211 @@
+//! The abstract syntax tree produced by the parser, and rewritten by the semantic analysis passes.
+
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum AST {
-    Dummy,
-}
\ No newline at end of file
+pub struct Program {
+    pub declarations: Vec<Declaration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageClass {
+    Auto,
+    Register,
+    Static,
+    Extern,
+}
+
+/// The C89 arithmetic types, 'void', and the types of functions. The sizes of the integer types, and
+/// whether plain 'char' is signed, depend on the target's data model. 'float' and 'double' are
+/// IEEE 754 single and double precision on every target; 'long double' is the same as 'double'.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Type {
+    Char,
+    SignedChar,
+    UnsignedChar,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+    Long,
+    UnsignedLong,
+    Float,
+    Double,
+    Void,
+    Function { params: Vec<Type>, return_type: Box<Type> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Declaration {
+    Function(FunctionDeclaration),
+    Variable(VariableDeclaration),
+    Enum(EnumDeclaration),
+}
+
+/// A function prototype if it has no body, or a function definition if it does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionDeclaration {
+    pub name: String,
+    /// An unnamed parameter, allowed only in a declaration without a body, has an empty name.
+    pub params: Vec<String>,
+    pub body: Option<Block>,
+    /// Always a 'Type::Function', with one parameter type per name in 'params'.
+    pub function_type: Type,
+    pub storage_class: Option<StorageClass>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableDeclaration {
+    pub name: String,
+    pub init: Option<Expression>,
+    pub variable_type: Type,
+    pub storage_class: Option<StorageClass>,
+}
+
+/// The definition of an enumerated type's constants. Enumerated types themselves are 'int'.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumDeclaration {
+    pub tag: Option<String>,
+    pub enumerators: Vec<Enumerator>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Enumerator {
+    pub name: String,
+    pub value: Option<Expression>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub items: Vec<BlockItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockItem {
+    Statement(Statement),
+    Declaration(Declaration),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForInit {
+    Declaration(VariableDeclaration),
+    Expression(Option<Expression>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Statement {
+    Return(Option<Expression>),
+    Expression(Expression),
+    If {
+        condition: Expression,
+        then: Box<Statement>,
+        otherwise: Option<Box<Statement>>,
+    },
+    Compound(Block),
+    Break,
+    Continue,
+    While {
+        condition: Expression,
+        body: Box<Statement>,
+    },
+    DoWhile {
+        body: Box<Statement>,
+        condition: Expression,
+    },
+    For {
+        init: ForInit,
+        condition: Option<Expression>,
+        post: Option<Expression>,
+        body: Box<Statement>,
+    },
+    Switch {
+        condition: Expression,
+        body: Box<Statement>,
+    },
+    /// After type checking, the label is a 'Constant' of the switch's promoted controlling type.
+    Case(Expression, Box<Statement>),
+    Default(Box<Statement>),
+    Goto(String),
+    Labelled(String, Box<Statement>),
+    Null,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOperator {
+    Negate,
+    Complement,
+    Not,
+    PreIncrement,
+    PreDecrement,
+    PostIncrement,
+    PostDecrement,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    ShiftLeft,
+    ShiftRight,
+    And,
+    Or,
+    Equal,
+    NotEqual,
+    LessThan,
+    LessOrEqual,
+    GreaterThan,
+    GreaterOrEqual,
+}
+
+/// An integer or floating constant as written in the source. Which type it has depends on the target's data
+/// model, so type checking replaces it with a 'Constant'.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Literal {
+    /// An unsuffixed decimal constant, e.g. '10'.
+    Decimal(u64),
+    /// An unsuffixed octal or hexadecimal constant, e.g. '012' or '0xa'.
+    OctalOrHexadecimal(u64),
+    /// e.g. '10u'
+    Unsigned(u64),
+    /// e.g. '10l'
+    Long(u64),
+    /// e.g. '10ul'
+    UnsignedLong(u64),
+    /// e.g. '1.5f', as the bits of the nearest IEEE single precision value.
+    Float(u32),
+    /// e.g. '1.5' or '15e-1', as the bits of the nearest IEEE double precision value.
+    Double(u64),
+}
+
+/// A typed value, which is always within the range of its type on the target. Floating values are
+/// held as their IEEE 754 bit patterns, so that constants can still be compared and hashed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Constant {
+    pub constant_type: Type,
+    pub value: i128,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expression {
+    Literal(Literal),
+    Constant(Constant),
+    Var(String),
+    /// Explicit casts, and after type checking, implicit conversions too.
+    Cast(Type, Box<Expression>),
+    Unary(UnaryOperator, Box<Expression>),
+    Binary(BinaryOperator, Box<Expression>, Box<Expression>),
+    Assignment(Box<Expression>, Box<Expression>),
+    /// e.g. 'a += 2', holding the arithmetic operator to apply. After type checking, the right
+    /// operand has been converted to the type the operation is performed in, except for shifts:
+    /// these are performed in the promoted type of the left operand, and the right is promoted.
+    CompoundAssignment(BinaryOperator, Box<Expression>, Box<Expression>),
+    Conditional(Box<Expression>, Box<Expression>, Box<Expression>),
+    FunctionCall(String, Vec<Expression>),
+    /// Both forms of 'sizeof' are replaced by a 'Constant' during type checking.
+    SizeOfType(Type),
+    SizeOfExpression(Box<Expression>),
+}
@@ -0,0 +1,39 @@
+//! The calling conventions of the targets: where a caller passes each argument, and where the
+//! called function leaves its result. Each back end lays out its calls and function entries by
+//! asking its convention, which passes the first arguments in registers and the rest in memory.
+
+/// Where an argument is passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location<R> {
+    Register(R),
+    /// A slot of the arguments passed in memory, numbered from zero for the first of them. Each
+    /// target gives the slots its own size and order.
+    Memory(usize),
+}
+
+pub trait CallingConvention {
+    type Register: Copy + 'static;
+
+    /// The registers the first arguments are passed in, in order.
+    const ARGUMENT_REGISTERS: &'static [Self::Register];
+
+    /// The register the result is returned in.
+    const RETURN_REGISTER: Self::Register;
+
+    /// Where the argument with the given index is passed.
+    fn argument(index: usize) -> Location<Self::Register> {
+        match Self::ARGUMENT_REGISTERS.get(index) {
+            Some(register) => Location::Register(*register),
+            None => Location::Memory(index - Self::ARGUMENT_REGISTERS.len()),
+        }
+    }
+
+    /// The number of slots in memory taken by a call with the given number of arguments.
+    fn memory_arguments(count: usize) -> usize {
+        count.saturating_sub(Self::ARGUMENT_REGISTERS.len())
+    }
+}
+
+#[cfg(test)]
+#[path = "./calling_convention_spec.rs"]
+mod calling_convention_spec;
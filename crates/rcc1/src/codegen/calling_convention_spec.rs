@@ -0,0 +1,32 @@
+mod calling_convention_spec {
+    use hamcrest2::prelude::*;
+
+    use crate::codegen::calling_convention::{CallingConvention, Location};
+    use crate::codegen::transputer::{self, Inmos};
+    use crate::codegen::x86_64::{self, SystemV};
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn the_inmos_convention_passes_three_arguments_in_the_evaluation_stack() {
+        assert_that!(Inmos::argument(0), eq(Location::Register(transputer::Register::A)));
+        assert_that!(Inmos::argument(2), eq(Location::Register(transputer::Register::C)));
+        assert_that!(Inmos::argument(3), eq(Location::Memory(0)));
+        assert_that!(Inmos::argument(5), eq(Location::Memory(2)));
+        assert_that!(Inmos::memory_arguments(2), eq(0));
+        assert_that!(Inmos::memory_arguments(5), eq(2));
+        assert_that!(Inmos::RETURN_REGISTER, eq(transputer::Register::A));
+    }
+
+    #[test]
+    fn the_system_v_convention_passes_six_arguments_in_registers() {
+        assert_that!(SystemV::argument(0), eq(Location::Register(x86_64::Register::DI)));
+        assert_that!(SystemV::argument(5), eq(Location::Register(x86_64::Register::R9)));
+        assert_that!(SystemV::argument(6), eq(Location::Memory(0)));
+        assert_that!(SystemV::memory_arguments(8), eq(2));
+        assert_that!(SystemV::RETURN_REGISTER, eq(x86_64::Register::AX));
+    }
+}
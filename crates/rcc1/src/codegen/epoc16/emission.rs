@@ -0,0 +1,280 @@
+//! Writes the assembly program as text in Intel syntax, where the destination operand comes
+//! first, for the assembler chosen with the program's AssemblerSyntax.
+//!
+//! The segments are those of the small memory model: '_TEXT' for code, '_DATA' for initialised
+//! data, and '_BSS' for data initialised to zero, which the startup code clears. The data
+//! segments are grouped as 'DGROUP', so that the variables in both are addressed from DS.
+//!
+//! The assemblers agree on the instructions, but differ in how they declare segments, symbols
+//! and data, how they give the size of a memory operand, and which names they allow. Those are
+//! the Syntax's; the layout of the program and the instructions are written here.
+
+use std::fmt::{Display, Formatter, Write};
+
+use common::assembler_syntax::AssemblerSyntax;
+
+use crate::codegen::epoc16::masm::Masm;
+use crate::codegen::epoc16::nasm::Nasm;
+use crate::codegen::epoc16::{
+    AssemblyType, BinaryOperator, ConditionCode, External, Function, Instruction, Operand, Program,
+    Register, StaticVariable, TopLevel, UnaryOperator,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    Text,
+    Data,
+    Bss,
+}
+
+impl Segment {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Segment::Text => "_TEXT",
+            Segment::Data => "_DATA",
+            Segment::Bss => "_BSS",
+        }
+    }
+}
+
+/// What an assembler's syntax decides. Each method gives whole lines, each ending in a newline,
+/// apart from those giving a symbol or an operand.
+pub trait Syntax {
+    /// The declarations of the processor, the segments and DGROUP.
+    fn prologue(&self) -> String;
+    fn begin_segment(&self, segment: Segment) -> String;
+    fn end_segment(&self, segment: Segment) -> String;
+    /// Ends the module.
+    fn epilogue(&self) -> String;
+
+    /// An IR name, as the assembler allows it.
+    fn symbol(&self, name: &str) -> String;
+    fn public(&self, name: &str) -> String;
+    fn external_function(&self, name: &str) -> String;
+    fn external_variable(&self, name: &str, assembly_type: AssemblyType) -> String;
+    fn begin_function(&self, name: &str) -> String;
+    fn end_function(&self, name: &str) -> String;
+    /// A static variable, initialised or, if it has no initial value, only reserved.
+    fn variable(&self, name: &str, assembly_type: AssemblyType, init: Option<i64>) -> String;
+
+    /// An operand in the stack frame, at an offset from BP.
+    fn stack(&self, assembly_type: AssemblyType, offset: i64) -> String;
+    /// A static variable, by its symbol, or a word within one, at an offset from it.
+    fn data(&self, assembly_type: AssemblyType, name: &str, offset: i64) -> String;
+    /// A conditional jump, which may be to a label out of the 8086's short range.
+    fn conditional_jump(&self, condition: ConditionCode, label: &str) -> String;
+}
+
+/// Writes the program in an assembler's syntax.
+pub fn emit(program: &Program, syntax: AssemblerSyntax) -> String {
+    match syntax {
+        AssemblerSyntax::MASM => Emitter { syntax: &Masm }.program(program),
+        AssemblerSyntax::NASM => Emitter { syntax: &Nasm }.program(program),
+    }
+}
+
+/// The program in the default syntax.
+impl Display for Program {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&emit(self, AssemblerSyntax::default()))
+    }
+}
+
+pub fn register(register: Register, assembly_type: AssemblyType) -> &'static str {
+    use AssemblyType::*;
+    match (register, assembly_type) {
+        (Register::AX, Byte) => "al",
+        (Register::AX, Word | Doubleword) => "ax",
+        (Register::BX, Byte) => "bl",
+        (Register::BX, Word | Doubleword) => "bx",
+        (Register::CX, Byte) => "cl",
+        (Register::CX, Word | Doubleword) => "cx",
+        (Register::DX, Byte) => "dl",
+        (Register::DX, Word | Doubleword) => "dx",
+        (Register::AH, _) => "ah",
+        (Register::BH, _) => "bh",
+        (Register::CH, _) => "ch",
+        (Register::DH, _) => "dh",
+        (Register::SI, _) => "si",
+        (Register::DI, _) => "di",
+        (Register::SP, _) => "sp",
+        (Register::BP, _) => "bp",
+    }
+}
+
+pub fn condition(condition: ConditionCode) -> &'static str {
+    match condition {
+        ConditionCode::E => "e",
+        ConditionCode::NE => "ne",
+        ConditionCode::L => "l",
+        ConditionCode::LE => "le",
+        ConditionCode::G => "g",
+        ConditionCode::GE => "ge",
+        ConditionCode::B => "b",
+        ConditionCode::BE => "be",
+        ConditionCode::A => "a",
+        ConditionCode::AE => "ae",
+    }
+}
+
+struct Emitter<'a> {
+    syntax: &'a dyn Syntax,
+}
+
+impl Emitter<'_> {
+    fn program(&self, program: &Program) -> String {
+        let syntax = self.syntax;
+        let mut out = syntax.prologue();
+
+        let variables: Vec<&StaticVariable> = program
+            .top_level
+            .iter()
+            .filter_map(|top_level| match top_level {
+                TopLevel::StaticVariable(variable) => Some(variable),
+                TopLevel::Function(_) => None,
+            })
+            .collect();
+        let (bss, data): (Vec<&StaticVariable>, Vec<&StaticVariable>) =
+            variables.into_iter().partition(|variable| variable.init == 0);
+        let external_variables: Vec<&External> = program
+            .externals
+            .iter()
+            .filter(|external| matches!(external, External::Variable(..)))
+            .collect();
+        if !data.is_empty() || !external_variables.is_empty() {
+            out += &syntax.begin_segment(Segment::Data);
+            for external in external_variables {
+                out += &self.external(external);
+            }
+            for variable in data {
+                out += &self.variable(variable);
+            }
+            out += &syntax.end_segment(Segment::Data);
+        }
+        if !bss.is_empty() {
+            out += &syntax.begin_segment(Segment::Bss);
+            for variable in bss {
+                out += &self.variable(variable);
+            }
+            out += &syntax.end_segment(Segment::Bss);
+        }
+
+        out += &syntax.begin_segment(Segment::Text);
+        for external in &program.externals {
+            if let External::Function(_) = external {
+                out += &self.external(external);
+            }
+        }
+        for top_level in &program.top_level {
+            if let TopLevel::Function(function) = top_level {
+                out += &self.function(function);
+            }
+        }
+        out += &syntax.end_segment(Segment::Text);
+        out + &syntax.epilogue()
+    }
+
+    fn external(&self, external: &External) -> String {
+        match external {
+            External::Function(name) => self.syntax.external_function(name),
+            External::Variable(name, assembly_type) => {
+                self.syntax.external_variable(name, *assembly_type)
+            }
+        }
+    }
+
+    fn function(&self, function: &Function) -> String {
+        let mut out = String::new();
+        if function.global {
+            out += &self.syntax.public(&function.name);
+        }
+        out += &self.syntax.begin_function(&function.name);
+        out += "\tpush\tbp\n\tmov\tbp, sp\n";
+        for instruction in &function.instructions {
+            self.instruction(&mut out, instruction);
+        }
+        out + &self.syntax.end_function(&function.name)
+    }
+
+    /// Variables initialised to zero are in '_BSS', and reserve their space uninitialised.
+    fn variable(&self, variable: &StaticVariable) -> String {
+        let mut out = String::new();
+        if variable.global {
+            out += &self.syntax.public(&variable.name);
+        }
+        let init = (variable.init != 0).then_some(variable.init);
+        out + &self.syntax.variable(&variable.name, variable.assembly_type, init)
+    }
+
+    /// An operand as it appears in an instruction of the given size.
+    fn operand(&self, operand: &Operand, assembly_type: AssemblyType) -> String {
+        match operand {
+            Operand::Immediate(value) => format!("{}", value),
+            Operand::Register(r) => register(*r, assembly_type).to_owned(),
+            Operand::Pseudo(name) | Operand::PseudoMem(name, _) => {
+                unreachable!("pseudo-register '{}' was not replaced", name)
+            }
+            Operand::Stack(offset) => self.syntax.stack(assembly_type, *offset),
+            Operand::Data(name, offset) => self.syntax.data(assembly_type, name, *offset),
+        }
+    }
+
+    fn instruction(&self, out: &mut String, instruction: &Instruction) {
+        let word = AssemblyType::Word;
+        // Writing to a String cannot fail.
+        let _ = match instruction {
+            Instruction::Mov(t, src, dst) => {
+                writeln!(out, "\tmov\t{}, {}", self.operand(dst, *t), self.operand(src, *t))
+            }
+            Instruction::Cbw => writeln!(out, "\tcbw"),
+            Instruction::Cwd => writeln!(out, "\tcwd"),
+            Instruction::Unary(operator, t, dst) => {
+                let name = match operator {
+                    UnaryOperator::Neg => "neg",
+                    UnaryOperator::Not => "not",
+                };
+                writeln!(out, "\t{}\t{}", name, self.operand(dst, *t))
+            }
+            Instruction::Binary(operator, t, src, dst) => {
+                let name = match operator {
+                    BinaryOperator::Add => "add",
+                    BinaryOperator::Adc => "adc",
+                    BinaryOperator::Sub => "sub",
+                    BinaryOperator::Sbb => "sbb",
+                    BinaryOperator::And => "and",
+                    BinaryOperator::Or => "or",
+                    BinaryOperator::Xor => "xor",
+                    BinaryOperator::Shl => "shl",
+                    BinaryOperator::Sar => "sar",
+                    BinaryOperator::Shr => "shr",
+                    BinaryOperator::Rcl => "rcl",
+                    BinaryOperator::Rcr => "rcr",
+                };
+                // A shift count in a register is always in CL.
+                let src = match (operator, src) {
+                    (
+                        BinaryOperator::Shl | BinaryOperator::Sar | BinaryOperator::Shr,
+                        Operand::Register(r),
+                    ) => register(*r, AssemblyType::Byte).to_owned(),
+                    _ => self.operand(src, *t),
+                };
+                writeln!(out, "\t{}\t{}, {}", name, self.operand(dst, *t), src)
+            }
+            Instruction::Cmp(t, src, dst) => {
+                writeln!(out, "\tcmp\t{}, {}", self.operand(dst, *t), self.operand(src, *t))
+            }
+            Instruction::Imul(src) => writeln!(out, "\timul\t{}", self.operand(src, word)),
+            Instruction::Idiv(src) => writeln!(out, "\tidiv\t{}", self.operand(src, word)),
+            Instruction::Div(src) => writeln!(out, "\tdiv\t{}", self.operand(src, word)),
+            Instruction::Jmp(label) => writeln!(out, "\tjmp\t{}", self.syntax.symbol(label)),
+            Instruction::JmpCC(cc, label) => {
+                write!(out, "{}", self.syntax.conditional_jump(*cc, &self.syntax.symbol(label)))
+            }
+            Instruction::Label(label) => writeln!(out, "{}:", self.syntax.symbol(label)),
+            Instruction::Push(src) => writeln!(out, "\tpush\t{}", self.operand(src, word)),
+            Instruction::Pop(dst) => writeln!(out, "\tpop\t{}", register(*dst, word)),
+            Instruction::Call(name) => writeln!(out, "\tcall\t{}", self.syntax.symbol(name)),
+            Instruction::Ret => writeln!(out, "\tmov\tsp, bp\n\tpop\tbp\n\tret"),
+        };
+    }
+}
@@ -0,0 +1,376 @@
+mod epoc16_spec {
+    use common::assembler_syntax::AssemblerSyntax;
+    use common::target_platform::TargetPlatform;
+    use hamcrest2::prelude::*;
+
+    use crate::codegen::epoc16::simulator::Simulator;
+    use crate::codegen::epoc16::generation::long_runtime_source;
+    use crate::codegen::epoc16::{assembly, generation, register_allocation, stack_frame, Program};
+    use crate::ir;
+    use crate::ir::soft_float::{self, runtime_source};
+    use crate::program_test_helper::ir_of;
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn compiled(input: &str) -> String {
+        compiled_for(input, AssemblerSyntax::MASM)
+    }
+
+    fn compiled_for(input: &str, syntax: AssemblerSyntax) -> String {
+        let (ir, symbols) = ir_of(input, TargetPlatform::EPOC16);
+        assembly(&ir, &symbols, &TargetPlatform::EPOC16.data_model(), syntax, false)
+    }
+
+    /// The allocated assembly program, with or without register allocation, and the value the IR
+    /// interpreter gives for it.
+    /// The program built with the 'long' runtime, and the result of interpreting it.
+    fn allocated(input: &str, allocate_registers: bool) -> (Program, Option<i128>) {
+        let (ir, symbols) = ir_of(&format!("{}\n{}", long_runtime_source(), input), TargetPlatform::EPOC16);
+        let data_model = TargetPlatform::EPOC16.data_model();
+        let mut program = generation::generate(&ir, &symbols, &data_model);
+        if allocate_registers {
+            register_allocation::allocate(&mut program, &symbols, &data_model);
+        }
+        stack_frame::allocate(&mut program, &symbols, &data_model);
+        (program, ir::run(&ir, &symbols, &data_model).ok())
+    }
+
+    /// The expected listings are written by hand to the conventions in the module's documentation.
+    /// They check the back end against those conventions only, not against TopSpeed C itself.
+    const HEADER: &str = "\t.8086
+_TEXT\tSEGMENT\tBYTE PUBLIC 'CODE'
+_TEXT\tENDS
+_DATA\tSEGMENT\tWORD PUBLIC 'DATA'
+_DATA\tENDS
+_BSS\tSEGMENT\tWORD PUBLIC 'BSS'
+_BSS\tENDS
+DGROUP\tGROUP\t_DATA, _BSS
+\tASSUME\tCS:_TEXT, DS:DGROUP, SS:DGROUP
+";
+
+    #[test]
+    fn listing_1_1() {
+        assert_that!(
+            compiled(include_str!("../../listing_1_1.c")),
+            eq(HEADER.to_owned()
+                + "_TEXT\tSEGMENT
+\tPUBLIC\tmain
+main\tPROC\tNEAR
+\tpush\tbp
+\tmov\tbp, sp
+\tmov\tax, 2
+\tmov\tsp, bp
+\tpop\tbp
+\tret
+\tmov\tax, 0
+\tmov\tsp, bp
+\tpop\tbp
+\tret
+main\tENDP
+_TEXT\tENDS
+\tEND
+")
+        );
+    }
+
+    #[test]
+    fn the_first_four_arguments_are_passed_in_registers() {
+        let assembly = compiled(
+            "int five(int a, int b, int c, int d, int e) { return e; }
+int main(void) { return five(1, 2, 3, 4, 5); }",
+        );
+        // The callee stores its register parameters in its frame, and addresses the stacked one
+        // above the saved BP and return address.
+        assert_that!(assembly.contains("\tmov\tWORD PTR [bp-2], ax\n\tmov\tWORD PTR [bp-4], bx\n"), eq(true));
+        assert_that!(assembly.contains("\tmov\tWORD PTR [bp-8], dx\n"), eq(true));
+        assert_that!(assembly.contains("\tmov\tax, WORD PTR [bp+4]\n"), eq(true));
+        // The caller pushes the fifth argument, loads the rest, and removes the pushed one.
+        assert_that!(assembly.contains("\tmov\tax, 5\n\tpush\tax\n"), eq(true));
+        assert_that!(assembly.contains("\tmov\tbx, ax\n"), eq(true));
+        assert_that!(assembly.contains("\tcall\tfive\n\tadd\tsp, 2\n"), eq(true));
+    }
+
+    #[test]
+    fn undefined_symbols_are_external_and_defined_ones_public() {
+        let assembly = compiled(
+            "extern int count;
+int putchar(int c);
+static int hidden = 3;
+int zeroed;
+int main(void) { static char last; last = 1; putchar(count); return hidden + zeroed + last; }",
+        );
+        assert_that!(assembly.contains("_DATA\tSEGMENT\n\tEXTRN\tcount:WORD\nhidden\tDW\t3\n_DATA\tENDS\n"), eq(true));
+        assert_that!(assembly.contains("\tPUBLIC\tzeroed\nzeroed\tDW\t?\n"), eq(true));
+        assert_that!(assembly.contains("last@"), eq(true));
+        assert_that!(assembly.contains("\tPUBLIC\thidden"), eq(false));
+        assert_that!(assembly.contains("_TEXT\tSEGMENT\n\tEXTRN\tputchar:NEAR\n"), eq(true));
+        assert_that!(assembly.contains("\tmov\tax, WORD PTR count\n"), eq(true));
+    }
+
+    #[test]
+    fn static_functions_are_not_public() {
+        let input = "static int helper(int a) { return a; } int main(void) { return helper(1); }";
+        let masm = compiled(input);
+        assert_that!(masm.contains("helper\tPROC\tNEAR\n"), eq(true));
+        assert_that!(masm.contains("\tPUBLIC\thelper"), eq(false));
+        assert_that!(masm.contains("\tPUBLIC\tmain\nmain\tPROC\tNEAR\n"), eq(true));
+        let nasm = compiled_for(input, AssemblerSyntax::NASM);
+        assert_that!(nasm.contains("\nhelper:\n"), eq(true));
+        assert_that!(nasm.contains("\tglobal\thelper"), eq(false));
+        assert_that!(nasm.contains("\tglobal\tmain\nmain:\n"), eq(true));
+    }
+
+    #[test]
+    fn comparisons_set_their_result_with_a_jump() {
+        let assembly = compiled("int less(unsigned a, unsigned b) { return a < b; }");
+        assert_that!(assembly.contains("\tcmp\tax, WORD PTR [bp-4]\n\tmov\tax, 1\n\tjb\tset@"), eq(true));
+        assert_that!(assembly.contains("\txor\tax, ax\nset@"), eq(true));
+    }
+
+    #[test]
+    fn listing_1_1_for_nasm() {
+        assert_that!(
+            compiled_for(include_str!("../../listing_1_1.c"), AssemblerSyntax::NASM),
+            eq("\tcpu\t8086
+segment\t_TEXT\tpublic class=CODE align=1
+segment\t_DATA\tpublic class=DATA align=2
+segment\t_BSS\tpublic class=BSS align=2
+group\tDGROUP\t_DATA _BSS
+segment\t_TEXT
+\tglobal\tmain
+main:
+\tpush\tbp
+\tmov\tbp, sp
+\tmov\tax, 2
+\tmov\tsp, bp
+\tpop\tbp
+\tret
+\tmov\tax, 0
+\tmov\tsp, bp
+\tpop\tbp
+\tret
+"
+            .to_owned())
+        );
+    }
+
+    #[test]
+    fn nasm_declares_symbols_and_sizes_memory_operands() {
+        let assembly = compiled_for(
+            "extern int count;
+int putchar(int c);
+static char hidden = 3;
+int zeroed;
+int main(void) { putchar(count); return hidden + zeroed; }",
+            AssemblerSyntax::NASM,
+        );
+        assert_that!(assembly.contains("segment\t_DATA\n\textern\tcount\nhidden\tdb\t3\n"), eq(true));
+        assert_that!(assembly.contains("segment\t_BSS\n\tglobal\tzeroed\nzeroed\tresw\t1\n"), eq(true));
+        assert_that!(assembly.contains("segment\t_TEXT\n\textern\tputchar\n"), eq(true));
+        assert_that!(assembly.contains("\tmov\tax, word [count]\n"), eq(true));
+        assert_that!(assembly.contains("\tmov\tal, byte [hidden]\n"), eq(true));
+        assert_that!(assembly.contains(" PTR "), eq(false));
+    }
+
+    #[test]
+    fn labels_cannot_clash_with_renamed_statics() {
+        let assembly = compiled("int main(void) { static int set = 3; int a = 2; return (a < set) + set; }");
+        assert_that!(assembly.contains("\nset@1\tDW\t3\n"), eq(true));
+        assert_that!(assembly.contains("\tjl\tset@@1\n\txor\tax, ax\nset@@1:\n"), eq(true));
+        assert_that!(assembly.contains("\nset@1:"), eq(false));
+    }
+
+    #[test]
+    fn nasm_conditional_jumps_reach_any_label() {
+        let assembly = compiled_for("int less(unsigned a, unsigned b) { return a < b; }", AssemblerSyntax::NASM);
+        assert_that!(assembly.contains("\tmov\tax, 1\n\tjae\tshort $+5\n\tjmp\tnear set..1\n"), eq(true));
+        assert_that!(assembly.contains("\txor\tax, ax\nset..1:\n"), eq(true));
+    }
+
+    #[test]
+    fn long_addition_carries_into_the_high_word() {
+        let assembly = compiled("long total; long add(long a, long b) { total = a + b; return total; }");
+        // Both 'long' parameters are pushed, the first lowest, leaving no register parameters.
+        assert_that!(
+            assembly.contains(
+                "\tmov\tax, WORD PTR [bp+4]\n\tmov\tdx, WORD PTR [bp+6]\n\tadd\tax, WORD PTR [bp+8]\n\tadc\tdx, WORD PTR [bp+10]\n"
+            ),
+            eq(true)
+        );
+        assert_that!(assembly.contains("\tmov\tWORD PTR total, ax\n\tmov\tWORD PTR total+2, dx\n"), eq(true));
+        assert_that!(assembly.contains("total\tDD\t?\n"), eq(true));
+    }
+
+    #[test]
+    fn long_multiplication_calls_the_runtime() {
+        let assembly = compiled("long scale(long a) { return a * 1000L; }");
+        // The operands are stacked as any other function's, the second pushed first, high word
+        // before low, and removed by the caller.
+        assert_that!(
+            assembly.contains(
+                "\tmov\tax, 0\n\tpush\tax\n\tmov\tax, 1000\n\tpush\tax\n\tmov\tax, WORD PTR [bp+6]\n\tpush\tax\n\tmov\tax, WORD PTR [bp+4]\n\tpush\tax\n\tcall\t__mulsi3\n\tadd\tsp, 8\n"
+            ),
+            eq(true)
+        );
+        assert_that!(assembly.contains("\tEXTRN\t__mulsi3:NEAR\n"), eq(true));
+    }
+
+    #[test]
+    fn long_division_and_remainder_call_the_signed_or_unsigned_helper() {
+        let assembly = compiled(
+            "int main(void) { long a = 100000; unsigned long b = 300000ul; return (int) (a / 7 + a % 7 + b / 7ul + b % 7ul); }",
+        );
+        for helper in ["__divsi3", "__modsi3", "__udivsi3", "__umodsi3"] {
+            assert_that!(assembly.contains(&format!("\tcall\t{}\n", helper)), eq(true));
+        }
+    }
+
+    #[test]
+    fn long_shifts_loop_a_bit_at_a_time() {
+        let assembly = compiled("long shift(long a, int n) { return a << n; }");
+        assert_that!(
+            assembly.contains("\tcmp\tcx, 0\n\tje\tshifted@@2\nshift@@1:\n\tshl\tax, 1\n\trcl\tdx, 1\n\tsub\tcx, 1\n\tjne\tshift@@1\nshifted@@2:\n"),
+            eq(true)
+        );
+    }
+
+    #[test]
+    fn long_comparisons_subtract_with_borrow() {
+        let assembly = compiled("int greater(long a, long b) { return a > b; }");
+        // 'a > b' is tested as 'b < a', so as not to need the zero flag.
+        assert_that!(
+            assembly.contains("\tmov\tax, WORD PTR [bp+8]\n\tmov\tdx, WORD PTR [bp+10]\n\tsub\tax, WORD PTR [bp+4]\n\tsbb\tdx, WORD PTR [bp+6]\n\tmov\tax, 1\n\tjl\tset@@1\n"),
+            eq(true)
+        );
+    }
+
+    #[test]
+    fn nasm_addresses_the_high_word_of_a_static_long() {
+        let assembly = compiled_for("long total = 70000; int main(void) { return (int) (total >> 16); }", AssemblerSyntax::NASM);
+        assert_that!(assembly.contains("total\tdd\t70000\n"), eq(true));
+        assert_that!(assembly.contains("\tmov\tdx, word [total+2]\n"), eq(true));
+    }
+
+    /// Programs whose results on the simulated 8086 must agree with the IR interpreter's, with
+    /// EPOC16's 16-bit int.
+    const SIMULATED_PROGRAMS: &[&str] = &[
+        include_str!("../../listing_1_1.c"),
+        "int main(void) { int a = 7, b = -3; return a * b + a / b - a % b; }",
+        "int main(void) { unsigned int u = 60000u; return u / 3u % 1000 + (u > 5u) + (u >> 15) + (5u < u) * 10; }",
+        "int main(void) { unsigned int u = 60000u, v = 7u; return u % v * 100 + (u <= v) + (v >= u) * 2 + (u >= u) * 4; }",
+        "int main(void) { int n = -1000; unsigned int big = 50000u; return (n >> 3) + (-16 >> 2) * 100 + (int) (big >> 12); }",
+        "int main(void) { char c = 200; signed char s = c; unsigned char uc = s; short sh = uc; return c + s * 100 + uc * 3 + sh; }",
+        "int main(void) { int x = 10; x <<= 3; x |= 5; x &= ~1; x -= 7; x /= 2; x %= 100; x ^= 3; return x >> 1; }",
+        "int main(void) { int count = 3; unsigned shifted = 1u << count * 4; return (int) (shifted >> 9) + (-16 >> count == -2); }",
+        "int eight(int a, int b, int c, int d, int e, int f, char g, unsigned h) { return a - b + c - d + e - f + g - (int) h; }
+int main(void) { return eight(10, 1, 20, 2, 30, 3, -4, 5u) * 100 + eight(1, 1, 1, 1, 1, 1, 1, 1); }",
+        "int fib(int n) { return n < 2 ? n : fib(n - 1) + fib(n - 2); } int main(void) { return fib(15); }",
+        "int counter = 5;
+static int total;
+static signed char last;
+static unsigned short wide = 65535;
+int bump(short by) { static int calls; calls++; total += by; last = by; wide = wide + by; return calls; }
+int main(void) { for (int i = 0; i < 10; i++) { bump(i * 30); } return total + bump(-1) + counter + last * 100 + wide; }",
+        "int classify(unsigned char c) {
+    switch (c) { case 90 + 7: return 1; case 200: return 2; default: return 3; case 0: break; }
+    return 4;
+}
+int main(void) { return classify(97) * 100 + classify(200) * 10 + classify(0) + (classify(1) == 3); }",
+        "int main(void) { int i = 0, odd = 0; do { if (i % 2) { odd++; continue; } if (i > 20) break; } while (++i < 100); return odd + !i + (i && odd) * 100 + (0 || i) * 1000; }",
+        "int main(void) { int a = -32767 - 1; unsigned int b = a; return (a < 0) + (b > 0) * 2 + (-a == a) * 4 + (~a == 32767) * 8 + (a != 3) * 16; }",
+        "int main(void) { int a = 3, b = -5, c = 7, d = 11, e = -13, f = 17, g = 19, h = -23;
+    return (a * b - c * d) * (e * f - g * h) - ((a - b) * (c - d) - (e - f) * (g - h)); }",
+        "int main(void) { unsigned int u = 60000u, v = 123u, w = 7u;
+    return (int) ((u - v * w) / (v + w * w) % (w * 3u + 1u)) + ((u ^ v) < (v - w * u)); }",
+        "int main(void) { int n = -10000, k = 2; return (n * 3 - k) >> (k * k - 1) >> (n > 0 ? 1 : k - 1); }",
+        "static short s = -300; static signed char c = -7;
+int main(void) { return (s - c) * (c - s) - (s * c - (c - s)) * ((s + 1) - (c + 2)); }",
+        "int three(int a, int b, int c) { return a * 100 + b * 10 + c; }
+int main(void) { int p = 3, q = 5; return three((p - q) * (q - p) - (p * q - q), (p + q) * (p - q), p - q) + three(1, 2, 3); }",
+        "int main(void) { char a = 1, b = 2, c = 3, d = 4, e = 5, f = 6, g = 7, h = 8, i = 9, j = 10;
+    int x = 1000, y = -7;
+    for (int n = 0; n < 3; n++) { a += b; b += c; c += d; d += e; e += f; f += g; g += h; h += i; i += j; j += a; x += y * n; }
+    return a + b + c + d + e + f + g + h + i + j + x + y; }",
+        "int twice(int n) { return n * 2; }
+int main(void) { int a = 5, b = 6, c = 7; char d = -3; unsigned char e = 250; int sum = 0;
+    for (int i = 0; i < 4; i++) { sum += twice(a + i) + b * c - d + e; a = b; b = c; c = sum % 11; }
+    return sum + a + b + c + d + e; }",
+        "int main(void) { int n = 1234; unsigned u = 40000u; int count = 0;
+    while (n > 0) { u = u / (unsigned) (n % 7 + 1) + u % 13u; count += n & 3; n >>= count % 3 + 1; }
+    return (int) u + count; }",
+        "int main(void) { long a = 100000, b = -70000; long c = a + b, d = a - b, e = -b, f = ~a;
+    return (int) (c / 10) + (int) (d >> 8) + (int) (e % 1000) + (int) (f & 0x7fff) + (a > b) + (b < a) * 2; }",
+        "int main(void) { long a = 123456, b = -789; unsigned long u = 4000000000ul, v = 65537ul;
+    return (int) (a * b / 1000) + (int) (a % b) + (int) (u / v) + (int) (u % v) + (int) (u >> 20) + (int) ((u << 3) >> 24); }",
+        "int main(void) { long a = -100003, b = -7; unsigned long big = 0xfffffff0ul;
+    return (int) (a / b) + (int) (a % b) * 100 + (int) (a % 7) * 1000 + (int) (-a / 7 % 100) + (int) (big * big); }",
+        "long total; static long counter = -5;
+long accumulate(int by, long times) { total += by * times; counter++; return total; }
+int main(void) { for (int i = 1; i <= 20; i++) { accumulate(i, 10000L + i); } return (int) (accumulate(0, 0) >> 10) + (int) counter; }",
+        "int order(long a, long b) { return (a < b) + (a <= b) * 2 + (a > b) * 4 + (a >= b) * 8 + (a == b) * 16 + (a != b) * 32; }
+int uorder(unsigned long a, unsigned long b) { return (a < b) + (a <= b) * 2 + (a > b) * 4 + (a >= b) * 8; }
+int main(void) { return order(-1, 1) + order(65536, 65535) * 64 + order(7, 7) * 4096
+    + uorder(4294967295ul, 1ul) * 3 + uorder(65536ul, 65536ul) + !(long) 0 * 100 + !(long) 65536 * 1000; }",
+        "int main(void) { char c = -3; unsigned char uc = 250; int i = -30000; unsigned u = 60000u;
+    long a = c, b = uc, d = i, e = u; unsigned long f = i;
+    int back = (int) (a * 100000 / 100000);
+    return (int) ((a + b + d + e) / 7) + (int) (f >> 17) + back + (char) e + (int) (e >> 1); }",
+        "long fib(long n) { return n < 2 ? n : fib(n - 1) + fib(n - 2); }
+long power(long base, int exponent) { long result = 1; while (exponent-- > 0) { result *= base; } return result; }
+int main(void) { long f = fib(17); if (f) { return (int) (f % 10000) + (int) (power(3, 19) / 1000000) + (int) (-power(-2, 31) >> 28); } return 0; }",
+        "int main(void) { long x = 1; int n = 0; long y = -1000000;
+    for (long i = 0; i < 40; i++) { x = x << 1 | (i & 1); n += (int) (x >> 30); }
+    return n + (int) (x & 0xff) + (int) (y >> (long) 4) + (int) ((y << 0) / -1000); }",
+    ];
+
+    fn simulate(allocate_registers: bool) {
+        for (index, program) in SIMULATED_PROGRAMS.iter().enumerate() {
+            let (assembly, interpreted) = allocated(program, allocate_registers);
+            let expected = interpreted.expect("the program runs in the interpreter");
+            let mut simulator = Simulator::new(&assembly);
+            let actual = simulator
+                .run()
+                .unwrap_or_else(|e| panic!("program {} failed: {}\n{}", index, e, assembly));
+            assert_that!((index, actual as i128), eq((index, expected)));
+            assert_that!(simulator.stack_low % 2, eq(0));
+        }
+    }
+
+    #[test]
+    fn simulated_results_agree_with_the_interpreter() {
+        simulate(false);
+    }
+
+    #[test]
+    fn simulated_results_with_registers_allocated_agree_with_the_interpreter() {
+        simulate(true);
+    }
+
+    /// A program using floating point, built with the soft-float runtime.
+    const SOFT_FLOAT_PROGRAM: &str = "double area(float width, double height) { return width * height; }
+int main(void) { float w = 2.5f; double h = 0.1, sum = 0; int i;
+    for (i = 0; i < 10; i++) sum = sum + area(w, h * i);
+    return (int) (sum * 100) + (sum > 11.0) * 1000 + (int) -(w / 3 * 7); }";
+
+    #[test]
+    fn soft_float_results_agree_with_the_interpreter() {
+        let data_model = TargetPlatform::EPOC16.data_model();
+        let (ir, symbols) = ir_of(SOFT_FLOAT_PROGRAM, TargetPlatform::EPOC16);
+        let expected = ir::run(&ir, &symbols, &data_model).expect("the program runs in the interpreter");
+        let (ir, mut symbols) =
+            ir_of(&format!("{}\n{}\n{}", long_runtime_source(), runtime_source(), SOFT_FLOAT_PROGRAM), TargetPlatform::EPOC16);
+        let ir = soft_float::lower(&ir, &mut symbols, &data_model);
+        for allocate_registers in [false, true] {
+            let mut program = generation::generate(&ir, &symbols, &data_model);
+            if allocate_registers {
+                register_allocation::allocate(&mut program, &symbols, &data_model);
+            }
+            stack_frame::allocate(&mut program, &symbols, &data_model);
+            let actual = Simulator::new(&program).run().unwrap_or_else(|e| panic!("{}", e));
+            assert_that!((allocate_registers, actual as i128), eq((allocate_registers, expected)));
+        }
+    }
+}
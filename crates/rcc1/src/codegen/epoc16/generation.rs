@@ -0,0 +1,685 @@
+//! Translates the IR into 8086 instructions operating on pseudo-registers, working in the
+//! accumulator: each operation loads its first operand into AX, or AL for a byte, operates on it
+//! with the second operand, which may be in memory or an immediate, and stores the result.
+//!
+//! The 8086 fixes some registers' roles, which generation keeps to: multiplication and division
+//! work on DX and AX, shift counts are in CL, and only AL can be sign extended, with 'cbw'. There
+//! are no conditional moves or 'setcc', so comparisons set their result with a conditional jump.
+//!
+//! A 'long' is worked on in DX and AX, its high and low words, which are addressed separately in
+//! memory. Addition and subtraction carry from the low words into the high, and a comparison
+//! subtracts the same way, leaving the flags as a 32-bit 'cmp' would but for the zero flag, which
+//! only equality needs, and so tests separately. Shifts move the pair by one bit at a time through
+//! the carry, in a loop. Multiplication, division and remainder call the 'long' runtime.
+
+use std::collections::{HashMap, HashSet};
+
+use common::data_model::DataModel;
+
+use crate::ast::{Constant, Type};
+use crate::codegen::calling_convention::Location;
+use crate::codegen::epoc16::{
+    AssemblyType, BinaryOperator, ConditionCode, External, Function, Instruction, Operand, Program,
+    Register, StaticVariable, TopLevel, TopSpeed, UnaryOperator,
+};
+use crate::ir;
+use crate::semantic::symbol_table::{IdentifierAttributes, SymbolTable};
+
+const AX: Operand = Operand::Register(Register::AX);
+const CX: Operand = Operand::Register(Register::CX);
+const DX: Operand = Operand::Register(Register::DX);
+
+/// The bytes between BP and the first stacked parameter: the saved BP and the near return
+/// address.
+const PARAMETERS_OFFSET: i64 = 4;
+
+/// The routines of rcc's own runtime, 'runtime/long.c', for the 32-bit operations the 8086 has
+/// no instructions for. They are named as libgcc's, and called as any other function, as the
+/// names and convention of TopSpeed's runtime helpers are not known. That is not what was asked
+/// for, which was names compatible with TopSpeed's runtime; see the README. Should TopSpeed's be
+/// supplied, they replace these, and the calls must follow their convention.
+pub const LONG_MULTIPLY: &str = "__mulsi3";
+pub const LONG_DIVIDE: &str = "__divsi3";
+pub const UNSIGNED_LONG_DIVIDE: &str = "__udivsi3";
+pub const LONG_REMAINDER: &str = "__modsi3";
+pub const UNSIGNED_LONG_REMAINDER: &str = "__umodsi3";
+
+/// The 'long' runtime's source as rcc1 sees it.
+#[cfg(test)]
+pub(crate) fn long_runtime_source() -> String {
+    crate::ir::soft_float::preprocessed(include_str!("../../../runtime/long.c"))
+}
+
+struct Generator<'a> {
+    symbols: &'a SymbolTable,
+    data_model: &'a DataModel,
+    instructions: Vec<Instruction>,
+    /// The parameters passed on the stack, which are addressed where the caller pushed them.
+    stacked: HashMap<String, Operand>,
+    /// Numbers the labels that comparisons jump to.
+    labels: &'a mut usize,
+}
+
+pub fn generate(program: &ir::Program, symbols: &SymbolTable, data_model: &DataModel) -> Program {
+    let mut labels = 0;
+    let top_level: Vec<TopLevel> = program
+        .top_level
+        .iter()
+        .map(|top_level| match top_level {
+            ir::TopLevel::Function(function) => {
+                let mut generator = Generator {
+                    symbols,
+                    data_model,
+                    instructions: vec![],
+                    stacked: HashMap::new(),
+                    labels: &mut labels,
+                };
+                TopLevel::Function(generator.function(function))
+            }
+            ir::TopLevel::StaticVariable(variable) => TopLevel::StaticVariable(StaticVariable {
+                name: variable.name.clone(),
+                global: variable.global,
+                assembly_type: AssemblyType::of(&variable.variable_type, data_model)
+                    .expect("wide variables are rejected before generation"),
+                init: variable.init.value as i64,
+            }),
+        })
+        .collect();
+    let externals = externals(&top_level, symbols, data_model);
+    Program { top_level, externals }
+}
+
+/// The operands an instruction reads or writes.
+fn operands(instruction: &Instruction) -> Vec<&Operand> {
+    match instruction {
+        Instruction::Mov(_, src, dst)
+        | Instruction::Binary(_, _, src, dst)
+        | Instruction::Cmp(_, src, dst) => vec![src, dst],
+        Instruction::Unary(_, _, operand)
+        | Instruction::Imul(operand)
+        | Instruction::Idiv(operand)
+        | Instruction::Div(operand)
+        | Instruction::Push(operand) => vec![operand],
+        Instruction::Cbw
+        | Instruction::Cwd
+        | Instruction::Jmp(_)
+        | Instruction::JmpCC(_, _)
+        | Instruction::Label(_)
+        | Instruction::Pop(_)
+        | Instruction::Call(_)
+        | Instruction::Ret => vec![],
+    }
+}
+
+/// The functions called, including the 'long' runtime's routines, and the static variables used,
+/// but not defined in the program, in the order they are first used.
+fn externals(top_level: &[TopLevel], symbols: &SymbolTable, data_model: &DataModel) -> Vec<External> {
+    let defined: HashSet<&str> = top_level
+        .iter()
+        .map(|top_level| match top_level {
+            TopLevel::Function(function) => function.name.as_str(),
+            TopLevel::StaticVariable(variable) => variable.name.as_str(),
+        })
+        .collect();
+    let mut externals = vec![];
+    for top_level in top_level {
+        let TopLevel::Function(function) = top_level else {
+            continue;
+        };
+        for instruction in &function.instructions {
+            let called = match instruction {
+                Instruction::Call(name) => Some(External::Function(name.clone())),
+                _ => None,
+            };
+            let variables = operands(instruction).into_iter().filter_map(|operand| match operand {
+                Operand::Pseudo(name) | Operand::PseudoMem(name, _) => symbols
+                    .get(name)
+                    .filter(|symbol| matches!(symbol.attributes, IdentifierAttributes::Static { .. }))
+                    .and_then(|symbol| AssemblyType::of(&symbol.symbol_type, data_model))
+                    .map(|assembly_type| External::Variable(name.clone(), assembly_type)),
+                _ => None,
+            });
+            for external in called.into_iter().chain(variables) {
+                let name = match &external {
+                    External::Function(name) | External::Variable(name, _) => name,
+                };
+                if !defined.contains(name.as_str()) && !externals.contains(&external) {
+                    externals.push(external);
+                }
+            }
+        }
+    }
+    externals
+}
+
+impl Generator<'_> {
+    fn emit(&mut self, instruction: Instruction) {
+        self.instructions.push(instruction);
+    }
+
+    fn value_type(&self, value: &ir::Value) -> Type {
+        match value {
+            ir::Value::Constant(constant) => constant.constant_type.clone(),
+            ir::Value::Var(name) => self
+                .symbols
+                .get(name)
+                .expect("every variable is in the symbol table")
+                .symbol_type
+                .clone(),
+        }
+    }
+
+    fn assembly_type(&self, value: &ir::Value) -> AssemblyType {
+        AssemblyType::of(&self.value_type(value), self.data_model)
+            .expect("every value is at most 32 bits wide")
+    }
+
+    fn is_signed(&self, value: &ir::Value) -> bool {
+        self.value_type(value).is_signed(self.data_model)
+    }
+
+    fn is_long(&self, value: &ir::Value) -> bool {
+        self.assembly_type(value) == AssemblyType::Doubleword
+    }
+
+    fn operand(&self, value: &ir::Value) -> Operand {
+        match value {
+            ir::Value::Constant(constant) => Operand::Immediate(constant.value as i64),
+            ir::Value::Var(name) => match self.stacked.get(name) {
+                Some(operand) => operand.clone(),
+                None => Operand::Pseudo(name.clone()),
+            },
+        }
+    }
+
+    /// A word of a 'long': the low word at offset 0, or the high word at offset 2.
+    fn word(&self, value: &ir::Value, offset: i64) -> Operand {
+        match value {
+            ir::Value::Constant(constant) => {
+                Operand::Immediate((constant.value as i64 >> (8 * offset)) & 0xffff)
+            }
+            ir::Value::Var(name) => match self.operand(value) {
+                Operand::Stack(base) => Operand::Stack(base + offset),
+                _ => Operand::PseudoMem(name.clone(), offset),
+            },
+        }
+    }
+
+    fn label(&mut self, purpose: &str) -> String {
+        *self.labels += 1;
+        ir::generated_name(purpose, *self.labels)
+    }
+
+    /// Loads a value into AX, or AL if it is a byte, or DX and AX if it is a 'long'.
+    fn load(&mut self, value: &ir::Value) {
+        match self.assembly_type(value) {
+            AssemblyType::Doubleword => {
+                self.emit(Instruction::Mov(AssemblyType::Word, self.word(value, 0), AX));
+                self.emit(Instruction::Mov(AssemblyType::Word, self.word(value, 2), DX));
+            }
+            assembly_type => self.emit(Instruction::Mov(assembly_type, self.operand(value), AX)),
+        }
+    }
+
+    /// Stores AX, or AL, or DX and AX, into a variable.
+    fn store(&mut self, dst: &ir::Value) {
+        match self.assembly_type(dst) {
+            AssemblyType::Doubleword => {
+                self.emit(Instruction::Mov(AssemblyType::Word, AX, self.word(dst, 0)));
+                self.emit(Instruction::Mov(AssemblyType::Word, DX, self.word(dst, 2)));
+            }
+            assembly_type => self.emit(Instruction::Mov(assembly_type, AX, self.operand(dst))),
+        }
+    }
+
+    /// Loads a value into AX, extended to a word, or a 'long' into DX and AX.
+    fn load_word(&mut self, value: &ir::Value) {
+        match self.assembly_type(value) {
+            AssemblyType::Word | AssemblyType::Doubleword => self.load(value),
+            AssemblyType::Byte => {
+                self.load(value);
+                self.extend(self.is_signed(value));
+            }
+        }
+    }
+
+    /// Pushes an argument, extended to a word, or a 'long' as two words with the high one first.
+    fn push(&mut self, value: &ir::Value) {
+        if self.is_long(value) {
+            for offset in [2, 0] {
+                self.emit(Instruction::Mov(AssemblyType::Word, self.word(value, offset), AX));
+                self.emit(Instruction::Push(AX));
+            }
+        } else {
+            self.load_word(value);
+            self.emit(Instruction::Push(AX));
+        }
+    }
+
+    /// Extends AL into AX.
+    fn extend(&mut self, signed: bool) {
+        if signed {
+            self.emit(Instruction::Cbw);
+        } else {
+            self.emit(Instruction::Mov(
+                AssemblyType::Byte,
+                Operand::Immediate(0),
+                Operand::Register(Register::AH),
+            ));
+        }
+    }
+
+    /// The register parameters arrive in AX, BX, CX and DX, and are stored in their
+    /// pseudo-registers. The stacked ones are left where they are, above the saved BP and the
+    /// return address.
+    fn function(&mut self, function: &ir::Function) -> Function {
+        let assembly_types: Vec<AssemblyType> = function
+            .params
+            .iter()
+            .map(|param| self.assembly_type(&ir::Value::Var(param.clone())))
+            .collect();
+        let locations = TopSpeed::locations(&assembly_types);
+        for ((param, assembly_type), location) in function.params.iter().zip(assembly_types).zip(locations) {
+            match location {
+                Location::Register(register) => self.emit(Instruction::Mov(
+                    assembly_type,
+                    Operand::Register(register),
+                    Operand::Pseudo(param.clone()),
+                )),
+                Location::Memory(slot) => {
+                    let offset = PARAMETERS_OFFSET + 2 * slot as i64;
+                    self.stacked.insert(param.clone(), Operand::Stack(offset));
+                }
+            }
+        }
+        for instruction in &function.body {
+            self.instruction(instruction);
+        }
+        Function {
+            name: function.name.clone(),
+            global: function.global,
+            instructions: std::mem::take(&mut self.instructions),
+            callee_saved: vec![],
+        }
+    }
+
+    fn instruction(&mut self, instruction: &ir::Instruction) {
+        match instruction {
+            ir::Instruction::IntToFloat { .. }
+            | ir::Instruction::UIntToFloat { .. }
+            | ir::Instruction::FloatToInt { .. }
+            | ir::Instruction::FloatToUInt { .. }
+            | ir::Instruction::FloatExtend { .. }
+            | ir::Instruction::FloatTruncate { .. } => {
+                unreachable!("soft-float lowering replaces floating conversions with calls")
+            }
+            // The value is returned in AX, extended to a word, or in DX and AX.
+            ir::Instruction::Return(value) => {
+                if let Some(value) = value {
+                    self.load_word(value);
+                }
+                self.emit(Instruction::Ret);
+            }
+            ir::Instruction::SignExtend { src, dst } | ir::Instruction::ZeroExtend { src, dst } => {
+                let signed = matches!(instruction, ir::Instruction::SignExtend { .. });
+                self.load(src);
+                if self.assembly_type(src) == AssemblyType::Byte && self.assembly_type(dst) != AssemblyType::Byte {
+                    self.extend(signed);
+                }
+                if self.is_long(dst) && !self.is_long(src) {
+                    if signed {
+                        self.emit(Instruction::Cwd);
+                    } else {
+                        self.emit(Instruction::Mov(AssemblyType::Word, Operand::Immediate(0), DX));
+                    }
+                }
+                self.store(dst);
+            }
+            // Bytes are the low halves of words, and words the low halves of 'long's, so a value
+            // is truncated by storing the low part of it.
+            ir::Instruction::Truncate { src, dst } | ir::Instruction::Copy { src, dst } => {
+                let dst_type = self.assembly_type(dst);
+                match src {
+                    ir::Value::Constant(constant) => {
+                        let constant = ir::Value::Constant(constant.convert(&self.value_type(dst), self.data_model));
+                        if dst_type == AssemblyType::Doubleword {
+                            for offset in [0, 2] {
+                                self.emit(Instruction::Mov(
+                                    AssemblyType::Word,
+                                    self.word(&constant, offset),
+                                    self.word(dst, offset),
+                                ));
+                            }
+                        } else {
+                            self.emit(Instruction::Mov(dst_type, self.operand(&constant), self.operand(dst)));
+                        }
+                    }
+                    ir::Value::Var(_) if self.is_long(src) && dst_type != AssemblyType::Doubleword => {
+                        self.emit(Instruction::Mov(dst_type, self.word(src, 0), AX));
+                        self.store(dst);
+                    }
+                    ir::Value::Var(_) => {
+                        self.load(src);
+                        self.store(dst);
+                    }
+                }
+            }
+            ir::Instruction::Unary { operator, src, dst } => {
+                match operator {
+                    // The high word is negated, less one if the low word borrows.
+                    ir::UnaryOperator::Negate if self.is_long(src) => {
+                        self.load(src);
+                        self.emit(Instruction::Unary(UnaryOperator::Neg, AssemblyType::Word, DX));
+                        self.emit(Instruction::Unary(UnaryOperator::Neg, AssemblyType::Word, AX));
+                        self.emit(Instruction::Binary(BinaryOperator::Sbb, AssemblyType::Word, Operand::Immediate(0), DX));
+                    }
+                    ir::UnaryOperator::Complement if self.is_long(src) => {
+                        self.load(src);
+                        self.emit(Instruction::Unary(UnaryOperator::Not, AssemblyType::Word, AX));
+                        self.emit(Instruction::Unary(UnaryOperator::Not, AssemblyType::Word, DX));
+                    }
+                    ir::UnaryOperator::Negate => {
+                        self.load(src);
+                        self.emit(Instruction::Unary(UnaryOperator::Neg, AssemblyType::Word, AX));
+                    }
+                    ir::UnaryOperator::Complement => {
+                        self.load(src);
+                        self.emit(Instruction::Unary(UnaryOperator::Not, AssemblyType::Word, AX));
+                    }
+                    ir::UnaryOperator::Not => {
+                        let zero = ir::Value::Constant(Constant {
+                            value: 0,
+                            constant_type: self.value_type(src),
+                        });
+                        self.comparison(ConditionCode::E, src, &zero);
+                    }
+                }
+                self.store(dst);
+            }
+            ir::Instruction::Binary {
+                operator,
+                src1,
+                src2,
+                dst,
+            } => {
+                self.binary(*operator, src1, src2);
+                self.store(dst);
+            }
+            ir::Instruction::Jump(label) => self.emit(Instruction::Jmp(label.clone())),
+            ir::Instruction::JumpIfZero(condition, label) => {
+                self.jump_if(condition, ConditionCode::E, label)
+            }
+            ir::Instruction::JumpIfNotZero(condition, label) => {
+                self.jump_if(condition, ConditionCode::NE, label)
+            }
+            ir::Instruction::Label(label) => self.emit(Instruction::Label(label.clone())),
+            ir::Instruction::FunctionCall { name, args, dst } => {
+                self.function_call(name, args, dst.as_ref())
+            }
+        }
+    }
+
+    /// Jumps if a value compares with zero as given. A constant condition is known now, and a
+    /// 'long' is zero if the OR of its words is.
+    fn jump_if(&mut self, condition: &ir::Value, code: ConditionCode, label: &str) {
+        match condition {
+            ir::Value::Constant(constant) => {
+                if (constant.value == 0) == (code == ConditionCode::E) {
+                    self.emit(Instruction::Jmp(label.to_owned()));
+                }
+            }
+            ir::Value::Var(_) if self.is_long(condition) => {
+                self.emit(Instruction::Mov(AssemblyType::Word, self.word(condition, 0), AX));
+                self.emit(Instruction::Binary(BinaryOperator::Or, AssemblyType::Word, self.word(condition, 2), AX));
+                self.emit(Instruction::JmpCC(code, label.to_owned()));
+            }
+            ir::Value::Var(_) => {
+                self.emit(Instruction::Cmp(
+                    self.assembly_type(condition),
+                    Operand::Immediate(0),
+                    self.operand(condition),
+                ));
+                self.emit(Instruction::JmpCC(code, label.to_owned()));
+            }
+        }
+    }
+
+    /// Leaves the result of a binary operation in AX, or DX and AX.
+    fn binary(&mut self, operator: ir::BinaryOperator, src1: &ir::Value, src2: &ir::Value) {
+        let signed = self.is_signed(src1);
+        if self.is_long(src1) && !is_comparison(operator) {
+            self.long_binary(operator, signed, src1, src2);
+            return;
+        }
+        let operation = match operator {
+            ir::BinaryOperator::Add => Some(BinaryOperator::Add),
+            ir::BinaryOperator::Subtract => Some(BinaryOperator::Sub),
+            ir::BinaryOperator::BitwiseAnd => Some(BinaryOperator::And),
+            ir::BinaryOperator::BitwiseOr => Some(BinaryOperator::Or),
+            ir::BinaryOperator::BitwiseXor => Some(BinaryOperator::Xor),
+            _ => None,
+        };
+        if let Some(operation) = operation {
+            self.load(src1);
+            self.emit(Instruction::Binary(
+                operation,
+                AssemblyType::Word,
+                self.operand(src2),
+                AX,
+            ));
+            return;
+        }
+
+        let condition = |signed_code, unsigned_code| if signed { signed_code } else { unsigned_code };
+        match operator {
+            // The low word of the product is the same whether the operands are signed or not.
+            ir::BinaryOperator::Multiply => {
+                self.load(src1);
+                let multiplier = self.register_operand(src2);
+                self.emit(Instruction::Imul(multiplier));
+            }
+            ir::BinaryOperator::Divide | ir::BinaryOperator::Remainder => {
+                self.load(src1);
+                let divisor = self.register_operand(src2);
+                if signed {
+                    self.emit(Instruction::Cwd);
+                    self.emit(Instruction::Idiv(divisor));
+                } else {
+                    self.emit(Instruction::Mov(AssemblyType::Word, Operand::Immediate(0), DX));
+                    self.emit(Instruction::Div(divisor));
+                }
+                if operator == ir::BinaryOperator::Remainder {
+                    self.emit(Instruction::Mov(AssemblyType::Word, DX, AX));
+                }
+            }
+            // The count must be in CL.
+            ir::BinaryOperator::ShiftLeft | ir::BinaryOperator::ShiftRight => {
+                self.emit(Instruction::Mov(AssemblyType::Word, self.count(src2), CX));
+                self.load(src1);
+                let shift = match operator {
+                    ir::BinaryOperator::ShiftLeft => BinaryOperator::Shl,
+                    _ if signed => BinaryOperator::Sar,
+                    _ => BinaryOperator::Shr,
+                };
+                self.emit(Instruction::Binary(shift, AssemblyType::Word, CX, AX));
+            }
+            ir::BinaryOperator::Equal => self.comparison(ConditionCode::E, src1, src2),
+            ir::BinaryOperator::NotEqual => self.comparison(ConditionCode::NE, src1, src2),
+            ir::BinaryOperator::LessThan => {
+                self.comparison(condition(ConditionCode::L, ConditionCode::B), src1, src2)
+            }
+            ir::BinaryOperator::LessOrEqual => {
+                self.comparison(condition(ConditionCode::LE, ConditionCode::BE), src1, src2)
+            }
+            ir::BinaryOperator::GreaterThan => {
+                self.comparison(condition(ConditionCode::G, ConditionCode::A), src1, src2)
+            }
+            ir::BinaryOperator::GreaterOrEqual => {
+                self.comparison(condition(ConditionCode::GE, ConditionCode::AE), src1, src2)
+            }
+            _ => unreachable!("{:?} is handled above", operator),
+        }
+    }
+
+    /// A 'long' operation, on DX and AX.
+    fn long_binary(&mut self, operator: ir::BinaryOperator, signed: bool, src1: &ir::Value, src2: &ir::Value) {
+        let (low, high) = match operator {
+            ir::BinaryOperator::Add => (BinaryOperator::Add, BinaryOperator::Adc),
+            ir::BinaryOperator::Subtract => (BinaryOperator::Sub, BinaryOperator::Sbb),
+            ir::BinaryOperator::BitwiseAnd => (BinaryOperator::And, BinaryOperator::And),
+            ir::BinaryOperator::BitwiseOr => (BinaryOperator::Or, BinaryOperator::Or),
+            ir::BinaryOperator::BitwiseXor => (BinaryOperator::Xor, BinaryOperator::Xor),
+            ir::BinaryOperator::Multiply => return self.long_helper(LONG_MULTIPLY, src1, src2),
+            ir::BinaryOperator::Divide if signed => return self.long_helper(LONG_DIVIDE, src1, src2),
+            ir::BinaryOperator::Divide => return self.long_helper(UNSIGNED_LONG_DIVIDE, src1, src2),
+            ir::BinaryOperator::Remainder if signed => return self.long_helper(LONG_REMAINDER, src1, src2),
+            ir::BinaryOperator::Remainder => return self.long_helper(UNSIGNED_LONG_REMAINDER, src1, src2),
+            ir::BinaryOperator::ShiftLeft | ir::BinaryOperator::ShiftRight => {
+                return self.long_shift(operator, signed, src1, src2)
+            }
+            _ => unreachable!("{:?} is a comparison", operator),
+        };
+        self.load(src1);
+        self.emit(Instruction::Binary(low, AssemblyType::Word, self.word(src2, 0), AX));
+        self.emit(Instruction::Binary(high, AssemblyType::Word, self.word(src2, 2), DX));
+    }
+
+    /// Calls a runtime routine with two 'long' operands, which leaves the result in DX and AX.
+    fn long_helper(&mut self, name: &str, src1: &ir::Value, src2: &ir::Value) {
+        self.function_call(name, &[src1.clone(), src2.clone()], None);
+    }
+
+    /// Shifts DX and AX by one bit at a time, carrying the bit between the words, CX times.
+    fn long_shift(&mut self, operator: ir::BinaryOperator, signed: bool, src1: &ir::Value, src2: &ir::Value) {
+        self.emit(Instruction::Mov(AssemblyType::Word, self.count(src2), CX));
+        self.load(src1);
+        let (repeat, done) = (self.label("shift"), self.label("shifted"));
+        self.emit(Instruction::Cmp(AssemblyType::Word, Operand::Immediate(0), CX));
+        self.emit(Instruction::JmpCC(ConditionCode::E, done.clone()));
+        self.emit(Instruction::Label(repeat.clone()));
+        let one = Operand::Immediate(1);
+        let (first, second) = match operator {
+            ir::BinaryOperator::ShiftLeft => ((BinaryOperator::Shl, AX), (BinaryOperator::Rcl, DX)),
+            _ if signed => ((BinaryOperator::Sar, DX), (BinaryOperator::Rcr, AX)),
+            _ => ((BinaryOperator::Shr, DX), (BinaryOperator::Rcr, AX)),
+        };
+        self.emit(Instruction::Binary(first.0, AssemblyType::Word, one.clone(), first.1));
+        self.emit(Instruction::Binary(second.0, AssemblyType::Word, one.clone(), second.1));
+        self.emit(Instruction::Binary(BinaryOperator::Sub, AssemblyType::Word, one, CX));
+        self.emit(Instruction::JmpCC(ConditionCode::NE, repeat));
+        self.emit(Instruction::Label(done));
+    }
+
+    /// A shift count, as a word: the low word of a 'long' count.
+    fn count(&self, value: &ir::Value) -> Operand {
+        if self.is_long(value) {
+            self.word(value, 0)
+        } else {
+            self.operand(value)
+        }
+    }
+
+    /// The operand of 'imul' or 'div', which cannot be an immediate, so constants are loaded
+    /// into CX.
+    fn register_operand(&mut self, value: &ir::Value) -> Operand {
+        match value {
+            ir::Value::Constant(_) => {
+                self.emit(Instruction::Mov(AssemblyType::Word, self.operand(value), CX));
+                CX
+            }
+            ir::Value::Var(_) => self.operand(value),
+        }
+    }
+
+    /// Leaves 1 in AX if the comparison holds, and 0 if not. 'mov' leaves the flags as 'cmp' set
+    /// them, for the jump over the clearing of AX.
+    fn comparison(&mut self, code: ConditionCode, src1: &ir::Value, src2: &ir::Value) {
+        let assembly_type = self.assembly_type(src1);
+        let code = match assembly_type {
+            AssemblyType::Doubleword => self.long_comparison(code, src1, src2),
+            _ => {
+                self.load(src1);
+                self.emit(Instruction::Cmp(assembly_type, self.operand(src2), AX));
+                code
+            }
+        };
+        let label = self.label("set");
+        self.emit(Instruction::Mov(AssemblyType::Word, Operand::Immediate(1), AX));
+        self.emit(Instruction::JmpCC(code, label.clone()));
+        self.emit(Instruction::Binary(BinaryOperator::Xor, AssemblyType::Word, AX, AX));
+        self.emit(Instruction::Label(label));
+    }
+
+    /// Sets the flags for a comparison of 'long's, giving the condition to test them for. For
+    /// equality the words are compared with XOR, and the differences ORed. Otherwise the second
+    /// is subtracted from the first, borrowing from the high words, which leaves all the flags
+    /// that matter but the zero flag, so those conditions that test it are turned round to
+    /// subtract the first from the second instead.
+    fn long_comparison(&mut self, code: ConditionCode, src1: &ir::Value, src2: &ir::Value) -> ConditionCode {
+        let word = AssemblyType::Word;
+        if let ConditionCode::E | ConditionCode::NE = code {
+            self.load(src1);
+            self.emit(Instruction::Binary(BinaryOperator::Xor, word, self.word(src2, 0), AX));
+            self.emit(Instruction::Binary(BinaryOperator::Xor, word, self.word(src2, 2), DX));
+            self.emit(Instruction::Binary(BinaryOperator::Or, word, DX, AX));
+            return code;
+        }
+        let (first, second, code) = match code {
+            ConditionCode::LE => (src2, src1, ConditionCode::GE),
+            ConditionCode::G => (src2, src1, ConditionCode::L),
+            ConditionCode::BE => (src2, src1, ConditionCode::AE),
+            ConditionCode::A => (src2, src1, ConditionCode::B),
+            _ => (src1, src2, code),
+        };
+        self.load(first);
+        self.emit(Instruction::Binary(BinaryOperator::Sub, word, self.word(second, 0), AX));
+        self.emit(Instruction::Binary(BinaryOperator::Sbb, word, self.word(second, 2), DX));
+        code
+    }
+
+    /// The stacked arguments are pushed from the last to the first, then the register arguments
+    /// are loaded, through AX as only AL can be sign extended, so AX is loaded last. A 'long'
+    /// result is left in DX and AX.
+    fn function_call(&mut self, name: &str, args: &[ir::Value], dst: Option<&ir::Value>) {
+        let assembly_types: Vec<AssemblyType> = args.iter().map(|arg| self.assembly_type(arg)).collect();
+        let locations = TopSpeed::locations(&assembly_types);
+        let stacked = TopSpeed::memory_slots(&assembly_types);
+        for (arg, location) in args.iter().zip(&locations).rev() {
+            if let Location::Memory(_) = location {
+                self.push(arg);
+            }
+        }
+        for (arg, location) in args.iter().zip(&locations).rev() {
+            if let Location::Register(register) = location {
+                self.load_word(arg);
+                if *register != Register::AX {
+                    self.emit(Instruction::Mov(AssemblyType::Word, AX, Operand::Register(*register)));
+                }
+            }
+        }
+        self.emit(Instruction::Call(name.to_owned()));
+        if stacked != 0 {
+            self.emit(Instruction::Binary(
+                BinaryOperator::Add,
+                AssemblyType::Word,
+                Operand::Immediate(2 * stacked as i64),
+                Operand::Register(Register::SP),
+            ));
+        }
+        if let Some(dst) = dst {
+            self.store(dst);
+        }
+    }
+}
+
+fn is_comparison(operator: ir::BinaryOperator) -> bool {
+    matches!(
+        operator,
+        ir::BinaryOperator::Equal
+            | ir::BinaryOperator::NotEqual
+            | ir::BinaryOperator::LessThan
+            | ir::BinaryOperator::LessOrEqual
+            | ir::BinaryOperator::GreaterThan
+            | ir::BinaryOperator::GreaterOrEqual
+    )
+}
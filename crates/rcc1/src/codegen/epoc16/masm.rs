@@ -0,0 +1,102 @@
+//! The syntax of the Microsoft Macro Assembler, as MASM 6 accepts it. Borland's Turbo Assembler
+//! accepts it too, in its default MASM mode, as do JWasm and its descendants.
+//!
+//! Memory operands give their size with 'PTR'. Conditional jumps are written as such, although
+//! the 8086's reach only 128 bytes, as MASM 6 lengthens those whose targets are further away into
+//! a reversed jump over a 'jmp'.
+//!
+//! IR names may contain '.', which MASM only allows at the start of a name, so it is written as
+//! '@'. C names cannot contain '@', and a renamed variable, e.g. 'set@1', is still kept apart from
+//! a generated label, e.g. 'set@@1', by the number of '.'s that 'ir::generated_name' gives it.
+
+use crate::codegen::epoc16::emission::{condition, Segment, Syntax};
+use crate::codegen::epoc16::{AssemblyType, ConditionCode};
+
+pub struct Masm;
+
+fn size(assembly_type: AssemblyType) -> &'static str {
+    match assembly_type {
+        AssemblyType::Byte => "BYTE",
+        AssemblyType::Word => "WORD",
+        AssemblyType::Doubleword => "DWORD",
+    }
+}
+
+impl Syntax for Masm {
+    fn prologue(&self) -> String {
+        "\t.8086
+_TEXT\tSEGMENT\tBYTE PUBLIC 'CODE'
+_TEXT\tENDS
+_DATA\tSEGMENT\tWORD PUBLIC 'DATA'
+_DATA\tENDS
+_BSS\tSEGMENT\tWORD PUBLIC 'BSS'
+_BSS\tENDS
+DGROUP\tGROUP\t_DATA, _BSS
+\tASSUME\tCS:_TEXT, DS:DGROUP, SS:DGROUP
+"
+        .to_owned()
+    }
+
+    fn begin_segment(&self, segment: Segment) -> String {
+        format!("{}\tSEGMENT\n", segment.name())
+    }
+
+    fn end_segment(&self, segment: Segment) -> String {
+        format!("{}\tENDS\n", segment.name())
+    }
+
+    fn epilogue(&self) -> String {
+        "\tEND\n".to_owned()
+    }
+
+    fn symbol(&self, name: &str) -> String {
+        name.replace('.', "@")
+    }
+
+    fn public(&self, name: &str) -> String {
+        format!("\tPUBLIC\t{}\n", self.symbol(name))
+    }
+
+    fn external_function(&self, name: &str) -> String {
+        format!("\tEXTRN\t{}:NEAR\n", self.symbol(name))
+    }
+
+    fn external_variable(&self, name: &str, assembly_type: AssemblyType) -> String {
+        format!("\tEXTRN\t{}:{}\n", self.symbol(name), size(assembly_type))
+    }
+
+    fn begin_function(&self, name: &str) -> String {
+        format!("{}\tPROC\tNEAR\n", self.symbol(name))
+    }
+
+    fn end_function(&self, name: &str) -> String {
+        format!("{}\tENDP\n", self.symbol(name))
+    }
+
+    fn variable(&self, name: &str, assembly_type: AssemblyType, init: Option<i64>) -> String {
+        let directive = match assembly_type {
+            AssemblyType::Byte => "DB",
+            AssemblyType::Word => "DW",
+            AssemblyType::Doubleword => "DD",
+        };
+        match init {
+            Some(init) => format!("{}\t{}\t{}\n", self.symbol(name), directive, init),
+            None => format!("{}\t{}\t?\n", self.symbol(name), directive),
+        }
+    }
+
+    fn stack(&self, assembly_type: AssemblyType, offset: i64) -> String {
+        format!("{} PTR [bp{:+}]", size(assembly_type), offset)
+    }
+
+    fn data(&self, assembly_type: AssemblyType, name: &str, offset: i64) -> String {
+        match offset {
+            0 => format!("{} PTR {}", size(assembly_type), self.symbol(name)),
+            _ => format!("{} PTR {}{:+}", size(assembly_type), self.symbol(name), offset),
+        }
+    }
+
+    fn conditional_jump(&self, cc: ConditionCode, label: &str) -> String {
+        format!("\tj{}\t{}\n", condition(cc), label)
+    }
+}
@@ -0,0 +1,288 @@
+//! The EPOC16 back end, generating 8086 assembly for the small memory model, following the
+//! conventions of JPI TopSpeed C 3.10, the compiler of Psion's SIBO C SDK, so that the output can
+//! be linked with code it compiled:
+//!
+//! * the first four arguments are passed in AX, BX, CX and DX, the rest on the stack, and the
+//!   result is returned in AX, or for a 'long', in DX and AX;
+//! * C names are used unchanged, without the underscore that Microsoft C prefixes them with;
+//! * code is in the '_TEXT' segment, and data in '_DATA' and '_BSS', grouped as 'DGROUP', which
+//!   DS and SS both address, so near pointers reach all data.
+//!
+//! As with the x86_64 back end, this is done in stages over an assembly AST:
+//!
+//! * generation translates each IR instruction, addressing IR variables as pseudo-registers. The
+//!   8086 has few registers, each with its own roles, so operations are done in the accumulator:
+//!   their first operand is loaded into AX, and the result stored from it, which keeps every
+//!   instruction to an operand form the 8086 can encode;
+//! * at '-O1', pseudo-registers are given registers where they can be, by colouring an
+//!   interference graph that models the 8086's byte registers and the registers' fixed roles;
+//! * the remaining pseudo-registers are given slots in the function's stack frame, or for static
+//!   variables, their symbols;
+//! * finally the program is written out as text, in the syntax of the Microsoft Macro Assembler
+//!   or of the Netwide Assembler.
+//!
+//! The 8086 has no 32-bit arithmetic, so a 'long' is kept in memory as two words, the low one
+//! first, and operated on a word at a time in DX and AX: addition, subtraction, comparison and
+//! shifts are done inline, carrying between the words, and multiplication and division by calling
+//! the routines of rcc's 'long' runtime.
+
+pub mod emission;
+pub mod generation;
+pub mod masm;
+pub mod nasm;
+pub mod register_allocation;
+pub mod stack_frame;
+
+#[cfg(test)]
+pub mod simulator;
+
+use common::assembler_syntax::AssemblerSyntax;
+use common::data_model::DataModel;
+
+use crate::ast::Type;
+use crate::codegen::calling_convention::{CallingConvention, Location};
+use crate::ir;
+use crate::semantic::symbol_table::SymbolTable;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub top_level: Vec<TopLevel>,
+    /// The symbols used but not defined in the module, which the assembler must be told of.
+    pub externals: Vec<External>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TopLevel {
+    Function(Function),
+    StaticVariable(StaticVariable),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub name: String,
+    pub global: bool,
+    pub instructions: Vec<Instruction>,
+    /// The callee-saved registers the function uses, which it saves on entry and restores on
+    /// return.
+    pub callee_saved: Vec<Register>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticVariable {
+    pub name: String,
+    pub global: bool,
+    pub assembly_type: AssemblyType,
+    /// The initial value, within the range of the variable's C type.
+    pub init: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum External {
+    Function(String),
+    Variable(String, AssemblyType),
+}
+
+/// The size of an operand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AssemblyType {
+    Byte,
+    Word,
+    /// A 'long', which only static variables and memory operands' sizes are declared as: it is
+    /// operated on as two words.
+    Doubleword,
+}
+
+impl AssemblyType {
+    /// The operand size of an integer type, if the 8086 has one.
+    pub fn of(c_type: &Type, data_model: &DataModel) -> Option<AssemblyType> {
+        match c_type.bits(data_model) {
+            8 => Some(AssemblyType::Byte),
+            16 => Some(AssemblyType::Word),
+            32 => Some(AssemblyType::Doubleword),
+            _ => None,
+        }
+    }
+
+    pub fn size(&self) -> i64 {
+        match self {
+            AssemblyType::Byte => 1,
+            AssemblyType::Word => 2,
+            AssemblyType::Doubleword => 4,
+        }
+    }
+}
+
+/// The word registers; as bytes, AX, BX, CX and DX are their low halves, AL, BL, CL and DL. DS
+/// and SS both address DGROUP throughout, so are never operands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Register {
+    AX,
+    BX,
+    CX,
+    DX,
+    SI,
+    DI,
+    SP,
+    BP,
+    /// The high bytes of AX, BX, CX and DX, which are only used as bytes.
+    AH,
+    BH,
+    CH,
+    DH,
+}
+
+/// TopSpeed C passes the first four arguments in registers, extended to words, and pushes the
+/// rest from the last to the first, so the first is lowest. The caller removes them after the
+/// call. The result is returned in AX. The callee preserves SI and DI, as well as BP, DS and SS,
+/// and may change AX, BX, CX and DX.
+///
+/// A 'long' does not fit a register, so is always pushed, high word first so that it is in memory
+/// in the usual order, and the registers go to the other arguments; it is returned in DX and AX.
+/// The trait's placement by index alone is for arguments that are all words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TopSpeed;
+
+impl CallingConvention for TopSpeed {
+    type Register = Register;
+
+    const ARGUMENT_REGISTERS: &'static [Register] =
+        &[Register::AX, Register::BX, Register::CX, Register::DX];
+
+    const RETURN_REGISTER: Register = Register::AX;
+}
+
+impl TopSpeed {
+    /// Where each of a call's arguments is passed, given their sizes. The slots in memory are
+    /// words, so a 'long' takes two.
+    pub fn locations(assembly_types: &[AssemblyType]) -> Vec<Location<Register>> {
+        let mut registers = Self::ARGUMENT_REGISTERS.iter();
+        let mut slot = 0;
+        assembly_types
+            .iter()
+            .map(|assembly_type| match assembly_type {
+                AssemblyType::Byte | AssemblyType::Word => match registers.next() {
+                    Some(register) => Location::Register(*register),
+                    None => {
+                        slot += 1;
+                        Location::Memory(slot - 1)
+                    }
+                },
+                AssemblyType::Doubleword => {
+                    slot += 2;
+                    Location::Memory(slot - 2)
+                }
+            })
+            .collect()
+    }
+
+    /// The number of word slots in memory the arguments take.
+    pub fn memory_slots(assembly_types: &[AssemblyType]) -> usize {
+        let locations = Self::locations(assembly_types);
+        locations
+            .iter()
+            .zip(assembly_types)
+            .map(|(location, assembly_type)| match location {
+                Location::Register(_) => 0,
+                Location::Memory(_) => (assembly_type.size() as usize).div_ceil(2),
+            })
+            .sum()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+    Immediate(i64),
+    Register(Register),
+    /// An IR variable, not yet assigned a location.
+    Pseudo(String),
+    /// A word at an offset within an IR variable kept in memory, not yet assigned its location.
+    PseudoMem(String, i64),
+    /// An offset from BP: negative for the frame's slots, positive for the stacked parameters.
+    Stack(i64),
+    /// A static variable, in DGROUP, at an offset from its symbol.
+    Data(String, i64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConditionCode {
+    E,
+    NE,
+    L,
+    LE,
+    G,
+    GE,
+    /// The unsigned comparisons: below, below or equal, above, above or equal.
+    B,
+    BE,
+    A,
+    AE,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOperator {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Add,
+    Sub,
+    And,
+    Or,
+    Xor,
+    /// Addition and subtraction with the carry, of the high words of 'long's.
+    Adc,
+    Sbb,
+    /// The shifts, by CL or by 1.
+    Shl,
+    Sar,
+    Shr,
+    /// Rotations through the carry, by 1, which carry a bit between the words of a 'long'.
+    Rcl,
+    Rcr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    Mov(AssemblyType, Operand, Operand),
+    /// Sign extends AL into AX.
+    Cbw,
+    /// Sign extends AX into DX.
+    Cwd,
+    Unary(UnaryOperator, AssemblyType, Operand),
+    Binary(BinaryOperator, AssemblyType, Operand, Operand),
+    Cmp(AssemblyType, Operand, Operand),
+    /// Multiplies AX by the operand, into DX and AX.
+    Imul(Operand),
+    /// Divides DX and AX by the operand, leaving the quotient in AX and the remainder in DX.
+    Idiv(Operand),
+    Div(Operand),
+    Jmp(String),
+    JmpCC(ConditionCode, String),
+    Label(String),
+    Push(Operand),
+    Pop(Register),
+    Call(String),
+    Ret,
+}
+
+/// Generates the assembly language text for a program, in an assembler's syntax.
+pub fn assembly(
+    program: &ir::Program,
+    symbols: &SymbolTable,
+    data_model: &DataModel,
+    syntax: AssemblerSyntax,
+    allocate_registers: bool,
+) -> String {
+    let mut program = generation::generate(program, symbols, data_model);
+    if allocate_registers {
+        register_allocation::allocate(&mut program, symbols, data_model);
+    }
+    stack_frame::allocate(&mut program, symbols, data_model);
+    emission::emit(&program, syntax)
+}
+
+#[cfg(test)]
+#[path = "./epoc16_spec.rs"]
+mod epoc16_spec;
@@ -0,0 +1,113 @@
+//! The syntax of the Netwide Assembler, for its 'obj' output format, which writes the OMF objects
+//! that the 8086 linkers read.
+//!
+//! NASM has no 'ASSUME', and makes references to a variable in a segment of DGROUP relative to the
+//! group, which is what DS addresses. Memory operands are always bracketed, with their size before
+//! them. Names may contain '.', so IR names are written unchanged.
+//!
+//! NASM does not lengthen conditional jumps whose targets are out of the 8086's 128 byte reach,
+//! as MASM does: its near conditional jumps need a 386. So each is written as the reversed
+//! condition's short jump over a near 'jmp', which is 3 bytes, to the label.
+
+use crate::codegen::epoc16::emission::{condition, Segment, Syntax};
+use crate::codegen::epoc16::{AssemblyType, ConditionCode};
+
+pub struct Nasm;
+
+fn size(assembly_type: AssemblyType) -> &'static str {
+    match assembly_type {
+        AssemblyType::Byte => "byte",
+        AssemblyType::Word => "word",
+        AssemblyType::Doubleword => "dword",
+    }
+}
+
+fn reversed(cc: ConditionCode) -> ConditionCode {
+    match cc {
+        ConditionCode::E => ConditionCode::NE,
+        ConditionCode::NE => ConditionCode::E,
+        ConditionCode::L => ConditionCode::GE,
+        ConditionCode::LE => ConditionCode::G,
+        ConditionCode::G => ConditionCode::LE,
+        ConditionCode::GE => ConditionCode::L,
+        ConditionCode::B => ConditionCode::AE,
+        ConditionCode::BE => ConditionCode::A,
+        ConditionCode::A => ConditionCode::BE,
+        ConditionCode::AE => ConditionCode::B,
+    }
+}
+
+impl Syntax for Nasm {
+    fn prologue(&self) -> String {
+        "\tcpu\t8086
+segment\t_TEXT\tpublic class=CODE align=1
+segment\t_DATA\tpublic class=DATA align=2
+segment\t_BSS\tpublic class=BSS align=2
+group\tDGROUP\t_DATA _BSS
+"
+        .to_owned()
+    }
+
+    fn begin_segment(&self, segment: Segment) -> String {
+        format!("segment\t{}\n", segment.name())
+    }
+
+    /// A segment continues until the next is begun.
+    fn end_segment(&self, _segment: Segment) -> String {
+        String::new()
+    }
+
+    fn epilogue(&self) -> String {
+        String::new()
+    }
+
+    fn symbol(&self, name: &str) -> String {
+        name.to_owned()
+    }
+
+    fn public(&self, name: &str) -> String {
+        format!("\tglobal\t{}\n", name)
+    }
+
+    fn external_function(&self, name: &str) -> String {
+        format!("\textern\t{}\n", name)
+    }
+
+    fn external_variable(&self, name: &str, _assembly_type: AssemblyType) -> String {
+        format!("\textern\t{}\n", name)
+    }
+
+    fn begin_function(&self, name: &str) -> String {
+        format!("{}:\n", name)
+    }
+
+    fn end_function(&self, _name: &str) -> String {
+        String::new()
+    }
+
+    fn variable(&self, name: &str, assembly_type: AssemblyType, init: Option<i64>) -> String {
+        match (init, assembly_type) {
+            (Some(init), AssemblyType::Byte) => format!("{}\tdb\t{}\n", name, init),
+            (Some(init), AssemblyType::Word) => format!("{}\tdw\t{}\n", name, init),
+            (Some(init), AssemblyType::Doubleword) => format!("{}\tdd\t{}\n", name, init),
+            (None, AssemblyType::Byte) => format!("{}\tresb\t1\n", name),
+            (None, AssemblyType::Word) => format!("{}\tresw\t1\n", name),
+            (None, AssemblyType::Doubleword) => format!("{}\tresd\t1\n", name),
+        }
+    }
+
+    fn stack(&self, assembly_type: AssemblyType, offset: i64) -> String {
+        format!("{} [bp{:+}]", size(assembly_type), offset)
+    }
+
+    fn data(&self, assembly_type: AssemblyType, name: &str, offset: i64) -> String {
+        match offset {
+            0 => format!("{} [{}]", size(assembly_type), name),
+            _ => format!("{} [{}{:+}]", size(assembly_type), name, offset),
+        }
+    }
+
+    fn conditional_jump(&self, cc: ConditionCode, label: &str) -> String {
+        format!("\tj{}\tshort $+5\n\tjmp\tnear {}\n", condition(reversed(cc)), label)
+    }
+}
@@ -0,0 +1,573 @@
+//! Gives pseudo-registers 8086 registers, by colouring an interference graph with iterated
+//! register coalescing, as the x86_64 back end does, adapted to the 8086's registers:
+//!
+//! * AX, BX, CX and DX are each two byte registers, AL and AH and so on, so a 'char' is given one
+//!   of the eight halves, and a word one of the six word registers. A colour is taken if any of
+//!   its bytes are, so a word in BX rules out BL and BH for its neighbours, and a byte in BH rules
+//!   out BX;
+//! * the registers' fixed roles are modelled as uses and definitions of the hardware registers:
+//!   AX is the accumulator, multiplication and division use DX and AX, shift counts are in CX,
+//!   and calls take their arguments in AX, BX, CX and DX and may change all four. A value live
+//!   across any of them interferes with the register, so is kept out of it;
+//! * SI and DI have no byte halves, and TopSpeed C has the callee save them, so they are only
+//!   given to words, after the registers a function may use freely;
+//! * SP and BP address the stack, and DS and SS are fixed to DGROUP in the small model, so none
+//!   of them is allocated;
+//! * a 'long' is addressed a word at a time, so stays in memory.
+//!
+//! A pseudo-register that cannot be coloured is spilled, and left for the stack frame.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use common::data_model::DataModel;
+
+use crate::ast::Type;
+use crate::codegen::calling_convention::{CallingConvention, Location};
+use crate::codegen::epoc16::{
+    AssemblyType, Function, Instruction, Operand, Program, Register, TopLevel, TopSpeed,
+};
+use crate::semantic::symbol_table::{IdentifierAttributes, SymbolTable};
+
+/// The registers a word is given, in order of preference.
+const WORD_REGISTERS: &[Register] = &[
+    Register::CX,
+    Register::DX,
+    Register::BX,
+    Register::AX,
+    Register::SI,
+    Register::DI,
+];
+
+/// The registers a byte is given, in order of preference: AX, BX, CX and DX stand for their low
+/// halves.
+const BYTE_REGISTERS: &[Register] = &[
+    Register::CX,
+    Register::CH,
+    Register::DX,
+    Register::DH,
+    Register::BX,
+    Register::BH,
+    Register::AX,
+    Register::AH,
+];
+
+const CALLER_SAVED: &[Register] = &[Register::AX, Register::BX, Register::CX, Register::DX];
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Node {
+    /// A word register, standing for both its halves.
+    Register(Register),
+    Pseudo(String),
+}
+
+type Graph = BTreeMap<Node, BTreeSet<Node>>;
+
+/// The bytes of the registers a colour occupies, one bit each: AL, AH, BL, BH, CL, CH, DL, DH,
+/// then SI and DI as one each.
+fn bytes(register: Register, assembly_type: AssemblyType) -> u16 {
+    let word = |low: u16| match assembly_type {
+        AssemblyType::Byte => low,
+        AssemblyType::Word | AssemblyType::Doubleword => low | low << 1,
+    };
+    match register {
+        Register::AX => word(1),
+        Register::BX => word(1 << 2),
+        Register::CX => word(1 << 4),
+        Register::DX => word(1 << 6),
+        Register::AH => 1 << 1,
+        Register::BH => 1 << 3,
+        Register::CH => 1 << 5,
+        Register::DH => 1 << 7,
+        Register::SI => 1 << 8,
+        Register::DI => 1 << 9,
+        Register::SP | Register::BP => 0,
+    }
+}
+
+/// The word register an operand's register is, or is a half of, if it is allocated.
+fn word_register(register: Register) -> Option<Register> {
+    match register {
+        Register::AX | Register::AH => Some(Register::AX),
+        Register::BX | Register::BH => Some(Register::BX),
+        Register::CX | Register::CH => Some(Register::CX),
+        Register::DX | Register::DH => Some(Register::DX),
+        Register::SI => Some(Register::SI),
+        Register::DI => Some(Register::DI),
+        Register::SP | Register::BP => None,
+    }
+}
+
+fn registers_for(assembly_type: AssemblyType) -> &'static [Register] {
+    match assembly_type {
+        AssemblyType::Byte => BYTE_REGISTERS,
+        AssemblyType::Word => WORD_REGISTERS,
+        AssemblyType::Doubleword => &[],
+    }
+}
+
+pub fn allocate(program: &mut Program, symbols: &SymbolTable, data_model: &DataModel) {
+    for top_level in &mut program.top_level {
+        if let TopLevel::Function(function) = top_level {
+            let result = match symbols.get(&function.name).map(|symbol| &symbol.symbol_type) {
+                Some(Type::Function { return_type, .. }) => AssemblyType::of(return_type, data_model),
+                _ => None,
+            };
+            let returns_long = result == Some(AssemblyType::Doubleword);
+            Allocator { symbols, data_model, returns_long }.function(function);
+        }
+    }
+}
+
+struct Allocator<'a> {
+    symbols: &'a SymbolTable,
+    data_model: &'a DataModel,
+    /// Whether the function returns a 'long', in DX as well as AX.
+    returns_long: bool,
+}
+
+impl Allocator<'_> {
+    fn function(&self, function: &mut Function) {
+        while self.coalesce(&mut function.instructions) {}
+
+        let graph = self.interference(&function.instructions);
+        let colours = self.colour(&graph, &function.instructions);
+        replace(&mut function.instructions, |name| {
+            colours.get(name).map(|register| Operand::Register(*register))
+        });
+        function.callee_saved = [Register::SI, Register::DI]
+            .into_iter()
+            .filter(|register| colours.values().any(|colour| colour == register))
+            .collect();
+    }
+
+    /// The graph node for an operand, if it is a register that is allocated or a pseudo-register
+    /// that is not a static variable.
+    fn node(&self, operand: &Operand) -> Option<Node> {
+        match operand {
+            Operand::Register(register) => word_register(*register).map(Node::Register),
+            Operand::Pseudo(name) => {
+                let symbol = self.symbols.get(name).expect("every variable is in the symbol table");
+                match symbol.attributes {
+                    IdentifierAttributes::Static { .. } => None,
+                    _ => Some(Node::Pseudo(name.clone())),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// The nodes an instruction reads, and those it writes. Writing either half of a register is
+    /// taken as writing all of it, so that a value in the other half interferes with it, and
+    /// writing a high half also reads the register, as its low half is kept.
+    fn uses_and_defs(&self, instruction: &Instruction) -> (Vec<Node>, Vec<Node>) {
+        let nodes = |operands: &[&Operand]| -> Vec<Node> {
+            operands.iter().filter_map(|operand| self.node(operand)).collect()
+        };
+        let registers = |registers: &[Register]| -> Vec<Node> {
+            registers.iter().map(|register| Node::Register(*register)).collect()
+        };
+        match instruction {
+            Instruction::Mov(
+                _,
+                src,
+                dst @ Operand::Register(Register::AH | Register::BH | Register::CH | Register::DH),
+            ) => (nodes(&[src, dst]), nodes(&[dst])),
+            Instruction::Mov(_, src, dst) => (nodes(&[src]), nodes(&[dst])),
+            Instruction::Cbw => (registers(&[Register::AX]), registers(&[Register::AX])),
+            Instruction::Cwd => (registers(&[Register::AX]), registers(&[Register::DX])),
+            Instruction::Unary(_, _, operand) => (nodes(&[operand]), nodes(&[operand])),
+            Instruction::Binary(_, _, src, dst) => (nodes(&[src, dst]), nodes(&[dst])),
+            Instruction::Cmp(_, src, dst) => (nodes(&[src, dst]), vec![]),
+            Instruction::Imul(operand) => {
+                let mut uses = nodes(&[operand]);
+                uses.push(Node::Register(Register::AX));
+                (uses, registers(&[Register::AX, Register::DX]))
+            }
+            Instruction::Idiv(operand) | Instruction::Div(operand) => {
+                let mut uses = nodes(&[operand]);
+                uses.extend(registers(&[Register::AX, Register::DX]));
+                (uses, registers(&[Register::AX, Register::DX]))
+            }
+            Instruction::Push(operand) => (nodes(&[operand]), vec![]),
+            Instruction::Pop(register) => (vec![], nodes(&[&Operand::Register(*register)])),
+            // The 'long' runtime's routines need not be in the symbol table, and take no arguments
+            // in registers.
+            Instruction::Call(name) => {
+                let params: Vec<AssemblyType> = match self.symbols.get(name).map(|symbol| &symbol.symbol_type) {
+                    Some(Type::Function { params, .. }) => params
+                        .iter()
+                        .map(|param| {
+                            AssemblyType::of(param, self.data_model).expect("every value is at most 32 bits wide")
+                        })
+                        .collect(),
+                    _ => vec![],
+                };
+                let in_registers: Vec<Register> = TopSpeed::locations(&params)
+                    .into_iter()
+                    .filter_map(|location| match location {
+                        Location::Register(register) => Some(register),
+                        Location::Memory(_) => None,
+                    })
+                    .collect();
+                (registers(&in_registers), registers(CALLER_SAVED))
+            }
+            Instruction::Ret if self.returns_long => {
+                (registers(&[TopSpeed::RETURN_REGISTER, Register::DX]), vec![])
+            }
+            Instruction::Ret => (registers(&[TopSpeed::RETURN_REGISTER]), vec![]),
+            Instruction::Jmp(_) | Instruction::JmpCC(_, _) | Instruction::Label(_) => {
+                (vec![], vec![])
+            }
+        }
+    }
+
+    /// The nodes live after each instruction.
+    fn liveness(&self, instructions: &[Instruction]) -> Vec<BTreeSet<Node>> {
+        let labels: HashMap<&str, usize> = instructions
+            .iter()
+            .enumerate()
+            .filter_map(|(index, instruction)| match instruction {
+                Instruction::Label(label) => Some((label.as_str(), index)),
+                _ => None,
+            })
+            .collect();
+        let successors: Vec<Vec<usize>> = instructions
+            .iter()
+            .enumerate()
+            .map(|(index, instruction)| match instruction {
+                Instruction::Jmp(label) => vec![labels[label.as_str()]],
+                Instruction::JmpCC(_, label) => vec![index + 1, labels[label.as_str()]],
+                Instruction::Ret => vec![],
+                _ => vec![index + 1],
+            })
+            .map(|successors| successors.into_iter().filter(|s| *s < instructions.len()).collect())
+            .collect();
+        let uses_and_defs: Vec<(Vec<Node>, Vec<Node>)> = instructions
+            .iter()
+            .map(|instruction| self.uses_and_defs(instruction))
+            .collect();
+
+        let mut live_in: Vec<BTreeSet<Node>> = vec![BTreeSet::new(); instructions.len()];
+        let mut live_out: Vec<BTreeSet<Node>> = vec![BTreeSet::new(); instructions.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for index in (0..instructions.len()).rev() {
+                let out: BTreeSet<Node> = successors[index]
+                    .iter()
+                    .flat_map(|successor| live_in[*successor].iter().cloned())
+                    .collect();
+                let (uses, defs) = &uses_and_defs[index];
+                let mut live: BTreeSet<Node> =
+                    out.iter().filter(|node| !defs.contains(node)).cloned().collect();
+                live.extend(uses.iter().cloned());
+                if live != live_in[index] || out != live_out[index] {
+                    changed = true;
+                    live_in[index] = live;
+                    live_out[index] = out;
+                }
+            }
+        }
+        live_out
+    }
+
+    fn interference(&self, instructions: &[Instruction]) -> Graph {
+        let mut graph = Graph::new();
+        for register in WORD_REGISTERS {
+            let others = WORD_REGISTERS
+                .iter()
+                .filter(|other| *other != register)
+                .map(|other| Node::Register(*other))
+                .collect();
+            graph.insert(Node::Register(*register), others);
+        }
+        let live_out = self.liveness(instructions);
+        for (instruction, live) in instructions.iter().zip(live_out) {
+            let (uses, defs) = self.uses_and_defs(instruction);
+            for node in uses.iter().chain(defs.iter()) {
+                graph.entry(node.clone()).or_default();
+            }
+            // The destination of a move may share its source's register, but a byte moved to or
+            // from a hardware register is only the same as its low half, so a byte in the high
+            // half would change it.
+            let moved = match instruction {
+                Instruction::Mov(AssemblyType::Byte, Operand::Register(_), _)
+                | Instruction::Mov(AssemblyType::Byte, _, Operand::Register(_)) => None,
+                Instruction::Mov(_, src, _) => self.node(src),
+                _ => None,
+            };
+            for def in &defs {
+                for node in &live {
+                    if node != def && Some(node) != moved.as_ref() {
+                        add_edge(&mut graph, def, node);
+                    }
+                }
+            }
+        }
+        graph
+    }
+
+    fn assembly_type(&self, node: &Node) -> AssemblyType {
+        match node {
+            Node::Register(_) => AssemblyType::Word,
+            Node::Pseudo(name) => {
+                let symbol = self.symbols.get(name).expect("every variable is in the symbol table");
+                AssemblyType::of(&symbol.symbol_type, self.data_model)
+                    .expect("wide variables are rejected before generation")
+            }
+        }
+    }
+
+    /// The most registers of a node's size its neighbours in the graph could take: a word takes
+    /// one word register, or two byte registers, and a byte takes one of either.
+    fn squeeze(&self, graph: &Graph, node: &Node) -> usize {
+        let assembly_type = self.assembly_type(node);
+        graph[node]
+            .iter()
+            .map(|neighbour| self.weight(assembly_type, neighbour))
+            .sum()
+    }
+
+    fn weight(&self, assembly_type: AssemblyType, neighbour: &Node) -> usize {
+        match (assembly_type, neighbour) {
+            (AssemblyType::Word | AssemblyType::Doubleword, _) => 1,
+            (AssemblyType::Byte, Node::Register(register)) => {
+                let taken = bytes(*register, AssemblyType::Word);
+                BYTE_REGISTERS
+                    .iter()
+                    .filter(|byte| bytes(**byte, AssemblyType::Byte) & taken != 0)
+                    .count()
+            }
+            (AssemblyType::Byte, Node::Pseudo(_)) => match self.assembly_type(neighbour) {
+                AssemblyType::Byte => 1,
+                AssemblyType::Word | AssemblyType::Doubleword => 2,
+            },
+        }
+    }
+
+    /// Whether a node can always be coloured, whatever its neighbours are given.
+    fn insignificant(&self, graph: &Graph, node: &Node) -> bool {
+        match node {
+            // Hardware registers cannot be simplified away, so are always significant.
+            Node::Register(_) => false,
+            Node::Pseudo(_) => {
+                self.squeeze(graph, node) < registers_for(self.assembly_type(node)).len()
+            }
+        }
+    }
+
+    /// Briggs: the merged node's neighbours of significant degree could not take all the
+    /// registers of its size.
+    fn briggs(&self, graph: &Graph, a: &Node, b: &Node) -> bool {
+        let assembly_type = self.assembly_type(a);
+        let neighbours: BTreeSet<&Node> = graph[a].iter().chain(graph[b].iter()).collect();
+        let significant: usize = neighbours
+            .into_iter()
+            .filter(|neighbour| !self.insignificant(graph, neighbour))
+            .map(|neighbour| self.weight(assembly_type, neighbour))
+            .sum();
+        significant < registers_for(assembly_type).len()
+    }
+
+    /// George: each neighbour of the pseudo-register already interferes with the hardware
+    /// register, or has insignificant degree.
+    fn george(&self, graph: &Graph, register: &Node, pseudo: &Node) -> bool {
+        graph[pseudo]
+            .iter()
+            .all(|neighbour| graph[register].contains(neighbour) || self.insignificant(graph, neighbour))
+    }
+
+    /// Coalesces the moves that can be, rewriting the instructions; gives whether any were.
+    fn coalesce(&self, instructions: &mut Vec<Instruction>) -> bool {
+        let mut graph = self.interference(instructions);
+        let mut merged: BTreeMap<String, Node> = BTreeMap::new();
+        let find = |merged: &BTreeMap<String, Node>, node: Node| -> Node {
+            let mut node = node;
+            while let Node::Pseudo(name) = &node {
+                match merged.get(name) {
+                    Some(into) => node = into.clone(),
+                    None => break,
+                }
+            }
+            node
+        };
+        for instruction in instructions.iter() {
+            let Instruction::Mov(_, src, dst) = instruction else {
+                continue;
+            };
+            let (Some(src), Some(dst)) = (self.node(src), self.node(dst)) else {
+                continue;
+            };
+            let (src, dst) = (find(&merged, src), find(&merged, dst));
+            if src == dst || graph[&src].contains(&dst) {
+                continue;
+            }
+            // A hardware register absorbs a pseudo-register, if it can hold its size; of two
+            // pseudo-registers, the source is kept. Pseudo-registers are only merged if they are
+            // the same size, so that one register or slot suits all their uses.
+            let (keep, remove) = match (&src, &dst) {
+                (Node::Register(_), Node::Register(_)) => continue,
+                (Node::Register(register), Node::Pseudo(_))
+                | (Node::Pseudo(_), Node::Register(register)) => {
+                    let pseudo = if let Node::Pseudo(_) = src { &src } else { &dst };
+                    if !registers_for(self.assembly_type(pseudo)).contains(register) {
+                        continue;
+                    }
+                    (Node::Register(*register), pseudo.clone())
+                }
+                (Node::Pseudo(_), Node::Pseudo(_)) => {
+                    if self.assembly_type(&src) != self.assembly_type(&dst) {
+                        continue;
+                    }
+                    (src, dst)
+                }
+            };
+            let conservative = match &keep {
+                Node::Register(_) => self.george(&graph, &keep, &remove),
+                Node::Pseudo(_) => self.briggs(&graph, &keep, &remove),
+            };
+            if !conservative {
+                continue;
+            }
+            let neighbours = graph.remove(&remove).unwrap_or_default();
+            for neighbour in neighbours {
+                if let Some(edges) = graph.get_mut(&neighbour) {
+                    edges.remove(&remove);
+                }
+                add_edge(&mut graph, &keep, &neighbour);
+            }
+            let Node::Pseudo(name) = remove else {
+                unreachable!("only pseudo-registers are removed");
+            };
+            merged.insert(name, keep);
+        }
+        if merged.is_empty() {
+            return false;
+        }
+        replace(instructions, |name| {
+            match find(&merged, Node::Pseudo(name.to_owned())) {
+                Node::Register(register) => Some(Operand::Register(register)),
+                Node::Pseudo(into) if into != name => Some(Operand::Pseudo(into)),
+                Node::Pseudo(_) => None,
+            }
+        });
+        true
+    }
+
+    /// Colours the pseudo-registers, giving the registers of those that are not spilled.
+    fn colour(&self, graph: &Graph, instructions: &[Instruction]) -> HashMap<String, Register> {
+        let mut costs: HashMap<String, usize> = HashMap::new();
+        for instruction in instructions {
+            let (uses, defs) = self.uses_and_defs(instruction);
+            for node in uses.into_iter().chain(defs) {
+                if let Node::Pseudo(name) = node {
+                    *costs.entry(name).or_default() += 1;
+                }
+            }
+        }
+
+        // Simplify, removing a node that can certainly be coloured if there is one, and otherwise
+        // the cheapest to spill for its degree, optimistically.
+        let mut remaining = graph.clone();
+        let mut stack = vec![];
+        loop {
+            let candidates: Vec<Node> = remaining
+                .keys()
+                .filter(|node| matches!(node, Node::Pseudo(_)))
+                .cloned()
+                .collect();
+            if candidates.is_empty() {
+                break;
+            }
+            let chosen = match candidates.iter().find(|node| self.insignificant(&remaining, node)) {
+                Some(node) => node.clone(),
+                None => candidates
+                    .iter()
+                    .min_by(|a, b| {
+                        let cost = |node: &Node| {
+                            let Node::Pseudo(name) = node else { unreachable!() };
+                            costs[name] as f64 / self.squeeze(&remaining, node).max(1) as f64
+                        };
+                        cost(a).total_cmp(&cost(b))
+                    })
+                    .expect("there are candidates")
+                    .clone(),
+            };
+            for neighbour in remaining.remove(&chosen).unwrap_or_default() {
+                if let Some(edges) = remaining.get_mut(&neighbour) {
+                    edges.remove(&chosen);
+                }
+            }
+            stack.push(chosen);
+        }
+
+        let mut colours: HashMap<String, Register> = HashMap::new();
+        while let Some(node) = stack.pop() {
+            let taken: u16 = graph[&node]
+                .iter()
+                .map(|neighbour| match neighbour {
+                    Node::Register(register) => bytes(*register, AssemblyType::Word),
+                    Node::Pseudo(other) => colours
+                        .get(other)
+                        .map_or(0, |register| bytes(*register, self.assembly_type(neighbour))),
+                })
+                .fold(0, |taken, bytes| taken | bytes);
+            let assembly_type = self.assembly_type(&node);
+            let Node::Pseudo(name) = node else { unreachable!("only pseudo-registers are coloured") };
+            if let Some(register) = registers_for(assembly_type)
+                .iter()
+                .find(|register| bytes(**register, assembly_type) & taken == 0)
+            {
+                colours.insert(name, *register);
+            }
+        }
+        colours
+    }
+}
+
+fn add_edge(graph: &mut Graph, a: &Node, b: &Node) {
+    graph.entry(a.clone()).or_default().insert(b.clone());
+    graph.entry(b.clone()).or_default().insert(a.clone());
+}
+
+/// Replaces pseudo-registers as the function gives, then removes the moves of a register to
+/// itself.
+fn replace(instructions: &mut Vec<Instruction>, by: impl Fn(&str) -> Option<Operand>) {
+    let replace = |operand: &mut Operand| {
+        if let Operand::Pseudo(name) = operand {
+            if let Some(replacement) = by(name) {
+                *operand = replacement;
+            }
+        }
+    };
+    for instruction in instructions.iter_mut() {
+        match instruction {
+            Instruction::Mov(_, src, dst)
+            | Instruction::Binary(_, _, src, dst)
+            | Instruction::Cmp(_, src, dst) => {
+                replace(src);
+                replace(dst);
+            }
+            Instruction::Unary(_, _, operand)
+            | Instruction::Imul(operand)
+            | Instruction::Idiv(operand)
+            | Instruction::Div(operand)
+            | Instruction::Push(operand) => replace(operand),
+            Instruction::Cbw
+            | Instruction::Cwd
+            | Instruction::Jmp(_)
+            | Instruction::JmpCC(_, _)
+            | Instruction::Label(_)
+            | Instruction::Pop(_)
+            | Instruction::Call(_)
+            | Instruction::Ret => {}
+        }
+    }
+    instructions.retain(|instruction| {
+        !matches!(instruction, Instruction::Mov(_, src, dst) if src == dst && matches!(src, Operand::Register(_)))
+    });
+}
+
+#[cfg(test)]
+#[path = "./register_allocation_spec.rs"]
+mod register_allocation_spec;
@@ -0,0 +1,130 @@
+mod register_allocation_spec {
+    use common::assembler_syntax::AssemblerSyntax;
+    use common::target_platform::TargetPlatform;
+    use hamcrest2::prelude::*;
+
+    use crate::codegen::epoc16::{
+        assembly, generation, register_allocation, AssemblyType, Instruction, Operand, Register, TopLevel,
+    };
+    use crate::program_test_helper::ir_of;
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn compiled(input: &str) -> String {
+        let (ir, symbols) = ir_of(input, TargetPlatform::EPOC16);
+        assembly(&ir, &symbols, &TargetPlatform::EPOC16.data_model(), AssemblerSyntax::MASM, true)
+    }
+
+    /// The instructions of the program's first function, after allocation, and the callee-saved
+    /// registers it uses.
+    fn allocated(input: &str) -> (Vec<Instruction>, Vec<Register>) {
+        let (ir, symbols) = ir_of(input, TargetPlatform::EPOC16);
+        let data_model = TargetPlatform::EPOC16.data_model();
+        let mut program = generation::generate(&ir, &symbols, &data_model);
+        register_allocation::allocate(&mut program, &symbols, &data_model);
+        match program.top_level.remove(0) {
+            TopLevel::Function(function) => (function.instructions, function.callee_saved),
+            TopLevel::StaticVariable(_) => unreachable!(),
+        }
+    }
+
+    fn pseudos(instructions: &[Instruction]) -> usize {
+        instructions
+            .iter()
+            .filter(|instruction| format!("{:?}", instruction).contains("Pseudo"))
+            .count()
+    }
+
+    #[test]
+    fn parameters_are_used_in_the_registers_they_arrive_in() {
+        assert_that!(
+            compiled("int add(int a, int b) { return a + b; }"),
+            eq("	.8086
+_TEXT	SEGMENT	BYTE PUBLIC 'CODE'
+_TEXT	ENDS
+_DATA	SEGMENT	WORD PUBLIC 'DATA'
+_DATA	ENDS
+_BSS	SEGMENT	WORD PUBLIC 'BSS'
+_BSS	ENDS
+DGROUP	GROUP	_DATA, _BSS
+	ASSUME	CS:_TEXT, DS:DGROUP, SS:DGROUP
+_TEXT	SEGMENT
+	PUBLIC	add
+add	PROC	NEAR
+	push	bp
+	mov	bp, sp
+	add	ax, bx
+	mov	sp, bp
+	pop	bp
+	ret
+	mov	ax, 0
+	mov	sp, bp
+	pop	bp
+	ret
+add	ENDP
+_TEXT	ENDS
+	END
+"
+            .to_owned())
+        );
+    }
+
+    #[test]
+    fn chars_are_given_byte_registers() {
+        let (instructions, _) =
+            allocated("int f(char a, char b) { char c = a + b; char d = a - b; return c * d; }");
+        assert_that!(pseudos(&instructions), eq(0));
+        // The parameters arrive in AL and BL, and are kept in byte registers, so that both halves
+        // of CX are used for the four chars.
+        assert_that!(
+            instructions.contains(&Instruction::Mov(
+                AssemblyType::Byte,
+                Operand::Register(Register::AX),
+                Operand::Register(Register::CH)
+            )),
+            eq(true)
+        );
+        let assembly = compiled("int f(char a, char b) { char c = a + b; char d = a - b; return c * d; }");
+        assert_that!(assembly.contains("\tmov\tdl, al\n\tmov\tcl, bl\n"), eq(true));
+    }
+
+    #[test]
+    fn values_live_across_calls_are_in_si_and_di_which_are_saved() {
+        let input = "int g(int n); int f(int a, int b) { int c = a * b; return g(a) + g(b) + c; }";
+        let (_, callee_saved) = allocated(input);
+        assert_that!(callee_saved, eq(vec![Register::SI, Register::DI]));
+        let assembly = compiled(input);
+        assert_that!(assembly.contains("\tsub\tsp, 2\n\tpush\tsi\n\tpush\tdi\n"), eq(true));
+        assert_that!(assembly.contains("\tpop\tdi\n\tpop\tsi\n\tmov\tsp, bp\n"), eq(true));
+        // With SI and DI taken, the result of the first call is spilled across the second.
+        assert_that!(assembly.contains("\tcall\tg\n\tmov\tWORD PTR [bp-2], ax\n"), eq(true));
+    }
+
+    #[test]
+    fn the_shift_count_and_the_dividend_keep_to_their_registers() {
+        let assembly = compiled("int f(int a, int b) { return (a << b) + a / b; }");
+        // 'a' arrives in AX, which the shift and the division need, and 'b' in BX, which is
+        // copied to CX for the count; so 'a' moves out to DX, until 'cwd' needs DX.
+        assert_that!(assembly.contains("\tmov\tdx, ax\n\tmov\tcx, bx\n\tmov\tax, dx\n\tshl\tax, cl\n"), eq(true));
+        assert_that!(assembly.contains("\tmov\tax, dx\n\tcwd\n\tidiv\tbx\n"), eq(true));
+    }
+
+    #[test]
+    fn static_variables_stay_in_memory() {
+        let (instructions, _) = allocated("int total; int f(int a) { total = total + a; return total; }");
+        assert_that!(pseudos(&instructions), eq(3));
+    }
+
+    #[test]
+    fn without_enough_registers_values_are_spilled() {
+        let (instructions, _) = allocated(
+            "int f(int a, int b, int c, int d) {
+    int e = a * b, g = b * c, h = c * d, i = d * a, j = a - b, k = b - c, l = c - d, m = d - a;
+    return e + g + h + i + j + k + l + m + a + b + c + d; }",
+        );
+        assert_that!(pseudos(&instructions), gt(0));
+    }
+}
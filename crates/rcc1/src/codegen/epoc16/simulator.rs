@@ -0,0 +1,436 @@
+//! A simulator for the subset of the 8086 used by the code generator, for testing generated
+//! programs without an assembler or an emulator. It runs the assembly AST, after stack frame
+//! allocation, and gives the value 'main' returns.
+//!
+//! Each instruction occupies one address, and the prologue that emission writes at the start of
+//! each function, saving BP and pointing it at the frame, is done by 'call'. The small model's
+//! single 64K segment holds the static variables at its bottom and the stack at its top. So that
+//! code relying on registers surviving a call is caught, 'ret' spoils all of them but those that
+//! may hold the result, AX and DX, and the callee-saved SI and DI, which it checks the function
+//! has preserved.
+
+use std::collections::HashMap;
+
+use crate::codegen::epoc16::{
+    AssemblyType, BinaryOperator, ConditionCode, Instruction, Operand, Program, Register, TopLevel,
+    UnaryOperator,
+};
+
+const DATA_BASE: u16 = 0x0100;
+/// The return address that ends the simulation, when 'main' returns to it.
+const HALT: u16 = 0xffff;
+const SPOILED: u16 = 0xdead;
+const MAX_STEPS: usize = 50_000_000;
+
+#[derive(Default)]
+struct Flags {
+    zero: bool,
+    sign: bool,
+    carry: bool,
+    overflow: bool,
+}
+
+pub struct Simulator<'a> {
+    code: Vec<&'a Instruction>,
+    addresses: HashMap<&'a str, u16>,
+    memory: Vec<u8>,
+    registers: HashMap<Register, u16>,
+    flags: Flags,
+    /// SI and DI as each active function was called with them.
+    preserved: Vec<(u16, u16)>,
+    /// The lowest address the stack has reached.
+    pub stack_low: u16,
+}
+
+impl<'a> Simulator<'a> {
+    pub fn new(program: &'a Program) -> Self {
+        let mut simulator = Simulator {
+            code: vec![],
+            addresses: HashMap::new(),
+            memory: vec![0; 0x10000],
+            registers: HashMap::new(),
+            flags: Flags::default(),
+            preserved: vec![],
+            stack_low: 0,
+        };
+        let mut data = DATA_BASE;
+        for top_level in &program.top_level {
+            match top_level {
+                TopLevel::Function(function) => {
+                    simulator.addresses.insert(&function.name, simulator.code.len() as u16);
+                    for instruction in &function.instructions {
+                        if let Instruction::Label(label) = instruction {
+                            simulator.addresses.insert(label, simulator.code.len() as u16);
+                        }
+                        simulator.code.push(instruction);
+                    }
+                }
+                TopLevel::StaticVariable(variable) => {
+                    let size = variable.assembly_type.size() as u16;
+                    data = data.div_ceil(size) * size;
+                    simulator.addresses.insert(&variable.name, data);
+                    match variable.assembly_type {
+                        AssemblyType::Doubleword => {
+                            simulator.write(data, AssemblyType::Word, variable.init as u16);
+                            simulator.write(data + 2, AssemblyType::Word, (variable.init >> 16) as u16);
+                        }
+                        assembly_type => simulator.write(data, assembly_type, variable.init as u16),
+                    }
+                    data += size;
+                }
+            }
+        }
+        simulator
+    }
+
+    fn register(&self, register: Register) -> u16 {
+        *self.registers.get(&register).unwrap_or(&SPOILED)
+    }
+
+    fn set_register(&mut self, register: Register, value: u16) {
+        self.registers.insert(register, value);
+    }
+
+    /// The word register whose high byte a register is, if it is one.
+    fn high_byte_of(register: Register) -> Option<Register> {
+        match register {
+            Register::AH => Some(Register::AX),
+            Register::BH => Some(Register::BX),
+            Register::CH => Some(Register::CX),
+            Register::DH => Some(Register::DX),
+            _ => None,
+        }
+    }
+
+    fn read(&self, address: u16, assembly_type: AssemblyType) -> u16 {
+        let low = self.memory[address as usize] as u16;
+        match assembly_type {
+            AssemblyType::Byte => low,
+            AssemblyType::Word | AssemblyType::Doubleword => {
+                low | (self.memory[address.wrapping_add(1) as usize] as u16) << 8
+            }
+        }
+    }
+
+    fn write(&mut self, address: u16, assembly_type: AssemblyType, value: u16) {
+        self.memory[address as usize] = value as u8;
+        if assembly_type != AssemblyType::Byte {
+            self.memory[address.wrapping_add(1) as usize] = (value >> 8) as u8;
+        }
+    }
+
+    fn address(&self, name: &str) -> Result<u16, String> {
+        self.addresses.get(name).copied().ok_or(format!("'{}' is not defined", name))
+    }
+
+    fn memory_address(&self, operand: &Operand) -> Result<Option<u16>, String> {
+        match operand {
+            Operand::Stack(offset) => Ok(Some(self.register(Register::BP).wrapping_add(*offset as u16))),
+            Operand::Data(name, offset) => Ok(Some(self.address(name)?.wrapping_add(*offset as u16))),
+            Operand::Pseudo(name) | Operand::PseudoMem(name, _) => {
+                Err(format!("pseudo-register '{}' was not replaced", name))
+            }
+            Operand::Immediate(_) | Operand::Register(_) => Ok(None),
+        }
+    }
+
+    fn get(&self, operand: &Operand, assembly_type: AssemblyType) -> Result<u16, String> {
+        let mask = if assembly_type == AssemblyType::Byte { 0xff } else { 0xffff };
+        match operand {
+            Operand::Immediate(value) => Ok(*value as u16 & mask),
+            Operand::Register(register) if Self::high_byte_of(*register).is_some() => {
+                Ok(self.register(Self::high_byte_of(*register).unwrap()) >> 8)
+            }
+            Operand::Register(register) => Ok(self.register(*register) & mask),
+            _ => Ok(self.read(self.memory_address(operand)?.unwrap(), assembly_type)),
+        }
+    }
+
+    fn set(&mut self, operand: &Operand, assembly_type: AssemblyType, value: u16) -> Result<(), String> {
+        match operand {
+            Operand::Immediate(_) => Err("an immediate is not a destination".to_owned()),
+            Operand::Register(register) if Self::high_byte_of(*register).is_some() => {
+                let word = Self::high_byte_of(*register).unwrap();
+                let old = self.register(word);
+                self.set_register(word, (old & 0xff) | (value & 0xff) << 8);
+                Ok(())
+            }
+            Operand::Register(register) if assembly_type == AssemblyType::Byte => {
+                let old = self.register(*register);
+                self.set_register(*register, (old & 0xff00) | (value & 0xff));
+                Ok(())
+            }
+            Operand::Register(register) => {
+                self.set_register(*register, value);
+                Ok(())
+            }
+            _ => {
+                let address = self.memory_address(operand)?.unwrap();
+                self.write(address, assembly_type, value);
+                Ok(())
+            }
+        }
+    }
+
+    fn push(&mut self, value: u16) {
+        let sp = self.register(Register::SP).wrapping_sub(2);
+        self.set_register(Register::SP, sp);
+        self.stack_low = self.stack_low.min(sp);
+        self.write(sp, AssemblyType::Word, value);
+    }
+
+    fn pop(&mut self) -> u16 {
+        let sp = self.register(Register::SP);
+        self.set_register(Register::SP, sp.wrapping_add(2));
+        self.read(sp, AssemblyType::Word)
+    }
+
+    /// The number of bits in an operand, and the mask of them.
+    fn width(assembly_type: AssemblyType) -> (u32, u32) {
+        match assembly_type {
+            AssemblyType::Byte => (8, 0xff),
+            AssemblyType::Word | AssemblyType::Doubleword => (16, 0xffff),
+        }
+    }
+
+    /// Sets the flags as 'sub' and 'cmp' do, for 'a - b', less the borrow for 'sbb'.
+    fn subtract_with(&mut self, a: u16, b: u16, borrow: bool, assembly_type: AssemblyType) -> u16 {
+        let (bits, mask) = Self::width(assembly_type);
+        let (a, b) = (a as u32 & mask, b as u32 & mask);
+        let result = a.wrapping_sub(b).wrapping_sub(borrow as u32) & mask;
+        let sign = 1 << (bits - 1);
+        self.flags = Flags {
+            zero: result == 0,
+            sign: result & sign != 0,
+            carry: b + borrow as u32 > a,
+            overflow: ((a ^ b) & (a ^ result) & sign) != 0,
+        };
+        result as u16
+    }
+
+    fn subtract(&mut self, a: u16, b: u16, assembly_type: AssemblyType) -> u16 {
+        self.subtract_with(a, b, false, assembly_type)
+    }
+
+    /// Sets the flags as 'add' does, adding the carry too for 'adc'.
+    fn add_with(&mut self, a: u16, b: u16, carry: bool, assembly_type: AssemblyType) -> u16 {
+        let (bits, mask) = Self::width(assembly_type);
+        let (a, b) = (a as u32 & mask, b as u32 & mask);
+        let result = (a + b + carry as u32) & mask;
+        let sign = 1 << (bits - 1);
+        self.flags = Flags {
+            zero: result == 0,
+            sign: result & sign != 0,
+            carry: a + b + carry as u32 > mask,
+            overflow: ((a ^ result) & (b ^ result) & sign) != 0,
+        };
+        result as u16
+    }
+
+    fn add(&mut self, a: u16, b: u16, assembly_type: AssemblyType) -> u16 {
+        self.add_with(a, b, false, assembly_type)
+    }
+
+    fn logical(&mut self, result: u16, assembly_type: AssemblyType) -> u16 {
+        self.shifted(result, false, assembly_type)
+    }
+
+    /// Sets the flags for a result, with the carry given, as the bit last shifted out.
+    fn shifted(&mut self, result: u16, carry: bool, assembly_type: AssemblyType) -> u16 {
+        let (bits, mask) = Self::width(assembly_type);
+        let result = (result as u32 & mask) as u16;
+        self.flags = Flags {
+            zero: result == 0,
+            sign: result >> (bits - 1) & 1 != 0,
+            carry,
+            overflow: false,
+        };
+        result
+    }
+
+    /// Shifts as 'shl', 'shr' and 'sar' do, by a count of at least 1.
+    fn shift(&mut self, operator: BinaryOperator, a: u16, count: u32, assembly_type: AssemblyType) -> u16 {
+        let (bits, mask) = Self::width(assembly_type);
+        let a = a as u32 & mask;
+        // The value, with the bits shifted out below it, or sign extended above it.
+        let (result, carry) = match operator {
+            BinaryOperator::Shl => {
+                let wide = (a as u64) << count.min(40);
+                (wide as u32, wide >> bits & 1 != 0)
+            }
+            BinaryOperator::Shr => {
+                let wide = ((a as u64) << 1) >> count.min(40);
+                ((wide >> 1) as u32, wide & 1 != 0)
+            }
+            _ => {
+                let signed = (a << (32 - bits)) as i32 >> (32 - bits);
+                let wide = ((signed as i64) << 1) >> count.min(40);
+                ((wide >> 1) as u32, wide & 1 != 0)
+            }
+        };
+        self.shifted(result as u16, carry, assembly_type)
+    }
+
+    /// Rotates through the carry by 1, as 'rcl' and 'rcr' do, leaving the other flags.
+    fn rotate(&mut self, operator: BinaryOperator, a: u16, assembly_type: AssemblyType) -> u16 {
+        let (bits, mask) = Self::width(assembly_type);
+        let a = a as u32 & mask;
+        let carry = self.flags.carry as u32;
+        let (result, out) = match operator {
+            BinaryOperator::Rcl => ((a << 1 | carry) & mask, a >> (bits - 1) & 1),
+            _ => (a >> 1 | carry << (bits - 1), a & 1),
+        };
+        self.flags.carry = out != 0;
+        result as u16
+    }
+
+    fn holds(&self, condition: ConditionCode) -> bool {
+        let Flags { zero, sign, carry, overflow } = self.flags;
+        match condition {
+            ConditionCode::E => zero,
+            ConditionCode::NE => !zero,
+            ConditionCode::L => sign != overflow,
+            ConditionCode::LE => zero || sign != overflow,
+            ConditionCode::G => !zero && sign == overflow,
+            ConditionCode::GE => sign == overflow,
+            ConditionCode::B => carry,
+            ConditionCode::BE => carry || zero,
+            ConditionCode::A => !carry && !zero,
+            ConditionCode::AE => !carry,
+        }
+    }
+
+    /// Does what 'call' and the called function's prologue do, giving its address.
+    fn call(&mut self, name: &str, return_address: u16) -> Result<u16, String> {
+        let target = self.address(name)?;
+        self.push(return_address);
+        self.push(self.register(Register::BP));
+        self.preserved.push((self.register(Register::SI), self.register(Register::DI)));
+        self.set_register(Register::BP, self.register(Register::SP));
+        Ok(target)
+    }
+
+    /// Runs 'main', giving the value it returns.
+    pub fn run(&mut self) -> Result<i16, String> {
+        self.set_register(Register::SP, 0);
+        self.stack_low = 0xffff;
+        let mut ip = self.call("main", HALT)?;
+        for _ in 0..MAX_STEPS {
+            let instruction = *self
+                .code
+                .get(ip as usize)
+                .ok_or(format!("ran off the end of the code at {}", ip))?;
+            match self.step(instruction, ip) {
+                Ok(Some(HALT)) => return Ok(self.register(Register::AX) as i16),
+                Ok(Some(next)) => ip = next,
+                Ok(None) => ip += 1,
+                Err(e) => return Err(format!("{} at {} ({:?})", e, ip, instruction)),
+            }
+        }
+        Err("too many steps".to_owned())
+    }
+
+    /// Executes an instruction, giving the address of the next one if it transfers control.
+    fn step(&mut self, instruction: &Instruction, ip: u16) -> Result<Option<u16>, String> {
+        match instruction {
+            Instruction::Mov(t, src, dst) => {
+                let value = self.get(src, *t)?;
+                self.set(dst, *t, value)?;
+            }
+            Instruction::Cbw => {
+                let al = self.register(Register::AX) as u8 as i8;
+                self.set_register(Register::AX, al as i16 as u16);
+            }
+            Instruction::Cwd => {
+                let ax = self.register(Register::AX) as i16;
+                self.set_register(Register::DX, if ax < 0 { 0xffff } else { 0 });
+            }
+            Instruction::Unary(operator, t, dst) => {
+                let value = self.get(dst, *t)?;
+                let result = match operator {
+                    UnaryOperator::Neg => self.subtract(0, value, *t),
+                    UnaryOperator::Not => !value,
+                };
+                self.set(dst, *t, result)?;
+            }
+            Instruction::Binary(operator, t, src, dst) => {
+                let (a, b) = (self.get(dst, *t)?, self.get(src, *t)?);
+                let result = match operator {
+                    BinaryOperator::Add => self.add(a, b, *t),
+                    BinaryOperator::Adc => self.add_with(a, b, self.flags.carry, *t),
+                    BinaryOperator::Sub => self.subtract(a, b, *t),
+                    BinaryOperator::Sbb => self.subtract_with(a, b, self.flags.carry, *t),
+                    BinaryOperator::And => self.logical(a & b, *t),
+                    BinaryOperator::Or => self.logical(a | b, *t),
+                    BinaryOperator::Xor => self.logical(a ^ b, *t),
+                    // A shift by zero leaves the flags.
+                    BinaryOperator::Shl | BinaryOperator::Shr | BinaryOperator::Sar if b & 0xff == 0 => a,
+                    BinaryOperator::Shl | BinaryOperator::Shr | BinaryOperator::Sar => {
+                        self.shift(*operator, a, b as u32 & 0xff, *t)
+                    }
+                    BinaryOperator::Rcl | BinaryOperator::Rcr => self.rotate(*operator, a, *t),
+                };
+                self.set(dst, *t, result)?;
+            }
+            Instruction::Cmp(t, src, dst) => {
+                let (a, b) = (self.get(dst, *t)?, self.get(src, *t)?);
+                self.subtract(a, b, *t);
+            }
+            Instruction::Imul(src) => {
+                let product = self.register(Register::AX) as i16 as i32 * self.get(src, AssemblyType::Word)? as i16 as i32;
+                self.set_register(Register::AX, product as u16);
+                self.set_register(Register::DX, (product >> 16) as u16);
+            }
+            Instruction::Idiv(src) => {
+                let dividend = (self.register(Register::DX) as u32) << 16 | self.register(Register::AX) as u32;
+                let divisor = self.get(src, AssemblyType::Word)? as i16 as i32;
+                let dividend = dividend as i32;
+                if divisor == 0 || !(-32768..=32767).contains(&(dividend / divisor)) {
+                    return Err("divide overflow".to_owned());
+                }
+                self.set_register(Register::AX, (dividend / divisor) as u16);
+                self.set_register(Register::DX, (dividend % divisor) as u16);
+            }
+            Instruction::Div(src) => {
+                let dividend = (self.register(Register::DX) as u32) << 16 | self.register(Register::AX) as u32;
+                let divisor = self.get(src, AssemblyType::Word)? as u32;
+                if divisor == 0 || dividend / divisor > 0xffff {
+                    return Err("divide overflow".to_owned());
+                }
+                self.set_register(Register::AX, (dividend / divisor) as u16);
+                self.set_register(Register::DX, (dividend % divisor) as u16);
+            }
+            Instruction::Jmp(label) => return Ok(Some(self.address(label)?)),
+            Instruction::JmpCC(condition, label) => {
+                if self.holds(*condition) {
+                    return Ok(Some(self.address(label)?));
+                }
+            }
+            Instruction::Label(_) => {}
+            Instruction::Push(src) => {
+                let value = self.get(src, AssemblyType::Word)?;
+                self.push(value);
+            }
+            Instruction::Pop(dst) => {
+                let value = self.pop();
+                self.set_register(*dst, value);
+            }
+            Instruction::Call(name) => return Ok(Some(self.call(name, ip + 1)?)),
+            Instruction::Ret => {
+                self.set_register(Register::SP, self.register(Register::BP));
+                let bp = self.pop();
+                self.set_register(Register::BP, bp);
+                let next = self.pop();
+                let preserved = self.preserved.pop().ok_or("'ret' without a call")?;
+                if preserved != (self.register(Register::SI), self.register(Register::DI)) {
+                    return Err("SI or DI was not preserved".to_owned());
+                }
+                for register in [Register::BX, Register::CX] {
+                    self.set_register(register, SPOILED);
+                }
+                return Ok(Some(next));
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,115 @@
+//! Replaces pseudo-registers with their locations: static variables are addressed by their
+//! symbols, and everything else is given a slot in the function's stack frame, below the saved
+//! BP. Words and 'long's are aligned to words, as the 8086 takes an extra bus cycle for a word at
+//! an odd address, and a 'long' is only ever accessed a word at a time. The
+//! frame is then allocated at the start of the function, keeping SP even, and below it the
+//! callee-saved registers the function uses are pushed, to be popped before each return.
+
+use std::collections::HashMap;
+
+use common::data_model::DataModel;
+
+use crate::codegen::epoc16::{
+    AssemblyType, BinaryOperator, Instruction, Operand, Program, Register, TopLevel,
+};
+use crate::semantic::symbol_table::{IdentifierAttributes, SymbolTable};
+
+struct Frame<'a> {
+    symbols: &'a SymbolTable,
+    data_model: &'a DataModel,
+    slots: HashMap<String, i64>,
+    /// The size of the frame so far, in bytes.
+    size: i64,
+}
+
+pub fn allocate(program: &mut Program, symbols: &SymbolTable, data_model: &DataModel) {
+    for top_level in &mut program.top_level {
+        if let TopLevel::Function(function) = top_level {
+            let mut frame = Frame {
+                symbols,
+                data_model,
+                slots: HashMap::new(),
+                size: 0,
+            };
+            for instruction in &mut function.instructions {
+                frame.instruction(instruction);
+            }
+            let size = (frame.size + 1) / 2 * 2;
+            let mut prologue = vec![];
+            if size != 0 {
+                prologue.push(Instruction::Binary(
+                    BinaryOperator::Sub,
+                    AssemblyType::Word,
+                    Operand::Immediate(size),
+                    Operand::Register(Register::SP),
+                ));
+            }
+            for register in &function.callee_saved {
+                prologue.push(Instruction::Push(Operand::Register(*register)));
+            }
+            let instructions = std::mem::take(&mut function.instructions);
+            function.instructions = prologue;
+            for instruction in instructions {
+                if instruction == Instruction::Ret {
+                    for register in function.callee_saved.iter().rev() {
+                        function.instructions.push(Instruction::Pop(*register));
+                    }
+                }
+                function.instructions.push(instruction);
+            }
+        }
+    }
+}
+
+impl Frame<'_> {
+    fn operand(&mut self, operand: &mut Operand) {
+        let (name, offset) = match operand {
+            Operand::Pseudo(name) => (name.clone(), 0),
+            Operand::PseudoMem(name, offset) => (name.clone(), *offset),
+            _ => return,
+        };
+        let symbol = self
+            .symbols
+            .get(&name)
+            .expect("every variable is in the symbol table");
+        *operand = match symbol.attributes {
+            IdentifierAttributes::Static { .. } => Operand::Data(name, offset),
+            _ => match self.slots.get(&name) {
+                Some(slot) => Operand::Stack(slot + offset),
+                None => {
+                    let size = AssemblyType::of(&symbol.symbol_type, self.data_model)
+                        .expect("every variable is at most 32 bits wide")
+                        .size();
+                    let alignment = size.min(2);
+                    self.size = (self.size + size + alignment - 1) / alignment * alignment;
+                    self.slots.insert(name, -self.size);
+                    Operand::Stack(-self.size + offset)
+                }
+            },
+        };
+    }
+
+    fn instruction(&mut self, instruction: &mut Instruction) {
+        match instruction {
+            Instruction::Mov(_, src, dst)
+            | Instruction::Binary(_, _, src, dst)
+            | Instruction::Cmp(_, src, dst) => {
+                self.operand(src);
+                self.operand(dst);
+            }
+            Instruction::Unary(_, _, operand)
+            | Instruction::Imul(operand)
+            | Instruction::Idiv(operand)
+            | Instruction::Div(operand)
+            | Instruction::Push(operand) => self.operand(operand),
+            Instruction::Cbw
+            | Instruction::Cwd
+            | Instruction::Jmp(_)
+            | Instruction::JmpCC(_, _)
+            | Instruction::Label(_)
+            | Instruction::Pop(_)
+            | Instruction::Call(_)
+            | Instruction::Ret => {}
+        }
+    }
+}
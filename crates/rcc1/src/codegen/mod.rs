@@ -0,0 +1,6 @@
+//! Code generation: translates the IR into assembly language for each target.
+
+pub mod calling_convention;
+pub mod epoc16;
+pub mod transputer;
+pub mod x86_64;
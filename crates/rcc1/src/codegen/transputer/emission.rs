@@ -0,0 +1,154 @@
+//! Writes the assembly program as TMASM source.
+//!
+//! The operands of the direct functions are written as signed decimal numbers, or as expressions
+//! of labels, and TMASM encodes each with the shortest sequence of 'pfix' and 'nfix' prefixes,
+//! iterating until the lengths of instructions whose operands depend on labels settle. So operands
+//! are never split into prefixes here, and negative values are written as such, rather than as
+//! the unsigned words with the same bits, which would need eight prefixes.
+//!
+//! Static variables follow the code, with words aligned for 'ldnl' and 'stnl'. The FPU entry
+//! operations are written as the 'ldc' of their numbers and 'fpentry'.
+//!
+//! Functions and variables with external linkage are declared 'PUBLIC', so that other modules can
+//! refer to them; the rest stay local to the module.
+
+use std::fmt::{Display, Formatter};
+
+use crate::codegen::transputer::{
+    DataSize, Instruction, Operand, Program, StaticVariable, TopLevel, Workspace,
+};
+
+impl Display for Program {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "\t.TRANSPUTER")?;
+        for top_level in &self.top_level {
+            if let TopLevel::Function(function) = top_level {
+                write!(f, "{}", Label { name: &function.name, global: function.global })?;
+                for instruction in &function.instructions {
+                    writeln!(f, "{}", instruction)?;
+                }
+            }
+        }
+        for top_level in &self.top_level {
+            if let TopLevel::StaticVariable(variable) = top_level {
+                write!(f, "{}", variable)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The label of a function or static variable, declared public if it has external linkage.
+struct Label<'a> {
+    name: &'a str,
+    global: bool,
+}
+
+impl Display for Label<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.global {
+            writeln!(f, "\tPUBLIC {}", self.name)?;
+        }
+        writeln!(f, "{}:", self.name)
+    }
+}
+
+impl Display for StaticVariable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let label = Label { name: &self.name, global: self.global };
+        match self.size {
+            DataSize::Byte => writeln!(f, "{}\tDB {}", label, self.init),
+            DataSize::Word => writeln!(f, "\tALIGN 4\n{}\tDD {}", label, self.init),
+            DataSize::Double => writeln!(
+                f,
+                "\tALIGN 4\n{}\tDD {}\n\tDD {}",
+                label,
+                self.init as i32,
+                (self.init >> 32) as i32
+            ),
+        }
+    }
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operand::Constant(value) => write!(f, "{}", value),
+            Operand::Distance(symbol, label) => write!(f, "{} - {}", symbol, label),
+        }
+    }
+}
+
+impl Display for Workspace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Workspace::Offset(offset) => write!(f, "{}", offset),
+            _ => unreachable!("workspace slot {:?} was not allocated", self),
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instruction::Ldc(operand) => write!(f, "\tldc {}", operand),
+            Instruction::Ldl(workspace) => write!(f, "\tldl {}", workspace),
+            Instruction::Stl(workspace) => write!(f, "\tstl {}", workspace),
+            Instruction::Ldlp(workspace) => write!(f, "\tldlp {}", workspace),
+            Instruction::Ldnl(offset) => write!(f, "\tldnl {}", offset),
+            Instruction::Stnl(offset) => write!(f, "\tstnl {}", offset),
+            Instruction::Adc(value) => write!(f, "\tadc {}", value),
+            Instruction::Eqc(value) => write!(f, "\teqc {}", value),
+            Instruction::Ajw(words) => write!(f, "\tajw {}", words),
+            Instruction::J(label) => write!(f, "\tj {}", label),
+            Instruction::Cj(label) => write!(f, "\tcj {}", label),
+            Instruction::Call(name) => write!(f, "\tcall {}", name),
+            Instruction::Ldpi => write!(f, "\tldpi"),
+            Instruction::Lb => write!(f, "\tlb"),
+            Instruction::Sb => write!(f, "\tsb"),
+            Instruction::Rev => write!(f, "\trev"),
+            Instruction::Pop => write!(f, "\tpop"),
+            Instruction::Mint => write!(f, "\tmint"),
+            Instruction::Sum => write!(f, "\tsum"),
+            Instruction::Diff => write!(f, "\tdiff"),
+            Instruction::Prod => write!(f, "\tprod"),
+            Instruction::Div => write!(f, "\tdiv"),
+            Instruction::Rem => write!(f, "\trem"),
+            Instruction::Ldiv => write!(f, "\tldiv"),
+            Instruction::And => write!(f, "\tand"),
+            Instruction::Or => write!(f, "\tor"),
+            Instruction::Xor => write!(f, "\txor"),
+            Instruction::Not => write!(f, "\tnot"),
+            Instruction::Shl => write!(f, "\tshl"),
+            Instruction::Shr => write!(f, "\tshr"),
+            Instruction::Lshr => write!(f, "\tlshr"),
+            Instruction::Xdble => write!(f, "\txdble"),
+            Instruction::Xword => write!(f, "\txword"),
+            Instruction::Gt => write!(f, "\tgt"),
+            Instruction::Ret => write!(f, "\tret"),
+            Instruction::Fpldnlsn => write!(f, "\tfpldnlsn"),
+            Instruction::Fpldnldb => write!(f, "\tfpldnldb"),
+            Instruction::Fpstnlsn => write!(f, "\tfpstnlsn"),
+            Instruction::Fpstnldb => write!(f, "\tfpstnldb"),
+            Instruction::Fpldzerosn => write!(f, "\tfpldzerosn"),
+            Instruction::Fpldzerodb => write!(f, "\tfpldzerodb"),
+            Instruction::Fpadd => write!(f, "\tfpadd"),
+            Instruction::Fpsub => write!(f, "\tfpsub"),
+            Instruction::Fpmul => write!(f, "\tfpmul"),
+            Instruction::Fpdiv => write!(f, "\tfpdiv"),
+            Instruction::Fprev => write!(f, "\tfprev"),
+            Instruction::Fpi32tor32 => write!(f, "\tfpi32tor32"),
+            Instruction::Fpi32tor64 => write!(f, "\tfpi32tor64"),
+            Instruction::Fpb32tor64 => write!(f, "\tfpb32tor64"),
+            Instruction::Fpint => write!(f, "\tfpint"),
+            Instruction::Fpstnli32 => write!(f, "\tfpstnli32"),
+            Instruction::Fpgt => write!(f, "\tfpgt"),
+            Instruction::Fpeq => write!(f, "\tfpeq"),
+            Instruction::Fpordered => write!(f, "\tfpordered"),
+            Instruction::Fpurz => write!(f, "\tldc 6\n\tfpentry"),
+            Instruction::Fpur32tor64 => write!(f, "\tldc 7\n\tfpentry"),
+            Instruction::Fpur64tor32 => write!(f, "\tldc 8\n\tfpentry"),
+            Instruction::Label(label) => write!(f, "{}:", label),
+        }
+    }
+}
@@ -0,0 +1,955 @@
+//! Translates the IR into sequences that load operands onto the evaluation stack, operate on them,
+//! and store the results.
+//!
+//! The pure instructions computing temporaries that are used just once, as the IR generator emits
+//! for the subexpressions of an expression, are not translated where they stand, but combined into
+//! a tree for the instruction that uses the temporary. The trees are scheduled on the three stack
+//! registers as a whole, spilling subtrees to the workspace only where they need more, so the
+//! temporaries mostly never reach the workspace. Before any instruction with an effect, e.g. a
+//! store to a variable, a call or a jump, the trees still waiting for their use are evaluated into
+//! their temporaries, so that they read the variables as they were.
+//!
+//! Local variables are kept in whole workspace words, holding their values sign or zero extended
+//! according to their types, so they can be loaded and compared as words; only conversions to a
+//! narrower type, and stores to static 'char' or 'short' variables, need to mask or extend.
+//!
+//! The T425 arithmetic instructions 'add', 'sub' and 'mul' set the error flag on overflow, so the
+//! modulo arithmetic instructions 'sum', 'diff' and 'prod' are used. There are no unsigned
+//! comparisons or divisions, nor an arithmetic right shift: unsigned values are compared by
+//! inverting their sign bits, divided with the double length 'ldiv', and signed values are
+//! shifted right by sign extending them to double length for 'lshr'.
+//!
+//! Floating point reaches here only for the T800, whose FPU loads and stores its values through
+//! addresses in A: a 'float' variable is kept in a word, and a 'double' in two, addressed with
+//! 'ldlp'. Its temporaries are deferred as floating trees in the same way. The FPU converts only
+//! between floating values and integers in memory, so those conversions go through the workspace,
+//! and floating constants other than zero are loaded from a pool of static words.
+
+use std::collections::HashMap;
+
+use common::data_model::DataModel;
+
+use crate::ast::{Constant, Type};
+use crate::codegen::calling_convention::{CallingConvention, Location};
+use crate::codegen::transputer::scheduling::{self, FloatTree, Tree};
+use crate::codegen::transputer::{
+    DataSize, Function, Inmos, Instruction, Operand, Program, StaticVariable, TopLevel, Workspace,
+};
+use crate::ir;
+use crate::semantic::symbol_table::{IdentifierAttributes, SymbolTable};
+
+struct Generator<'a> {
+    symbols: &'a SymbolTable,
+    data_model: &'a DataModel,
+    params: &'a [String],
+    instructions: Vec<Instruction>,
+    /// Numbers the labels that static variables are addressed from.
+    anchors: &'a mut usize,
+    /// The number of instructions defining and using each variable of the function.
+    definitions: HashMap<String, usize>,
+    uses: HashMap<String, usize>,
+    /// The trees computing temporaries that are yet to be used, in the order they were defined.
+    pending: Vec<(String, Tree)>,
+    pending_floats: Vec<(String, FloatTree)>,
+    /// The pool of floating constants, shared by the program's functions.
+    constants: &'a mut Vec<StaticVariable>,
+    /// Numbers the temporaries that subtrees are spilled to.
+    spills: usize,
+}
+
+pub fn generate(program: &ir::Program, symbols: &SymbolTable, data_model: &DataModel) -> Program {
+    let mut anchors = 0;
+    let mut constants = vec![];
+    let mut top_level: Vec<TopLevel> = program
+        .top_level
+        .iter()
+        .map(|top_level| match top_level {
+            ir::TopLevel::Function(function) => {
+                let mut generator = Generator {
+                    symbols,
+                    data_model,
+                    params: &function.params,
+                    instructions: vec![],
+                    anchors: &mut anchors,
+                    definitions: HashMap::new(),
+                    uses: HashMap::new(),
+                    pending: vec![],
+                    pending_floats: vec![],
+                    constants: &mut constants,
+                    spills: 0,
+                };
+                TopLevel::Function(generator.function(function))
+            }
+            ir::TopLevel::StaticVariable(variable) => {
+                let value = variable.init.value;
+                let (size, init) = match variable.variable_type.bits(data_model) {
+                    8 => (DataSize::Byte, (value & 0xff) as i64),
+                    16 => (DataSize::Word, (value & 0xffff) as i64),
+                    64 => (DataSize::Double, value as i64),
+                    _ => (DataSize::Word, value as i32 as i64),
+                };
+                TopLevel::StaticVariable(StaticVariable {
+                    name: variable.name.clone(),
+                    global: variable.global,
+                    size,
+                    init,
+                })
+            }
+        })
+        .collect();
+    top_level.extend(constants.into_iter().map(TopLevel::StaticVariable));
+    Program { top_level }
+}
+
+/// An argument of a call, as the trees pushing its words, or as a floating tree whose value is
+/// to be stored in a temporary for its words to be loaded from.
+enum Argument {
+    Words(Vec<Tree>),
+    Floating(FloatTree),
+}
+
+/// The values an instruction reads, and the variable it writes, if any.
+fn operands(instruction: &ir::Instruction) -> (Vec<&ir::Value>, Option<&ir::Value>) {
+    match instruction {
+        ir::Instruction::Return(value) => (value.iter().collect(), None),
+        ir::Instruction::SignExtend { src, dst }
+        | ir::Instruction::ZeroExtend { src, dst }
+        | ir::Instruction::Truncate { src, dst }
+        | ir::Instruction::IntToFloat { src, dst }
+        | ir::Instruction::UIntToFloat { src, dst }
+        | ir::Instruction::FloatToInt { src, dst }
+        | ir::Instruction::FloatToUInt { src, dst }
+        | ir::Instruction::FloatExtend { src, dst }
+        | ir::Instruction::FloatTruncate { src, dst }
+        | ir::Instruction::Copy { src, dst }
+        | ir::Instruction::Unary { src, dst, .. } => (vec![src], Some(dst)),
+        ir::Instruction::Binary { src1, src2, dst, .. } => (vec![src1, src2], Some(dst)),
+        ir::Instruction::JumpIfZero(condition, _) | ir::Instruction::JumpIfNotZero(condition, _) => {
+            (vec![condition], None)
+        }
+        ir::Instruction::Jump(_) | ir::Instruction::Label(_) => (vec![], None),
+        ir::Instruction::FunctionCall { args, dst, .. } => (args.iter().collect(), dst.as_ref()),
+    }
+}
+
+impl Generator<'_> {
+    fn emit(&mut self, instruction: Instruction) {
+        self.instructions.push(instruction);
+    }
+
+    fn value_type(&self, value: &ir::Value) -> Type {
+        match value {
+            ir::Value::Constant(constant) => constant.constant_type.clone(),
+            ir::Value::Var(name) => self
+                .symbols
+                .get(name)
+                .expect("every variable is in the symbol table")
+                .symbol_type
+                .clone(),
+        }
+    }
+
+    fn is_signed(&self, value: &ir::Value) -> bool {
+        self.value_type(value).is_signed(self.data_model)
+    }
+
+    fn is_floating(&self, value: &ir::Value) -> bool {
+        self.value_type(value).is_floating()
+    }
+
+    fn is_double(&self, value: &ir::Value) -> bool {
+        self.value_type(value) == Type::Double
+    }
+
+    fn is_static(&self, name: &str) -> bool {
+        matches!(
+            self.symbols.get(name).map(|symbol| &symbol.attributes),
+            Some(IdentifierAttributes::Static { .. })
+        )
+    }
+
+    /// The workspace slot of a variable, or the lower of its two for a 'double'. The parameters
+    /// are numbered by the words they are passed in.
+    fn workspace(&self, name: &str) -> Workspace {
+        let mut words = 0;
+        for param in self.params {
+            if param == name {
+                return Workspace::Parameter(words);
+            }
+            words += if self.is_double(&ir::Value::Var(param.clone())) { 2 } else { 1 };
+        }
+        if self.is_double(&ir::Value::Var(name.to_owned())) {
+            Workspace::PseudoDouble(name.to_owned(), 0)
+        } else {
+            Workspace::Pseudo(name.to_owned())
+        }
+    }
+
+    /// A word of a variable's slots, 0 being the lower.
+    fn word(workspace: Workspace, word: usize) -> Workspace {
+        match workspace {
+            Workspace::PseudoDouble(name, _) => Workspace::PseudoDouble(name, word),
+            Workspace::Parameter(index) => Workspace::Parameter(index + word),
+            _ => workspace,
+        }
+    }
+
+    /// Whether the value of a variable can be left as a tree for its use: it must be a temporary,
+    /// defined and used just once.
+    fn is_deferred(&self, name: &str) -> bool {
+        name.starts_with('%')
+            && self.definitions.get(name) == Some(&1)
+            && self.uses.get(name) == Some(&1)
+    }
+
+    fn ldc(value: i32) -> Instruction {
+        Instruction::Ldc(Operand::Constant(value))
+    }
+
+    /// Loads the address of a static variable, relative to the instruction pointer so that the
+    /// code can be loaded anywhere.
+    fn address(&mut self, name: &str) -> Vec<Instruction> {
+        *self.anchors += 1;
+        let anchor = ir::generated_name("ldpi", *self.anchors);
+        vec![
+            Instruction::Ldc(Operand::Distance(name.to_owned(), anchor.clone())),
+            Instruction::Ldpi,
+            Instruction::Label(anchor),
+        ]
+    }
+
+    /// The tree pushing a value. Constants are held within the range of their type, so the
+    /// unsigned ones beyond the range of 'int' become the negative words with the same bits.
+    fn leaf(&mut self, value: &ir::Value) -> Tree {
+        match value {
+            ir::Value::Constant(constant) => Tree::leaf(vec![Self::ldc(constant.value as i32)]),
+            ir::Value::Var(name) => {
+                if let Some(index) = self.pending.iter().position(|(pending, _)| pending == name) {
+                    return self.pending.remove(index).1;
+                }
+                if !self.is_static(name) {
+                    return Tree::leaf(vec![Instruction::Ldl(self.workspace(name))]);
+                }
+                let value_type = self.value_type(value);
+                let mut instructions = self.address(name);
+                match value_type.bits(self.data_model) {
+                    8 => instructions.push(Instruction::Lb),
+                    _ => instructions.push(Instruction::Ldnl(0)),
+                }
+                if value_type.bits(self.data_model) < 32 && value_type.is_signed(self.data_model) {
+                    instructions.extend(self.sign_extension(&value_type));
+                    Tree::Leaf { instructions, depth: 2 }
+                } else {
+                    Tree::leaf(instructions)
+                }
+            }
+        }
+    }
+
+    /// Pops a value into a variable.
+    fn store(&mut self, dst: &ir::Value) {
+        let ir::Value::Var(name) = dst else {
+            unreachable!("the IR only stores to variables")
+        };
+        if self.is_static(name) {
+            match self.value_type(dst).bits(self.data_model) {
+                8 => {
+                    let address = self.address(name);
+                    self.instructions.extend(address);
+                    self.emit(Instruction::Sb);
+                }
+                bits => {
+                    if bits == 16 {
+                        self.emit(Self::ldc(0xffff));
+                        self.emit(Instruction::And);
+                    }
+                    let address = self.address(name);
+                    self.instructions.extend(address);
+                    self.emit(Instruction::Stnl(0));
+                }
+            }
+        } else {
+            self.emit(Instruction::Stl(self.workspace(name)));
+        }
+    }
+
+    /// The floating tree pushing a value onto the FPU's stack.
+    fn float_leaf(&mut self, value: &ir::Value) -> FloatTree {
+        let double = self.is_double(value);
+        match value {
+            ir::Value::Constant(constant) => self.float_constant(constant),
+            ir::Value::Var(name) => {
+                if let Some(index) = self.pending_floats.iter().position(|(pending, _)| pending == name) {
+                    return self.pending_floats.remove(index).1;
+                }
+                let mut instructions = if self.is_static(name) {
+                    self.address(name)
+                } else {
+                    vec![Instruction::Ldlp(self.workspace(name))]
+                };
+                instructions.push(FloatTree::load(double));
+                FloatTree::Leaf { instructions, double }
+            }
+        }
+    }
+
+    /// Loads a floating constant: zero with the FPU's own instructions, and the others from the
+    /// pool, where each is kept once.
+    fn float_constant(&mut self, constant: &Constant) -> FloatTree {
+        let double = constant.constant_type == Type::Double;
+        if constant.value == 0 {
+            return Self::float_zero(double);
+        }
+        let (size, init) = if double {
+            (DataSize::Double, constant.value as i64)
+        } else {
+            (DataSize::Word, constant.value as i32 as i64)
+        };
+        let name = match self.constants.iter().find(|pooled| pooled.size == size && pooled.init == init) {
+            Some(pooled) => pooled.name.clone(),
+            None => {
+                let name = ir::generated_name("fpconst", self.constants.len() + 1);
+                self.constants.push(StaticVariable { name: name.clone(), global: false, size, init });
+                name
+            }
+        };
+        let mut instructions = self.address(&name);
+        instructions.push(FloatTree::load(double));
+        FloatTree::Leaf { instructions, double }
+    }
+
+    fn float_zero(double: bool) -> FloatTree {
+        let zero = if double {
+            Instruction::Fpldzerodb
+        } else {
+            Instruction::Fpldzerosn
+        };
+        FloatTree::Leaf { instructions: vec![zero], double }
+    }
+
+    /// Pops FA into a variable.
+    fn store_float(&mut self, dst: &ir::Value) {
+        let ir::Value::Var(name) = dst else {
+            unreachable!("the IR only stores to variables")
+        };
+        if self.is_static(name) {
+            let address = self.address(name);
+            self.instructions.extend(address);
+        } else {
+            self.emit(Instruction::Ldlp(self.workspace(name)));
+        }
+        self.emit(FloatTree::store(self.is_double(dst)));
+    }
+
+    /// Sign extends the zero extended value of a narrow type, using 'xword', which takes the
+    /// type's sign bit.
+    fn sign_extension(&self, value_type: &Type) -> Vec<Instruction> {
+        vec![
+            Self::ldc(1 << (value_type.bits(self.data_model) - 1)),
+            Instruction::Xword,
+        ]
+    }
+
+    /// Converts a word to a narrower type, as C converts integers: its low bits are kept, and
+    /// sign extended if the type is signed.
+    fn narrow(&self, operand: Tree, to: &Type) -> Tree {
+        let bits = to.bits(self.data_model);
+        if bits >= 32 {
+            return operand;
+        }
+        let mut instructions = vec![Self::ldc(((1i64 << bits) - 1) as i32), Instruction::And];
+        if to.is_signed(self.data_model) {
+            instructions.extend(self.sign_extension(to));
+        }
+        Tree::Unary { operand: Box::new(operand), instructions, depth: 2 }
+    }
+
+    /// Evaluates a tree into A, first spilling any subtrees it cannot hold on the stack.
+    fn evaluate(&mut self, tree: Tree) {
+        let spills = &mut self.spills;
+        let tree = scheduling::fit(tree, &mut self.instructions, &mut || {
+            *spills += 1;
+            format!("%spill.{}", spills)
+        });
+        scheduling::evaluate(&tree, &mut self.instructions);
+    }
+
+    /// Evaluates a floating tree into FA, first spilling any subtrees it cannot hold on the FPU's
+    /// stack.
+    fn evaluate_float(&mut self, tree: FloatTree) {
+        let spills = &mut self.spills;
+        let tree = scheduling::fit_float(tree, &mut self.instructions, &mut || {
+            *spills += 1;
+            format!("%spill.{}", spills)
+        });
+        scheduling::evaluate_float(&tree, &mut self.instructions);
+    }
+
+    /// Evaluates the trees waiting for their uses into their temporaries.
+    fn flush(&mut self) {
+        for (name, tree) in std::mem::take(&mut self.pending) {
+            self.evaluate(tree);
+            self.emit(Instruction::Stl(self.workspace(&name)));
+        }
+        for (name, tree) in std::mem::take(&mut self.pending_floats) {
+            let double = tree.is_double();
+            self.evaluate_float(tree);
+            self.emit(Instruction::Ldlp(self.workspace(&name)));
+            self.emit(FloatTree::store(double));
+        }
+    }
+
+    fn function(&mut self, function: &ir::Function) -> Function {
+        for instruction in &function.body {
+            let (sources, dst) = operands(instruction);
+            for name in sources.into_iter().filter_map(|value| match value {
+                ir::Value::Var(name) => Some(name),
+                ir::Value::Constant(_) => None,
+            }) {
+                *self.uses.entry(name.clone()).or_default() += 1;
+            }
+            if let Some(ir::Value::Var(name)) = dst {
+                *self.definitions.entry(name.clone()).or_default() += 1;
+            }
+        }
+        for instruction in &function.body {
+            self.instruction(instruction);
+        }
+        self.flush();
+        Function {
+            name: function.name.clone(),
+            global: function.global,
+            instructions: std::mem::take(&mut self.instructions),
+        }
+    }
+
+    fn instruction(&mut self, instruction: &ir::Instruction) {
+        match instruction {
+            // A floating value is returned in FA, the others in A.
+            ir::Instruction::Return(Some(value)) if self.is_floating(value) => {
+                let tree = self.float_leaf(value);
+                self.flush();
+                self.evaluate_float(tree);
+                self.emit(Instruction::Ret);
+            }
+            ir::Instruction::Return(value) => {
+                let tree = value.as_ref().map(|value| self.leaf(value));
+                self.flush();
+                if let Some(tree) = tree {
+                    self.evaluate(tree);
+                }
+                self.emit(Instruction::Ret);
+            }
+            ir::Instruction::SignExtend { dst, .. }
+            | ir::Instruction::ZeroExtend { dst, .. }
+            | ir::Instruction::Truncate { dst, .. }
+            | ir::Instruction::IntToFloat { dst, .. }
+            | ir::Instruction::UIntToFloat { dst, .. }
+            | ir::Instruction::FloatExtend { dst, .. }
+            | ir::Instruction::FloatTruncate { dst, .. }
+            | ir::Instruction::Copy { dst, .. }
+            | ir::Instruction::Unary { dst, .. }
+            | ir::Instruction::Binary { dst, .. }
+                if self.is_floating(dst) =>
+            {
+                let tree = self.float_tree(instruction);
+                match dst {
+                    ir::Value::Var(name) if self.is_deferred(name) => {
+                        self.pending_floats.push((name.clone(), tree))
+                    }
+                    _ => {
+                        self.flush();
+                        self.evaluate_float(tree);
+                        self.store_float(dst);
+                    }
+                }
+            }
+            ir::Instruction::SignExtend { dst, .. }
+            | ir::Instruction::ZeroExtend { dst, .. }
+            | ir::Instruction::Truncate { dst, .. }
+            | ir::Instruction::FloatToInt { dst, .. }
+            | ir::Instruction::FloatToUInt { dst, .. }
+            | ir::Instruction::Copy { dst, .. }
+            | ir::Instruction::Unary { dst, .. }
+            | ir::Instruction::Binary { dst, .. } => {
+                let tree = self.tree(instruction);
+                match dst {
+                    ir::Value::Var(name) if self.is_deferred(name) => {
+                        self.pending.push((name.clone(), tree))
+                    }
+                    _ => {
+                        self.flush();
+                        self.evaluate(tree);
+                        self.store(dst);
+                    }
+                }
+            }
+            ir::Instruction::IntToFloat { .. }
+            | ir::Instruction::UIntToFloat { .. }
+            | ir::Instruction::FloatExtend { .. }
+            | ir::Instruction::FloatTruncate { .. } => unreachable!("floating conversions have floating results"),
+            ir::Instruction::Jump(label) => {
+                self.flush();
+                self.emit(Instruction::J(label.clone()));
+            }
+            // 'cj' jumps if A is zero.
+            ir::Instruction::JumpIfZero(condition, label) => {
+                let tree = if self.is_floating(condition) {
+                    Tree::Unary {
+                        operand: Box::new(self.float_is_zero(condition)),
+                        instructions: vec![Instruction::Eqc(0)],
+                        depth: 1,
+                    }
+                } else {
+                    self.leaf(condition)
+                };
+                self.flush();
+                self.evaluate(tree);
+                self.emit(Instruction::Cj(label.clone()));
+            }
+            ir::Instruction::JumpIfNotZero(condition, label) => {
+                let tree = if self.is_floating(condition) {
+                    self.float_is_zero(condition)
+                } else {
+                    Tree::Unary {
+                        operand: Box::new(self.leaf(condition)),
+                        instructions: vec![Instruction::Eqc(0)],
+                        depth: 1,
+                    }
+                };
+                self.flush();
+                self.evaluate(tree);
+                self.emit(Instruction::Cj(label.clone()));
+            }
+            ir::Instruction::Label(label) => {
+                self.flush();
+                self.emit(Instruction::Label(label.clone()));
+            }
+            ir::Instruction::FunctionCall { name, args, dst } => {
+                self.function_call(name, args, dst.as_ref())
+            }
+        }
+    }
+
+    /// The tree computing the value of a pure instruction.
+    fn tree(&mut self, instruction: &ir::Instruction) -> Tree {
+        match instruction {
+            ir::Instruction::SignExtend { src, dst }
+            | ir::Instruction::ZeroExtend { src, dst }
+            | ir::Instruction::Truncate { src, dst }
+            | ir::Instruction::Copy { src, dst } => {
+                let dst_type = self.value_type(dst);
+                match src {
+                    ir::Value::Constant(constant) => Tree::leaf(vec![Self::ldc(
+                        constant.convert(&dst_type, self.data_model).value as i32,
+                    )]),
+                    // Variables hold their values extended to a word already, so only values
+                    // outside the range of the destination type need converting.
+                    ir::Value::Var(_) => {
+                        let src_type = self.value_type(src);
+                        let operand = self.leaf(src);
+                        if src_type.min_value(self.data_model) < dst_type.min_value(self.data_model)
+                            || src_type.max_value(self.data_model) > dst_type.max_value(self.data_model)
+                        {
+                            self.narrow(operand, &dst_type)
+                        } else {
+                            operand
+                        }
+                    }
+                }
+            }
+            ir::Instruction::FloatToInt { src, dst } | ir::Instruction::FloatToUInt { src, dst } => {
+                self.float_to_int(src, dst)
+            }
+            ir::Instruction::Unary { operator: ir::UnaryOperator::Not, src, .. } if self.is_floating(src) => {
+                self.float_is_zero(src)
+            }
+            ir::Instruction::Binary { operator, src1, src2, .. } if self.is_floating(src1) => {
+                self.float_comparison(*operator, src1, src2)
+            }
+            ir::Instruction::Unary { operator, src, .. } => {
+                let operand = self.leaf(src);
+                match operator {
+                    ir::UnaryOperator::Negate => Tree::Binary {
+                        left: Box::new(Tree::leaf(vec![Self::ldc(0)])),
+                        right: Box::new(operand),
+                        instructions: vec![Instruction::Diff],
+                        commutative: false,
+                    },
+                    ir::UnaryOperator::Complement => Tree::Unary {
+                        operand: Box::new(operand),
+                        instructions: vec![Instruction::Not],
+                        depth: 1,
+                    },
+                    ir::UnaryOperator::Not => Tree::Unary {
+                        operand: Box::new(operand),
+                        instructions: vec![Instruction::Eqc(0)],
+                        depth: 1,
+                    },
+                }
+            }
+            ir::Instruction::Binary {
+                operator,
+                src1,
+                src2,
+                ..
+            } => self.binary(*operator, src1, src2),
+            _ => unreachable!("{:?} is not a pure instruction", instruction),
+        }
+    }
+
+    /// The tree computing a binary operation.
+    fn binary(&mut self, operator: ir::BinaryOperator, src1: &ir::Value, src2: &ir::Value) -> Tree {
+        let signed = self.is_signed(src1);
+        let operation = match operator {
+            ir::BinaryOperator::Add => Some((Instruction::Sum, true)),
+            ir::BinaryOperator::Subtract => Some((Instruction::Diff, false)),
+            ir::BinaryOperator::Multiply => Some((Instruction::Prod, true)),
+            ir::BinaryOperator::Divide if signed => Some((Instruction::Div, false)),
+            ir::BinaryOperator::Remainder if signed => Some((Instruction::Rem, false)),
+            ir::BinaryOperator::BitwiseAnd => Some((Instruction::And, true)),
+            ir::BinaryOperator::BitwiseOr => Some((Instruction::Or, true)),
+            ir::BinaryOperator::BitwiseXor => Some((Instruction::Xor, true)),
+            ir::BinaryOperator::ShiftLeft => Some((Instruction::Shl, false)),
+            ir::BinaryOperator::ShiftRight if !signed => Some((Instruction::Shr, false)),
+            _ => None,
+        };
+        if let Some((operation, commutative)) = operation {
+            return Tree::Binary {
+                left: Box::new(self.leaf(src1)),
+                right: Box::new(self.leaf(src2)),
+                instructions: vec![operation],
+                commutative,
+            };
+        }
+
+        match operator {
+            ir::BinaryOperator::Divide | ir::BinaryOperator::Remainder => Tree::LongDivide {
+                left: Box::new(self.leaf(src1)),
+                right: Box::new(self.leaf(src2)),
+                remainder: operator == ir::BinaryOperator::Remainder,
+            },
+            ir::BinaryOperator::ShiftRight => Tree::ShiftRight {
+                left: Box::new(self.leaf(src1)),
+                right: Box::new(self.leaf(src2)),
+            },
+            ir::BinaryOperator::Equal | ir::BinaryOperator::NotEqual => {
+                let mut tree = match (src1, src2) {
+                    (value, ir::Value::Constant(constant)) | (ir::Value::Constant(constant), value) => {
+                        Tree::Unary {
+                            operand: Box::new(self.leaf(value)),
+                            instructions: vec![Instruction::Eqc(constant.value as i32)],
+                            depth: 1,
+                        }
+                    }
+                    _ => Tree::Binary {
+                        left: Box::new(self.leaf(src1)),
+                        right: Box::new(self.leaf(src2)),
+                        instructions: vec![Instruction::Diff, Instruction::Eqc(0)],
+                        commutative: true,
+                    },
+                };
+                if operator == ir::BinaryOperator::NotEqual {
+                    tree = Tree::Unary {
+                        operand: Box::new(tree),
+                        instructions: vec![Instruction::Eqc(0)],
+                        depth: 1,
+                    };
+                }
+                tree
+            }
+            // 'gt' is the only comparison: the others swap its operands, or invert its result.
+            _ => {
+                let (left, right, invert) = match operator {
+                    ir::BinaryOperator::GreaterThan => (src1, src2, false),
+                    ir::BinaryOperator::LessThan => (src2, src1, false),
+                    ir::BinaryOperator::LessOrEqual => (src1, src2, true),
+                    ir::BinaryOperator::GreaterOrEqual => (src2, src1, true),
+                    _ => unreachable!("arithmetic operators are handled above"),
+                };
+                let mut instructions = vec![Instruction::Gt];
+                if invert {
+                    instructions.push(Instruction::Eqc(0));
+                }
+                Tree::Binary {
+                    left: Box::new(self.comparable(left, signed)),
+                    right: Box::new(self.comparable(right, signed)),
+                    instructions,
+                    commutative: false,
+                }
+            }
+        }
+    }
+
+    /// The tree pushing a value for the signed comparison 'gt'. Inverting the sign bits of
+    /// unsigned values maps them onto the signed range in the same order.
+    fn comparable(&mut self, value: &ir::Value, signed: bool) -> Tree {
+        match value {
+            _ if signed => self.leaf(value),
+            ir::Value::Constant(Constant { value, .. }) => {
+                Tree::leaf(vec![Self::ldc(*value as i32 ^ i32::MIN)])
+            }
+            ir::Value::Var(_) => Tree::Unary {
+                operand: Box::new(self.leaf(value)),
+                instructions: vec![Instruction::Mint, Instruction::Xor],
+                depth: 2,
+            },
+        }
+    }
+
+    /// The floating tree computing the floating value of a pure instruction.
+    fn float_tree(&mut self, instruction: &ir::Instruction) -> FloatTree {
+        match instruction {
+            ir::Instruction::Copy { src, .. } => self.float_leaf(src),
+            ir::Instruction::IntToFloat { src, dst } | ir::Instruction::UIntToFloat { src, dst } => {
+                self.int_to_float(src, dst)
+            }
+            ir::Instruction::FloatExtend { src, .. } => FloatTree::Unary {
+                operand: Box::new(self.float_leaf(src)),
+                instructions: vec![Instruction::Fpur32tor64],
+                double: true,
+            },
+            ir::Instruction::FloatTruncate { src, .. } => FloatTree::Unary {
+                operand: Box::new(self.float_leaf(src)),
+                instructions: vec![Instruction::Fpur64tor32],
+                double: false,
+            },
+            // Multiplying by -1 changes the sign of every value, zeros included, exactly.
+            ir::Instruction::Unary { operator: ir::UnaryOperator::Negate, src, dst } => {
+                let double = self.is_double(dst);
+                let minus_one = if double { Constant::double(-1.0) } else { Constant::float(-1.0) };
+                FloatTree::Binary {
+                    left: Box::new(self.float_leaf(src)),
+                    right: Box::new(self.float_constant(&minus_one)),
+                    instructions: vec![Instruction::Fpmul],
+                    commutative: true,
+                    double,
+                }
+            }
+            ir::Instruction::Binary { operator, src1, src2, dst } => {
+                let (operation, commutative) = match operator {
+                    ir::BinaryOperator::Add => (Instruction::Fpadd, true),
+                    ir::BinaryOperator::Subtract => (Instruction::Fpsub, false),
+                    ir::BinaryOperator::Multiply => (Instruction::Fpmul, true),
+                    ir::BinaryOperator::Divide => (Instruction::Fpdiv, false),
+                    _ => unreachable!("{:?} has no floating result", operator),
+                };
+                FloatTree::Binary {
+                    left: Box::new(self.float_leaf(src1)),
+                    right: Box::new(self.float_leaf(src2)),
+                    instructions: vec![operation],
+                    commutative,
+                    double: self.is_double(dst),
+                }
+            }
+            _ => unreachable!("{:?} has no floating result", instruction),
+        }
+    }
+
+    /// Converts an integer to a floating value. The FPU loads integers from memory, so a local
+    /// variable is loaded from its own slot, which holds its value extended to a word, as is a
+    /// static 'int' or 'long'; any other value is stored in a temporary first. An 'unsigned int'
+    /// is loaded as a 'double', in which it is exact, and rounded to a 'float' if need be.
+    fn int_to_float(&mut self, src: &ir::Value, dst: &ir::Value) -> FloatTree {
+        let dst_type = self.value_type(dst);
+        let double = dst_type == Type::Double;
+        let name = match src {
+            ir::Value::Constant(constant) => {
+                return self.float_constant(&constant.convert(&dst_type, self.data_model));
+            }
+            ir::Value::Var(name) => name,
+        };
+        let src_type = self.value_type(src);
+        let word = src_type.bits(self.data_model) == 32;
+        let unsigned = word && !src_type.is_signed(self.data_model);
+        let in_place = !self.pending.iter().any(|(pending, _)| pending == name) && (!self.is_static(name) || word);
+        let mut instructions = if !in_place {
+            let tree = self.leaf(src);
+            vec![Instruction::Ldlp(self.spill_word(tree))]
+        } else if self.is_static(name) {
+            self.address(name)
+        } else {
+            vec![Instruction::Ldlp(self.workspace(name))]
+        };
+        instructions.push(match (unsigned, double) {
+            (true, _) => Instruction::Fpb32tor64,
+            (false, true) => Instruction::Fpi32tor64,
+            (false, false) => Instruction::Fpi32tor32,
+        });
+        let leaf = FloatTree::Leaf { instructions, double: double || unsigned };
+        if unsigned && !double {
+            FloatTree::Unary { operand: Box::new(leaf), instructions: vec![Instruction::Fpur64tor32], double: false }
+        } else {
+            leaf
+        }
+    }
+
+    /// Converts a floating value to an integer, truncating it towards zero: 'fpint' rounds it to
+    /// an integral value, in the mode that 'fpurz' sets for the next operation, and 'fpstnli32'
+    /// stores it as a word in a temporary. That takes only the range of 'int', so a value for an
+    /// 'unsigned int' is made a 'double' and offset by -2^31 first, and its sign bit inverted after.
+    fn float_to_int(&mut self, src: &ir::Value, dst: &ir::Value) -> Tree {
+        let double = self.is_double(src);
+        let unsigned = !self.is_signed(dst) && self.value_type(dst).bits(self.data_model) == 32;
+        let mut operand = self.float_leaf(src);
+        if unsigned && !double {
+            operand = FloatTree::Unary { operand: Box::new(operand), instructions: vec![Instruction::Fpur32tor64], double: true };
+        }
+        operand = FloatTree::Unary {
+            operand: Box::new(operand),
+            instructions: vec![Instruction::Fpurz, Instruction::Fpint],
+            double: double || unsigned,
+        };
+        self.spills += 1;
+        let slot = Workspace::Pseudo(format!("%spill.{}", self.spills));
+        let mut instructions = vec![Instruction::Ldlp(slot.clone()), Instruction::Fpstnli32, Instruction::Ldl(slot)];
+        if unsigned {
+            operand = FloatTree::Binary {
+                left: Box::new(operand),
+                right: Box::new(self.float_constant(&Constant::double(2147483648.0))),
+                instructions: vec![Instruction::Fpsub],
+                commutative: false,
+                double: true,
+            };
+            instructions.extend([Instruction::Mint, Instruction::Xor]);
+        }
+        Tree::Float { operand: Box::new(operand), instructions, depth: if unsigned { 2 } else { 1 } }
+    }
+
+    /// Compares floating values with 'fpgt' or 'fpeq', which pop both and push the result onto the
+    /// evaluation stack. Neither is true if the values are unordered, i.e. either is a NaN, so
+    /// 'a <= b' is not '!(a > b)' but also needs 'fpordered', which leaves the values in place.
+    fn float_comparison(&mut self, operator: ir::BinaryOperator, src1: &ir::Value, src2: &ir::Value) -> Tree {
+        let (left, right, operation, instructions, depth) = match operator {
+            ir::BinaryOperator::Equal => (src1, src2, vec![Instruction::Fpeq], vec![], 1),
+            ir::BinaryOperator::NotEqual => (src1, src2, vec![Instruction::Fpeq], vec![Instruction::Eqc(0)], 1),
+            ir::BinaryOperator::GreaterThan => (src1, src2, vec![Instruction::Fpgt], vec![], 1),
+            ir::BinaryOperator::LessThan => (src2, src1, vec![Instruction::Fpgt], vec![], 1),
+            ir::BinaryOperator::LessOrEqual | ir::BinaryOperator::GreaterOrEqual => {
+                let (left, right) = if operator == ir::BinaryOperator::LessOrEqual {
+                    (src1, src2)
+                } else {
+                    (src2, src1)
+                };
+                let operation = vec![Instruction::Fpordered, Instruction::Fpgt];
+                (left, right, operation, vec![Instruction::Eqc(0), Instruction::And], 2)
+            }
+            _ => unreachable!("{:?} has a floating result", operator),
+        };
+        let operand = FloatTree::Binary {
+            left: Box::new(self.float_leaf(left)),
+            right: Box::new(self.float_leaf(right)),
+            commutative: operation == [Instruction::Fpeq],
+            instructions: operation,
+            double: self.is_double(src1),
+        };
+        Tree::Float { operand: Box::new(operand), instructions, depth }
+    }
+
+    /// The tree testing whether a floating value is zero, as both zeros are.
+    fn float_is_zero(&mut self, value: &ir::Value) -> Tree {
+        let double = self.is_double(value);
+        let operand = FloatTree::Binary {
+            left: Box::new(self.float_leaf(value)),
+            right: Box::new(Self::float_zero(double)),
+            instructions: vec![Instruction::Fpeq],
+            commutative: true,
+            double,
+        };
+        Tree::Float { operand: Box::new(operand), instructions: vec![], depth: 1 }
+    }
+
+    /// Evaluates a tree into a new workspace temporary, giving the leaf that loads it.
+    fn spill(&mut self, tree: Tree) -> Tree {
+        let slot = self.spill_word(tree);
+        Tree::leaf(vec![Instruction::Ldl(slot)])
+    }
+
+    /// Evaluates a tree into a new workspace temporary, giving its slot.
+    fn spill_word(&mut self, tree: Tree) -> Workspace {
+        self.spills += 1;
+        let slot = Workspace::Pseudo(format!("%spill.{}", self.spills));
+        self.evaluate(tree);
+        self.emit(Instruction::Stl(slot.clone()));
+        slot
+    }
+
+    /// Evaluates a floating tree into a new workspace temporary, giving its slot.
+    fn spill_float(&mut self, tree: FloatTree) -> Workspace {
+        self.spills += 1;
+        let double = tree.is_double();
+        let slot = FloatTree::temporary(format!("%spill.{}", self.spills), double);
+        self.evaluate_float(tree);
+        self.emit(Instruction::Ldlp(slot.clone()));
+        self.emit(FloatTree::store(double));
+        slot
+    }
+
+    /// The arguments passed in memory are stored at the bottom of the caller's workspace, then
+    /// those passed in registers are loaded, from C down to A. Each of these is evaluated above
+    /// the ones already loaded, so any needing more of the stack than is left are evaluated into
+    /// temporaries first. Floating arguments are passed as their words, which are loaded from the
+    /// variables holding them, or from temporaries they are evaluated into before any of the
+    /// others. The result comes back in A, or in FA if it is floating.
+    fn function_call(&mut self, name: &str, args: &[ir::Value], dst: Option<&ir::Value>) {
+        let arguments: Vec<Argument> = args
+            .iter()
+            .map(|arg| match arg {
+                ir::Value::Var(name)
+                    if self.is_floating(arg)
+                        && !self.is_static(name)
+                        && !self.pending_floats.iter().any(|(pending, _)| pending == name) =>
+                {
+                    let slot = self.workspace(name);
+                    let words = if self.is_double(arg) { 2 } else { 1 };
+                    Argument::Words(
+                        (0..words)
+                            .map(|word| Tree::leaf(vec![Instruction::Ldl(Self::word(slot.clone(), word))]))
+                            .collect(),
+                    )
+                }
+                _ if self.is_floating(arg) => Argument::Floating(self.float_leaf(arg)),
+                _ => Argument::Words(vec![self.leaf(arg)]),
+            })
+            .collect();
+        self.flush();
+        let mut trees = vec![];
+        for argument in arguments {
+            match argument {
+                Argument::Words(words) => trees.extend(words),
+                Argument::Floating(tree) => {
+                    let words = if tree.is_double() { 2 } else { 1 };
+                    let slot = self.spill_float(tree);
+                    trees.extend((0..words).map(|word| Tree::leaf(vec![Instruction::Ldl(Self::word(slot.clone(), word))])));
+                }
+            }
+        }
+        let mut registers = vec![];
+        for (index, tree) in trees.into_iter().enumerate() {
+            match Inmos::argument(index) {
+                Location::Register(_) => registers.push(tree),
+                Location::Memory(slot) => {
+                    self.evaluate(tree);
+                    self.emit(Instruction::Stl(Workspace::Argument(slot)));
+                }
+            }
+        }
+        let mut loads = vec![];
+        for (loaded, tree) in registers.into_iter().rev().enumerate() {
+            if tree.need() > scheduling::STACK_DEPTH - loaded {
+                loads.push(self.spill(tree));
+            } else {
+                loads.push(tree);
+            }
+        }
+        for tree in loads {
+            self.evaluate(tree);
+        }
+        self.emit(Instruction::Call(name.to_owned()));
+        match dst {
+            Some(dst) if self.is_floating(dst) => self.store_float(dst),
+            Some(dst) => self.store(dst),
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! The Transputer back end, generating T425 assembly for the Parachute project's TMASM assembler,
+//! or T800 assembly, which evaluates floating point on the FPU rather than in the soft-float
+//! runtime.
+//!
+//! The Transputer has no general purpose registers: instructions take their operands from a
+//! three-register evaluation stack (A, B and C), and variables live in the workspace, addressed
+//! in words relative to the workspace pointer. The IR instructions making up an expression are
+//! gathered into a tree, whose evaluation is scheduled on the evaluation stack, and the result
+//! stored in the workspace. Nothing is left on the evaluation stack between statements, as its
+//! contents are lost whenever the process is descheduled, e.g. at a jump. The T800's FPU has a
+//! three-register stack of its own, on which floating expressions are scheduled separately.
+//!
+//! As with the x86_64 back end, this is done in stages over an assembly AST:
+//!
+//! * generation translates the IR, scheduling expression trees to fit the three registers, and
+//!   addressing variables as pseudo workspace slots;
+//! * the workspace frame of each function is laid out, and the pseudo slots replaced by offsets;
+//! * finally the program is written out as text, or as an object for the linker.
+
+pub mod emission;
+pub mod generation;
+pub mod object;
+pub mod scheduling;
+pub mod workspace;
+
+#[cfg(test)]
+pub mod simulator;
+
+use common::data_model::DataModel;
+
+use crate::codegen::calling_convention::CallingConvention;
+use crate::ir;
+use crate::semantic::symbol_table::SymbolTable;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub top_level: Vec<TopLevel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TopLevel {
+    Function(Function),
+    StaticVariable(StaticVariable),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub name: String,
+    pub global: bool,
+    pub instructions: Vec<Instruction>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticVariable {
+    pub name: String,
+    pub global: bool,
+    pub size: DataSize,
+    /// The initial contents, as they are held in memory.
+    pub init: i64,
+}
+
+/// The T425 can load and store bytes and words, but not 16-bit halfwords, so a 'short' variable
+/// is given a word of its own, holding its value in the lower half.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataSize {
+    Byte,
+    Word,
+    /// The two words of a 'double', the low word first.
+    Double,
+}
+
+/// A word of the workspace. Every variable takes a whole word, holding its value sign or zero
+/// extended, other than a 'double', which takes two.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Workspace {
+    /// An IR variable, not yet assigned a slot.
+    Pseudo(String),
+    /// A word of an IR 'double' variable, not yet assigned its two slots: 0 for the low word, which
+    /// is the lower, and 1 for the high word.
+    PseudoDouble(String, usize),
+    /// A parameter of the function, numbered from zero, in its caller's workspace.
+    Parameter(usize),
+    /// A slot of the arguments passed in memory to a called function, numbered from zero.
+    Argument(usize),
+    /// An offset in words from the workspace pointer.
+    Offset(i32),
+}
+
+/// The registers of the evaluation stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    A,
+    B,
+    C,
+}
+
+/// The INMOS convention, as followed by its C and occam compilers: the first three arguments are
+/// passed in A, B and C, which 'call' stores in the called function's workspace, just above the
+/// return address, and the rest at the bottom of the caller's workspace, the first lowest, which
+/// follow them. So the called function finds all of its parameters in consecutive words. The
+/// result is returned in A.
+///
+/// On the T800, floating arguments are passed as their words, a 'float' taking one argument's
+/// place and a 'double' two, the low word first, so that they too are found in consecutive words.
+/// A floating result is returned in the FPU's FA register.
+///
+/// Each function adjusts the workspace pointer with 'ajw' to allocate its frame below the words
+/// 'call' stores, and releases it before 'ret'. Static variables are addressed relative to the
+/// instruction pointer rather than through a static base, so direct calls pass no static link.
+///
+/// TODO the static link for calls through function pointers is not yet handled: the parser has
+/// no function pointers, so there is no indirect call to pass it in. When they are added, the
+/// link needs a slot ahead of the arguments, in 'argument' and 'memory_arguments', and a spec
+/// calling through a pointer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Inmos;
+
+impl CallingConvention for Inmos {
+    type Register = Register;
+
+    const ARGUMENT_REGISTERS: &'static [Register] = &[Register::A, Register::B, Register::C];
+
+    const RETURN_REGISTER: Register = Register::A;
+}
+
+/// The operand of 'ldc'.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+    Constant(i32),
+    /// The distance in bytes from the label to the symbol, which 'ldpi' turns into the symbol's
+    /// address when the label immediately follows it.
+    Distance(String, String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    // The direct functions, whose operands are encoded by the assembler.
+    Ldc(Operand),
+    Ldl(Workspace),
+    Stl(Workspace),
+    Ldlp(Workspace),
+    Ldnl(i32),
+    Stnl(i32),
+    Adc(i32),
+    Eqc(i32),
+    Ajw(i32),
+    J(String),
+    Cj(String),
+    Call(String),
+    // The operations, which take their operands from the evaluation stack.
+    Ldpi,
+    Lb,
+    Sb,
+    Rev,
+    Pop,
+    Mint,
+    Sum,
+    Diff,
+    Prod,
+    Div,
+    Rem,
+    Ldiv,
+    And,
+    Or,
+    Xor,
+    Not,
+    Shl,
+    Shr,
+    Lshr,
+    Xdble,
+    Xword,
+    Gt,
+    Ret,
+    // The operations of the T800's FPU, which take their operands from its own three-register
+    // stack, FA, FB and FC, and any address or integer operand from A.
+    Fpldnlsn,
+    Fpldnldb,
+    Fpstnlsn,
+    Fpstnldb,
+    Fpldzerosn,
+    Fpldzerodb,
+    Fpadd,
+    Fpsub,
+    Fpmul,
+    Fpdiv,
+    Fprev,
+    Fpi32tor32,
+    Fpi32tor64,
+    Fpb32tor64,
+    Fpint,
+    Fpstnli32,
+    Fpgt,
+    Fpeq,
+    Fpordered,
+    // The FPU entry operations, selected by the number in A for 'fpentry', so each is written as
+    // an 'ldc' followed by 'fpentry', and needs a free register of the evaluation stack.
+    Fpurz,
+    Fpur32tor64,
+    Fpur64tor32,
+    Label(String),
+}
+
+/// Generates the assembly program, with the workspace of each function laid out.
+pub fn program(program: &ir::Program, symbols: &SymbolTable, data_model: &DataModel) -> Program {
+    let mut program = generation::generate(program, symbols, data_model);
+    workspace::allocate(&mut program);
+    program
+}
+
+/// Generates the assembly language text for a program.
+pub fn assembly(program: &ir::Program, symbols: &SymbolTable, data_model: &DataModel) -> String {
+    self::program(program, symbols, data_model).to_string()
+}
+
+#[cfg(test)]
+#[path = "./transputer_spec.rs"]
+mod transputer_spec;
@@ -0,0 +1,139 @@
+//! Writes the assembly program as a relocatable object, for the linker to combine with the other
+//! modules of the program.
+//!
+//! The operations, and the direct functions whose operands are constants or workspace offsets,
+//! are encoded here, with the shortest prefix sequences. Those whose operands are labels are left
+//! to the linker, which knows where everything ends up. As in the assembly text, static variables
+//! follow the code, with words aligned.
+
+use tlink::encoding::*;
+use tlink::object::{Fragment, Object};
+
+use crate::codegen::transputer::{
+    DataSize, Instruction, Operand, Program, StaticVariable, TopLevel, Workspace,
+};
+
+/// Generates the object for a program.
+pub fn object(program: &Program) -> Object {
+    let mut object = Object::default();
+    for top_level in &program.top_level {
+        if let TopLevel::Function(function) = top_level {
+            if function.global {
+                object.globals.push(function.name.clone());
+            }
+            object.code.push(Fragment::Label(function.name.clone()));
+            for instruction in &function.instructions {
+                push(&mut object.code, fragment(instruction));
+            }
+        }
+    }
+    for top_level in &program.top_level {
+        if let TopLevel::StaticVariable(variable) = top_level {
+            if variable.global {
+                object.globals.push(variable.name.clone());
+            }
+            object.data.extend(data(variable));
+        }
+    }
+    object
+}
+
+/// Adds a fragment, running bytes on from those before.
+fn push(fragments: &mut Vec<Fragment>, fragment: Fragment) {
+    if let (Some(Fragment::Bytes(previous)), Fragment::Bytes(bytes)) = (fragments.last_mut(), &fragment) {
+        previous.extend(bytes);
+        return;
+    }
+    fragments.push(fragment);
+}
+
+fn data(variable: &StaticVariable) -> Vec<Fragment> {
+    let label = Fragment::Label(variable.name.clone());
+    match variable.size {
+        DataSize::Byte => vec![label, Fragment::Bytes(vec![variable.init as u8])],
+        DataSize::Word => vec![Fragment::Align(4), label, Fragment::Bytes((variable.init as i32).to_le_bytes().to_vec())],
+        DataSize::Double => vec![Fragment::Align(4), label, Fragment::Bytes(variable.init.to_le_bytes().to_vec())],
+    }
+}
+
+fn offset(workspace: &Workspace) -> i32 {
+    match workspace {
+        Workspace::Offset(offset) => *offset,
+        _ => unreachable!("workspace slot {:?} was not allocated", workspace),
+    }
+}
+
+fn relative(function: u8, symbol: &str) -> Fragment {
+    Fragment::Direct(function, tlink::object::Operand::Relative(symbol.to_owned()))
+}
+
+fn fragment(instruction: &Instruction) -> Fragment {
+    let bytes = match instruction {
+        Instruction::Ldc(Operand::Constant(value)) => encode(LDC, *value),
+        Instruction::Ldc(Operand::Distance(symbol, label)) => {
+            return Fragment::Direct(LDC, tlink::object::Operand::Distance(symbol.clone(), label.clone()));
+        }
+        Instruction::Ldl(workspace) => encode(LDL, offset(workspace)),
+        Instruction::Stl(workspace) => encode(STL, offset(workspace)),
+        Instruction::Ldlp(workspace) => encode(LDLP, offset(workspace)),
+        Instruction::Ldnl(offset) => encode(LDNL, *offset),
+        Instruction::Stnl(offset) => encode(STNL, *offset),
+        Instruction::Adc(value) => encode(ADC, *value),
+        Instruction::Eqc(value) => encode(EQC, *value),
+        Instruction::Ajw(words) => encode(AJW, *words),
+        Instruction::J(label) => return relative(J, label),
+        Instruction::Cj(label) => return relative(CJ, label),
+        Instruction::Call(name) => return relative(CALL, name),
+        Instruction::Label(label) => return Fragment::Label(label.clone()),
+        Instruction::Ldpi => operation(LDPI),
+        Instruction::Lb => operation(LB),
+        Instruction::Sb => operation(SB),
+        Instruction::Rev => operation(REV),
+        Instruction::Pop => operation(POP),
+        Instruction::Mint => operation(MINT),
+        Instruction::Sum => operation(SUM),
+        Instruction::Diff => operation(DIFF),
+        Instruction::Prod => operation(PROD),
+        Instruction::Div => operation(DIV),
+        Instruction::Rem => operation(REM),
+        Instruction::Ldiv => operation(LDIV),
+        Instruction::And => operation(AND),
+        Instruction::Or => operation(OR),
+        Instruction::Xor => operation(XOR),
+        Instruction::Not => operation(NOT),
+        Instruction::Shl => operation(SHL),
+        Instruction::Shr => operation(SHR),
+        Instruction::Lshr => operation(LSHR),
+        Instruction::Xdble => operation(XDBLE),
+        Instruction::Xword => operation(XWORD),
+        Instruction::Gt => operation(GT),
+        Instruction::Ret => operation(RET),
+        Instruction::Fpldnlsn => operation(FPLDNLSN),
+        Instruction::Fpldnldb => operation(FPLDNLDB),
+        Instruction::Fpstnlsn => operation(FPSTNLSN),
+        Instruction::Fpstnldb => operation(FPSTNLDB),
+        Instruction::Fpldzerosn => operation(FPLDZEROSN),
+        Instruction::Fpldzerodb => operation(FPLDZERODB),
+        Instruction::Fpadd => operation(FPADD),
+        Instruction::Fpsub => operation(FPSUB),
+        Instruction::Fpmul => operation(FPMUL),
+        Instruction::Fpdiv => operation(FPDIV),
+        Instruction::Fprev => operation(FPREV),
+        Instruction::Fpi32tor32 => operation(FPI32TOR32),
+        Instruction::Fpi32tor64 => operation(FPI32TOR64),
+        Instruction::Fpb32tor64 => operation(FPB32TOR64),
+        Instruction::Fpint => operation(FPINT),
+        Instruction::Fpstnli32 => operation(FPSTNLI32),
+        Instruction::Fpgt => operation(FPGT),
+        Instruction::Fpeq => operation(FPEQ),
+        Instruction::Fpordered => operation(FPORDERED),
+        Instruction::Fpurz => [encode(LDC, FPURZ), operation(FPENTRY)].concat(),
+        Instruction::Fpur32tor64 => [encode(LDC, FPUR32TOR64), operation(FPENTRY)].concat(),
+        Instruction::Fpur64tor32 => [encode(LDC, FPUR64TOR32), operation(FPENTRY)].concat(),
+    };
+    Fragment::Bytes(bytes)
+}
+
+#[cfg(test)]
+#[path = "./object_spec.rs"]
+mod object_spec;
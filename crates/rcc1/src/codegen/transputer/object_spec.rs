@@ -0,0 +1,115 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod object_spec {
+
+    use hamcrest2::prelude::*;
+    use tlink::encoding::{CALL, CJ, LDC};
+    use tlink::object::{self, Fragment};
+
+    use crate::codegen::transputer::object::object;
+    use crate::codegen::transputer::{
+        DataSize, Function, Instruction, Operand, Program, StaticVariable, TopLevel, Workspace,
+    };
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn function(name: &str, global: bool, instructions: Vec<Instruction>) -> TopLevel {
+        TopLevel::Function(Function { name: name.to_owned(), global, instructions })
+    }
+
+    fn variable(name: &str, global: bool, size: DataSize, init: i64) -> TopLevel {
+        TopLevel::StaticVariable(StaticVariable { name: name.to_owned(), global, size, init })
+    }
+
+    #[test]
+    fn constant_operands_and_operations_are_encoded() {
+        let program = Program {
+            top_level: vec![function("main", true, vec![
+                Instruction::Ajw(-1),
+                Instruction::Ldc(Operand::Constant(300)),
+                Instruction::Stl(Workspace::Offset(0)),
+                Instruction::Ldl(Workspace::Offset(0)),
+                Instruction::Ajw(1),
+                Instruction::Ret,
+            ])],
+        };
+
+        let object = object(&program);
+
+        assert_that!(object.code, equal_to(vec![
+            Fragment::Label("main".to_owned()),
+            Fragment::Bytes(vec![0x60, 0xBF, 0x21, 0x22, 0x4C, 0xD0, 0x70, 0xB1, 0x22, 0xF0]),
+        ]));
+        assert_that!(object.globals, equal_to(vec!["main".to_owned()]));
+    }
+
+    #[test]
+    fn label_operands_are_left_to_the_linker() {
+        let program = Program {
+            top_level: vec![function("loop", false, vec![
+                Instruction::Label("while_start.1".to_owned()),
+                Instruction::Ldc(Operand::Distance("counter".to_owned(), "ldpi.1".to_owned())),
+                Instruction::Ldpi,
+                Instruction::Label("ldpi.1".to_owned()),
+                Instruction::Cj("while_start.1".to_owned()),
+                Instruction::Call("putchar".to_owned()),
+                Instruction::Ret,
+            ])],
+        };
+
+        let object = object(&program);
+
+        assert_that!(object.code, equal_to(vec![
+            Fragment::Label("loop".to_owned()),
+            Fragment::Label("while_start.1".to_owned()),
+            Fragment::Direct(LDC, object::Operand::Distance("counter".to_owned(), "ldpi.1".to_owned())),
+            Fragment::Bytes(vec![0x21, 0xFB]),
+            Fragment::Label("ldpi.1".to_owned()),
+            Fragment::Direct(CJ, object::Operand::Relative("while_start.1".to_owned())),
+            Fragment::Direct(CALL, object::Operand::Relative("putchar".to_owned())),
+            Fragment::Bytes(vec![0x22, 0xF0]),
+        ]));
+        assert_that!(object.globals.is_empty(), eq(true));
+    }
+
+    #[test]
+    fn fpu_entry_operations_load_their_numbers() {
+        let program = Program {
+            top_level: vec![function("round", true, vec![Instruction::Fpur64tor32])],
+        };
+
+        let object = object(&program);
+
+        assert_that!(object.code[1].clone(), equal_to(Fragment::Bytes(vec![0x48, 0x2A, 0xFB])));
+    }
+
+    #[test]
+    fn statics_follow_the_code_with_words_aligned() {
+        let program = Program {
+            top_level: vec![
+                variable("flag", false, DataSize::Byte, 1),
+                function("main", true, vec![Instruction::Ret]),
+                variable("count", true, DataSize::Word, -2),
+                variable("scale", false, DataSize::Double, 0x3FF8_0000_0000_0000),
+            ],
+        };
+
+        let object = object(&program);
+
+        assert_that!(object.data, equal_to(vec![
+            Fragment::Label("flag".to_owned()),
+            Fragment::Bytes(vec![1]),
+            Fragment::Align(4),
+            Fragment::Label("count".to_owned()),
+            Fragment::Bytes(vec![0xFE, 0xFF, 0xFF, 0xFF]),
+            Fragment::Align(4),
+            Fragment::Label("scale".to_owned()),
+            Fragment::Bytes(vec![0, 0, 0, 0, 0, 0, 0xF8, 0x3F]),
+        ]));
+        assert_that!(object.globals, equal_to(vec!["main".to_owned(), "count".to_owned()]));
+    }
+}
@@ -0,0 +1,297 @@
+//! Schedules the evaluation of expression trees on the three-register evaluation stack, using
+//! Sethi-Ullman numbering: the stack depth each subtree needs is computed bottom up, and at each
+//! node the operand needing the deeper stack is evaluated first, so that the other operand is
+//! evaluated above a single value. Operands are exchanged with 'rev' when a non-commutative
+//! operation has to evaluate its right operand first.
+//!
+//! Where a tree still needs more than three registers, its deepest subtrees are evaluated first
+//! and spilled to workspace temporaries, which are then loaded as leaves. This is always
+//! sufficient: every node needs at most three registers once its operands are leaves.
+//!
+//! The trees are pure, reading variables but not changing them, so their evaluation can be
+//! reordered and spilled freely.
+//!
+//! Floating expressions are scheduled in the same way on the FPU's stack, which is also three
+//! registers deep, but separate: a floating tree's leaves use a register of the evaluation stack
+//! only for the moment it takes to load a value from its address, so a floating tree appears in
+//! an integer one, e.g. as the operands of a comparison, as a node needing little of the
+//! evaluation stack, however much of the FPU's stack it needs.
+
+use crate::codegen::transputer::{Instruction, Operand, Workspace};
+
+/// The number of registers in the evaluation stack.
+pub const STACK_DEPTH: usize = 3;
+
+/// The number of registers in the FPU's stack.
+pub const FLOAT_STACK_DEPTH: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tree {
+    /// Instructions that push a single value, e.g. loading a variable, needing the given depth of
+    /// stack.
+    Leaf { instructions: Vec<Instruction>, depth: usize },
+    /// Instructions that operate on a single operand, needing the given depth of stack, including
+    /// the operand's register.
+    Unary { operand: Box<Tree>, instructions: Vec<Instruction>, depth: usize },
+    /// Instructions that operate on two operands, with the left in B and the right in A.
+    Binary { left: Box<Tree>, right: Box<Tree>, instructions: Vec<Instruction>, commutative: bool },
+    /// The unsigned division, whose 'ldiv' takes the dividend as a double word in C and B, so needs
+    /// a zero below the left operand.
+    LongDivide { left: Box<Tree>, right: Box<Tree>, remainder: bool },
+    /// The signed right shift, for which the left operand is sign extended to a double word in B
+    /// and A by 'xdble', before the count is loaded for 'lshr'.
+    ShiftRight { left: Box<Tree>, right: Box<Tree> },
+    /// Instructions that take the value of a floating tree, e.g. comparing its operands, and push
+    /// the integer result, needing the given depth of stack.
+    Float { operand: Box<FloatTree>, instructions: Vec<Instruction>, depth: usize },
+}
+
+/// A floating expression, evaluated on the FPU's stack. Each node records whether its value is a
+/// 'double', so that it can be spilled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FloatTree {
+    /// Instructions that push a single value, e.g. loading a variable through its address in A.
+    Leaf { instructions: Vec<Instruction>, double: bool },
+    /// Instructions that operate on a single operand in FA.
+    Unary { operand: Box<FloatTree>, instructions: Vec<Instruction>, double: bool },
+    /// Instructions that operate on two operands, with the left in FB and the right in FA.
+    Binary { left: Box<FloatTree>, right: Box<FloatTree>, instructions: Vec<Instruction>, commutative: bool, double: bool },
+}
+
+impl Tree {
+    /// A leaf loading a single word, e.g. from the workspace.
+    pub fn leaf(instructions: Vec<Instruction>) -> Tree {
+        Tree::Leaf { instructions, depth: 1 }
+    }
+
+    /// The depth of evaluation stack needed to evaluate the tree.
+    pub fn need(&self) -> usize {
+        match self {
+            Tree::Leaf { depth, .. } => *depth,
+            Tree::Unary { operand, depth, .. } => operand.need().max(*depth),
+            Tree::Binary { left, right, .. } => {
+                let (left, right) = (left.need(), right.need());
+                if left == right {
+                    left + 1
+                } else {
+                    left.max(right)
+                }
+            }
+            Tree::LongDivide { left, right, .. } => (1 + left.need()).max(2 + right.need()),
+            Tree::ShiftRight { left, right } => left.need().max(2 + right.need()),
+            Tree::Float { depth, .. } => *depth,
+        }
+    }
+}
+
+impl FloatTree {
+    /// The depth of the FPU's stack needed to evaluate the tree.
+    pub fn need(&self) -> usize {
+        match self {
+            FloatTree::Leaf { .. } => 1,
+            FloatTree::Unary { operand, .. } => operand.need(),
+            FloatTree::Binary { left, right, .. } => {
+                let (left, right) = (left.need(), right.need());
+                if left == right {
+                    left + 1
+                } else {
+                    left.max(right)
+                }
+            }
+        }
+    }
+
+    pub fn is_double(&self) -> bool {
+        match self {
+            FloatTree::Leaf { double, .. }
+            | FloatTree::Unary { double, .. }
+            | FloatTree::Binary { double, .. } => *double,
+        }
+    }
+
+    /// The loads and stores of a floating value through its address in A.
+    pub fn load(double: bool) -> Instruction {
+        if double {
+            Instruction::Fpldnldb
+        } else {
+            Instruction::Fpldnlsn
+        }
+    }
+
+    pub fn store(double: bool) -> Instruction {
+        if double {
+            Instruction::Fpstnldb
+        } else {
+            Instruction::Fpstnlsn
+        }
+    }
+
+    /// The workspace temporary holding a floating value, addressed by its lower word.
+    pub fn temporary(name: String, double: bool) -> Workspace {
+        if double {
+            Workspace::PseudoDouble(name, 0)
+        } else {
+            Workspace::Pseudo(name)
+        }
+    }
+}
+
+/// Spills subtrees of the tree until it needs no more than the evaluation stack. Each spilled
+/// subtree is evaluated into `spills`, followed by a store to a new workspace temporary, named
+/// by `temporary`.
+pub fn fit(tree: Tree, spills: &mut Vec<Instruction>, temporary: &mut impl FnMut() -> String) -> Tree {
+    match tree {
+        Tree::Leaf { .. } => tree,
+        Tree::Unary { operand, instructions, depth } => Tree::Unary {
+            operand: Box::new(fit(*operand, spills, temporary)),
+            instructions,
+            depth,
+        },
+        Tree::Binary { left, right, instructions, commutative } => {
+            let mut left = fit(*left, spills, temporary);
+            let right = fit(*right, spills, temporary);
+            if left.need() == STACK_DEPTH && right.need() == STACK_DEPTH {
+                left = spill(left, spills, temporary);
+            }
+            Tree::Binary { left: Box::new(left), right: Box::new(right), instructions, commutative }
+        }
+        Tree::LongDivide { left, right, remainder } => {
+            let mut left = fit(*left, spills, temporary);
+            let mut right = fit(*right, spills, temporary);
+            if 1 + left.need() > STACK_DEPTH {
+                left = spill(left, spills, temporary);
+            }
+            if 2 + right.need() > STACK_DEPTH {
+                right = spill(right, spills, temporary);
+            }
+            Tree::LongDivide { left: Box::new(left), right: Box::new(right), remainder }
+        }
+        Tree::ShiftRight { left, right } => {
+            let left = fit(*left, spills, temporary);
+            let mut right = fit(*right, spills, temporary);
+            if 2 + right.need() > STACK_DEPTH {
+                right = spill(right, spills, temporary);
+            }
+            Tree::ShiftRight { left: Box::new(left), right: Box::new(right) }
+        }
+        Tree::Float { operand, instructions, depth } => Tree::Float {
+            operand: Box::new(fit_float(*operand, spills, temporary)),
+            instructions,
+            depth,
+        },
+    }
+}
+
+/// Spills subtrees of a floating tree until it needs no more than the FPU's stack, as `fit` does.
+pub fn fit_float(tree: FloatTree, spills: &mut Vec<Instruction>, temporary: &mut impl FnMut() -> String) -> FloatTree {
+    match tree {
+        FloatTree::Leaf { .. } => tree,
+        FloatTree::Unary { operand, instructions, double } => FloatTree::Unary {
+            operand: Box::new(fit_float(*operand, spills, temporary)),
+            instructions,
+            double,
+        },
+        FloatTree::Binary { left, right, instructions, commutative, double } => {
+            let mut left = fit_float(*left, spills, temporary);
+            let right = fit_float(*right, spills, temporary);
+            if left.need() == FLOAT_STACK_DEPTH && right.need() == FLOAT_STACK_DEPTH {
+                left = spill_float(left, spills, temporary);
+            }
+            FloatTree::Binary { left: Box::new(left), right: Box::new(right), instructions, commutative, double }
+        }
+    }
+}
+
+/// Evaluates a tree into a new workspace temporary, giving the leaf that loads it.
+fn spill(tree: Tree, spills: &mut Vec<Instruction>, temporary: &mut impl FnMut() -> String) -> Tree {
+    let name = temporary();
+    evaluate(&tree, spills);
+    spills.push(Instruction::Stl(Workspace::Pseudo(name.clone())));
+    Tree::leaf(vec![Instruction::Ldl(Workspace::Pseudo(name))])
+}
+
+/// Evaluates a floating tree into a new workspace temporary, giving the leaf that loads it.
+fn spill_float(tree: FloatTree, spills: &mut Vec<Instruction>, temporary: &mut impl FnMut() -> String) -> FloatTree {
+    let double = tree.is_double();
+    let slot = FloatTree::temporary(temporary(), double);
+    evaluate_float(&tree, spills);
+    spills.push(Instruction::Ldlp(slot.clone()));
+    spills.push(FloatTree::store(double));
+    FloatTree::Leaf { instructions: vec![Instruction::Ldlp(slot), FloatTree::load(double)], double }
+}
+
+/// Appends the instructions that evaluate the tree, leaving its value in A.
+pub fn evaluate(tree: &Tree, instructions: &mut Vec<Instruction>) {
+    match tree {
+        Tree::Leaf { instructions: leaf, .. } => instructions.extend(leaf.iter().cloned()),
+        Tree::Unary { operand, instructions: operation, .. } => {
+            evaluate(operand, instructions);
+            instructions.extend(operation.iter().cloned());
+        }
+        Tree::Binary { left, right, instructions: operation, commutative } => {
+            if right.need() > left.need() {
+                evaluate(right, instructions);
+                evaluate(left, instructions);
+                if !commutative {
+                    instructions.push(Instruction::Rev);
+                }
+            } else {
+                evaluate(left, instructions);
+                evaluate(right, instructions);
+            }
+            instructions.extend(operation.iter().cloned());
+        }
+        // 'ldiv' leaves the quotient in A and the remainder in B; 'pop' discards A.
+        Tree::LongDivide { left, right, remainder } => {
+            instructions.push(Instruction::Ldc(Operand::Constant(0)));
+            evaluate(left, instructions);
+            evaluate(right, instructions);
+            instructions.push(Instruction::Ldiv);
+            if !remainder {
+                instructions.push(Instruction::Rev);
+            }
+            instructions.push(Instruction::Pop);
+        }
+        // 'lshr' leaves the low word of the result in A and the high word in B.
+        Tree::ShiftRight { left, right } => {
+            evaluate(left, instructions);
+            instructions.push(Instruction::Xdble);
+            evaluate(right, instructions);
+            instructions.push(Instruction::Lshr);
+            instructions.push(Instruction::Rev);
+            instructions.push(Instruction::Pop);
+        }
+        Tree::Float { operand, instructions: operation, .. } => {
+            evaluate_float(operand, instructions);
+            instructions.extend(operation.iter().cloned());
+        }
+    }
+}
+
+/// Appends the instructions that evaluate a floating tree, leaving its value in FA.
+pub fn evaluate_float(tree: &FloatTree, instructions: &mut Vec<Instruction>) {
+    match tree {
+        FloatTree::Leaf { instructions: leaf, .. } => instructions.extend(leaf.iter().cloned()),
+        FloatTree::Unary { operand, instructions: operation, .. } => {
+            evaluate_float(operand, instructions);
+            instructions.extend(operation.iter().cloned());
+        }
+        FloatTree::Binary { left, right, instructions: operation, commutative, .. } => {
+            if right.need() > left.need() {
+                evaluate_float(right, instructions);
+                evaluate_float(left, instructions);
+                if !commutative {
+                    instructions.push(Instruction::Fprev);
+                }
+            } else {
+                evaluate_float(left, instructions);
+                evaluate_float(right, instructions);
+            }
+            instructions.extend(operation.iter().cloned());
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./scheduling_spec.rs"]
+mod scheduling_spec;
@@ -0,0 +1,232 @@
+mod scheduling_spec {
+    use hamcrest2::prelude::*;
+
+    use crate::codegen::transputer::scheduling::{
+        evaluate, evaluate_float, fit, fit_float, FloatTree, Tree, FLOAT_STACK_DEPTH, STACK_DEPTH,
+    };
+    use crate::codegen::transputer::{Instruction, Workspace};
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn variable(name: &str) -> Tree {
+        Tree::leaf(vec![Instruction::Ldl(Workspace::Pseudo(name.to_owned()))])
+    }
+
+    fn binary(left: Tree, right: Tree, operation: Instruction, commutative: bool) -> Tree {
+        Tree::Binary {
+            left: Box::new(left),
+            right: Box::new(right),
+            instructions: vec![operation],
+            commutative,
+        }
+    }
+
+    fn difference(left: Tree, right: Tree) -> Tree {
+        binary(left, right, Instruction::Diff, false)
+    }
+
+    /// A complete tree of differences of the given height, over distinct variables.
+    fn complete(height: usize, next: &mut usize) -> Tree {
+        if height == 0 {
+            *next += 1;
+            variable(&format!("v{}", next))
+        } else {
+            difference(complete(height - 1, next), complete(height - 1, next))
+        }
+    }
+
+    /// Fits and evaluates a tree, giving the spills followed by its evaluation.
+    fn scheduled(tree: Tree) -> Vec<Instruction> {
+        let mut count = 0;
+        let mut instructions = vec![];
+        let tree = fit(tree, &mut instructions, &mut || {
+            count += 1;
+            format!("%spill.{}", count)
+        });
+        evaluate(&tree, &mut instructions);
+        instructions
+    }
+
+    /// The greatest number of values the instructions hold on the evaluation stack, and the
+    /// number left on it at the end.
+    fn depths(instructions: &[Instruction]) -> (usize, usize) {
+        let (mut depth, mut max) = (0usize, 0);
+        for instruction in instructions {
+            match instruction {
+                Instruction::Ldc(_) | Instruction::Ldl(_) | Instruction::Mint | Instruction::Xdble => {
+                    depth += 1
+                }
+                Instruction::Rev
+                | Instruction::Not
+                | Instruction::Eqc(_)
+                | Instruction::Ldpi
+                | Instruction::Ldnl(_)
+                | Instruction::Label(_) => {}
+                _ => depth -= 1,
+            }
+            max = max.max(depth);
+        }
+        (max, depth)
+    }
+
+    #[test]
+    fn the_need_of_a_node_is_one_more_only_when_its_operands_need_the_same() {
+        assert_that!(variable("a").need(), eq(1));
+        assert_that!(difference(variable("a"), variable("b")).need(), eq(2));
+        let deeper = difference(difference(variable("a"), variable("b")), variable("c"));
+        assert_that!(deeper.need(), eq(2));
+        assert_that!(complete(2, &mut 0).need(), eq(3));
+        assert_that!(complete(3, &mut 0).need(), eq(4));
+    }
+
+    #[test]
+    fn the_deeper_operand_is_evaluated_first_and_reversed_if_not_commutative() {
+        let tree = difference(variable("a"), difference(variable("b"), variable("c")));
+        assert_that!(
+            scheduled(tree),
+            eq(vec![
+                Instruction::Ldl(Workspace::Pseudo("b".to_owned())),
+                Instruction::Ldl(Workspace::Pseudo("c".to_owned())),
+                Instruction::Diff,
+                Instruction::Ldl(Workspace::Pseudo("a".to_owned())),
+                Instruction::Rev,
+                Instruction::Diff,
+            ])
+        );
+    }
+
+    #[test]
+    fn commutative_operands_are_not_reversed() {
+        let tree = binary(
+            variable("a"),
+            binary(variable("b"), variable("c"), Instruction::Prod, true),
+            Instruction::Sum,
+            true,
+        );
+        let instructions = scheduled(tree);
+        assert_that!(instructions.contains(&Instruction::Rev), eq(false));
+        assert_that!(instructions.last(), eq(Some(&Instruction::Sum)));
+    }
+
+    #[test]
+    fn a_tree_needing_more_than_the_stack_spills_a_subtree() {
+        let instructions = scheduled(complete(3, &mut 0));
+        let spill = Instruction::Stl(Workspace::Pseudo("%spill.1".to_owned()));
+        assert_that!(instructions.contains(&spill), eq(true));
+        assert_that!(
+            instructions.contains(&Instruction::Stl(Workspace::Pseudo("%spill.2".to_owned()))),
+            eq(false)
+        );
+        assert_that!(depths(&instructions), eq((STACK_DEPTH, 1)));
+    }
+
+    #[test]
+    fn no_tree_overflows_the_stack() {
+        for height in 0..8 {
+            let instructions = scheduled(complete(height, &mut 0));
+            let (max, left) = depths(&instructions);
+            assert_that!(max <= STACK_DEPTH, eq(true));
+            assert_that!(left, eq(1));
+        }
+    }
+
+    #[test]
+    fn the_long_division_needs_room_for_the_high_word() {
+        let divide = |left, right| Tree::LongDivide {
+            left: Box::new(left),
+            right: Box::new(right),
+            remainder: false,
+        };
+        assert_that!(divide(variable("a"), variable("b")).need(), eq(3));
+        let tree = divide(complete(2, &mut 0), complete(1, &mut 10));
+        assert_that!(tree.need(), eq(4));
+        let instructions = scheduled(tree);
+        assert_that!(depths(&instructions), eq((STACK_DEPTH, 1)));
+        assert_that!(
+            instructions.ends_with(&[Instruction::Ldiv, Instruction::Rev, Instruction::Pop]),
+            eq(true)
+        );
+    }
+
+    #[test]
+    fn the_shift_count_is_loaded_above_the_double_word() {
+        let tree = Tree::ShiftRight {
+            left: Box::new(complete(2, &mut 0)),
+            right: Box::new(complete(1, &mut 10)),
+        };
+        assert_that!(tree.need(), eq(4));
+        let instructions = scheduled(tree);
+        assert_that!(depths(&instructions), eq((STACK_DEPTH, 1)));
+        assert_that!(
+            instructions.ends_with(&[Instruction::Lshr, Instruction::Rev, Instruction::Pop]),
+            eq(true)
+        );
+    }
+
+    /// A complete floating tree of differences of the given height, over distinct variables.
+    fn complete_float(height: usize, next: &mut usize) -> FloatTree {
+        if height == 0 {
+            *next += 1;
+            FloatTree::Leaf {
+                instructions: vec![Instruction::Ldlp(Workspace::PseudoDouble(format!("v{}", next), 0)), Instruction::Fpldnldb],
+                double: true,
+            }
+        } else {
+            FloatTree::Binary {
+                left: Box::new(complete_float(height - 1, next)),
+                right: Box::new(complete_float(height - 1, next)),
+                instructions: vec![Instruction::Fpsub],
+                commutative: false,
+                double: true,
+            }
+        }
+    }
+
+    /// The greatest number of values the instructions hold on the FPU's stack, and the number
+    /// left on it at the end.
+    fn float_depths(instructions: &[Instruction]) -> (usize, usize) {
+        let (mut depth, mut max) = (0usize, 0);
+        for instruction in instructions {
+            match instruction {
+                Instruction::Fpldnldb => depth += 1,
+                Instruction::Fpsub | Instruction::Fpstnldb => depth -= 1,
+                _ => {}
+            }
+            max = max.max(depth);
+        }
+        (max, depth)
+    }
+
+    #[test]
+    fn floating_trees_are_scheduled_on_the_fpu_stack() {
+        for height in 0..6 {
+            let mut instructions = vec![];
+            let tree = fit_float(complete_float(height, &mut 0), &mut instructions, &mut || "%spill".to_owned());
+            evaluate_float(&tree, &mut instructions);
+            let (max, left) = float_depths(&instructions);
+            assert_that!(max <= FLOAT_STACK_DEPTH, eq(true));
+            assert_that!(left, eq(1));
+        }
+    }
+
+    #[test]
+    fn a_floating_comparison_needs_little_of_the_evaluation_stack() {
+        let comparison = Tree::Float {
+            operand: Box::new(complete_float(3, &mut 0)),
+            instructions: vec![],
+            depth: 1,
+        };
+        let tree = difference(complete(2, &mut 10), comparison);
+        assert_that!(tree.need(), eq(3));
+        let instructions = scheduled(tree);
+        // The floating tree needs four of the FPU's registers, so its left subtree is spilled.
+        assert_that!(
+            instructions.iter().filter(|instruction| **instruction == Instruction::Fpstnldb).count(),
+            eq(1)
+        );
+        assert_that!(instructions.last(), eq(Some(&Instruction::Diff)));
+    }
+}
@@ -0,0 +1,469 @@
+//! A simulator for the subset of the T425 and T800 used by the code generator, for testing
+//! generated programs without an assembler or an emulator. It runs the assembly AST, after
+//! workspace allocation, and gives the value 'main' returns.
+//!
+//! Each instruction occupies one address, and static variables are placed above the code. The
+//! evaluation stack registers are tracked as undefined until loaded, and become undefined when
+//! their values are popped, or lost at a jump, which is where the T425 may deschedule the process,
+//! and at a call or return, other than the returned value in A. Reading an undefined register is
+//! an error, as it would read garbage on the Transputer, and so is pushing a value onto a full
+//! stack, which would silently lose the value in C.
+//!
+//! The FPU's stack is tracked in the same way, with each value a 'float' or a 'double', and
+//! operating on values of different precisions, or storing one as the other, is an error too.
+//! Only FA survives a return, holding a floating result.
+
+use std::collections::HashMap;
+
+use crate::codegen::transputer::{DataSize, Instruction, Operand, Program, TopLevel, Workspace};
+
+const DATA_BASE: u32 = 0x1000_0000;
+const WORKSPACE_TOP: u32 = 0x8000_0000;
+/// The return address that ends the simulation, when 'main' returns to it.
+const HALT: u32 = 0xffff_fffc;
+const MAX_STEPS: usize = 50_000_000;
+
+/// A value on the FPU's stack, which records its precision.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Real {
+    Single(f32),
+    Double(f64),
+}
+
+impl Real {
+    fn value(self) -> f64 {
+        match self {
+            Real::Single(value) => value as f64,
+            Real::Double(value) => value,
+        }
+    }
+
+    fn is_double(self) -> bool {
+        matches!(self, Real::Double(_))
+    }
+
+    /// A value of the same precision, rounded to it. Rounding the exact result of a 'float'
+    /// operation's 'double' gives the same as rounding once.
+    fn with(self, value: f64) -> Real {
+        match self {
+            Real::Single(_) => Real::Single(value as f32),
+            Real::Double(_) => Real::Double(value),
+        }
+    }
+}
+
+pub struct Simulator<'a> {
+    code: Vec<&'a Instruction>,
+    addresses: HashMap<&'a str, u32>,
+    memory: HashMap<u32, u8>,
+    registers: [Option<i32>; 3],
+    float_registers: [Option<Real>; 3],
+    /// Whether 'fpurz' has set rounding towards zero for the next operation.
+    round_to_zero: bool,
+    wptr: u32,
+    /// The greatest number of defined values held on the evaluation stack.
+    pub max_depth: usize,
+    /// The greatest number of values held on the FPU's stack.
+    pub max_float_depth: usize,
+}
+
+impl<'a> Simulator<'a> {
+    pub fn new(program: &'a Program) -> Self {
+        let mut simulator = Simulator {
+            code: vec![],
+            addresses: HashMap::new(),
+            memory: HashMap::new(),
+            registers: [None; 3],
+            float_registers: [None; 3],
+            round_to_zero: false,
+            wptr: WORKSPACE_TOP,
+            max_depth: 0,
+            max_float_depth: 0,
+        };
+        let mut data = DATA_BASE;
+        for top_level in &program.top_level {
+            match top_level {
+                TopLevel::Function(function) => {
+                    simulator.addresses.insert(&function.name, simulator.code.len() as u32);
+                    for instruction in &function.instructions {
+                        if let Instruction::Label(label) = instruction {
+                            simulator.addresses.insert(label, simulator.code.len() as u32);
+                        }
+                        simulator.code.push(instruction);
+                    }
+                }
+                TopLevel::StaticVariable(variable) => match variable.size {
+                    DataSize::Byte => {
+                        simulator.addresses.insert(&variable.name, data);
+                        simulator.memory.insert(data, variable.init as u8);
+                        data += 1;
+                    }
+                    DataSize::Word => {
+                        data = (data + 3) & !3;
+                        simulator.addresses.insert(&variable.name, data);
+                        simulator.write_word(data, variable.init as i32);
+                        data += 4;
+                    }
+                    DataSize::Double => {
+                        data = (data + 3) & !3;
+                        simulator.addresses.insert(&variable.name, data);
+                        simulator.write_word(data, variable.init as i32);
+                        simulator.write_word(data + 4, (variable.init >> 32) as i32);
+                        data += 8;
+                    }
+                },
+            }
+        }
+        simulator
+    }
+
+    fn read_word(&self, address: u32) -> i32 {
+        let bytes = [0, 1, 2, 3].map(|offset| self.read_byte(address.wrapping_add(offset)));
+        i32::from_le_bytes(bytes)
+    }
+
+    fn read_byte(&self, address: u32) -> u8 {
+        *self.memory.get(&address).unwrap_or(&0)
+    }
+
+    fn write_word(&mut self, address: u32, value: i32) {
+        for (offset, byte) in value.to_le_bytes().into_iter().enumerate() {
+            self.memory.insert(address.wrapping_add(offset as u32), byte);
+        }
+    }
+
+    fn workspace(&self, workspace: &Workspace) -> u32 {
+        match workspace {
+            Workspace::Offset(offset) => self.wptr.wrapping_add((offset * 4) as u32),
+            _ => panic!("workspace slot {:?} was not allocated", workspace),
+        }
+    }
+
+    fn address(&self, name: &str) -> Result<u32, String> {
+        self.addresses.get(name).copied().ok_or(format!("'{}' is not defined", name))
+    }
+
+    fn push(&mut self, value: i32) -> Result<(), String> {
+        if self.registers[2].is_some() {
+            return Err("evaluation stack overflow".to_owned());
+        }
+        self.registers = [Some(value), self.registers[0], self.registers[1]];
+        let depth = self.registers.iter().filter(|register| register.is_some()).count();
+        self.max_depth = self.max_depth.max(depth);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<i32, String> {
+        let value = self.registers[0].ok_or("read an undefined register")?;
+        self.registers = [self.registers[1], self.registers[2], None];
+        Ok(value)
+    }
+
+    fn push_float(&mut self, value: Real) -> Result<(), String> {
+        if self.float_registers[2].is_some() {
+            return Err("FPU stack overflow".to_owned());
+        }
+        self.float_registers = [Some(value), self.float_registers[0], self.float_registers[1]];
+        let depth = self.float_registers.iter().filter(|register| register.is_some()).count();
+        self.max_float_depth = self.max_float_depth.max(depth);
+        Ok(())
+    }
+
+    fn pop_float(&mut self) -> Result<Real, String> {
+        let value = self.float_registers[0].ok_or("read an undefined FPU register")?;
+        self.float_registers = [self.float_registers[1], self.float_registers[2], None];
+        Ok(value)
+    }
+
+    /// Pops the operands of a binary operation, giving FB and FA, which must be of the same
+    /// precision.
+    fn pop_floats(&mut self) -> Result<(Real, Real), String> {
+        let a = self.pop_float()?;
+        let b = self.pop_float()?;
+        if a.is_double() != b.is_double() {
+            return Err("operands of different precisions".to_owned());
+        }
+        Ok((b, a))
+    }
+
+    /// The FPU entry operations are selected by the number loaded into A.
+    fn fpentry(&mut self) -> Result<(), String> {
+        self.push(0)?;
+        self.pop()?;
+        Ok(())
+    }
+
+    /// Runs 'main', giving the value it returns.
+    pub fn run(&mut self) -> Result<i32, String> {
+        self.wptr -= 4;
+        self.write_word(self.wptr, HALT as i32);
+        let mut iptr = self.address("main")?;
+        for _ in 0..MAX_STEPS {
+            let instruction = *self
+                .code
+                .get(iptr as usize)
+                .ok_or(format!("ran off the end of the code at {}", iptr))?;
+            match self.step(instruction, iptr) {
+                Ok(Some(next)) if next == HALT => return self.pop(),
+                Ok(Some(next)) => iptr = next,
+                Ok(None) => iptr += 1,
+                Err(e) => return Err(format!("{} at {} ({:?})", e, iptr, instruction)),
+            }
+        }
+        Err("too many steps".to_owned())
+    }
+
+    /// Executes an instruction, giving the address of the next one if it transfers control.
+    fn step(&mut self, instruction: &Instruction, iptr: u32) -> Result<Option<u32>, String> {
+        let round_to_zero = std::mem::take(&mut self.round_to_zero);
+        match instruction {
+            Instruction::Ldc(Operand::Constant(value)) => self.push(*value)?,
+            Instruction::Ldc(Operand::Distance(symbol, label)) => {
+                let distance = self.address(symbol)?.wrapping_sub(self.address(label)?);
+                self.push(distance as i32)?
+            }
+            Instruction::Ldl(workspace) => self.push(self.read_word(self.workspace(workspace)))?,
+            Instruction::Stl(workspace) => {
+                let value = self.pop()?;
+                self.write_word(self.workspace(workspace), value);
+            }
+            Instruction::Ldlp(workspace) => self.push(self.workspace(workspace) as i32)?,
+            Instruction::Ldnl(offset) => {
+                let address = self.pop()?;
+                self.push(self.read_word(address.wrapping_add(offset * 4) as u32))?;
+            }
+            Instruction::Stnl(offset) => {
+                let address = self.pop()?;
+                let value = self.pop()?;
+                self.write_word(address.wrapping_add(offset * 4) as u32, value);
+            }
+            Instruction::Lb => {
+                let address = self.pop()?;
+                self.push(self.read_byte(address as u32) as i32)?;
+            }
+            Instruction::Sb => {
+                let address = self.pop()?;
+                let value = self.pop()?;
+                self.memory.insert(address as u32, value as u8);
+            }
+            Instruction::Adc(value) => {
+                let a = self.pop()?;
+                self.push(a.wrapping_add(*value))?;
+            }
+            Instruction::Eqc(value) => {
+                let a = self.pop()?;
+                self.push((a == *value) as i32)?;
+            }
+            Instruction::Ajw(words) => self.wptr = self.wptr.wrapping_add((words * 4) as u32),
+            Instruction::J(label) => {
+                self.registers = [None; 3];
+                self.float_registers = [None; 3];
+                return Ok(Some(self.address(label)?));
+            }
+            Instruction::Cj(label) => {
+                if self.registers[0].ok_or("read an undefined register")? == 0 {
+                    self.registers = [None; 3];
+                    self.float_registers = [None; 3];
+                    return Ok(Some(self.address(label)?));
+                }
+                self.pop()?;
+            }
+            Instruction::Call(name) => {
+                let target = self.address(name)?;
+                self.wptr -= 16;
+                self.write_word(self.wptr, iptr as i32 + 1);
+                for (index, register) in self.registers.into_iter().enumerate() {
+                    self.write_word(self.wptr + 4 + 4 * index as u32, register.unwrap_or(0));
+                }
+                self.registers = [None; 3];
+                self.float_registers = [None; 3];
+                return Ok(Some(target));
+            }
+            Instruction::Ret => {
+                let next = self.read_word(self.wptr) as u32;
+                self.wptr += 16;
+                self.registers = [self.registers[0], None, None];
+                self.float_registers = [self.float_registers[0], None, None];
+                return Ok(Some(next));
+            }
+            Instruction::Ldpi => {
+                let a = self.pop()?;
+                self.push(a.wrapping_add(iptr as i32 + 1))?;
+            }
+            Instruction::Rev => {
+                let a = self.pop()?;
+                let b = self.pop()?;
+                self.push(a)?;
+                self.push(b)?;
+            }
+            Instruction::Pop => {
+                self.pop()?;
+            }
+            Instruction::Mint => self.push(i32::MIN)?,
+            Instruction::Not => {
+                let a = self.pop()?;
+                self.push(!a)?;
+            }
+            Instruction::Xdble => {
+                let a = self.pop()?;
+                self.push(if a < 0 { -1 } else { 0 })?;
+                self.push(a)?;
+            }
+            Instruction::Ldiv => {
+                let divisor = self.pop()? as u32;
+                let low = self.pop()? as u32;
+                let high = self.pop()? as u32;
+                if high >= divisor {
+                    return Err("ldiv overflowed".to_owned());
+                }
+                let dividend = ((high as u64) << 32) | low as u64;
+                self.push((dividend % divisor as u64) as i32)?;
+                self.push((dividend / divisor as u64) as i32)?;
+            }
+            Instruction::Lshr => {
+                let count = self.pop()? as u32;
+                let low = self.pop()? as u32;
+                let high = self.pop()? as u32;
+                let double = ((high as u64) << 32) | low as u64;
+                let shifted = if count >= 64 { 0 } else { double >> count };
+                self.push((shifted >> 32) as i32)?;
+                self.push(shifted as i32)?;
+            }
+            Instruction::Fpldnlsn => {
+                let address = self.pop()? as u32;
+                self.push_float(Real::Single(f32::from_bits(self.read_word(address) as u32)))?;
+            }
+            Instruction::Fpldnldb => {
+                let address = self.pop()? as u32;
+                let low = self.read_word(address) as u32 as u64;
+                let high = self.read_word(address.wrapping_add(4)) as u32 as u64;
+                self.push_float(Real::Double(f64::from_bits((high << 32) | low)))?;
+            }
+            Instruction::Fpstnlsn => {
+                let address = self.pop()? as u32;
+                match self.pop_float()? {
+                    Real::Single(value) => self.write_word(address, value.to_bits() as i32),
+                    Real::Double(_) => return Err("stored a double as a float".to_owned()),
+                }
+            }
+            Instruction::Fpstnldb => {
+                let address = self.pop()? as u32;
+                match self.pop_float()? {
+                    Real::Double(value) => {
+                        self.write_word(address, value.to_bits() as i32);
+                        self.write_word(address.wrapping_add(4), (value.to_bits() >> 32) as i32);
+                    }
+                    Real::Single(_) => return Err("stored a float as a double".to_owned()),
+                }
+            }
+            Instruction::Fpldzerosn => self.push_float(Real::Single(0.0))?,
+            Instruction::Fpldzerodb => self.push_float(Real::Double(0.0))?,
+            Instruction::Fpadd | Instruction::Fpsub | Instruction::Fpmul | Instruction::Fpdiv => {
+                let (b, a) = self.pop_floats()?;
+                let result = match instruction {
+                    Instruction::Fpadd => b.value() + a.value(),
+                    Instruction::Fpsub => b.value() - a.value(),
+                    Instruction::Fpmul => b.value() * a.value(),
+                    _ => b.value() / a.value(),
+                };
+                self.push_float(b.with(result))?;
+            }
+            Instruction::Fprev => {
+                let a = self.pop_float()?;
+                let b = self.pop_float()?;
+                self.push_float(a)?;
+                self.push_float(b)?;
+            }
+            Instruction::Fpi32tor32 | Instruction::Fpi32tor64 | Instruction::Fpb32tor64 => {
+                let address = self.pop()? as u32;
+                let word = self.read_word(address);
+                self.push_float(match instruction {
+                    Instruction::Fpi32tor32 => Real::Single(word as f32),
+                    Instruction::Fpi32tor64 => Real::Double(word as f64),
+                    _ => Real::Double(word as u32 as f64),
+                })?;
+            }
+            Instruction::Fpint => {
+                let a = self.pop_float()?;
+                let value = if round_to_zero {
+                    a.value().trunc()
+                } else {
+                    a.value().round_ties_even()
+                };
+                self.push_float(a.with(value))?;
+            }
+            Instruction::Fpstnli32 => {
+                let address = self.pop()? as u32;
+                let value = self.pop_float()?.value();
+                if value.fract() != 0.0 || !(i32::MIN as f64..=i32::MAX as f64).contains(&value) {
+                    return Err(format!("fpstnli32 of {}", value));
+                }
+                self.write_word(address, value as i32);
+            }
+            Instruction::Fpgt | Instruction::Fpeq => {
+                let (b, a) = self.pop_floats()?;
+                let result = match instruction {
+                    Instruction::Fpgt => b.value() > a.value(),
+                    _ => b.value() == a.value(),
+                };
+                self.push(result as i32)?;
+            }
+            Instruction::Fpordered => {
+                let a = self.float_registers[0].ok_or("read an undefined FPU register")?;
+                let b = self.float_registers[1].ok_or("read an undefined FPU register")?;
+                self.push(!(a.value().is_nan() || b.value().is_nan()) as i32)?;
+            }
+            Instruction::Fpurz => {
+                self.fpentry()?;
+                self.round_to_zero = true;
+            }
+            Instruction::Fpur32tor64 => {
+                self.fpentry()?;
+                match self.pop_float()? {
+                    Real::Single(value) => self.push_float(Real::Double(value as f64))?,
+                    Real::Double(_) => return Err("fpur32tor64 of a double".to_owned()),
+                }
+            }
+            Instruction::Fpur64tor32 => {
+                self.fpentry()?;
+                match self.pop_float()? {
+                    Real::Double(value) => self.push_float(Real::Single(value as f32))?,
+                    Real::Single(_) => return Err("fpur64tor32 of a float".to_owned()),
+                }
+            }
+            Instruction::Label(_) => {}
+            _ => {
+                let a = self.pop()?;
+                let b = self.pop()?;
+                let result = match instruction {
+                    Instruction::Sum => b.wrapping_add(a),
+                    Instruction::Diff => b.wrapping_sub(a),
+                    Instruction::Prod => b.wrapping_mul(a),
+                    Instruction::Div | Instruction::Rem if a == 0 || (b == i32::MIN && a == -1) => {
+                        return Err("division overflowed".to_owned())
+                    }
+                    Instruction::Div => b / a,
+                    Instruction::Rem => b % a,
+                    Instruction::And => b & a,
+                    Instruction::Or => b | a,
+                    Instruction::Xor => b ^ a,
+                    Instruction::Shl => (b as u32).checked_shl(a as u32).unwrap_or(0) as i32,
+                    Instruction::Shr => (b as u32).checked_shr(a as u32).unwrap_or(0) as i32,
+                    Instruction::Gt => (b > a) as i32,
+                    // The sign bit in A must be above the value in B.
+                    Instruction::Xword if (b as u32 as u64) < 2 * (a as u32 as u64) && a.count_ones() == 1 => {
+                        if b & a != 0 {
+                            b.wrapping_sub(a.wrapping_mul(2))
+                        } else {
+                            b
+                        }
+                    }
+                    Instruction::Xword => return Err("xword of an out of range value".to_owned()),
+                    _ => unreachable!("{:?} is handled above", instruction),
+                };
+                self.push(result)?;
+            }
+        }
+        Ok(None)
+    }
+}
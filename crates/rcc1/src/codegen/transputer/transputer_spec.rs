@@ -0,0 +1,420 @@
+mod transputer_spec {
+    use common::target_platform::TargetPlatform;
+    use hamcrest2::prelude::*;
+
+    use crate::codegen::transputer::scheduling::{FLOAT_STACK_DEPTH, STACK_DEPTH};
+    use crate::codegen::transputer::simulator::Simulator;
+    use crate::codegen::transputer::{assembly, generation, workspace, Program};
+    use crate::ir;
+    use crate::ir::soft_float::{self, runtime_source};
+    use crate::program_test_helper::ir_of;
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn compiled(input: &str) -> String {
+        let (ir, symbols) = ir_of(input, TargetPlatform::Transputer);
+        assembly(&ir, &symbols, &TargetPlatform::Transputer.data_model())
+    }
+
+    /// The allocated assembly program, and the value the IR interpreter gives for it.
+    fn allocated(input: &str) -> (Program, Option<i128>) {
+        let (ir, symbols) = ir_of(input, TargetPlatform::Transputer);
+        let data_model = TargetPlatform::Transputer.data_model();
+        let mut program = generation::generate(&ir, &symbols, &data_model);
+        workspace::allocate(&mut program);
+        (program, ir::run(&ir, &symbols, &data_model).ok())
+    }
+
+    #[test]
+    fn listing_1_1() {
+        assert_that!(
+            compiled(include_str!("../../listing_1_1.c")),
+            eq("\t.TRANSPUTER
+\tPUBLIC main
+main:
+\tldc 2
+\tret
+\tldc 0
+\tret
+"
+            .to_owned())
+        );
+    }
+
+    #[test]
+    fn locals_are_below_the_parameters_in_the_workspace() {
+        assert_that!(
+            compiled("int add(int a, int b) { int c = a + b; return c; }"),
+            eq("\t.TRANSPUTER
+\tPUBLIC add
+add:
+\tajw -1
+\tldl 2
+\tldl 3
+\tsum
+\tstl 0
+\tldl 0
+\tajw 1
+\tret
+\tldc 0
+\tajw 1
+\tret
+"
+            .to_owned())
+        );
+    }
+
+    #[test]
+    fn the_first_three_arguments_are_passed_in_the_evaluation_stack() {
+        let assembly = compiled(
+            "int f(int a, int b, int c, int d);
+int main(void) { int x = 5; return f(x, 2, 3, 4); }",
+        );
+        assert_that!(
+            assembly.contains(
+                "main:
+\tajw -3
+\tldc 5
+\tstl 1
+\tldc 4
+\tstl 0
+\tldc 3
+\tldc 2
+\tldl 1
+\tcall f
+\tstl 2
+\tldl 2
+\tajw 3
+\tret
+"
+            ),
+            eq(true)
+        );
+    }
+
+    #[test]
+    fn static_variables_are_addressed_relative_to_the_instruction_pointer() {
+        let assembly =
+            compiled("static signed char flag; short s = -2; int main(void) { flag = 1; return s; }");
+        assert_that!(
+            assembly.contains("\tldc flag - ldpi..1\n\tldpi\nldpi..1:\n\tsb\n"),
+            eq(true)
+        );
+        assert_that!(
+            assembly.contains("\tldc s - ldpi..2\n\tldpi\nldpi..2:\n\tldnl 0\n\tldc 32768\n\txword\n"),
+            eq(true)
+        );
+        assert_that!(
+            assembly.ends_with("flag:\n\tDB 0\n\tALIGN 4\n\tPUBLIC s\ns:\n\tDD 65534\n"),
+            eq(true)
+        );
+    }
+
+    #[test]
+    fn anchors_cannot_clash_with_renamed_statics() {
+        assert_that!(
+            compiled("int main(void) { static int ldpi = 3; return ldpi; }"),
+            eq("\t.TRANSPUTER
+\tPUBLIC main
+main:
+\tldc ldpi.1 - ldpi..1
+\tldpi
+ldpi..1:
+\tldnl 0
+\tret
+\tldc 0
+\tret
+\tALIGN 4
+ldpi.1:
+\tDD 3
+"
+            .to_owned())
+        );
+    }
+
+    #[test]
+    fn only_names_with_external_linkage_are_public() {
+        let assembly = compiled(
+            "static int helper(int a) { return a; } static int hidden = 1; int shared = 2;
+int main(void) { return helper(hidden + shared); }",
+        );
+        assert_that!(assembly.contains("\nhelper:\n"), eq(true));
+        assert_that!(assembly.contains("\tPUBLIC helper\n"), eq(false));
+        assert_that!(assembly.contains("\tPUBLIC hidden\n"), eq(false));
+        assert_that!(assembly.contains("\tPUBLIC main\nmain:\n"), eq(true));
+        assert_that!(assembly.contains("\tPUBLIC shared\nshared:\n"), eq(true));
+    }
+
+    #[test]
+    fn operands_are_left_to_the_assembler_to_prefix() {
+        let assembly =
+            compiled("int main(void) { unsigned int u = 4294967295u; return u > 4000000000u; }");
+        assert_that!(assembly.contains("\tldc -1\n"), eq(true));
+        assert_that!(assembly.contains("\tajw -"), eq(true));
+        // The unsigned constant is compared with its sign bit inverted.
+        assert_that!(assembly.contains("\tldc 1852516352\n\tgt\n"), eq(true));
+    }
+
+    #[test]
+    fn the_modulo_arithmetic_instructions_are_used() {
+        let assembly = compiled("int main(void) { int a = 2147483647; return a * 2 + 1 - a; }");
+        assert_that!(assembly.contains("\tprod\n"), eq(true));
+        assert_that!(assembly.contains("\tsum\n"), eq(true));
+        assert_that!(assembly.contains("\tdiff\n"), eq(true));
+        assert_that!(assembly.contains("\tadd\n") || assembly.contains("\tmul\n"), eq(false));
+    }
+
+    /// Programs whose results on the simulated Transputer must agree with the IR interpreter's.
+    const SIMULATED_PROGRAMS: &[&str] = &[
+        include_str!("../../listing_1_1.c"),
+        "int main(void) { int a = 7, b = -3; return a * b + a / b - a % b; }",
+        "int main(void) { unsigned int u = 4000000000u; return u / 3u % 1000 + (u > 5u) + (u >> 31) + (5u < u) * 10; }",
+        "int main(void) { unsigned int u = 4000000000u, v = 7u; return u % v * 100 + (u <= v) + (v >= u) * 2 + (u >= u) * 4; }",
+        "int main(void) { int n = -1000; unsigned int big = 3000000000u; return (n >> 3) + (-16 >> 2) * 1000 + (int) (big >> 28); }",
+        "int main(void) { char c = 200; signed char s = c; unsigned short us = s; short sh = us; return c + s * 1000 + us * 3 + sh; }",
+        "int main(void) { int x = 10; x <<= 3; x |= 5; x &= ~1; x -= 7; x /= 2; x %= 100; x ^= 3; return x >> 1; }",
+        "int main(void) { int count = 3; long shifted = 1L << count * 9; return (int) (shifted >> 25) + (-16 >> count == -2); }",
+        "int eight(int a, int b, int c, int d, int e, int f, char g, long h) { return a - b + c - d + e - f + g - (int) h; }
+int main(void) { return eight(10, 1, 20, 2, 30, 3, -4, 5L) * 1000 + eight(1, 1, 1, 1, 1, 1, 1, 1); }",
+        "int fib(int n) { return n < 2 ? n : fib(n - 1) + fib(n - 2); } int main(void) { return fib(15); }",
+        "int counter = 5;
+static long total;
+static signed char last;
+static unsigned short wide = 65535;
+int bump(short by) { static int calls; calls++; total += by; last = by; wide = wide + by; return calls; }
+int main(void) { for (int i = 0; i < 10; i++) { bump(i * 30); } return (int) total + bump(-1) + counter + last * 1000 + wide; }",
+        "int classify(unsigned char c) {
+    switch (c) { case 90 + 7: return 1; case 200: return 2; default: return 3; case 0: break; }
+    return 4;
+}
+int main(void) { return classify(97) * 100 + classify(200) * 10 + classify(0) + (classify(1) == 3); }",
+        "int main(void) { int i = 0, odd = 0; do { if (i % 2) { odd++; continue; } if (i > 20) break; } while (++i < 100); return odd + !i + (i && odd) * 100 + (0 || i) * 1000; }",
+        "int main(void) { int a = -2147483647 - 1; unsigned int b = a; return (a < 0) + (b > 0) * 2 + (-a == a) * 4 + (~a == 2147483647) * 8 + (a != 3) * 16; }",
+        "int main(void) { int a = 3, b = -5, c = 7, d = 11, e = -13, f = 17, g = 19, h = -23;
+    return (a * b - c * d) * (e * f - g * h) - ((a - b) * (c - d) - (e - f) * (g - h)); }",
+        "int main(void) { unsigned int u = 4000000000u, v = 12345u, w = 7u;
+    return (int) ((u - v * w) / (v + w * w) % (w * 3u + 1u)) + ((u ^ v) < (v - w * u)); }",
+        "int main(void) { int n = -100000, k = 2; return (n * 3 - k) >> (k * k - 1) >> (n > 0 ? 1 : k - 1); }",
+        "static short s = -300; static signed char c = -7;
+int main(void) { return (s - c) * (c - s) - (s * c - (c - s)) * ((s + 1) - (c + 2)); }",
+        "int three(int a, int b, int c) { return a * 10000 + b * 100 + c; }
+int main(void) { int p = 3, q = 5; return three((p - q) * (q - p) - (p * q - q), (p + q) * (p - q), p - q) + three(1, 2, 3); }",
+    ];
+
+    #[test]
+    fn simulated_results_agree_with_the_interpreter() {
+        for (index, program) in SIMULATED_PROGRAMS.iter().enumerate() {
+            let (assembly, interpreted) = allocated(program);
+            let expected = interpreted.expect("the program runs in the interpreter");
+            let mut simulator = Simulator::new(&assembly);
+            let actual = simulator
+                .run()
+                .unwrap_or_else(|e| panic!("program {} failed: {}\n{}", index, e, assembly));
+            assert_that!((index, actual as i128), eq((index, expected)));
+            assert_that!(simulator.max_depth <= STACK_DEPTH, eq(true));
+        }
+    }
+
+    #[test]
+    fn deep_expressions_spill_subexpressions_to_the_workspace() {
+        let assembly = compiled(
+            "int f(int a, int b, int c, int d, int e, int f, int g, int h) {
+    return (a - b) * (c - d) - (e - f) * (g - h); }",
+        );
+        // The products need three registers each, so the first is stored while the second is
+        // evaluated.
+        assert_that!(
+            assembly.contains("\tprod\n\tstl 0\n\tldl 6\n\tldl 7\n\tdiff\n\tldl 8\n\tldl 9\n\tdiff\n\tprod\n\tldl 0\n\trev\n\tdiff\n"),
+            eq(true)
+        );
+    }
+
+    #[test]
+    fn single_use_temporaries_are_not_stored() {
+        let assembly = compiled("int f(int a, int b, int c) { return a - (b - c); }");
+        assert_that!(
+            assembly.contains("\tldl 2\n\tldl 3\n\tdiff\n\tldl 1\n\trev\n\tdiff\n\tret\n"),
+            eq(true)
+        );
+    }
+
+    /// Generates expressions over int and unsigned int variables, pseudo-randomly but the same
+    /// each time, with shift counts and divisors kept in range.
+    struct Expressions(u64);
+
+    impl Expressions {
+        fn next(&mut self, bound: u64) -> u64 {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (self.0 >> 33) % bound
+        }
+
+        fn expression(&mut self, height: usize) -> String {
+            if height == 0 || self.next(5) == 0 {
+                return match self.next(3) {
+                    0 => format!("{}", self.next(200) as i64 - 100),
+                    _ => ["a", "b", "c", "d", "u", "v", "w", "x"][self.next(8) as usize].to_owned(),
+                };
+            }
+            let left = self.expression(height - 1);
+            let right = self.expression(height - 1);
+            match self.next(12) {
+                0 => format!("({} >> ({} & 15))", left, right),
+                1 => format!("({} / ({} | 1u))", left, right),
+                2 => format!("({} % ({} | 1u))", left, right),
+                3 => format!("-({} ^ {})", left, right),
+                4 => format!("~({} | {})", left, right),
+                operator => format!(
+                    "({} {} {})",
+                    left,
+                    ["+", "-", "*", "&", "<", "==", ">="][operator as usize - 5],
+                    right
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn generated_expressions_never_overflow_the_stack() {
+        let mut expressions = Expressions(2024);
+        for _ in 0..60 {
+            let expression = expressions.expression(6);
+            let program = format!(
+                "int a = -7, b = 100003, c = -2147483647, d = 12;
+unsigned int u = 3000000000u, v = 5u, w = 65537u, x = 4294967295u;
+int main(void) {{ return {}; }}",
+                expression
+            );
+            let (assembly, interpreted) = allocated(&program);
+            let expected = interpreted.expect("the program runs in the interpreter");
+            let mut simulator = Simulator::new(&assembly);
+            let actual = simulator
+                .run()
+                .unwrap_or_else(|e| panic!("{} failed: {}\n{}", expression, e, assembly));
+            assert_that!((&expression, actual as i128), eq((&expression, expected)));
+        }
+    }
+
+    /// A program using floating point, built with the soft-float runtime.
+    const SOFT_FLOAT_PROGRAM: &str = "double area(float width, double height) { return width * height; }
+int main(void) { float w = 2.5f; double h = 0.1, sum = 0; int i;
+    for (i = 0; i < 10; i++) sum = sum + area(w, h * i);
+    return (int) (sum * 100) + (sum > 11.0) * 1000 + (int) -(w / 3 * 7); }";
+
+    #[test]
+    fn soft_float_results_agree_with_the_interpreter() {
+        let data_model = TargetPlatform::Transputer.data_model();
+        let (ir, symbols) = ir_of(SOFT_FLOAT_PROGRAM, TargetPlatform::Transputer);
+        let expected = ir::run(&ir, &symbols, &data_model).expect("the program runs in the interpreter");
+        let (ir, mut symbols) = ir_of(&format!("{}\n{}", runtime_source(), SOFT_FLOAT_PROGRAM), TargetPlatform::Transputer);
+        let ir = soft_float::lower(&ir, &mut symbols, &data_model);
+        let mut program = generation::generate(&ir, &symbols, &data_model);
+        workspace::allocate(&mut program);
+        let actual = Simulator::new(&program).run().unwrap_or_else(|e| panic!("{}", e));
+        assert_that!(actual as i128, eq(expected));
+    }
+
+    #[test]
+    fn floating_values_are_loaded_through_their_addresses_onto_the_fpu_stack() {
+        assert_that!(
+            compiled("int less(float a, float b) { return a <= b; }"),
+            eq("\t.TRANSPUTER
+\tPUBLIC less
+less:
+\tldlp 1
+\tfpldnlsn
+\tldlp 2
+\tfpldnlsn
+\tfpordered
+\tfpgt
+\teqc 0
+\tand
+\tret
+\tldc 0
+\tret
+"
+            .to_owned())
+        );
+    }
+
+    #[test]
+    fn a_double_takes_two_words_and_floating_constants_are_pooled() {
+        assert_that!(
+            compiled("double twice(double d) { double e = d * 2.0; return e + 2.0; }"),
+            eq("\t.TRANSPUTER
+\tPUBLIC twice
+twice:
+\tajw -2
+\tldlp 3
+\tfpldnldb
+\tldc fpconst..1 - ldpi..1
+\tldpi
+ldpi..1:
+\tfpldnldb
+\tfpmul
+\tldlp 0
+\tfpstnldb
+\tldlp 0
+\tfpldnldb
+\tldc fpconst..1 - ldpi..2
+\tldpi
+ldpi..2:
+\tfpldnldb
+\tfpadd
+\tajw 2
+\tret
+\tfpldzerodb
+\tajw 2
+\tret
+\tALIGN 4
+fpconst..1:
+\tDD 0
+\tDD 1073741824
+"
+            .to_owned())
+        );
+    }
+
+    #[test]
+    fn pooled_constants_cannot_clash_with_renamed_statics() {
+        let assembly = compiled("double f(void) { static double fpconst = 0.5; return fpconst * 2.0; }");
+        assert_that!(assembly.contains("\nfpconst.1:\n\tDD 0\n\tDD 1071644672\n"), eq(true));
+        assert_that!(assembly.contains("\nfpconst..1:\n\tDD 0\n\tDD 1073741824\n"), eq(true));
+    }
+
+    /// Programs using floating point, which the T800 evaluates on its FPU.
+    const FPU_PROGRAMS: [&str; 5] = [
+        SOFT_FLOAT_PROGRAM,
+        "static double total = 0.5; static float scale = 3.0f;
+double f(double a, double b, float c, double d, float e) { return a - b * c + d / e; }
+int main(void) { double x = f(1.0, 2.0, 3.5f, 10.0, 4.0f); total = total + x;
+    return (int) (total * 1000) + (int) (scale * scale); }",
+        "unsigned int u = 4000000000u;
+int main(void) { double d = u; float f = u; unsigned int back = d + 1.0; unsigned int v = 3.9e9f;
+    unsigned char c = 200; signed char s = -100; float g = c + s; short h = -3; double k = h;
+    return (back - 4000000000u) + (int) g * 10 + (int) k * 1000 + (int) (f / 1000000) * 10000 + (v == 3900000000u) * 2; }",
+        "int main(void) { double zero = 0.0, one = 1.0; double nan = zero / zero; float m = -0.0f; int r = 0;
+    r = r + (nan < one) + (nan <= one) * 2 + (nan >= one) * 4 + (nan > one) * 8 + (nan == nan) * 16 + (nan != nan) * 32;
+    r = r + (one <= one) * 64 + (one >= one + one) * 128 + (1 / -zero < 0) * 256 + !m * 512 + (1 / -m > 0) * 1024;
+    if (nan) r = r + 2048; if (!zero) r = r + 4096; while (m) r = 0; return r; }",
+        "double a = 1.5, b = 2.25, c = -3.0, d = 4.5, e = 0.5, f = 7.0, g = -1.25, h = 2.0;
+int main(void) { return (int) (((a - b) * (c - d) - (e - f) * (g - h)) / ((a + c) * (b - d) - (e * f - g / h)) * 1000000); }",
+    ];
+
+    #[test]
+    fn fpu_results_agree_with_the_interpreter() {
+        for (index, program) in FPU_PROGRAMS.iter().enumerate() {
+            let (assembly, interpreted) = allocated(program);
+            let expected = interpreted.expect("the program runs in the interpreter");
+            let mut simulator = Simulator::new(&assembly);
+            let actual = simulator
+                .run()
+                .unwrap_or_else(|e| panic!("program {} failed: {}\n{}", index, e, assembly));
+            assert_that!((index, actual as i128), eq((index, expected)));
+            assert_that!(simulator.max_depth <= STACK_DEPTH, eq(true));
+            assert_that!(simulator.max_float_depth <= FLOAT_STACK_DEPTH, eq(true));
+        }
+    }
+}
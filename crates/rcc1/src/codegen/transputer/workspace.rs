@@ -0,0 +1,82 @@
+//! Lays out each function's workspace frame, and replaces the pseudo workspace slots with word
+//! offsets from the workspace pointer. From the bottom, the frame holds:
+//!
+//! * the arguments passed in memory by the calls the function makes, enough words for the call
+//!   with the most;
+//! * a word for each local variable and temporary, or two for a 'double'.
+//!
+//! Above the frame are the words stored by 'call': the return address, then the evaluation stack,
+//! holding the parameters passed in registers, then the parameters passed in memory, which the
+//! caller left at the bottom of its own frame.
+//!
+//! The frame is allocated with 'ajw' on entry, and released before each 'ret'.
+
+use std::collections::HashMap;
+
+use crate::codegen::calling_convention::{CallingConvention, Location};
+use crate::codegen::transputer::{Inmos, Instruction, Program, TopLevel, Workspace};
+
+/// The words stored by 'call': the return address, and the A, B and C registers.
+const CALL_WORDS: i32 = 4;
+
+pub fn allocate(program: &mut Program) {
+    for top_level in &mut program.top_level {
+        if let TopLevel::Function(function) = top_level {
+            let arguments = function
+                .instructions
+                .iter()
+                .filter_map(|instruction| match instruction {
+                    Instruction::Stl(Workspace::Argument(index)) => Some(*index as i32 + 1),
+                    _ => None,
+                })
+                .max()
+                .unwrap_or(0);
+
+            let mut slots: HashMap<String, i32> = HashMap::new();
+            let mut frame = arguments;
+            for instruction in &function.instructions {
+                if let Instruction::Ldl(workspace) | Instruction::Stl(workspace) | Instruction::Ldlp(workspace) = instruction {
+                    let (name, words) = match workspace {
+                        Workspace::Pseudo(name) => (name, 1),
+                        Workspace::PseudoDouble(name, _) => (name, 2),
+                        _ => continue,
+                    };
+                    if !slots.contains_key(name) {
+                        slots.insert(name.clone(), frame);
+                        frame += words;
+                    }
+                }
+            }
+
+            let offset = |workspace: &Workspace| {
+                Workspace::Offset(match workspace {
+                    Workspace::Pseudo(name) => slots[name],
+                    Workspace::PseudoDouble(name, word) => slots[name] + *word as i32,
+                    Workspace::Parameter(index) => match Inmos::argument(*index) {
+                        Location::Register(register) => frame + 1 + register as i32,
+                        Location::Memory(slot) => frame + CALL_WORDS + slot as i32,
+                    },
+                    Workspace::Argument(index) => *index as i32,
+                    Workspace::Offset(offset) => *offset,
+                })
+            };
+            let mut instructions = vec![];
+            if frame != 0 {
+                instructions.push(Instruction::Ajw(-frame));
+            }
+            for instruction in std::mem::take(&mut function.instructions) {
+                match instruction {
+                    Instruction::Ldl(workspace) => instructions.push(Instruction::Ldl(offset(&workspace))),
+                    Instruction::Stl(workspace) => instructions.push(Instruction::Stl(offset(&workspace))),
+                    Instruction::Ldlp(workspace) => instructions.push(Instruction::Ldlp(offset(&workspace))),
+                    Instruction::Ret if frame != 0 => {
+                        instructions.push(Instruction::Ajw(frame));
+                        instructions.push(Instruction::Ret);
+                    }
+                    _ => instructions.push(instruction),
+                }
+            }
+            function.instructions = instructions;
+        }
+    }
+}
@@ -0,0 +1,353 @@
+//! Writes the assembly program as text for the GNU assembler, in AT&T syntax: the source operand
+//! comes first, registers and immediates are prefixed with '%' and '$', and instructions have a
+//! suffix giving their operand size. SSE instructions are named for their operand size instead,
+//! e.g. 'addsd' and 'addss', which are the suffixes of 'Double' and 'Single'.
+//!
+//! Symbols follow the host's conventions, so the output can be assembled and linked with the
+//! host's gcc: macOS prefixes C names with an underscore, and Linux calls functions defined in
+//! other modules through the procedure linkage table.
+
+use std::fmt::{Display, Formatter};
+
+use crate::codegen::x86_64::{
+    AssemblyType, BinaryOperator, ConditionCode, Instruction, Operand, Program, Register,
+    StaticConstant, StaticVariable, TopLevel, UnaryOperator,
+};
+
+fn symbol(name: &str) -> String {
+    if cfg!(target_os = "macos") {
+        format!("_{}", name)
+    } else {
+        name.to_owned()
+    }
+}
+
+/// Labels local to a function, which do not appear in the object file's symbol table.
+fn local_label(label: &str) -> String {
+    if cfg!(target_os = "macos") {
+        format!("L{}", label)
+    } else {
+        format!(".L{}", label)
+    }
+}
+
+fn suffix(assembly_type: AssemblyType) -> &'static str {
+    match assembly_type {
+        AssemblyType::Byte => "b",
+        AssemblyType::Word => "w",
+        AssemblyType::Longword => "l",
+        AssemblyType::Quadword => "q",
+        AssemblyType::Single => "ss",
+        AssemblyType::Double => "sd",
+    }
+}
+
+fn register(register: Register, assembly_type: AssemblyType) -> &'static str {
+    use AssemblyType::*;
+    match (register, assembly_type) {
+        (Register::AX, Byte) => "%al",
+        (Register::AX, Word) => "%ax",
+        (Register::AX, Longword) => "%eax",
+        (Register::AX, Quadword) => "%rax",
+        (Register::BX, Byte) => "%bl",
+        (Register::BX, Word) => "%bx",
+        (Register::BX, Longword) => "%ebx",
+        (Register::BX, Quadword) => "%rbx",
+        (Register::CX, Byte) => "%cl",
+        (Register::CX, Word) => "%cx",
+        (Register::CX, Longword) => "%ecx",
+        (Register::CX, Quadword) => "%rcx",
+        (Register::DX, Byte) => "%dl",
+        (Register::DX, Word) => "%dx",
+        (Register::DX, Longword) => "%edx",
+        (Register::DX, Quadword) => "%rdx",
+        (Register::DI, Byte) => "%dil",
+        (Register::DI, Word) => "%di",
+        (Register::DI, Longword) => "%edi",
+        (Register::DI, Quadword) => "%rdi",
+        (Register::SI, Byte) => "%sil",
+        (Register::SI, Word) => "%si",
+        (Register::SI, Longword) => "%esi",
+        (Register::SI, Quadword) => "%rsi",
+        (Register::R8, Byte) => "%r8b",
+        (Register::R8, Word) => "%r8w",
+        (Register::R8, Longword) => "%r8d",
+        (Register::R8, Quadword) => "%r8",
+        (Register::R9, Byte) => "%r9b",
+        (Register::R9, Word) => "%r9w",
+        (Register::R9, Longword) => "%r9d",
+        (Register::R9, Quadword) => "%r9",
+        (Register::R10, Byte) => "%r10b",
+        (Register::R10, Word) => "%r10w",
+        (Register::R10, Longword) => "%r10d",
+        (Register::R10, Quadword) => "%r10",
+        (Register::R11, Byte) => "%r11b",
+        (Register::R11, Word) => "%r11w",
+        (Register::R11, Longword) => "%r11d",
+        (Register::R11, Quadword) => "%r11",
+        (Register::R12, Byte) => "%r12b",
+        (Register::R12, Word) => "%r12w",
+        (Register::R12, Longword) => "%r12d",
+        (Register::R12, Quadword) => "%r12",
+        (Register::R13, Byte) => "%r13b",
+        (Register::R13, Word) => "%r13w",
+        (Register::R13, Longword) => "%r13d",
+        (Register::R13, Quadword) => "%r13",
+        (Register::R14, Byte) => "%r14b",
+        (Register::R14, Word) => "%r14w",
+        (Register::R14, Longword) => "%r14d",
+        (Register::R14, Quadword) => "%r14",
+        (Register::R15, Byte) => "%r15b",
+        (Register::R15, Word) => "%r15w",
+        (Register::R15, Longword) => "%r15d",
+        (Register::R15, Quadword) => "%r15",
+        (Register::SP, _) => "%rsp",
+        (Register::BP, _) => "%rbp",
+        (Register::XMM0, _) => "%xmm0",
+        (Register::XMM1, _) => "%xmm1",
+        (Register::XMM2, _) => "%xmm2",
+        (Register::XMM3, _) => "%xmm3",
+        (Register::XMM4, _) => "%xmm4",
+        (Register::XMM5, _) => "%xmm5",
+        (Register::XMM6, _) => "%xmm6",
+        (Register::XMM7, _) => "%xmm7",
+        (Register::XMM14, _) => "%xmm14",
+        (Register::XMM15, _) => "%xmm15",
+        (register, Single | Double) => unreachable!("no floating value is moved into {:?}", register),
+    }
+}
+
+/// An operand as it appears in an instruction of the given size.
+fn operand(operand: &Operand, assembly_type: AssemblyType) -> String {
+    match operand {
+        // Unsigned 64-bit values above the signed range are written as their two's complement.
+        Operand::Immediate(value) if assembly_type == AssemblyType::Quadword => {
+            format!("${}", *value as i64)
+        }
+        Operand::Immediate(value) => format!("${}", value),
+        Operand::Register(r) => register(*r, assembly_type).to_owned(),
+        Operand::Pseudo(name) => unreachable!("pseudo-register '{}' was not replaced", name),
+        Operand::Stack(offset) => format!("{}(%rbp)", offset),
+        Operand::Data(name) => format!("{}(%rip)", symbol(name)),
+    }
+}
+
+fn condition(condition: ConditionCode) -> &'static str {
+    match condition {
+        ConditionCode::E => "e",
+        ConditionCode::NE => "ne",
+        ConditionCode::L => "l",
+        ConditionCode::LE => "le",
+        ConditionCode::G => "g",
+        ConditionCode::GE => "ge",
+        ConditionCode::B => "b",
+        ConditionCode::BE => "be",
+        ConditionCode::A => "a",
+        ConditionCode::AE => "ae",
+        ConditionCode::P => "p",
+    }
+}
+
+impl Display for Program {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for top_level in &self.top_level {
+            write!(f, "{}", top_level)?;
+        }
+        if cfg!(target_os = "linux") {
+            // The stack need not be executable.
+            writeln!(f, "\t.section .note.GNU-stack,\"\",@progbits")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for TopLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TopLevel::Function(function) => {
+                if function.global {
+                    writeln!(f, "\t.globl {}", symbol(&function.name))?;
+                }
+                writeln!(f, "\t.text")?;
+                writeln!(f, "{}:", symbol(&function.name))?;
+                writeln!(f, "\tpushq %rbp")?;
+                writeln!(f, "\tmovq %rsp, %rbp")?;
+                for instruction in &function.instructions {
+                    writeln!(f, "{}", instruction)?;
+                }
+                Ok(())
+            }
+            TopLevel::StaticVariable(variable) => write!(f, "{}", variable),
+            TopLevel::StaticConstant(constant) => write!(f, "{}", constant),
+        }
+    }
+}
+
+/// Zero-initialised variables go in the BSS section, which takes no space in the object file.
+impl Display for StaticVariable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.global {
+            writeln!(f, "\t.globl {}", symbol(&self.name))?;
+        }
+        writeln!(f, "\t{}", if self.init == 0 { ".bss" } else { ".data" })?;
+        writeln!(f, "\t.balign {}", self.assembly_type.size())?;
+        writeln!(f, "{}:", symbol(&self.name))?;
+        if self.init == 0 {
+            writeln!(f, "\t.zero {}", self.assembly_type.size())
+        } else {
+            let directive = match self.assembly_type {
+                AssemblyType::Byte => ".byte",
+                AssemblyType::Word => ".value",
+                AssemblyType::Longword | AssemblyType::Single => ".long",
+                AssemblyType::Quadword | AssemblyType::Double => ".quad",
+            };
+            writeln!(f, "\t{} {}", directive, self.init as i64)
+        }
+    }
+}
+
+/// Constants go in read-only data, which on macOS is a section of literals of their size.
+impl Display for StaticConstant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if cfg!(target_os = "macos") {
+            writeln!(f, "\t.literal{}", self.alignment)?;
+        } else {
+            writeln!(f, "\t.section .rodata")?;
+        }
+        writeln!(f, "\t.balign {}", self.alignment)?;
+        writeln!(f, "{}:", symbol(&self.name))?;
+        let directive = match self.assembly_type {
+            AssemblyType::Single => ".long",
+            _ => ".quad",
+        };
+        writeln!(f, "\t{} {}", directive, self.init as i64)?;
+        let padding = self.alignment - self.assembly_type.size();
+        if padding != 0 {
+            writeln!(f, "\t.zero {}", padding)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instruction::Mov(t, src, dst) => write!(
+                f,
+                "\tmov{} {}, {}",
+                suffix(*t),
+                operand(src, *t),
+                operand(dst, *t)
+            ),
+            Instruction::Movsx(src_type, dst_type, src, dst) => write!(
+                f,
+                "\tmovs{}{} {}, {}",
+                suffix(*src_type),
+                suffix(*dst_type),
+                operand(src, *src_type),
+                operand(dst, *dst_type)
+            ),
+            Instruction::MovZeroExtend(src_type, dst_type, src, dst) => write!(
+                f,
+                "\tmovz{}{} {}, {}",
+                suffix(*src_type),
+                suffix(*dst_type),
+                operand(src, *src_type),
+                operand(dst, *dst_type)
+            ),
+            Instruction::Unary(operator, t, dst) => {
+                let name = match operator {
+                    UnaryOperator::Neg => "neg",
+                    UnaryOperator::Not => "not",
+                };
+                write!(f, "\t{}{} {}", name, suffix(*t), operand(dst, *t))
+            }
+            // The bitwise SSE instructions operate on whole registers, and have no scalar forms.
+            Instruction::Binary(BinaryOperator::Xor, t, src, dst) if t.is_floating() => {
+                let name = if *t == AssemblyType::Single { "xorps" } else { "xorpd" };
+                write!(f, "\t{} {}, {}", name, operand(src, *t), operand(dst, *t))
+            }
+            Instruction::Binary(operator, t, src, dst) => {
+                let name = match operator {
+                    BinaryOperator::Add => "add",
+                    BinaryOperator::Sub => "sub",
+                    BinaryOperator::Imul if t.is_floating() => "mul",
+                    BinaryOperator::Imul => "imul",
+                    BinaryOperator::DivFloating => "div",
+                    BinaryOperator::And => "and",
+                    BinaryOperator::Or => "or",
+                    BinaryOperator::Xor => "xor",
+                    BinaryOperator::Sal => "sal",
+                    BinaryOperator::Sar => "sar",
+                    BinaryOperator::Shr => "shr",
+                };
+                // A shift count in a register is always in CL.
+                let src = match (operator, src) {
+                    (
+                        BinaryOperator::Sal | BinaryOperator::Sar | BinaryOperator::Shr,
+                        Operand::Register(r),
+                    ) => register(*r, AssemblyType::Byte).to_owned(),
+                    _ => operand(src, *t),
+                };
+                write!(f, "\t{}{} {}, {}", name, suffix(*t), src, operand(dst, *t))
+            }
+            Instruction::Cmp(t, src, dst) => write!(
+                f,
+                "\t{}{} {}, {}",
+                if t.is_floating() { "ucomi" } else { "cmp" },
+                suffix(*t),
+                operand(src, *t),
+                operand(dst, *t)
+            ),
+            Instruction::Idiv(t, src) => write!(f, "\tidiv{} {}", suffix(*t), operand(src, *t)),
+            Instruction::Div(t, src) => write!(f, "\tdiv{} {}", suffix(*t), operand(src, *t)),
+            Instruction::Cdq(AssemblyType::Quadword) => write!(f, "\tcqo"),
+            Instruction::Cdq(_) => write!(f, "\tcdq"),
+            Instruction::Cvtsi2sd(src_type, dst_type, src, dst) => write!(
+                f,
+                "\tcvtsi2{}{} {}, {}",
+                suffix(*dst_type),
+                suffix(*src_type),
+                operand(src, *src_type),
+                operand(dst, *dst_type)
+            ),
+            Instruction::Cvttsd2si(src_type, dst_type, src, dst) => write!(
+                f,
+                "\tcvtt{}2si{} {}, {}",
+                suffix(*src_type),
+                suffix(*dst_type),
+                operand(src, *src_type),
+                operand(dst, *dst_type)
+            ),
+            Instruction::FloatConvert(src_type, dst_type, src, dst) => write!(
+                f,
+                "\tcvt{}2{} {}, {}",
+                suffix(*src_type),
+                suffix(*dst_type),
+                operand(src, *src_type),
+                operand(dst, *dst_type)
+            ),
+            Instruction::Jmp(label) => write!(f, "\tjmp {}", local_label(label)),
+            Instruction::JmpCC(cc, label) => {
+                write!(f, "\tj{} {}", condition(*cc), local_label(label))
+            }
+            Instruction::SetCC(cc, dst) => write!(
+                f,
+                "\tset{} {}",
+                condition(*cc),
+                operand(dst, AssemblyType::Byte)
+            ),
+            Instruction::Label(label) => write!(f, "{}:", local_label(label)),
+            Instruction::Push(src) => write!(f, "\tpushq {}", operand(src, AssemblyType::Quadword)),
+            Instruction::Pop(r) => write!(f, "\tpopq {}", register(*r, AssemblyType::Quadword)),
+            Instruction::Call(name, defined) => {
+                if !defined && cfg!(target_os = "linux") {
+                    write!(f, "\tcall {}@PLT", symbol(name))
+                } else {
+                    write!(f, "\tcall {}", symbol(name))
+                }
+            }
+            Instruction::Ret => write!(f, "\tmovq %rbp, %rsp\n\tpopq %rbp\n\tret"),
+        }
+    }
+}
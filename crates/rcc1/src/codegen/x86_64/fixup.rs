@@ -0,0 +1,207 @@
+//! Rewrites instructions whose operands x86_64 cannot encode, using R10 and XMM14 for sources and
+//! R11 and XMM15 for destinations, which generation never uses. For example, no instruction takes
+//! two memory operands, 64-bit instructions only take 32-bit sign-extended immediates (except
+//! 'mov'), and 'imul', the extending moves, the conversions and the SSE arithmetic and
+//! comparisons must have a register destination.
+
+use crate::codegen::x86_64::{
+    AssemblyType, BinaryOperator, Instruction, Operand, Program, Register, TopLevel,
+};
+
+const R10: Operand = Operand::Register(Register::R10);
+const R11: Operand = Operand::Register(Register::R11);
+const XMM14: Operand = Operand::Register(Register::XMM14);
+const XMM15: Operand = Operand::Register(Register::XMM15);
+
+pub fn fix_instructions(program: &mut Program) {
+    for top_level in &mut program.top_level {
+        if let TopLevel::Function(function) = top_level {
+            function.instructions = std::mem::take(&mut function.instructions)
+                .into_iter()
+                .flat_map(fix)
+                .collect();
+        }
+    }
+}
+
+/// Is the immediate too large for a 64-bit instruction other than 'mov'?
+fn large_immediate(assembly_type: AssemblyType, operand: &Operand) -> bool {
+    assembly_type == AssemblyType::Quadword && operand.is_large_immediate()
+}
+
+fn is_register(operand: &Operand) -> bool {
+    matches!(operand, Operand::Register(_))
+}
+
+fn fix(instruction: Instruction) -> Vec<Instruction> {
+    match instruction {
+        Instruction::Mov(assembly_type, src, dst)
+            if assembly_type.is_floating() && src.is_memory() && dst.is_memory() =>
+        {
+            vec![
+                Instruction::Mov(assembly_type, src, XMM14),
+                Instruction::Mov(assembly_type, XMM14, dst),
+            ]
+        }
+        Instruction::Binary(operator, assembly_type, src, dst)
+            if assembly_type.is_floating() && !is_register(&dst) =>
+        {
+            vec![
+                Instruction::Mov(assembly_type, dst.clone(), XMM15),
+                Instruction::Binary(operator, assembly_type, src, XMM15),
+                Instruction::Mov(assembly_type, XMM15, dst),
+            ]
+        }
+        Instruction::Cmp(assembly_type, src, dst)
+            if assembly_type.is_floating() && !is_register(&dst) =>
+        {
+            vec![
+                Instruction::Mov(assembly_type, dst, XMM15),
+                Instruction::Cmp(assembly_type, src, XMM15),
+            ]
+        }
+        Instruction::Cvtsi2sd(src_type, dst_type, src, dst) => {
+            let mut instructions = vec![];
+            let src = if let Operand::Immediate(_) = src {
+                instructions.push(Instruction::Mov(src_type, src, R10));
+                R10
+            } else {
+                src
+            };
+            if is_register(&dst) {
+                instructions.push(Instruction::Cvtsi2sd(src_type, dst_type, src, dst));
+            } else {
+                instructions.push(Instruction::Cvtsi2sd(src_type, dst_type, src, XMM15));
+                instructions.push(Instruction::Mov(dst_type, XMM15, dst));
+            }
+            instructions
+        }
+        Instruction::Cvttsd2si(src_type, dst_type, src, dst) if !is_register(&dst) => vec![
+            Instruction::Cvttsd2si(src_type, dst_type, src, R11),
+            Instruction::Mov(dst_type, R11, dst),
+        ],
+        Instruction::FloatConvert(src_type, dst_type, src, dst) if !is_register(&dst) => vec![
+            Instruction::FloatConvert(src_type, dst_type, src, XMM15),
+            Instruction::Mov(dst_type, XMM15, dst),
+        ],
+        Instruction::Mov(assembly_type, src, dst) if src.is_memory() && dst.is_memory() => vec![
+            Instruction::Mov(assembly_type, src, R10),
+            Instruction::Mov(assembly_type, R10, dst),
+        ],
+        // Only a move to a register can take a 64-bit immediate.
+        Instruction::Mov(AssemblyType::Quadword, src, dst)
+            if src.is_large_immediate() && dst.is_memory() =>
+        {
+            vec![
+                Instruction::Mov(AssemblyType::Quadword, src, R10),
+                Instruction::Mov(AssemblyType::Quadword, R10, dst),
+            ]
+        }
+        Instruction::Movsx(src_type, dst_type, src, dst) => {
+            extend(Instruction::Movsx, src_type, dst_type, src, dst)
+        }
+        // There is no instruction to zero extend 32 bits to 64, as a 32-bit move into a register
+        // clears the upper half.
+        Instruction::MovZeroExtend(AssemblyType::Longword, AssemblyType::Quadword, src, dst) => {
+            match dst {
+                Operand::Register(_) => vec![Instruction::Mov(AssemblyType::Longword, src, dst)],
+                _ => vec![
+                    Instruction::Mov(AssemblyType::Longword, src, R11),
+                    Instruction::Mov(AssemblyType::Quadword, R11, dst),
+                ],
+            }
+        }
+        Instruction::MovZeroExtend(src_type, dst_type, src, dst) => {
+            extend(Instruction::MovZeroExtend, src_type, dst_type, src, dst)
+        }
+        Instruction::Binary(BinaryOperator::Imul, assembly_type, src, dst) if dst.is_memory() => {
+            let src_fixes = if large_immediate(assembly_type, &src) {
+                vec![
+                    Instruction::Mov(assembly_type, src, R10),
+                    Instruction::Binary(BinaryOperator::Imul, assembly_type, R10, R11),
+                ]
+            } else {
+                vec![Instruction::Binary(
+                    BinaryOperator::Imul,
+                    assembly_type,
+                    src,
+                    R11,
+                )]
+            };
+            let mut instructions = vec![Instruction::Mov(assembly_type, dst.clone(), R11)];
+            instructions.extend(src_fixes);
+            instructions.push(Instruction::Mov(assembly_type, R11, dst));
+            instructions
+        }
+        Instruction::Binary(operator, assembly_type, src, dst)
+            if (src.is_memory() && dst.is_memory()) || large_immediate(assembly_type, &src) =>
+        {
+            vec![
+                Instruction::Mov(assembly_type, src, R10),
+                Instruction::Binary(operator, assembly_type, R10, dst),
+            ]
+        }
+        Instruction::Cmp(assembly_type, src, dst) => {
+            let mut instructions = vec![];
+            let src =
+                if (src.is_memory() && dst.is_memory()) || large_immediate(assembly_type, &src) {
+                    instructions.push(Instruction::Mov(assembly_type, src, R10));
+                    R10
+                } else {
+                    src
+                };
+            let dst = if let Operand::Immediate(_) = dst {
+                instructions.push(Instruction::Mov(assembly_type, dst, R11));
+                R11
+            } else {
+                dst
+            };
+            instructions.push(Instruction::Cmp(assembly_type, src, dst));
+            instructions
+        }
+        Instruction::Idiv(assembly_type, operand @ Operand::Immediate(_)) => {
+            vec![
+                Instruction::Mov(assembly_type, operand, R10),
+                Instruction::Idiv(assembly_type, R10),
+            ]
+        }
+        Instruction::Div(assembly_type, operand @ Operand::Immediate(_)) => {
+            vec![
+                Instruction::Mov(assembly_type, operand, R10),
+                Instruction::Div(assembly_type, R10),
+            ]
+        }
+        Instruction::Push(operand) if operand.is_large_immediate() => {
+            vec![
+                Instruction::Mov(AssemblyType::Quadword, operand, R10),
+                Instruction::Push(R10),
+            ]
+        }
+        _ => vec![instruction],
+    }
+}
+
+/// The extending moves cannot take an immediate source, and must have a register destination.
+fn extend(
+    make: fn(AssemblyType, AssemblyType, Operand, Operand) -> Instruction,
+    src_type: AssemblyType,
+    dst_type: AssemblyType,
+    src: Operand,
+    dst: Operand,
+) -> Vec<Instruction> {
+    let mut instructions = vec![];
+    let src = if let Operand::Immediate(_) = src {
+        instructions.push(Instruction::Mov(src_type, src, R10));
+        R10
+    } else {
+        src
+    };
+    match dst {
+        Operand::Register(_) => instructions.push(make(src_type, dst_type, src, dst)),
+        _ => {
+            instructions.push(make(src_type, dst_type, src, R11));
+            instructions.push(Instruction::Mov(dst_type, R11, dst));
+        }
+    }
+    instructions
+}
@@ -0,0 +1,697 @@
+//! Translates the IR into x86_64 instructions operating on pseudo-registers. The IR's types come
+//! from the symbol table: they give each instruction's operand size, and whether comparisons,
+//! division, right shifts and extensions are signed.
+//!
+//! Floating constants are kept in read-only data, as SSE instructions take no immediates.
+//! Comparing a NaN sets the flags as 'unordered', for which 'above' and 'above or equal' are
+//! false but the others are not, so 'less than' is generated as 'greater than' with the operands
+//! swapped, and equality checks the parity flag too. XMM0 serves as a scratch register, as
+//! floating values are only ever in registers while being passed or returned.
+
+use common::data_model::DataModel;
+
+use crate::ast::{Constant, Type};
+use crate::codegen::x86_64::{
+    AssemblyType, BinaryOperator, ConditionCode, Function, Instruction, Operand, Program, Register,
+    StaticConstant, StaticVariable, SystemV, TopLevel, UnaryOperator,
+};
+use crate::codegen::calling_convention::{CallingConvention, Location};
+use crate::ir;
+use crate::semantic::symbol_table::{IdentifierAttributes, SymbolTable};
+
+struct Generator<'a> {
+    symbols: &'a SymbolTable,
+    data_model: &'a DataModel,
+    instructions: Vec<Instruction>,
+    /// The floating constants of the whole program.
+    constants: &'a mut Vec<StaticConstant>,
+    /// The number of labels generated so far in the program.
+    labels: &'a mut usize,
+}
+
+pub fn generate(program: &ir::Program, symbols: &SymbolTable, data_model: &DataModel) -> Program {
+    let mut constants = vec![];
+    let mut labels = 0;
+    let mut top_level: Vec<TopLevel> = program
+        .top_level
+        .iter()
+        .map(|top_level| match top_level {
+            ir::TopLevel::Function(function) => {
+                let mut generator = Generator {
+                    symbols,
+                    data_model,
+                    instructions: vec![],
+                    constants: &mut constants,
+                    labels: &mut labels,
+                };
+                TopLevel::Function(generator.function(function))
+            }
+            ir::TopLevel::StaticVariable(variable) => TopLevel::StaticVariable(StaticVariable {
+                name: variable.name.clone(),
+                global: variable.global,
+                assembly_type: AssemblyType::of(&variable.variable_type, data_model),
+                init: variable.init.value,
+            }),
+        })
+        .collect();
+    top_level.extend(constants.into_iter().map(TopLevel::StaticConstant));
+    Program { top_level }
+}
+
+
+impl Generator<'_> {
+    fn emit(&mut self, instruction: Instruction) {
+        self.instructions.push(instruction);
+    }
+
+    fn label(&mut self, purpose: &str) -> String {
+        *self.labels += 1;
+        ir::generated_name(purpose, *self.labels)
+    }
+
+    fn value_type(&self, value: &ir::Value) -> Type {
+        match value {
+            ir::Value::Constant(constant) => constant.constant_type.clone(),
+            ir::Value::Var(name) => self
+                .symbols
+                .get(name)
+                .expect("every variable is in the symbol table")
+                .symbol_type
+                .clone(),
+        }
+    }
+
+    fn assembly_type(&self, value: &ir::Value) -> AssemblyType {
+        AssemblyType::of(&self.value_type(value), self.data_model)
+    }
+
+    fn is_signed(&self, value: &ir::Value) -> bool {
+        self.value_type(value).is_signed(self.data_model)
+    }
+
+    fn operand(&mut self, value: &ir::Value) -> Operand {
+        match value {
+            ir::Value::Constant(constant) if constant.constant_type.is_floating() => {
+                let assembly_type = AssemblyType::of(&constant.constant_type, self.data_model);
+                self.constant(assembly_type, constant.value, assembly_type.size())
+            }
+            ir::Value::Constant(constant) => Operand::Immediate(constant.value),
+            ir::Value::Var(name) => Operand::Pseudo(name.clone()),
+        }
+    }
+
+    /// A floating constant with the given bits, pooled with any others like it.
+    fn constant(&mut self, assembly_type: AssemblyType, init: i128, alignment: i64) -> Operand {
+        let pooled = self.constants.iter().find(|constant| {
+            constant.assembly_type == assembly_type
+                && constant.init == init
+                && constant.alignment == alignment
+        });
+        let name = match pooled {
+            Some(constant) => constant.name.clone(),
+            None => {
+                let name = ir::generated_name("fpconst", self.constants.len() + 1);
+                self.constants.push(StaticConstant {
+                    name: name.clone(),
+                    alignment,
+                    assembly_type,
+                    init,
+                });
+                name
+            }
+        };
+        Operand::Data(name)
+    }
+
+    /// A floating constant of the given size, which must be exact in 'float' if it is one.
+    fn floating_constant(&mut self, assembly_type: AssemblyType, value: f64, alignment: i64) -> Operand {
+        let constant = match assembly_type {
+            AssemblyType::Single => Constant::float(value as f32),
+            _ => Constant::double(value),
+        };
+        self.constant(assembly_type, constant.value, alignment)
+    }
+
+    fn return_register(assembly_type: AssemblyType) -> Register {
+        if assembly_type.is_floating() {
+            SystemV::FLOATING_RETURN_REGISTER
+        } else {
+            SystemV::RETURN_REGISTER
+        }
+    }
+
+    /// The parameters arrive in registers, then on the stack above the return address and the
+    /// saved frame pointer. They are copied to their pseudo-registers.
+    fn function(&mut self, function: &ir::Function) -> Function {
+        let params: Vec<ir::Value> = function
+            .params
+            .iter()
+            .map(|param| ir::Value::Var(param.clone()))
+            .collect();
+        let assembly_types: Vec<AssemblyType> =
+            params.iter().map(|param| self.assembly_type(param)).collect();
+        let locations = SystemV::arguments(&assembly_types);
+        for ((param, assembly_type), location) in params.iter().zip(assembly_types).zip(locations) {
+            let src = match location {
+                Location::Register(register) => Operand::Register(register),
+                Location::Memory(slot) => Operand::Stack(16 + 8 * slot as i64),
+            };
+            let dst = self.operand(param);
+            self.emit(Instruction::Mov(assembly_type, src, dst));
+        }
+        for instruction in &function.body {
+            self.instruction(instruction);
+        }
+        Function {
+            name: function.name.clone(),
+            global: function.global,
+            instructions: std::mem::take(&mut self.instructions),
+            callee_saved: vec![],
+        }
+    }
+
+    fn instruction(&mut self, instruction: &ir::Instruction) {
+        match instruction {
+            ir::Instruction::Return(value) => {
+                if let Some(value) = value {
+                    let assembly_type = self.assembly_type(value);
+                    let src = self.operand(value);
+                    self.emit(Instruction::Mov(
+                        assembly_type,
+                        src,
+                        Operand::Register(Self::return_register(assembly_type)),
+                    ));
+                }
+                self.emit(Instruction::Ret);
+            }
+            ir::Instruction::SignExtend { src, dst } => {
+                let (src_type, dst_type) = (self.assembly_type(src), self.assembly_type(dst));
+                let (src, dst) = (self.operand(src), self.operand(dst));
+                self.emit(Instruction::Movsx(src_type, dst_type, src, dst));
+            }
+            ir::Instruction::ZeroExtend { src, dst } => {
+                let (src_type, dst_type) = (self.assembly_type(src), self.assembly_type(dst));
+                let (src, dst) = (self.operand(src), self.operand(dst));
+                self.emit(Instruction::MovZeroExtend(src_type, dst_type, src, dst));
+            }
+            // Reading the narrower operand from a wider one gives its low bits, as x86_64 is
+            // little endian. Constants are truncated here.
+            ir::Instruction::Truncate { src, dst } => {
+                let src = match src {
+                    ir::Value::Constant(constant) => ir::Value::Constant(
+                        constant.convert(&self.value_type(dst), self.data_model),
+                    ),
+                    ir::Value::Var(_) => src.clone(),
+                };
+                let assembly_type = self.assembly_type(dst);
+                let (src, dst) = (self.operand(&src), self.operand(dst));
+                self.emit(Instruction::Mov(assembly_type, src, dst));
+            }
+            ir::Instruction::IntToFloat { src, dst } => self.int_to_float(src, dst),
+            ir::Instruction::UIntToFloat { src, dst } => self.uint_to_float(src, dst),
+            ir::Instruction::FloatToInt { src, dst } => self.float_to_int(src, dst),
+            ir::Instruction::FloatToUInt { src, dst } => self.float_to_uint(src, dst),
+            ir::Instruction::FloatExtend { src, dst }
+            | ir::Instruction::FloatTruncate { src, dst } => {
+                let (src_type, dst_type) = (self.assembly_type(src), self.assembly_type(dst));
+                let (src, dst) = (self.operand(src), self.operand(dst));
+                self.emit(Instruction::FloatConvert(src_type, dst_type, src, dst));
+            }
+            ir::Instruction::Copy { src, dst } => {
+                let assembly_type = self.assembly_type(dst);
+                let (src, dst) = (self.operand(src), self.operand(dst));
+                self.emit(Instruction::Mov(assembly_type, src, dst));
+            }
+            ir::Instruction::Unary {
+                operator: ir::UnaryOperator::Not,
+                src,
+                dst,
+            } => {
+                let floating = self.compare_with_zero(src);
+                self.set_equality(ConditionCode::E, floating, dst);
+            }
+            ir::Instruction::Unary { operator, src, dst } => {
+                let assembly_type = self.assembly_type(src);
+                let (src, dst) = (self.operand(src), self.operand(dst));
+                self.emit(Instruction::Mov(assembly_type, src, dst.clone()));
+                match operator {
+                    // Flipping the sign bit negates zeros and NaNs too, which subtracting from
+                    // zero would not.
+                    ir::UnaryOperator::Negate if assembly_type.is_floating() => {
+                        let sign = self.floating_constant(assembly_type, -0.0, 16);
+                        self.emit(Instruction::Binary(
+                            BinaryOperator::Xor,
+                            assembly_type,
+                            sign,
+                            dst,
+                        ));
+                    }
+                    ir::UnaryOperator::Negate => {
+                        self.emit(Instruction::Unary(UnaryOperator::Neg, assembly_type, dst))
+                    }
+                    ir::UnaryOperator::Complement => {
+                        self.emit(Instruction::Unary(UnaryOperator::Not, assembly_type, dst))
+                    }
+                    ir::UnaryOperator::Not => unreachable!("handled above"),
+                }
+            }
+            ir::Instruction::Binary {
+                operator,
+                src1,
+                src2,
+                dst,
+            } => self.binary(*operator, src1, src2, dst),
+            ir::Instruction::Jump(label) => self.emit(Instruction::Jmp(label.clone())),
+            // A NaN is not zero, so the jump is not taken if the comparison is unordered.
+            ir::Instruction::JumpIfZero(condition, label) => {
+                if self.compare_with_zero(condition) {
+                    let unordered = self.label("unordered");
+                    self.emit(Instruction::JmpCC(ConditionCode::P, unordered.clone()));
+                    self.emit(Instruction::JmpCC(ConditionCode::E, label.clone()));
+                    self.emit(Instruction::Label(unordered));
+                } else {
+                    self.emit(Instruction::JmpCC(ConditionCode::E, label.clone()));
+                }
+            }
+            ir::Instruction::JumpIfNotZero(condition, label) => {
+                if self.compare_with_zero(condition) {
+                    self.emit(Instruction::JmpCC(ConditionCode::P, label.clone()));
+                }
+                self.emit(Instruction::JmpCC(ConditionCode::NE, label.clone()));
+            }
+            ir::Instruction::Label(label) => self.emit(Instruction::Label(label.clone())),
+            ir::Instruction::FunctionCall { name, args, dst } => {
+                self.function_call(name, args, dst.as_ref())
+            }
+        }
+    }
+
+    /// Compares a value with zero, giving whether it is floating.
+    fn compare_with_zero(&mut self, value: &ir::Value) -> bool {
+        let assembly_type = self.assembly_type(value);
+        let zero = if assembly_type.is_floating() {
+            self.constant(assembly_type, 0, assembly_type.size())
+        } else {
+            Operand::Immediate(0)
+        };
+        let value = self.operand(value);
+        self.emit(Instruction::Cmp(assembly_type, zero, value));
+        assembly_type.is_floating()
+    }
+
+    /// Sets an 'int' to one if the condition holds, and zero otherwise.
+    fn set_condition(&mut self, condition: ConditionCode, dst: &ir::Value) {
+        let assembly_type = self.assembly_type(dst);
+        let dst = self.operand(dst);
+        self.emit(Instruction::Mov(assembly_type, Operand::Immediate(0), dst.clone()));
+        self.emit(Instruction::SetCC(condition, dst));
+    }
+
+    /// Sets an 'int' to one if a comparison found its operands equal, or not equal. A NaN is
+    /// equal to nothing, so a floating comparison that is unordered gives one only for 'NE'.
+    fn set_equality(&mut self, condition: ConditionCode, floating: bool, dst: &ir::Value) {
+        if !floating {
+            return self.set_condition(condition, dst);
+        }
+        let assembly_type = self.assembly_type(dst);
+        let dst = self.operand(dst);
+        let unordered = self.label("unordered");
+        let not_equal = condition == ConditionCode::NE;
+        self.emit(Instruction::Mov(
+            assembly_type,
+            Operand::Immediate(not_equal as i128),
+            dst.clone(),
+        ));
+        self.emit(Instruction::JmpCC(ConditionCode::P, unordered.clone()));
+        if not_equal {
+            self.emit(Instruction::Mov(assembly_type, Operand::Immediate(0), dst.clone()));
+        }
+        self.emit(Instruction::SetCC(condition, dst));
+        self.emit(Instruction::Label(unordered));
+    }
+
+    fn binary(
+        &mut self,
+        operator: ir::BinaryOperator,
+        src1: &ir::Value,
+        src2: &ir::Value,
+        dst: &ir::Value,
+    ) {
+        let assembly_type = self.assembly_type(src1);
+        if assembly_type.is_floating() {
+            return self.floating_binary(operator, assembly_type, src1, src2, dst);
+        }
+        let signed = self.is_signed(src1);
+        let arithmetic = match operator {
+            ir::BinaryOperator::Add => Some(BinaryOperator::Add),
+            ir::BinaryOperator::Subtract => Some(BinaryOperator::Sub),
+            ir::BinaryOperator::Multiply => Some(BinaryOperator::Imul),
+            ir::BinaryOperator::BitwiseAnd => Some(BinaryOperator::And),
+            ir::BinaryOperator::BitwiseOr => Some(BinaryOperator::Or),
+            ir::BinaryOperator::BitwiseXor => Some(BinaryOperator::Xor),
+            _ => None,
+        };
+        if let Some(arithmetic) = arithmetic {
+            let (src1, src2, dst) = (self.operand(src1), self.operand(src2), self.operand(dst));
+            self.emit(Instruction::Mov(assembly_type, src1, dst.clone()));
+            self.emit(Instruction::Binary(arithmetic, assembly_type, src2, dst));
+            return;
+        }
+
+        match operator {
+            ir::BinaryOperator::Divide | ir::BinaryOperator::Remainder => {
+                let ax = Operand::Register(Register::AX);
+                let dx = Operand::Register(Register::DX);
+                let (src1, src2) = (self.operand(src1), self.operand(src2));
+                self.emit(Instruction::Mov(assembly_type, src1, ax.clone()));
+                if signed {
+                    self.emit(Instruction::Cdq(assembly_type));
+                    self.emit(Instruction::Idiv(assembly_type, src2));
+                } else {
+                    self.emit(Instruction::Mov(
+                        assembly_type,
+                        Operand::Immediate(0),
+                        dx.clone(),
+                    ));
+                    self.emit(Instruction::Div(assembly_type, src2));
+                }
+                let result = if operator == ir::BinaryOperator::Divide {
+                    ax
+                } else {
+                    dx
+                };
+                let dst = self.operand(dst);
+                self.emit(Instruction::Mov(assembly_type, result, dst));
+            }
+            ir::BinaryOperator::ShiftLeft | ir::BinaryOperator::ShiftRight => {
+                let shift = match (operator, signed) {
+                    (ir::BinaryOperator::ShiftLeft, _) => BinaryOperator::Sal,
+                    (_, true) => BinaryOperator::Sar,
+                    (_, false) => BinaryOperator::Shr,
+                };
+                let (src1, dst_operand) = (self.operand(src1), self.operand(dst));
+                self.emit(Instruction::Mov(assembly_type, src1, dst_operand.clone()));
+                // A variable count must be in CL. The processor masks the count to the width of
+                // the operand, so a constant is masked the same way.
+                let count = match src2 {
+                    ir::Value::Constant(Constant { value, .. }) => {
+                        Operand::Immediate(value & (assembly_type.size() as i128 * 8 - 1))
+                    }
+                    ir::Value::Var(_) => {
+                        let count_type = self.assembly_type(src2);
+                        let src2 = self.operand(src2);
+                        self.emit(Instruction::Mov(
+                            count_type,
+                            src2,
+                            Operand::Register(Register::CX),
+                        ));
+                        Operand::Register(Register::CX)
+                    }
+                };
+                self.emit(Instruction::Binary(shift, assembly_type, count, dst_operand));
+            }
+            _ => {
+                let condition = match (operator, signed) {
+                    (ir::BinaryOperator::Equal, _) => ConditionCode::E,
+                    (ir::BinaryOperator::NotEqual, _) => ConditionCode::NE,
+                    (ir::BinaryOperator::LessThan, true) => ConditionCode::L,
+                    (ir::BinaryOperator::LessThan, false) => ConditionCode::B,
+                    (ir::BinaryOperator::LessOrEqual, true) => ConditionCode::LE,
+                    (ir::BinaryOperator::LessOrEqual, false) => ConditionCode::BE,
+                    (ir::BinaryOperator::GreaterThan, true) => ConditionCode::G,
+                    (ir::BinaryOperator::GreaterThan, false) => ConditionCode::A,
+                    (ir::BinaryOperator::GreaterOrEqual, true) => ConditionCode::GE,
+                    (ir::BinaryOperator::GreaterOrEqual, false) => ConditionCode::AE,
+                    _ => unreachable!("arithmetic operators are handled above"),
+                };
+                let (src1, src2) = (self.operand(src1), self.operand(src2));
+                self.emit(Instruction::Cmp(assembly_type, src2, src1));
+                self.set_condition(condition, dst);
+            }
+        }
+    }
+
+    fn floating_binary(
+        &mut self,
+        operator: ir::BinaryOperator,
+        assembly_type: AssemblyType,
+        src1: &ir::Value,
+        src2: &ir::Value,
+        dst: &ir::Value,
+    ) {
+        let arithmetic = match operator {
+            ir::BinaryOperator::Add => Some(BinaryOperator::Add),
+            ir::BinaryOperator::Subtract => Some(BinaryOperator::Sub),
+            ir::BinaryOperator::Multiply => Some(BinaryOperator::Imul),
+            ir::BinaryOperator::Divide => Some(BinaryOperator::DivFloating),
+            _ => None,
+        };
+        if let Some(arithmetic) = arithmetic {
+            let (src1, src2, dst) = (self.operand(src1), self.operand(src2), self.operand(dst));
+            self.emit(Instruction::Mov(assembly_type, src1, dst.clone()));
+            self.emit(Instruction::Binary(arithmetic, assembly_type, src2, dst));
+            return;
+        }
+
+        let (condition, left, right) = match operator {
+            ir::BinaryOperator::Equal => (ConditionCode::E, src1, src2),
+            ir::BinaryOperator::NotEqual => (ConditionCode::NE, src1, src2),
+            ir::BinaryOperator::GreaterThan => (ConditionCode::A, src1, src2),
+            ir::BinaryOperator::GreaterOrEqual => (ConditionCode::AE, src1, src2),
+            ir::BinaryOperator::LessThan => (ConditionCode::A, src2, src1),
+            ir::BinaryOperator::LessOrEqual => (ConditionCode::AE, src2, src1),
+            _ => unreachable!("the operands of '%', bitwise operators and shifts are integers"),
+        };
+        let (left, right) = (self.operand(left), self.operand(right));
+        self.emit(Instruction::Cmp(assembly_type, right, left));
+        match condition {
+            ConditionCode::E | ConditionCode::NE => self.set_equality(condition, true, dst),
+            _ => self.set_condition(condition, dst),
+        }
+    }
+
+    /// Integers narrower than 32 bits are sign extended first, as there is no conversion from
+    /// them.
+    fn int_to_float(&mut self, src: &ir::Value, dst: &ir::Value) {
+        let (src_type, dst_type) = (self.assembly_type(src), self.assembly_type(dst));
+        let (mut src, dst) = (self.operand(src), self.operand(dst));
+        let src_type = match src_type {
+            AssemblyType::Byte | AssemblyType::Word => {
+                let ax = Operand::Register(Register::AX);
+                self.emit(Instruction::Movsx(src_type, AssemblyType::Longword, src, ax.clone()));
+                src = ax;
+                AssemblyType::Longword
+            }
+            _ => src_type,
+        };
+        self.emit(Instruction::Cvtsi2sd(src_type, dst_type, src, dst));
+    }
+
+    /// Unsigned integers narrower than 64 bits are zero extended, and converted as signed ones.
+    /// Those of 64 bits with the top bit set are halved first and the result doubled, keeping the
+    /// lowest bit so that the halved value rounds the same way.
+    fn uint_to_float(&mut self, src: &ir::Value, dst: &ir::Value) {
+        let (src_type, dst_type) = (self.assembly_type(src), self.assembly_type(dst));
+        let (src, dst) = (self.operand(src), self.operand(dst));
+        let ax = Operand::Register(Register::AX);
+        let dx = Operand::Register(Register::DX);
+        if src_type != AssemblyType::Quadword {
+            self.emit(Instruction::MovZeroExtend(
+                src_type,
+                AssemblyType::Quadword,
+                src,
+                ax.clone(),
+            ));
+            self.emit(Instruction::Cvtsi2sd(AssemblyType::Quadword, dst_type, ax, dst));
+            return;
+        }
+        let (large, end) = (self.label("convert_large"), self.label("convert_end"));
+        self.emit(Instruction::Cmp(
+            AssemblyType::Quadword,
+            Operand::Immediate(0),
+            src.clone(),
+        ));
+        self.emit(Instruction::JmpCC(ConditionCode::L, large.clone()));
+        self.emit(Instruction::Cvtsi2sd(
+            AssemblyType::Quadword,
+            dst_type,
+            src.clone(),
+            dst.clone(),
+        ));
+        self.emit(Instruction::Jmp(end.clone()));
+        self.emit(Instruction::Label(large));
+        self.emit(Instruction::Mov(AssemblyType::Quadword, src, ax.clone()));
+        self.emit(Instruction::Mov(AssemblyType::Quadword, ax.clone(), dx.clone()));
+        self.emit(Instruction::Binary(
+            BinaryOperator::Shr,
+            AssemblyType::Quadword,
+            Operand::Immediate(1),
+            dx.clone(),
+        ));
+        self.emit(Instruction::Binary(
+            BinaryOperator::And,
+            AssemblyType::Quadword,
+            Operand::Immediate(1),
+            ax.clone(),
+        ));
+        self.emit(Instruction::Binary(
+            BinaryOperator::Or,
+            AssemblyType::Quadword,
+            ax,
+            dx.clone(),
+        ));
+        self.emit(Instruction::Cvtsi2sd(AssemblyType::Quadword, dst_type, dx, dst.clone()));
+        self.emit(Instruction::Binary(BinaryOperator::Add, dst_type, dst.clone(), dst));
+        self.emit(Instruction::Label(end));
+    }
+
+    /// Integers narrower than 32 bits are converted to 32 bits, and their low bits kept.
+    fn float_to_int(&mut self, src: &ir::Value, dst: &ir::Value) {
+        let (src_type, dst_type) = (self.assembly_type(src), self.assembly_type(dst));
+        let (src, dst) = (self.operand(src), self.operand(dst));
+        match dst_type {
+            AssemblyType::Byte | AssemblyType::Word => {
+                let ax = Operand::Register(Register::AX);
+                self.emit(Instruction::Cvttsd2si(
+                    src_type,
+                    AssemblyType::Longword,
+                    src,
+                    ax.clone(),
+                ));
+                self.emit(Instruction::Mov(dst_type, ax, dst));
+            }
+            _ => self.emit(Instruction::Cvttsd2si(src_type, dst_type, src, dst)),
+        }
+    }
+
+    /// Unsigned integers narrower than 64 bits are converted to a wider signed integer, and
+    /// their low bits kept. Values of 2^63 and above are out of the signed range of 64 bits, so
+    /// 2^63 is subtracted before converting them, and added back after.
+    fn float_to_uint(&mut self, src: &ir::Value, dst: &ir::Value) {
+        let (src_type, dst_type) = (self.assembly_type(src), self.assembly_type(dst));
+        let (src, dst) = (self.operand(src), self.operand(dst));
+        let ax = Operand::Register(Register::AX);
+        let converted_type = match dst_type {
+            AssemblyType::Byte | AssemblyType::Word => AssemblyType::Longword,
+            AssemblyType::Longword => AssemblyType::Quadword,
+            _ => {
+                let (large, end) = (self.label("convert_large"), self.label("convert_end"));
+                let limit = self.floating_constant(src_type, 9223372036854775808.0, src_type.size());
+                let xmm0 = Operand::Register(Register::XMM0);
+                self.emit(Instruction::Cmp(src_type, limit.clone(), src.clone()));
+                self.emit(Instruction::JmpCC(ConditionCode::AE, large.clone()));
+                self.emit(Instruction::Cvttsd2si(
+                    src_type,
+                    AssemblyType::Quadword,
+                    src.clone(),
+                    dst.clone(),
+                ));
+                self.emit(Instruction::Jmp(end.clone()));
+                self.emit(Instruction::Label(large));
+                self.emit(Instruction::Mov(src_type, src, xmm0.clone()));
+                self.emit(Instruction::Binary(BinaryOperator::Sub, src_type, limit, xmm0.clone()));
+                self.emit(Instruction::Cvttsd2si(
+                    src_type,
+                    AssemblyType::Quadword,
+                    xmm0,
+                    dst.clone(),
+                ));
+                self.emit(Instruction::Binary(
+                    BinaryOperator::Add,
+                    AssemblyType::Quadword,
+                    Operand::Immediate(1 << 63),
+                    dst,
+                ));
+                self.emit(Instruction::Label(end));
+                return;
+            }
+        };
+        self.emit(Instruction::Cvttsd2si(src_type, converted_type, src, ax.clone()));
+        self.emit(Instruction::Mov(dst_type, ax, dst));
+    }
+
+    /// Moves a value into a register for passing as an argument. Arguments narrower than 32 bits
+    /// are extended to 32 bits, as gcc and clang expect. A floating argument passed on the stack
+    /// is moved into AX as its bits.
+    fn argument_into(&mut self, arg: &ir::Value, register: Register) {
+        let assembly_type = self.assembly_type(arg);
+        let signed = self.is_signed(arg);
+        let (src, dst) = (self.operand(arg), Operand::Register(register));
+        let instruction = match assembly_type {
+            AssemblyType::Byte | AssemblyType::Word if signed => {
+                Instruction::Movsx(assembly_type, AssemblyType::Longword, src, dst)
+            }
+            AssemblyType::Byte | AssemblyType::Word => {
+                Instruction::MovZeroExtend(assembly_type, AssemblyType::Longword, src, dst)
+            }
+            AssemblyType::Single | AssemblyType::Double
+                if !SystemV::FLOATING_ARGUMENT_REGISTERS.contains(&register) =>
+            {
+                let bits = match assembly_type {
+                    AssemblyType::Single => AssemblyType::Longword,
+                    _ => AssemblyType::Quadword,
+                };
+                Instruction::Mov(bits, src, dst)
+            }
+            _ => Instruction::Mov(assembly_type, src, dst),
+        };
+        self.emit(instruction);
+    }
+
+    /// The stack must be 16-byte aligned at the call, so it is padded if an odd number of
+    /// arguments are pushed. Each takes eight bytes; the last is pushed first.
+    fn function_call(&mut self, name: &str, args: &[ir::Value], dst: Option<&ir::Value>) {
+        let assembly_types: Vec<AssemblyType> =
+            args.iter().map(|arg| self.assembly_type(arg)).collect();
+        let locations = SystemV::arguments(&assembly_types);
+        let stack_args: Vec<&ir::Value> = args
+            .iter()
+            .zip(&locations)
+            .filter(|(_, location)| matches!(location, Location::Memory(_)))
+            .map(|(arg, _)| arg)
+            .collect();
+        let padding = if stack_args.len() % 2 == 1 { 8 } else { 0 };
+        if padding != 0 {
+            self.emit(Instruction::Binary(
+                BinaryOperator::Sub,
+                AssemblyType::Quadword,
+                Operand::Immediate(padding),
+                Operand::Register(Register::SP),
+            ));
+        }
+        for arg in stack_args.iter().rev() {
+            self.argument_into(arg, Register::AX);
+            self.emit(Instruction::Push(Operand::Register(Register::AX)));
+        }
+        for (arg, location) in args.iter().zip(&locations) {
+            if let Location::Register(register) = location {
+                self.argument_into(arg, *register);
+            }
+        }
+
+        let defined = matches!(
+            self.symbols.get(name).map(|symbol| &symbol.attributes),
+            Some(IdentifierAttributes::Function { defined: true, .. })
+        );
+        self.emit(Instruction::Call(name.to_owned(), defined));
+
+        let deallocate = 8 * stack_args.len() as i128 + padding;
+        if deallocate != 0 {
+            self.emit(Instruction::Binary(
+                BinaryOperator::Add,
+                AssemblyType::Quadword,
+                Operand::Immediate(deallocate),
+                Operand::Register(Register::SP),
+            ));
+        }
+        if let Some(dst) = dst {
+            let assembly_type = self.assembly_type(dst);
+            let dst = self.operand(dst);
+            self.emit(Instruction::Mov(
+                assembly_type,
+                Operand::Register(Self::return_register(assembly_type)),
+                dst,
+            ));
+        }
+    }
+}
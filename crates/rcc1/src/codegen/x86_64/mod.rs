@@ -0,0 +1,322 @@
+//! The x86_64 back end, generating AT&T syntax for the GNU assembler, following the System V ABI,
+//! so that programs can be assembled and linked with gcc and run natively.
+//!
+//! As in the book, this is done in stages over an assembly AST:
+//!
+//! * generation translates each IR instruction into instructions whose operands may be
+//!   pseudo-registers, one for each IR variable;
+//! * when optimising, the register allocator gives pseudo-registers hardware registers where it
+//!   can;
+//! * the remaining pseudo-registers are replaced by stack slots or, for static variables, by
+//!   RIP-relative data references, and each function's frame is allocated;
+//! * instructions are fixed up where their operands are not valid for x86_64, e.g. both in memory;
+//! * finally the program is written out as text.
+//!
+//! Floating values are operated on with the scalar SSE2 instructions, which every x86_64 processor
+//! has. They are never given registers by the allocator, only passed and returned in them.
+
+pub mod emission;
+pub mod fixup;
+pub mod generation;
+pub mod register_allocation;
+pub mod stack_frame;
+
+use common::data_model::DataModel;
+
+use crate::ast::Type;
+use crate::codegen::calling_convention::{CallingConvention, Location};
+use crate::ir;
+use crate::semantic::symbol_table::SymbolTable;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub top_level: Vec<TopLevel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TopLevel {
+    Function(Function),
+    StaticVariable(StaticVariable),
+    StaticConstant(StaticConstant),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub name: String,
+    pub global: bool,
+    pub instructions: Vec<Instruction>,
+    /// The callee-saved registers the function uses, which it must save and restore.
+    pub callee_saved: Vec<Register>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticVariable {
+    pub name: String,
+    pub global: bool,
+    pub assembly_type: AssemblyType,
+    /// The initial value, within the range of the variable's C type.
+    pub init: i128,
+}
+
+/// A floating constant, which SSE instructions can only read from memory. Each is kept once, in
+/// read-only data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticConstant {
+    pub name: String,
+    /// The alignment in bytes, which is 16 for the operands of 'xorps' and 'xorpd'. The constant
+    /// is padded with zeros to fill it.
+    pub alignment: i64,
+    pub assembly_type: AssemblyType,
+    /// The IEEE 754 bits of the value.
+    pub init: i128,
+}
+
+/// The size of an operand, which gives the instruction suffix and the register names. 'float' and
+/// 'double' are 'Single' and 'Double', which are operated on in the SSE registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AssemblyType {
+    Byte,
+    Word,
+    Longword,
+    Quadword,
+    Single,
+    Double,
+}
+
+impl AssemblyType {
+    /// The operand size of an arithmetic type.
+    pub fn of(c_type: &Type, data_model: &DataModel) -> AssemblyType {
+        match (c_type.is_floating(), c_type.bits(data_model)) {
+            (false, 8) => AssemblyType::Byte,
+            (false, 16) => AssemblyType::Word,
+            (false, 32) => AssemblyType::Longword,
+            (false, 64) => AssemblyType::Quadword,
+            (true, 32) => AssemblyType::Single,
+            (true, 64) => AssemblyType::Double,
+            (_, bits) => unreachable!("no x86_64 operand size of {} bits", bits),
+        }
+    }
+
+    pub fn size(&self) -> i64 {
+        match self {
+            AssemblyType::Byte => 1,
+            AssemblyType::Word => 2,
+            AssemblyType::Longword | AssemblyType::Single => 4,
+            AssemblyType::Quadword | AssemblyType::Double => 8,
+        }
+    }
+
+    pub fn is_floating(&self) -> bool {
+        matches!(self, AssemblyType::Single | AssemblyType::Double)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Register {
+    AX,
+    BX,
+    CX,
+    DX,
+    DI,
+    SI,
+    R8,
+    R9,
+    R10,
+    R11,
+    R12,
+    R13,
+    R14,
+    R15,
+    SP,
+    BP,
+    XMM0,
+    XMM1,
+    XMM2,
+    XMM3,
+    XMM4,
+    XMM5,
+    XMM6,
+    XMM7,
+    XMM14,
+    XMM15,
+}
+
+/// The System V ABI passes the first six integer arguments in registers, and the rest on the
+/// stack, eight bytes each, the first lowest. The result is returned in AX.
+///
+/// Floating arguments are passed in XMM0 to XMM7 instead, and a floating result returned in XMM0;
+/// each class of argument takes the next register of its own, so 'SystemV::arguments' gives where
+/// the arguments of a call with floating ones go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SystemV;
+
+impl SystemV {
+    pub const FLOATING_ARGUMENT_REGISTERS: &'static [Register] = &[
+        Register::XMM0,
+        Register::XMM1,
+        Register::XMM2,
+        Register::XMM3,
+        Register::XMM4,
+        Register::XMM5,
+        Register::XMM6,
+        Register::XMM7,
+    ];
+
+    pub const FLOATING_RETURN_REGISTER: Register = Register::XMM0;
+
+    /// Where each argument is passed, given the arguments' operand sizes.
+    pub fn arguments(assembly_types: &[AssemblyType]) -> Vec<Location<Register>> {
+        let (mut integers, mut floats, mut slots) = (0, 0, 0);
+        assembly_types
+            .iter()
+            .map(|assembly_type| {
+                let (registers, used) = if assembly_type.is_floating() {
+                    (Self::FLOATING_ARGUMENT_REGISTERS, &mut floats)
+                } else {
+                    (Self::ARGUMENT_REGISTERS, &mut integers)
+                };
+                match registers.get(*used) {
+                    Some(register) => {
+                        *used += 1;
+                        Location::Register(*register)
+                    }
+                    None => {
+                        slots += 1;
+                        Location::Memory(slots - 1)
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+impl CallingConvention for SystemV {
+    type Register = Register;
+
+    const ARGUMENT_REGISTERS: &'static [Register] = &[
+        Register::DI,
+        Register::SI,
+        Register::DX,
+        Register::CX,
+        Register::R8,
+        Register::R9,
+    ];
+
+    const RETURN_REGISTER: Register = Register::AX;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+    Immediate(i128),
+    Register(Register),
+    /// An IR variable, not yet assigned a location.
+    Pseudo(String),
+    /// An offset from the frame pointer.
+    Stack(i64),
+    /// A static variable, addressed relative to the instruction pointer.
+    Data(String),
+}
+
+impl Operand {
+    pub fn is_memory(&self) -> bool {
+        matches!(self, Operand::Stack(_) | Operand::Data(_))
+    }
+
+    /// Can this be encoded as the 32-bit immediate of a 64-bit instruction?
+    pub fn is_large_immediate(&self) -> bool {
+        matches!(self, Operand::Immediate(value) if i32::try_from(*value).is_err())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConditionCode {
+    E,
+    NE,
+    L,
+    LE,
+    G,
+    GE,
+    /// The unsigned comparisons: below, below or equal, above, above or equal.
+    B,
+    BE,
+    A,
+    AE,
+    /// Parity, which a floating comparison sets when either operand is a NaN.
+    P,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOperator {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Add,
+    Sub,
+    Imul,
+    And,
+    Or,
+    Xor,
+    Sal,
+    Sar,
+    Shr,
+    /// Floating division; integers are divided by 'Idiv' and 'Div'.
+    DivFloating,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    Mov(AssemblyType, Operand, Operand),
+    /// Sign extends from the first type to the second.
+    Movsx(AssemblyType, AssemblyType, Operand, Operand),
+    /// Zero extends from the first type to the second.
+    MovZeroExtend(AssemblyType, AssemblyType, Operand, Operand),
+    Unary(UnaryOperator, AssemblyType, Operand),
+    Binary(BinaryOperator, AssemblyType, Operand, Operand),
+    /// A floating comparison sets the flags as an unsigned one does, and parity too if it is
+    /// unordered.
+    Cmp(AssemblyType, Operand, Operand),
+    Idiv(AssemblyType, Operand),
+    Div(AssemblyType, Operand),
+    /// Sign extends the accumulator into DX, before a signed division.
+    Cdq(AssemblyType),
+    /// Converts a signed integer of the first type to the floating second type.
+    Cvtsi2sd(AssemblyType, AssemblyType, Operand, Operand),
+    /// Converts a value of the floating first type to a signed integer of the second type,
+    /// truncating towards zero.
+    Cvttsd2si(AssemblyType, AssemblyType, Operand, Operand),
+    /// Converts between 'float' and 'double'.
+    FloatConvert(AssemblyType, AssemblyType, Operand, Operand),
+    Jmp(String),
+    JmpCC(ConditionCode, String),
+    SetCC(ConditionCode, Operand),
+    Label(String),
+    Push(Operand),
+    Pop(Register),
+    /// The flag says whether the function is defined in this module, or must be called through
+    /// the procedure linkage table.
+    Call(String, bool),
+    Ret,
+}
+
+/// Generates the assembly language text for a program, allocating registers if asked to.
+pub fn assembly(
+    program: &ir::Program,
+    symbols: &SymbolTable,
+    data_model: &DataModel,
+    allocate_registers: bool,
+) -> String {
+    let mut program = generation::generate(program, symbols, data_model);
+    if allocate_registers {
+        register_allocation::allocate(&mut program, symbols, data_model);
+    }
+    stack_frame::allocate(&mut program, symbols, data_model);
+    fixup::fix_instructions(&mut program);
+    program.to_string()
+}
+
+#[cfg(test)]
+#[path = "./x86_64_spec.rs"]
+mod x86_64_spec;
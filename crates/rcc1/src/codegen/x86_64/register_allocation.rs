@@ -0,0 +1,472 @@
+//! Gives pseudo-registers hardware registers, by colouring an interference graph with iterated
+//! register coalescing, following the book's chapter on register allocation:
+//!
+//! * liveness analysis finds the pseudo-registers and hardware registers live after each
+//!   instruction;
+//! * those defined by an instruction interfere with those live after it, except that the
+//!   destination of a move does not interfere with its source, so they may share a register;
+//! * moves between registers that do not interfere are coalesced, where the Briggs or George test
+//!   shows that colouring the graph is no harder for it, and the graph rebuilt until there are no
+//!   more;
+//! * the graph is then coloured by simplifying and selecting, optimistically. A pseudo-register
+//!   that cannot be coloured is spilled, and left for the stack frame.
+//!
+//! R10 and R11 are kept for fixing up instructions, and SP and BP for the frame, so twelve
+//! registers are allocated. The caller-saved ones are preferred, as the callee-saved ones must be
+//! saved by the function; a pseudo-register live across a call interferes with every
+//! caller-saved register, as the call may change them, so gets a callee-saved one.
+//!
+//! Floating pseudo-registers are not allocated, and are left for the stack frame too.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use common::data_model::DataModel;
+
+use crate::ast::Type;
+use crate::codegen::calling_convention::{CallingConvention, Location};
+use crate::codegen::x86_64::{
+    AssemblyType, Function, Instruction, Operand, Program, Register, SystemV, TopLevel,
+};
+use crate::semantic::symbol_table::{IdentifierAttributes, SymbolTable};
+
+/// The registers to allocate, in order of preference.
+const ALLOCATABLE: &[Register] = &[
+    Register::AX,
+    Register::CX,
+    Register::DX,
+    Register::DI,
+    Register::SI,
+    Register::R8,
+    Register::R9,
+    Register::BX,
+    Register::R12,
+    Register::R13,
+    Register::R14,
+    Register::R15,
+];
+
+const CALLER_SAVED: &[Register] = &[
+    Register::AX,
+    Register::CX,
+    Register::DX,
+    Register::DI,
+    Register::SI,
+    Register::R8,
+    Register::R9,
+];
+
+/// The number of colours.
+const K: usize = ALLOCATABLE.len();
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Node {
+    Register(Register),
+    Pseudo(String),
+}
+
+type Graph = BTreeMap<Node, BTreeSet<Node>>;
+
+pub fn allocate(program: &mut Program, symbols: &SymbolTable, data_model: &DataModel) {
+    for top_level in &mut program.top_level {
+        if let TopLevel::Function(function) = top_level {
+            Allocator { symbols, data_model }.function(function);
+        }
+    }
+}
+
+struct Allocator<'a> {
+    symbols: &'a SymbolTable,
+    data_model: &'a DataModel,
+}
+
+impl Allocator<'_> {
+    fn function(&self, function: &mut Function) {
+        while self.coalesce(&mut function.instructions) {}
+
+        let graph = self.interference(&function.instructions);
+        let colours = self.colour(&graph, &function.instructions);
+        replace(&mut function.instructions, |name| {
+            colours.get(name).map(|register| Operand::Register(*register))
+        });
+        function.callee_saved = ALLOCATABLE
+            .iter()
+            .filter(|register| !CALLER_SAVED.contains(register))
+            .filter(|register| colours.values().any(|colour| colour == *register))
+            .copied()
+            .collect();
+    }
+
+    /// The graph node for an operand, if it is a register that is allocated or a pseudo-register
+    /// that is neither a static variable nor floating.
+    fn node(&self, operand: &Operand) -> Option<Node> {
+        match operand {
+            Operand::Register(register) if ALLOCATABLE.contains(register) => {
+                Some(Node::Register(*register))
+            }
+            Operand::Pseudo(name) => {
+                let symbol = self.symbols.get(name).expect("every variable is in the symbol table");
+                match symbol.attributes {
+                    IdentifierAttributes::Static { .. } => None,
+                    _ if symbol.symbol_type.is_floating() => None,
+                    _ => Some(Node::Pseudo(name.clone())),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// The nodes an instruction reads, and those it writes.
+    fn uses_and_defs(&self, instruction: &Instruction) -> (Vec<Node>, Vec<Node>) {
+        let nodes = |operands: &[&Operand]| -> Vec<Node> {
+            operands.iter().filter_map(|operand| self.node(operand)).collect()
+        };
+        let registers = |registers: &[Register]| -> Vec<Node> {
+            registers.iter().map(|register| Node::Register(*register)).collect()
+        };
+        match instruction {
+            Instruction::Mov(_, src, dst)
+            | Instruction::Movsx(_, _, src, dst)
+            | Instruction::MovZeroExtend(_, _, src, dst)
+            | Instruction::Cvtsi2sd(_, _, src, dst)
+            | Instruction::Cvttsd2si(_, _, src, dst)
+            | Instruction::FloatConvert(_, _, src, dst) => (nodes(&[src]), nodes(&[dst])),
+            Instruction::Unary(_, _, operand) => (nodes(&[operand]), nodes(&[operand])),
+            Instruction::Binary(_, _, src, dst) => (nodes(&[src, dst]), nodes(&[dst])),
+            Instruction::Cmp(_, src, dst) => (nodes(&[src, dst]), vec![]),
+            Instruction::Idiv(_, operand) | Instruction::Div(_, operand) => {
+                let mut uses = nodes(&[operand]);
+                uses.extend(registers(&[Register::AX, Register::DX]));
+                (uses, registers(&[Register::AX, Register::DX]))
+            }
+            Instruction::Cdq(_) => (registers(&[Register::AX]), registers(&[Register::DX])),
+            // 'set' writes only the low byte, so the rest of the operand is kept.
+            Instruction::SetCC(_, operand) => (nodes(&[operand]), nodes(&[operand])),
+            Instruction::Push(operand) => (nodes(&[operand]), vec![]),
+            Instruction::Pop(register) => (vec![], nodes(&[&Operand::Register(*register)])),
+            Instruction::Call(name, _) => {
+                let params: Vec<AssemblyType> =
+                    match self.symbols.get(name).map(|symbol| &symbol.symbol_type) {
+                        Some(Type::Function { params, .. }) => params
+                            .iter()
+                            .map(|param| AssemblyType::of(param, self.data_model))
+                            .collect(),
+                        _ => vec![],
+                    };
+                let in_registers: Vec<Register> = SystemV::arguments(&params)
+                    .into_iter()
+                    .filter_map(|location| match location {
+                        Location::Register(register) if ALLOCATABLE.contains(&register) => {
+                            Some(register)
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                (registers(&in_registers), registers(CALLER_SAVED))
+            }
+            Instruction::Ret => (registers(&[SystemV::RETURN_REGISTER]), vec![]),
+            Instruction::Jmp(_) | Instruction::JmpCC(_, _) | Instruction::Label(_) => {
+                (vec![], vec![])
+            }
+        }
+    }
+
+    /// The nodes live after each instruction.
+    fn liveness(&self, instructions: &[Instruction]) -> Vec<BTreeSet<Node>> {
+        let labels: HashMap<&str, usize> = instructions
+            .iter()
+            .enumerate()
+            .filter_map(|(index, instruction)| match instruction {
+                Instruction::Label(label) => Some((label.as_str(), index)),
+                _ => None,
+            })
+            .collect();
+        let successors: Vec<Vec<usize>> = instructions
+            .iter()
+            .enumerate()
+            .map(|(index, instruction)| match instruction {
+                Instruction::Jmp(label) => vec![labels[label.as_str()]],
+                Instruction::JmpCC(_, label) => vec![index + 1, labels[label.as_str()]],
+                Instruction::Ret => vec![],
+                _ => vec![index + 1],
+            })
+            .map(|successors| successors.into_iter().filter(|s| *s < instructions.len()).collect())
+            .collect();
+        let uses_and_defs: Vec<(Vec<Node>, Vec<Node>)> = instructions
+            .iter()
+            .map(|instruction| self.uses_and_defs(instruction))
+            .collect();
+
+        let mut live_in: Vec<BTreeSet<Node>> = vec![BTreeSet::new(); instructions.len()];
+        let mut live_out: Vec<BTreeSet<Node>> = vec![BTreeSet::new(); instructions.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for index in (0..instructions.len()).rev() {
+                let out: BTreeSet<Node> = successors[index]
+                    .iter()
+                    .flat_map(|successor| live_in[*successor].iter().cloned())
+                    .collect();
+                let (uses, defs) = &uses_and_defs[index];
+                let mut live: BTreeSet<Node> =
+                    out.iter().filter(|node| !defs.contains(node)).cloned().collect();
+                live.extend(uses.iter().cloned());
+                if live != live_in[index] || out != live_out[index] {
+                    changed = true;
+                    live_in[index] = live;
+                    live_out[index] = out;
+                }
+            }
+        }
+        live_out
+    }
+
+    fn interference(&self, instructions: &[Instruction]) -> Graph {
+        let mut graph = Graph::new();
+        for register in ALLOCATABLE {
+            let others = ALLOCATABLE
+                .iter()
+                .filter(|other| *other != register)
+                .map(|other| Node::Register(*other))
+                .collect();
+            graph.insert(Node::Register(*register), others);
+        }
+        let live_out = self.liveness(instructions);
+        for (instruction, live) in instructions.iter().zip(live_out) {
+            let (uses, defs) = self.uses_and_defs(instruction);
+            for node in uses.iter().chain(defs.iter()) {
+                graph.entry(node.clone()).or_default();
+            }
+            let moved = match instruction {
+                Instruction::Mov(_, src, _) => self.node(src),
+                _ => None,
+            };
+            for def in &defs {
+                for node in &live {
+                    if node != def && Some(node) != moved.as_ref() {
+                        add_edge(&mut graph, def, node);
+                    }
+                }
+            }
+        }
+        graph
+    }
+
+    fn assembly_type(&self, name: &str) -> AssemblyType {
+        let symbol = self.symbols.get(name).expect("every variable is in the symbol table");
+        AssemblyType::of(&symbol.symbol_type, self.data_model)
+    }
+
+    /// Coalesces the moves that can be, rewriting the instructions; gives whether any were.
+    fn coalesce(&self, instructions: &mut Vec<Instruction>) -> bool {
+        let mut graph = self.interference(instructions);
+        let mut merged: BTreeMap<String, Node> = BTreeMap::new();
+        let find = |merged: &BTreeMap<String, Node>, node: Node| -> Node {
+            let mut node = node;
+            while let Node::Pseudo(name) = &node {
+                match merged.get(name) {
+                    Some(into) => node = into.clone(),
+                    None => break,
+                }
+            }
+            node
+        };
+        for instruction in instructions.iter() {
+            let Instruction::Mov(_, src, dst) = instruction else {
+                continue;
+            };
+            let (Some(src), Some(dst)) = (self.node(src), self.node(dst)) else {
+                continue;
+            };
+            let (src, dst) = (find(&merged, src), find(&merged, dst));
+            if src == dst || graph[&src].contains(&dst) {
+                continue;
+            }
+            // A hardware register absorbs a pseudo-register; of two pseudo-registers, the
+            // source is kept. Pseudo-registers are only merged if they are the same size, so
+            // that a spilled one's slot is large enough for all its uses.
+            let (keep, remove) = match (&src, &dst) {
+                (Node::Register(_), Node::Register(_)) => continue,
+                (Node::Register(_), Node::Pseudo(_)) => (src, dst),
+                (Node::Pseudo(_), Node::Register(_)) => (dst, src),
+                (Node::Pseudo(a), Node::Pseudo(b)) => {
+                    if self.assembly_type(a) != self.assembly_type(b) {
+                        continue;
+                    }
+                    (src, dst)
+                }
+            };
+            let conservative = match &keep {
+                Node::Register(_) => george(&graph, &keep, &remove),
+                Node::Pseudo(_) => briggs(&graph, &keep, &remove),
+            };
+            if !conservative {
+                continue;
+            }
+            let neighbours = graph.remove(&remove).unwrap_or_default();
+            for neighbour in neighbours {
+                if let Some(edges) = graph.get_mut(&neighbour) {
+                    edges.remove(&remove);
+                }
+                add_edge(&mut graph, &keep, &neighbour);
+            }
+            let Node::Pseudo(name) = remove else {
+                unreachable!("only pseudo-registers are removed");
+            };
+            merged.insert(name, keep);
+        }
+        if merged.is_empty() {
+            return false;
+        }
+        replace(instructions, |name| {
+            match find(&merged, Node::Pseudo(name.to_owned())) {
+                Node::Register(register) => Some(Operand::Register(register)),
+                Node::Pseudo(into) if into != name => Some(Operand::Pseudo(into)),
+                Node::Pseudo(_) => None,
+            }
+        });
+        true
+    }
+
+    /// Colours the pseudo-registers, giving the registers of those that are not spilled.
+    fn colour(&self, graph: &Graph, instructions: &[Instruction]) -> HashMap<String, Register> {
+        let mut costs: HashMap<String, usize> = HashMap::new();
+        for instruction in instructions {
+            let (uses, defs) = self.uses_and_defs(instruction);
+            for node in uses.into_iter().chain(defs) {
+                if let Node::Pseudo(name) = node {
+                    *costs.entry(name).or_default() += 1;
+                }
+            }
+        }
+
+        // Simplify, removing a node that can certainly be coloured if there is one, and otherwise
+        // the cheapest to spill for its degree, optimistically.
+        let mut remaining = graph.clone();
+        let mut stack = vec![];
+        loop {
+            let pseudos = remaining.keys().filter_map(|node| match node {
+                Node::Pseudo(name) => Some(name.clone()),
+                Node::Register(_) => None,
+            });
+            let degree = |name: &String| remaining[&Node::Pseudo(name.clone())].len();
+            let candidates: Vec<String> = pseudos.collect();
+            if candidates.is_empty() {
+                break;
+            }
+            let chosen = match candidates.iter().find(|name| degree(name) < K) {
+                Some(name) => name.clone(),
+                None => candidates
+                    .iter()
+                    .min_by(|a, b| {
+                        let a_cost = costs[*a] as f64 / degree(a) as f64;
+                        let b_cost = costs[*b] as f64 / degree(b) as f64;
+                        a_cost.total_cmp(&b_cost)
+                    })
+                    .expect("there are candidates")
+                    .clone(),
+            };
+            let node = Node::Pseudo(chosen.clone());
+            for neighbour in remaining.remove(&node).unwrap_or_default() {
+                if let Some(edges) = remaining.get_mut(&neighbour) {
+                    edges.remove(&node);
+                }
+            }
+            stack.push(chosen);
+        }
+
+        let mut colours: HashMap<String, Register> = HashMap::new();
+        while let Some(name) = stack.pop() {
+            let taken: Vec<Register> = graph[&Node::Pseudo(name.clone())]
+                .iter()
+                .filter_map(|neighbour| match neighbour {
+                    Node::Register(register) => Some(*register),
+                    Node::Pseudo(other) => colours.get(other).copied(),
+                })
+                .collect();
+            if let Some(register) = ALLOCATABLE.iter().find(|register| !taken.contains(register)) {
+                colours.insert(name, *register);
+            }
+        }
+        colours
+    }
+}
+
+fn add_edge(graph: &mut Graph, a: &Node, b: &Node) {
+    graph.entry(a.clone()).or_default().insert(b.clone());
+    graph.entry(b.clone()).or_default().insert(a.clone());
+}
+
+/// Briggs: the merged node has fewer than K neighbours of significant degree.
+fn briggs(graph: &Graph, a: &Node, b: &Node) -> bool {
+    let neighbours: BTreeSet<&Node> = graph[a].iter().chain(graph[b].iter()).collect();
+    let significant = neighbours
+        .into_iter()
+        .filter(|neighbour| {
+            // Hardware registers cannot be simplified away, so are always significant.
+            if let Node::Register(_) = neighbour {
+                return true;
+            }
+            let mut degree = graph[*neighbour].len();
+            // A neighbour of both loses one edge in the merge.
+            if graph[a].contains(*neighbour) && graph[b].contains(*neighbour) {
+                degree -= 1;
+            }
+            degree >= K
+        })
+        .count();
+    significant < K
+}
+
+/// George: each neighbour of the pseudo-register already interferes with the hardware register,
+/// or has insignificant degree.
+fn george(graph: &Graph, register: &Node, pseudo: &Node) -> bool {
+    graph[pseudo]
+        .iter()
+        .all(|neighbour| graph[register].contains(neighbour) || graph[neighbour].len() < K)
+}
+
+/// Replaces pseudo-registers as the function gives, then removes the moves of a register to
+/// itself.
+fn replace(instructions: &mut Vec<Instruction>, by: impl Fn(&str) -> Option<Operand>) {
+    let replace = |operand: &mut Operand| {
+        if let Operand::Pseudo(name) = operand {
+            if let Some(replacement) = by(name) {
+                *operand = replacement;
+            }
+        }
+    };
+    for instruction in instructions.iter_mut() {
+        match instruction {
+            Instruction::Mov(_, src, dst)
+            | Instruction::Movsx(_, _, src, dst)
+            | Instruction::MovZeroExtend(_, _, src, dst)
+            | Instruction::Binary(_, _, src, dst)
+            | Instruction::Cmp(_, src, dst)
+            | Instruction::Cvtsi2sd(_, _, src, dst)
+            | Instruction::Cvttsd2si(_, _, src, dst)
+            | Instruction::FloatConvert(_, _, src, dst) => {
+                replace(src);
+                replace(dst);
+            }
+            Instruction::Unary(_, _, operand)
+            | Instruction::Idiv(_, operand)
+            | Instruction::Div(_, operand)
+            | Instruction::SetCC(_, operand)
+            | Instruction::Push(operand) => replace(operand),
+            Instruction::Cdq(_)
+            | Instruction::Jmp(_)
+            | Instruction::JmpCC(_, _)
+            | Instruction::Label(_)
+            | Instruction::Pop(_)
+            | Instruction::Call(_, _)
+            | Instruction::Ret => {}
+        }
+    }
+    instructions.retain(|instruction| {
+        !matches!(instruction, Instruction::Mov(_, src, dst) if src == dst && matches!(src, Operand::Register(_)))
+    });
+}
+
+#[cfg(test)]
+#[path = "./register_allocation_spec.rs"]
+mod register_allocation_spec;
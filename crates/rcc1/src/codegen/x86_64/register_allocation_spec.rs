@@ -0,0 +1,108 @@
+mod register_allocation_spec {
+    use common::target_platform::TargetPlatform;
+    use hamcrest2::prelude::*;
+
+    use crate::codegen::x86_64::{generation, register_allocation, Instruction, Operand, Register, TopLevel};
+    use crate::codegen::x86_64::assembly;
+    use crate::program_test_helper::ir_of;
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    /// The instructions of the program's first function, after allocation.
+    fn allocated(input: &str) -> (Vec<Instruction>, Vec<Register>) {
+        let (ir, symbols) = ir_of(input, TargetPlatform::X86_64);
+        let data_model = TargetPlatform::X86_64.data_model();
+        let mut program = generation::generate(&ir, &symbols, &data_model);
+        register_allocation::allocate(&mut program, &symbols, &data_model);
+        match program.top_level.remove(0) {
+            TopLevel::Function(function) => (function.instructions, function.callee_saved),
+            TopLevel::StaticVariable(_) | TopLevel::StaticConstant(_) => unreachable!(),
+        }
+    }
+
+    fn pseudos(instructions: &[Instruction]) -> usize {
+        instructions
+            .iter()
+            .filter(|instruction| format!("{:?}", instruction).contains("Pseudo"))
+            .count()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parameters_are_used_in_the_registers_they_arrive_in() {
+        let (ir, symbols) = ir_of("int add(int a, int b) { return a + b; }", TargetPlatform::X86_64);
+        assert_that!(
+            assembly(&ir, &symbols, &TargetPlatform::X86_64.data_model(), true),
+            eq("\t.globl add
+\t.text
+add:
+\tpushq %rbp
+\tmovq %rsp, %rbp
+\taddl %esi, %edi
+\tmovl %edi, %eax
+\tmovq %rbp, %rsp
+\tpopq %rbp
+\tret
+\tmovl $0, %eax
+\tmovq %rbp, %rsp
+\tpopq %rbp
+\tret
+\t.section .note.GNU-stack,\"\",@progbits
+"
+            .to_owned())
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn values_live_across_calls_are_in_callee_saved_registers() {
+        let (ir, symbols) = ir_of("int g(int x); int main(void) { int k = 3; int r = g(k); return r + k; }", TargetPlatform::X86_64);
+        let assembly = assembly(&ir, &symbols, &TargetPlatform::X86_64.data_model(), true);
+        // The register is saved below an eight byte pad, which keeps the stack aligned.
+        assert_that!(
+            assembly.contains("\tsubq $8, %rsp\n\tpushq %rbx\n\tmovl $3, %ebx\n\tmovl %ebx, %edi\n\tcall g@PLT\n"),
+            eq(true)
+        );
+        assert_that!(assembly.contains("\taddl %ebx, %eax\n\tpopq %rbx\n\tmovq %rbp, %rsp\n"), eq(true));
+    }
+
+    #[test]
+    fn moves_between_non_interfering_values_are_coalesced() {
+        let (instructions, callee_saved) =
+            allocated("int f(int a) { int b = a; int c = b; int d = c; return d; }");
+        assert_that!(pseudos(&instructions), eq(0));
+        assert_that!(callee_saved.is_empty(), eq(true));
+        // Everything is coalesced into the argument and return registers.
+        assert_that!(
+            instructions.iter().take_while(|instruction| **instruction != Instruction::Ret).count(),
+            eq(1)
+        );
+    }
+
+    #[test]
+    fn divisions_do_not_clobber_live_values() {
+        let (instructions, _) = allocated("int f(int a, int b, int c) { return a / b + c % b; }");
+        for instruction in &instructions {
+            if let Instruction::Idiv(_, divisor) = instruction {
+                assert_that!(divisor, not(eq(&Operand::Register(Register::AX))));
+                assert_that!(divisor, not(eq(&Operand::Register(Register::DX))));
+            }
+        }
+    }
+
+    #[test]
+    fn values_are_spilled_when_there_are_too_few_registers() {
+        let (instructions, callee_saved) = allocated(
+            "int id(int x);
+int main(void) { int a = id(1), b = id(2), c = id(3), d = id(4), e = id(5), f = id(6), g = id(7), h = id(8);
+    int i = id(9), j = id(10), k = id(11), l = id(12), m = id(13), n = id(14), o = id(15);
+    return a + b + c + d + e + f + g + h + i + j + k + l + m + n + o; }",
+        );
+        // Only the five callee-saved registers survive the calls, so the rest are spilled.
+        assert_that!(callee_saved.len(), eq(5));
+        assert_that!(pseudos(&instructions) > 0, eq(true));
+    }
+}
@@ -0,0 +1,113 @@
+//! Replaces pseudo-registers with their locations: static variables are addressed relative to the
+//! instruction pointer, and everything else is given a slot in the function's stack frame, below
+//! the saved frame pointer. Each slot is aligned to its size. The frame is then allocated at the
+//! start of the function, and the callee-saved registers the function uses pushed below it, with
+//! the frame rounded up so the stack stays 16-byte aligned. They are popped before each return.
+
+use std::collections::HashMap;
+
+use common::data_model::DataModel;
+
+use crate::codegen::x86_64::{
+    AssemblyType, BinaryOperator, Instruction, Operand, Program, Register, TopLevel,
+};
+use crate::semantic::symbol_table::{IdentifierAttributes, SymbolTable};
+
+struct Frame<'a> {
+    symbols: &'a SymbolTable,
+    data_model: &'a DataModel,
+    slots: HashMap<String, i64>,
+    /// The size of the frame so far, in bytes.
+    size: i64,
+}
+
+pub fn allocate(program: &mut Program, symbols: &SymbolTable, data_model: &DataModel) {
+    for top_level in &mut program.top_level {
+        if let TopLevel::Function(function) = top_level {
+            let mut frame = Frame {
+                symbols,
+                data_model,
+                slots: HashMap::new(),
+                size: 0,
+            };
+            for instruction in &mut function.instructions {
+                frame.instruction(instruction);
+            }
+            let saved = 8 * function.callee_saved.len() as i64;
+            let size = (frame.size + saved + 15) / 16 * 16 - saved;
+            let mut prologue = vec![];
+            if size != 0 {
+                prologue.push(Instruction::Binary(
+                    BinaryOperator::Sub,
+                    AssemblyType::Quadword,
+                    Operand::Immediate(size as i128),
+                    Operand::Register(Register::SP),
+                ));
+            }
+            for register in &function.callee_saved {
+                prologue.push(Instruction::Push(Operand::Register(*register)));
+            }
+            let instructions = std::mem::take(&mut function.instructions);
+            function.instructions = prologue;
+            for instruction in instructions {
+                if instruction == Instruction::Ret {
+                    for register in function.callee_saved.iter().rev() {
+                        function.instructions.push(Instruction::Pop(*register));
+                    }
+                }
+                function.instructions.push(instruction);
+            }
+        }
+    }
+}
+
+impl Frame<'_> {
+    fn operand(&mut self, operand: &mut Operand) {
+        if let Operand::Pseudo(name) = operand {
+            let symbol = self
+                .symbols
+                .get(name)
+                .expect("every variable is in the symbol table");
+            *operand = match symbol.attributes {
+                IdentifierAttributes::Static { .. } => Operand::Data(name.clone()),
+                _ => match self.slots.get(name) {
+                    Some(offset) => Operand::Stack(*offset),
+                    None => {
+                        let size = AssemblyType::of(&symbol.symbol_type, self.data_model).size();
+                        self.size = (self.size + size + size - 1) / size * size;
+                        self.slots.insert(name.clone(), -self.size);
+                        Operand::Stack(-self.size)
+                    }
+                },
+            };
+        }
+    }
+
+    fn instruction(&mut self, instruction: &mut Instruction) {
+        match instruction {
+            Instruction::Mov(_, src, dst)
+            | Instruction::Movsx(_, _, src, dst)
+            | Instruction::MovZeroExtend(_, _, src, dst)
+            | Instruction::Binary(_, _, src, dst)
+            | Instruction::Cmp(_, src, dst)
+            | Instruction::Cvtsi2sd(_, _, src, dst)
+            | Instruction::Cvttsd2si(_, _, src, dst)
+            | Instruction::FloatConvert(_, _, src, dst) => {
+                self.operand(src);
+                self.operand(dst);
+            }
+            Instruction::Unary(_, _, operand)
+            | Instruction::Idiv(_, operand)
+            | Instruction::Div(_, operand)
+            | Instruction::SetCC(_, operand)
+            | Instruction::Push(operand) => self.operand(operand),
+            Instruction::Cdq(_)
+            | Instruction::Jmp(_)
+            | Instruction::JmpCC(_, _)
+            | Instruction::Label(_)
+            | Instruction::Pop(_)
+            | Instruction::Call(_, _)
+            | Instruction::Ret => {}
+        }
+    }
+}
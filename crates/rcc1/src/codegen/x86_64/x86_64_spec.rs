@@ -0,0 +1,349 @@
+mod x86_64_spec {
+    use std::fs::File;
+    use std::io::Write;
+    use std::process::Command;
+
+    use common::target_platform::TargetPlatform;
+    use common_test::file_utils_test_helper::temp_config_dir;
+    use hamcrest2::prelude::*;
+    use log::warn;
+
+    use crate::codegen::x86_64::fixup::fix_instructions;
+    use crate::codegen::x86_64::{
+        assembly, AssemblyType, BinaryOperator, Function, Instruction, Operand, Program, Register,
+        TopLevel,
+    };
+    use crate::ir::run;
+    use crate::program_test_helper::ir_of;
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    /// The assembly for a program, and the exit code the IR interpreter gives for it.
+    fn compiled(input: &str) -> (String, Option<i128>) {
+        compiled_with(input, false)
+    }
+
+    fn compiled_with(input: &str, allocate_registers: bool) -> (String, Option<i128>) {
+        let (ir, symbols) = ir_of(input, TargetPlatform::X86_64);
+        let data_model = TargetPlatform::X86_64.data_model();
+        (
+            assembly(&ir, &symbols, &data_model, allocate_registers),
+            run(&ir, &symbols, &data_model).ok(),
+        )
+    }
+
+    fn fixed(instructions: Vec<Instruction>) -> Vec<Instruction> {
+        let mut program = Program {
+            top_level: vec![TopLevel::Function(Function {
+                name: "f".to_owned(),
+                global: true,
+                instructions,
+                callee_saved: vec![],
+            })],
+        };
+        fix_instructions(&mut program);
+        match program.top_level.remove(0) {
+            TopLevel::Function(function) => function.instructions,
+            TopLevel::StaticVariable(_) | TopLevel::StaticConstant(_) => unreachable!(),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn listing_1_1() {
+        let (assembly, _) = compiled(include_str!("../../listing_1_1.c"));
+        assert_that!(
+            assembly,
+            eq("\t.globl main
+\t.text
+main:
+\tpushq %rbp
+\tmovq %rsp, %rbp
+\tmovl $2, %eax
+\tmovq %rbp, %rsp
+\tpopq %rbp
+\tret
+\tmovl $0, %eax
+\tmovq %rbp, %rsp
+\tpopq %rbp
+\tret
+\t.section .note.GNU-stack,\"\",@progbits
+"
+            .to_owned())
+        );
+    }
+
+    #[test]
+    fn static_functions_are_not_global() {
+        let (assembly, _) =
+            compiled("static int helper(int a) { return a; } int main(void) { return helper(1); }");
+        assert_that!(assembly.contains("\t.text\nhelper:\n"), eq(true));
+        assert_that!(assembly.contains("\t.globl helper"), eq(false));
+        assert_that!(assembly.contains("\t.globl main\n"), eq(true));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn static_variables_and_calls_to_other_modules() {
+        let (assembly, _) = compiled(
+            "int putchar(int c); static long count = 3; char flag; int main(void) { flag = 1; return putchar(count); }",
+        );
+        assert_that!(
+            assembly.contains("\t.data\n\t.balign 8\ncount:\n\t.quad 3\n"),
+            eq(true)
+        );
+        assert_that!(
+            assembly.contains("\t.globl flag\n\t.bss\n\t.balign 1\nflag:\n\t.zero 1\n"),
+            eq(true)
+        );
+        assert_that!(assembly.contains("\tmovb $1, flag(%rip)\n"), eq(true));
+        assert_that!(assembly.contains("\tcall putchar@PLT\n"), eq(true));
+    }
+
+    #[test]
+    fn memory_to_memory_moves_go_through_a_register() {
+        assert_that!(
+            fixed(vec![Instruction::Mov(
+                AssemblyType::Longword,
+                Operand::Stack(-4),
+                Operand::Stack(-8)
+            )]),
+            eq(vec![
+                Instruction::Mov(
+                    AssemblyType::Longword,
+                    Operand::Stack(-4),
+                    Operand::Register(Register::R10)
+                ),
+                Instruction::Mov(
+                    AssemblyType::Longword,
+                    Operand::Register(Register::R10),
+                    Operand::Stack(-8)
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn large_64_bit_immediates_go_through_a_register() {
+        let large = Operand::Immediate(1 << 40);
+        assert_that!(
+            fixed(vec![Instruction::Binary(
+                BinaryOperator::Add,
+                AssemblyType::Quadword,
+                large.clone(),
+                Operand::Stack(-8)
+            )]),
+            eq(vec![
+                Instruction::Mov(
+                    AssemblyType::Quadword,
+                    large.clone(),
+                    Operand::Register(Register::R10)
+                ),
+                Instruction::Binary(
+                    BinaryOperator::Add,
+                    AssemblyType::Quadword,
+                    Operand::Register(Register::R10),
+                    Operand::Stack(-8)
+                ),
+            ])
+        );
+        // A 32-bit instruction can take any 32-bit immediate.
+        let unsigned = Instruction::Binary(
+            BinaryOperator::Add,
+            AssemblyType::Longword,
+            Operand::Immediate(4000000000),
+            Operand::Stack(-4),
+        );
+        assert_that!(fixed(vec![unsigned.clone()]), eq(vec![unsigned.clone()]));
+    }
+
+    #[test]
+    fn multiplication_into_memory_goes_through_a_register() {
+        assert_that!(
+            fixed(vec![Instruction::Binary(
+                BinaryOperator::Imul,
+                AssemblyType::Longword,
+                Operand::Immediate(3),
+                Operand::Stack(-4)
+            )]),
+            eq(vec![
+                Instruction::Mov(
+                    AssemblyType::Longword,
+                    Operand::Stack(-4),
+                    Operand::Register(Register::R11)
+                ),
+                Instruction::Binary(
+                    BinaryOperator::Imul,
+                    AssemblyType::Longword,
+                    Operand::Immediate(3),
+                    Operand::Register(Register::R11)
+                ),
+                Instruction::Mov(
+                    AssemblyType::Longword,
+                    Operand::Register(Register::R11),
+                    Operand::Stack(-4)
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn zero_extending_32_bits_is_a_32_bit_move() {
+        assert_that!(
+            fixed(vec![Instruction::MovZeroExtend(
+                AssemblyType::Longword,
+                AssemblyType::Quadword,
+                Operand::Stack(-4),
+                Operand::Stack(-16)
+            )]),
+            eq(vec![
+                Instruction::Mov(
+                    AssemblyType::Longword,
+                    Operand::Stack(-4),
+                    Operand::Register(Register::R11)
+                ),
+                Instruction::Mov(
+                    AssemblyType::Quadword,
+                    Operand::Register(Register::R11),
+                    Operand::Stack(-16)
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn floating_arithmetic_into_memory_goes_through_a_register() {
+        assert_that!(
+            fixed(vec![Instruction::Binary(
+                BinaryOperator::Add,
+                AssemblyType::Double,
+                Operand::Data("fpconst..1".to_owned()),
+                Operand::Stack(-8)
+            )]),
+            eq(vec![
+                Instruction::Mov(
+                    AssemblyType::Double,
+                    Operand::Stack(-8),
+                    Operand::Register(Register::XMM15)
+                ),
+                Instruction::Binary(
+                    BinaryOperator::Add,
+                    AssemblyType::Double,
+                    Operand::Data("fpconst..1".to_owned()),
+                    Operand::Register(Register::XMM15)
+                ),
+                Instruction::Mov(
+                    AssemblyType::Double,
+                    Operand::Register(Register::XMM15),
+                    Operand::Stack(-8)
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn floating_constants_are_pooled_in_read_only_data() {
+        let (assembly, _) =
+            compiled("double d = 2.5; int main(void) { double x = d * 1.5; return (x + 1.5) > 0; }");
+        assert_that!(assembly.matches("\t.quad 4609434218613702656\n").count(), eq(1));
+        assert_that!(assembly.contains("\t.data\n\t.balign 8\nd:\n\t.quad 4612811918334230528\n"), eq(true));
+    }
+
+    /// Programs that are assembled and linked with gcc and run, checking that their exit codes
+    /// agree with the IR interpreter's.
+    const NATIVE_PROGRAMS: &[&str] = &[
+        include_str!("../../listing_1_1.c"),
+        "int main(void) { int a = 7, b = -3; return (a * b + a / b - a % b) & 255; }",
+        "int main(void) { unsigned int u = 4000000000u; return u / 3u % 256 + (u > 5u) + (u >> 31); }",
+        "int main(void) { unsigned long big = 0ul - 7; long l = -9000000000L; return (big % 100 == 4) + (l / 7 < 0) * 2 + (int) (big >> 60); }",
+        "int main(void) { char c = -1; unsigned char uc = c; short s = uc; unsigned short us = -s; return (s - 200) + (us > 65000); }",
+        "int main(void) { int x = 10; x <<= 3; x |= 5; x &= ~1; x -= 7; x /= 2; x %= 100; x ^= 3; return x >> 1; }",
+        "int main(void) { int count = 3; long shifted = 1L << count * 10; return (int) (shifted >> 25) + (-16 >> count == -2); }",
+        "int eight(int a, int b, int c, int d, int e, int f, char g, long h) { return a - b + c - d + e - f + g - (int) h; }
+int main(void) { return eight(10, 1, 20, 2, 30, 3, -4, 5L) + eight(1, 1, 1, 1, 1, 1, 1, 1); }",
+        "int seven(int a, int b, int c, int d, int e, int f, int g) { return g * 10 + a; }
+int main(void) { return seven(1, 2, 3, 4, 5, 6, 7); }",
+        "int fib(int n) { return n < 2 ? n : fib(n - 1) + fib(n - 2); } int main(void) { return fib(13) % 256; }",
+        "int counter = 5;
+static long total;
+int bump(short by) { static int calls; calls++; total += by; return calls; }
+int main(void) { for (int i = 0; i < 10; i++) { bump(i); } return (int) total + bump(-1) + counter; }",
+        "int classify(unsigned char c) {
+    switch (c) { case 90 + 7: return 1; case 200: return 2; default: return 3; case 0: break; }
+    return 4;
+}
+int main(void) { return classify(97) * 100 + classify(200) * 10 + classify(0) + (classify(1) == 3); }",
+        "int main(void) { int i = 0, odd = 0; do { if (i % 2) { odd++; continue; } if (i > 20) break; } while (++i < 100); return odd + !i + (i && odd) * 100; }",
+        "int main(void) { long a = 2147483647L; int b = a + 1 > a; unsigned long c = -1; return b + (c > 0) * 2 + (c == 18446744073709551615ul) * 4; }",
+        "int id(int x) { return x; }
+int main(void) { int a = id(1), b = id(2), c = id(3), d = id(4), e = id(5), f = id(6), g = id(7), h = id(8);
+    int i = id(9), j = id(10), k = id(11), l = id(12), m = id(13), n = id(14), o = id(15);
+    return a * b + c * d - e + f * g - h + i * j - k + l * m - n + o + a * o - b * n + c * m; }",
+        "long mix(long a, int b, short c, char d, unsigned char e, long f, int g, long h) {
+    long t = a / b + c % d;
+    unsigned long u = (unsigned long) f << (e & 7);
+    return t - (long) (u >> 3) + g * h - (a > f ? e : d);
+}
+int main(void) { int sum = 0; for (int i = 1; i < 20; i++) { sum += (int) mix(1000L * i, i, -300, -7, 200, -i, 3, 9L); } return sum & 255; }",
+        "double half(double d) { return d / 2; }
+int main(void) { double x = 7.0; float f = 2.5f; x = half(x) + f * 3 - 0.25; return (int) (x * 10) % 256; }",
+        "int main(void) { double zero = 0.0; double nan = zero / zero;
+    return (nan == nan) + (nan != nan) * 2 + (nan < 1.0) * 4 + (nan >= 1.0) * 8 + !nan * 16 + (nan ? 32 : 0) + (1.5 <= 1.5) * 64 + (-zero == zero) * 128; }",
+        "int main(void) { unsigned long big = 18446744073709551615ul; double d = big; unsigned long back = d / 2;
+    unsigned int u = 4000000000u; float f = u; char c = -3; double dc = c; unsigned char uc = 250; float fuc = uc;
+    unsigned long large = 1e19; unsigned int mid = 3e9; short s = -2.9; unsigned char small = 200.7;
+    return (back == 9223372036854775808ul) + (f > 3.9e9f) * 2 + (dc == -3.0) * 4 + (fuc == 250.0f) * 8
+        + (mid == 3000000000u) * 16 + (large == 10000000000000000000ul) * 32 + (s == -2) * 64 + (small == 200) * 128; }",
+        "double mixed(int a, double b, float c, long d, double e, double f, double g, double h, double i, double j, double k, int l, float m) {
+    return a + b + c + d + e + f + g + h + i + j + k + l + m;
+}
+int main(void) { return (int) mixed(1, 2.0, 3.0f, 4L, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 11.0, 12, 13.5f); }",
+        "static float total = 1.5f; double scale = -2.0;
+float third(double t) { return t / 3; }
+int main(void) { for (int i = 0; i < 4; i++) { total += -scale * i; } float back = third(total); return (int) (back * 10) + (int) -scale + (total > 13.0f && scale) * 100; }",
+    ];
+
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    #[test]
+    fn native_exit_codes_agree_with_the_interpreter() {
+        run_natively(false);
+    }
+
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    #[test]
+    fn native_exit_codes_agree_with_the_interpreter_when_registers_are_allocated() {
+        run_natively(true);
+    }
+
+    fn run_natively(allocate_registers: bool) {
+        let (temp, _temp_dir) = temp_config_dir();
+        for (index, program) in NATIVE_PROGRAMS.iter().enumerate() {
+            let (assembly, interpreted) = compiled_with(program, allocate_registers);
+            let s_file = temp.join(format!("program{}.s", index));
+            let executable = temp.join(format!("program{}", index));
+            File::create(&s_file)
+                .unwrap()
+                .write_all(assembly.as_bytes())
+                .unwrap();
+            match Command::new("gcc")
+                .arg("-o")
+                .arg(&executable)
+                .arg(&s_file)
+                .status()
+            {
+                Ok(status) if status.success() => {}
+                Ok(status) => panic!("gcc failed with {} on\n{}", status, assembly),
+                Err(e) => {
+                    warn!("Skipping native runs, as gcc could not be run: {}", e);
+                    return;
+                }
+            }
+            let actual = Command::new(&executable).status().unwrap().code().unwrap() as i128;
+            let expected = interpreted.expect("the program runs in the interpreter") & 0xff;
+            assert_that!((index, actual), eq((index, expected)));
+        }
+    }
+}
@@ -2,6 +2,8 @@ use std::{ffi::OsString, path::Path};
 
 use anyhow::{bail, Result};
 use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use common::assembler_syntax::AssemblerSyntax;
+use common::processor::Processor;
 use common::target_platform::TargetPlatform;
 
 use crate::compiler::CompilerOptions;
@@ -40,7 +42,13 @@ where
             Arg::new("codegen")
                 .short('c')
                 .long("codegen")
-                .help("Run the lexer, parser, and assembly generation, but stop before code generation")
+                .help("Run the lexer, parser, IR and assembly generation, but stop before writing any output")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("dump-ir")
+                .long("dump-ir")
+                .help("Print the intermediate representation (TACKY) to standard output")
                 .action(ArgAction::SetTrue),
         )
         .arg(
@@ -50,16 +58,43 @@ where
                 .help("Choose the target archtiecture")
                 .value_parser(value_parser!(TargetPlatform)),
         )
+        .arg(
+            Arg::new("optimisation")
+                .short('O')
+                .help("The optimisation level: 0 places every variable on the stack, 1 allocates registers")
+                .value_parser(value_parser!(u8).range(0..=1))
+                .default_value("0"),
+        )
+        .arg(
+            Arg::new("syntax")
+                .long("syntax")
+                .help("Choose the syntax of the assembler for EPOC16")
+                .value_parser(value_parser!(AssemblerSyntax)),
+        )
+        .arg(
+            Arg::new("cpu")
+                .long("cpu")
+                .help("Choose the Transputer, and so whether floating point uses its FPU")
+                .value_parser(value_parser!(Processor)),
+        )
         .arg(
             Arg::new("output")
                 .short('o')
                 .long("output")
-                .help("The path (absolute or relative) of the output assembler file (.asm)")
+                .help("The path (absolute or relative) of the output assembler file (.s for X86_64, otherwise .asm), or for the Transputer, object file (.o)")
                 // Not making this required, as the test harness will want to run just the lex/parse/codegen without output.
         )
         .try_get_matches_from(itr)
 }
 
+/// The GNU assembler used for x86_64 expects '.s'; the Transputer and EPOC16 assemblers '.asm'.
+pub fn assembler_suffix(target_platform: TargetPlatform) -> &'static str {
+    match target_platform {
+        TargetPlatform::X86_64 => "s",
+        TargetPlatform::Transputer | TargetPlatform::EPOC16 => "asm",
+    }
+}
+
 pub fn validate_command_line(arguments: ArgMatches) -> Result<CompilerOptions> {
     match arguments.get_one::<String>("file") {
         Some(file) => {
@@ -68,26 +103,48 @@ pub fn validate_command_line(arguments: ArgMatches) -> Result<CompilerOptions> {
                 if !file_path.exists() {
                     bail!(format!("'{}' could not be found", file));
                 }
-                // There may be an output file.
-                let asm_file = match arguments.get_one::<String>("output") {
+                let target_platform = *arguments
+                    .get_one::<TargetPlatform>("arch")
+                    .unwrap_or(&TargetPlatform::Transputer);
+                // There may be an output file, with the suffix the target's assembler expects, or
+                // for the Transputer, an object for the linker.
+                let (asm_file, object_file) = match arguments.get_one::<String>("output") {
                     Some(o) => {
-                        if o.to_lowercase().ends_with(".asm") {
-                            Some(Box::new(Path::new(o).to_owned()))
+                        let suffix = assembler_suffix(target_platform);
+                        if o.to_lowercase().ends_with(&format!(".{}", suffix)) {
+                            (Some(Box::new(Path::new(o).to_owned())), None)
+                        } else if target_platform == TargetPlatform::Transputer && o.to_lowercase().ends_with(".o") {
+                            (None, Some(Box::new(Path::new(o).to_owned())))
                         } else {
-                            bail!("'{}' is not an assembler file (.asm)", o);
+                            bail!("'{}' is not an assembler file (.{})", o, suffix);
                         }
                     },
-                    None => None,
+                    None => (None, None),
+                };
+                // Only EPOC16 has a choice of assembler.
+                let assembler_syntax = match arguments.get_one::<AssemblerSyntax>("syntax") {
+                    Some(syntax) if target_platform == TargetPlatform::EPOC16 => *syntax,
+                    Some(_) => bail!("There is no choice of assembler syntax for {}", target_platform),
+                    None => AssemblerSyntax::default(),
+                };
+                // Only the Transputer has a choice of processor.
+                let processor = match arguments.get_one::<Processor>("cpu") {
+                    Some(processor) if target_platform == TargetPlatform::Transputer => *processor,
+                    Some(_) => bail!("There is no choice of CPU for {}", target_platform),
+                    None => Processor::default(),
                 };
                 Ok(CompilerOptions {
                     c_file: Box::new(file_path.to_owned()),
                     asm_file,
+                    object_file,
                     lex: arguments.get_flag("lex"),
                     parse: arguments.get_flag("parse"),
                     codegen: arguments.get_flag("codegen"),
-                    target_platform: *arguments
-                        .get_one::<TargetPlatform>("arch")
-                        .unwrap_or(&TargetPlatform::Transputer),
+                    dump_ir: arguments.get_flag("dump-ir"),
+                    target_platform,
+                    assembler_syntax,
+                    processor,
+                    optimisation_level: *arguments.get_one::<u8>("optimisation").expect("it has a default"),
                 })
             } else {
                 bail!("'{}' is not a preprocessed C filename (.i)", file)
@@ -5,6 +5,8 @@ mod command_line_spec {
 
     use std::{fs::File, path::PathBuf};
 
+    use common::assembler_syntax::AssemblerSyntax;
+    use common::processor::Processor;
     use common::target_platform::TargetPlatform;
     use common_test::file_utils_test_helper::temp_config_dir;
     use hamcrest2::prelude::*;
@@ -110,6 +112,79 @@ mod command_line_spec {
         assert_that!(result.asm_file, eq(Some(Box::new(PathBuf::from("OUTPUT.ASM".to_owned())))));
     }
 
+    #[test]
+    fn gas_file_given_for_x86_64() {
+        let (i_file, _temp_dir) = create_file();
+
+        let arg_vec = vec![
+            "rcc1",
+            i_file.to_str().unwrap(),
+            "-a",
+            "X86_64",
+            "-o",
+            "output.s",
+        ];
+        let result = validate_command_line(parse_command_line(arg_vec).unwrap())
+            .expect("Expected a valid command line");
+        assert_that!(
+            result.asm_file,
+            eq(Some(Box::new(PathBuf::from("output.s".to_owned()))))
+        );
+    }
+
+    #[test]
+    fn asm_file_given_for_x86_64() {
+        let (i_file, _temp_dir) = create_file();
+
+        let arg_vec = vec![
+            "rcc1",
+            i_file.to_str().unwrap(),
+            "-a",
+            "X86_64",
+            "-o",
+            "output.asm",
+        ];
+        let result = validate_command_line(parse_command_line(arg_vec).unwrap());
+        assert_that!(
+            result.unwrap_err().to_string(),
+            equal_to("'output.asm' is not an assembler file (.s)")
+        );
+    }
+
+    #[test]
+    fn gas_file_given_for_transputer() {
+        let (i_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc1", i_file.to_str().unwrap(), "-o", "output.s"];
+        let result = validate_command_line(parse_command_line(arg_vec).unwrap());
+        assert_that!(
+            result.unwrap_err().to_string(),
+            equal_to("'output.s' is not an assembler file (.asm)")
+        );
+    }
+
+    #[test]
+    fn object_file_given_for_transputer() {
+        let (i_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc1", i_file.to_str().unwrap(), "-o", "output.o"];
+        let result = validate_command_line(parse_command_line(arg_vec).unwrap()).expect("Expected a valid command line");
+        assert_that!(result.object_file, eq(Some(Box::new(PathBuf::from("output.o".to_owned())))));
+        assert_that!(result.asm_file, eq(None));
+    }
+
+    #[test]
+    fn object_file_given_for_epoc16() {
+        let (i_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc1", i_file.to_str().unwrap(), "-a", "EPOC16", "-o", "output.o"];
+        let result = validate_command_line(parse_command_line(arg_vec).unwrap());
+        assert_that!(
+            result.unwrap_err().to_string(),
+            equal_to("'output.o' is not an assembler file (.asm)")
+        );
+    }
+
     #[test]
     fn all_flags_off_by_default() {
         let (i_file, _temp_dir) = create_file();
@@ -119,6 +194,7 @@ mod command_line_spec {
         assert_that!(compiler_options.lex, equal_to(false));
         assert_that!(compiler_options.parse, equal_to(false));
         assert_that!(compiler_options.codegen, equal_to(false));
+        assert_that!(compiler_options.dump_ir, equal_to(false));
         assert_that!(compiler_options.target_platform, equal_to(TargetPlatform::Transputer));
     }
 
@@ -149,6 +225,15 @@ mod command_line_spec {
         assert_that!(compiler_options.codegen, equal_to(true));
     }
 
+    #[test]
+    fn dump_ir_flag() {
+        let (i_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc1", i_file.to_str().unwrap(), "--dump-ir"];
+        let compiler_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(compiler_options.dump_ir, equal_to(true));
+    }
+
     #[test]
     fn architecture_epoc16() {
         let (i_file, _temp_dir) = create_file();
@@ -167,6 +252,88 @@ mod command_line_spec {
         assert_that!(compiler_options.target_platform, equal_to(TargetPlatform::X86_64));
     }
 
+    #[test]
+    fn masm_syntax_by_default() {
+        let (i_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc1", i_file.to_str().unwrap(), "-a", "EPOC16"];
+        let compiler_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(compiler_options.assembler_syntax, equal_to(AssemblerSyntax::MASM));
+    }
+
+    #[test]
+    fn nasm_syntax_for_epoc16() {
+        let (i_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc1", i_file.to_str().unwrap(), "-a", "EPOC16", "--syntax", "NASM"];
+        let compiler_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(compiler_options.assembler_syntax, equal_to(AssemblerSyntax::NASM));
+    }
+
+    #[test]
+    fn syntax_given_for_transputer() {
+        let (i_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc1", i_file.to_str().unwrap(), "--syntax", "NASM"];
+        let result = validate_command_line(parse_command_line(arg_vec).unwrap());
+        assert_that!(result.is_err(), equal_to(true));
+        assert_that!(result.err().unwrap().to_string(), equal_to("There is no choice of assembler syntax for Transputer"));
+    }
+
+    #[test]
+    fn t425_by_default() {
+        let (i_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc1", i_file.to_str().unwrap()];
+        let compiler_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(compiler_options.processor, equal_to(Processor::T425));
+    }
+
+    #[test]
+    fn t800_for_transputer() {
+        let (i_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc1", i_file.to_str().unwrap(), "--cpu", "T800"];
+        let compiler_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(compiler_options.processor, equal_to(Processor::T800));
+    }
+
+    #[test]
+    fn cpu_given_for_epoc16() {
+        let (i_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc1", i_file.to_str().unwrap(), "-a", "EPOC16", "--cpu", "T805"];
+        let result = validate_command_line(parse_command_line(arg_vec).unwrap());
+        assert_that!(result.is_err(), equal_to(true));
+        assert_that!(result.err().unwrap().to_string(), equal_to("There is no choice of CPU for EPOC16"));
+    }
+
+    #[test]
+    fn optimisation_level_zero_by_default() {
+        let (i_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc1", i_file.to_str().unwrap()];
+        let compiler_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(compiler_options.optimisation_level, equal_to(0));
+    }
+
+    #[test]
+    fn optimisation_level_one() {
+        let (i_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc1", i_file.to_str().unwrap(), "-O1"];
+        let compiler_options = validate_command_line(parse_command_line(arg_vec).unwrap()).unwrap();
+        assert_that!(compiler_options.optimisation_level, equal_to(1));
+    }
+
+    #[test]
+    fn unknown_optimisation_level() {
+        let (i_file, _temp_dir) = create_file();
+
+        let arg_vec = vec!["rcc1", i_file.to_str().unwrap(), "-O3"];
+        assert_that!(parse_command_line(arg_vec).is_err(), equal_to(true));
+    }
+
     fn create_file() -> (PathBuf, TempDir) {
         let (temp, temp_dir) = temp_config_dir();
         let i_file = temp.join("HELLOWORLD.I");
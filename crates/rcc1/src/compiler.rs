@@ -3,20 +3,36 @@ use std::io::Read;
 use std::path::PathBuf;
 use chumsky::prelude::*;
 use log::{debug, error, info};
+use common::assembler_syntax::AssemblerSyntax;
+use common::processor::Processor;
 use common::target_platform::TargetPlatform;
 use sysexits::ExitCode;
+use crate::codegen;
+use crate::ir;
 use crate::lexer::lexer;
+use crate::parser::parser;
+use crate::semantic::analyse;
+use anyhow::{bail, Context};
 
 #[derive(Debug, Clone)]
 pub struct CompilerOptions {
     pub c_file: Box<PathBuf>,
     pub asm_file: Option<Box<PathBuf>>,
+    /// The Transputer back end can also write the program as an object for the linker.
+    pub object_file: Option<Box<PathBuf>>,
     pub lex: bool,
     pub parse: bool,
     pub codegen: bool,
+    pub dump_ir: bool,
     pub target_platform: TargetPlatform,
+    pub assembler_syntax: AssemblerSyntax,
+    /// The T800 and T805 evaluate floating point on their FPU, rather than in the soft-float runtime.
+    pub processor: Processor,
+    /// Registers are allocated from level 1.
+    pub optimisation_level: u8,
 }
 
+#[derive(Default)]
 pub struct Compiler {
 
 }
@@ -61,11 +77,96 @@ impl Compiler {
                 return Ok(ExitCode::DataErr);
             }
         }
-        //
-        // let (tokens, errs) = lexer.parse(&*input_buffer).into_output_errors();
-        // debug!("Lexed tokens: {:#?}", tokens);
-        // errs.into_iter().for_each(|e| error!("{:?}", e));
+        let lexical_analysis_start = std::time::Instant::now();
+        let (tokens, errs) = lexer.parse(&*input_buffer).into_output_errors();
+        let lexical_analysis_duration = lexical_analysis_start.elapsed();
+        debug!("Lexical analysis took {:?}μs", lexical_analysis_duration.as_micros());
+        let tokens = match tokens {
+            Some(tokens) if errs.is_empty() => tokens,
+            _ => {
+                error!("Lexical analysis unsuccessful");
+                errs.into_iter().for_each(|e| error!("{:?}", e));
+                return Ok(ExitCode::DataErr);
+            }
+        };
+        debug!("Lexed tokens: {:?}", tokens);
 
+        let parse_start = std::time::Instant::now();
+        let (program, errs) = parser().parse(tokens.as_slice()).into_output_errors();
+        let parse_duration = parse_start.elapsed();
+        debug!("Parsing took {:?}μs", parse_duration.as_micros());
+        let program = match program {
+            Some(program) if errs.is_empty() => program,
+            _ => {
+                error!("Parsing unsuccessful");
+                errs.into_iter().for_each(|e| error!("{:?}", e));
+                return Ok(ExitCode::DataErr);
+            }
+        };
+        debug!("AST: {:#?}", program);
+        if options.parse {
+            info!("Parsing successful");
+            return Ok(ExitCode::Ok);
+        }
+
+        let data_model = options.target_platform.data_model();
+        let (program, mut symbols) = match analyse(program, &data_model) {
+            Ok(analysed) => analysed,
+            Err(errs) => {
+                error!("Semantic analysis unsuccessful");
+                errs.into_iter().for_each(|e| error!("{}", e));
+                return Ok(ExitCode::DataErr);
+            }
+        };
+        debug!("Validated AST: {:#?}", program);
+        debug!("Symbol table: {:#?}", symbols);
+
+        let ir_generation_start = std::time::Instant::now();
+        let ir = ir::generate(&program, &mut symbols, &data_model);
+        let ir_generation_duration = ir_generation_start.elapsed();
+        debug!("IR generation took {:?}μs", ir_generation_duration.as_micros());
+        debug!("IR:\n{}", ir);
+        if options.dump_ir {
+            print!("{}", ir);
+        }
+
+        let ir = match options.target_platform {
+            TargetPlatform::X86_64 => ir,
+            TargetPlatform::Transputer if options.processor.has_floating_point_unit() => ir,
+            TargetPlatform::Transputer | TargetPlatform::EPOC16 => {
+                let lowered = ir::soft_float::lower(&ir, &mut symbols, &data_model);
+                debug!("Soft-float IR:\n{}", lowered);
+                lowered
+            }
+        };
+
+        let (assembly, object) = match options.target_platform {
+            TargetPlatform::X86_64 => (codegen::x86_64::assembly(&ir, &symbols, &data_model, options.optimisation_level > 0), None),
+            TargetPlatform::Transputer => {
+                let program = codegen::transputer::program(&ir, &symbols, &data_model);
+                (program.to_string(), Some(codegen::transputer::object::object(&program)))
+            }
+            TargetPlatform::EPOC16 => (codegen::epoc16::assembly(&ir, &symbols, &data_model, options.assembler_syntax, options.optimisation_level > 0), None),
+        };
+        debug!("Assembly:\n{}", assembly);
+        if options.codegen {
+            info!("Code generation successful");
+            return Ok(ExitCode::Ok);
+        }
+
+        if let Some(asm_file) = options.asm_file {
+            debug!("Writing {}", asm_file.display());
+            std::fs::write(asm_file.as_path(), assembly)
+                .with_context(|| format!("Could not write assembler file {}", asm_file.display()))?;
+        }
+        if let Some(object_file) = options.object_file {
+            let Some(object) = object else {
+                bail!("Objects cannot be written for {}", options.target_platform);
+            };
+            debug!("Writing {}", object_file.display());
+            std::fs::write(object_file.as_path(), object.to_bytes())
+                .with_context(|| format!("Could not write object file {}", object_file.display()))?;
+        }
         Ok(ExitCode::Ok)
     }
 }
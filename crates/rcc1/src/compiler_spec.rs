@@ -3,12 +3,15 @@ extern crate hamcrest2;
 #[cfg(test)]
 mod compiler_spec {
     use anyhow::{Error, Result};
+    use common::assembler_syntax::AssemblerSyntax;
+    use common::processor::Processor;
     use common::target_platform::TargetPlatform;
     use common_test::file_utils_test_helper::temp_config_dir;
     use hamcrest2::prelude::*;
     use std::io::Write;
     use std::fs::File;
     use sysexits::ExitCode;
+    use tlink::object::{Fragment, Object};
 
     use crate::compiler::{Compiler, CompilerOptions};
 
@@ -29,27 +32,305 @@ mod compiler_spec {
     #[test]
     fn just_lexer_test_fail() {
         // no preprocessor changes in this simple code, so .c not .i
-        let contents = "<<>>".as_bytes();
+        let contents = "int main(void) { return @; }".as_bytes();
         let out = lexer_test(contents);
         assert!(out.is_ok()); // an Err is a failure
         assert_that!(out.unwrap(), eq(ExitCode::DataErr));
     }
 
+    #[test]
+    fn just_parser_test_ok_listing_1_1() {
+        let contents = include_str!("listing_1_1.c").as_ref();
+        let out = parser_test(contents);
+        assert_that!(out.unwrap(), eq(ExitCode::Ok));
+    }
+
+    #[test]
+    fn just_parser_test_fail() {
+        let contents = "int main(void) { return 2 }".as_bytes();
+        let out = parser_test(contents);
+        assert_that!(out.unwrap(), eq(ExitCode::DataErr));
+    }
+
+    #[test]
+    fn compile_goto_error_cleanup() {
+        let contents = "int main(void) {
+    int status = 0;
+    if (status) goto fail;
+    return 0;
+fail:
+    return 1;
+}".as_bytes();
+        let out = compile_test(contents, false, false);
+        assert_that!(out.unwrap(), eq(ExitCode::Ok));
+    }
+
+    #[test]
+    fn compile_undefined_label_fails() {
+        let contents = "int main(void) {
+    goto fail;
+    return 0;
+}".as_bytes();
+        let out = compile_test(contents, false, false);
+        assert_that!(out.unwrap(), eq(ExitCode::DataErr));
+    }
+
+    #[test]
+    fn undefined_label_is_not_checked_when_just_parsing() {
+        let contents = "int main(void) {
+    goto fail;
+    return 0;
+}".as_bytes();
+        let out = parser_test(contents);
+        assert_that!(out.unwrap(), eq(ExitCode::Ok));
+    }
+
+    #[test]
+    fn compile_storage_classes() {
+        let contents = "static int counter;
+extern int limit;
+int limit = 10;
+static int next(void) {
+    static int calls;
+    calls = calls + 1;
+    return counter = counter + 1;
+}
+int main(void) {
+    register int i;
+    for (i = 0; i < limit; i = i + 1) next();
+    return counter;
+}".as_bytes();
+        let out = compile_test(contents, false, false);
+        assert_that!(out.unwrap(), eq(ExitCode::Ok));
+    }
+
+    #[test]
+    fn compile_conflicting_linkage_fails() {
+        let contents = "int x; static int x; int main(void) { return x; }".as_bytes();
+        let out = compile_test(contents, false, false);
+        assert_that!(out.unwrap(), eq(ExitCode::DataErr));
+    }
+
+    #[test]
+    fn compile_integer_types_and_constant_expressions() {
+        let contents = "enum state { IDLE, RUNNING = 4, STOPPED };
+static unsigned long ticks = 60UL * 60 * 1000;
+short limit = sizeof(long) << 2;
+int step(enum state current, unsigned char input) {
+    switch (current) {
+    case IDLE: return input ? RUNNING : IDLE;
+    case RUNNING: return (int) (ticks % 7) + limit;
+    default: return STOPPED;
+    }
+}".as_bytes();
+        let out = compile_test(contents, false, false);
+        assert_that!(out.unwrap(), eq(ExitCode::Ok));
+    }
+
+    #[test]
+    fn compile_division_by_zero_in_static_initialiser_fails() {
+        let contents = "static int per_item = 100 / (1 - 1);".as_bytes();
+        let out = compile_test(contents, false, false);
+        assert_that!(out.unwrap(), eq(ExitCode::DataErr));
+    }
+
+    #[test]
+    fn codegen_stops_after_generating_ir() {
+        let contents = "int counter;
+int count(int limit) {
+    int total = 0;
+    for (int i = 0; i < limit; i++) {
+        if (i % 3 == 0 || i > 10) continue;
+        total += i;
+    }
+    switch (total & 3) { case 1: counter++; break; default: ; }
+    return total;
+}".as_bytes();
+        let out = codegen_test(contents);
+        assert_that!(out.unwrap(), eq(ExitCode::Ok));
+    }
+
+    #[test]
+    fn x86_64_compilation_writes_the_assembler_file() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let s_file = temp.join("file.s");
+        let contents = include_str!("listing_1_1.c").as_ref();
+        let out = compile_with(contents, |options| {
+            options.target_platform = TargetPlatform::X86_64;
+            options.asm_file = Some(Box::new(s_file.clone()));
+        });
+        assert_that!(out.unwrap(), eq(ExitCode::Ok));
+        let assembly = std::fs::read_to_string(&s_file).expect("Expected the assembler file to be written");
+        assert_that!(assembly.contains("\tmovl $2, %eax\n"), eq(true));
+    }
+
+    #[test]
+    fn transputer_compilation_writes_the_assembler_file() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let asm_file = temp.join("file.asm");
+        let contents = include_str!("listing_1_1.c").as_ref();
+        let out = compile_with(contents, |options| options.asm_file = Some(Box::new(asm_file.clone())));
+        assert_that!(out.unwrap(), eq(ExitCode::Ok));
+        let assembly = std::fs::read_to_string(&asm_file).expect("Expected the assembler file to be written");
+        assert_that!(assembly.contains("\tldc 2\n\tret\n"), eq(true));
+    }
+
+    #[test]
+    fn transputer_compilation_writes_the_object_file() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let o_file = temp.join("file.o");
+        let contents = include_str!("listing_1_1.c").as_ref();
+        let out = compile_with(contents, |options| options.object_file = Some(Box::new(o_file.clone())));
+        assert_that!(out.unwrap(), eq(ExitCode::Ok));
+        let bytes = std::fs::read(&o_file).expect("Expected the object file to be written");
+        let object = Object::from_bytes(&bytes).expect("Expected a Transputer object");
+        assert_that!(object.globals, equal_to(vec!["main".to_owned()]));
+        assert_that!(object.code[0].clone(), equal_to(Fragment::Label("main".to_owned())));
+        // ldc 2; ret
+        assert_that!(matches!(&object.code[1], Fragment::Bytes(bytes) if bytes.starts_with(&[0x42, 0x22, 0xF0])), eq(true));
+    }
+
+    #[test]
+    fn objects_are_only_written_for_the_transputer() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let o_file = temp.join("file.o");
+        let contents = include_str!("listing_1_1.c").as_ref();
+        let out = compile_with(contents, |options| {
+            options.target_platform = TargetPlatform::EPOC16;
+            options.object_file = Some(Box::new(o_file.clone()));
+        });
+        assert_that!(out.unwrap_err().to_string(), equal_to("Objects cannot be written for EPOC16".to_owned()));
+    }
+
+    #[test]
+    fn epoc16_compilation_writes_the_assembler_file() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let asm_file = temp.join("file.asm");
+        let contents = include_str!("listing_1_1.c").as_ref();
+        let out = compile_with(contents, |options| {
+            options.target_platform = TargetPlatform::EPOC16;
+            options.asm_file = Some(Box::new(asm_file.clone()));
+        });
+        assert_that!(out.unwrap(), eq(ExitCode::Ok));
+        let assembly = std::fs::read_to_string(&asm_file).expect("Expected the assembler file to be written");
+        assert_that!(assembly.contains("\tmov\tax, 2\n"), eq(true));
+    }
+
+    #[test]
+    fn epoc16_compilation_of_long_works_a_word_at_a_time() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let asm_file = temp.join("file.asm");
+        let contents = "long total; int main(void) { total = total * 3; return (int) total; }".as_bytes();
+        let out = compile_with(contents, |options| {
+            options.target_platform = TargetPlatform::EPOC16;
+            options.asm_file = Some(Box::new(asm_file.clone()));
+        });
+        assert_that!(out.unwrap(), eq(ExitCode::Ok));
+        let assembly = std::fs::read_to_string(&asm_file).expect("Expected the assembler file to be written");
+        assert_that!(assembly.contains("\tEXTRN\t__mulsi3:NEAR\n"), eq(true));
+        assert_that!(assembly.contains("\tmov\tax, WORD PTR total+2\n\tpush\tax\n"), eq(true));
+    }
+
+    #[test]
+    fn transputer_compilation_of_float_calls_the_soft_float_runtime() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let asm_file = temp.join("file.asm");
+        let contents = "float half(float f) { return f / 2; } int main(void) { return half(3.0f) > 1; }".as_bytes();
+        let out = compile_with(contents, |options| options.asm_file = Some(Box::new(asm_file.clone())));
+        assert_that!(out.unwrap(), eq(ExitCode::Ok));
+        let assembly = std::fs::read_to_string(&asm_file).expect("Expected the assembler file to be written");
+        assert_that!(assembly.contains("__divsf3"), eq(true));
+        assert_that!(assembly.contains("__gtsf2"), eq(true));
+    }
+
+    #[test]
+    fn t800_compilation_of_float_uses_the_fpu() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let asm_file = temp.join("file.asm");
+        let contents = "float half(float f) { return f / 2; } int main(void) { return half(3.0f) > 1; }".as_bytes();
+        let out = compile_with(contents, |options| {
+            options.processor = Processor::T800;
+            options.asm_file = Some(Box::new(asm_file.clone()));
+        });
+        assert_that!(out.unwrap(), eq(ExitCode::Ok));
+        let assembly = std::fs::read_to_string(&asm_file).expect("Expected the assembler file to be written");
+        assert_that!(assembly.contains("\tfpdiv\n"), eq(true));
+        assert_that!(assembly.contains("\tfpgt\n"), eq(true));
+        assert_that!(assembly.contains("__divsf3"), eq(false));
+    }
+
+    #[test]
+    fn epoc16_compilation_of_double_calls_the_soft_float_runtime() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let asm_file = temp.join("file.asm");
+        let contents = "double scale; int main(void) { scale = scale * 1.5; return (int) scale; }".as_bytes();
+        let out = compile_with(contents, |options| {
+            options.target_platform = TargetPlatform::EPOC16;
+            options.asm_file = Some(Box::new(asm_file.clone()));
+        });
+        assert_that!(out.unwrap(), eq(ExitCode::Ok));
+        let assembly = std::fs::read_to_string(&asm_file).expect("Expected the assembler file to be written");
+        assert_that!(assembly.contains("__muldf3"), eq(true));
+        assert_that!(assembly.contains("__fixdfsi"), eq(true));
+        assert_that!(assembly.contains("__double_low"), eq(true));
+    }
+
+    #[test]
+    fn x86_64_compilation_of_floating_point_uses_sse() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let asm_file = temp.join("file.s");
+        let contents = "double twice(double d) { return d * 2; } int main(void) { return twice(0.5) > 0; }".as_bytes();
+        let out = compile_with(contents, |options| {
+            options.target_platform = TargetPlatform::X86_64;
+            options.asm_file = Some(Box::new(asm_file.clone()));
+        });
+        assert_that!(out.unwrap(), eq(ExitCode::Ok));
+        let assembly = std::fs::read_to_string(&asm_file).expect("Expected the assembler file to be written");
+        assert_that!(assembly.contains("\tmulsd "), eq(true));
+        assert_that!(assembly.contains("\tucomisd "), eq(true));
+        assert_that!(assembly.contains("__muldf3"), eq(false));
+    }
+
     fn lexer_test(contents: &[u8]) -> Result<ExitCode, Error> {
+        compile_test(contents, true, false)
+    }
+
+    fn parser_test(contents: &[u8]) -> Result<ExitCode, Error> {
+        compile_test(contents, false, true)
+    }
+
+    fn codegen_test(contents: &[u8]) -> Result<ExitCode, Error> {
+        compile_with(contents, |options| options.codegen = true)
+    }
+
+    fn compile_test(contents: &[u8], lex: bool, parse: bool) -> Result<ExitCode, Error> {
+        compile_with(contents, |options| {
+            options.lex = lex;
+            options.parse = parse;
+        })
+    }
+
+    fn compile_with(contents: &[u8], configure: impl FnOnce(&mut CompilerOptions)) -> Result<ExitCode, Error> {
         let (temp, _temp_dir) = temp_config_dir();
         let i_file = temp.join("file.i");
         let mut file = File::create(i_file.clone())?;
-        file.write(contents).expect("Expected to write file contents");
+        file.write_all(contents).expect("Expected to write file contents");
         drop(file);
 
-        let compiler_options = CompilerOptions {
+        let mut compiler_options = CompilerOptions {
             c_file: Box::new(i_file.clone()),
             asm_file: None,
-            lex: true,
+            object_file: None,
+            lex: false,
             parse: false,
             codegen: false,
+            dump_ir: false,
             target_platform: TargetPlatform::default(),
+            assembler_syntax: AssemblerSyntax::default(),
+            processor: Processor::default(),
+            optimisation_level: 0,
         };
+        configure(&mut compiler_options);
         let compiler = Compiler::new();
         compiler.compile(compiler_options)
     }
@@ -0,0 +1,579 @@
+//! Lowers the type checked AST to the IR. Type checking has made every conversion an explicit
+//! cast and evaluated every constant expression, so all that is left is to flatten expressions
+//! into temporaries and statements into jumps.
+//!
+//! Temporaries are named '%1', '%2', ..., and generated labels are named for their purpose, e.g.
+//! 'if_else..3'. Neither can clash with an identifier from the program, which are renamed to
+//! 'name.N', or with a goto label, which are renamed to 'function.label': see 'generated_name'.
+
+use std::collections::VecDeque;
+
+use common::data_model::DataModel;
+
+use crate::ast::{
+    self, Block, BlockItem, Constant, Declaration, Expression, ForInit, FunctionDeclaration, Statement, StorageClass,
+    Type, VariableDeclaration,
+};
+use crate::ir::{
+    generated_name, BinaryOperator, Function, Instruction, Program, StaticVariable, TopLevel, UnaryOperator, Value,
+};
+use crate::semantic::symbol_table::{IdentifierAttributes, InitialValue, Symbol, SymbolTable};
+
+struct Generator<'a> {
+    symbols: &'a mut SymbolTable,
+    data_model: &'a DataModel,
+    instructions: Vec<Instruction>,
+    /// Numbers temporaries and labels, so they are unique across the whole program.
+    counter: usize,
+    /// The labels that 'break' and 'continue' jump to, innermost last.
+    breaks: Vec<String>,
+    continues: Vec<String>,
+    /// The labels of the cases of each enclosing switch, in the order they appear in its body.
+    cases: Vec<VecDeque<String>>,
+}
+
+/// Generates the IR for a type checked program, adding its temporaries to the symbol table.
+pub fn generate(program: &ast::Program, symbols: &mut SymbolTable, data_model: &DataModel) -> Program {
+    let mut generator = Generator {
+        symbols,
+        data_model,
+        instructions: vec![],
+        counter: 0,
+        breaks: vec![],
+        continues: vec![],
+        cases: vec![],
+    };
+    let mut top_level: Vec<TopLevel> = program
+        .declarations
+        .iter()
+        .filter_map(|declaration| match declaration {
+            Declaration::Function(function) => generator.function(function).map(TopLevel::Function),
+            Declaration::Variable(_) | Declaration::Enum(_) => None,
+        })
+        .collect();
+    top_level.extend(static_variables(generator.symbols));
+    Program { top_level }
+}
+
+/// The definitions of every variable with static storage duration, both file and block scope.
+/// Tentative definitions that were never given an initialiser are zero.
+fn static_variables(symbols: &SymbolTable) -> Vec<TopLevel> {
+    symbols
+        .iter()
+        .filter_map(|(name, symbol)| match &symbol.attributes {
+            IdentifierAttributes::Static { initial_value, global } => {
+                let init = match initial_value {
+                    InitialValue::Initial(constant) => constant.clone(),
+                    InitialValue::Tentative => Constant { constant_type: symbol.symbol_type.clone(), value: 0 },
+                    InitialValue::NoInitialiser => return None,
+                };
+                Some(TopLevel::StaticVariable(StaticVariable {
+                    name: name.clone(),
+                    global: *global,
+                    variable_type: symbol.symbol_type.clone(),
+                    init,
+                }))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The case and default statements of a switch statement's body, in order, without those of any
+/// nested switch statement.
+fn collect_cases<'s>(statement: &'s Statement, cases: &mut Vec<&'s Statement>) {
+    match statement {
+        Statement::Case(_, body) | Statement::Default(body) => {
+            cases.push(statement);
+            collect_cases(body, cases);
+        }
+        Statement::If { then, otherwise, .. } => {
+            collect_cases(then, cases);
+            if let Some(otherwise) = otherwise {
+                collect_cases(otherwise, cases);
+            }
+        }
+        Statement::Compound(block) => {
+            for item in &block.items {
+                if let BlockItem::Statement(statement) = item {
+                    collect_cases(statement, cases);
+                }
+            }
+        }
+        Statement::While { body, .. }
+        | Statement::DoWhile { body, .. }
+        | Statement::For { body, .. }
+        | Statement::Labelled(_, body) => collect_cases(body, cases),
+        Statement::Switch { .. }
+        | Statement::Return(_)
+        | Statement::Expression(_)
+        | Statement::Break
+        | Statement::Continue
+        | Statement::Goto(_)
+        | Statement::Null => {}
+    }
+}
+
+fn binary_operator(operator: ast::BinaryOperator) -> BinaryOperator {
+    match operator {
+        ast::BinaryOperator::Add => BinaryOperator::Add,
+        ast::BinaryOperator::Subtract => BinaryOperator::Subtract,
+        ast::BinaryOperator::Multiply => BinaryOperator::Multiply,
+        ast::BinaryOperator::Divide => BinaryOperator::Divide,
+        ast::BinaryOperator::Remainder => BinaryOperator::Remainder,
+        ast::BinaryOperator::BitwiseAnd => BinaryOperator::BitwiseAnd,
+        ast::BinaryOperator::BitwiseOr => BinaryOperator::BitwiseOr,
+        ast::BinaryOperator::BitwiseXor => BinaryOperator::BitwiseXor,
+        ast::BinaryOperator::ShiftLeft => BinaryOperator::ShiftLeft,
+        ast::BinaryOperator::ShiftRight => BinaryOperator::ShiftRight,
+        ast::BinaryOperator::Equal => BinaryOperator::Equal,
+        ast::BinaryOperator::NotEqual => BinaryOperator::NotEqual,
+        ast::BinaryOperator::LessThan => BinaryOperator::LessThan,
+        ast::BinaryOperator::LessOrEqual => BinaryOperator::LessOrEqual,
+        ast::BinaryOperator::GreaterThan => BinaryOperator::GreaterThan,
+        ast::BinaryOperator::GreaterOrEqual => BinaryOperator::GreaterOrEqual,
+        ast::BinaryOperator::And | ast::BinaryOperator::Or => unreachable!("logical operators are lowered to jumps"),
+    }
+}
+
+/// The instruction that converts a value from one type to another.
+pub(crate) fn conversion(src: Value, dst: Value, from: &Type, to: &Type, data_model: &DataModel) -> Instruction {
+    let (from_bits, to_bits) = (from.bits(data_model), to.bits(data_model));
+    match (from.is_floating(), to.is_floating()) {
+        (false, true) if from.is_signed(data_model) => Instruction::IntToFloat { src, dst },
+        (false, true) => Instruction::UIntToFloat { src, dst },
+        (true, false) if to.is_signed(data_model) => Instruction::FloatToInt { src, dst },
+        (true, false) => Instruction::FloatToUInt { src, dst },
+        (true, true) if to_bits > from_bits => Instruction::FloatExtend { src, dst },
+        (true, true) if to_bits < from_bits => Instruction::FloatTruncate { src, dst },
+        _ if to_bits == from_bits => Instruction::Copy { src, dst },
+        _ if to_bits < from_bits => Instruction::Truncate { src, dst },
+        _ if from.is_signed(data_model) => Instruction::SignExtend { src, dst },
+        _ => Instruction::ZeroExtend { src, dst },
+    }
+}
+
+fn variable_name(expression: &Expression) -> &str {
+    match expression {
+        Expression::Var(name) => name,
+        _ => unreachable!("identifier resolution only allows variables as lvalues"),
+    }
+}
+
+impl Generator<'_> {
+    fn function(&mut self, function: &FunctionDeclaration) -> Option<Function> {
+        let body = function.body.as_ref()?;
+        self.block(body);
+        // Falling off the end of a function returns zero, which 'main' relies on.
+        let return_value = match &function.function_type {
+            Type::Function { return_type, .. } if **return_type == Type::Void => None,
+            Type::Function { return_type, .. } => {
+                Some(Value::Constant(Constant { constant_type: *return_type.clone(), value: 0 }))
+            }
+            _ => unreachable!("functions have function types"),
+        };
+        self.emit(Instruction::Return(return_value));
+        Some(Function {
+            name: function.name.clone(),
+            global: self.symbols.is_global(&function.name),
+            params: function.params.clone(),
+            body: std::mem::take(&mut self.instructions),
+        })
+    }
+
+    fn emit(&mut self, instruction: Instruction) {
+        self.instructions.push(instruction);
+    }
+
+    fn label(&mut self, purpose: &str) -> String {
+        self.counter += 1;
+        generated_name(purpose, self.counter)
+    }
+
+    /// A new temporary of the given type.
+    fn temporary(&mut self, temporary_type: &Type) -> Value {
+        self.counter += 1;
+        let name = format!("%{}", self.counter);
+        self.symbols.insert(&name, Symbol { symbol_type: temporary_type.clone(), attributes: IdentifierAttributes::Local });
+        Value::Var(name)
+    }
+
+    fn value_type(&self, value: &Value) -> Type {
+        match value {
+            Value::Constant(constant) => constant.constant_type.clone(),
+            Value::Var(name) => self.symbols.get(name).expect("every variable is in the symbol table").symbol_type.clone(),
+        }
+    }
+
+    /// Emits the conversion of a value to the type of the destination.
+    fn convert_into(&mut self, src: Value, dst: Value) {
+        let (from, to) = (self.value_type(&src), self.value_type(&dst));
+        self.emit(conversion(src, dst, &from, &to, self.data_model));
+    }
+
+    /// The value converted to the given type, in a new temporary unless it already has it.
+    /// Constants are converted here and now.
+    fn convert(&mut self, value: Value, to: &Type) -> Value {
+        match value {
+            _ if self.value_type(&value) == *to => value,
+            Value::Constant(constant) => Value::Constant(constant.convert(to, self.data_model)),
+            Value::Var(_) => {
+                let dst = self.temporary(to);
+                self.convert_into(value, dst.clone());
+                dst
+            }
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        for item in &block.items {
+            match item {
+                BlockItem::Statement(statement) => self.statement(statement),
+                BlockItem::Declaration(Declaration::Variable(variable)) => self.variable_declaration(variable),
+                BlockItem::Declaration(Declaration::Function(_) | Declaration::Enum(_)) => {}
+            }
+        }
+    }
+
+    /// Only automatic variables are initialised where they are declared. Static ones are defined
+    /// with their initial value at the top level.
+    fn variable_declaration(&mut self, variable: &VariableDeclaration) {
+        match (&variable.storage_class, &variable.init) {
+            (Some(StorageClass::Static | StorageClass::Extern), _) | (_, None) => {}
+            (_, Some(init)) => {
+                let src = self.expression(init);
+                self.emit(Instruction::Copy { src, dst: Value::Var(variable.name.clone()) });
+            }
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Return(expression) => {
+                let value = expression.as_ref().map(|expression| self.expression(expression));
+                self.emit(Instruction::Return(value));
+            }
+            Statement::Expression(expression) => {
+                self.expression(expression);
+            }
+            Statement::If { condition, then, otherwise } => {
+                let else_label = self.label("if_else");
+                let condition = self.expression(condition);
+                self.emit(Instruction::JumpIfZero(condition, else_label.clone()));
+                self.statement(then);
+                match otherwise {
+                    Some(otherwise) => {
+                        let end_label = self.label("if_end");
+                        self.emit(Instruction::Jump(end_label.clone()));
+                        self.emit(Instruction::Label(else_label));
+                        self.statement(otherwise);
+                        self.emit(Instruction::Label(end_label));
+                    }
+                    None => self.emit(Instruction::Label(else_label)),
+                }
+            }
+            Statement::Compound(block) => self.block(block),
+            Statement::Break => {
+                let target = self.breaks.last().expect("type checking ensures break is within a loop or switch").clone();
+                self.emit(Instruction::Jump(target));
+            }
+            Statement::Continue => {
+                let target = self.continues.last().expect("type checking ensures continue is within a loop").clone();
+                self.emit(Instruction::Jump(target));
+            }
+            Statement::While { condition, body } => {
+                let (continue_label, break_label) = (self.label("while_continue"), self.label("while_break"));
+                self.emit(Instruction::Label(continue_label.clone()));
+                let condition = self.expression(condition);
+                self.emit(Instruction::JumpIfZero(condition, break_label.clone()));
+                self.loop_body(body, &continue_label, &break_label);
+                self.emit(Instruction::Jump(continue_label));
+                self.emit(Instruction::Label(break_label));
+            }
+            Statement::DoWhile { body, condition } => {
+                let start_label = self.label("do_start");
+                let (continue_label, break_label) = (self.label("do_continue"), self.label("do_break"));
+                self.emit(Instruction::Label(start_label.clone()));
+                self.loop_body(body, &continue_label, &break_label);
+                self.emit(Instruction::Label(continue_label));
+                let condition = self.expression(condition);
+                self.emit(Instruction::JumpIfNotZero(condition, start_label));
+                self.emit(Instruction::Label(break_label));
+            }
+            Statement::For { init, condition, post, body } => {
+                match init {
+                    ForInit::Declaration(variable) => self.variable_declaration(variable),
+                    ForInit::Expression(expression) => {
+                        if let Some(expression) = expression {
+                            self.expression(expression);
+                        }
+                    }
+                }
+                let start_label = self.label("for_start");
+                let (continue_label, break_label) = (self.label("for_continue"), self.label("for_break"));
+                self.emit(Instruction::Label(start_label.clone()));
+                if let Some(condition) = condition {
+                    let condition = self.expression(condition);
+                    self.emit(Instruction::JumpIfZero(condition, break_label.clone()));
+                }
+                self.loop_body(body, &continue_label, &break_label);
+                self.emit(Instruction::Label(continue_label));
+                if let Some(post) = post {
+                    self.expression(post);
+                }
+                self.emit(Instruction::Jump(start_label));
+                self.emit(Instruction::Label(break_label));
+            }
+            Statement::Switch { condition, body } => self.switch(condition, body),
+            Statement::Case(_, body) | Statement::Default(body) => {
+                let label = self
+                    .cases
+                    .last_mut()
+                    .and_then(VecDeque::pop_front)
+                    .expect("type checking ensures cases are within a switch");
+                self.emit(Instruction::Label(label));
+                self.statement(body);
+            }
+            Statement::Goto(label) => self.emit(Instruction::Jump(label.clone())),
+            Statement::Labelled(label, body) => {
+                self.emit(Instruction::Label(label.clone()));
+                self.statement(body);
+            }
+            Statement::Null => {}
+        }
+    }
+
+    fn loop_body(&mut self, body: &Statement, continue_label: &str, break_label: &str) {
+        self.continues.push(continue_label.to_owned());
+        self.breaks.push(break_label.to_owned());
+        self.statement(body);
+        self.breaks.pop();
+        self.continues.pop();
+    }
+
+    /// The controlling value is compared with each case in turn, jumping to the first that is
+    /// equal, or otherwise to the default, or past the body if there is none.
+    fn switch(&mut self, condition: &Expression, body: &Statement) {
+        let condition = self.expression(condition);
+        let break_label = self.label("switch_break");
+        let mut cases = vec![];
+        collect_cases(body, &mut cases);
+
+        let mut labels = VecDeque::new();
+        let mut default_label = None;
+        for case in cases {
+            match case {
+                Statement::Case(Expression::Constant(constant), _) => {
+                    let label = self.label("switch_case");
+                    let equal = self.temporary(&Type::Int);
+                    self.emit(Instruction::Binary {
+                        operator: BinaryOperator::Equal,
+                        src1: condition.clone(),
+                        src2: Value::Constant(constant.clone()),
+                        dst: equal.clone(),
+                    });
+                    self.emit(Instruction::JumpIfNotZero(equal, label.clone()));
+                    labels.push_back(label);
+                }
+                Statement::Default(_) => {
+                    let label = self.label("switch_default");
+                    default_label = Some(label.clone());
+                    labels.push_back(label);
+                }
+                _ => unreachable!("type checking evaluates case labels to constants"),
+            }
+        }
+        self.emit(Instruction::Jump(default_label.unwrap_or_else(|| break_label.clone())));
+
+        self.cases.push(labels);
+        self.breaks.push(break_label.clone());
+        self.statement(body);
+        self.breaks.pop();
+        self.cases.pop();
+        self.emit(Instruction::Label(break_label));
+    }
+
+    /// Emits the instructions to evaluate an expression, returning where its value is. Expressions
+    /// of type 'void' have no value, and give a dummy one that is never used.
+    fn expression(&mut self, expression: &Expression) -> Value {
+        match expression {
+            Expression::Constant(constant) => Value::Constant(constant.clone()),
+            Expression::Var(name) => Value::Var(name.clone()),
+            Expression::Cast(Type::Void, operand) => {
+                self.expression(operand);
+                Value::Constant(Constant::int(0))
+            }
+            Expression::Cast(target, operand) => {
+                let value = self.expression(operand);
+                self.convert(value, target)
+            }
+            Expression::Unary(operator, operand) => self.unary(*operator, operand),
+            Expression::Binary(ast::BinaryOperator::And, left, right) => self.logical(true, left, right),
+            Expression::Binary(ast::BinaryOperator::Or, left, right) => self.logical(false, left, right),
+            Expression::Binary(operator, left, right) => {
+                let src1 = self.expression(left);
+                let src2 = self.expression(right);
+                let result_type = match operator {
+                    ast::BinaryOperator::Equal
+                    | ast::BinaryOperator::NotEqual
+                    | ast::BinaryOperator::LessThan
+                    | ast::BinaryOperator::LessOrEqual
+                    | ast::BinaryOperator::GreaterThan
+                    | ast::BinaryOperator::GreaterOrEqual => Type::Int,
+                    _ => self.value_type(&src1),
+                };
+                let dst = self.temporary(&result_type);
+                self.emit(Instruction::Binary { operator: binary_operator(*operator), src1, src2, dst: dst.clone() });
+                dst
+            }
+            Expression::Assignment(left, right) => {
+                let src = self.expression(right);
+                let dst = Value::Var(variable_name(left).to_owned());
+                self.emit(Instruction::Copy { src, dst: dst.clone() });
+                dst
+            }
+            Expression::CompoundAssignment(operator, left, right) => {
+                let variable = Value::Var(variable_name(left).to_owned());
+                let src2 = self.expression(right);
+                let operation_type = match operator {
+                    ast::BinaryOperator::ShiftLeft | ast::BinaryOperator::ShiftRight => {
+                        self.value_type(&variable).promoted(self.data_model)
+                    }
+                    _ => self.value_type(&src2),
+                };
+                let src1 = self.convert(variable.clone(), &operation_type);
+                let result = self.temporary(&operation_type);
+                self.emit(Instruction::Binary { operator: binary_operator(*operator), src1, src2, dst: result.clone() });
+                self.convert_into(result, variable.clone());
+                variable
+            }
+            Expression::Conditional(condition, then, otherwise) => {
+                let (else_label, end_label) = (self.label("conditional_else"), self.label("conditional_end"));
+                let condition = self.expression(condition);
+                self.emit(Instruction::JumpIfZero(condition, else_label.clone()));
+                let void = self.is_void(then);
+                let then = self.expression(then);
+                // Type checking has converted both arms to the same type, unless both are 'void'.
+                let result = match void {
+                    true => None,
+                    false => Some(self.temporary(&self.value_type(&then))),
+                };
+                if let Some(result) = &result {
+                    self.emit(Instruction::Copy { src: then, dst: result.clone() });
+                }
+                self.emit(Instruction::Jump(end_label.clone()));
+                self.emit(Instruction::Label(else_label));
+                let otherwise = self.expression(otherwise);
+                if let Some(result) = &result {
+                    self.emit(Instruction::Copy { src: otherwise, dst: result.clone() });
+                }
+                self.emit(Instruction::Label(end_label));
+                result.unwrap_or(Value::Constant(Constant::int(0)))
+            }
+            Expression::FunctionCall(name, arguments) => {
+                let args = arguments.iter().map(|argument| self.expression(argument)).collect();
+                let return_type = match &self.symbols.get(name).expect("functions are declared before use").symbol_type {
+                    Type::Function { return_type, .. } => *return_type.clone(),
+                    _ => unreachable!("type checking ensures only functions are called"),
+                };
+                let dst = match return_type {
+                    Type::Void => None,
+                    return_type => Some(self.temporary(&return_type)),
+                };
+                self.emit(Instruction::FunctionCall { name: name.clone(), args, dst: dst.clone() });
+                dst.unwrap_or(Value::Constant(Constant::int(0)))
+            }
+            Expression::Literal(_) | Expression::SizeOfType(_) | Expression::SizeOfExpression(_) => {
+                unreachable!("type checking replaces literals and 'sizeof' with constants")
+            }
+        }
+    }
+
+    fn is_void(&self, expression: &Expression) -> bool {
+        match expression {
+            Expression::Cast(target, _) => *target == Type::Void,
+            Expression::Conditional(_, then, _) => self.is_void(then),
+            Expression::FunctionCall(name, _) => matches!(
+                self.symbols.get(name).map(|symbol| &symbol.symbol_type),
+                Some(Type::Function { return_type, .. }) if **return_type == Type::Void
+            ),
+            _ => false,
+        }
+    }
+
+    fn unary(&mut self, operator: ast::UnaryOperator, operand: &Expression) -> Value {
+        let ir_operator = match operator {
+            ast::UnaryOperator::Negate => UnaryOperator::Negate,
+            ast::UnaryOperator::Complement => UnaryOperator::Complement,
+            ast::UnaryOperator::Not => UnaryOperator::Not,
+            ast::UnaryOperator::PreIncrement | ast::UnaryOperator::PostIncrement => {
+                return self.increment(operator, BinaryOperator::Add, operand);
+            }
+            ast::UnaryOperator::PreDecrement | ast::UnaryOperator::PostDecrement => {
+                return self.increment(operator, BinaryOperator::Subtract, operand);
+            }
+        };
+        let src = self.expression(operand);
+        let result_type = match ir_operator {
+            UnaryOperator::Not => Type::Int,
+            // Type checking has promoted the operand.
+            UnaryOperator::Negate | UnaryOperator::Complement => self.value_type(&src),
+        };
+        let dst = self.temporary(&result_type);
+        self.emit(Instruction::Unary { operator: ir_operator, src, dst: dst.clone() });
+        dst
+    }
+
+    /// The increment and decrement operators add or subtract one in the promoted type of the
+    /// variable, and convert the result back. The postfix forms give the value from before.
+    fn increment(&mut self, operator: ast::UnaryOperator, ir_operator: BinaryOperator, operand: &Expression) -> Value {
+        let variable = Value::Var(variable_name(operand).to_owned());
+        let variable_type = self.value_type(&variable);
+        let result = match operator {
+            ast::UnaryOperator::PostIncrement | ast::UnaryOperator::PostDecrement => {
+                let old = self.temporary(&variable_type);
+                self.emit(Instruction::Copy { src: variable.clone(), dst: old.clone() });
+                old
+            }
+            _ => variable.clone(),
+        };
+        let operation_type = variable_type.promoted(self.data_model);
+        let src1 = self.convert(variable.clone(), &operation_type);
+        let one = Value::Constant(Constant::int(1).convert(&operation_type, self.data_model));
+        let dst = self.temporary(&operation_type);
+        self.emit(Instruction::Binary { operator: ir_operator, src1, src2: one, dst: dst.clone() });
+        self.convert_into(dst, variable);
+        result
+    }
+
+    /// '&&' and '||' only evaluate their right operand if the left does not decide the result.
+    fn logical(&mut self, and: bool, left: &Expression, right: &Expression) -> Value {
+        let (short_circuit_label, end_label) = if and {
+            (self.label("and_false"), self.label("and_end"))
+        } else {
+            (self.label("or_true"), self.label("or_end"))
+        };
+        let short_circuit = |value, label| {
+            if and {
+                Instruction::JumpIfZero(value, label)
+            } else {
+                Instruction::JumpIfNotZero(value, label)
+            }
+        };
+        let result = self.temporary(&Type::Int);
+        let left = self.expression(left);
+        self.emit(short_circuit(left, short_circuit_label.clone()));
+        let right = self.expression(right);
+        self.emit(short_circuit(right, short_circuit_label.clone()));
+        self.emit(Instruction::Copy { src: Value::Constant(Constant::int(and as i128)), dst: result.clone() });
+        self.emit(Instruction::Jump(end_label.clone()));
+        self.emit(Instruction::Label(short_circuit_label));
+        self.emit(Instruction::Copy { src: Value::Constant(Constant::int(!and as i128)), dst: result.clone() });
+        self.emit(Instruction::Label(end_label));
+        result
+    }
+}
+
+#[cfg(test)]
+#[path = "./generation_spec.rs"]
+mod generation_spec;
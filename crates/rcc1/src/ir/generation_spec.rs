@@ -0,0 +1,334 @@
+mod generation_spec {
+    use common::target_platform::TargetPlatform;
+    use hamcrest2::prelude::*;
+
+    use crate::ast::Type;
+    use crate::program_test_helper::ir_of;
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn dump(input: &str, target_platform: TargetPlatform) -> String {
+        ir_of(input, target_platform).0.to_string()
+    }
+
+    #[test]
+    fn return_constant() {
+        assert_that!(
+            dump("int main(void) { return 2; }", TargetPlatform::Transputer),
+            eq("global function main() {
+    return 2
+    return 0
+}
+")
+        );
+    }
+
+    #[test]
+    fn nested_expressions_use_temporaries() {
+        assert_that!(
+            dump("int f(int a, int b) { return -(a + b) * ~b; }", TargetPlatform::Transputer),
+            eq("global function f(a.1, b.2) {
+    %1 = add a.1, b.2
+    %2 = negate %1
+    %3 = complement b.2
+    %4 = multiply %2, %3
+    return %4
+    return 0
+}
+")
+        );
+    }
+
+    #[test]
+    fn temporaries_are_typed_in_the_symbol_table() {
+        let (_, symbols) = ir_of("long f(long a) { return a * 2; }", TargetPlatform::EPOC16);
+        assert_that!(symbols.get("%1").map(|symbol| symbol.symbol_type.clone()), eq(Some(Type::Long)));
+    }
+
+    #[test]
+    fn conversions_depend_on_the_target_widths() {
+        let input = "long f(int a, unsigned int b) { return a + b; }";
+        assert_that!(
+            dump(input, TargetPlatform::EPOC16),
+            eq("global function f(a.1, b.2) {
+    %1 = a.1
+    %2 = add %1, b.2
+    %3 = zero_extend %2
+    return %3
+    return (long) 0
+}
+")
+        );
+        assert_that!(
+            dump(input, TargetPlatform::Transputer),
+            eq("global function f(a.1, b.2) {
+    %1 = a.1
+    %2 = add %1, b.2
+    %3 = %2
+    return %3
+    return (long) 0
+}
+")
+        );
+    }
+
+    #[test]
+    fn floating_conversions() {
+        assert_that!(
+            dump("double f(float x, unsigned char c, long l) { return x * c + l; }", TargetPlatform::EPOC16),
+            eq("global function f(x.1, c.2, l.3) {
+    %1 = uint_to_float c.2
+    %2 = multiply x.1, %1
+    %3 = int_to_float l.3
+    %4 = add %2, %3
+    %5 = float_extend %4
+    return %5
+    return (double) 0.0
+}
+")
+        );
+        assert_that!(
+            dump("int f(double d) { return (float) d < 1; }", TargetPlatform::EPOC16),
+            eq("global function f(d.1) {
+    %1 = float_truncate d.1
+    %2 = less_than %1, (float) 1.0
+    return %2
+    return 0
+}
+")
+        );
+    }
+
+    #[test]
+    fn character_increment_widens_and_truncates() {
+        assert_that!(
+            dump("int f(char c) { return c++; }", TargetPlatform::EPOC16),
+            eq("global function f(c.1) {
+    %1 = c.1
+    %2 = sign_extend c.1
+    %3 = add %2, 1
+    c.1 = truncate %3
+    %4 = sign_extend %1
+    return %4
+    return 0
+}
+")
+        );
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        assert_that!(
+            dump("int f(int a, int b) { return a && b || a; }", TargetPlatform::Transputer),
+            eq("global function f(a.1, b.2) {
+    jump_if_zero a.1, and_false..4
+    jump_if_zero b.2, and_false..4
+    %6 = 1
+    jump and_end..5
+and_false..4:
+    %6 = 0
+and_end..5:
+    jump_if_not_zero %6, or_true..1
+    jump_if_not_zero a.1, or_true..1
+    %3 = 0
+    jump or_end..2
+or_true..1:
+    %3 = 1
+or_end..2:
+    return %3
+    return 0
+}
+")
+        );
+    }
+
+    #[test]
+    fn compound_assignment_converts_to_the_operation_type_and_back() {
+        assert_that!(
+            dump("int f(short s, long l) { s += l; s <<= 2; return s; }", TargetPlatform::EPOC16),
+            eq("global function f(s.1, l.2) {
+    %1 = sign_extend s.1
+    %2 = add %1, l.2
+    s.1 = truncate %2
+    %3 = s.1
+    %4 = shift_left %3, 2
+    s.1 = %4
+    %5 = s.1
+    return %5
+    return 0
+}
+")
+        );
+    }
+
+    #[test]
+    fn loops_with_break_and_continue() {
+        assert_that!(
+            dump(
+                "int f(int n) { int total = 0; for (int i = 0; i < n; i = i + 1) { if (i == 3) continue; if (i == 5) break; total = total + i; } while (n) n = n - 1; do n = n + 1; while (n < 2); return total; }",
+                TargetPlatform::Transputer
+            ),
+            eq("global function f(n.1) {
+    total.2 = 0
+    i.3 = 0
+for_start..1:
+    %4 = less_than i.3, n.1
+    jump_if_zero %4, for_break..3
+    %6 = equal i.3, 3
+    jump_if_zero %6, if_else..5
+    jump for_continue..2
+if_else..5:
+    %8 = equal i.3, 5
+    jump_if_zero %8, if_else..7
+    jump for_break..3
+if_else..7:
+    %9 = add total.2, i.3
+    total.2 = %9
+for_continue..2:
+    %10 = add i.3, 1
+    i.3 = %10
+    jump for_start..1
+for_break..3:
+while_continue..11:
+    jump_if_zero n.1, while_break..12
+    %13 = subtract n.1, 1
+    n.1 = %13
+    jump while_continue..11
+while_break..12:
+do_start..14:
+    %17 = add n.1, 1
+    n.1 = %17
+do_continue..15:
+    %18 = less_than n.1, 2
+    jump_if_not_zero %18, do_start..14
+do_break..16:
+    return total.2
+    return 0
+}
+")
+        );
+    }
+
+    #[test]
+    fn switch_compares_each_case_then_jumps_to_the_default() {
+        assert_that!(
+            dump(
+                "int f(char c) { switch (c) { case 1: return 10; case 2: { switch (c) { default: break; } } default: return 20; case 3: break; } return 0; }",
+                TargetPlatform::EPOC16
+            ),
+            eq("global function f(c.1) {
+    %1 = sign_extend c.1
+    %4 = equal %1, 1
+    jump_if_not_zero %4, switch_case..3
+    %6 = equal %1, 2
+    jump_if_not_zero %6, switch_case..5
+    %9 = equal %1, 3
+    jump_if_not_zero %9, switch_case..8
+    jump switch_default..7
+switch_case..3:
+    return 10
+switch_case..5:
+    %10 = sign_extend c.1
+    jump switch_default..12
+switch_default..12:
+    jump switch_break..11
+switch_break..11:
+switch_default..7:
+    return 20
+switch_case..8:
+    jump switch_break..2
+switch_break..2:
+    return 0
+    return 0
+}
+")
+        );
+    }
+
+    #[test]
+    fn conditional_and_goto() {
+        assert_that!(
+            dump("int f(int a) { if (a) goto done; a = a ? 1 : 2L; done: return a; }", TargetPlatform::Transputer),
+            eq("global function f(a.1) {
+    jump_if_zero a.1, if_else..1
+    jump f.done
+if_else..1:
+    jump_if_zero a.1, conditional_else..2
+    %4 = (long) 1
+    jump conditional_end..3
+conditional_else..2:
+    %4 = (long) 2
+conditional_end..3:
+    %5 = %4
+    a.1 = %5
+f.done:
+    return a.1
+    return 0
+}
+")
+        );
+    }
+
+    #[test]
+    fn function_calls_and_void() {
+        assert_that!(
+            dump("void g(long x); int h(void); void f(void) { g(h()); (void) h(); return; }", TargetPlatform::EPOC16),
+            eq("global function f() {
+    %1 = call h()
+    %2 = sign_extend %1
+    call g(%2)
+    %3 = call h()
+    return
+    return
+}
+")
+        );
+    }
+
+    #[test]
+    fn static_variables_are_defined_at_the_top_level() {
+        assert_that!(
+            dump(
+                "int tentative; static long initialised = 3; extern int elsewhere; int f(void) { static unsigned char local = 300; return local + elsewhere; }",
+                TargetPlatform::X86_64
+            ),
+            eq("global function f() {
+    %1 = zero_extend local.1
+    %2 = add %1, elsewhere
+    return %2
+    return 0
+}
+variable initialised: long = 3
+variable local.1: unsigned char = 44
+global variable tentative: int = 0
+")
+        );
+    }
+
+    #[test]
+    fn generated_labels_cannot_clash_with_renamed_statics() {
+        assert_that!(
+            dump(
+                "int main(void) { static int if_else = 5; int a = 3; if (a) a = 1; else a = 2; return if_else + a; }",
+                TargetPlatform::Transputer
+            ),
+            eq("global function main() {
+    a.2 = 3
+    jump_if_zero a.2, if_else..1
+    a.2 = 1
+    jump if_end..2
+if_else..1:
+    a.2 = 2
+if_end..2:
+    %3 = add if_else.1, a.2
+    return %3
+    return 0
+}
+variable if_else.1: int = 5
+")
+        );
+    }
+}
@@ -0,0 +1,308 @@
+//! An interpreter for the IR, which runs a program's 'main' as it would run on a target, with
+//! every value held in the width of its type in the target's data model. It lets the tests check
+//! the meaning of programs for EPOC16 and the Transputer without an emulator, and compare the
+//! results for x86_64 with those of a native compiler.
+//!
+//! Only the program itself can be run: there is no C library, so calling a function that is not
+//! defined in the program is an error. Reading an automatic variable before it has been assigned
+//! gives zero.
+//!
+//! Floating values are held as their IEEE 754 bit patterns, as in constants, and the arithmetic on
+//! them is the host's, which is IEEE 754 rounding to nearest: the reference that the soft-float
+//! runtime is tested against.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use common::data_model::DataModel;
+
+use crate::ast::{Constant, Type};
+use crate::ir::{BinaryOperator, Function, Instruction, Program, TopLevel, UnaryOperator, Value};
+use crate::semantic::symbol_table::{IdentifierAttributes, SymbolTable};
+
+/// Deeper recursion than this is taken to be unbounded.
+const MAX_CALL_DEPTH: usize = 100_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterpreterError {
+    NoMain,
+    UndefinedFunction(String),
+    DivisionByZero(String),
+    CallDepthExceeded,
+}
+
+impl Display for InterpreterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InterpreterError::NoMain => write!(f, "No definition of 'main'"),
+            InterpreterError::UndefinedFunction(name) => write!(f, "Call to undefined function '{}'", name),
+            InterpreterError::DivisionByZero(function) => write!(f, "Division by zero in function '{}'", function),
+            InterpreterError::CallDepthExceeded => write!(f, "Call depth exceeded {}", MAX_CALL_DEPTH),
+        }
+    }
+}
+
+/// A function being executed.
+struct Frame<'p> {
+    function: &'p Function,
+    /// The index of the next instruction.
+    pc: usize,
+    locals: HashMap<String, i128>,
+    /// Where the caller wants the returned value.
+    result: Option<Value>,
+}
+
+struct Interpreter<'p> {
+    symbols: &'p SymbolTable,
+    data_model: &'p DataModel,
+    functions: HashMap<&'p str, (&'p Function, HashMap<&'p str, usize>)>,
+    statics: HashMap<String, i128>,
+}
+
+/// Runs the program's 'main' function, returning the value it returns: the program's exit
+/// status.
+pub fn run(program: &Program, symbols: &SymbolTable, data_model: &DataModel) -> Result<i128, InterpreterError> {
+    let mut interpreter = Interpreter::new(program, symbols, data_model);
+    let main = interpreter.functions.get("main").ok_or(InterpreterError::NoMain)?.0;
+    let args = vec![0; main.params.len()];
+    interpreter.execute(main, args)
+}
+
+/// Calls one of the program's functions with the given arguments, returning the value it returns
+/// and the final values of the static variables. This lets a library without a 'main', such as
+/// the soft-float runtime, be tested function by function.
+pub fn call(
+    program: &Program,
+    symbols: &SymbolTable,
+    data_model: &DataModel,
+    name: &str,
+    args: Vec<i128>,
+) -> Result<(i128, HashMap<String, i128>), InterpreterError> {
+    let mut interpreter = Interpreter::new(program, symbols, data_model);
+    let function = interpreter.functions.get(name).ok_or_else(|| InterpreterError::UndefinedFunction(name.to_owned()))?.0;
+    let value = interpreter.execute(function, args)?;
+    Ok((value, interpreter.statics))
+}
+
+impl<'p> Interpreter<'p> {
+    fn new(program: &'p Program, symbols: &'p SymbolTable, data_model: &'p DataModel) -> Self {
+        let mut interpreter = Interpreter { symbols, data_model, functions: HashMap::new(), statics: HashMap::new() };
+        for top_level in &program.top_level {
+            match top_level {
+                TopLevel::Function(function) => {
+                    let labels = function
+                        .body
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(index, instruction)| match instruction {
+                            Instruction::Label(label) => Some((label.as_str(), index)),
+                            _ => None,
+                        })
+                        .collect();
+                    interpreter.functions.insert(&function.name, (function, labels));
+                }
+                TopLevel::StaticVariable(variable) => {
+                    interpreter.statics.insert(variable.name.clone(), variable.init.value);
+                }
+            }
+        }
+        interpreter
+    }
+
+    fn value_type(&self, value: &Value) -> Type {
+        match value {
+            Value::Constant(constant) => constant.constant_type.clone(),
+            Value::Var(name) => self.symbols.get(name).expect("every variable is in the symbol table").symbol_type.clone(),
+        }
+    }
+
+    fn is_static(&self, name: &str) -> bool {
+        matches!(self.symbols.get(name).map(|symbol| &symbol.attributes), Some(IdentifierAttributes::Static { .. }))
+    }
+
+    fn read(&self, frame: &Frame, value: &Value) -> i128 {
+        match value {
+            Value::Constant(constant) => constant.value,
+            Value::Var(name) if self.is_static(name) => self.statics.get(name).copied().unwrap_or(0),
+            Value::Var(name) => frame.locals.get(name).copied().unwrap_or(0),
+        }
+    }
+
+    /// The value as a constant of its type.
+    fn constant(&self, frame: &Frame, value: &Value) -> Constant {
+        Constant { constant_type: self.value_type(value), value: self.read(frame, value) }
+    }
+
+    /// Stores a value in a variable, reduced into the range of the variable's type. This is all
+    /// that the integer conversion instructions need do, as the targets all use two's complement.
+    /// Floating values are already the bits of the variable's type.
+    fn write(&mut self, frame: &mut Frame, dst: &Value, value: i128) {
+        let Value::Var(name) = dst else { unreachable!("only variables are assigned") };
+        let dst_type = self.value_type(dst);
+        let value = if dst_type.is_floating() { value } else { Constant::wrapping(dst_type, value, self.data_model).value };
+        if self.is_static(name) {
+            self.statics.insert(name.clone(), value);
+        } else {
+            frame.locals.insert(name.clone(), value);
+        }
+    }
+
+    fn frame(&self, name: &str, args: Vec<i128>, result: Option<Value>) -> Result<Frame<'p>, InterpreterError> {
+        let function = self.functions.get(name).ok_or_else(|| InterpreterError::UndefinedFunction(name.to_owned()))?.0;
+        let locals = function.params.iter().cloned().zip(args).collect();
+        Ok(Frame { function, pc: 0, locals, result })
+    }
+
+    /// Calls are handled with an explicit stack of frames, so that deep recursion in the program
+    /// does not exhaust the interpreter's own stack.
+    fn execute(&mut self, function: &'p Function, args: Vec<i128>) -> Result<i128, InterpreterError> {
+        let mut stack = vec![self.frame(&function.name, args, None)?];
+        loop {
+            let frame = stack.last_mut().expect("there is always a frame until the outermost function returns");
+            let function = frame.function;
+            let instruction = &function.body[frame.pc];
+            frame.pc += 1;
+            match instruction {
+                Instruction::Return(value) => {
+                    let value = value.as_ref().map(|value| self.read(frame, value)).unwrap_or(0);
+                    let returned = stack.pop().expect("returning from a frame");
+                    match stack.last_mut() {
+                        Some(caller) => {
+                            if let Some(result) = &returned.result {
+                                self.write(caller, result, value);
+                            }
+                        }
+                        None => return Ok(value),
+                    }
+                }
+                Instruction::FunctionCall { name, args, dst } => {
+                    let args = args.iter().map(|arg| self.read(frame, arg)).collect();
+                    let callee = self.frame(name, args, dst.clone())?;
+                    if stack.len() >= MAX_CALL_DEPTH {
+                        return Err(InterpreterError::CallDepthExceeded);
+                    }
+                    stack.push(callee);
+                }
+                Instruction::Jump(label) => frame.pc = self.target(frame, label),
+                Instruction::JumpIfZero(condition, label) => {
+                    if self.constant(frame, condition).is_zero() {
+                        frame.pc = self.target(frame, label);
+                    }
+                }
+                Instruction::JumpIfNotZero(condition, label) => {
+                    if !self.constant(frame, condition).is_zero() {
+                        frame.pc = self.target(frame, label);
+                    }
+                }
+                Instruction::Label(_) => {}
+                Instruction::Copy { src, dst }
+                | Instruction::SignExtend { src, dst }
+                | Instruction::ZeroExtend { src, dst }
+                | Instruction::Truncate { src, dst } => {
+                    let value = self.read(frame, src);
+                    self.write(frame, dst, value);
+                }
+                Instruction::IntToFloat { src, dst }
+                | Instruction::UIntToFloat { src, dst }
+                | Instruction::FloatToInt { src, dst }
+                | Instruction::FloatToUInt { src, dst }
+                | Instruction::FloatExtend { src, dst }
+                | Instruction::FloatTruncate { src, dst } => {
+                    let value = self.constant(frame, src).convert(&self.value_type(dst), self.data_model).value;
+                    self.write(frame, dst, value);
+                }
+                Instruction::Unary { operator, src, dst } => {
+                    let operand = self.constant(frame, src);
+                    let value = operand.value;
+                    let result = match operator {
+                        UnaryOperator::Negate if operand.constant_type.is_floating() => {
+                            floating(&operand.constant_type, -operand.floating()).value
+                        }
+                        UnaryOperator::Negate => -value,
+                        UnaryOperator::Complement => !value,
+                        UnaryOperator::Not => operand.is_zero() as i128,
+                    };
+                    self.write(frame, dst, result);
+                }
+                Instruction::Binary { operator, src1, src2, dst } => {
+                    let result = self.binary(frame, *operator, src1, src2)?;
+                    self.write(frame, dst, result);
+                }
+            }
+        }
+    }
+
+    fn target(&self, frame: &Frame, label: &str) -> usize {
+        *self.functions[frame.function.name.as_str()].1.get(label).expect("jumps are to labels in the same function")
+    }
+
+    /// The operands hold their mathematical values, so most operations need only be reduced into
+    /// the range of the result's type when it is written.
+    fn binary(&self, frame: &Frame, operator: BinaryOperator, src1: &Value, src2: &Value) -> Result<i128, InterpreterError> {
+        let operand_type = self.value_type(src1);
+        if operand_type.is_floating() {
+            let (a, b) = (self.constant(frame, src1).floating(), self.constant(frame, src2).floating());
+            return Ok(floating_binary(operator, &operand_type, a, b));
+        }
+        let (a, b) = (self.read(frame, src1), self.read(frame, src2));
+        let division_by_zero = || InterpreterError::DivisionByZero(frame.function.name.clone());
+        Ok(match operator {
+            BinaryOperator::Add => a + b,
+            BinaryOperator::Subtract => a - b,
+            BinaryOperator::Multiply => a * b,
+            // Division truncates towards zero on all targets.
+            BinaryOperator::Divide => a.checked_div(b).ok_or_else(division_by_zero)?,
+            BinaryOperator::Remainder => a.checked_rem(b).ok_or_else(division_by_zero)?,
+            BinaryOperator::BitwiseAnd => a & b,
+            BinaryOperator::BitwiseOr => a | b,
+            BinaryOperator::BitwiseXor => a ^ b,
+            // A count beyond the width is undefined; the bits are all shifted out. Signed values
+            // are shifted right arithmetically.
+            BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => {
+                let bits = self.value_type(src1).bits(self.data_model) as i128;
+                let count = b.clamp(0, bits) as u32;
+                if operator == BinaryOperator::ShiftLeft {
+                    a << count
+                } else {
+                    a >> count
+                }
+            }
+            BinaryOperator::Equal => (a == b) as i128,
+            BinaryOperator::NotEqual => (a != b) as i128,
+            BinaryOperator::LessThan => (a < b) as i128,
+            BinaryOperator::LessOrEqual => (a <= b) as i128,
+            BinaryOperator::GreaterThan => (a > b) as i128,
+            BinaryOperator::GreaterOrEqual => (a >= b) as i128,
+        })
+    }
+}
+
+/// The bits of a value of a floating type, rounded from a 'double'.
+fn floating(floating_type: &Type, value: f64) -> Constant {
+    match floating_type {
+        Type::Float => Constant::float(value as f32),
+        _ => Constant::double(value),
+    }
+}
+
+/// Arithmetic on 'float' operands is done in 'double' and then rounded, which gives the same
+/// result as rounding once. Division by zero gives an infinity or NaN, rather than an error.
+fn floating_binary(operator: BinaryOperator, operand_type: &Type, a: f64, b: f64) -> i128 {
+    match operator {
+        BinaryOperator::Add => floating(operand_type, a + b).value,
+        BinaryOperator::Subtract => floating(operand_type, a - b).value,
+        BinaryOperator::Multiply => floating(operand_type, a * b).value,
+        BinaryOperator::Divide => floating(operand_type, a / b).value,
+        BinaryOperator::Equal => (a == b) as i128,
+        BinaryOperator::NotEqual => (a != b) as i128,
+        BinaryOperator::LessThan => (a < b) as i128,
+        BinaryOperator::LessOrEqual => (a <= b) as i128,
+        BinaryOperator::GreaterThan => (a > b) as i128,
+        BinaryOperator::GreaterOrEqual => (a >= b) as i128,
+        _ => unreachable!("type checking rejects floating operands of '{}'", operator),
+    }
+}
+
+#[cfg(test)]
+#[path = "./interpreter_spec.rs"]
+mod interpreter_spec;
@@ -0,0 +1,223 @@
+mod interpreter_spec {
+    use std::fs::File;
+    use std::io::Write;
+    use std::process::Command;
+
+    use common::target_platform::TargetPlatform;
+    use common_test::file_utils_test_helper::temp_config_dir;
+    use hamcrest2::prelude::*;
+    use log::warn;
+
+    use crate::ir::interpreter::InterpreterError;
+    use crate::ir::run;
+    use crate::program_test_helper::ir_of;
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn interpret(input: &str, target_platform: TargetPlatform) -> Result<i128, InterpreterError> {
+        let (ir, symbols) = ir_of(input, target_platform);
+        run(&ir, &symbols, &target_platform.data_model())
+    }
+
+    fn exit_code(input: &str, target_platform: TargetPlatform) -> i128 {
+        interpret(input, target_platform).unwrap()
+    }
+
+    #[test]
+    fn return_value_of_main() {
+        assert_that!(exit_code("int main(void) { return 2 + 3 * 4; }", TargetPlatform::Transputer), eq(14));
+        assert_that!(exit_code("int main(void) { }", TargetPlatform::Transputer), eq(0));
+    }
+
+    #[test]
+    fn int_overflow_depends_on_the_data_model() {
+        let input = "int main(void) { int x = 32767; x = x + 1; return x < 0; }";
+        assert_that!(exit_code(input, TargetPlatform::EPOC16), eq(1));
+        assert_that!(exit_code(input, TargetPlatform::Transputer), eq(0));
+        assert_that!(exit_code(input, TargetPlatform::X86_64), eq(0));
+    }
+
+    #[test]
+    fn plain_char_signedness_depends_on_the_target() {
+        let input = "int main(void) { char c = 200; return c < 0; }";
+        assert_that!(exit_code(input, TargetPlatform::EPOC16), eq(1));
+        assert_that!(exit_code(input, TargetPlatform::Transputer), eq(0));
+    }
+
+    #[test]
+    fn unsigned_arithmetic_wraps_in_the_width_of_the_type() {
+        let input = "int main(void) { unsigned int u = 0; u = u - 1; return u == 65535u; }";
+        assert_that!(exit_code(input, TargetPlatform::EPOC16), eq(1));
+        assert_that!(exit_code(input, TargetPlatform::Transputer), eq(0));
+        assert_that!(
+            exit_code("int main(void) { unsigned char c = 255; c++; return c; }", TargetPlatform::EPOC16),
+            eq(0)
+        );
+    }
+
+    #[test]
+    fn long_is_32_bits_on_epoc16() {
+        let input = "int main(void) { long l = 65536L * 2; int i = l; return (l >> 16) + i; }";
+        assert_that!(exit_code(input, TargetPlatform::EPOC16), eq(2));
+    }
+
+    #[test]
+    fn shifts_and_division_of_negative_values() {
+        let input = "int main(void) { int a = -17; return (a >> 2 == -5) + (a / 4 == -4) * 2 + (a % 4 == -1) * 4; }";
+        assert_that!(exit_code(input, TargetPlatform::EPOC16), eq(7));
+    }
+
+    #[test]
+    fn recursion() {
+        let input = "int fib(int n) { return n < 2 ? n : fib(n - 1) + fib(n - 2); }
+int main(void) { return fib(12); }";
+        assert_that!(exit_code(input, TargetPlatform::Transputer), eq(144));
+    }
+
+    #[test]
+    fn deep_recursion_does_not_exhaust_the_interpreter_stack() {
+        let input = "int depth(int n) { return n == 0 ? 0 : 1 + depth(n - 1); }
+int main(void) { return depth(50000) == 50000; }";
+        assert_that!(exit_code(input, TargetPlatform::Transputer), eq(1));
+    }
+
+    #[test]
+    fn unbounded_recursion_is_an_error() {
+        let input = "int forever(int n) { return forever(n + 1); } int main(void) { return forever(0); }";
+        assert_that!(interpret(input, TargetPlatform::Transputer), eq(Err(InterpreterError::CallDepthExceeded)));
+    }
+
+    #[test]
+    fn static_variables_keep_their_values_between_calls() {
+        let input = "int total = 10;
+int next(void) { static int count; return ++count; }
+int main(void) { next(); next(); total += next(); return total; }";
+        assert_that!(exit_code(input, TargetPlatform::EPOC16), eq(13));
+    }
+
+    #[test]
+    fn logical_operators_short_circuit_side_effects() {
+        let input = "int calls;
+int touch(void) { calls++; return 1; }
+int main(void) { int a = 0 && touch(); int b = 1 || touch(); return calls * 10 + a + b; }";
+        assert_that!(exit_code(input, TargetPlatform::Transputer), eq(1));
+    }
+
+    #[test]
+    fn float_arithmetic_is_rounded_to_single_precision() {
+        let input = "int main(void) { float f = 16777216.0f; double d = 16777216.0; f = f + 1; d = d + 1; return (d - f) * 10; }";
+        assert_that!(exit_code(input, TargetPlatform::EPOC16), eq(10));
+    }
+
+    #[test]
+    fn floating_conversions_truncate_towards_zero() {
+        let input = "int main(void) { double d = -7.9; float f = 2.5f; unsigned u = f * 3; return (int) d * 100 + u; }";
+        assert_that!(exit_code(input, TargetPlatform::Transputer), eq(-693));
+    }
+
+    #[test]
+    fn negative_zero_and_nan_in_conditions() {
+        let input = "int main(void) { double zero = 0.0; double nan = zero / zero; int r = 0;
+    if (-zero) r = 1;
+    if (nan) r += 2;
+    if (nan == nan) r += 4;
+    if (nan != nan) r += 8;
+    return r + (-zero == 0) * 16; }";
+        assert_that!(exit_code(input, TargetPlatform::Transputer), eq(26));
+    }
+
+    #[test]
+    fn switch_falls_through() {
+        let input = "int classify(int n) {
+    int result = 0;
+    switch (n) {
+    case 1: result += 1;
+    case 2: result += 2; break;
+    default: result = 100;
+    case 3: result += 3;
+    }
+    return result;
+}
+int main(void) { return classify(1) * 1000 + classify(2) * 100 + classify(3) * 10 + (classify(9) == 103); }";
+        assert_that!(exit_code(input, TargetPlatform::Transputer), eq(3231));
+    }
+
+    #[test]
+    fn runtime_division_by_zero_is_an_error() {
+        let input = "int main(void) { int zero = 0; return 1 / zero; }";
+        assert_that!(interpret(input, TargetPlatform::Transputer), eq(Err(InterpreterError::DivisionByZero("main".to_owned()))));
+    }
+
+    #[test]
+    fn calls_outside_the_program_are_an_error() {
+        let input = "int putchar(int c); int main(void) { return putchar(65); }";
+        assert_that!(
+            interpret(input, TargetPlatform::Transputer),
+            eq(Err(InterpreterError::UndefinedFunction("putchar".to_owned())))
+        );
+    }
+
+    #[test]
+    fn main_must_be_defined() {
+        assert_that!(interpret("int f(void) { return 1; }", TargetPlatform::Transputer), eq(Err(InterpreterError::NoMain)));
+    }
+
+    /// Programs whose x86_64 exit status is compared with that of the same program compiled by
+    /// gcc. They avoid undefined behaviour, so both must agree.
+    const DIFFERENTIAL_PROGRAMS: &[&str] = &[
+        "int main(void) { return 42; }",
+        "int main(void) { int a = 7, b = -3; return (a * b + a / b - a % b) & 255; }",
+        "int main(void) { unsigned int u = 4000000000u; long l = u; return (int) (l % 251); }",
+        "int main(void) { long big = 1L << 40; int truncated = big >> 33; return truncated; }",
+        "int main(void) { char c = -1; unsigned char uc = c; short s = uc; return s - 200; }",
+        "int main(void) { unsigned long ul = 0ul - 1; return (int) (ul >> 58) + (-1 < 0u); }",
+        "int gcd(int a, int b) { while (b) { int t = a % b; a = b; b = t; } return a; }
+int main(void) { return gcd(1071, 462) + gcd(270, 192); }",
+        "int collatz(long n) { int steps = 0; while (n != 1) { n = n % 2 ? 3 * n + 1 : n / 2; steps++; } return steps; }
+int main(void) { return collatz(27); }",
+        "int main(void) {
+    int sum = 0;
+    for (int i = 0; i < 100; i++) {
+        if (i % 3 == 0) continue;
+        if (i > 50) break;
+        sum += i ^ (i << 1);
+    }
+    return sum % 256;
+}",
+        "static int counter;
+int bump(int by) { counter += by; return counter; }
+int main(void) {
+    int i = 0;
+    do { bump(i); } while (++i < 10);
+    switch (counter) { case 45: return bump(0) + 1; default: return 0; }
+}",
+        "int main(void) { int x = 10; x <<= 3; x |= 5; x &= ~1; x -= 7; x /= 2; x %= 100; return x; }",
+        "int main(void) { short s = 300; signed char sc = s; unsigned short us = -sc; return us % 256 + (sc < 0); }",
+    ];
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn x86_64_exit_codes_agree_with_gcc() {
+        let (temp, _temp_dir) = temp_config_dir();
+        for (index, program) in DIFFERENTIAL_PROGRAMS.iter().enumerate() {
+            let c_file = temp.join(format!("program{}.c", index));
+            let executable = temp.join(format!("program{}", index));
+            File::create(&c_file).unwrap().write_all(program.as_bytes()).unwrap();
+            let compiled = Command::new("gcc").arg("-std=c99").arg("-w").arg("-o").arg(&executable).arg(&c_file).status();
+            match compiled {
+                Ok(status) if status.success() => {}
+                Ok(status) => panic!("gcc failed with {} on {}", status, program),
+                Err(e) => {
+                    warn!("Skipping the comparison with gcc, which could not be run: {}", e);
+                    return;
+                }
+            }
+            let expected = Command::new(&executable).status().unwrap().code().unwrap() as i128;
+            let actual = exit_code(program, TargetPlatform::X86_64) & 0xff;
+            assert_that!((index, actual), eq((index, expected)));
+        }
+    }
+}
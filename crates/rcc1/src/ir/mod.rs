@@ -0,0 +1,260 @@
+//! The three-address intermediate representation (TACKY) between the AST and the code generators.
+//! Every expression is broken down into instructions with at most two operands, each of which is
+//! a constant or a variable, and all control flow is made explicit with labels and jumps. It is
+//! target independent, except that constants and conversions are already sized for the target's
+//! data model.
+//!
+//! The IR does not record the types of its variables: they are in the symbol table, which
+//! generation extends with an entry for every temporary it introduces.
+//!
+//! The textual form produced by 'Display' is what '--dump-ir' prints, e.g.
+//!
+//! ```text
+//! global function main() {
+//!     %1 = add x.1, 2
+//!     jump_if_zero %1, if_else..2
+//!     return %1
+//! if_else..2:
+//!     return 0
+//! }
+//! ```
+
+pub mod generation;
+pub mod interpreter;
+pub mod soft_float;
+
+use std::fmt::{Display, Formatter};
+
+use crate::ast::{Constant, Type};
+
+pub use generation::generate;
+pub use interpreter::run;
+
+/// The name of a label, or other symbol, that the compiler generates rather than takes from the
+/// program, e.g. 'if_else..3', numbered to be unique. The program's identifiers keep their names
+/// or are renamed to 'name.N', and goto labels to 'function.label', none of which has two '.'s.
+pub fn generated_name(purpose: &str, number: usize) -> String {
+    format!("{}..{}", purpose, number)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub top_level: Vec<TopLevel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TopLevel {
+    Function(Function),
+    /// A variable with static storage duration, defined in this module.
+    StaticVariable(StaticVariable),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub name: String,
+    /// Is the function visible to other modules?
+    pub global: bool,
+    pub params: Vec<String>,
+    pub body: Vec<Instruction>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticVariable {
+    pub name: String,
+    pub global: bool,
+    pub variable_type: Type,
+    pub init: Constant,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Constant(Constant),
+    Var(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOperator {
+    Negate,
+    Complement,
+    Not,
+}
+
+/// Arithmetic is performed in the type of the operands, which are always the same promoted type,
+/// except for shifts, where the count may be of a different type. Comparisons give an 'int'. The
+/// operands of the arithmetic operators and comparisons may be floating; those of the others are
+/// always integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    ShiftLeft,
+    ShiftRight,
+    Equal,
+    NotEqual,
+    LessThan,
+    LessOrEqual,
+    GreaterThan,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    Return(Option<Value>),
+    /// Widens a signed value.
+    SignExtend { src: Value, dst: Value },
+    /// Widens an unsigned value.
+    ZeroExtend { src: Value, dst: Value },
+    /// Narrows a value, discarding its high bits. Conversions between integer types of the same
+    /// width are a 'Copy'.
+    Truncate { src: Value, dst: Value },
+    /// Converts a signed integer to the nearest value of a floating type.
+    IntToFloat { src: Value, dst: Value },
+    /// Converts an unsigned integer to the nearest value of a floating type.
+    UIntToFloat { src: Value, dst: Value },
+    /// Converts a floating value to a signed integer, truncating towards zero. The result is
+    /// undefined if the integer part is out of range.
+    FloatToInt { src: Value, dst: Value },
+    /// Converts a floating value to an unsigned integer, truncating towards zero.
+    FloatToUInt { src: Value, dst: Value },
+    /// Widens a 'float' to a 'double', which is exact.
+    FloatExtend { src: Value, dst: Value },
+    /// Rounds a 'double' to the nearest 'float'.
+    FloatTruncate { src: Value, dst: Value },
+    Unary { operator: UnaryOperator, src: Value, dst: Value },
+    Binary { operator: BinaryOperator, src1: Value, src2: Value, dst: Value },
+    Copy { src: Value, dst: Value },
+    Jump(String),
+    /// Jumps if the value compares equal to zero, which for a floating value includes -0.0.
+    JumpIfZero(Value, String),
+    JumpIfNotZero(Value, String),
+    Label(String),
+    /// The destination is absent for functions returning 'void'.
+    FunctionCall { name: String, args: Vec<Value>, dst: Option<Value> },
+}
+
+impl Display for Program {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for top_level in &self.top_level {
+            write!(f, "{}", top_level)?;
+        }
+        Ok(())
+    }
+}
+
+fn visibility(global: bool) -> &'static str {
+    if global {
+        "global "
+    } else {
+        ""
+    }
+}
+
+impl Display for TopLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TopLevel::Function(function) => {
+                writeln!(f, "{}function {}({}) {{", visibility(function.global), function.name, function.params.join(", "))?;
+                for instruction in &function.body {
+                    match instruction {
+                        Instruction::Label(_) => writeln!(f, "{}", instruction)?,
+                        _ => writeln!(f, "    {}", instruction)?,
+                    }
+                }
+                writeln!(f, "}}")
+            }
+            TopLevel::StaticVariable(variable) => writeln!(
+                f,
+                "{}variable {}: {} = {}",
+                visibility(variable.global),
+                variable.name,
+                variable.variable_type,
+                variable.init
+            ),
+        }
+    }
+}
+
+/// Constants of type 'int' are written bare, and all others with their type as a cast, e.g.
+/// '(unsigned long) 10' or '(double) 0.5'.
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Constant(Constant { constant_type: Type::Int, value }) => write!(f, "{}", value),
+            Value::Constant(constant) => write!(f, "({}) {}", constant.constant_type, constant),
+            Value::Var(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl Display for UnaryOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            UnaryOperator::Negate => "negate",
+            UnaryOperator::Complement => "complement",
+            UnaryOperator::Not => "not",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Display for BinaryOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            BinaryOperator::Add => "add",
+            BinaryOperator::Subtract => "subtract",
+            BinaryOperator::Multiply => "multiply",
+            BinaryOperator::Divide => "divide",
+            BinaryOperator::Remainder => "remainder",
+            BinaryOperator::BitwiseAnd => "bitwise_and",
+            BinaryOperator::BitwiseOr => "bitwise_or",
+            BinaryOperator::BitwiseXor => "bitwise_xor",
+            BinaryOperator::ShiftLeft => "shift_left",
+            BinaryOperator::ShiftRight => "shift_right",
+            BinaryOperator::Equal => "equal",
+            BinaryOperator::NotEqual => "not_equal",
+            BinaryOperator::LessThan => "less_than",
+            BinaryOperator::LessOrEqual => "less_or_equal",
+            BinaryOperator::GreaterThan => "greater_than",
+            BinaryOperator::GreaterOrEqual => "greater_or_equal",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instruction::Return(Some(value)) => write!(f, "return {}", value),
+            Instruction::Return(None) => write!(f, "return"),
+            Instruction::SignExtend { src, dst } => write!(f, "{} = sign_extend {}", dst, src),
+            Instruction::ZeroExtend { src, dst } => write!(f, "{} = zero_extend {}", dst, src),
+            Instruction::Truncate { src, dst } => write!(f, "{} = truncate {}", dst, src),
+            Instruction::IntToFloat { src, dst } => write!(f, "{} = int_to_float {}", dst, src),
+            Instruction::UIntToFloat { src, dst } => write!(f, "{} = uint_to_float {}", dst, src),
+            Instruction::FloatToInt { src, dst } => write!(f, "{} = float_to_int {}", dst, src),
+            Instruction::FloatToUInt { src, dst } => write!(f, "{} = float_to_uint {}", dst, src),
+            Instruction::FloatExtend { src, dst } => write!(f, "{} = float_extend {}", dst, src),
+            Instruction::FloatTruncate { src, dst } => write!(f, "{} = float_truncate {}", dst, src),
+            Instruction::Unary { operator, src, dst } => write!(f, "{} = {} {}", dst, operator, src),
+            Instruction::Binary { operator, src1, src2, dst } => write!(f, "{} = {} {}, {}", dst, operator, src1, src2),
+            Instruction::Copy { src, dst } => write!(f, "{} = {}", dst, src),
+            Instruction::Jump(target) => write!(f, "jump {}", target),
+            Instruction::JumpIfZero(condition, target) => write!(f, "jump_if_zero {}, {}", condition, target),
+            Instruction::JumpIfNotZero(condition, target) => write!(f, "jump_if_not_zero {}, {}", condition, target),
+            Instruction::Label(label) => write!(f, "{}:", label),
+            Instruction::FunctionCall { name, args, dst } => {
+                let args: Vec<String> = args.iter().map(Value::to_string).collect();
+                match dst {
+                    Some(dst) => write!(f, "{} = call {}({})", dst, name, args.join(", ")),
+                    None => write!(f, "call {}({})", name, args.join(", ")),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,409 @@
+//! Soft-float lowering, for the targets without floating point hardware: the T425 and the Psion's
+//! 8086/V20. Every floating operation is replaced with a call to a routine of the soft-float
+//! runtime, 'runtime/softfloat.c', which is written in the C that rcc itself compiles, so that the
+//! code generators only ever see integers.
+//!
+//! A 'float' is held in an 'unsigned long', 32 bits on both targets, as its IEEE 754 bits. A
+//! 'double' is held in two: the variable itself holds the high word, with the sign and exponent,
+//! and a companion variable named 'name.low' holds the low word. A 'double' is passed as two
+//! arguments, high word first, and returned with its high word as the function's value and its
+//! low word in the runtime's static variable '__double_low', which the caller copies straight
+//! after the call.
+//!
+//! The routines are named and behave as those of libgcc: e.g. '__addsf3' adds two floats, and
+//! '__ltdf2' compares two doubles, giving a negative result if the first is less. Conversions
+//! between floating types and integers go through 'long' or 'unsigned long'.
+
+use common::data_model::DataModel;
+
+use crate::ast::{Constant, Type};
+use crate::ir::generation::conversion;
+use crate::ir::{BinaryOperator, Function, Instruction, Program, StaticVariable, TopLevel, UnaryOperator, Value};
+use crate::semantic::symbol_table::{IdentifierAttributes, InitialValue, Symbol, SymbolTable};
+
+/// The runtime's variable holding the low word of a returned 'double'.
+pub const DOUBLE_LOW: &str = "__double_low";
+
+const SIGN_BIT: i128 = 0x8000_0000;
+
+/// The name of the variable holding the low word of a 'double'.
+fn low(name: &str) -> String {
+    format!("{}.low", name)
+}
+
+/// The names of the variables holding a variable of the given type after lowering.
+fn word_names(name: &str, variable_type: &Type) -> Vec<String> {
+    match variable_type {
+        Type::Double => vec![name.to_owned(), low(name)],
+        _ => vec![name.to_owned()],
+    }
+}
+
+/// The words a value of the given type is held in after lowering.
+fn word_types(value_type: &Type) -> Vec<Type> {
+    match value_type {
+        Type::Float => vec![Type::UnsignedLong],
+        Type::Double => vec![Type::UnsignedLong, Type::UnsignedLong],
+        _ => vec![value_type.clone()],
+    }
+}
+
+/// The words of a constant, high word first.
+fn constant_words(constant: &Constant) -> Vec<Constant> {
+    let word = |value: i128| Constant { constant_type: Type::UnsignedLong, value };
+    match constant.constant_type {
+        Type::Float => vec![word(constant.value)],
+        Type::Double => vec![word(constant.value >> 32), word(constant.value & 0xffff_ffff)],
+        _ => vec![constant.clone()],
+    }
+}
+
+fn lowered_type(symbol_type: &Type) -> Type {
+    match symbol_type {
+        Type::Function { params, return_type } => Type::Function {
+            params: params.iter().flat_map(word_types).collect(),
+            return_type: Box::new(word_types(return_type).remove(0)),
+        },
+        _ => word_types(symbol_type).remove(0),
+    }
+}
+
+/// The routine suffix for operands of a floating type, as in libgcc: 'sf' for single precision
+/// and 'df' for double.
+fn mode(floating_type: &Type) -> &'static str {
+    match floating_type {
+        Type::Float => "sf",
+        _ => "df",
+    }
+}
+
+struct Lowering<'a> {
+    /// The symbol table as it was before lowering, which still has the floating types.
+    original: SymbolTable,
+    symbols: &'a mut SymbolTable,
+    data_model: &'a DataModel,
+    instructions: Vec<Instruction>,
+    /// Numbers new temporaries, following on from those of IR generation.
+    counter: usize,
+}
+
+/// Lowers the floating operations of a program to calls to the soft-float runtime, and rewrites
+/// the symbol table to hold floating values in integers. A program without floating point is
+/// unchanged.
+pub fn lower(program: &Program, symbols: &mut SymbolTable, data_model: &DataModel) -> Program {
+    let original = symbols.clone();
+    let counter = original
+        .iter()
+        .filter_map(|(name, _)| name.strip_prefix('%').and_then(|number| number.parse().ok()))
+        .max()
+        .unwrap_or(0);
+    for (name, symbol) in original.iter() {
+        let initial_words = match &symbol.attributes {
+            IdentifierAttributes::Static { initial_value: InitialValue::Initial(constant), .. } => {
+                constant_words(constant).into_iter().map(Some).collect()
+            }
+            _ => vec![None; 2],
+        };
+        for (word_name, initial) in word_names(name, &symbol.symbol_type).iter().zip(initial_words) {
+            let attributes = match (&symbol.attributes, initial) {
+                (IdentifierAttributes::Static { global, .. }, Some(constant)) => {
+                    IdentifierAttributes::Static { initial_value: InitialValue::Initial(constant), global: *global }
+                }
+                (attributes, _) => attributes.clone(),
+            };
+            symbols.insert(word_name, Symbol { symbol_type: lowered_type(&symbol.symbol_type), attributes });
+        }
+    }
+
+    let mut lowering = Lowering { original, symbols, data_model, instructions: vec![], counter };
+    let top_level = program
+        .top_level
+        .iter()
+        .flat_map(|top_level| match top_level {
+            TopLevel::Function(function) => vec![TopLevel::Function(lowering.function(function))],
+            TopLevel::StaticVariable(variable) => lowering.static_variable(variable),
+        })
+        .collect();
+    Program { top_level }
+}
+
+impl Lowering<'_> {
+    fn emit(&mut self, instruction: Instruction) {
+        self.instructions.push(instruction);
+    }
+
+    /// The type of a value before lowering.
+    fn original_type(&self, value: &Value) -> Type {
+        match value {
+            Value::Constant(constant) => constant.constant_type.clone(),
+            Value::Var(name) => self.original.get(name).expect("every variable is in the symbol table").symbol_type.clone(),
+        }
+    }
+
+    /// The type of a value after lowering.
+    fn value_type(&self, value: &Value) -> Type {
+        match value {
+            Value::Constant(constant) => constant.constant_type.clone(),
+            Value::Var(name) => self.symbols.get(name).expect("every variable is in the symbol table").symbol_type.clone(),
+        }
+    }
+
+    fn is_floating(&self, value: &Value) -> bool {
+        self.original_type(value).is_floating()
+    }
+
+    /// The words holding a value: one for an integer or a 'float', and two for a 'double', high
+    /// word first.
+    fn words(&self, value: &Value) -> Vec<Value> {
+        match value {
+            Value::Constant(constant) => constant_words(constant).into_iter().map(Value::Constant).collect(),
+            Value::Var(name) if self.original_type(value) == Type::Double => {
+                vec![value.clone(), Value::Var(low(name))]
+            }
+            Value::Var(_) => vec![value.clone()],
+        }
+    }
+
+    fn temporary(&mut self, temporary_type: &Type) -> Value {
+        self.counter += 1;
+        let name = format!("%{}", self.counter);
+        self.symbols.insert(&name, Symbol { symbol_type: temporary_type.clone(), attributes: IdentifierAttributes::Local });
+        Value::Var(name)
+    }
+
+    /// The integer value converted to the given integer type, in a new temporary unless it
+    /// already has it.
+    fn convert(&mut self, value: Value, to: &Type) -> Value {
+        let from = self.value_type(&value);
+        match value {
+            _ if from == *to => value,
+            Value::Constant(constant) => Value::Constant(constant.convert(to, self.data_model)),
+            Value::Var(_) => {
+                let dst = self.temporary(to);
+                self.emit(conversion(value, dst.clone(), &from, to, self.data_model));
+                dst
+            }
+        }
+    }
+
+    /// Calls a routine of the runtime, declaring it if the program has not, and puts its result
+    /// in the destination's words.
+    fn call(&mut self, name: &str, args: Vec<Value>, return_type: Type, dst: Vec<Value>) {
+        if self.symbols.get(name).is_none() {
+            let params = args.iter().map(|arg| self.value_type(arg)).collect();
+            self.symbols.insert(name, Symbol {
+                symbol_type: Type::Function { params, return_type: Box::new(return_type) },
+                attributes: IdentifierAttributes::Function { defined: false, global: true },
+            });
+        }
+        self.emit_call(name, args, dst);
+    }
+
+    /// Emits a call, copying the low word of a 'double' result from where the callee left it.
+    fn emit_call(&mut self, name: &str, args: Vec<Value>, dst: Vec<Value>) {
+        let mut dst = dst.into_iter();
+        self.emit(Instruction::FunctionCall { name: name.to_owned(), args, dst: dst.next() });
+        if let Some(low) = dst.next() {
+            let src = self.double_low();
+            self.emit(Instruction::Copy { src, dst: low });
+        }
+    }
+
+    fn double_low(&mut self) -> Value {
+        if self.symbols.get(DOUBLE_LOW).is_none() {
+            self.symbols.insert(DOUBLE_LOW, Symbol {
+                symbol_type: Type::UnsignedLong,
+                attributes: IdentifierAttributes::Static { initial_value: InitialValue::NoInitialiser, global: true },
+            });
+        }
+        Value::Var(DOUBLE_LOW.to_owned())
+    }
+
+    /// Calls one of the runtime's comparisons, giving an 'int' that compares with zero as the
+    /// operands compare with each other.
+    fn compare(&mut self, operator: &str, src1: &Value, src2: &Value) -> Value {
+        let name = format!("__{}{}2", operator, mode(&self.original_type(src1)));
+        let mut args = self.words(src1);
+        args.extend(self.words(src2));
+        let result = self.temporary(&Type::Int);
+        self.call(&name, args, Type::Int, vec![result.clone()]);
+        result
+    }
+
+    fn zero(&self, floating_type: &Type) -> Value {
+        Value::Constant(Constant::int(0).convert(floating_type, self.data_model))
+    }
+
+    fn function(&mut self, function: &Function) -> Function {
+        for instruction in &function.body {
+            self.instruction(instruction);
+        }
+        let params = function
+            .params
+            .iter()
+            .flat_map(|param| word_names(param, &self.original_type(&Value::Var(param.clone()))))
+            .collect();
+        Function {
+            params,
+            body: std::mem::take(&mut self.instructions),
+            ..function.clone()
+        }
+    }
+
+    fn static_variable(&self, variable: &StaticVariable) -> Vec<TopLevel> {
+        word_names(&variable.name, &variable.variable_type)
+            .into_iter()
+            .zip(constant_words(&variable.init))
+            .map(|(name, init)| {
+                TopLevel::StaticVariable(StaticVariable {
+                    name,
+                    variable_type: init.constant_type.clone(),
+                    init,
+                    ..variable.clone()
+                })
+            })
+            .collect()
+    }
+
+    fn instruction(&mut self, instruction: &Instruction) {
+        match instruction {
+            Instruction::Return(Some(value)) if self.is_floating(value) => {
+                let mut words = self.words(value).into_iter();
+                let high = words.next();
+                if let Some(low) = words.next() {
+                    let dst = self.double_low();
+                    self.emit(Instruction::Copy { src: low, dst });
+                }
+                self.emit(Instruction::Return(high));
+            }
+            Instruction::Copy { src, dst } if self.is_floating(dst) => {
+                for (src, dst) in self.words(src).into_iter().zip(self.words(dst)) {
+                    self.emit(Instruction::Copy { src, dst });
+                }
+            }
+            Instruction::IntToFloat { src, dst } | Instruction::UIntToFloat { src, dst } => {
+                let (name, long_type) = match instruction {
+                    Instruction::IntToFloat { .. } => ("floatsi", Type::Long),
+                    _ => ("floatunsi", Type::UnsignedLong),
+                };
+                let src = self.convert(src.clone(), &long_type);
+                let name = format!("__{}{}", name, mode(&self.original_type(dst)));
+                self.call(&name, vec![src], Type::UnsignedLong, self.words(dst));
+            }
+            Instruction::FloatToInt { src, dst } | Instruction::FloatToUInt { src, dst } => {
+                let (name, long_type) = match instruction {
+                    Instruction::FloatToInt { .. } => ("fix", Type::Long),
+                    _ => ("fixuns", Type::UnsignedLong),
+                };
+                let name = format!("__{}{}si", name, mode(&self.original_type(src)));
+                let result = self.temporary(&long_type);
+                self.call(&name, self.words(src), long_type.clone(), vec![result.clone()]);
+                let dst_type = self.value_type(dst);
+                self.emit(conversion(result, dst.clone(), &long_type, &dst_type, self.data_model));
+            }
+            Instruction::FloatExtend { src, dst } => {
+                self.call("__extendsfdf2", self.words(src), Type::UnsignedLong, self.words(dst));
+            }
+            Instruction::FloatTruncate { src, dst } => {
+                self.call("__truncdfsf2", self.words(src), Type::UnsignedLong, self.words(dst));
+            }
+            // Negation only flips the sign bit, in the high word.
+            Instruction::Unary { operator: UnaryOperator::Negate, src, dst } if self.is_floating(src) => {
+                let mut words = self.words(src).into_iter().zip(self.words(dst));
+                let (high, dst_high) = words.next().expect("a high word");
+                let sign = Value::Constant(Constant { constant_type: Type::UnsignedLong, value: SIGN_BIT });
+                self.emit(Instruction::Binary { operator: BinaryOperator::BitwiseXor, src1: high, src2: sign, dst: dst_high });
+                for (src, dst) in words {
+                    self.emit(Instruction::Copy { src, dst });
+                }
+            }
+            Instruction::Unary { operator: UnaryOperator::Not, src, dst } if self.is_floating(src) => {
+                let zero = self.zero(&self.original_type(src));
+                let compared = self.compare("eq", src, &zero);
+                self.emit(Instruction::Binary {
+                    operator: BinaryOperator::Equal,
+                    src1: compared,
+                    src2: Value::Constant(Constant::int(0)),
+                    dst: dst.clone(),
+                });
+            }
+            Instruction::Binary { operator, src1, src2, dst } if self.is_floating(src1) => {
+                let arithmetic = match operator {
+                    BinaryOperator::Add => Some("add"),
+                    BinaryOperator::Subtract => Some("sub"),
+                    BinaryOperator::Multiply => Some("mul"),
+                    BinaryOperator::Divide => Some("div"),
+                    _ => None,
+                };
+                match arithmetic {
+                    Some(name) => {
+                        let name = format!("__{}{}3", name, mode(&self.original_type(src1)));
+                        let mut args = self.words(src1);
+                        args.extend(self.words(src2));
+                        self.call(&name, args, Type::UnsignedLong, self.words(dst));
+                    }
+                    None => {
+                        let name = match operator {
+                            BinaryOperator::Equal => "eq",
+                            BinaryOperator::NotEqual => "ne",
+                            BinaryOperator::LessThan => "lt",
+                            BinaryOperator::LessOrEqual => "le",
+                            BinaryOperator::GreaterThan => "gt",
+                            BinaryOperator::GreaterOrEqual => "ge",
+                            _ => unreachable!("type checking rejects floating operands of '{}'", operator),
+                        };
+                        let compared = self.compare(name, src1, src2);
+                        self.emit(Instruction::Binary {
+                            operator: *operator,
+                            src1: compared,
+                            src2: Value::Constant(Constant::int(0)),
+                            dst: dst.clone(),
+                        });
+                    }
+                }
+            }
+            // '__nesf2' gives zero only if the value equals zero, which -0.0 does.
+            Instruction::JumpIfZero(condition, target) if self.is_floating(condition) => {
+                let zero = self.zero(&self.original_type(condition));
+                let compared = self.compare("ne", condition, &zero);
+                self.emit(Instruction::JumpIfZero(compared, target.clone()));
+            }
+            Instruction::JumpIfNotZero(condition, target) if self.is_floating(condition) => {
+                let zero = self.zero(&self.original_type(condition));
+                let compared = self.compare("ne", condition, &zero);
+                self.emit(Instruction::JumpIfNotZero(compared, target.clone()));
+            }
+            Instruction::FunctionCall { name, args, dst } => {
+                let args = args.iter().flat_map(|arg| self.words(arg)).collect();
+                let dst = dst.iter().flat_map(|dst| self.words(dst)).collect();
+                self.emit_call(name, args, dst);
+            }
+            _ => self.emit(instruction.clone()),
+        }
+    }
+}
+
+/// The runtime's source as rcc1 sees it.
+#[cfg(test)]
+pub(crate) fn runtime_source() -> String {
+    preprocessed(include_str!("../../runtime/softfloat.c"))
+}
+
+/// A runtime's source as rcc1 sees it, i.e. preprocessed, which only removes its comments.
+#[cfg(test)]
+pub(crate) fn preprocessed(source: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(start) = rest.find("/*") {
+        output.push_str(&rest[..start]);
+        output.push(' ');
+        let end = rest[start + 2..].find("*/").expect("a terminated comment");
+        rest = &rest[start + 2 + end + 2..];
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+#[path = "./soft_float_spec.rs"]
+mod soft_float_spec;
@@ -0,0 +1,374 @@
+mod soft_float_spec {
+    use std::sync::OnceLock;
+
+    use common::data_model::DataModel;
+    use common::target_platform::TargetPlatform;
+    use hamcrest2::prelude::*;
+
+    use crate::ir::soft_float::{lower, runtime_source, DOUBLE_LOW};
+    use crate::ir::{interpreter, run, Program};
+    use crate::program_test_helper::ir_of;
+    use crate::semantic::symbol_table::SymbolTable;
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn lowered(input: &str, target_platform: TargetPlatform) -> (Program, SymbolTable) {
+        let (ir, mut symbols) = ir_of(input, target_platform);
+        let ir = lower(&ir, &mut symbols, &target_platform.data_model());
+        (ir, symbols)
+    }
+
+    struct Runtime {
+        data_model: DataModel,
+        program: Program,
+        symbols: SymbolTable,
+    }
+
+    static TRANSPUTER_RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    static EPOC16_RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+    /// The runtime, compiled for a target by rcc itself.
+    fn runtime(target_platform: TargetPlatform) -> &'static Runtime {
+        let cell = match target_platform {
+            TargetPlatform::EPOC16 => &EPOC16_RUNTIME,
+            _ => &TRANSPUTER_RUNTIME,
+        };
+        cell.get_or_init(|| {
+            let data_model = target_platform.data_model();
+            let (program, symbols) = lowered(&runtime_source(), target_platform);
+            Runtime { data_model, program, symbols }
+        })
+    }
+
+    impl Runtime {
+        /// Calls a routine with 32 bit arguments, giving its result and the final value of
+        /// '__double_low'.
+        fn call(&self, name: &str, args: &[i128]) -> (i128, i128) {
+            let (value, statics) = interpreter::call(&self.program, &self.symbols, &self.data_model, name, args.to_vec())
+                .unwrap_or_else(|e| panic!("{}: {}", name, e));
+            (value, statics[DOUBLE_LOW])
+        }
+
+        fn float(&self, name: &str, args: &[f32]) -> f32 {
+            let args: Vec<i128> = args.iter().map(|f| f.to_bits() as i128).collect();
+            f32::from_bits(self.call(name, &args).0 as u32)
+        }
+
+        fn double(&self, name: &str, args: &[f64]) -> f64 {
+            let args: Vec<i128> = args.iter().flat_map(|d| double_words(*d)).collect();
+            let (high, low) = self.call(name, &args);
+            f64::from_bits((high as u32 as u64) << 32 | low as u32 as u64)
+        }
+
+        fn compare_floats(&self, name: &str, a: f32, b: f32) -> i128 {
+            self.call(name, &[a.to_bits() as i128, b.to_bits() as i128]).0
+        }
+
+        fn compare_doubles(&self, name: &str, a: f64, b: f64) -> i128 {
+            let mut args = double_words(a);
+            args.extend(double_words(b));
+            self.call(name, &args).0
+        }
+    }
+
+    fn double_words(d: f64) -> Vec<i128> {
+        let bits = d.to_bits();
+        vec![(bits >> 32) as i128, (bits & 0xffff_ffff) as i128]
+    }
+
+    /// Values at the edges of single precision: zeros, infinities, a NaN, subnormals, the
+    /// extremes of the normal range, and some that do not have exact representations.
+    fn special_floats() -> Vec<f32> {
+        vec![
+            0.0, -0.0, 1.0, -1.0, 0.1, -2.5, 3.0, 1.5, 16_777_217.0, 1e10, -1e-10,
+            f32::MAX, f32::MIN, f32::MIN_POSITIVE, -f32::MIN_POSITIVE,
+            f32::from_bits(1), f32::from_bits(0x807f_ffff), f32::from_bits(0x0040_0000),
+            f32::INFINITY, f32::NEG_INFINITY, f32::NAN,
+        ]
+    }
+
+    fn special_doubles() -> Vec<f64> {
+        vec![
+            0.0, -0.0, 1.0, -1.0, 0.1, -2.5, 3.0, 1.5, 9_007_199_254_740_993.0, 1e300, -1e-300,
+            f64::MAX, f64::MIN, f64::MIN_POSITIVE, -f64::MIN_POSITIVE,
+            f64::from_bits(1), f64::from_bits(0x800f_ffff_ffff_ffff), f64::from_bits(0x0008_0000_0000_0000),
+            f64::INFINITY, f64::NEG_INFINITY, f64::NAN,
+        ]
+    }
+
+    /// A xorshift generator, so that the random operands are the same on every run.
+    struct Random(u64);
+
+    impl Random {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// Any bit pattern, or, as often, one with an exponent near that of one, so that the
+        /// operands of additions overlap.
+        fn float(&mut self) -> f32 {
+            let bits = self.next() as u32;
+            match bits & 1 {
+                0 => f32::from_bits(bits),
+                _ => f32::from_bits((bits & 0x807f_ffff) | (0x3f00_0000 + ((bits >> 8) & 0x0f00_0000))),
+            }
+        }
+
+        fn double(&mut self) -> f64 {
+            let bits = self.next();
+            match bits & 1 {
+                0 => f64::from_bits(bits),
+                _ => f64::from_bits((bits & 0x800f_ffff_ffff_ffff) | (0x3fe0_0000_0000_0000 + ((bits >> 8) & 0x00e0_0000_0000_0000))),
+            }
+        }
+    }
+
+    fn float_operands(count: usize) -> Vec<(f32, f32)> {
+        let specials = special_floats();
+        let mut random = Random(0x2545_f491_4f6c_dd1d);
+        let mut operands: Vec<(f32, f32)> = specials.iter().flat_map(|a| specials.iter().map(move |b| (*a, *b))).collect();
+        operands.extend((0..count).map(|_| (random.float(), random.float())));
+        operands
+    }
+
+    fn double_operands(count: usize) -> Vec<(f64, f64)> {
+        let specials = special_doubles();
+        let mut random = Random(0x9e37_79b9_7f4a_7c15);
+        let mut operands: Vec<(f64, f64)> = specials.iter().flat_map(|a| specials.iter().map(move |b| (*a, *b))).collect();
+        operands.extend((0..count).map(|_| (random.double(), random.double())));
+        operands
+    }
+
+    /// Whether two results are the same bits, or are both NaNs, whose payloads IEEE 754 leaves
+    /// open.
+    fn same_float(actual: f32, expected: f32) -> bool {
+        actual.to_bits() == expected.to_bits() || (actual.is_nan() && expected.is_nan())
+    }
+
+    fn same_double(actual: f64, expected: f64) -> bool {
+        actual.to_bits() == expected.to_bits() || (actual.is_nan() && expected.is_nan())
+    }
+
+    /// A routine of the runtime and the host's equivalent.
+    type Operation<T> = (&'static str, fn(T, T) -> T);
+
+    /// A comparison, whether its result means it holds, and the host's equivalent.
+    type Relation = (&'static str, fn(i128) -> bool, fn(f64, f64) -> bool);
+
+    fn float_arithmetic(target_platform: TargetPlatform) {
+        let runtime = runtime(target_platform);
+        let operations: [Operation<f32>; 4] = [
+            ("__addsf3", |a, b| a + b),
+            ("__subsf3", |a, b| a - b),
+            ("__mulsf3", |a, b| a * b),
+            ("__divsf3", |a, b| a / b),
+        ];
+        for (a, b) in float_operands(300) {
+            for (name, operation) in operations {
+                let actual = runtime.float(name, &[a, b]);
+                let expected = operation(a, b);
+                assert!(same_float(actual, expected), "{}({:e}, {:e}) gave {:e} ({:#010x}), not {:e} ({:#010x})",
+                    name, a, b, actual, actual.to_bits(), expected, expected.to_bits());
+            }
+        }
+    }
+
+    fn double_arithmetic(target_platform: TargetPlatform) {
+        let runtime = runtime(target_platform);
+        let operations: [Operation<f64>; 4] = [
+            ("__adddf3", |a, b| a + b),
+            ("__subdf3", |a, b| a - b),
+            ("__muldf3", |a, b| a * b),
+            ("__divdf3", |a, b| a / b),
+        ];
+        for (a, b) in double_operands(150) {
+            for (name, operation) in operations {
+                let actual = runtime.double(name, &[a, b]);
+                let expected = operation(a, b);
+                assert!(same_double(actual, expected), "{}({:e}, {:e}) gave {:e} ({:#018x}), not {:e} ({:#018x})",
+                    name, a, b, actual, actual.to_bits(), expected, expected.to_bits());
+            }
+        }
+    }
+
+    /// The libgcc comparisons give a result that compares with zero as the operands compare with
+    /// each other, or that makes the comparison false if either is a NaN.
+    fn comparisons(target_platform: TargetPlatform) {
+        let runtime = runtime(target_platform);
+        let relations: [Relation; 6] = [
+            ("eq", |r| r == 0, |a, b| a == b),
+            ("ne", |r| r != 0, |a, b| a != b),
+            ("lt", |r| r < 0, |a, b| a < b),
+            ("le", |r| r <= 0, |a, b| a <= b),
+            ("gt", |r| r > 0, |a, b| a > b),
+            ("ge", |r| r >= 0, |a, b| a >= b),
+        ];
+        for (a, b) in float_operands(50) {
+            for (name, holds, expected) in relations {
+                let result = runtime.compare_floats(&format!("__{}sf2", name), a, b);
+                assert_that!((name, a.to_bits(), b.to_bits(), holds(result)), eq((name, a.to_bits(), b.to_bits(), expected(a as f64, b as f64))));
+            }
+        }
+        for (a, b) in double_operands(50) {
+            for (name, holds, expected) in relations {
+                let result = runtime.compare_doubles(&format!("__{}df2", name), a, b);
+                assert_that!((name, a.to_bits(), b.to_bits(), holds(result)), eq((name, a.to_bits(), b.to_bits(), expected(a, b))));
+            }
+        }
+    }
+
+    fn conversions(target_platform: TargetPlatform) {
+        let runtime = runtime(target_platform);
+        let mut random = Random(0x1234_5678_9abc_def1);
+        let mut integers: Vec<u32> = vec![0, 1, 2, 3, 0x7fff_ffff, 0x8000_0000, 0x8000_0001, 0xffff_ffff, 16_777_217, 0x7fff_ffc0];
+        integers.extend((0..100).map(|_| (random.next() >> (random.next() % 32)) as u32));
+        for i in integers {
+            let (signed, unsigned) = (i as i32, i);
+            assert_that!((i, runtime.call("__floatsisf", &[signed as i128]).0 as u32), eq((i, (signed as f32).to_bits())));
+            assert_that!((i, runtime.call("__floatunsisf", &[unsigned as i128]).0 as u32), eq((i, (unsigned as f32).to_bits())));
+            let (high, low) = runtime.call("__floatsidf", &[signed as i128]);
+            assert_that!((i, (high as u32 as u64) << 32 | low as u32 as u64), eq((i, (signed as f64).to_bits())));
+            let (high, low) = runtime.call("__floatunsidf", &[unsigned as i128]);
+            assert_that!((i, (high as u32 as u64) << 32 | low as u32 as u64), eq((i, (unsigned as f64).to_bits())));
+        }
+
+        let mut floats = special_floats();
+        floats.extend([2_147_483_520.0, 2_147_483_648.0, -2_147_483_648.0, 4_294_967_040.0, 4_294_967_296.0, -0.75, 0.999_999_94]);
+        floats.extend((0..100).map(|_| random.float() * 1e9));
+        for f in floats {
+            let bits = f.to_bits() as i128;
+            assert_that!((bits, runtime.call("__fixsfsi", &[bits]).0), eq((bits, (f as i32) as i128)));
+            assert_that!((bits, runtime.call("__fixunssfsi", &[bits]).0), eq((bits, (f as u32) as i128)));
+            let (high, low) = runtime.call("__extendsfdf2", &[bits]);
+            let extended = f64::from_bits((high as u32 as u64) << 32 | low as u32 as u64);
+            assert!(same_double(extended, f as f64), "__extendsfdf2({:e}) gave {:e}", f, extended);
+        }
+
+        let mut doubles = special_doubles();
+        doubles.extend([2_147_483_647.5, 2_147_483_648.0, -2_147_483_648.5, -2_147_483_649.0, 4_294_967_295.5, -0.75]);
+        doubles.extend([f32::MAX as f64 * 1.000_000_1, f32::MIN_POSITIVE as f64 / 3.0, 1.000_000_059_604_644_8]);
+        doubles.extend((0..100).map(|_| random.double() * 1e9));
+        for d in doubles {
+            let words = double_words(d);
+            assert_that!((d.to_bits(), runtime.call("__fixdfsi", &words).0), eq((d.to_bits(), (d as i32) as i128)));
+            assert_that!((d.to_bits(), runtime.call("__fixunsdfsi", &words).0), eq((d.to_bits(), (d as u32) as i128)));
+            let truncated = f32::from_bits(runtime.call("__truncdfsf2", &words).0 as u32);
+            assert!(same_float(truncated, d as f32), "__truncdfsf2({:e}) gave {:e}", d, truncated);
+        }
+    }
+
+    #[test]
+    fn transputer_float_arithmetic_is_ieee_754() {
+        float_arithmetic(TargetPlatform::Transputer);
+    }
+
+    #[test]
+    fn transputer_double_arithmetic_is_ieee_754() {
+        double_arithmetic(TargetPlatform::Transputer);
+    }
+
+    #[test]
+    fn transputer_comparisons_are_ieee_754() {
+        comparisons(TargetPlatform::Transputer);
+    }
+
+    #[test]
+    fn transputer_conversions_are_ieee_754() {
+        conversions(TargetPlatform::Transputer);
+    }
+
+    #[test]
+    fn epoc16_float_arithmetic_is_ieee_754() {
+        float_arithmetic(TargetPlatform::EPOC16);
+    }
+
+    #[test]
+    fn epoc16_double_arithmetic_is_ieee_754() {
+        double_arithmetic(TargetPlatform::EPOC16);
+    }
+
+    #[test]
+    fn epoc16_comparisons_are_ieee_754() {
+        comparisons(TargetPlatform::EPOC16);
+    }
+
+    #[test]
+    fn epoc16_conversions_are_ieee_754() {
+        conversions(TargetPlatform::EPOC16);
+    }
+
+    #[test]
+    fn floating_operations_become_calls() {
+        let input = "float f; double d;
+int main(void) { f = f * 2.0f; d = f; return d < 1.0 && !f; }";
+        let (ir, symbols) = lowered(input, TargetPlatform::Transputer);
+        let ir = ir.to_string();
+        for name in ["__mulsf3", "__extendsfdf2", "__ltdf2", "__eqsf2"] {
+            assert_that!((name, ir.contains(name)), eq((name, true)));
+        }
+        assert_that!(ir.contains("int_to_float") || ir.contains("float_extend"), eq(false));
+        assert_that!(symbols.get("d.low").map(|symbol| symbol.symbol_type.to_string()), eq(Some("unsigned long".to_owned())));
+        assert_that!(symbols.get(DOUBLE_LOW).is_some(), eq(true));
+    }
+
+    #[test]
+    fn a_program_without_floating_point_is_unchanged() {
+        let input = "long total; int main(void) { total = total * 3; return total > 2; }";
+        let (ir, _) = ir_of(input, TargetPlatform::EPOC16);
+        let (lowered, _) = lowered(input, TargetPlatform::EPOC16);
+        assert_that!(lowered.to_string(), eq(ir.to_string()));
+    }
+
+    /// A program gives the same result interpreted with floating point as it does lowered to
+    /// integers and built with the runtime.
+    fn same_result_lowered(input: &str, target_platform: TargetPlatform) -> i128 {
+        let data_model = target_platform.data_model();
+        let (ir, symbols) = ir_of(input, target_platform);
+        let expected = run(&ir, &symbols, &data_model).unwrap();
+        let (ir, symbols) = lowered(&format!("{}\n{}", runtime_source(), input), target_platform);
+        assert_that!(run(&ir, &symbols, &data_model).unwrap(), eq(expected));
+        expected
+    }
+
+    #[test]
+    fn lowered_programs_give_the_same_results() {
+        let input = "double square(double x) { return x * x; }
+float scaled(float f, int n) { return f * n - 0.25f; }
+double total = 0.5;
+int main(void) {
+    int i;
+    float f = 1.0f / 3;
+    double d = f;
+    for (i = 1; i <= 10; i++)
+        total = total + square(i) / 7;
+    if (!(total > 0.0) || d == 0.0)
+        return -1;
+    return (int) (total * 3) + (int) scaled(f, 9) * 1000 + (-d < 0) * 10000;
+}";
+        for target_platform in [TargetPlatform::Transputer, TargetPlatform::EPOC16] {
+            assert_that!(same_result_lowered(input, target_platform), eq(12_166));
+        }
+    }
+
+    #[test]
+    fn lowered_conversions_give_the_same_results() {
+        let input = "unsigned char clamp(double d) { return d; }
+int main(void) {
+    long l = -100000L;
+    unsigned long u = 3000000000ul;
+    double d = l;
+    float f = u;
+    signed char c = -3;
+    return (d / c > 33333.0) + ((unsigned long) f == u) * 2 + (clamp(200.7) == 200) * 4 + ((long) (float) l == l) * 8;
+}";
+        for target_platform in [TargetPlatform::Transputer, TargetPlatform::EPOC16] {
+            assert_that!(same_result_lowered(input, target_platform), eq(15));
+        }
+    }
+}
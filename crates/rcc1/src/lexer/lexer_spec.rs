@@ -3,6 +3,7 @@ mod lexer_spec {
     use log::{error, info};
     use chumsky::prelude::*;
     use hamcrest2::prelude::*;
+    use crate::ast::Literal;
     use crate::lexer::{lexer, Token};
 
     #[ctor::ctor]
@@ -35,9 +36,132 @@ mod lexer_spec {
             Token::Rparen,
             Token::Lbrace,
             Token::Keyword(String::from("return")),
-            Token::Constant(2),
+            Token::Constant(Literal::Decimal(2)),
             Token::Semicolon,
             Token::Rbrace,
         ]));
     }
-}
\ No newline at end of file
+
+    fn lex(input: &str) -> Vec<Token> {
+        let (tokens, errs) = lexer().parse(input).into_output_errors();
+        errs.into_iter().for_each(|e| error!("{:?}", e));
+        tokens.unwrap()
+    }
+
+    fn lex_fails(input: &str) -> bool {
+        !lexer().parse(input).into_output_errors().1.is_empty()
+    }
+
+    #[test]
+    fn longest_operator_wins() {
+        assert_that!(lex("<<= << <= < -- -> -= -"), eq(vec![
+            Token::LeftShiftAssign,
+            Token::LeftShift,
+            Token::LessEqual,
+            Token::Less,
+            Token::Decrement,
+            Token::Arrow,
+            Token::MinusAssign,
+            Token::Minus,
+        ]));
+    }
+
+    #[test]
+    fn operators_without_whitespace() {
+        assert_that!(lex("a+=b?c:d;"), eq(vec![
+            Token::Identifier(String::from("a")),
+            Token::PlusAssign,
+            Token::Identifier(String::from("b")),
+            Token::Question,
+            Token::Identifier(String::from("c")),
+            Token::Colon,
+            Token::Identifier(String::from("d")),
+            Token::Semicolon,
+        ]));
+    }
+
+    #[test]
+    fn keywords_are_whole_words() {
+        assert_that!(lex("goto gotoer integer int"), eq(vec![
+            Token::Keyword(String::from("goto")),
+            Token::Identifier(String::from("gotoer")),
+            Token::Identifier(String::from("integer")),
+            Token::Keyword(String::from("int")),
+        ]));
+    }
+
+    #[test]
+    fn decimal_octal_and_hexadecimal_constants() {
+        assert_that!(lex("0 42 017 0x1F 0XfF"), eq(vec![
+            Token::Constant(Literal::Decimal(0)),
+            Token::Constant(Literal::Decimal(42)),
+            Token::Constant(Literal::OctalOrHexadecimal(15)),
+            Token::Constant(Literal::OctalOrHexadecimal(31)),
+            Token::Constant(Literal::OctalOrHexadecimal(255)),
+        ]));
+    }
+
+    #[test]
+    fn integer_suffixes() {
+        assert_that!(lex("1u 2L 3ul 4LU 0x5uL 4294967296"), eq(vec![
+            Token::Constant(Literal::Unsigned(1)),
+            Token::Constant(Literal::Long(2)),
+            Token::Constant(Literal::UnsignedLong(3)),
+            Token::Constant(Literal::UnsignedLong(4)),
+            Token::Constant(Literal::UnsignedLong(5)),
+            Token::Constant(Literal::Decimal(4294967296)),
+        ]));
+    }
+
+    #[test]
+    fn invalid_suffixes() {
+        assert_that!(lex_fails("1uu"), eq(true));
+        assert_that!(lex_fails("1ll"), eq(true));
+    }
+
+    #[test]
+    fn constant_running_into_identifier() {
+        assert_that!(lex_fails("123abc"), eq(true));
+    }
+
+    #[test]
+    fn constant_too_large() {
+        assert_that!(lex_fails("18446744073709551616"), eq(true));
+    }
+
+    #[test]
+    fn floating_constants() {
+        assert_that!(lex("1.5 .25 3. 1e3 2.5E-2 0.1f 1e+2L"), eq(vec![
+            Token::Constant(Literal::Double(1.5f64.to_bits())),
+            Token::Constant(Literal::Double(0.25f64.to_bits())),
+            Token::Constant(Literal::Double(3.0f64.to_bits())),
+            Token::Constant(Literal::Double(1000.0f64.to_bits())),
+            Token::Constant(Literal::Double(0.025f64.to_bits())),
+            Token::Constant(Literal::Float(0.1f32.to_bits())),
+            Token::Constant(Literal::Double(100.0f64.to_bits())),
+        ]));
+    }
+
+    #[test]
+    fn member_access_is_not_a_floating_constant() {
+        assert_that!(lex("a.b"), eq(vec![
+            Token::Identifier(String::from("a")),
+            Token::Dot,
+            Token::Identifier(String::from("b")),
+        ]));
+    }
+
+    #[test]
+    fn invalid_floating_constants() {
+        assert_that!(lex_fails("1e"), eq(true));
+        assert_that!(lex_fails("1.5u"), eq(true));
+        assert_that!(lex_fails("1.0ff"), eq(true));
+        assert_that!(lex_fails("1e39f"), eq(true));
+        assert_that!(lex_fails("1e309"), eq(true));
+    }
+
+    #[test]
+    fn invalid_character() {
+        assert_that!(lex_fails("int @"), eq(true));
+    }
+}
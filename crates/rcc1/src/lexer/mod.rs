@@ -1,31 +1,173 @@
 use chumsky::prelude::*;
 
+use crate::ast::Literal;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
-    Lparen, Rparen, Lbrace, Rbrace, Semicolon, Keyword(String), Identifier(String), Constant(u32)
+    Lparen, Rparen, Lbrace, Rbrace, Lbracket, Rbracket, Semicolon, Comma, Colon, Question,
+    Tilde, Bang, Plus, Minus, Asterisk, Slash, Percent, Ampersand, Pipe, Caret,
+    LeftShift, RightShift, LogicalAnd, LogicalOr,
+    Less, Greater, LessEqual, GreaterEqual, EqualEqual, BangEqual,
+    Assign, PlusAssign, MinusAssign, AsteriskAssign, SlashAssign, PercentAssign,
+    AmpersandAssign, PipeAssign, CaretAssign, LeftShiftAssign, RightShiftAssign,
+    Increment, Decrement, Dot, Arrow, Ellipsis,
+    Keyword(String), Identifier(String), Constant(Literal)
 }
 
+/// All C89 keywords are reserved by the lexer, even those the parser does not yet understand, so
+/// that they can never be mistaken for identifiers.
+pub const KEYWORDS: [&str; 32] = [
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+    "enum", "extern", "float", "for", "goto", "if", "int", "long", "register", "return", "short",
+    "signed", "sizeof", "static", "struct", "switch", "typedef", "union", "unsigned", "void",
+    "volatile", "while",
+];
+
 // TODO the lexer should take a Stream not a &str as input.
 pub fn lexer<'src>() -> impl Parser<'src, &'src str, Vec<Token>, extra::Err<Rich<'src, char>>> {
-    let lparen = just('(').to(Token::Lparen).padded();
-    let rparen = just(')').to(Token::Rparen).padded();
-    let lbrace = just('{').to(Token::Lbrace).padded();
-    let rbrace = just('}').to(Token::Rbrace).padded();
-    let semicolon = just(';').to(Token::Semicolon).padded();
-    let keyword = text::keyword("int").or(text::keyword("void")).or(text::keyword("return")).map(|x: &str| Token::Keyword(x.to_owned()));
-    let identifier = text::ascii::ident().map(|x: &str| Token::Identifier(x.to_owned())).padded();
-    let constant = text::int(10)
-        .map(|s: &str| Token::Constant(s.parse().unwrap()))
-        .padded();
-
-    let all_tokens = choice((lparen, rparen, lbrace, rbrace, semicolon, keyword, identifier, constant))
-        .repeated()
-        .collect();
+    // Longest operators first, so that e.g. '<<=' is not lexed as '<' '<='.
+    let three_char_operators = choice((
+        just("<<=").to(Token::LeftShiftAssign),
+        just(">>=").to(Token::RightShiftAssign),
+        just("...").to(Token::Ellipsis),
+    ));
+    let two_char_operators = choice((
+        just("->").to(Token::Arrow),
+        just("++").to(Token::Increment),
+        just("--").to(Token::Decrement),
+        just("<<").to(Token::LeftShift),
+        just(">>").to(Token::RightShift),
+        just("<=").to(Token::LessEqual),
+        just(">=").to(Token::GreaterEqual),
+        just("==").to(Token::EqualEqual),
+        just("!=").to(Token::BangEqual),
+        just("&&").to(Token::LogicalAnd),
+        just("||").to(Token::LogicalOr),
+        just("+=").to(Token::PlusAssign),
+        just("-=").to(Token::MinusAssign),
+        just("*=").to(Token::AsteriskAssign),
+        just("/=").to(Token::SlashAssign),
+        just("%=").to(Token::PercentAssign),
+        just("&=").to(Token::AmpersandAssign),
+        just("|=").to(Token::PipeAssign),
+        just("^=").to(Token::CaretAssign),
+    ));
+    let one_char_operators = choice((
+        just('(').to(Token::Lparen),
+        just(')').to(Token::Rparen),
+        just('{').to(Token::Lbrace),
+        just('}').to(Token::Rbrace),
+        just('[').to(Token::Lbracket),
+        just(']').to(Token::Rbracket),
+        just(';').to(Token::Semicolon),
+        just(',').to(Token::Comma),
+        just(':').to(Token::Colon),
+        just('?').to(Token::Question),
+        just('~').to(Token::Tilde),
+        just('!').to(Token::Bang),
+        just('+').to(Token::Plus),
+        just('-').to(Token::Minus),
+        just('*').to(Token::Asterisk),
+        just('/').to(Token::Slash),
+        just('%').to(Token::Percent),
+        just('&').to(Token::Ampersand),
+        just('|').to(Token::Pipe),
+        just('^').to(Token::Caret),
+        just('<').to(Token::Less),
+        just('>').to(Token::Greater),
+        just('=').to(Token::Assign),
+        just('.').to(Token::Dot),
+    ));
+
+    let identifier_or_keyword = text::ascii::ident().map(|x: &str| {
+        if KEYWORDS.contains(&x) {
+            Token::Keyword(x.to_owned())
+        } else {
+            Token::Identifier(x.to_owned())
+        }
+    });
 
-    all_tokens
+    // A constant must not run straight into an identifier or another constant, e.g. '123abc' and
+    // '1.5u' are invalid.
+    let word_boundary = any()
+        .filter(|c: &char| c.is_ascii_alphanumeric() || *c == '_' || *c == '.')
+        .not();
+    let hexadecimal = just('0')
+        .ignore_then(one_of("xX"))
+        .ignore_then(text::digits(16).at_least(1).to_slice())
+        .map(|s: &str| (s, 16));
+    let octal = just('0')
+        .then(text::digits(8).at_least(1))
+        .to_slice()
+        .map(|s: &str| (s, 8));
+    let decimal = text::int(10).map(|s: &str| (s, 10));
+    let unsigned = one_of("uU").to(true);
+    let long = one_of("lL").to(true);
+    let suffix = choice((
+        unsigned.then(long.or_not().map(Option::unwrap_or_default)),
+        long.then(unsigned.or_not().map(Option::unwrap_or_default)).map(|(long, unsigned)| (unsigned, long)),
+    ))
+    .or_not()
+    .map(Option::unwrap_or_default);
+    let constant = choice((hexadecimal, octal, decimal))
+        .then(suffix)
+        .then_ignore(word_boundary)
+        .try_map(|((s, radix), (unsigned, long)), span| {
+            let value = u64::from_str_radix(s, radix)
+                .map_err(|_| Rich::custom(span, format!("integer constant '{}' is too large", s)))?;
+            Ok(Token::Constant(match (unsigned, long) {
+                (false, false) if radix == 10 => Literal::Decimal(value),
+                (false, false) => Literal::OctalOrHexadecimal(value),
+                (true, false) => Literal::Unsigned(value),
+                (false, true) => Literal::Long(value),
+                (true, true) => Literal::UnsignedLong(value),
+            }))
+        });
+
+    // A floating constant has a fraction, an exponent, or both, and is rounded to its type here
+    // (C89 6.1.3.1). 'l' makes it a 'long double', which is the same as 'double'. Its value must
+    // be representable, i.e. not overflow to infinity.
+    let exponent = one_of("eE").then(one_of("+-").or_not()).then(text::digits(10).at_least(1));
+    let fraction = choice((
+        text::digits(10).at_least(1).then(just('.')).then(text::digits(10).or_not()).ignored(),
+        just('.').then(text::digits(10).at_least(1)).ignored(),
+    ));
+    let floating_constant = choice((
+        fraction.then(exponent.or_not()).ignored(),
+        text::digits(10).at_least(1).then(exponent).ignored(),
+    ))
+    .to_slice()
+    .then(one_of("fFlL").or_not())
+    .then_ignore(word_boundary)
+    .try_map(|(s, suffix): (&str, Option<char>), span| {
+        let out_of_range = || Rich::custom(span, format!("floating constant '{}' is out of range", s));
+        match suffix {
+            Some('f' | 'F') => {
+                let value: f32 = s.parse().expect("lexed as a floating constant");
+                value.is_finite().then(|| Token::Constant(Literal::Float(value.to_bits()))).ok_or_else(out_of_range)
+            }
+            _ => {
+                let value: f64 = s.parse().expect("lexed as a floating constant");
+                value.is_finite().then(|| Token::Constant(Literal::Double(value.to_bits()))).ok_or_else(out_of_range)
+            }
+        }
+    });
+
+    // Floating constants come before the operators, as they may start with a '.'.
+    choice((
+        three_char_operators,
+        two_char_operators,
+        floating_constant,
+        one_char_operators,
+        identifier_or_keyword,
+        constant,
+    ))
+        .padded()
+        .repeated()
+        .collect()
+        .padded()
 }
 
 #[cfg(test)]
 #[path = "./lexer_spec.rs"]
 mod lexer_spec;
-
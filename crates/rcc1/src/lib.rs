@@ -1,5 +1,11 @@
 pub mod ast;
+pub mod codegen;
 pub mod command_line;
 pub mod compiler;
+pub mod ir;
 pub mod lexer;
 pub mod parser;
+pub mod semantic;
+
+#[cfg(test)]
+pub mod program_test_helper;
@@ -1,13 +1,572 @@
-use crate::ast::AST;
+use crate::ast::{
+    BinaryOperator, Block, BlockItem, Declaration, EnumDeclaration, Enumerator, Expression,
+    ForInit, FunctionDeclaration, Program, Statement, StorageClass, Type, UnaryOperator,
+    VariableDeclaration,
+};
 use crate::lexer::Token;
-use chumsky::input::Input;
+use chumsky::input::ValueInput;
 use chumsky::prelude::*;
 
-pub fn parser<'a, I>() -> impl Parser<'a, I, AST, extra::Err<Rich<'a, Token>>>
+type ParserExtra<'a> = extra::Err<Rich<'a, Token>>;
+
+fn keyword<'a, I>(word: &str) -> impl Parser<'a, I, (), ParserExtra<'a>> + Clone
+where
+    I: ValueInput<'a, Token = Token, Span = SimpleSpan>,
+{
+    just(Token::Keyword(word.to_owned())).ignored()
+}
+
+fn identifier<'a, I>() -> impl Parser<'a, I, String, ParserExtra<'a>> + Clone
+where
+    I: ValueInput<'a, Token = Token, Span = SimpleSpan>,
+{
+    select! { Token::Identifier(name) => name }
+}
+
+fn binary(operator: BinaryOperator, left: Expression, right: Expression) -> Expression {
+    Expression::Binary(operator, Box::new(left), Box::new(right))
+}
+
+pub fn expression<'a, I>() -> impl Parser<'a, I, Expression, ParserExtra<'a>> + Clone
+where
+    I: ValueInput<'a, Token = Token, Span = SimpleSpan>,
+{
+    recursive(|expression| {
+        let arguments = expression
+            .clone()
+            .separated_by(just(Token::Comma))
+            .collect::<Vec<_>>()
+            .delimited_by(just(Token::Lparen), just(Token::Rparen));
+
+        let primary = choice((
+            select! { Token::Constant(literal) => Expression::Literal(literal) },
+            identifier()
+                .then(arguments.or_not())
+                .map(|(name, arguments)| match arguments {
+                    Some(arguments) => Expression::FunctionCall(name, arguments),
+                    None => Expression::Var(name),
+                }),
+            expression
+                .clone()
+                .delimited_by(just(Token::Lparen), just(Token::Rparen)),
+        ));
+
+        let postfix_operator = choice((
+            just(Token::Increment).to(UnaryOperator::PostIncrement),
+            just(Token::Decrement).to(UnaryOperator::PostDecrement),
+        ));
+        let postfix = primary.foldl(postfix_operator.repeated(), |operand, operator| {
+            Expression::Unary(operator, Box::new(operand))
+        });
+
+        let prefix_operator = choice((
+            just(Token::Minus).to(UnaryOperator::Negate),
+            just(Token::Tilde).to(UnaryOperator::Complement),
+            just(Token::Bang).to(UnaryOperator::Not),
+            just(Token::Increment).to(UnaryOperator::PreIncrement),
+            just(Token::Decrement).to(UnaryOperator::PreDecrement),
+        ));
+        // Casts are included here, as they bind as tightly as the prefix operators. Each
+        // alternative is rejected by its first token or two, so trying them in turn is cheap.
+        let parenthesised_type_name = type_name().delimited_by(just(Token::Lparen), just(Token::Rparen));
+        let unary = recursive(|unary| {
+            choice((
+                keyword("sizeof")
+                    .ignore_then(parenthesised_type_name.clone())
+                    .map(Expression::SizeOfType),
+                keyword("sizeof")
+                    .ignore_then(unary.clone())
+                    .map(|operand| Expression::SizeOfExpression(Box::new(operand))),
+                parenthesised_type_name
+                    .then(unary.clone())
+                    .map(|(target, operand)| Expression::Cast(target, Box::new(operand))),
+                prefix_operator
+                    .then(unary)
+                    .map(|(operator, operand)| Expression::Unary(operator, Box::new(operand))),
+                postfix,
+            ))
+        })
+        .boxed();
+
+        // Each precedence level, from tightest binding to loosest, is a left-associative fold over
+        // the level below.
+        let multiplicative = unary.clone().foldl(
+            choice((
+                just(Token::Asterisk).to(BinaryOperator::Multiply),
+                just(Token::Slash).to(BinaryOperator::Divide),
+                just(Token::Percent).to(BinaryOperator::Remainder),
+            ))
+            .then(unary)
+            .repeated(),
+            |left, (operator, right)| binary(operator, left, right),
+        ).boxed();
+        let additive = multiplicative.clone().foldl(
+            choice((
+                just(Token::Plus).to(BinaryOperator::Add),
+                just(Token::Minus).to(BinaryOperator::Subtract),
+            ))
+            .then(multiplicative)
+            .repeated(),
+            |left, (operator, right)| binary(operator, left, right),
+        ).boxed();
+        let shift = additive.clone().foldl(
+            choice((
+                just(Token::LeftShift).to(BinaryOperator::ShiftLeft),
+                just(Token::RightShift).to(BinaryOperator::ShiftRight),
+            ))
+            .then(additive)
+            .repeated(),
+            |left, (operator, right)| binary(operator, left, right),
+        ).boxed();
+        let relational = shift.clone().foldl(
+            choice((
+                just(Token::Less).to(BinaryOperator::LessThan),
+                just(Token::LessEqual).to(BinaryOperator::LessOrEqual),
+                just(Token::Greater).to(BinaryOperator::GreaterThan),
+                just(Token::GreaterEqual).to(BinaryOperator::GreaterOrEqual),
+            ))
+            .then(shift)
+            .repeated(),
+            |left, (operator, right)| binary(operator, left, right),
+        ).boxed();
+        let equality = relational.clone().foldl(
+            choice((
+                just(Token::EqualEqual).to(BinaryOperator::Equal),
+                just(Token::BangEqual).to(BinaryOperator::NotEqual),
+            ))
+            .then(relational)
+            .repeated(),
+            |left, (operator, right)| binary(operator, left, right),
+        ).boxed();
+        let bitwise_and = equality.clone().foldl(
+            just(Token::Ampersand).to(BinaryOperator::BitwiseAnd).then(equality).repeated(),
+            |left, (operator, right)| binary(operator, left, right),
+        ).boxed();
+        let bitwise_xor = bitwise_and.clone().foldl(
+            just(Token::Caret).to(BinaryOperator::BitwiseXor).then(bitwise_and).repeated(),
+            |left, (operator, right)| binary(operator, left, right),
+        ).boxed();
+        let bitwise_or = bitwise_xor.clone().foldl(
+            just(Token::Pipe).to(BinaryOperator::BitwiseOr).then(bitwise_xor).repeated(),
+            |left, (operator, right)| binary(operator, left, right),
+        ).boxed();
+        let logical_and = bitwise_or.clone().foldl(
+            just(Token::LogicalAnd).to(BinaryOperator::And).then(bitwise_or).repeated(),
+            |left, (operator, right)| binary(operator, left, right),
+        ).boxed();
+        let logical_or = logical_and.clone().foldl(
+            just(Token::LogicalOr).to(BinaryOperator::Or).then(logical_and).repeated(),
+            |left, (operator, right)| binary(operator, left, right),
+        ).boxed();
+
+        // Conditional and assignment expressions are right-associative. Any expression is
+        // accepted on the left of an assignment here; semantic analysis rejects invalid lvalues.
+        let conditional = recursive(|conditional| {
+            logical_or
+                .then(
+                    just(Token::Question)
+                        .ignore_then(expression.clone())
+                        .then_ignore(just(Token::Colon))
+                        .then(conditional)
+                        .or_not(),
+                )
+                .map(|(condition, branches)| match branches {
+                    Some((then, otherwise)) => Expression::Conditional(
+                        Box::new(condition),
+                        Box::new(then),
+                        Box::new(otherwise),
+                    ),
+                    None => condition,
+                })
+        });
+
+        let assignment_operator = choice((
+            just(Token::Assign).to(None),
+            just(Token::PlusAssign).to(Some(BinaryOperator::Add)),
+            just(Token::MinusAssign).to(Some(BinaryOperator::Subtract)),
+            just(Token::AsteriskAssign).to(Some(BinaryOperator::Multiply)),
+            just(Token::SlashAssign).to(Some(BinaryOperator::Divide)),
+            just(Token::PercentAssign).to(Some(BinaryOperator::Remainder)),
+            just(Token::AmpersandAssign).to(Some(BinaryOperator::BitwiseAnd)),
+            just(Token::PipeAssign).to(Some(BinaryOperator::BitwiseOr)),
+            just(Token::CaretAssign).to(Some(BinaryOperator::BitwiseXor)),
+            just(Token::LeftShiftAssign).to(Some(BinaryOperator::ShiftLeft)),
+            just(Token::RightShiftAssign).to(Some(BinaryOperator::ShiftRight)),
+        ));
+        conditional
+            .then(assignment_operator.then(expression).or_not())
+            .map(|(left, assignment)| match assignment {
+                Some((None, right)) => Expression::Assignment(Box::new(left), Box::new(right)),
+                Some((Some(operator), right)) => {
+                    Expression::CompoundAssignment(operator, Box::new(left), Box::new(right))
+                }
+                None => left,
+            })
+            .boxed()
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Specifier {
+    /// One of the type specifier keywords, other than 'enum'.
+    Type(&'static str),
+    /// An enumerated type, which defines its enumerators if it has a body.
+    Enum(Option<EnumDeclaration>),
+    StorageClass(StorageClass),
+}
+
+struct Specifiers {
+    specifier_type: Type,
+    storage_class: Option<StorageClass>,
+    enum_declaration: Option<EnumDeclaration>,
+}
+
+const TYPE_KEYWORDS: [&str; 9] = ["void", "char", "short", "int", "long", "float", "double", "signed", "unsigned"];
+
+/// Any type or storage class specifier, except an enum with a body. Enum bodies contain
+/// expressions, and are not allowed in type names, so that expressions can contain casts.
+fn simple_specifier<'a, I>() -> impl Parser<'a, I, Specifier, ParserExtra<'a>> + Clone
+where
+    I: ValueInput<'a, Token = Token, Span = SimpleSpan>,
+{
+    choice((
+        select! { Token::Keyword(word) if TYPE_KEYWORDS.contains(&word.as_str()) => word }.map(|word| {
+            Specifier::Type(TYPE_KEYWORDS.into_iter().find(|keyword| *keyword == word).expect("is a type keyword"))
+        }),
+        keyword("enum")
+            .ignore_then(identifier())
+            .then_ignore(just(Token::Lbrace).not().rewind())
+            .to(Specifier::Enum(None)),
+        keyword("auto").to(Specifier::StorageClass(StorageClass::Auto)),
+        keyword("register").to(Specifier::StorageClass(StorageClass::Register)),
+        keyword("static").to(Specifier::StorageClass(StorageClass::Static)),
+        keyword("extern").to(Specifier::StorageClass(StorageClass::Extern)),
+    ))
+}
+
+fn enum_definition<'a, I>() -> impl Parser<'a, I, Specifier, ParserExtra<'a>> + Clone
+where
+    I: ValueInput<'a, Token = Token, Span = SimpleSpan>,
+{
+    let enumerator = identifier()
+        .then(just(Token::Assign).ignore_then(expression()).or_not())
+        .map(|(name, value)| Enumerator { name, value });
+    keyword("enum")
+        .ignore_then(identifier().or_not())
+        .then(
+            enumerator
+                .separated_by(just(Token::Comma))
+                .at_least(1)
+                .collect::<Vec<_>>()
+                .delimited_by(just(Token::Lbrace), just(Token::Rbrace)),
+        )
+        .map(|(tag, enumerators)| Specifier::Enum(Some(EnumDeclaration { tag, enumerators })))
+}
+
+/// The C89 combinations of type specifier keywords, in any order, and the types they name.
+fn specified_type(keywords: &[&str], enums: usize) -> Option<Type> {
+    let count = |word: &str| keywords.iter().filter(|keyword| **keyword == word).count();
+    if keywords.iter().any(|keyword| count(keyword) > 1) {
+        return None;
+    }
+    let (signed, unsigned) = (count("signed") == 1, count("unsigned") == 1);
+    let mut base: Vec<&str> = keywords
+        .iter()
+        .copied()
+        .filter(|keyword| *keyword != "signed" && *keyword != "unsigned")
+        .collect();
+    base.sort();
+    let sign = |plain: Type, unsigned_type: Type| match (signed, unsigned) {
+        (true, true) => None,
+        (_, false) => Some(plain),
+        (false, true) => Some(unsigned_type),
+    };
+    match (base.as_slice(), enums) {
+        ([], 1) if !signed && !unsigned => Some(Type::Int),
+        (_, 0) => match base.as_slice() {
+            ["void"] if !signed && !unsigned => Some(Type::Void),
+            ["float"] if !signed && !unsigned => Some(Type::Float),
+            // 'long double' has the same representation as 'double' on all targets.
+            ["double"] | ["double", "long"] if !signed && !unsigned => Some(Type::Double),
+            ["char"] if signed && !unsigned => Some(Type::SignedChar),
+            ["char"] => sign(Type::Char, Type::UnsignedChar),
+            ["short"] | ["int", "short"] => sign(Type::Short, Type::UnsignedShort),
+            ["long"] | ["int", "long"] => sign(Type::Long, Type::UnsignedLong),
+            ["int"] => sign(Type::Int, Type::UnsignedInt),
+            [] if signed || unsigned => sign(Type::Int, Type::UnsignedInt),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The type and storage class specifiers may appear in any order, but they must name exactly one
+/// type, and there may be at most one storage class.
+fn specifiers<'a, I, S>(specifier: S) -> impl Parser<'a, I, Specifiers, ParserExtra<'a>> + Clone
+where
+    I: ValueInput<'a, Token = Token, Span = SimpleSpan>,
+    S: Parser<'a, I, Specifier, ParserExtra<'a>> + Clone,
+{
+    specifier
+        .repeated()
+        .at_least(1)
+        .collect::<Vec<_>>()
+        .try_map(|specifiers, span| {
+            let mut keywords = vec![];
+            let mut enums = 0;
+            let mut enum_declaration = None;
+            let mut storage_classes = vec![];
+            for specifier in specifiers {
+                match specifier {
+                    Specifier::Type(keyword) => keywords.push(keyword),
+                    Specifier::Enum(declaration) => {
+                        enums += 1;
+                        enum_declaration = declaration;
+                    }
+                    Specifier::StorageClass(storage_class) => storage_classes.push(storage_class),
+                }
+            }
+            let specifier_type = specified_type(&keywords, enums).ok_or_else(|| Rich::custom(span, "invalid type specifier"))?;
+            if storage_classes.len() > 1 {
+                return Err(Rich::custom(span, "invalid storage class"));
+            }
+            Ok(Specifiers { specifier_type, storage_class: storage_classes.first().copied(), enum_declaration })
+        })
+}
+
+/// The type in a cast or 'sizeof'.
+fn type_name<'a, I>() -> impl Parser<'a, I, Type, ParserExtra<'a>> + Clone
+where
+    I: ValueInput<'a, Token = Token, Span = SimpleSpan>,
+{
+    specifiers(simple_specifier()).try_map(|specifiers, span| match specifiers.storage_class {
+        None => Ok(specifiers.specifier_type),
+        Some(_) => Err(Rich::custom(span, "invalid type name")),
+    })
+}
+
+#[derive(Debug, Clone)]
+enum Declarator {
+    Function(Vec<(Type, String)>),
+    Variable(Option<Expression>),
+}
+
+fn declarations(specifiers: Specifiers, declarators: Vec<(String, Declarator)>, body: Option<Block>) -> Vec<Declaration> {
+    let Specifiers { specifier_type, storage_class, enum_declaration } = specifiers;
+    let mut declarations: Vec<Declaration> = enum_declaration.into_iter().map(Declaration::Enum).collect();
+    for (name, declarator) in declarators {
+        declarations.push(match declarator {
+            Declarator::Function(params) => {
+                let (param_types, params) = params.into_iter().unzip();
+                Declaration::Function(FunctionDeclaration {
+                    name,
+                    params,
+                    body: body.clone(),
+                    function_type: Type::Function { params: param_types, return_type: Box::new(specifier_type.clone()) },
+                    storage_class,
+                })
+            }
+            Declarator::Variable(init) => Declaration::Variable(VariableDeclaration {
+                name,
+                init,
+                variable_type: specifier_type.clone(),
+                storage_class,
+            }),
+        });
+    }
+    declarations
+}
+
+/// A declaration may declare several functions and variables, e.g. 'int a, b = 2, f(void);', and
+/// may also define an enum's constants.
+pub fn declaration<'a, I>() -> impl Parser<'a, I, Vec<Declaration>, ParserExtra<'a>> + Clone
+where
+    I: ValueInput<'a, Token = Token, Span = SimpleSpan>,
+{
+    recursive(|declaration| {
+        let block = block(declaration);
+
+        // 'register' is the only storage class allowed for a parameter, and has no effect. Only a
+        // function definition needs the parameters' names, so a prototype may leave them out.
+        let param = specifiers(simple_specifier())
+            .then(identifier().or_not())
+            .try_map(|(specifiers, name), span| match (specifiers.storage_class, specifiers.specifier_type) {
+                (Some(StorageClass::Auto | StorageClass::Static | StorageClass::Extern), _) => {
+                    Err(Rich::custom(span, "invalid storage class for parameter"))
+                }
+                (_, Type::Void) => Err(Rich::custom(span, "parameter has void type")),
+                (_, param_type) => Ok((param_type, name)),
+            });
+        let params = choice((
+            keyword("void").to(vec![]),
+            param.separated_by(just(Token::Comma)).at_least(1).collect::<Vec<_>>(),
+        ))
+        .or_not()
+        .map(Option::unwrap_or_default)
+        .delimited_by(just(Token::Lparen), just(Token::Rparen));
+
+        let named_params = params.clone().try_map(|params, span| {
+            params
+                .into_iter()
+                .map(|(param_type, name)| name.map(|name| (param_type, name)))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| Rich::custom(span, "parameter name omitted"))
+        });
+        let function_definition = identifier()
+            .then(named_params)
+            .then(block)
+            .map(|((name, params), body)| (vec![(name, Declarator::Function(params))], Some(body)));
+        let declarator = identifier().then(choice((
+            params.map(|params| {
+                Declarator::Function(
+                    params.into_iter().map(|(param_type, name)| (param_type, name.unwrap_or_default())).collect(),
+                )
+            }),
+            just(Token::Assign).ignore_then(expression()).or_not().map(Declarator::Variable),
+        )));
+        let declarators = declarator
+            .separated_by(just(Token::Comma))
+            .collect::<Vec<_>>()
+            .then_ignore(just(Token::Semicolon))
+            .map(|declarators| (declarators, None));
+
+        specifiers(choice((enum_definition(), simple_specifier())))
+            .then(choice((function_definition, declarators)))
+            .try_map(|(specifiers, (declarators, body)), span| {
+                if declarators.is_empty() && specifiers.enum_declaration.is_none() {
+                    return Err(Rich::custom(span, "declaration does not declare anything"));
+                }
+                if specifiers.specifier_type == Type::Void
+                    && declarators.iter().any(|(_, declarator)| matches!(declarator, Declarator::Variable(_)))
+                {
+                    return Err(Rich::custom(span, "variable declared void"));
+                }
+                Ok(declarations(specifiers, declarators, body))
+            })
+            .boxed()
+    })
+}
+
+fn block<'a, I, D>(declaration: D) -> impl Parser<'a, I, Block, ParserExtra<'a>> + Clone
+where
+    I: ValueInput<'a, Token = Token, Span = SimpleSpan>,
+    D: Parser<'a, I, Vec<Declaration>, ParserExtra<'a>> + Clone + 'a,
+{
+    recursive(|block| {
+        let parenthesised_expression =
+            expression().delimited_by(just(Token::Lparen), just(Token::Rparen));
+        let semicolon = just(Token::Semicolon);
+
+        let statement = recursive(|statement| {
+            let return_statement = keyword("return")
+                .ignore_then(expression().or_not())
+                .then_ignore(semicolon.clone())
+                .map(Statement::Return);
+            let if_statement = keyword("if")
+                .ignore_then(parenthesised_expression.clone())
+                .then(statement.clone())
+                .then(keyword("else").ignore_then(statement.clone()).or_not())
+                .map(|((condition, then), otherwise)| Statement::If {
+                    condition,
+                    then: Box::new(then),
+                    otherwise: otherwise.map(Box::new),
+                });
+            let switch_statement = keyword("switch")
+                .ignore_then(parenthesised_expression.clone())
+                .then(statement.clone())
+                .map(|(condition, body)| Statement::Switch { condition, body: Box::new(body) });
+            let case_statement = keyword("case")
+                .ignore_then(expression())
+                .then_ignore(just(Token::Colon))
+                .then(statement.clone())
+                .map(|(label, statement)| Statement::Case(label, Box::new(statement)));
+            let default_statement = keyword("default")
+                .ignore_then(just(Token::Colon))
+                .ignore_then(statement.clone())
+                .map(|statement| Statement::Default(Box::new(statement)));
+            let goto_statement = keyword("goto")
+                .ignore_then(identifier())
+                .then_ignore(semicolon.clone())
+                .map(Statement::Goto);
+            let labelled_statement = identifier()
+                .then_ignore(just(Token::Colon))
+                .then(statement.clone())
+                .map(|(label, statement)| Statement::Labelled(label, Box::new(statement)));
+            let break_statement = keyword("break").then_ignore(semicolon.clone()).to(Statement::Break);
+            let continue_statement = keyword("continue").then_ignore(semicolon.clone()).to(Statement::Continue);
+            let while_statement = keyword("while")
+                .ignore_then(parenthesised_expression.clone())
+                .then(statement.clone())
+                .map(|(condition, body)| Statement::While { condition, body: Box::new(body) });
+            let do_while_statement = keyword("do")
+                .ignore_then(statement.clone())
+                .then_ignore(keyword("while"))
+                .then(parenthesised_expression.clone())
+                .then_ignore(semicolon.clone())
+                .map(|(body, condition)| Statement::DoWhile { body: Box::new(body), condition });
+            let for_init = choice((
+                declaration.clone().try_map(|declarations: Vec<Declaration>, span| match declarations.as_slice() {
+                    [Declaration::Variable(variable)] => Ok(ForInit::Declaration(variable.clone())),
+                    _ => Err(Rich::custom(span, "invalid declaration in for loop initialiser")),
+                }),
+                expression().or_not().then_ignore(semicolon.clone()).map(ForInit::Expression),
+            ));
+            let for_statement = keyword("for")
+                .ignore_then(just(Token::Lparen))
+                .ignore_then(for_init)
+                .then(expression().or_not())
+                .then_ignore(semicolon.clone())
+                .then(expression().or_not())
+                .then_ignore(just(Token::Rparen))
+                .then(statement.clone())
+                .map(|(((init, condition), post), body)| Statement::For {
+                    init,
+                    condition,
+                    post,
+                    body: Box::new(body),
+                });
+            let expression_statement = expression().then_ignore(semicolon.clone()).map(Statement::Expression);
+
+            choice((
+                return_statement,
+                if_statement,
+                switch_statement,
+                case_statement,
+                default_statement,
+                goto_statement,
+                labelled_statement,
+                break_statement,
+                continue_statement,
+                while_statement,
+                do_while_statement,
+                for_statement,
+                block.clone().map(Statement::Compound),
+                semicolon.clone().to(Statement::Null),
+                expression_statement,
+            ))
+            .boxed()
+        });
+
+        choice((
+            declaration.map(|declarations| declarations.into_iter().map(BlockItem::Declaration).collect()),
+            statement.map(|statement| vec![BlockItem::Statement(statement)]),
+        ))
+        .repeated()
+        .collect::<Vec<Vec<_>>>()
+        .delimited_by(just(Token::Lbrace), just(Token::Rbrace))
+        .map(|items| Block { items: items.into_iter().flatten().collect() })
+        .boxed()
+    })
+}
+
+pub fn parser<'a, I>() -> impl Parser<'a, I, Program, ParserExtra<'a>>
 where
-    I: Input<'a, Token = Token, Span = SimpleSpan>,
+    I: ValueInput<'a, Token = Token, Span = SimpleSpan>,
 {
-    just(Token::Lparen).to(AST::Dummy)
+    declaration()
+        .repeated()
+        .collect::<Vec<_>>()
+        .map(|declarations| Program { declarations: declarations.into_iter().flatten().collect() })
 }
 
 #[cfg(test)]
@@ -3,8 +3,10 @@ mod parser_spec {
     use chumsky::{
         input::Stream,
         prelude::*,
-    }; 
-    use crate::lexer::Token;
+    };
+    use hamcrest2::prelude::*;
+    use crate::ast::{BinaryOperator, Block, BlockItem, Declaration, EnumDeclaration, Enumerator, Expression, FunctionDeclaration, Literal, Program, Statement, StorageClass, Type, UnaryOperator, VariableDeclaration};
+    use crate::lexer::{lexer, Token};
     use crate::parser::parser;
 
     #[ctor::ctor]
@@ -22,17 +24,392 @@ mod parser_spec {
             Token::Rparen,
             Token::Lbrace,
             Token::Keyword(String::from("return")),
-            Token::Constant(2),
+            Token::Constant(Literal::Decimal(2)),
             Token::Semicolon,
             Token::Rbrace,
         ];
 
-        let stream = Stream::from_iter(tokens.into_iter());
+        let stream = Stream::from_iter(tokens);
 
         let parser = parser();
         let (ast, errs) = parser.parse(stream).into_output_errors();
         info!("AST output of parser: {:#?}", ast);
         errs.into_iter().for_each(|e| error!("{:?}", e));
+        assert_that!(ast.unwrap(), eq(Program {
+            declarations: vec![Declaration::Function(FunctionDeclaration {
+                name: "main".to_owned(),
+                params: vec![],
+                body: Some(Block { items: vec![BlockItem::Statement(Statement::Return(Some(Expression::Literal(Literal::Decimal(2)))))] }),
+                function_type: Type::Function { params: vec![], return_type: Box::new(Type::Int) },
+                storage_class: None,
+            })],
+        }));
+    }
+
+    fn parse(input: &str) -> Program {
+        let tokens = lexer().parse(input).into_result().unwrap();
+        let (ast, errs) = parser().parse(tokens.as_slice()).into_output_errors();
+        errs.into_iter().for_each(|e| error!("{:?}", e));
+        ast.unwrap()
+    }
+
+    fn function(program: &Program, index: usize) -> FunctionDeclaration {
+        match &program.declarations[index] {
+            Declaration::Function(function) => function.clone(),
+            other => panic!("not a function: {:?}", other),
+        }
+    }
+
+    fn body_of_main(input: &str) -> Vec<BlockItem> {
+        function(&parse(&format!("int main(void) {{ {} }}", input)), 0).body.unwrap().items
+    }
+
+    fn returned_expression(input: &str) -> Expression {
+        match &body_of_main(&format!("return {};", input))[0] {
+            BlockItem::Statement(Statement::Return(Some(expression))) => expression.clone(),
+            other => panic!("not a return statement: {:?}", other),
+        }
+    }
+
+    fn constant(value: u64) -> Box<Expression> {
+        Box::new(Expression::Literal(Literal::Decimal(value)))
+    }
+
+    fn var(name: &str) -> Box<Expression> {
+        Box::new(Expression::Var(name.to_owned()))
+    }
+
+    #[test]
+    fn precedence_of_multiplication_over_addition() {
+        assert_that!(returned_expression("1 + 2 * 3"), eq(Expression::Binary(
+            BinaryOperator::Add,
+            constant(1),
+            Box::new(Expression::Binary(BinaryOperator::Multiply, constant(2), constant(3))),
+        )));
+    }
+
+    #[test]
+    fn subtraction_is_left_associative() {
+        assert_that!(returned_expression("1 - 2 - 3"), eq(Expression::Binary(
+            BinaryOperator::Subtract,
+            Box::new(Expression::Binary(BinaryOperator::Subtract, constant(1), constant(2))),
+            constant(3),
+        )));
+    }
+
+    #[test]
+    fn unary_operators_nest() {
+        assert_that!(returned_expression("-~!x++"), eq(Expression::Unary(
+            UnaryOperator::Negate,
+            Box::new(Expression::Unary(
+                UnaryOperator::Complement,
+                Box::new(Expression::Unary(
+                    UnaryOperator::Not,
+                    Box::new(Expression::Unary(UnaryOperator::PostIncrement, var("x"))),
+                )),
+            )),
+        )));
+    }
+
+    #[test]
+    fn assignment_is_right_associative() {
+        assert_that!(returned_expression("a = b += 2"), eq(Expression::Assignment(
+            var("a"),
+            Box::new(Expression::CompoundAssignment(BinaryOperator::Add, var("b"), constant(2))),
+        )));
+    }
+
+    #[test]
+    fn conditional_is_right_associative() {
+        assert_that!(returned_expression("a ? 1 : b ? 2 : 3"), eq(Expression::Conditional(
+            var("a"),
+            constant(1),
+            Box::new(Expression::Conditional(var("b"), constant(2), constant(3))),
+        )));
+    }
+
+    #[test]
+    fn function_call() {
+        assert_that!(returned_expression("f(1, g())"), eq(Expression::FunctionCall(
+            "f".to_owned(),
+            vec![Expression::Literal(Literal::Decimal(1)), Expression::FunctionCall("g".to_owned(), vec![])],
+        )));
+    }
+
+    #[test]
+    fn declaration_with_initialiser() {
+        assert_that!(body_of_main("int x = 3;"), eq(vec![
+            BlockItem::Declaration(Declaration::Variable(VariableDeclaration {
+                name: "x".to_owned(),
+                init: Some(Expression::Literal(Literal::Decimal(3))),
+                variable_type: Type::Int,
+                storage_class: None,
+            })),
+        ]));
+    }
+
+    #[test]
+    fn goto_and_labelled_statement() {
+        assert_that!(body_of_main("goto out; out: return 0;"), eq(vec![
+            BlockItem::Statement(Statement::Goto("out".to_owned())),
+            BlockItem::Statement(Statement::Labelled(
+                "out".to_owned(),
+                Box::new(Statement::Return(Some(Expression::Literal(Literal::Decimal(0))))),
+            )),
+        ]));
+    }
+
+    #[test]
+    fn dangling_else_binds_to_nearest_if() {
+        assert_that!(body_of_main("if (a) if (b) return 1; else return 2;"), eq(vec![
+            BlockItem::Statement(Statement::If {
+                condition: Expression::Var("a".to_owned()),
+                then: Box::new(Statement::If {
+                    condition: Expression::Var("b".to_owned()),
+                    then: Box::new(Statement::Return(Some(Expression::Literal(Literal::Decimal(1))))),
+                    otherwise: Some(Box::new(Statement::Return(Some(Expression::Literal(Literal::Decimal(2)))))),
+                }),
+                otherwise: None,
+            }),
+        ]));
+    }
+
+    #[test]
+    fn loops() {
+        let items = body_of_main("
+            for (int i = 0; i < 10; i = i + 1) continue;
+            for (;;) break;
+            while (1) ;
+            do { } while (0);");
+        assert_that!(items.len(), eq(4));
+        assert_that!(matches!(&items[1], BlockItem::Statement(Statement::For { condition: None, post: None, .. })), eq(true));
+    }
+
+    #[test]
+    fn function_parameters() {
+        let program = parse("int add(int a, int b) { return a + b; }");
+        assert_that!(function(&program, 0).params, eq(vec!["a".to_owned(), "b".to_owned()]));
+    }
+
+    #[test]
+    fn prototype_parameters_may_be_unnamed() {
+        let program = parse("int helper(int, long b); int main(void) { return helper(1, 2); }");
+        assert_that!(function(&program, 0).body, none());
+        assert_that!(function(&program, 0).params, eq(vec!["".to_owned(), "b".to_owned()]));
+        assert_that!(function(&program, 0).function_type, eq(Type::Function {
+            params: vec![Type::Int, Type::Long],
+            return_type: Box::new(Type::Int),
+        }));
+    }
 
+    #[test]
+    fn definition_parameters_must_be_named() {
+        assert_that!(parse_fails("int helper(int) { return 0; }"), eq(true));
+    }
+
+    #[test]
+    fn file_scope_declarations() {
+        let program = parse("
+static int count;
+extern int total = 3;
+int f(int a);
+static int g(void) { return count; }");
+        assert_that!(program.declarations.len(), eq(4));
+        assert_that!(program.declarations[0].clone(), eq(Declaration::Variable(VariableDeclaration {
+            name: "count".to_owned(),
+            init: None,
+            variable_type: Type::Int,
+            storage_class: Some(StorageClass::Static),
+        })));
+        assert_that!(program.declarations[1].clone(), eq(Declaration::Variable(VariableDeclaration {
+            name: "total".to_owned(),
+            init: Some(Expression::Literal(Literal::Decimal(3))),
+            variable_type: Type::Int,
+            storage_class: Some(StorageClass::Extern),
+        })));
+        assert_that!(function(&program, 2).body, none());
+        assert_that!(function(&program, 3).storage_class, eq(Some(StorageClass::Static)));
+    }
+
+    #[test]
+    fn specifiers_in_any_order() {
+        let items = body_of_main("int static a; register int b; auto int c; extern int f(void);");
+        let storage_classes: Vec<Option<StorageClass>> = items.iter().map(|item| match item {
+            BlockItem::Declaration(Declaration::Variable(variable)) => variable.storage_class,
+            BlockItem::Declaration(Declaration::Function(function)) => function.storage_class,
+            other => panic!("not a declaration: {:?}", other),
+        }).collect();
+        assert_that!(storage_classes, eq(vec![
+            Some(StorageClass::Static),
+            Some(StorageClass::Register),
+            Some(StorageClass::Auto),
+            Some(StorageClass::Extern),
+        ]));
+    }
+
+    fn variable_types(input: &str) -> Vec<Type> {
+        parse(input).declarations.iter().map(|declaration| match declaration {
+            Declaration::Variable(variable) => variable.variable_type.clone(),
+            other => panic!("not a variable: {:?}", other),
+        }).collect()
+    }
+
+    #[test]
+    fn integer_types() {
+        assert_that!(variable_types("
+char a; signed char b; unsigned char c;
+short d; short int e; signed short f; unsigned short int g;
+int h; signed i; unsigned j; int unsigned k;
+long l; long int m; unsigned long n; long unsigned int o;"), eq(vec![
+            Type::Char, Type::SignedChar, Type::UnsignedChar,
+            Type::Short, Type::Short, Type::Short, Type::UnsignedShort,
+            Type::Int, Type::Int, Type::UnsignedInt, Type::UnsignedInt,
+            Type::Long, Type::Long, Type::UnsignedLong, Type::UnsignedLong,
+        ]));
+    }
+
+    #[test]
+    fn floating_types() {
+        assert_that!(variable_types("float a; double b; long double c; double long d;"), eq(vec![
+            Type::Float, Type::Double, Type::Double, Type::Double,
+        ]));
+        assert_that!(parse_fails("unsigned double x;"), eq(true));
+        assert_that!(parse_fails("long float x;"), eq(true));
+        assert_that!(parse_fails("short double x;"), eq(true));
+    }
+
+    #[test]
+    fn invalid_type_specifiers() {
+        assert_that!(parse_fails("long long x;"), eq(true));
+        assert_that!(parse_fails("signed unsigned x;"), eq(true));
+        assert_that!(parse_fails("short char x;"), eq(true));
+        assert_that!(parse_fails("void x;"), eq(true));
+        assert_that!(parse_fails("int f(void x);"), eq(true));
+        assert_that!(parse_fails("int f(static int x);"), eq(true));
+    }
+
+    #[test]
+    fn function_types() {
+        let program = parse("unsigned long f(char c, register long l); void g(void);");
+        assert_that!(function(&program, 0).function_type, eq(Type::Function {
+            params: vec![Type::Char, Type::Long],
+            return_type: Box::new(Type::UnsignedLong),
+        }));
+        assert_that!(function(&program, 1).function_type, eq(Type::Function {
+            params: vec![],
+            return_type: Box::new(Type::Void),
+        }));
+    }
+
+    #[test]
+    fn several_declarators() {
+        let program = parse("static long a, b = 2, f(void);");
+        assert_that!(program.declarations.len(), eq(3));
+        assert_that!(program.declarations[1].clone(), eq(Declaration::Variable(VariableDeclaration {
+            name: "b".to_owned(),
+            init: Some(Expression::Literal(Literal::Decimal(2))),
+            variable_type: Type::Long,
+            storage_class: Some(StorageClass::Static),
+        })));
+        assert_that!(function(&program, 2).storage_class, eq(Some(StorageClass::Static)));
+    }
+
+    #[test]
+    fn enum_definitions() {
+        let program = parse("enum colour { RED, GREEN = 5, BLUE } paint; enum colour brush; enum { ALONE };");
+        assert_that!(program.declarations.clone(), eq(vec![
+            Declaration::Enum(EnumDeclaration {
+                tag: Some("colour".to_owned()),
+                enumerators: vec![
+                    Enumerator { name: "RED".to_owned(), value: None },
+                    Enumerator { name: "GREEN".to_owned(), value: Some(Expression::Literal(Literal::Decimal(5))) },
+                    Enumerator { name: "BLUE".to_owned(), value: None },
+                ],
+            }),
+            Declaration::Variable(VariableDeclaration {
+                name: "paint".to_owned(),
+                init: None,
+                variable_type: Type::Int,
+                storage_class: None,
+            }),
+            Declaration::Variable(VariableDeclaration {
+                name: "brush".to_owned(),
+                init: None,
+                variable_type: Type::Int,
+                storage_class: None,
+            }),
+            Declaration::Enum(EnumDeclaration {
+                tag: None,
+                enumerators: vec![Enumerator { name: "ALONE".to_owned(), value: None }],
+            }),
+        ]));
+    }
+
+    #[test]
+    fn declaration_must_declare_something() {
+        assert_that!(parse_fails("int;"), eq(true));
+        assert_that!(parse_fails("enum colour;"), eq(true));
+    }
+
+    #[test]
+    fn casts_and_sizeof() {
+        assert_that!(returned_expression("(unsigned char) -x + sizeof(long) + sizeof x"), eq(Expression::Binary(
+            BinaryOperator::Add,
+            Box::new(Expression::Binary(
+                BinaryOperator::Add,
+                Box::new(Expression::Cast(
+                    Type::UnsignedChar,
+                    Box::new(Expression::Unary(UnaryOperator::Negate, var("x"))),
+                )),
+                Box::new(Expression::SizeOfType(Type::Long)),
+            )),
+            Box::new(Expression::SizeOfExpression(var("x"))),
+        )));
+        assert_that!(returned_expression("(x)"), eq(*var("x")));
+    }
+
+    #[test]
+    fn switch_statement() {
+        assert_that!(body_of_main("switch (x) { case 1: return 1; default: ; }"), eq(vec![
+            BlockItem::Statement(Statement::Switch {
+                condition: *var("x"),
+                body: Box::new(Statement::Compound(Block { items: vec![
+                    BlockItem::Statement(Statement::Case(
+                        Expression::Literal(Literal::Decimal(1)),
+                        Box::new(Statement::Return(Some(Expression::Literal(Literal::Decimal(1))))),
+                    )),
+                    BlockItem::Statement(Statement::Default(Box::new(Statement::Null))),
+                ] })),
+            }),
+        ]));
+    }
+
+    #[test]
+    fn return_without_value() {
+        assert_that!(body_of_main("return;"), eq(vec![BlockItem::Statement(Statement::Return(None))]));
+    }
+
+    fn parse_fails(input: &str) -> bool {
+        let tokens = lexer().parse(input).into_result().unwrap();
+        let (_, errs) = parser().parse(tokens.as_slice()).into_output_errors();
+        !errs.is_empty()
+    }
+
+    #[test]
+    fn multiple_storage_classes_is_an_error() {
+        assert_that!(parse_fails("static extern int x;"), eq(true));
+    }
+
+    #[test]
+    fn missing_or_repeated_type_is_an_error() {
+        assert_that!(parse_fails("static x;"), eq(true));
+        assert_that!(parse_fails("int int x;"), eq(true));
+    }
+
+    #[test]
+    fn missing_semicolon_is_an_error() {
+        let tokens = lexer().parse("int main(void) { return 2 }").into_result().unwrap();
+        let (_, errs) = parser().parse(tokens.as_slice()).into_output_errors();
+        assert_that!(errs.is_empty(), eq(false));
     }
 }
@@ -0,0 +1,26 @@
+//! Builds programs for the specs, through the same stages as the compiler.
+
+use chumsky::prelude::*;
+use common::target_platform::TargetPlatform;
+
+use crate::ast;
+use crate::ir;
+use crate::lexer::lexer;
+use crate::parser::parser;
+use crate::semantic::analyse;
+use crate::semantic::symbol_table::SymbolTable;
+
+/// The AST of a program, which must lex and parse.
+pub fn parsed(input: &str) -> ast::Program {
+    let tokens = lexer().parse(input).into_result().unwrap();
+    let program = parser().parse(tokens.as_slice()).into_result().unwrap();
+    program
+}
+
+/// The IR of a program for a target, with the symbol table it was generated with. The program
+/// must be valid.
+pub fn ir_of(input: &str, target_platform: TargetPlatform) -> (ir::Program, SymbolTable) {
+    let data_model = target_platform.data_model();
+    let (program, mut symbols) = analyse(parsed(input), &data_model).unwrap();
+    (ir::generate(&program, &mut symbols, &data_model), symbols)
+}
@@ -0,0 +1,222 @@
+//! Evaluation of integer constant expressions, as needed for static initialisers, case labels,
+//! enumeration constants, array sizes and bitfield widths, and of the arithmetic constant
+//! expressions that static initialisers may also be. Expressions must have been type checked
+//! first, so that every conversion is an explicit cast and every literal is a typed constant.
+//! Arithmetic is done in the width of each operation's type on the target, wrapping exactly as
+//! the target would, so that e.g. '32767 + 1' is -32768 on EPOC16, but 32768 on a Transputer.
+//! Signed overflow is reported as a warning; integer division by zero is an error. Floating
+//! arithmetic is IEEE 754, rounding to nearest, so e.g. '1.0 / 0' is infinity.
+//!
+//! Arrays and bitfields are not parsed yet, so nothing calls 'array_size' or 'bitfield_width'
+//! but their specs until the type checker meets their declarations.
+
+use std::fmt::{Display, Formatter};
+
+use common::data_model::DataModel;
+
+use crate::ast::{BinaryOperator, Constant, Expression, Type, UnaryOperator};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConstantError {
+    NotConstant,
+    DivisionByZero,
+    ArraySizeNotPositive(i128),
+    BitfieldWidthOutOfRange { width: i128, bits: u32 },
+}
+
+impl Display for ConstantError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConstantError::NotConstant => write!(f, "Expression is not constant"),
+            ConstantError::DivisionByZero => write!(f, "Division by zero in constant expression"),
+            ConstantError::ArraySizeNotPositive(size) => write!(f, "Array size {} is not positive", size),
+            ConstantError::BitfieldWidthOutOfRange { width, bits } => {
+                write!(f, "Bitfield width {} is not between 0 and {}", width, bits)
+            }
+        }
+    }
+}
+
+pub struct ConstantEvaluator<'a> {
+    data_model: &'a DataModel,
+    /// Diagnostics that do not stop evaluation, e.g. signed overflow.
+    pub warnings: Vec<String>,
+}
+
+impl<'a> ConstantEvaluator<'a> {
+    pub fn new(data_model: &'a DataModel) -> Self {
+        Self { data_model, warnings: vec![] }
+    }
+
+    pub fn evaluate(&mut self, expression: &Expression) -> Result<Constant, ConstantError> {
+        match expression {
+            Expression::Constant(constant) => Ok(constant.clone()),
+            Expression::Cast(target, operand) if target.is_arithmetic() => {
+                Ok(self.evaluate(operand)?.convert(target, self.data_model))
+            }
+            Expression::Unary(operator, operand) => self.unary(*operator, operand),
+            Expression::Binary(operator, left, right) => self.binary(*operator, left, right),
+            Expression::Conditional(condition, then, otherwise) => {
+                if !self.evaluate(condition)?.is_zero() {
+                    self.evaluate(then)
+                } else {
+                    self.evaluate(otherwise)
+                }
+            }
+            _ => Err(ConstantError::NotConstant),
+        }
+    }
+
+    /// The number of elements in an array, which must be positive.
+    pub fn array_size(&mut self, expression: &Expression) -> Result<u64, ConstantError> {
+        let size = self.evaluate(expression)?.value;
+        if size > 0 {
+            Ok(size as u64)
+        } else {
+            Err(ConstantError::ArraySizeNotPositive(size))
+        }
+    }
+
+    /// The width of a bitfield, which may be zero, but no wider than the field's type.
+    pub fn bitfield_width(&mut self, expression: &Expression, field_type: &Type) -> Result<u32, ConstantError> {
+        let width = self.evaluate(expression)?.value;
+        let bits = field_type.bits(self.data_model);
+        if (0..=bits as i128).contains(&width) {
+            Ok(width as u32)
+        } else {
+            Err(ConstantError::BitfieldWidthOutOfRange { width, bits })
+        }
+    }
+
+    /// The result of an operation on values of the given type, which is reduced into the type's
+    /// range with a warning if it overflows a signed type.
+    fn result(&mut self, result_type: &Type, value: i128) -> Constant {
+        let in_range = (result_type.min_value(self.data_model)..=result_type.max_value(self.data_model)).contains(&value);
+        if !in_range && result_type.is_signed(self.data_model) {
+            self.warnings.push(format!("Integer overflow in constant expression of type '{}'", result_type));
+        }
+        Constant::wrapping(result_type.clone(), value, self.data_model)
+    }
+
+    fn unary(&mut self, operator: UnaryOperator, operand: &Expression) -> Result<Constant, ConstantError> {
+        let operand = self.evaluate(operand)?;
+        let operand_type = &operand.constant_type;
+        match operator {
+            UnaryOperator::Negate if operand_type.is_floating() => Ok(floating(operand_type, -operand.floating())),
+            UnaryOperator::Negate => Ok(self.result(operand_type, -operand.value)),
+            UnaryOperator::Complement if operand_type.is_floating() => Err(ConstantError::NotConstant),
+            UnaryOperator::Complement => Ok(Constant::wrapping(operand_type.clone(), !operand.value, self.data_model)),
+            UnaryOperator::Not => Ok(Constant::int(operand.is_zero() as i128)),
+            UnaryOperator::PreIncrement
+            | UnaryOperator::PreDecrement
+            | UnaryOperator::PostIncrement
+            | UnaryOperator::PostDecrement => Err(ConstantError::NotConstant),
+        }
+    }
+
+    fn binary(&mut self, operator: BinaryOperator, left: &Expression, right: &Expression) -> Result<Constant, ConstantError> {
+        // The right operand of '&&' and '||' is not evaluated if the left decides the result, so
+        // e.g. '0 && 1 / 0' is fine.
+        match operator {
+            BinaryOperator::And => {
+                let result = !self.evaluate(left)?.is_zero() && !self.evaluate(right)?.is_zero();
+                return Ok(Constant::int(result as i128));
+            }
+            BinaryOperator::Or => {
+                let result = !self.evaluate(left)?.is_zero() || !self.evaluate(right)?.is_zero();
+                return Ok(Constant::int(result as i128));
+            }
+            _ => {}
+        }
+
+        let left = self.evaluate(left)?;
+        let right = self.evaluate(right)?;
+        // Type checking has converted both operands to the type of the operation, except for
+        // shifts, where the result has the type of the left operand.
+        let operation_type = &left.constant_type;
+        if operation_type.is_floating() {
+            return floating_binary(operator, &left, &right);
+        }
+        let (a, b) = (left.value, right.value);
+        match operator {
+            BinaryOperator::Add => Ok(self.result(operation_type, a + b)),
+            BinaryOperator::Subtract => Ok(self.result(operation_type, a - b)),
+            BinaryOperator::Multiply => Ok(self.result(operation_type, a * b)),
+            // Division truncates towards zero on all targets.
+            BinaryOperator::Divide if b != 0 => Ok(self.result(operation_type, a / b)),
+            BinaryOperator::Remainder if b != 0 => Ok(self.result(operation_type, a % b)),
+            BinaryOperator::Divide | BinaryOperator::Remainder => Err(ConstantError::DivisionByZero),
+            BinaryOperator::BitwiseAnd => Ok(Constant::wrapping(operation_type.clone(), a & b, self.data_model)),
+            BinaryOperator::BitwiseOr => Ok(Constant::wrapping(operation_type.clone(), a | b, self.data_model)),
+            BinaryOperator::BitwiseXor => Ok(Constant::wrapping(operation_type.clone(), a ^ b, self.data_model)),
+            BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => Ok(self.shift(operator, &left, b)),
+            BinaryOperator::Equal => Ok(Constant::int((a == b) as i128)),
+            BinaryOperator::NotEqual => Ok(Constant::int((a != b) as i128)),
+            BinaryOperator::LessThan => Ok(Constant::int((a < b) as i128)),
+            BinaryOperator::LessOrEqual => Ok(Constant::int((a <= b) as i128)),
+            BinaryOperator::GreaterThan => Ok(Constant::int((a > b) as i128)),
+            BinaryOperator::GreaterOrEqual => Ok(Constant::int((a >= b) as i128)),
+            BinaryOperator::And | BinaryOperator::Or => unreachable!("handled above"),
+        }
+    }
+
+    /// A shift by a negative count, or by at least the width of the type, is undefined; it is
+    /// reported, and the count is clamped to the width. Shifting a signed value left is only
+    /// reported as overflow if bits beyond the sign bit are lost, e.g. '1 << 15' for a 16-bit
+    /// 'int' is the commonly intended -32768.
+    fn shift(&mut self, operator: BinaryOperator, left: &Constant, count: i128) -> Constant {
+        let shifted_type = &left.constant_type;
+        let bits = shifted_type.bits(self.data_model);
+        let count_in_range = (0..bits as i128).contains(&count);
+        if !count_in_range {
+            self.warnings.push(format!("Shift count {} is out of range for type '{}'", count, shifted_type));
+        }
+        let count = count.clamp(0, bits as i128) as u32;
+        let value = if operator == BinaryOperator::ShiftLeft {
+            let shifted = left.value << count;
+            let unsigned_max = (1i128 << bits) - 1;
+            let overflows = !(shifted_type.min_value(self.data_model)..=unsigned_max).contains(&shifted);
+            if count_in_range && overflows && shifted_type.is_signed(self.data_model) {
+                self.warnings.push(format!("Integer overflow in constant expression of type '{}'", shifted_type));
+            }
+            shifted
+        } else {
+            // Signed values are shifted arithmetically.
+            left.value >> count
+        };
+        Constant::wrapping(shifted_type.clone(), value, self.data_model)
+    }
+}
+
+/// A floating constant of the given type, rounded from a 'double'.
+fn floating(floating_type: &Type, value: f64) -> Constant {
+    match floating_type {
+        Type::Float => Constant::float(value as f32),
+        _ => Constant::double(value),
+    }
+}
+
+/// Arithmetic on 'float' operands is done in 'double' and then rounded, which gives the same
+/// result as rounding once, as 'double' has more than twice the precision. The operators that only
+/// apply to integers have already been reported by type checking.
+fn floating_binary(operator: BinaryOperator, left: &Constant, right: &Constant) -> Result<Constant, ConstantError> {
+    let operation_type = &left.constant_type;
+    let (a, b) = (left.floating(), right.floating());
+    Ok(match operator {
+        BinaryOperator::Add => floating(operation_type, a + b),
+        BinaryOperator::Subtract => floating(operation_type, a - b),
+        BinaryOperator::Multiply => floating(operation_type, a * b),
+        BinaryOperator::Divide => floating(operation_type, a / b),
+        BinaryOperator::Equal => Constant::int((a == b) as i128),
+        BinaryOperator::NotEqual => Constant::int((a != b) as i128),
+        BinaryOperator::LessThan => Constant::int((a < b) as i128),
+        BinaryOperator::LessOrEqual => Constant::int((a <= b) as i128),
+        BinaryOperator::GreaterThan => Constant::int((a > b) as i128),
+        BinaryOperator::GreaterOrEqual => Constant::int((a >= b) as i128),
+        _ => return Err(ConstantError::NotConstant),
+    })
+}
+
+#[cfg(test)]
+#[path = "./constant_evaluation_spec.rs"]
+mod constant_evaluation_spec;
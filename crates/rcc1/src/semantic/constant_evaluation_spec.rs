@@ -0,0 +1,185 @@
+mod constant_evaluation_spec {
+    use common::target_platform::TargetPlatform;
+    use hamcrest2::prelude::*;
+
+    use crate::ast::{BlockItem, Constant, Declaration, Expression, Statement, Type};
+    use crate::program_test_helper::parsed;
+    use crate::semantic::analyse;
+    use crate::semantic::constant_evaluation::{ConstantError, ConstantEvaluator};
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    /// The expression, type checked for the target, as the evaluator requires.
+    fn typed(input: &str, target_platform: TargetPlatform) -> Expression {
+        let program = parsed(&format!("int main(void) {{ {}; }}", input));
+        let (program, _) = analyse(program, &target_platform.data_model()).unwrap();
+        match &program.declarations[0] {
+            Declaration::Function(function) => match &function.body.as_ref().unwrap().items[0] {
+                BlockItem::Statement(Statement::Expression(expression)) => expression.clone(),
+                other => panic!("not an expression statement: {:?}", other),
+            },
+            other => panic!("not a function: {:?}", other),
+        }
+    }
+
+    fn evaluate(input: &str, target_platform: TargetPlatform) -> (Result<Constant, ConstantError>, Vec<String>) {
+        let data_model = target_platform.data_model();
+        let mut evaluator = ConstantEvaluator::new(&data_model);
+        let result = evaluator.evaluate(&typed(input, target_platform));
+        (result, evaluator.warnings)
+    }
+
+    fn value(input: &str, target_platform: TargetPlatform) -> Constant {
+        evaluate(input, target_platform).0.unwrap()
+    }
+
+    fn constant(constant_type: Type, value: i128) -> Constant {
+        Constant { constant_type, value }
+    }
+
+    #[test]
+    fn signed_overflow_wraps_in_the_width_of_int_with_a_warning() {
+        let (result, warnings) = evaluate("32767 + 1", TargetPlatform::EPOC16);
+        assert_that!(result, eq(Ok(constant(Type::Int, -32768))));
+        assert_that!(warnings, eq(vec!["Integer overflow in constant expression of type 'int'".to_owned()]));
+
+        let (result, warnings) = evaluate("32767 + 1", TargetPlatform::Transputer);
+        assert_that!(result, eq(Ok(constant(Type::Int, 32768))));
+        assert_that!(warnings.is_empty(), eq(true));
+    }
+
+    #[test]
+    fn unsigned_arithmetic_wraps_silently() {
+        let (result, warnings) = evaluate("65535u + 1", TargetPlatform::EPOC16);
+        assert_that!(result, eq(Ok(constant(Type::UnsignedInt, 0))));
+        assert_that!(warnings.is_empty(), eq(true));
+        assert_that!(value("0u - 1", TargetPlatform::Transputer), eq(constant(Type::UnsignedInt, 4294967295)));
+        assert_that!(value("0ul - 1", TargetPlatform::X86_64), eq(constant(Type::UnsignedLong, 18446744073709551615)));
+    }
+
+    #[test]
+    fn literals_take_the_first_type_that_fits_the_target() {
+        assert_that!(value("32768", TargetPlatform::EPOC16), eq(constant(Type::Long, 32768)));
+        assert_that!(value("32768", TargetPlatform::Transputer), eq(constant(Type::Int, 32768)));
+        assert_that!(value("0xffff", TargetPlatform::EPOC16), eq(constant(Type::UnsignedInt, 65535)));
+        assert_that!(value("2147483648", TargetPlatform::Transputer), eq(constant(Type::UnsignedLong, 2147483648)));
+        assert_that!(value("2147483648", TargetPlatform::X86_64), eq(constant(Type::Long, 2147483648)));
+    }
+
+    #[test]
+    fn comparisons_follow_the_usual_arithmetic_conversions_of_the_target() {
+        // 'long' can hold every 'unsigned int' on EPOC16, so the comparison is signed...
+        assert_that!(value("-1L < 1u", TargetPlatform::EPOC16), eq(constant(Type::Int, 1)));
+        // ...but not on a Transputer, where both are 32 bits, so -1 becomes 'unsigned long'.
+        assert_that!(value("-1L < 1u", TargetPlatform::Transputer), eq(constant(Type::Int, 0)));
+        assert_that!(value("-1 < 1u", TargetPlatform::X86_64), eq(constant(Type::Int, 0)));
+    }
+
+    #[test]
+    fn sizeof_depends_on_the_data_model() {
+        assert_that!(value("sizeof(int)", TargetPlatform::EPOC16), eq(constant(Type::UnsignedInt, 2)));
+        assert_that!(value("sizeof(long)", TargetPlatform::Transputer), eq(constant(Type::UnsignedInt, 4)));
+        assert_that!(value("sizeof(long)", TargetPlatform::X86_64), eq(constant(Type::UnsignedLong, 8)));
+        assert_that!(value("sizeof 1L + 1", TargetPlatform::EPOC16), eq(constant(Type::UnsignedInt, 5)));
+    }
+
+    #[test]
+    fn casts_truncate_and_extend() {
+        assert_that!(value("(unsigned char) 300", TargetPlatform::X86_64), eq(constant(Type::UnsignedChar, 44)));
+        assert_that!(value("(char) 200", TargetPlatform::EPOC16), eq(constant(Type::Char, -56)));
+        assert_that!(value("(char) 200", TargetPlatform::Transputer), eq(constant(Type::Char, 200)));
+        assert_that!(value("(long) (short) 40000", TargetPlatform::X86_64), eq(constant(Type::Long, -25536)));
+    }
+
+    #[test]
+    fn unsigned_short_promotes_to_unsigned_int_on_epoc16() {
+        assert_that!(value("(unsigned short) 65535 + 1", TargetPlatform::EPOC16), eq(constant(Type::UnsignedInt, 0)));
+        assert_that!(value("(unsigned short) 65535 + 1", TargetPlatform::X86_64), eq(constant(Type::Int, 65536)));
+    }
+
+    #[test]
+    fn division_truncates_towards_zero() {
+        assert_that!(value("-7 / 2", TargetPlatform::EPOC16), eq(constant(Type::Int, -3)));
+        assert_that!(value("-7 % 2", TargetPlatform::EPOC16), eq(constant(Type::Int, -1)));
+    }
+
+    #[test]
+    fn division_by_zero_is_an_error() {
+        assert_that!(evaluate("1 / 0", TargetPlatform::Transputer).0, eq(Err(ConstantError::DivisionByZero)));
+        assert_that!(evaluate("1 % (2 - 2)", TargetPlatform::Transputer).0, eq(Err(ConstantError::DivisionByZero)));
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        assert_that!(value("0 && 1 / 0", TargetPlatform::Transputer), eq(constant(Type::Int, 0)));
+        assert_that!(value("1 || 1 / 0", TargetPlatform::Transputer), eq(constant(Type::Int, 1)));
+        assert_that!(value("1 ? 2 : 1 / 0", TargetPlatform::Transputer), eq(constant(Type::Int, 2)));
+    }
+
+    #[test]
+    fn shifts() {
+        let (result, warnings) = evaluate("1 << 15", TargetPlatform::EPOC16);
+        assert_that!(result, eq(Ok(constant(Type::Int, -32768))));
+        assert_that!(warnings.is_empty(), eq(true));
+
+        let (result, warnings) = evaluate("1 << 16", TargetPlatform::EPOC16);
+        assert_that!(result, eq(Ok(constant(Type::Int, 0))));
+        assert_that!(warnings, eq(vec!["Shift count 16 is out of range for type 'int'".to_owned()]));
+
+        let (_, warnings) = evaluate("3 << 15", TargetPlatform::EPOC16);
+        assert_that!(warnings, eq(vec!["Integer overflow in constant expression of type 'int'".to_owned()]));
+
+        assert_that!(value("-16 >> 2", TargetPlatform::Transputer), eq(constant(Type::Int, -4)));
+        assert_that!(value("1L << 40", TargetPlatform::X86_64), eq(constant(Type::Long, 1 << 40)));
+    }
+
+    #[test]
+    fn floating_arithmetic_is_rounded_to_its_type() {
+        assert_that!(value("1.0 / 3", TargetPlatform::Transputer), eq(Constant::double(1.0 / 3.0)));
+        assert_that!(value("1.0f / 3", TargetPlatform::Transputer), eq(Constant::float(1.0 / 3.0)));
+        assert_that!(value("0.1f + 0.2", TargetPlatform::Transputer), eq(Constant::double(0.1f32 as f64 + 0.2)));
+        assert_that!(value("-0.0", TargetPlatform::Transputer).value, eq(i128::from((-0.0f64).to_bits())));
+    }
+
+    #[test]
+    fn floating_comparisons_and_conversions() {
+        assert_that!(value("0.5 < 1", TargetPlatform::EPOC16), eq(Constant::int(1)));
+        assert_that!(value("!0.0 + !-0.0 + (0.5 && 2)", TargetPlatform::EPOC16), eq(Constant::int(3)));
+        assert_that!(value("(int) -2.9", TargetPlatform::EPOC16), eq(Constant::int(-2)));
+        assert_that!(value("(float) 16777217", TargetPlatform::EPOC16), eq(Constant::float(16_777_216.0)));
+    }
+
+    #[test]
+    fn floating_division_by_zero_is_infinite() {
+        assert_that!(value("1.0 / 0", TargetPlatform::Transputer), eq(Constant::double(f64::INFINITY)));
+    }
+
+    #[test]
+    fn non_constant_expressions() {
+        assert_that!(evaluate("main()", TargetPlatform::Transputer).0, eq(Err(ConstantError::NotConstant)));
+    }
+
+    #[test]
+    fn array_sizes_must_be_positive() {
+        let data_model = TargetPlatform::EPOC16.data_model();
+        let mut evaluator = ConstantEvaluator::new(&data_model);
+        assert_that!(evaluator.array_size(&typed("2 * 3", TargetPlatform::EPOC16)), eq(Ok(6)));
+        assert_that!(evaluator.array_size(&typed("1 - 1", TargetPlatform::EPOC16)), eq(Err(ConstantError::ArraySizeNotPositive(0))));
+    }
+
+    #[test]
+    fn bitfield_widths_are_limited_by_the_width_of_the_field_type() {
+        let expression = typed("17", TargetPlatform::EPOC16);
+        let epoc16 = TargetPlatform::EPOC16.data_model();
+        let transputer = TargetPlatform::Transputer.data_model();
+        assert_that!(
+            ConstantEvaluator::new(&epoc16).bitfield_width(&expression, &Type::Int),
+            eq(Err(ConstantError::BitfieldWidthOutOfRange { width: 17, bits: 16 }))
+        );
+        assert_that!(ConstantEvaluator::new(&transputer).bitfield_width(&expression, &Type::Int), eq(Ok(17)));
+        assert_that!(ConstantEvaluator::new(&transputer).bitfield_width(&typed("0", TargetPlatform::Transputer), &Type::UnsignedInt), eq(Ok(0)));
+    }
+}
@@ -0,0 +1,329 @@
+//! Identifier resolution: checks that every variable and function is declared before use, and
+//! that no scope declares the same identifier twice (unless both declarations have linkage, and
+//! so refer to the same object). Variables without linkage are renamed to '<name>.<n>' so that
+//! later stages need not know about scopes; identifiers with linkage keep their names.
+//! Enumeration constants share the same name space as variables, and are renamed likewise.
+//! Invalid lvalues are also reported here.
+
+use std::collections::HashMap;
+
+use crate::ast::{
+    Block, BlockItem, Declaration, EnumDeclaration, Enumerator, Expression, ForInit,
+    FunctionDeclaration, Program, Statement, StorageClass, UnaryOperator, VariableDeclaration,
+};
+use crate::semantic::SemanticResult;
+
+struct Entry {
+    unique_name: String,
+    has_linkage: bool,
+    is_enumerator: bool,
+}
+
+#[derive(Default)]
+struct IdentifierResolver {
+    scopes: Vec<HashMap<String, Entry>>,
+    counter: usize,
+    errors: Vec<String>,
+}
+
+pub fn resolve_identifiers(program: Program) -> SemanticResult<Program> {
+    let mut resolver = IdentifierResolver::default();
+    resolver.scopes.push(HashMap::new());
+    let declarations = program
+        .declarations
+        .into_iter()
+        .map(|declaration| resolver.file_scope_declaration(declaration))
+        .collect();
+    if resolver.errors.is_empty() {
+        Ok(Program { declarations })
+    } else {
+        Err(resolver.errors)
+    }
+}
+
+impl IdentifierResolver {
+    fn current_scope(&mut self) -> &mut HashMap<String, Entry> {
+        self.scopes.last_mut().expect("there is always a file scope")
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Entry> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn make_unique(&mut self, name: &str) -> String {
+        self.counter += 1;
+        format!("{}.{}", name, self.counter)
+    }
+
+    fn file_scope_declaration(&mut self, declaration: Declaration) -> Declaration {
+        match declaration {
+            Declaration::Function(function) => Declaration::Function(self.function_declaration(function, true)),
+            Declaration::Variable(variable) => {
+                if let Some(storage_class @ (StorageClass::Auto | StorageClass::Register)) = variable.storage_class {
+                    self.errors.push(format!(
+                        "File scope declaration of '{}' cannot be {}",
+                        variable.name,
+                        storage_class_name(storage_class)
+                    ));
+                }
+                if self.current_scope().get(&variable.name).is_some_and(|existing| existing.is_enumerator) {
+                    self.errors.push(format!("Duplicate declaration of '{}'", variable.name));
+                }
+                self.current_scope().insert(
+                    variable.name.clone(),
+                    Entry { unique_name: variable.name.clone(), has_linkage: true, is_enumerator: false },
+                );
+                let init = variable.init.map(|init| self.expression(init));
+                Declaration::Variable(VariableDeclaration { init, ..variable })
+            }
+            Declaration::Enum(enum_declaration) => Declaration::Enum(self.enum_declaration(enum_declaration)),
+        }
+    }
+
+    fn enum_declaration(&mut self, enum_declaration: EnumDeclaration) -> EnumDeclaration {
+        let enumerators = enum_declaration
+            .enumerators
+            .into_iter()
+            .map(|enumerator| {
+                if self.current_scope().contains_key(&enumerator.name) {
+                    self.errors.push(format!("Duplicate declaration of '{}'", enumerator.name));
+                }
+                // An enumerator is in scope from the end of its own definition.
+                let value = enumerator.value.map(|value| self.expression(value));
+                let unique_name = self.make_unique(&enumerator.name);
+                self.current_scope().insert(
+                    enumerator.name,
+                    Entry { unique_name: unique_name.clone(), has_linkage: false, is_enumerator: true },
+                );
+                Enumerator { name: unique_name, value }
+            })
+            .collect();
+        EnumDeclaration { enumerators, ..enum_declaration }
+    }
+
+    fn function_declaration(&mut self, function: FunctionDeclaration, at_file_scope: bool) -> FunctionDeclaration {
+        if !at_file_scope {
+            if function.body.is_some() {
+                self.errors.push(format!("Function '{}' cannot be defined inside another function", function.name));
+            }
+            if function.storage_class == Some(StorageClass::Static) {
+                self.errors.push(format!("Block scope declaration of function '{}' cannot be static", function.name));
+            }
+        }
+        if let Some(storage_class @ (StorageClass::Auto | StorageClass::Register)) = function.storage_class {
+            self.errors.push(format!(
+                "Declaration of function '{}' cannot be {}",
+                function.name,
+                storage_class_name(storage_class)
+            ));
+        }
+        if self.current_scope().get(&function.name).is_some_and(|existing| !existing.has_linkage) {
+            self.errors.push(format!("Duplicate declaration of '{}'", function.name));
+        }
+        self.current_scope().insert(
+            function.name.clone(),
+            Entry { unique_name: function.name.clone(), has_linkage: true, is_enumerator: false },
+        );
+
+        // Parameters and the outermost block of the body share a scope.
+        self.scopes.push(HashMap::new());
+        let mut params = vec![];
+        for param in function.params {
+            if param.is_empty() {
+                params.push(param);
+                continue;
+            }
+            if self.current_scope().contains_key(&param) {
+                self.errors.push(format!("Duplicate parameter '{}' in function '{}'", param, function.name));
+            }
+            let unique_name = self.make_unique(&param);
+            self.current_scope().insert(
+                param,
+                Entry { unique_name: unique_name.clone(), has_linkage: false, is_enumerator: false },
+            );
+            params.push(unique_name);
+        }
+        let body = function.body.map(|body| self.block_items(body));
+        self.scopes.pop();
+
+        FunctionDeclaration { params, body, ..function }
+    }
+
+    fn local_variable_declaration(&mut self, variable: VariableDeclaration) -> VariableDeclaration {
+        let is_extern = variable.storage_class == Some(StorageClass::Extern);
+        if self.current_scope().get(&variable.name).is_some_and(|existing| !(existing.has_linkage && is_extern)) {
+            self.errors.push(format!("Duplicate declaration of '{}'", variable.name));
+        }
+        if is_extern {
+            self.current_scope().insert(
+                variable.name.clone(),
+                Entry { unique_name: variable.name.clone(), has_linkage: true, is_enumerator: false },
+            );
+            let init = variable.init.map(|init| self.expression(init));
+            return VariableDeclaration { init, ..variable };
+        }
+        let unique_name = self.make_unique(&variable.name);
+        self.current_scope().insert(
+            variable.name,
+            Entry { unique_name: unique_name.clone(), has_linkage: false, is_enumerator: false },
+        );
+        // The variable is in scope in its own initialiser.
+        let init = variable.init.map(|init| self.expression(init));
+        VariableDeclaration { name: unique_name, init, ..variable }
+    }
+
+    fn block(&mut self, block: Block) -> Block {
+        self.scopes.push(HashMap::new());
+        let block = self.block_items(block);
+        self.scopes.pop();
+        block
+    }
+
+    fn block_items(&mut self, block: Block) -> Block {
+        let items = block
+            .items
+            .into_iter()
+            .map(|item| match item {
+                BlockItem::Statement(statement) => BlockItem::Statement(self.statement(statement)),
+                BlockItem::Declaration(Declaration::Function(function)) => {
+                    BlockItem::Declaration(Declaration::Function(self.function_declaration(function, false)))
+                }
+                BlockItem::Declaration(Declaration::Variable(variable)) => {
+                    BlockItem::Declaration(Declaration::Variable(self.local_variable_declaration(variable)))
+                }
+                BlockItem::Declaration(Declaration::Enum(enum_declaration)) => {
+                    BlockItem::Declaration(Declaration::Enum(self.enum_declaration(enum_declaration)))
+                }
+            })
+            .collect();
+        Block { items }
+    }
+
+    fn statement(&mut self, statement: Statement) -> Statement {
+        match statement {
+            Statement::Return(expression) => Statement::Return(expression.map(|e| self.expression(e))),
+            Statement::Expression(expression) => Statement::Expression(self.expression(expression)),
+            Statement::If { condition, then, otherwise } => Statement::If {
+                condition: self.expression(condition),
+                then: Box::new(self.statement(*then)),
+                otherwise: otherwise.map(|otherwise| Box::new(self.statement(*otherwise))),
+            },
+            Statement::Compound(block) => Statement::Compound(self.block(block)),
+            Statement::While { condition, body } => Statement::While {
+                condition: self.expression(condition),
+                body: Box::new(self.statement(*body)),
+            },
+            Statement::DoWhile { body, condition } => Statement::DoWhile {
+                body: Box::new(self.statement(*body)),
+                condition: self.expression(condition),
+            },
+            Statement::For { init, condition, post, body } => {
+                // The for loop header introduces a scope of its own.
+                self.scopes.push(HashMap::new());
+                let init = match init {
+                    ForInit::Declaration(variable) => {
+                        if let Some(storage_class @ (StorageClass::Static | StorageClass::Extern)) = variable.storage_class {
+                            self.errors.push(format!(
+                                "Declaration of '{}' in for loop initialiser cannot be {}",
+                                variable.name,
+                                storage_class_name(storage_class)
+                            ));
+                        }
+                        ForInit::Declaration(self.local_variable_declaration(variable))
+                    }
+                    ForInit::Expression(expression) => ForInit::Expression(expression.map(|e| self.expression(e))),
+                };
+                let statement = Statement::For {
+                    init,
+                    condition: condition.map(|e| self.expression(e)),
+                    post: post.map(|e| self.expression(e)),
+                    body: Box::new(self.statement(*body)),
+                };
+                self.scopes.pop();
+                statement
+            }
+            Statement::Switch { condition, body } => Statement::Switch {
+                condition: self.expression(condition),
+                body: Box::new(self.statement(*body)),
+            },
+            Statement::Case(label, statement) => Statement::Case(self.expression(label), Box::new(self.statement(*statement))),
+            Statement::Default(statement) => Statement::Default(Box::new(self.statement(*statement))),
+            Statement::Labelled(label, statement) => Statement::Labelled(label, Box::new(self.statement(*statement))),
+            Statement::Break | Statement::Continue | Statement::Goto(_) | Statement::Null => statement,
+        }
+    }
+
+    fn lvalue(&mut self, expression: &Expression) {
+        let is_lvalue = match expression {
+            Expression::Var(name) => !self.lookup(name).is_some_and(|entry| entry.is_enumerator),
+            _ => false,
+        };
+        if !is_lvalue {
+            self.errors.push("Invalid lvalue".to_owned());
+        }
+    }
+
+    fn expression(&mut self, expression: Expression) -> Expression {
+        match expression {
+            Expression::Literal(_) | Expression::Constant(_) | Expression::SizeOfType(_) => expression,
+            Expression::Var(name) => match self.lookup(&name) {
+                Some(entry) => Expression::Var(entry.unique_name.clone()),
+                None => {
+                    self.errors.push(format!("Undeclared variable '{}'", name));
+                    Expression::Var(name)
+                }
+            },
+            Expression::Cast(target, operand) => Expression::Cast(target, Box::new(self.expression(*operand))),
+            Expression::SizeOfExpression(operand) => Expression::SizeOfExpression(Box::new(self.expression(*operand))),
+            Expression::Unary(operator, operand) => {
+                if matches!(
+                    operator,
+                    UnaryOperator::PreIncrement | UnaryOperator::PreDecrement | UnaryOperator::PostIncrement | UnaryOperator::PostDecrement
+                ) {
+                    self.lvalue(&operand);
+                }
+                Expression::Unary(operator, Box::new(self.expression(*operand)))
+            }
+            Expression::Binary(operator, left, right) => {
+                Expression::Binary(operator, Box::new(self.expression(*left)), Box::new(self.expression(*right)))
+            }
+            Expression::Assignment(left, right) => {
+                self.lvalue(&left);
+                Expression::Assignment(Box::new(self.expression(*left)), Box::new(self.expression(*right)))
+            }
+            Expression::CompoundAssignment(operator, left, right) => {
+                self.lvalue(&left);
+                Expression::CompoundAssignment(operator, Box::new(self.expression(*left)), Box::new(self.expression(*right)))
+            }
+            Expression::Conditional(condition, then, otherwise) => Expression::Conditional(
+                Box::new(self.expression(*condition)),
+                Box::new(self.expression(*then)),
+                Box::new(self.expression(*otherwise)),
+            ),
+            Expression::FunctionCall(name, arguments) => {
+                let name = match self.lookup(&name) {
+                    Some(entry) => entry.unique_name.clone(),
+                    None => {
+                        self.errors.push(format!("Undeclared function '{}'", name));
+                        name
+                    }
+                };
+                let arguments = arguments.into_iter().map(|argument| self.expression(argument)).collect();
+                Expression::FunctionCall(name, arguments)
+            }
+        }
+    }
+}
+
+pub fn storage_class_name(storage_class: StorageClass) -> &'static str {
+    match storage_class {
+        StorageClass::Auto => "auto",
+        StorageClass::Register => "register",
+        StorageClass::Static => "static",
+        StorageClass::Extern => "extern",
+    }
+}
+
+#[cfg(test)]
+#[path = "./identifier_resolution_spec.rs"]
+mod identifier_resolution_spec;
@@ -0,0 +1,193 @@
+mod identifier_resolution_spec {
+    use hamcrest2::prelude::*;
+
+    use crate::ast::{BlockItem, Declaration, Expression, FunctionDeclaration, Program, Statement};
+    use crate::program_test_helper::parsed;
+    use crate::semantic::identifier_resolution::resolve_identifiers;
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn resolve(input: &str) -> Program {
+        resolve_identifiers(parsed(input)).unwrap()
+    }
+
+    fn errors(input: &str) -> Vec<String> {
+        resolve_identifiers(parsed(input)).unwrap_err()
+    }
+
+    fn function(program: &Program, index: usize) -> FunctionDeclaration {
+        match &program.declarations[index] {
+            Declaration::Function(function) => function.clone(),
+            other => panic!("not a function: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn locals_and_parameters_are_renamed_uniquely() {
+        let program = resolve("
+int f(int a) {
+    int b = a;
+    {
+        int b = 2;
+        return b;
+    }
+}");
+        let f = function(&program, 0);
+        assert_that!(f.params, eq(vec!["a.1".to_owned()]));
+        let items = f.body.unwrap().items;
+        match &items[0] {
+            BlockItem::Declaration(Declaration::Variable(variable)) => {
+                assert_that!(variable.name.as_str(), eq("b.2"));
+                assert_that!(variable.init.clone(), eq(Some(Expression::Var("a.1".to_owned()))));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match &items[1] {
+            BlockItem::Statement(Statement::Compound(block)) => {
+                assert_that!(block.items[1].clone(), eq(BlockItem::Statement(Statement::Return(Some(Expression::Var("b.3".to_owned()))))));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn identifiers_with_linkage_keep_their_names() {
+        let program = resolve("
+int total;
+int f(void) {
+    extern int total;
+    static int calls;
+    return total + calls;
+}");
+        let items = function(&program, 1).body.unwrap().items;
+        assert_that!(items[2].clone(), eq(BlockItem::Statement(Statement::Return(Some(Expression::Binary(
+            crate::ast::BinaryOperator::Add,
+            Box::new(Expression::Var("total".to_owned())),
+            Box::new(Expression::Var("calls.1".to_owned())),
+        ))))));
+    }
+
+    #[test]
+    fn local_extern_shadows_outer_local() {
+        let program = resolve("
+int x = 10;
+int main(void) {
+    int x = 1;
+    {
+        extern int x;
+        return x;
+    }
+}");
+        let items = function(&program, 1).body.unwrap().items;
+        match &items[1] {
+            BlockItem::Statement(Statement::Compound(block)) => {
+                assert_that!(block.items[1].clone(), eq(BlockItem::Statement(Statement::Return(Some(Expression::Var("x".to_owned()))))));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn undeclared_variable() {
+        assert_that!(errors("int main(void) { return x; }"), eq(vec!["Undeclared variable 'x'".to_owned()]));
+    }
+
+    #[test]
+    fn variable_used_outside_its_scope() {
+        assert_that!(errors("int main(void) { { int x = 1; } return x; }"), eq(vec!["Undeclared variable 'x'".to_owned()]));
+    }
+
+    #[test]
+    fn for_loop_variable_is_scoped_to_the_loop() {
+        assert_that!(
+            errors("int main(void) { for (int i = 0; i < 3; i = i + 1) ; return i; }"),
+            eq(vec!["Undeclared variable 'i'".to_owned()])
+        );
+    }
+
+    #[test]
+    fn undeclared_function() {
+        assert_that!(errors("int main(void) { return f(); }"), eq(vec!["Undeclared function 'f'".to_owned()]));
+    }
+
+    #[test]
+    fn duplicate_local() {
+        assert_that!(errors("int main(void) { int x; int x; return 0; }"), eq(vec!["Duplicate declaration of 'x'".to_owned()]));
+    }
+
+    #[test]
+    fn local_conflicts_with_parameter() {
+        assert_that!(errors("int f(int a) { int a; return a; }"), eq(vec!["Duplicate declaration of 'a'".to_owned()]));
+    }
+
+    #[test]
+    fn duplicate_parameter() {
+        assert_that!(errors("int f(int a, int a) { return a; }"), eq(vec!["Duplicate parameter 'a' in function 'f'".to_owned()]));
+    }
+
+    #[test]
+    fn unnamed_prototype_parameters_are_not_duplicates() {
+        let program = resolve("int f(int, int); int main(void) { return f(1, 2); }");
+        assert_that!(function(&program, 0).params, eq(vec!["".to_owned(), "".to_owned()]));
+    }
+
+    #[test]
+    fn extern_after_local_in_same_scope() {
+        assert_that!(errors("int main(void) { int x; extern int x; return x; }"), eq(vec!["Duplicate declaration of 'x'".to_owned()]));
+    }
+
+    #[test]
+    fn repeated_extern_in_same_scope_is_allowed() {
+        resolve("int main(void) { extern int x; extern int x; return x; }");
+    }
+
+    #[test]
+    fn nested_function_definition() {
+        assert_that!(
+            errors("int main(void) { int f(void) { return 1; } return f(); }"),
+            eq(vec!["Function 'f' cannot be defined inside another function".to_owned()])
+        );
+    }
+
+    #[test]
+    fn block_scope_static_function() {
+        assert_that!(
+            errors("int main(void) { static int f(void); return f(); }"),
+            eq(vec!["Block scope declaration of function 'f' cannot be static".to_owned()])
+        );
+    }
+
+    #[test]
+    fn file_scope_auto_and_register() {
+        assert_that!(errors("auto int a; register int b;"), eq(vec![
+            "File scope declaration of 'a' cannot be auto".to_owned(),
+            "File scope declaration of 'b' cannot be register".to_owned(),
+        ]));
+    }
+
+    #[test]
+    fn auto_function() {
+        assert_that!(errors("auto int f(void);"), eq(vec!["Declaration of function 'f' cannot be auto".to_owned()]));
+    }
+
+    #[test]
+    fn static_in_for_loop_initialiser() {
+        assert_that!(
+            errors("int main(void) { for (static int i = 0; i < 3; i = i + 1) ; return 0; }"),
+            eq(vec!["Declaration of 'i' in for loop initialiser cannot be static".to_owned()])
+        );
+    }
+
+    #[test]
+    fn invalid_lvalues() {
+        assert_that!(errors("int main(void) { int a; 2 = a; a + 1 = 3; ++3; (a = 1)++; return a; }"), eq(vec![
+            "Invalid lvalue".to_owned(),
+            "Invalid lvalue".to_owned(),
+            "Invalid lvalue".to_owned(),
+            "Invalid lvalue".to_owned(),
+        ]));
+    }
+}
@@ -0,0 +1,130 @@
+//! Label resolution: goto labels have function scope, so each function's labels are collected
+//! (reporting duplicates), every goto is checked against them (reporting undefined labels), and
+//! both are renamed to '<function>.<label>' so that they are unique across the whole program.
+
+use std::collections::HashSet;
+
+use crate::ast::{Block, BlockItem, Declaration, FunctionDeclaration, Program, Statement};
+use crate::semantic::SemanticResult;
+
+pub fn resolve_labels(program: Program) -> SemanticResult<Program> {
+    let mut errors = vec![];
+    let declarations = program
+        .declarations
+        .into_iter()
+        .map(|declaration| match declaration {
+            Declaration::Function(function) => Declaration::Function(resolve_function(function, &mut errors)),
+            Declaration::Variable(_) | Declaration::Enum(_) => declaration,
+        })
+        .collect();
+    if errors.is_empty() {
+        Ok(Program { declarations })
+    } else {
+        Err(errors)
+    }
+}
+
+fn resolve_function(mut function: FunctionDeclaration, errors: &mut Vec<String>) -> FunctionDeclaration {
+    if let Some(body) = &mut function.body {
+        let mut labels = HashSet::new();
+        collect_block(body, &function.name, &mut labels, errors);
+        let resolver = FunctionLabels { function: &function.name, labels: &labels };
+        resolver.rename_block(body, errors);
+    }
+    function
+}
+
+fn collect_block(block: &Block, function: &str, labels: &mut HashSet<String>, errors: &mut Vec<String>) {
+    for item in &block.items {
+        if let BlockItem::Statement(statement) = item {
+            collect_statement(statement, function, labels, errors);
+        }
+    }
+}
+
+fn collect_statement(statement: &Statement, function: &str, labels: &mut HashSet<String>, errors: &mut Vec<String>) {
+    match statement {
+        Statement::Labelled(label, inner) => {
+            if !labels.insert(label.clone()) {
+                errors.push(format!("Duplicate label '{}' in function '{}'", label, function));
+            }
+            collect_statement(inner, function, labels, errors);
+        }
+        Statement::If { then, otherwise, .. } => {
+            collect_statement(then, function, labels, errors);
+            if let Some(otherwise) = otherwise {
+                collect_statement(otherwise, function, labels, errors);
+            }
+        }
+        Statement::Compound(block) => collect_block(block, function, labels, errors),
+        Statement::While { body, .. }
+        | Statement::DoWhile { body, .. }
+        | Statement::For { body, .. }
+        | Statement::Switch { body, .. }
+        | Statement::Case(_, body)
+        | Statement::Default(body) => collect_statement(body, function, labels, errors),
+        Statement::Return(_)
+        | Statement::Expression(_)
+        | Statement::Break
+        | Statement::Continue
+        | Statement::Goto(_)
+        | Statement::Null => {}
+    }
+}
+
+struct FunctionLabels<'a> {
+    function: &'a str,
+    labels: &'a HashSet<String>,
+}
+
+impl FunctionLabels<'_> {
+    fn unique_name(&self, label: &str) -> String {
+        format!("{}.{}", self.function, label)
+    }
+
+    fn rename_block(&self, block: &mut Block, errors: &mut Vec<String>) {
+        for item in &mut block.items {
+            if let BlockItem::Statement(statement) = item {
+                self.rename_statement(statement, errors);
+            }
+        }
+    }
+
+    fn rename_statement(&self, statement: &mut Statement, errors: &mut Vec<String>) {
+        match statement {
+            Statement::Labelled(label, inner) => {
+                *label = self.unique_name(label);
+                self.rename_statement(inner, errors);
+            }
+            Statement::Goto(label) => {
+                if self.labels.contains(label) {
+                    *label = self.unique_name(label);
+                } else {
+                    errors.push(format!("Undefined label '{}' in function '{}'", label, self.function));
+                }
+            }
+            Statement::If { then, otherwise, .. } => {
+                self.rename_statement(then, errors);
+                if let Some(otherwise) = otherwise {
+                    self.rename_statement(otherwise, errors);
+                }
+            }
+            Statement::Compound(block) => self.rename_block(block, errors),
+            Statement::While { body, .. }
+            | Statement::DoWhile { body, .. }
+            | Statement::For { body, .. }
+            | Statement::Switch { body, .. }
+            | Statement::Case(_, body)
+            | Statement::Default(body) => self.rename_statement(body, errors),
+            Statement::Return(_)
+            | Statement::Expression(_)
+            | Statement::Break
+            | Statement::Continue
+            | Statement::Null => {}
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./label_resolution_spec.rs"]
+mod label_resolution_spec;
@@ -0,0 +1,126 @@
+mod label_resolution_spec {
+    use hamcrest2::prelude::*;
+
+    use crate::ast::{BlockItem, Declaration, Program, Statement};
+    use crate::program_test_helper::parsed;
+    use crate::semantic::label_resolution::resolve_labels;
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn statements(program: &Program, function: usize) -> Vec<Statement> {
+        let body = match &program.declarations[function] {
+            Declaration::Function(function) => function.body.clone().unwrap(),
+            _ => panic!("not a function"),
+        };
+        body.items.iter().filter_map(|item| match item {
+            BlockItem::Statement(statement) => Some(statement.clone()),
+            BlockItem::Declaration(_) => None,
+        }).collect()
+    }
+
+    #[test]
+    fn labels_are_renamed_uniquely_per_function() {
+        let program = parsed("
+int main(void) {
+    goto end;
+end:
+    return 0;
+}
+int other(void) {
+    goto end;
+end:
+    return 1;
+}");
+        let resolved = resolve_labels(program).unwrap();
+        assert_that!(statements(&resolved, 0)[0].clone(), eq(Statement::Goto("main.end".to_owned())));
+        assert_that!(matches!(&statements(&resolved, 0)[1], Statement::Labelled(label, _) if label == "main.end"), eq(true));
+        assert_that!(statements(&resolved, 1)[0].clone(), eq(Statement::Goto("other.end".to_owned())));
+        assert_that!(matches!(&statements(&resolved, 1)[1], Statement::Labelled(label, _) if label == "other.end"), eq(true));
+    }
+
+    #[test]
+    fn backward_goto_to_nested_label() {
+        let program = parsed("
+int main(void) {
+    int i = 0;
+    {
+        if (i) top: i = i + 1;
+    }
+    if (i < 10) goto top;
+    return i;
+}");
+        assert_that!(resolve_labels(program).is_ok(), eq(true));
+    }
+
+    #[test]
+    fn error_cleanup_idiom() {
+        let program = parsed("
+int open_all(int a, int b) {
+    int result = 0;
+    if (!a) goto fail_a;
+    if (!b) goto fail_b;
+    return 0;
+fail_b:
+    result = result + 1;
+fail_a:
+    result = result + 1;
+    return result;
+}");
+        assert_that!(resolve_labels(program).is_ok(), eq(true));
+    }
+
+    #[test]
+    fn undefined_label() {
+        let program = parsed("
+int main(void) {
+    goto nowhere;
+    return 0;
+}");
+        assert_that!(resolve_labels(program).unwrap_err(), eq(vec!["Undefined label 'nowhere' in function 'main'".to_owned()]));
+    }
+
+    #[test]
+    fn labels_are_not_visible_in_other_functions() {
+        let program = parsed("
+int first(void) {
+here:
+    return 0;
+}
+int second(void) {
+    goto here;
+}");
+        assert_that!(resolve_labels(program).unwrap_err(), eq(vec!["Undefined label 'here' in function 'second'".to_owned()]));
+    }
+
+    #[test]
+    fn duplicate_label() {
+        let program = parsed("
+int main(void) {
+twice:
+    ;
+    {
+twice:
+        return 0;
+    }
+}");
+        assert_that!(resolve_labels(program).unwrap_err(), eq(vec!["Duplicate label 'twice' in function 'main'".to_owned()]));
+    }
+
+    #[test]
+    fn all_errors_are_reported() {
+        let program = parsed("
+int main(void) {
+    goto a;
+dup: dup:
+    goto b;
+}");
+        assert_that!(resolve_labels(program).unwrap_err(), eq(vec![
+            "Duplicate label 'dup' in function 'main'".to_owned(),
+            "Undefined label 'a' in function 'main'".to_owned(),
+            "Undefined label 'b' in function 'main'".to_owned(),
+        ]));
+    }
+}
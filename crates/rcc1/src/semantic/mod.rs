@@ -0,0 +1,23 @@
+//! Semantic analysis: passes over the AST that validate it and rewrite it ready for IR generation.
+//! Each pass reports all the problems it finds, rather than stopping at the first.
+
+use common::data_model::DataModel;
+
+use crate::ast::Program;
+use crate::semantic::symbol_table::SymbolTable;
+
+pub mod constant_evaluation;
+pub mod identifier_resolution;
+pub mod label_resolution;
+pub mod symbol_table;
+pub mod type_checking;
+pub mod types;
+
+pub type SemanticResult<T> = Result<T, Vec<String>>;
+
+/// Type checking, and so the whole analysis, depends on the sizes of the types on the target.
+pub fn analyse(program: Program, data_model: &DataModel) -> SemanticResult<(Program, SymbolTable)> {
+    let program = identifier_resolution::resolve_identifiers(program)?;
+    let program = label_resolution::resolve_labels(program)?;
+    type_checking::check_types(program, data_model)
+}
@@ -0,0 +1,67 @@
+//! The symbol table records the type, linkage and storage duration of every identifier in the
+//! program, keyed by the unique names given to them by identifier resolution. It is built by
+//! type checking, and consulted by the later stages, e.g. to decide which symbols are emitted as
+//! visible to other modules.
+
+use std::collections::BTreeMap;
+
+use crate::ast::{Constant, Type};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InitialValue {
+    /// A file scope declaration without an initialiser or 'extern': it becomes a zero-initialised
+    /// definition unless some other declaration of the same variable supplies an initialiser.
+    Tentative,
+    /// The initialiser's value, converted to the variable's type.
+    Initial(Constant),
+    /// An 'extern' declaration: the definition is elsewhere.
+    NoInitialiser,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdentifierAttributes {
+    Function { defined: bool, global: bool },
+    /// Variables with static storage duration: file scope variables, and block scope 'static'
+    /// and 'extern' variables.
+    Static { initial_value: InitialValue, global: bool },
+    /// Variables with automatic storage duration, including parameters.
+    Local,
+    /// Enumeration constants, which are always of type 'int'.
+    EnumConstant(Constant),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub symbol_type: Type,
+    pub attributes: IdentifierAttributes,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolTable {
+    symbols: BTreeMap<String, Symbol>,
+}
+
+impl SymbolTable {
+    pub fn get(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.get(name)
+    }
+
+    pub fn insert(&mut self, name: &str, symbol: Symbol) {
+        self.symbols.insert(name.to_owned(), symbol);
+    }
+
+    /// All symbols, ordered by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Symbol)> {
+        self.symbols.iter()
+    }
+
+    /// Does the named function or variable have external linkage, and so should be visible to
+    /// other modules when linking?
+    pub fn is_global(&self, name: &str) -> bool {
+        match self.symbols.get(name).map(|symbol| &symbol.attributes) {
+            Some(IdentifierAttributes::Function { global, .. }) => *global,
+            Some(IdentifierAttributes::Static { global, .. }) => *global,
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,640 @@
+//! Type checking: builds the symbol table, checking that every use of an identifier agrees with
+//! its declarations, and that the declarations agree with each other. This is where linkage is
+//! resolved, and C89 tentative definitions of file scope variables are merged.
+//!
+//! The AST is rewritten so that later stages need not know C's conversion rules: every implicit
+//! conversion becomes an explicit cast, literals become constants of the type they have on the
+//! target, and 'sizeof', enumeration constants, case labels and static initialisers are
+//! evaluated. The operators that only apply to integers are rejected for floating operands.
+
+use std::collections::HashSet;
+
+use common::data_model::DataModel;
+use log::warn;
+
+use crate::ast::{
+    BinaryOperator, Block, BlockItem, Constant, Declaration, EnumDeclaration, Enumerator, Expression,
+    ForInit, FunctionDeclaration, Literal, Program, Statement, StorageClass, Type, UnaryOperator,
+    VariableDeclaration,
+};
+use crate::semantic::constant_evaluation::{ConstantError, ConstantEvaluator};
+use crate::semantic::symbol_table::{IdentifierAttributes, InitialValue, Symbol, SymbolTable};
+use crate::semantic::types::{common_type, literal_constant, size_type};
+use crate::semantic::SemanticResult;
+
+/// An enclosing switch statement.
+struct Switch {
+    controlling_type: Type,
+    cases: HashSet<i128>,
+    has_default: bool,
+}
+
+struct TypeChecker<'a> {
+    data_model: &'a DataModel,
+    symbols: SymbolTable,
+    errors: Vec<String>,
+    /// The name and return type of the function whose body is being checked.
+    function: (String, Type),
+    switches: Vec<Switch>,
+    /// The number of loops enclosing the statement being checked.
+    loops: usize,
+}
+
+pub fn check_types(program: Program, data_model: &DataModel) -> SemanticResult<(Program, SymbolTable)> {
+    let mut checker = TypeChecker {
+        data_model,
+        symbols: SymbolTable::default(),
+        errors: vec![],
+        function: (String::new(), Type::Void),
+        switches: vec![],
+        loops: 0,
+    };
+    let declarations = program
+        .declarations
+        .into_iter()
+        .map(|declaration| match declaration {
+            Declaration::Function(function) => Declaration::Function(checker.function_declaration(function)),
+            Declaration::Variable(variable) => Declaration::Variable(checker.file_scope_variable_declaration(variable)),
+            Declaration::Enum(enum_declaration) => Declaration::Enum(checker.enum_declaration(enum_declaration)),
+        })
+        .collect();
+    if checker.errors.is_empty() {
+        Ok((Program { declarations }, checker.symbols))
+    } else {
+        Err(checker.errors)
+    }
+}
+
+/// The expression converted from one type to another, if they differ.
+fn convert(expression: Expression, from: &Type, to: &Type) -> Expression {
+    if from == to {
+        expression
+    } else {
+        Expression::Cast(to.clone(), Box::new(expression))
+    }
+}
+
+/// The symbol of a binary operator whose operands must be integers.
+fn integer_operator(operator: BinaryOperator) -> Option<&'static str> {
+    match operator {
+        BinaryOperator::Remainder => Some("%"),
+        BinaryOperator::BitwiseAnd => Some("&"),
+        BinaryOperator::BitwiseOr => Some("|"),
+        BinaryOperator::BitwiseXor => Some("^"),
+        BinaryOperator::ShiftLeft => Some("<<"),
+        BinaryOperator::ShiftRight => Some(">>"),
+        _ => None,
+    }
+}
+
+impl TypeChecker<'_> {
+    fn function_declaration(&mut self, function: FunctionDeclaration) -> FunctionDeclaration {
+        let has_body = function.body.is_some();
+        let mut already_defined = false;
+        let mut global = function.storage_class != Some(StorageClass::Static);
+
+        if let Some(old) = self.symbols.get(&function.name) {
+            if old.symbol_type != function.function_type {
+                self.errors.push(format!("Incompatible declarations of function '{}'", function.name));
+            }
+            if let IdentifierAttributes::Function { defined, global: old_global } = old.attributes {
+                already_defined = defined;
+                if already_defined && has_body {
+                    self.errors.push(format!("Function '{}' is defined more than once", function.name));
+                }
+                if old_global && function.storage_class == Some(StorageClass::Static) {
+                    self.errors.push(format!("Static declaration of function '{}' follows non-static declaration", function.name));
+                }
+                global = old_global;
+            }
+        }
+
+        self.symbols.insert(&function.name, Symbol {
+            symbol_type: function.function_type.clone(),
+            attributes: IdentifierAttributes::Function { defined: already_defined || has_body, global },
+        });
+
+        let body = function.body.map(|body| {
+            if let Type::Function { params, return_type } = &function.function_type {
+                for (param, param_type) in function.params.iter().zip(params) {
+                    self.symbols.insert(param, Symbol { symbol_type: param_type.clone(), attributes: IdentifierAttributes::Local });
+                }
+                self.function = (function.name.clone(), *return_type.clone());
+            }
+            self.block(body)
+        });
+        FunctionDeclaration { body, ..function }
+    }
+
+    /// Type checks and evaluates a constant expression, reporting any warnings.
+    fn constant(&mut self, expression: Expression) -> Result<Constant, ConstantError> {
+        let (expression, _) = self.value(expression);
+        let mut evaluator = ConstantEvaluator::new(self.data_model);
+        let constant = evaluator.evaluate(&expression);
+        evaluator.warnings.iter().for_each(|warning| warn!("{}", warning));
+        constant
+    }
+
+    /// Converts a constant, warning if the conversion changes its value, e.g. initialising an
+    /// 'int' with 40000 on EPOC16, or with 2.5. Rounding to a floating type is not reported, as
+    /// most decimal fractions cannot be represented exactly anyway.
+    fn converted_constant(&self, constant: Constant, to: &Type) -> Constant {
+        let converted = constant.convert(to, self.data_model);
+        let changed = match (constant.constant_type.is_floating(), to.is_floating()) {
+            (_, true) => false,
+            (true, false) => Constant::double(constant.floating()) != converted.convert(&Type::Double, self.data_model),
+            (false, false) => converted.value != constant.value,
+        };
+        if changed {
+            warn!("Conversion from '{}' to '{}' changes value from {} to {}", constant.constant_type, to, constant, converted);
+        }
+        converted
+    }
+
+    /// '~', '%', the bitwise operators and the shifts only apply to integers.
+    fn check_integer_operands(&mut self, operator: &str, operand_types: &[&Type]) {
+        if let Some(floating) = operand_types.iter().find(|operand_type| operand_type.is_floating()) {
+            self.errors.push(format!("Invalid operand of type '{}' to '{}'", floating, operator));
+        }
+    }
+
+    /// The value of a static variable's initialiser, converted to the variable's type.
+    fn static_initialiser(&mut self, init: Expression, variable: &VariableDeclaration, scope: &str) -> Option<Constant> {
+        match self.constant(init) {
+            Ok(constant) => Some(self.converted_constant(constant, &variable.variable_type)),
+            Err(ConstantError::NotConstant) => {
+                self.errors.push(format!("Non-constant initialiser for {} variable '{}'", scope, variable.name));
+                None
+            }
+            Err(error) => {
+                self.errors.push(error.to_string());
+                None
+            }
+        }
+    }
+
+    fn zero(&self, zero_type: &Type) -> Constant {
+        Constant { constant_type: zero_type.clone(), value: 0 }
+    }
+
+    fn file_scope_variable_declaration(&mut self, variable: VariableDeclaration) -> VariableDeclaration {
+        let init = variable
+            .init
+            .clone()
+            .map(|init| self.static_initialiser(init, &variable, "file scope").unwrap_or_else(|| self.zero(&variable.variable_type)));
+        let mut initial_value = match &init {
+            Some(constant) => InitialValue::Initial(constant.clone()),
+            None if variable.storage_class == Some(StorageClass::Extern) => InitialValue::NoInitialiser,
+            None => InitialValue::Tentative,
+        };
+        let mut global = variable.storage_class != Some(StorageClass::Static);
+
+        if let Some(old) = self.symbols.get(&variable.name) {
+            match &old.attributes {
+                IdentifierAttributes::Static { initial_value: old_initial_value, global: old_global } => {
+                    if old.symbol_type != variable.variable_type {
+                        self.errors.push(format!("Conflicting types for '{}'", variable.name));
+                    }
+                    if variable.storage_class == Some(StorageClass::Extern) {
+                        // 'extern' inherits the linkage of a prior declaration.
+                        global = *old_global;
+                    } else if *old_global != global {
+                        self.errors.push(format!("Conflicting linkage for variable '{}'", variable.name));
+                    }
+                    match (old_initial_value, &initial_value) {
+                        (InitialValue::Initial(_), InitialValue::Initial(_)) => {
+                            self.errors.push(format!("Conflicting definitions of variable '{}'", variable.name));
+                        }
+                        (InitialValue::Initial(_), _) => initial_value = old_initial_value.clone(),
+                        (InitialValue::Tentative, InitialValue::NoInitialiser) => initial_value = InitialValue::Tentative,
+                        _ => {}
+                    }
+                }
+                _ => self.errors.push(format!("'{}' redeclared as a different kind of symbol", variable.name)),
+            }
+        }
+
+        self.symbols.insert(&variable.name, Symbol {
+            symbol_type: variable.variable_type.clone(),
+            attributes: IdentifierAttributes::Static { initial_value, global },
+        });
+        VariableDeclaration { init: init.map(Expression::Constant), ..variable }
+    }
+
+    fn local_variable_declaration(&mut self, variable: VariableDeclaration) -> VariableDeclaration {
+        match variable.storage_class {
+            Some(StorageClass::Extern) => {
+                if variable.init.is_some() {
+                    self.errors.push(format!("Block scope extern declaration of '{}' cannot have an initialiser", variable.name));
+                }
+                match self.symbols.get(&variable.name) {
+                    Some(old) => match old.symbol_type {
+                        Type::Function { .. } => {
+                            self.errors.push(format!("'{}' redeclared as a different kind of symbol", variable.name));
+                        }
+                        _ if old.symbol_type != variable.variable_type => {
+                            self.errors.push(format!("Conflicting types for '{}'", variable.name));
+                        }
+                        _ => {}
+                    },
+                    None => self.symbols.insert(&variable.name, Symbol {
+                        symbol_type: variable.variable_type.clone(),
+                        attributes: IdentifierAttributes::Static { initial_value: InitialValue::NoInitialiser, global: true },
+                    }),
+                }
+                variable
+            }
+            Some(StorageClass::Static) => {
+                let init = match variable.init.clone() {
+                    Some(init) => self.static_initialiser(init, &variable, "static"),
+                    None => None,
+                }
+                .unwrap_or_else(|| self.zero(&variable.variable_type));
+                self.symbols.insert(&variable.name, Symbol {
+                    symbol_type: variable.variable_type.clone(),
+                    attributes: IdentifierAttributes::Static { initial_value: InitialValue::Initial(init.clone()), global: false },
+                });
+                VariableDeclaration { init: Some(Expression::Constant(init)), ..variable }
+            }
+            Some(StorageClass::Auto) | Some(StorageClass::Register) | None => {
+                self.symbols.insert(&variable.name, Symbol {
+                    symbol_type: variable.variable_type.clone(),
+                    attributes: IdentifierAttributes::Local,
+                });
+                let init = variable.init.map(|init| {
+                    let (init, init_type) = self.value(init);
+                    convert(init, &init_type, &variable.variable_type)
+                });
+                VariableDeclaration { init, ..variable }
+            }
+        }
+    }
+
+    /// Each enumerator without a value has the value of the one before plus one, starting at zero.
+    /// All must be representable as an 'int'.
+    fn enum_declaration(&mut self, enum_declaration: EnumDeclaration) -> EnumDeclaration {
+        let mut next = 0;
+        let enumerators = enum_declaration
+            .enumerators
+            .into_iter()
+            .map(|enumerator| {
+                let value = match enumerator.value {
+                    Some(value) => match self.constant(value) {
+                        Ok(constant) if constant.constant_type.is_floating() => {
+                            self.errors.push(format!("Value of enumerator '{}' is not an integer", enumerator.name));
+                            next
+                        }
+                        Ok(constant) => constant.value,
+                        Err(ConstantError::NotConstant) => {
+                            self.errors.push(format!("Non-constant value for enumerator '{}'", enumerator.name));
+                            next
+                        }
+                        Err(error) => {
+                            self.errors.push(error.to_string());
+                            next
+                        }
+                    },
+                    None => next,
+                };
+                if !(Type::Int.min_value(self.data_model)..=Type::Int.max_value(self.data_model)).contains(&value) {
+                    self.errors.push(format!("Value {} of enumerator '{}' is out of range of 'int'", value, enumerator.name));
+                }
+                next = value + 1;
+                let constant = Constant::wrapping(Type::Int, value, self.data_model);
+                self.symbols.insert(&enumerator.name, Symbol {
+                    symbol_type: Type::Int,
+                    attributes: IdentifierAttributes::EnumConstant(constant.clone()),
+                });
+                Enumerator { value: Some(Expression::Constant(constant)), ..enumerator }
+            })
+            .collect();
+        EnumDeclaration { enumerators, ..enum_declaration }
+    }
+
+    fn block(&mut self, block: Block) -> Block {
+        let items = block
+            .items
+            .into_iter()
+            .map(|item| match item {
+                BlockItem::Statement(statement) => BlockItem::Statement(self.statement(statement)),
+                BlockItem::Declaration(Declaration::Function(function)) => {
+                    BlockItem::Declaration(Declaration::Function(self.function_declaration(function)))
+                }
+                BlockItem::Declaration(Declaration::Variable(variable)) => {
+                    BlockItem::Declaration(Declaration::Variable(self.local_variable_declaration(variable)))
+                }
+                BlockItem::Declaration(Declaration::Enum(enum_declaration)) => {
+                    BlockItem::Declaration(Declaration::Enum(self.enum_declaration(enum_declaration)))
+                }
+            })
+            .collect();
+        Block { items }
+    }
+
+    fn statement(&mut self, statement: Statement) -> Statement {
+        match statement {
+            Statement::Return(Some(expression)) => {
+                let (function, return_type) = self.function.clone();
+                let (expression, expression_type) = self.value(expression);
+                if return_type == Type::Void {
+                    self.errors.push(format!("Return with a value in function '{}' returning void", function));
+                }
+                Statement::Return(Some(convert(expression, &expression_type, &return_type)))
+            }
+            Statement::Return(None) => statement,
+            Statement::Expression(expression) => Statement::Expression(self.expression(expression).0),
+            Statement::If { condition, then, otherwise } => Statement::If {
+                condition: self.value(condition).0,
+                then: Box::new(self.statement(*then)),
+                otherwise: otherwise.map(|otherwise| Box::new(self.statement(*otherwise))),
+            },
+            Statement::Compound(block) => Statement::Compound(self.block(block)),
+            Statement::While { condition, body } => Statement::While {
+                condition: self.value(condition).0,
+                body: Box::new(self.loop_body(*body)),
+            },
+            Statement::DoWhile { body, condition } => Statement::DoWhile {
+                body: Box::new(self.loop_body(*body)),
+                condition: self.value(condition).0,
+            },
+            Statement::For { init, condition, post, body } => Statement::For {
+                init: match init {
+                    ForInit::Declaration(variable) => ForInit::Declaration(self.local_variable_declaration(variable)),
+                    ForInit::Expression(expression) => ForInit::Expression(expression.map(|e| self.expression(e).0)),
+                },
+                condition: condition.map(|condition| self.value(condition).0),
+                post: post.map(|post| self.expression(post).0),
+                body: Box::new(self.loop_body(*body)),
+            },
+            Statement::Switch { condition, body } => {
+                let (condition, condition_type) = self.value(condition);
+                if condition_type.is_floating() {
+                    self.errors.push(format!("Switch statement on a value of type '{}', which is not an integer", condition_type));
+                }
+                let controlling_type = condition_type.promoted(self.data_model);
+                let condition = convert(condition, &condition_type, &controlling_type);
+                self.switches.push(Switch { controlling_type, cases: HashSet::new(), has_default: false });
+                let body = self.statement(*body);
+                self.switches.pop();
+                Statement::Switch { condition, body: Box::new(body) }
+            }
+            Statement::Case(label, statement) => {
+                let label = self.case_label(label);
+                Statement::Case(Expression::Constant(label), Box::new(self.statement(*statement)))
+            }
+            Statement::Default(statement) => {
+                match self.switches.last_mut() {
+                    Some(switch) if switch.has_default => {
+                        self.errors.push("Multiple default labels in one switch statement".to_owned());
+                    }
+                    Some(switch) => switch.has_default = true,
+                    None => self.errors.push("Default label not within a switch statement".to_owned()),
+                }
+                Statement::Default(Box::new(self.statement(*statement)))
+            }
+            Statement::Labelled(label, statement) => Statement::Labelled(label, Box::new(self.statement(*statement))),
+            Statement::Break => {
+                if self.loops == 0 && self.switches.is_empty() {
+                    self.errors.push("Break statement not within a loop or switch".to_owned());
+                }
+                statement
+            }
+            Statement::Continue => {
+                if self.loops == 0 {
+                    self.errors.push("Continue statement not within a loop".to_owned());
+                }
+                statement
+            }
+            Statement::Goto(_) | Statement::Null => statement,
+        }
+    }
+
+    fn loop_body(&mut self, body: Statement) -> Statement {
+        self.loops += 1;
+        let body = self.statement(body);
+        self.loops -= 1;
+        body
+    }
+
+    /// A case label is converted to the promoted type of its switch's controlling expression, and
+    /// must differ from the switch's other case labels after conversion.
+    fn case_label(&mut self, label: Expression) -> Constant {
+        let label = match self.constant(label) {
+            Ok(constant) if constant.constant_type.is_floating() => {
+                self.errors.push("Case label is not an integer constant expression".to_owned());
+                Constant::int(0)
+            }
+            Ok(constant) => constant,
+            Err(ConstantError::NotConstant) => {
+                self.errors.push("Case label is not a constant expression".to_owned());
+                Constant::int(0)
+            }
+            Err(error) => {
+                self.errors.push(error.to_string());
+                Constant::int(0)
+            }
+        };
+        let Some(controlling_type) = self.switches.last().map(|switch| switch.controlling_type.clone()) else {
+            self.errors.push("Case label not within a switch statement".to_owned());
+            return label;
+        };
+        let label = self.converted_constant(label, &controlling_type);
+        let switch = self.switches.last_mut().expect("within a switch");
+        if !switch.cases.insert(label.value) {
+            self.errors.push(format!("Duplicate case value {}", label.value));
+        }
+        label
+    }
+
+    /// Type checks an expression whose value is used, which so must not be 'void'.
+    fn value(&mut self, expression: Expression) -> (Expression, Type) {
+        let (expression, expression_type) = self.expression(expression);
+        if expression_type == Type::Void {
+            self.errors.push("Void value used in expression".to_owned());
+        }
+        (expression, expression_type)
+    }
+
+    /// Returns the type checked expression and its type.
+    fn expression(&mut self, expression: Expression) -> (Expression, Type) {
+        match expression {
+            Expression::Literal(literal) => match literal_constant(&literal, self.data_model) {
+                Some(constant) => {
+                    let constant_type = constant.constant_type.clone();
+                    (Expression::Constant(constant), constant_type)
+                }
+                None => {
+                    let (Literal::Decimal(value)
+                    | Literal::OctalOrHexadecimal(value)
+                    | Literal::Unsigned(value)
+                    | Literal::Long(value)
+                    | Literal::UnsignedLong(value)) = literal
+                    else {
+                        unreachable!("floating literals always have a constant")
+                    };
+                    self.errors.push(format!("Integer constant {} is too large for its type", value));
+                    (Expression::Constant(Constant::int(0)), Type::Int)
+                }
+            },
+            Expression::Constant(ref constant) => {
+                let constant_type = constant.constant_type.clone();
+                (expression, constant_type)
+            }
+            Expression::Var(name) => match self.symbols.get(&name) {
+                Some(Symbol { attributes: IdentifierAttributes::EnumConstant(constant), .. }) => {
+                    (Expression::Constant(constant.clone()), Type::Int)
+                }
+                Some(Symbol { symbol_type: Type::Function { .. }, .. }) => {
+                    self.errors.push(format!("Function '{}' used as a variable", name));
+                    (Expression::Var(name), Type::Int)
+                }
+                Some(symbol) => {
+                    let variable_type = symbol.symbol_type.clone();
+                    (Expression::Var(name), variable_type)
+                }
+                None => (Expression::Var(name), Type::Int),
+            },
+            Expression::Cast(target, operand) => {
+                let (operand, _) = if target == Type::Void { self.expression(*operand) } else { self.value(*operand) };
+                (Expression::Cast(target.clone(), Box::new(operand)), target)
+            }
+            Expression::Unary(operator, operand) => {
+                let (operand, operand_type) = self.value(*operand);
+                if operator == UnaryOperator::Complement {
+                    self.check_integer_operands("~", &[&operand_type]);
+                }
+                match operator {
+                    UnaryOperator::Negate | UnaryOperator::Complement => {
+                        let promoted = operand_type.promoted(self.data_model);
+                        let operand = convert(operand, &operand_type, &promoted);
+                        (Expression::Unary(operator, Box::new(operand)), promoted)
+                    }
+                    UnaryOperator::Not => (Expression::Unary(operator, Box::new(operand)), Type::Int),
+                    UnaryOperator::PreIncrement
+                    | UnaryOperator::PreDecrement
+                    | UnaryOperator::PostIncrement
+                    | UnaryOperator::PostDecrement => (Expression::Unary(operator, Box::new(operand)), operand_type),
+                }
+            }
+            Expression::Binary(operator, left, right) => self.binary(operator, *left, *right),
+            Expression::Assignment(left, right) => {
+                let (left, left_type) = self.value(*left);
+                let (right, right_type) = self.value(*right);
+                let right = convert(right, &right_type, &left_type);
+                (Expression::Assignment(Box::new(left), Box::new(right)), left_type)
+            }
+            Expression::CompoundAssignment(operator, left, right) => {
+                let (left, left_type) = self.value(*left);
+                let (right, right_type) = self.value(*right);
+                if let Some(symbol) = integer_operator(operator) {
+                    self.check_integer_operands(&format!("{}=", symbol), &[&left_type, &right_type]);
+                }
+                let operation_type = match operator {
+                    BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => right_type.promoted(self.data_model),
+                    _ => common_type(&left_type, &right_type, self.data_model),
+                };
+                let right = convert(right, &right_type, &operation_type);
+                (Expression::CompoundAssignment(operator, Box::new(left), Box::new(right)), left_type)
+            }
+            Expression::Conditional(condition, then, otherwise) => {
+                let (condition, _) = self.value(*condition);
+                let (then, then_type) = self.expression(*then);
+                let (otherwise, otherwise_type) = self.expression(*otherwise);
+                let result_type = match (&then_type, &otherwise_type) {
+                    (Type::Void, Type::Void) => Type::Void,
+                    (Type::Void, _) | (_, Type::Void) => {
+                        self.errors.push("Void value used in expression".to_owned());
+                        Type::Void
+                    }
+                    _ => common_type(&then_type, &otherwise_type, self.data_model),
+                };
+                let then = convert(then, &then_type, &result_type);
+                let otherwise = convert(otherwise, &otherwise_type, &result_type);
+                (Expression::Conditional(Box::new(condition), Box::new(then), Box::new(otherwise)), result_type)
+            }
+            Expression::FunctionCall(name, arguments) => {
+                let (params, return_type) = match self.symbols.get(&name).map(|symbol| &symbol.symbol_type) {
+                    Some(Type::Function { params, return_type }) => {
+                        if params.len() != arguments.len() {
+                            self.errors.push(format!("Function '{}' called with the wrong number of arguments", name));
+                        }
+                        (params.clone(), *return_type.clone())
+                    }
+                    Some(_) => {
+                        self.errors.push(format!("Variable '{}' called as a function", name));
+                        (vec![], Type::Int)
+                    }
+                    None => (vec![], Type::Int),
+                };
+                let arguments = arguments
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, argument)| {
+                        let (argument, argument_type) = self.value(argument);
+                        match params.get(index) {
+                            Some(param_type) => convert(argument, &argument_type, param_type),
+                            None => argument,
+                        }
+                    })
+                    .collect();
+                (Expression::FunctionCall(name, arguments), return_type)
+            }
+            Expression::SizeOfType(sized_type) => (self.size_of(&sized_type), size_type(self.data_model)),
+            Expression::SizeOfExpression(operand) => {
+                // The operand is not evaluated, only its type is needed.
+                let (_, operand_type) = self.value(*operand);
+                (self.size_of(&operand_type), size_type(self.data_model))
+            }
+        }
+    }
+
+    fn size_of(&mut self, sized_type: &Type) -> Expression {
+        if !sized_type.is_arithmetic() {
+            self.errors.push(format!("Invalid application of 'sizeof' to type '{}'", sized_type));
+        }
+        Expression::Constant(Constant {
+            constant_type: size_type(self.data_model),
+            value: sized_type.size(self.data_model) as i128,
+        })
+    }
+
+    fn binary(&mut self, operator: BinaryOperator, left: Expression, right: Expression) -> (Expression, Type) {
+        let (left, left_type) = self.value(left);
+        let (right, right_type) = self.value(right);
+        if let Some(symbol) = integer_operator(operator) {
+            self.check_integer_operands(symbol, &[&left_type, &right_type]);
+        }
+        let binary = |left, right| Expression::Binary(operator, Box::new(left), Box::new(right));
+        match operator {
+            BinaryOperator::And | BinaryOperator::Or => (binary(left, right), Type::Int),
+            // The operands of a shift are promoted separately, and the result has the type of the
+            // left.
+            BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => {
+                let promoted_left = left_type.promoted(self.data_model);
+                let promoted_right = right_type.promoted(self.data_model);
+                let left = convert(left, &left_type, &promoted_left);
+                let right = convert(right, &right_type, &promoted_right);
+                (binary(left, right), promoted_left)
+            }
+            _ => {
+                let operation_type = common_type(&left_type, &right_type, self.data_model);
+                let left = convert(left, &left_type, &operation_type);
+                let right = convert(right, &right_type, &operation_type);
+                let result_type = match operator {
+                    BinaryOperator::Equal
+                    | BinaryOperator::NotEqual
+                    | BinaryOperator::LessThan
+                    | BinaryOperator::LessOrEqual
+                    | BinaryOperator::GreaterThan
+                    | BinaryOperator::GreaterOrEqual => Type::Int,
+                    _ => operation_type,
+                };
+                (binary(left, right), result_type)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./type_checking_spec.rs"]
+mod type_checking_spec;
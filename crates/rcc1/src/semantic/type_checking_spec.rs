@@ -0,0 +1,360 @@
+mod type_checking_spec {
+    use hamcrest2::prelude::*;
+
+    use common::target_platform::TargetPlatform;
+
+    use crate::ast::{Constant, Type};
+    use crate::program_test_helper::parsed;
+    use crate::semantic::analyse;
+    use crate::semantic::symbol_table::{IdentifierAttributes, InitialValue, SymbolTable};
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn symbols(input: &str) -> SymbolTable {
+        symbols_for(input, TargetPlatform::default())
+    }
+
+    fn symbols_for(input: &str, target_platform: TargetPlatform) -> SymbolTable {
+        analyse(parsed(input), &target_platform.data_model()).unwrap().1
+    }
+
+    fn errors(input: &str) -> Vec<String> {
+        analyse(parsed(input), &TargetPlatform::default().data_model()).unwrap_err()
+    }
+
+    fn initial(constant_type: Type, value: i128) -> InitialValue {
+        InitialValue::Initial(Constant { constant_type, value })
+    }
+
+    fn attributes(symbols: &SymbolTable, name: &str) -> IdentifierAttributes {
+        symbols.get(name).unwrap().attributes.clone()
+    }
+
+    fn static_variable(initial_value: InitialValue, global: bool) -> IdentifierAttributes {
+        IdentifierAttributes::Static { initial_value, global }
+    }
+
+    #[test]
+    fn tentative_definitions_are_merged() {
+        let symbols = symbols("int x; int x; extern int x;");
+        assert_that!(attributes(&symbols, "x"), eq(static_variable(InitialValue::Tentative, true)));
+    }
+
+    #[test]
+    fn tentative_definitions_merge_with_a_definition() {
+        let symbols = symbols("int x; int x = 3; int x;");
+        assert_that!(attributes(&symbols, "x"), eq(static_variable(initial(Type::Int, 3), true)));
+    }
+
+    #[test]
+    fn extern_declaration_without_definition() {
+        let symbols = symbols("extern int x;");
+        assert_that!(attributes(&symbols, "x"), eq(static_variable(InitialValue::NoInitialiser, true)));
+    }
+
+    #[test]
+    fn extern_definition() {
+        let symbols = symbols("extern int x = 4;");
+        assert_that!(attributes(&symbols, "x"), eq(static_variable(initial(Type::Int, 4), true)));
+    }
+
+    #[test]
+    fn extern_inherits_internal_linkage() {
+        let symbols = symbols("static int x; extern int x;");
+        assert_that!(attributes(&symbols, "x"), eq(static_variable(InitialValue::Tentative, false)));
+        assert_that!(symbols.is_global("x"), eq(false));
+    }
+
+    #[test]
+    fn conflicting_definitions() {
+        assert_that!(errors("int x = 1; int x = 2;"), eq(vec!["Conflicting definitions of variable 'x'".to_owned()]));
+    }
+
+    #[test]
+    fn conflicting_linkage() {
+        assert_that!(errors("int x; static int x;"), eq(vec!["Conflicting linkage for variable 'x'".to_owned()]));
+        assert_that!(errors("static int x; int x;"), eq(vec!["Conflicting linkage for variable 'x'".to_owned()]));
+    }
+
+    #[test]
+    fn non_constant_file_scope_initialiser() {
+        assert_that!(errors("int x = 1; int y = x;"), eq(vec!["Non-constant initialiser for file scope variable 'y'".to_owned()]));
+    }
+
+    #[test]
+    fn static_local_variables() {
+        let symbols = symbols("int f(void) { static int calls; static int start = 5; int automatic; return calls; }");
+        assert_that!(attributes(&symbols, "calls.1"), eq(static_variable(initial(Type::Int, 0), false)));
+        assert_that!(attributes(&symbols, "start.2"), eq(static_variable(initial(Type::Int, 5), false)));
+        assert_that!(attributes(&symbols, "automatic.3"), eq(IdentifierAttributes::Local));
+    }
+
+    #[test]
+    fn auto_and_register_locals_are_automatic() {
+        let symbols = symbols("int f(void) { auto int a = 1; register int r = 2; return a + r; }");
+        assert_that!(attributes(&symbols, "a.1"), eq(IdentifierAttributes::Local));
+        assert_that!(attributes(&symbols, "r.2"), eq(IdentifierAttributes::Local));
+    }
+
+    #[test]
+    fn non_constant_static_local_initialiser() {
+        assert_that!(
+            errors("int f(int a) { static int b = a; return b; }"),
+            eq(vec!["Non-constant initialiser for static variable 'b.2'".to_owned()])
+        );
+    }
+
+    #[test]
+    fn local_extern_refers_to_file_scope_variable() {
+        let symbols = symbols("int f(void) { extern int shared; return shared; } int shared = 7;");
+        assert_that!(attributes(&symbols, "shared"), eq(static_variable(initial(Type::Int, 7), true)));
+    }
+
+    #[test]
+    fn local_extern_with_initialiser() {
+        assert_that!(
+            errors("int f(void) { extern int x = 1; return x; }"),
+            eq(vec!["Block scope extern declaration of 'x' cannot have an initialiser".to_owned()])
+        );
+    }
+
+    #[test]
+    fn function_linkage() {
+        let symbols = symbols("static int helper(void); int helper(void) { return 1; } int api(void) { return helper(); }");
+        assert_that!(attributes(&symbols, "helper"), eq(IdentifierAttributes::Function { defined: true, global: false }));
+        assert_that!(attributes(&symbols, "api"), eq(IdentifierAttributes::Function { defined: true, global: true }));
+        assert_that!(symbols.is_global("helper"), eq(false));
+        assert_that!(symbols.is_global("api"), eq(true));
+    }
+
+    #[test]
+    fn static_function_follows_non_static() {
+        assert_that!(
+            errors("int f(void); static int f(void) { return 0; }"),
+            eq(vec!["Static declaration of function 'f' follows non-static declaration".to_owned()])
+        );
+    }
+
+    #[test]
+    fn function_defined_twice() {
+        assert_that!(
+            errors("int f(void) { return 0; } int f(void) { return 1; }"),
+            eq(vec!["Function 'f' is defined more than once".to_owned()])
+        );
+    }
+
+    #[test]
+    fn incompatible_function_declarations() {
+        assert_that!(
+            errors("int f(int a); int f(int a, int b) { return a; }"),
+            eq(vec!["Incompatible declarations of function 'f'".to_owned()])
+        );
+    }
+
+    #[test]
+    fn function_declared_but_not_defined() {
+        let symbols = symbols("int f(int a); int main(void) { return f(1); }");
+        assert_that!(attributes(&symbols, "f"), eq(IdentifierAttributes::Function { defined: false, global: true }));
+        assert_that!(symbols.get("f").unwrap().symbol_type.clone(), eq(Type::Function { params: vec![Type::Int], return_type: Box::new(Type::Int) }));
+    }
+
+    #[test]
+    fn wrong_number_of_arguments() {
+        assert_that!(
+            errors("int f(int a); int main(void) { return f(); }"),
+            eq(vec!["Function 'f' called with the wrong number of arguments".to_owned()])
+        );
+    }
+
+    #[test]
+    fn variable_and_function_with_the_same_name() {
+        assert_that!(errors("int f(void); int f;"), eq(vec!["'f' redeclared as a different kind of symbol".to_owned()]));
+        assert_that!(
+            errors("int x; int main(void) { return x(); }"),
+            eq(vec!["Variable 'x' called as a function".to_owned()])
+        );
+        assert_that!(
+            errors("int f(void); int main(void) { return f + 1; }"),
+            eq(vec!["Function 'f' used as a variable".to_owned()])
+        );
+    }
+
+    #[test]
+    fn static_initialisers_are_evaluated_for_the_target() {
+        let program = "int size = sizeof(long) * 2; static unsigned mask = ~0u >> 4; long big = 1L << 20;";
+        let epoc16 = symbols_for(program, TargetPlatform::EPOC16);
+        assert_that!(attributes(&epoc16, "size"), eq(static_variable(initial(Type::Int, 8), true)));
+        assert_that!(attributes(&epoc16, "mask"), eq(static_variable(initial(Type::UnsignedInt, 0xfff), false)));
+        assert_that!(attributes(&epoc16, "big"), eq(static_variable(initial(Type::Long, 1 << 20), true)));
+        let x86_64 = symbols_for(program, TargetPlatform::X86_64);
+        assert_that!(attributes(&x86_64, "size"), eq(static_variable(initial(Type::Int, 16), true)));
+        assert_that!(attributes(&x86_64, "mask"), eq(static_variable(initial(Type::UnsignedInt, 0xfffffff), false)));
+    }
+
+    #[test]
+    fn static_initialisers_are_converted_to_the_variable_type() {
+        let symbols = symbols_for("int i = 40000; unsigned char c = -1;", TargetPlatform::EPOC16);
+        assert_that!(attributes(&symbols, "i"), eq(static_variable(initial(Type::Int, -25536), true)));
+        assert_that!(attributes(&symbols, "c"), eq(static_variable(initial(Type::UnsignedChar, 255), true)));
+    }
+
+    #[test]
+    fn division_by_zero_in_initialiser() {
+        assert_that!(errors("int x = 1 / 0;"), eq(vec!["Division by zero in constant expression".to_owned()]));
+        assert_that!(
+            errors("int f(void) { static int y = 2 % 0; return y; }"),
+            eq(vec!["Division by zero in constant expression".to_owned()])
+        );
+    }
+
+    #[test]
+    fn enumeration_constants() {
+        let symbols = symbols("enum { A, B = 10, C, D = B - 20, E };");
+        let values: Vec<IdentifierAttributes> = ["A.1", "B.2", "C.3", "D.4", "E.5"].iter().map(|name| attributes(&symbols, name)).collect();
+        assert_that!(values, eq([0, 10, 11, -10, -9].iter().map(|value| IdentifierAttributes::EnumConstant(Constant::int(*value))).collect::<Vec<_>>()));
+    }
+
+    #[test]
+    fn enumeration_constants_must_fit_in_int() {
+        let program = parsed("enum { BIG = 32767, BIGGER };");
+        assert_that!(
+            analyse(program.clone(), &TargetPlatform::EPOC16.data_model()).unwrap_err(),
+            eq(vec!["Value 32768 of enumerator 'BIGGER.2' is out of range of 'int'".to_owned()])
+        );
+        assert_that!(analyse(program, &TargetPlatform::Transputer.data_model()).is_ok(), eq(true));
+    }
+
+    #[test]
+    fn enumeration_constants_are_not_lvalues() {
+        assert_that!(errors("enum { A }; int main(void) { A = 1; return A; }"), eq(vec!["Invalid lvalue".to_owned()]));
+    }
+
+    #[test]
+    fn enumeration_constants_in_constant_expressions() {
+        let symbols = symbols("enum { WIDTH = 4, HEIGHT = 3 }; int area = WIDTH * HEIGHT;");
+        assert_that!(attributes(&symbols, "area"), eq(static_variable(initial(Type::Int, 12), true)));
+    }
+
+    #[test]
+    fn case_labels() {
+        symbols("enum { ONE = 1 }; int main(void) { int x = 2; switch (x) { case ONE: case 1 + 1: default: break; } return 0; }");
+        assert_that!(
+            errors("int main(void) { switch (1) { case 1: case 2 - 1: ; } return 0; }"),
+            eq(vec!["Duplicate case value 1".to_owned()])
+        );
+        assert_that!(
+            errors("int main(void) { int x; switch (x) { case x: ; } return 0; }"),
+            eq(vec!["Case label is not a constant expression".to_owned()])
+        );
+        assert_that!(
+            errors("int main(void) { switch (1) { default: ; default: ; } return 0; }"),
+            eq(vec!["Multiple default labels in one switch statement".to_owned()])
+        );
+        assert_that!(
+            errors("int main(void) { case 1: default: return 0; }"),
+            eq(vec!["Case label not within a switch statement".to_owned(), "Default label not within a switch statement".to_owned()])
+        );
+    }
+
+    #[test]
+    fn case_labels_are_converted_to_the_controlling_type() {
+        // 65536 and 0 are the same 'unsigned int' on EPOC16, but not on x86_64.
+        let program = "int main(void) { unsigned u = 0; switch (u) { case 0: case 65536: ; } return 0; }";
+        assert_that!(
+            analyse(parsed(program), &TargetPlatform::EPOC16.data_model()).unwrap_err(),
+            eq(vec!["Duplicate case value 0".to_owned()])
+        );
+        assert_that!(analyse(parsed(program), &TargetPlatform::X86_64.data_model()).is_ok(), eq(true));
+    }
+
+    #[test]
+    fn conflicting_types() {
+        assert_that!(errors("int x; long x;"), eq(vec!["Conflicting types for 'x'".to_owned()]));
+        assert_that!(
+            errors("int f(void) { extern char x; return x; } int x;"),
+            eq(vec!["Conflicting types for 'x'".to_owned()])
+        );
+        assert_that!(
+            errors("int f(long a); int f(int a) { return a; }"),
+            eq(vec!["Incompatible declarations of function 'f'".to_owned()])
+        );
+    }
+
+    #[test]
+    fn void_functions() {
+        symbols("void f(void) { return; } int main(void) { f(); (void) 0; return 0; }");
+        assert_that!(
+            errors("void f(void) { return 1; }"),
+            eq(vec!["Return with a value in function 'f' returning void".to_owned()])
+        );
+        assert_that!(
+            errors("void f(void); int main(void) { return f() + 1; }"),
+            eq(vec!["Void value used in expression".to_owned()])
+        );
+        assert_that!(errors("int x = sizeof(void);"), eq(vec!["Invalid application of 'sizeof' to type 'void'".to_owned()]));
+    }
+
+    #[test]
+    fn floating_static_initialisers() {
+        let symbols = symbols("float f = 1 / 4.0; double d = 3; int i = 2.75; double e = 1.0f / 3;");
+        assert_that!(attributes(&symbols, "f"), eq(static_variable(initial(Type::Float, 0.25f32.to_bits() as i128), true)));
+        assert_that!(attributes(&symbols, "d"), eq(static_variable(initial(Type::Double, 3.0f64.to_bits() as i128), true)));
+        assert_that!(attributes(&symbols, "i"), eq(static_variable(initial(Type::Int, 2), true)));
+        assert_that!(
+            attributes(&symbols, "e"),
+            eq(static_variable(initial(Type::Double, ((1.0f32 / 3.0) as f64).to_bits() as i128), true))
+        );
+    }
+
+    #[test]
+    fn integer_operators_reject_floating_operands() {
+        assert_that!(
+            errors("double d; int main(void) { return ~d + (1 % d) + (d << 1); }"),
+            eq(vec![
+                "Invalid operand of type 'double' to '~'".to_owned(),
+                "Invalid operand of type 'double' to '%'".to_owned(),
+                "Invalid operand of type 'double' to '<<'".to_owned(),
+            ])
+        );
+        assert_that!(
+            errors("float f; int main(void) { int i = 1; i |= f; return i; }"),
+            eq(vec!["Invalid operand of type 'float' to '|='".to_owned()])
+        );
+    }
+
+    #[test]
+    fn switch_and_case_labels_must_be_integers() {
+        assert_that!(
+            errors("int main(void) { double d = 1; switch (d) { default: ; } return 0; }"),
+            eq(vec!["Switch statement on a value of type 'double', which is not an integer".to_owned()])
+        );
+        assert_that!(
+            errors("int main(void) { switch (1) { case 1.0: ; } return 0; }"),
+            eq(vec!["Case label is not an integer constant expression".to_owned()])
+        );
+        assert_that!(
+            errors("enum { A = 1.5 };"),
+            eq(vec!["Value of enumerator 'A.1' is not an integer".to_owned()])
+        );
+    }
+
+    #[test]
+    fn literal_too_large_for_the_target() {
+        assert_that!(
+            analyse(parsed("long x = 4294967296;"), &TargetPlatform::EPOC16.data_model()).unwrap_err(),
+            eq(vec!["Integer constant 4294967296 is too large for its type".to_owned()])
+        );
+    }
+
+    #[test]
+    fn break_and_continue_must_be_within_a_loop_or_switch() {
+        symbols("int main(void) { while (1) { switch (2) { case 2: continue; default: break; } break; } return 0; }");
+        assert_that!(
+            errors("int main(void) { break; switch (1) { default: continue; } return 0; }"),
+            eq(vec!["Break statement not within a loop or switch".to_owned(), "Continue statement not within a loop".to_owned()])
+        );
+    }
+}
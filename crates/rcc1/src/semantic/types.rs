@@ -0,0 +1,245 @@
+//! The properties of the C types on a target, and the conversions between them (C89 6.2.1). These
+//! all depend on the target's data model: e.g. 'unsigned short' promotes to 'int' on x86_64, but
+//! to 'unsigned int' on EPOC16, where both are 16 bits wide. The floating types are the same
+//! everywhere, and their conversions are done as the host does them: IEEE 754, rounding to
+//! nearest.
+
+use std::fmt::{Display, Formatter};
+
+use common::data_model::DataModel;
+
+use crate::ast::{Constant, Literal, Type};
+
+impl Type {
+    pub fn is_integer(&self) -> bool {
+        !matches!(self, Type::Float | Type::Double | Type::Void | Type::Function { .. })
+    }
+
+    pub fn is_floating(&self) -> bool {
+        matches!(self, Type::Float | Type::Double)
+    }
+
+    pub fn is_arithmetic(&self) -> bool {
+        self.is_integer() || self.is_floating()
+    }
+
+    /// The width of an arithmetic type. Functions and 'void' have no width.
+    pub fn bits(&self, data_model: &DataModel) -> u32 {
+        match self {
+            Type::Char | Type::SignedChar | Type::UnsignedChar => DataModel::CHAR_BITS,
+            Type::Short | Type::UnsignedShort => data_model.short_bits,
+            Type::Int | Type::UnsignedInt => data_model.int_bits,
+            Type::Long | Type::UnsignedLong => data_model.long_bits,
+            Type::Float => 32,
+            Type::Double => 64,
+            Type::Void | Type::Function { .. } => 0,
+        }
+    }
+
+    /// The size of an arithmetic type in bytes, as given by 'sizeof'.
+    pub fn size(&self, data_model: &DataModel) -> u64 {
+        (self.bits(data_model) / DataModel::CHAR_BITS) as u64
+    }
+
+    pub fn is_signed(&self, data_model: &DataModel) -> bool {
+        match self {
+            Type::Char => data_model.char_is_signed,
+            Type::SignedChar | Type::Short | Type::Int | Type::Long => true,
+            _ => false,
+        }
+    }
+
+    pub fn min_value(&self, data_model: &DataModel) -> i128 {
+        if self.is_signed(data_model) {
+            -(1 << (self.bits(data_model) - 1))
+        } else {
+            0
+        }
+    }
+
+    pub fn max_value(&self, data_model: &DataModel) -> i128 {
+        if self.is_signed(data_model) {
+            (1 << (self.bits(data_model) - 1)) - 1
+        } else {
+            (1 << self.bits(data_model)) - 1
+        }
+    }
+
+    /// The integral promotions: types narrower than 'int' are promoted to 'int' if it can hold all
+    /// their values, and to 'unsigned int' otherwise.
+    pub fn promoted(&self, data_model: &DataModel) -> Type {
+        match self {
+            Type::Char | Type::SignedChar | Type::UnsignedChar | Type::Short | Type::UnsignedShort => {
+                if self.max_value(data_model) <= Type::Int.max_value(data_model) {
+                    Type::Int
+                } else {
+                    Type::UnsignedInt
+                }
+            }
+            _ => self.clone(),
+        }
+    }
+}
+
+/// Types are named as they would be written in C.
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Char => write!(f, "char"),
+            Type::SignedChar => write!(f, "signed char"),
+            Type::UnsignedChar => write!(f, "unsigned char"),
+            Type::Short => write!(f, "short"),
+            Type::UnsignedShort => write!(f, "unsigned short"),
+            Type::Int => write!(f, "int"),
+            Type::UnsignedInt => write!(f, "unsigned int"),
+            Type::Long => write!(f, "long"),
+            Type::UnsignedLong => write!(f, "unsigned long"),
+            Type::Float => write!(f, "float"),
+            Type::Double => write!(f, "double"),
+            Type::Void => write!(f, "void"),
+            Type::Function { params, return_type } if params.is_empty() => write!(f, "{} (void)", return_type),
+            Type::Function { params, return_type } => {
+                let params: Vec<String> = params.iter().map(Type::to_string).collect();
+                write!(f, "{} ({})", return_type, params.join(", "))
+            }
+        }
+    }
+}
+
+/// The usual arithmetic conversions, giving the type that a binary operator with operands of the
+/// given types is performed in.
+pub fn common_type(left: &Type, right: &Type, data_model: &DataModel) -> Type {
+    let (left, right) = (left.promoted(data_model), right.promoted(data_model));
+    let either = |t: Type| left == t || right == t;
+    if left == right {
+        left
+    } else if either(Type::Double) {
+        Type::Double
+    } else if either(Type::Float) {
+        Type::Float
+    } else if either(Type::UnsignedLong) {
+        Type::UnsignedLong
+    } else if either(Type::Long) && either(Type::UnsignedInt) {
+        // 'long' is only wide enough for all 'unsigned int' values if it is wider than 'int'.
+        if Type::Long.max_value(data_model) >= Type::UnsignedInt.max_value(data_model) {
+            Type::Long
+        } else {
+            Type::UnsignedLong
+        }
+    } else if either(Type::Long) {
+        Type::Long
+    } else if either(Type::UnsignedInt) {
+        Type::UnsignedInt
+    } else {
+        Type::Int
+    }
+}
+
+/// The type of 'sizeof', i.e. 'size_t': the unsigned type as wide as a pointer.
+pub fn size_type(data_model: &DataModel) -> Type {
+    if data_model.int_bits == data_model.pointer_bits {
+        Type::UnsignedInt
+    } else {
+        Type::UnsignedLong
+    }
+}
+
+/// The type of an integer literal is the first of a list of candidates that can represent its
+/// value (C89 6.1.3.2). There is none if the literal is too large for every candidate. Floating
+/// literals have already been rounded to their type by the lexer.
+pub fn literal_constant(literal: &Literal, data_model: &DataModel) -> Option<Constant> {
+    let (value, candidates): (u64, &[Type]) = match literal {
+        Literal::Float(bits) => return Some(Constant { constant_type: Type::Float, value: *bits as i128 }),
+        Literal::Double(bits) => return Some(Constant { constant_type: Type::Double, value: *bits as i128 }),
+        Literal::Decimal(value) => (*value, &[Type::Int, Type::Long, Type::UnsignedLong]),
+        Literal::OctalOrHexadecimal(value) => {
+            (*value, &[Type::Int, Type::UnsignedInt, Type::Long, Type::UnsignedLong])
+        }
+        Literal::Unsigned(value) => (*value, &[Type::UnsignedInt, Type::UnsignedLong]),
+        Literal::Long(value) => (*value, &[Type::Long, Type::UnsignedLong]),
+        Literal::UnsignedLong(value) => (*value, &[Type::UnsignedLong]),
+    };
+    candidates
+        .iter()
+        .find(|candidate| value as i128 <= candidate.max_value(data_model))
+        .map(|constant_type| Constant { constant_type: constant_type.clone(), value: value as i128 })
+}
+
+impl Constant {
+    /// A constant of the given integer type, with the value reduced modulo 2^n, where n is the
+    /// width of the type, into the type's range. This is how the targets convert integers: they
+    /// all use two's complement, and truncate or extend without trapping.
+    pub fn wrapping(constant_type: Type, value: i128, data_model: &DataModel) -> Constant {
+        let bits = constant_type.bits(data_model);
+        let modulus = 1i128 << bits;
+        let mut value = value.rem_euclid(modulus);
+        if value > constant_type.max_value(data_model) {
+            value -= modulus;
+        }
+        Constant { constant_type, value }
+    }
+
+    /// Integers are converted to the nearest floating value, without going through 'double',
+    /// which could round twice. Floating values are truncated towards zero to convert them to
+    /// integers; those out of range are undefined in C, and here saturate, with NaN giving zero.
+    pub fn convert(&self, to: &Type, data_model: &DataModel) -> Constant {
+        match (self.constant_type.is_floating(), to) {
+            (false, Type::Float) => Constant::float(self.value as f32),
+            (false, Type::Double) => Constant::double(self.value as f64),
+            (false, _) => Constant::wrapping(to.clone(), self.value, data_model),
+            (true, Type::Float) => Constant::float(self.floating() as f32),
+            (true, Type::Double) => Constant::double(self.floating()),
+            (true, _) => Constant {
+                constant_type: to.clone(),
+                value: (self.floating() as i128).clamp(to.min_value(data_model), to.max_value(data_model)),
+            },
+        }
+    }
+
+    pub fn int(value: i128) -> Constant {
+        Constant { constant_type: Type::Int, value }
+    }
+
+    pub fn float(value: f32) -> Constant {
+        Constant { constant_type: Type::Float, value: value.to_bits() as i128 }
+    }
+
+    pub fn double(value: f64) -> Constant {
+        Constant { constant_type: Type::Double, value: value.to_bits() as i128 }
+    }
+
+    /// The value of a constant of floating type.
+    pub fn floating(&self) -> f64 {
+        match self.constant_type {
+            Type::Float => f32::from_bits(self.value as u32) as f64,
+            Type::Double => f64::from_bits(self.value as u64),
+            _ => unreachable!("only floating constants hold bit patterns"),
+        }
+    }
+
+    /// Does the constant compare equal to zero? Negative zero does, even though its bits are not
+    /// all zero.
+    pub fn is_zero(&self) -> bool {
+        if self.constant_type.is_floating() {
+            self.floating() == 0.0
+        } else {
+            self.value == 0
+        }
+    }
+}
+
+/// Integers are written in decimal, and floating values as the shortest decimal that reads back
+/// as the same value.
+impl Display for Constant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.constant_type {
+            Type::Float => write!(f, "{:?}", f32::from_bits(self.value as u32)),
+            Type::Double => write!(f, "{:?}", self.floating()),
+            _ => write!(f, "{}", self.value),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./types_spec.rs"]
+mod types_spec;
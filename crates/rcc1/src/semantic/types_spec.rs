@@ -0,0 +1,78 @@
+mod types_spec {
+    use common::target_platform::TargetPlatform;
+    use hamcrest2::prelude::*;
+
+    use crate::ast::{Constant, Literal, Type};
+    use crate::semantic::types::{common_type, literal_constant, size_type};
+
+    #[test]
+    fn widths() {
+        let epoc16 = TargetPlatform::EPOC16.data_model();
+        let x86_64 = TargetPlatform::X86_64.data_model();
+        assert_that!(Type::Int.bits(&epoc16), eq(16));
+        assert_that!(Type::Long.bits(&epoc16), eq(32));
+        assert_that!(Type::Long.size(&x86_64), eq(8));
+        assert_that!(Type::Int.max_value(&epoc16), eq(32767));
+        assert_that!(Type::UnsignedLong.max_value(&x86_64), eq(18446744073709551615));
+    }
+
+    #[test]
+    fn plain_char_is_unsigned_on_the_transputer() {
+        assert_that!(Type::Char.is_signed(&TargetPlatform::Transputer.data_model()), eq(false));
+        assert_that!(Type::Char.is_signed(&TargetPlatform::EPOC16.data_model()), eq(true));
+        assert_that!(Type::Char.is_signed(&TargetPlatform::X86_64.data_model()), eq(true));
+    }
+
+    #[test]
+    fn integral_promotions() {
+        let epoc16 = TargetPlatform::EPOC16.data_model();
+        let transputer = TargetPlatform::Transputer.data_model();
+        assert_that!(Type::UnsignedChar.promoted(&epoc16), eq(Type::Int));
+        assert_that!(Type::Short.promoted(&epoc16), eq(Type::Int));
+        assert_that!(Type::UnsignedShort.promoted(&epoc16), eq(Type::UnsignedInt));
+        assert_that!(Type::UnsignedShort.promoted(&transputer), eq(Type::Int));
+        assert_that!(Type::Long.promoted(&epoc16), eq(Type::Long));
+    }
+
+    #[test]
+    fn usual_arithmetic_conversions() {
+        let epoc16 = TargetPlatform::EPOC16.data_model();
+        let transputer = TargetPlatform::Transputer.data_model();
+        let x86_64 = TargetPlatform::X86_64.data_model();
+        assert_that!(common_type(&Type::Char, &Type::Short, &epoc16), eq(Type::Int));
+        assert_that!(common_type(&Type::Int, &Type::UnsignedInt, &epoc16), eq(Type::UnsignedInt));
+        assert_that!(common_type(&Type::Long, &Type::UnsignedInt, &epoc16), eq(Type::Long));
+        assert_that!(common_type(&Type::Long, &Type::UnsignedInt, &transputer), eq(Type::UnsignedLong));
+        assert_that!(common_type(&Type::UnsignedInt, &Type::Long, &x86_64), eq(Type::Long));
+        assert_that!(common_type(&Type::UnsignedLong, &Type::Char, &x86_64), eq(Type::UnsignedLong));
+    }
+
+    #[test]
+    fn size_type_is_as_wide_as_a_pointer() {
+        assert_that!(size_type(&TargetPlatform::EPOC16.data_model()), eq(Type::UnsignedInt));
+        assert_that!(size_type(&TargetPlatform::Transputer.data_model()), eq(Type::UnsignedInt));
+        assert_that!(size_type(&TargetPlatform::X86_64.data_model()), eq(Type::UnsignedLong));
+    }
+
+    #[test]
+    fn literal_types() {
+        let epoc16 = TargetPlatform::EPOC16.data_model();
+        let literal_type = |literal| literal_constant(&literal, &epoc16).map(|constant| constant.constant_type);
+        assert_that!(literal_type(Literal::Decimal(40000)), eq(Some(Type::Long)));
+        assert_that!(literal_type(Literal::OctalOrHexadecimal(40000)), eq(Some(Type::UnsignedInt)));
+        assert_that!(literal_type(Literal::Unsigned(70000)), eq(Some(Type::UnsignedLong)));
+        assert_that!(literal_type(Literal::Long(1)), eq(Some(Type::Long)));
+        assert_that!(literal_type(Literal::Decimal(4294967296)), eq(None));
+    }
+
+    #[test]
+    fn conversions_wrap() {
+        let epoc16 = TargetPlatform::EPOC16.data_model();
+        assert_that!(Constant::wrapping(Type::Int, 40000, &epoc16), eq(Constant { constant_type: Type::Int, value: -25536 }));
+        assert_that!(Constant::wrapping(Type::UnsignedInt, -1, &epoc16), eq(Constant { constant_type: Type::UnsignedInt, value: 65535 }));
+        assert_that!(
+            Constant::int(-1).convert(&Type::UnsignedLong, &epoc16),
+            eq(Constant { constant_type: Type::UnsignedLong, value: 4294967295 })
+        );
+    }
+}
@@ -0,0 +1,112 @@
+//! Encodes Transputer instructions. Each instruction byte holds a function code in its upper four
+//! bits and four bits of operand in its lower. Longer operands are built up in the operand
+//! register by a sequence of 'pfix' and 'nfix' prefixes, and the sixteenth direct function,
+//! 'opr', performs the operation its operand selects.
+
+/// The direct functions.
+pub const J: u8 = 0x0;
+pub const LDLP: u8 = 0x1;
+pub const PFIX: u8 = 0x2;
+pub const LDNL: u8 = 0x3;
+pub const LDC: u8 = 0x4;
+pub const LDNLP: u8 = 0x5;
+pub const NFIX: u8 = 0x6;
+pub const LDL: u8 = 0x7;
+pub const ADC: u8 = 0x8;
+pub const CALL: u8 = 0x9;
+pub const CJ: u8 = 0xA;
+pub const AJW: u8 = 0xB;
+pub const EQC: u8 = 0xC;
+pub const STL: u8 = 0xD;
+pub const STNL: u8 = 0xE;
+pub const OPR: u8 = 0xF;
+
+/// The operations, performed by 'opr'.
+pub const REV: i32 = 0x00;
+pub const LB: i32 = 0x01;
+pub const DIFF: i32 = 0x04;
+pub const PROD: i32 = 0x08;
+pub const GT: i32 = 0x09;
+pub const STOPP: i32 = 0x15;
+pub const LDIV: i32 = 0x1A;
+pub const LDPI: i32 = 0x1B;
+pub const XDBLE: i32 = 0x1D;
+pub const REM: i32 = 0x1F;
+pub const RET: i32 = 0x20;
+pub const DIV: i32 = 0x2C;
+pub const NOT: i32 = 0x32;
+pub const XOR: i32 = 0x33;
+pub const LSHR: i32 = 0x35;
+pub const XWORD: i32 = 0x3A;
+pub const SB: i32 = 0x3B;
+pub const SHR: i32 = 0x40;
+pub const SHL: i32 = 0x41;
+pub const MINT: i32 = 0x42;
+pub const AND: i32 = 0x46;
+pub const OR: i32 = 0x4B;
+pub const SUM: i32 = 0x52;
+pub const POP: i32 = 0x79;
+
+/// The operations of the T800's FPU.
+pub const FPSTNLDB: i32 = 0x84;
+pub const FPADD: i32 = 0x87;
+pub const FPSTNLSN: i32 = 0x88;
+pub const FPSUB: i32 = 0x89;
+pub const FPLDNLDB: i32 = 0x8A;
+pub const FPMUL: i32 = 0x8B;
+pub const FPDIV: i32 = 0x8C;
+pub const FPLDNLSN: i32 = 0x8E;
+pub const FPORDERED: i32 = 0x92;
+pub const FPGT: i32 = 0x94;
+pub const FPEQ: i32 = 0x95;
+pub const FPI32TOR32: i32 = 0x96;
+pub const FPI32TOR64: i32 = 0x98;
+pub const FPB32TOR64: i32 = 0x9A;
+pub const FPSTNLI32: i32 = 0x9E;
+pub const FPLDZEROSN: i32 = 0x9F;
+pub const FPLDZERODB: i32 = 0xA0;
+pub const FPINT: i32 = 0xA1;
+pub const FPREV: i32 = 0xA4;
+pub const FPENTRY: i32 = 0xAB;
+
+/// The FPU entry operations, selected by the number loaded into A for 'fpentry'.
+pub const FPURZ: i32 = 0x06;
+pub const FPUR32TOR64: i32 = 0x07;
+pub const FPUR64TOR32: i32 = 0x08;
+
+/// Encodes a direct function with the shortest sequence of prefixes that builds its operand.
+pub fn encode(function: u8, operand: i32) -> Vec<u8> {
+    let mut bytes = if (0..16).contains(&operand) {
+        vec![]
+    } else if operand >= 16 {
+        encode(PFIX, operand >> 4)
+    } else {
+        encode(NFIX, !operand >> 4)
+    };
+    bytes.push((function << 4) | (operand & 0xF) as u8);
+    bytes
+}
+
+/// The number of bytes the shortest encoding of an operand takes.
+pub fn encoded_length(operand: i32) -> usize {
+    encode(J, operand).len()
+}
+
+/// Encodes a direct function in a given number of bytes, which must be at least the shortest
+/// encoding's, by starting with 'pfix 0's, which leave the operand register clear.
+pub fn encode_in(function: u8, operand: i32, length: usize) -> Vec<u8> {
+    let shortest = encode(function, operand);
+    assert!(shortest.len() <= length, "operand {} does not fit in {} bytes", operand, length);
+    let mut bytes = vec![PFIX << 4; length - shortest.len()];
+    bytes.extend(shortest);
+    bytes
+}
+
+/// Encodes an operation.
+pub fn operation(code: i32) -> Vec<u8> {
+    encode(OPR, code)
+}
+
+#[cfg(test)]
+#[path = "./encoding_spec.rs"]
+mod encoding_spec;
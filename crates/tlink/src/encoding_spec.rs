@@ -0,0 +1,78 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod encoding_spec {
+
+    use hamcrest2::prelude::*;
+
+    use crate::encoding::*;
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    /// Runs the instruction bytes through the operand register as the Transputer does, giving the
+    /// function and operand of the last.
+    fn decode(bytes: &[u8]) -> (u8, i32) {
+        let mut operand: u32 = 0;
+        for byte in &bytes[..bytes.len() - 1] {
+            let nibble = (byte & 0xF) as u32;
+            operand = match byte >> 4 {
+                PFIX => (operand | nibble) << 4,
+                NFIX => !(operand | nibble) << 4,
+                function => panic!("function {} is not a prefix", function),
+            };
+        }
+        let last = bytes[bytes.len() - 1];
+        (last >> 4, (operand | (last & 0xF) as u32) as i32)
+    }
+
+    #[test]
+    fn short_operands_need_no_prefix() {
+        assert_that!(encode(LDC, 0), equal_to(vec![0x40]));
+        assert_that!(encode(LDL, 15), equal_to(vec![0x7F]));
+    }
+
+    #[test]
+    fn positive_operands_are_prefixed_with_pfix() {
+        assert_that!(encode(LDC, 16), equal_to(vec![0x21, 0x40]));
+        assert_that!(encode(LDC, 0x123), equal_to(vec![0x21, 0x22, 0x43]));
+    }
+
+    #[test]
+    fn negative_operands_are_prefixed_with_nfix() {
+        assert_that!(encode(LDC, -1), equal_to(vec![0x60, 0x4F]));
+        assert_that!(encode(AJW, -16), equal_to(vec![0x60, 0xB0]));
+        assert_that!(encode(J, -17), equal_to(vec![0x61, 0x0F]));
+    }
+
+    #[test]
+    fn operations_are_encoded_with_opr() {
+        assert_that!(operation(REV), equal_to(vec![0xF0]));
+        assert_that!(operation(RET), equal_to(vec![0x22, 0xF0]));
+        assert_that!(operation(FPENTRY), equal_to(vec![0x2A, 0xFB]));
+    }
+
+    #[test]
+    fn encodings_decode_to_their_operands() {
+        for operand in [0, 1, 15, 16, 255, 256, 4095, 65536, i32::MAX, -1, -15, -16, -17, -256, -257, i32::MIN] {
+            let bytes = encode(CALL, operand);
+            assert_that!(decode(&bytes), equal_to((CALL, operand)));
+            assert_that!(encoded_length(operand), equal_to(bytes.len()));
+        }
+    }
+
+    #[test]
+    fn longest_encodings_take_eight_bytes() {
+        assert_that!(encoded_length(i32::MAX), equal_to(8));
+        assert_that!(encoded_length(i32::MIN), equal_to(8));
+    }
+
+    #[test]
+    fn encodings_are_padded_with_pfix_zero() {
+        let bytes = encode_in(J, -20, 4);
+        assert_that!(bytes.clone(), equal_to(vec![0x20, 0x20, 0x61, 0x0C]));
+        assert_that!(decode(&bytes), equal_to((J, -20)));
+    }
+}
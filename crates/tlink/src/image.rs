@@ -0,0 +1,64 @@
+//! The linked image, as loaded by the Parachute emulator, and its map.
+//!
+//! The image file holds the bytes of memory from the base address, and execution starts at its
+//! first byte, the boot stub. The map is a text file listing where each module's code and data
+//! were placed, and the address of every global.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    /// The address at which the image is loaded and entered.
+    pub base: u32,
+    /// The address of the entry point, called by the boot stub.
+    pub entry: u32,
+    pub bytes: Vec<u8>,
+    pub sections: Vec<Section>,
+    /// The globals, in order of address.
+    pub symbols: Vec<Symbol>,
+}
+
+/// The code or data of a module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub module: String,
+    pub kind: &'static str,
+    pub address: u32,
+    pub size: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub module: String,
+    pub address: u32,
+}
+
+impl Image {
+    pub fn map(&self) -> String {
+        let mut map = String::new();
+        writeln!(map, "Base   {:08X}", self.base).unwrap();
+        writeln!(map, "Size   {:08X}", self.bytes.len()).unwrap();
+        writeln!(map, "Entry  {:08X}", self.entry).unwrap();
+        writeln!(map, "\nSections:").unwrap();
+        for section in &self.sections {
+            writeln!(map, "{:08X} {:08X} {:<4} {}", section.address, section.size, section.kind, section.module).unwrap();
+        }
+        writeln!(map, "\nSymbols:").unwrap();
+        for symbol in &self.symbols {
+            writeln!(map, "{:08X} {} ({})", symbol.address, symbol.name, symbol.module).unwrap();
+        }
+        map
+    }
+
+    /// Writes the image and its map.
+    pub fn write(&self, image_file: &Path, map_file: &Path) -> Result<()> {
+        std::fs::write(image_file, &self.bytes)
+            .with_context(|| format!("Could not write image {}", image_file.display()))?;
+        std::fs::write(map_file, self.map())
+            .with_context(|| format!("Could not write map {}", map_file.display()))
+    }
+}
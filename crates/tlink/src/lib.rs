@@ -0,0 +1,11 @@
+//! The Transputer linker, which combines the relocatable objects of a program's modules into a
+//! boot image for the Parachute emulator, with a map of where everything was placed.
+//!
+//! The objects are written by rcc1's Transputer back end. Their code is kept as fragments, so
+//! that the direct functions whose operands depend on where symbols end up can be encoded with
+//! prefix sequences of the right length once the modules are laid out.
+
+pub mod encoding;
+pub mod image;
+pub mod linker;
+pub mod object;
@@ -0,0 +1,240 @@
+//! Links the objects of a program's modules into an image.
+//!
+//! The image starts with a boot stub, which calls the entry point and stops the process when it
+//! returns, followed by the code of every module in order, then their data. Labels are resolved
+//! within their own module first, then among the globals of all modules.
+//!
+//! The operands of the direct functions left for the linker depend on the addresses of their
+//! symbols, and so on the lengths of the instructions between, which depend on their operands in
+//! turn. Each starts out a single byte long and is lengthened while its operand doesn't fit,
+//! until no more need lengthening. As they only ever grow this always settles, though not always
+//! on the shortest encoding: those that end up longer than their operands need are padded with
+//! 'pfix 0'.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use log::debug;
+
+use crate::encoding::{encode_in, encoded_length, operation, CALL, STOPP};
+use crate::image::{Image, Section, Symbol};
+use crate::object::{Fragment, Object, Operand};
+
+/// The first address of the T425's memory available to programs, above the on-chip link and
+/// event channels.
+pub const MEM_START: u32 = 0x8000_0070;
+
+/// The name given to the boot stub in the map.
+const BOOT: &str = "(boot)";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Module {
+    /// The name of the module, as reported in errors and the map.
+    pub name: String,
+    pub object: Object,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkOptions {
+    /// The address at which the image is loaded and entered.
+    pub base: u32,
+    /// The global called by the boot stub.
+    pub entry: String,
+}
+
+impl Default for LinkOptions {
+    fn default() -> Self {
+        LinkOptions { base: MEM_START, entry: "main".to_owned() }
+    }
+}
+
+/// A fragment in its place in the image, with its references resolved to the indices of the
+/// labels they refer to.
+enum Placed {
+    Bytes(Vec<u8>),
+    Label,
+    Relative(u8, usize),
+    Distance(u8, usize, usize),
+    Address(usize, i32),
+    Align(u32),
+}
+
+/// The fragments of the whole image, with the labels each module can refer to.
+struct Layout {
+    placed: Vec<Placed>,
+    /// The module, code or data, and the range of fragments, of each section.
+    sections: Vec<(usize, &'static str, usize, usize)>,
+    /// The labels of each module, the boot stub last.
+    labels: Vec<HashMap<String, usize>>,
+    globals: HashMap<String, (usize, usize)>,
+}
+
+pub fn link(modules: &[Module], options: &LinkOptions) -> Result<Image> {
+    let boot = boot_stub(&options.entry);
+    let mut names: Vec<&str> = modules.iter().map(|module| module.name.as_str()).collect();
+    names.push(BOOT);
+    let mut layout = Layout { placed: vec![], sections: vec![], labels: vec![HashMap::new(); names.len()], globals: HashMap::new() };
+
+    // Labels are gathered before any reference is resolved, as they may be referred to before
+    // they are defined, and in other modules.
+    let mut sections = vec![(modules.len(), "code", &boot)];
+    sections.extend(modules.iter().enumerate().map(|(index, module)| (index, "code", &module.object.code)));
+    sections.extend(modules.iter().enumerate().map(|(index, module)| (index, "data", &module.object.data)));
+    let mut index = 0;
+    for (module, _, fragments) in &sections {
+        for fragment in fragments.iter() {
+            if let Fragment::Label(label) = fragment {
+                if layout.labels[*module].insert(label.clone(), index).is_some() {
+                    bail!("'{}' is defined twice in {}", label, names[*module]);
+                }
+            }
+            index += 1;
+        }
+    }
+    for (module_index, module) in modules.iter().enumerate() {
+        for global in &module.object.globals {
+            let Some(label) = layout.labels[module_index].get(global) else {
+                bail!("'{}' is declared global in {} but not defined", global, module.name);
+            };
+            if let Some((other, _)) = layout.globals.insert(global.clone(), (module_index, *label)) {
+                bail!("'{}' is defined in both {} and {}", global, names[other], module.name);
+            }
+        }
+    }
+    if !layout.globals.contains_key(&options.entry) {
+        bail!("The entry point '{}' is not defined", options.entry);
+    }
+
+    for (module, kind, fragments) in &sections {
+        let start = layout.placed.len();
+        for fragment in fragments.iter() {
+            let resolve = |symbol: &str| match layout.labels[*module].get(symbol) {
+                Some(label) => Ok(*label),
+                None => match layout.globals.get(symbol) {
+                    Some((_, label)) => Ok(*label),
+                    None => bail!("'{}' referenced in {} is not defined", symbol, names[*module]),
+                },
+            };
+            let placed = match fragment {
+                Fragment::Bytes(bytes) => Placed::Bytes(bytes.clone()),
+                Fragment::Label(_) => Placed::Label,
+                Fragment::Direct(function, Operand::Relative(symbol)) => Placed::Relative(*function, resolve(symbol)?),
+                Fragment::Direct(function, Operand::Distance(symbol, label)) => {
+                    Placed::Distance(*function, resolve(symbol)?, resolve(label)?)
+                }
+                Fragment::Address(symbol, offset) => Placed::Address(resolve(symbol)?, *offset),
+                Fragment::Align(alignment) => Placed::Align(*alignment),
+            };
+            layout.placed.push(placed);
+        }
+        layout.sections.push((*module, kind, start, layout.placed.len()));
+    }
+
+    let (addresses, lengths) = size(&layout.placed, options.base);
+    let bytes = emit(&layout.placed, &addresses, &lengths, options.base);
+
+    let address = |index: usize| options.base.wrapping_add(addresses[index] as u32);
+    let sections = layout.sections.iter()
+        .map(|(module, kind, start, end)| Section {
+            module: names[*module].to_owned(),
+            kind,
+            address: address(*start),
+            size: (addresses[*end] - addresses[*start]) as u32,
+        })
+        .collect();
+    let mut symbols: Vec<Symbol> = layout.globals.iter()
+        .map(|(name, (module, label))| Symbol { name: name.clone(), module: names[*module].to_owned(), address: address(*label) })
+        .collect();
+    symbols.sort_by(|a, b| a.address.cmp(&b.address).then_with(|| a.name.cmp(&b.name)));
+    let entry = address(layout.globals[&options.entry].1);
+    Ok(Image { base: options.base, entry, bytes, sections, symbols })
+}
+
+fn boot_stub(entry: &str) -> Vec<Fragment> {
+    vec![
+        Fragment::Direct(CALL, Operand::Relative(entry.to_owned())),
+        Fragment::Bytes(operation(STOPP)),
+    ]
+}
+
+/// Settles the lengths of the direct functions, giving the offset of each fragment from the base,
+/// with the offset of the end of the image last, and the length of each.
+fn size(placed: &[Placed], base: u32) -> (Vec<i64>, Vec<usize>) {
+    let mut lengths: Vec<usize> = placed.iter()
+        .map(|fragment| match fragment {
+            Placed::Bytes(bytes) => bytes.len(),
+            Placed::Label | Placed::Align(_) => 0,
+            Placed::Relative(..) | Placed::Distance(..) => 1,
+            Placed::Address(..) => 4,
+        })
+        .collect();
+    let mut passes = 0;
+    loop {
+        passes += 1;
+        let addresses = addresses(placed, &mut lengths, base);
+        let mut settled = true;
+        for (index, fragment) in placed.iter().enumerate() {
+            if let Some(value) = operand(fragment, index, &addresses, &lengths) {
+                let needed = encoded_length(value);
+                if needed > lengths[index] {
+                    lengths[index] = needed;
+                    settled = false;
+                }
+            }
+        }
+        if settled {
+            debug!("Direct function lengths settled after {} passes", passes);
+            return (addresses, lengths);
+        }
+    }
+}
+
+/// The offsets of the fragments from the base, with the padding of each alignment.
+fn addresses(placed: &[Placed], lengths: &mut [usize], base: u32) -> Vec<i64> {
+    let mut addresses = Vec::with_capacity(placed.len() + 1);
+    let mut offset: i64 = 0;
+    for (index, fragment) in placed.iter().enumerate() {
+        if let Placed::Align(alignment) = fragment {
+            let alignment = *alignment as i64;
+            let address = base as i64 + offset;
+            lengths[index] = ((alignment - address % alignment) % alignment) as usize;
+        }
+        addresses.push(offset);
+        offset += lengths[index] as i64;
+    }
+    addresses.push(offset);
+    addresses
+}
+
+/// The operand of a direct function, given where everything is placed.
+fn operand(fragment: &Placed, index: usize, addresses: &[i64], lengths: &[usize]) -> Option<i32> {
+    match fragment {
+        Placed::Relative(_, symbol) => Some((addresses[*symbol] - (addresses[index] + lengths[index] as i64)) as i32),
+        Placed::Distance(_, symbol, label) => Some((addresses[*symbol] - addresses[*label]) as i32),
+        _ => None,
+    }
+}
+
+fn emit(placed: &[Placed], addresses: &[i64], lengths: &[usize], base: u32) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(addresses[placed.len()] as usize);
+    for (index, fragment) in placed.iter().enumerate() {
+        match fragment {
+            Placed::Bytes(fragment_bytes) => bytes.extend(fragment_bytes),
+            Placed::Label => {}
+            Placed::Relative(function, _) | Placed::Distance(function, _, _) => {
+                let value = operand(fragment, index, addresses, lengths).unwrap();
+                bytes.extend(encode_in(*function, value, lengths[index]));
+            }
+            Placed::Address(symbol, offset) => {
+                let address = (base as i64 + addresses[*symbol] + *offset as i64) as u32;
+                bytes.extend(address.to_le_bytes());
+            }
+            Placed::Align(_) => bytes.resize(bytes.len() + lengths[index], 0),
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+#[path = "./linker_spec.rs"]
+mod linker_spec;
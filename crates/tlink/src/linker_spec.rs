@@ -0,0 +1,267 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod linker_spec {
+
+    use hamcrest2::prelude::*;
+
+    use crate::encoding::{CALL, J, LDC};
+    use crate::image::{Section, Symbol};
+    use crate::linker::{link, LinkOptions, Module, MEM_START};
+    use crate::object::{Fragment, Object, Operand};
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    const RET: [u8; 2] = [0x22, 0xF0];
+
+    const LDPI: [u8; 2] = [0x21, 0xFB];
+
+    /// 'call main' then 'stopp', when main follows immediately.
+    const BOOT: [u8; 3] = [0x92, 0x21, 0xF5];
+
+    fn module(name: &str, code: Vec<Fragment>, data: Vec<Fragment>, globals: &[&str]) -> Module {
+        Module {
+            name: name.to_owned(),
+            object: Object { code, data, globals: globals.iter().map(|global| global.to_string()).collect() },
+        }
+    }
+
+    fn label(name: &str) -> Fragment {
+        Fragment::Label(name.to_owned())
+    }
+
+    fn bytes(bytes: &[u8]) -> Fragment {
+        Fragment::Bytes(bytes.to_vec())
+    }
+
+    fn relative(function: u8, symbol: &str) -> Fragment {
+        Fragment::Direct(function, Operand::Relative(symbol.to_owned()))
+    }
+
+    fn concatenated(parts: &[&[u8]]) -> Vec<u8> {
+        parts.concat()
+    }
+
+    #[test]
+    fn calls_are_resolved_across_modules() {
+        let modules = vec![
+            module("main.o", vec![label("main"), relative(CALL, "f"), bytes(&RET)], vec![], &["main"]),
+            module("f.o", vec![label("f"), bytes(&RET)], vec![], &["f"]),
+        ];
+
+        let image = link(&modules, &LinkOptions::default()).unwrap();
+
+        assert_that!(image.bytes, equal_to(concatenated(&[&BOOT, &[0x92], &RET, &RET])));
+        assert_that!(image.entry, equal_to(MEM_START + 3));
+        assert_that!(image.symbols, equal_to(vec![
+            Symbol { name: "main".to_owned(), module: "main.o".to_owned(), address: MEM_START + 3 },
+            Symbol { name: "f".to_owned(), module: "f.o".to_owned(), address: MEM_START + 6 },
+        ]));
+    }
+
+    #[test]
+    fn local_labels_are_resolved_in_their_own_module() {
+        let modules = vec![
+            module("main.o", vec![label("main"), relative(CALL, "helper"), bytes(&RET), label("helper"), bytes(&RET)], vec![], &["main"]),
+            module("f.o", vec![label("helper"), relative(J, "helper")], vec![], &[]),
+        ];
+
+        let image = link(&modules, &LinkOptions::default()).unwrap();
+
+        // The second module's 'j helper' jumps to itself.
+        assert_that!(image.bytes, equal_to(concatenated(&[&BOOT, &[0x92], &RET, &RET, &[0x60, 0x0E]])));
+    }
+
+    #[test]
+    fn forward_jumps_are_lengthened_to_fit() {
+        let modules = vec![
+            module("main.o", vec![label("main"), relative(J, "end"), bytes(&[0; 20]), label("end"), bytes(&RET)], vec![], &["main"]),
+        ];
+
+        let image = link(&modules, &LinkOptions::default()).unwrap();
+
+        assert_that!(image.bytes, equal_to(concatenated(&[&BOOT, &[0x21, 0x04], &[0; 20], &RET])));
+    }
+
+    #[test]
+    fn backward_jumps_are_lengthened_to_fit() {
+        let modules = vec![
+            module("main.o", vec![label("main"), label("loop.1"), bytes(&[0; 20]), relative(J, "loop.1")], vec![], &["main"]),
+        ];
+
+        let image = link(&modules, &LinkOptions::default()).unwrap();
+
+        assert_that!(image.bytes, equal_to(concatenated(&[&BOOT, &[0; 20], &[0x61, 0x0A]])));
+    }
+
+    #[test]
+    fn lengthening_one_instruction_can_lengthen_another() {
+        // 'j end' needs a prefix only once 'j far' has grown to take one.
+        let modules = vec![
+            module("main.o", vec![
+                label("main"),
+                relative(J, "end"),
+                relative(J, "far"),
+                bytes(&[0; 14]),
+                label("end"),
+                bytes(&[0; 3]),
+                label("far"),
+                bytes(&RET),
+            ], vec![], &["main"]),
+        ];
+
+        let image = link(&modules, &LinkOptions::default()).unwrap();
+
+        assert_that!(image.bytes, equal_to(concatenated(&[&BOOT, &[0x21, 0x00], &[0x21, 0x01], &[0; 14], &[0; 3], &RET])));
+    }
+
+    #[test]
+    fn statics_are_addressed_relative_to_the_instruction_pointer() {
+        let modules = vec![
+            module(
+                "main.o",
+                vec![
+                    label("main"),
+                    Fragment::Direct(LDC, Operand::Distance("counter".to_owned(), "ldpi.1".to_owned())),
+                    bytes(&LDPI),
+                    label("ldpi.1"),
+                    bytes(&RET),
+                ],
+                vec![Fragment::Align(4), label("counter"), bytes(&[1, 0, 0, 0])],
+                &["main", "counter"],
+            ),
+        ];
+
+        let image = link(&modules, &LinkOptions::default()).unwrap();
+
+        assert_that!(image.bytes, equal_to(concatenated(&[&BOOT, &[0x42], &LDPI, &RET, &[1, 0, 0, 0]])));
+        assert_that!(image.sections, equal_to(vec![
+            Section { module: "(boot)".to_owned(), kind: "code", address: MEM_START, size: 3 },
+            Section { module: "main.o".to_owned(), kind: "code", address: MEM_START + 3, size: 5 },
+            Section { module: "main.o".to_owned(), kind: "data", address: MEM_START + 8, size: 4 },
+        ]));
+    }
+
+    #[test]
+    fn data_is_aligned_to_absolute_addresses() {
+        let modules = vec![
+            module("main.o", vec![label("main"), bytes(&RET)], vec![bytes(&[7]), Fragment::Align(4), label("word"), bytes(&[1, 0, 0, 0])], &["main", "word"]),
+        ];
+        let options = LinkOptions { base: 0x1001, ..LinkOptions::default() };
+
+        let image = link(&modules, &options).unwrap();
+
+        assert_that!(image.bytes, equal_to(concatenated(&[&BOOT, &RET, &[7], &[0], &[1, 0, 0, 0]])));
+        assert_that!(image.symbols[1].address, equal_to(0x1008));
+    }
+
+    #[test]
+    fn addresses_are_absolute() {
+        let modules = vec![
+            module("main.o", vec![label("main"), bytes(&RET)], vec![Fragment::Address("main".to_owned(), 1)], &["main"]),
+        ];
+
+        let image = link(&modules, &LinkOptions::default()).unwrap();
+
+        assert_that!(image.bytes[5..].to_vec(), equal_to((MEM_START + 4).to_le_bytes().to_vec()));
+    }
+
+    #[test]
+    fn undefined_references_are_errors() {
+        let modules = vec![
+            module("main.o", vec![label("main"), relative(CALL, "putchar"), bytes(&RET)], vec![], &["main"]),
+        ];
+
+        let result = link(&modules, &LinkOptions::default());
+
+        assert_that!(result.err().unwrap().to_string(), equal_to("'putchar' referenced in main.o is not defined".to_owned()));
+    }
+
+    #[test]
+    fn local_labels_are_not_visible_to_other_modules() {
+        let modules = vec![
+            module("main.o", vec![label("main"), relative(CALL, "helper"), bytes(&RET)], vec![], &["main"]),
+            module("f.o", vec![label("helper"), bytes(&RET)], vec![], &[]),
+        ];
+
+        let result = link(&modules, &LinkOptions::default());
+
+        assert_that!(result.err().unwrap().to_string(), equal_to("'helper' referenced in main.o is not defined".to_owned()));
+    }
+
+    #[test]
+    fn globals_defined_twice_are_errors() {
+        let modules = vec![
+            module("main.o", vec![label("main"), bytes(&RET)], vec![], &["main"]),
+            module("f.o", vec![label("main"), bytes(&RET)], vec![], &["main"]),
+        ];
+
+        let result = link(&modules, &LinkOptions::default());
+
+        assert_that!(result.err().unwrap().to_string(), equal_to("'main' is defined in both main.o and f.o".to_owned()));
+    }
+
+    #[test]
+    fn labels_defined_twice_in_a_module_are_errors() {
+        let modules = vec![
+            module("main.o", vec![label("main"), bytes(&RET)], vec![label("main")], &["main"]),
+        ];
+
+        let result = link(&modules, &LinkOptions::default());
+
+        assert_that!(result.err().unwrap().to_string(), equal_to("'main' is defined twice in main.o".to_owned()));
+    }
+
+    #[test]
+    fn undefined_globals_are_errors() {
+        let modules = vec![
+            module("main.o", vec![label("main"), bytes(&RET)], vec![], &["main", "counter"]),
+        ];
+
+        let result = link(&modules, &LinkOptions::default());
+
+        assert_that!(result.err().unwrap().to_string(), equal_to("'counter' is declared global in main.o but not defined".to_owned()));
+    }
+
+    #[test]
+    fn entry_point_must_be_defined() {
+        let modules = vec![
+            module("main.o", vec![label("main"), bytes(&RET)], vec![], &[]),
+        ];
+
+        let result = link(&modules, &LinkOptions::default());
+
+        assert_that!(result.err().unwrap().to_string(), equal_to("The entry point 'main' is not defined".to_owned()));
+    }
+
+    #[test]
+    fn map_lists_sections_and_symbols() {
+        let modules = vec![
+            module("main.o", vec![label("main"), relative(CALL, "f"), bytes(&RET)], vec![], &["main"]),
+            module("f.o", vec![label("f"), bytes(&RET)], vec![Fragment::Align(4), label("count"), bytes(&[0; 4])], &["f", "count"]),
+        ];
+
+        let image = link(&modules, &LinkOptions::default()).unwrap();
+
+        assert_that!(image.map(), equal_to(concat!(
+            "Base   80000070\n",
+            "Size   0000000C\n",
+            "Entry  80000073\n",
+            "\n",
+            "Sections:\n",
+            "80000070 00000003 code (boot)\n",
+            "80000073 00000003 code main.o\n",
+            "80000076 00000002 code f.o\n",
+            "80000078 00000000 data main.o\n",
+            "80000078 00000004 data f.o\n",
+            "\n",
+            "Symbols:\n",
+            "80000073 main (main.o)\n",
+            "80000076 f (f.o)\n",
+            "80000078 count (f.o)\n",
+        ).to_owned()));
+    }
+}
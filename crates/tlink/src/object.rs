@@ -0,0 +1,219 @@
+//! The relocatable object format of a Transputer module.
+//!
+//! An object holds the module's code and data as sequences of fragments. Most are bytes already
+//! encoded, but a direct function whose operand depends on where a symbol is placed is kept as
+//! its function and operand, to be encoded once the program is laid out, since the length of its
+//! prefix sequence depends on the value and the value on the lengths of the instructions between.
+//! Labels are local to their module unless they are named as globals.
+//!
+//! Written out, all numbers are little-endian and strings are prefixed by their lengths in bytes:
+//!
+//! * the magic "TOBJ" and the format version;
+//! * the number of globals, and their names;
+//! * the number of code fragments, and the fragments;
+//! * the number of data fragments, and the fragments.
+//!
+//! Each fragment starts with a tag byte, followed by its fields in order.
+
+use anyhow::{bail, Result};
+
+const MAGIC: &[u8] = b"TOBJ";
+
+const VERSION: u8 = 1;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Object {
+    pub code: Vec<Fragment>,
+    pub data: Vec<Fragment>,
+    /// The labels other modules can refer to.
+    pub globals: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fragment {
+    Bytes(Vec<u8>),
+    Label(String),
+    /// A direct function with an operand resolved at link time.
+    Direct(u8, Operand),
+    /// The absolute address of a symbol, plus an offset, as a word.
+    Address(String, i32),
+    /// Padding to a multiple of a number of bytes.
+    Align(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+    /// The distance from the end of the instruction to the symbol, as taken by 'j', 'cj' and 'call'.
+    Relative(String),
+    /// The distance from the label to the symbol, which 'ldpi' turns into the symbol's address
+    /// when the label immediately follows it.
+    Distance(String, String),
+}
+
+impl Object {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer { bytes: MAGIC.to_vec() };
+        writer.u8(VERSION);
+        writer.u32(self.globals.len() as u32);
+        for global in &self.globals {
+            writer.string(global);
+        }
+        writer.fragments(&self.code);
+        writer.fragments(&self.data);
+        writer.bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Object> {
+        if !bytes.starts_with(MAGIC) {
+            bail!("not a Transputer object");
+        }
+        let mut reader = Reader { bytes, position: MAGIC.len() };
+        let version = reader.u8()?;
+        if version != VERSION {
+            bail!("unsupported object version {}", version);
+        }
+        let mut globals = vec![];
+        for _ in 0..reader.u32()? {
+            globals.push(reader.string()?);
+        }
+        let code = reader.fragments()?;
+        let data = reader.fragments()?;
+        if reader.position != bytes.len() {
+            bail!("trailing bytes after object");
+        }
+        Ok(Object { code, data, globals })
+    }
+}
+
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend(value.to_le_bytes());
+    }
+
+    fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.bytes.extend(value.as_bytes());
+    }
+
+    fn fragments(&mut self, fragments: &[Fragment]) {
+        self.u32(fragments.len() as u32);
+        for fragment in fragments {
+            match fragment {
+                Fragment::Bytes(bytes) => {
+                    self.u8(0);
+                    self.u32(bytes.len() as u32);
+                    self.bytes.extend(bytes);
+                }
+                Fragment::Label(label) => {
+                    self.u8(1);
+                    self.string(label);
+                }
+                Fragment::Direct(function, operand) => {
+                    self.u8(2);
+                    self.u8(*function);
+                    match operand {
+                        Operand::Relative(symbol) => {
+                            self.u8(0);
+                            self.string(symbol);
+                        }
+                        Operand::Distance(symbol, label) => {
+                            self.u8(1);
+                            self.string(symbol);
+                            self.string(label);
+                        }
+                    }
+                }
+                Fragment::Address(symbol, offset) => {
+                    self.u8(3);
+                    self.string(symbol);
+                    self.u32(*offset as u32);
+                }
+                Fragment::Align(alignment) => {
+                    self.u8(4);
+                    self.u32(*alignment);
+                }
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, length: usize) -> Result<&[u8]> {
+        if self.bytes.len() - self.position < length {
+            bail!("truncated object");
+        }
+        let taken = &self.bytes[self.position..self.position + length];
+        self.position += length;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let length = self.u32()? as usize;
+        match String::from_utf8(self.take(length)?.to_vec()) {
+            Ok(string) => Ok(string),
+            Err(_) => bail!("symbol name is not UTF-8"),
+        }
+    }
+
+    fn fragments(&mut self) -> Result<Vec<Fragment>> {
+        let mut fragments = vec![];
+        for _ in 0..self.u32()? {
+            let fragment = match self.u8()? {
+                0 => {
+                    let length = self.u32()? as usize;
+                    Fragment::Bytes(self.take(length)?.to_vec())
+                }
+                1 => Fragment::Label(self.string()?),
+                2 => {
+                    let function = self.u8()?;
+                    if function > 0xF {
+                        bail!("invalid direct function {}", function);
+                    }
+                    let operand = match self.u8()? {
+                        0 => Operand::Relative(self.string()?),
+                        1 => Operand::Distance(self.string()?, self.string()?),
+                        tag => bail!("invalid operand tag {}", tag),
+                    };
+                    Fragment::Direct(function, operand)
+                }
+                3 => Fragment::Address(self.string()?, self.u32()? as i32),
+                4 => {
+                    let alignment = self.u32()?;
+                    if !alignment.is_power_of_two() {
+                        bail!("invalid alignment {}", alignment);
+                    }
+                    Fragment::Align(alignment)
+                }
+                tag => bail!("invalid fragment tag {}", tag),
+            };
+            fragments.push(fragment);
+        }
+        Ok(fragments)
+    }
+}
+
+#[cfg(test)]
+#[path = "./object_spec.rs"]
+mod object_spec;
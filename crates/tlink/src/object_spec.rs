@@ -0,0 +1,84 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod object_spec {
+
+    use hamcrest2::prelude::*;
+
+    use crate::encoding::{CALL, LDC};
+    use crate::object::{Fragment, Object, Operand};
+
+    #[ctor::ctor]
+    fn before_each() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn object() -> Object {
+        Object {
+            code: vec![
+                Fragment::Label("main".to_owned()),
+                Fragment::Bytes(vec![0x60, 0xBC]),
+                Fragment::Direct(LDC, Operand::Distance("counter".to_owned(), "ldpi.1".to_owned())),
+                Fragment::Bytes(vec![0x21, 0xFB]),
+                Fragment::Label("ldpi.1".to_owned()),
+                Fragment::Direct(CALL, Operand::Relative("putchar".to_owned())),
+            ],
+            data: vec![
+                Fragment::Align(4),
+                Fragment::Label("counter".to_owned()),
+                Fragment::Bytes(vec![1, 0, 0, 0]),
+                Fragment::Address("main".to_owned(), -4),
+            ],
+            globals: vec!["main".to_owned(), "counter".to_owned()],
+        }
+    }
+
+    #[test]
+    fn objects_are_read_back_as_written() {
+        let object = object();
+
+        assert_that!(Object::from_bytes(&object.to_bytes()).unwrap(), equal_to(object.clone()));
+    }
+
+    #[test]
+    fn empty_objects_are_read_back() {
+        let object = Object::default();
+
+        assert_that!(Object::from_bytes(&object.to_bytes()).unwrap(), equal_to(object.clone()));
+    }
+
+    #[test]
+    fn other_files_are_not_objects() {
+        let result = Object::from_bytes(b"\t.TRANSPUTER\nmain:\n");
+
+        assert_that!(result.err().unwrap().to_string(), equal_to("not a Transputer object".to_owned()));
+    }
+
+    #[test]
+    fn other_versions_are_not_read() {
+        let mut bytes = object().to_bytes();
+        bytes[4] = 2;
+
+        let result = Object::from_bytes(&bytes);
+
+        assert_that!(result.err().unwrap().to_string(), equal_to("unsupported object version 2".to_owned()));
+    }
+
+    #[test]
+    fn truncated_objects_are_errors() {
+        let bytes = object().to_bytes();
+
+        for length in 4..bytes.len() {
+            let result = Object::from_bytes(&bytes[..length]);
+            assert_that!(result.is_err(), equal_to(true));
+        }
+    }
+
+    #[test]
+    fn trailing_bytes_are_errors() {
+        let mut bytes = object().to_bytes();
+        bytes.push(0);
+
+        assert_that!(Object::from_bytes(&bytes).is_err(), equal_to(true));
+    }
+}